@@ -0,0 +1,3 @@
+pub fn add(x: u32, y: u32) -> u32 {
+    x + y
+}
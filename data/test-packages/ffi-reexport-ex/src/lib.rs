@@ -0,0 +1,25 @@
+//! FFI functions called through other modules and re-exports
+
+mod sys;
+
+pub use sys::abs as c_abs;
+
+pub mod api {
+    use crate::sys::*;
+
+    pub fn glob_import() -> i32 {
+        unsafe { abs(-1) }
+    }
+
+    pub fn reexport() -> i32 {
+        unsafe { crate::c_abs(-2) }
+    }
+
+    pub fn full_path() -> i32 {
+        unsafe { crate::sys::labs(-3) as i32 }
+    }
+
+    pub fn extern_static() -> i32 {
+        unsafe { crate::sys::errno_value }
+    }
+}
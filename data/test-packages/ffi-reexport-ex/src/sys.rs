@@ -0,0 +1,5 @@
+extern "C" {
+    pub fn abs(x: i32) -> i32;
+    pub fn labs(x: i64) -> i64;
+    pub static errno_value: i32;
+}
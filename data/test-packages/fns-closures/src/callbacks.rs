@@ -0,0 +1,34 @@
+use std::fs::remove_file;
+use std::process::Command;
+
+// Only called through the table
+fn cleanup() {
+    remove_file("/tmp/cargo-scan-cleanup").unwrap();
+}
+
+static HANDLERS: [fn(); 1] = [cleanup];
+
+// Only called by the spawned thread
+fn run_handlers() {
+    for h in HANDLERS {
+        h();
+    }
+}
+
+pub fn run_in_thread() {
+    std::thread::spawn(run_handlers).join().unwrap();
+}
+
+// Only called inside a macro
+fn shell_output() -> String {
+    let out = Command::new("sh").output().unwrap();
+    String::from_utf8_lossy(&out.stdout).into_owned()
+}
+
+pub fn print_output() {
+    println!("{}", shell_output());
+}
+
+fn unused() {
+    remove_file("/tmp/cargo-scan-unused").unwrap();
+}
@@ -0,0 +1,22 @@
+use std::fs::remove_file;
+use std::path::Path;
+
+pub struct Handlers {
+    pub on_cleanup: fn(&Path) -> std::io::Result<()>,
+}
+
+pub static CLEANUP: [fn(&Path) -> std::io::Result<()>; 1] = [remove_file];
+
+pub fn call_local(p: &Path) {
+    let f = remove_file;
+    f(p).unwrap();
+}
+
+pub fn call_field(p: &Path) {
+    let h = Handlers { on_cleanup: remove_file };
+    (h.on_cleanup)(p).unwrap();
+}
+
+pub fn call_table(p: &Path) {
+    CLEANUP[0](p).unwrap();
+}
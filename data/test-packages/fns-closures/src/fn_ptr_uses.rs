@@ -0,0 +1,10 @@
+use crate::fn_ptr_sinks::{Handlers, CLEANUP};
+use std::path::Path;
+
+pub fn call_other_field(h: &Handlers, p: &Path) {
+    (h.on_cleanup)(p).unwrap();
+}
+
+pub fn call_other_table(p: &Path) {
+    CLEANUP[0](p).unwrap();
+}
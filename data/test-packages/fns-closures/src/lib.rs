@@ -1,4 +1,8 @@
+pub mod callbacks;
 pub mod closure_effects;
 pub mod closure_types;
 pub mod consts;
+pub mod fn_ptr_sinks;
+pub mod fn_ptr_uses;
+pub mod indirect_process;
 pub mod unsafe_fn_pointers;
@@ -0,0 +1,23 @@
+use std::fs;
+
+// Effects inside macros are only found when scanning the expanded source
+macro_rules! delete {
+    ($path:expr) => {
+        fs::remove_file($path)
+    };
+}
+
+macro_rules! make_deleter {
+    ($name:ident) => {
+        pub fn $name(path: &str) -> std::io::Result<()> {
+            fs::remove_file(path)
+        }
+    };
+}
+
+make_deleter!(delete_generated);
+
+pub fn delete_twice(a: &str, b: &str) -> std::io::Result<()> {
+    delete!(a)?;
+    fs::remove_file(b)
+}
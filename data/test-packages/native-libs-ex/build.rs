@@ -0,0 +1,4 @@
+fn main() {
+    cc::Build::new().file("src/foo.c").compile("foo");
+    println!("cargo:rustc-link-lib=z");
+}
@@ -0,0 +1 @@
+int foo_double(int x) { return x * 2; }
@@ -0,0 +1,18 @@
+use std::os::raw::{c_double, c_int};
+
+#[link(name = "m")]
+extern "C" {
+    pub fn cos(x: c_double) -> c_double;
+}
+
+extern "C" {
+    pub fn foo_double(x: c_int) -> c_int;
+}
+
+pub fn cosine(x: f64) -> f64 {
+    unsafe { cos(x) }
+}
+
+pub fn double(x: i32) -> i32 {
+    unsafe { foo_double(x) }
+}
@@ -0,0 +1,6 @@
+use std::net::TcpListener;
+
+fn main() {
+    let listener = TcpListener::bind("127.0.0.1:8080").unwrap();
+    drop(listener);
+}
@@ -0,0 +1,17 @@
+use std::fs;
+
+pub fn read_config() -> String {
+    fs::read_to_string("config.toml").unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    #[test]
+    fn test_read_config() {
+        env::set_var("CONFIG", "test");
+        assert!(read_config().is_empty());
+    }
+}
@@ -0,0 +1,7 @@
+use std::process::Command;
+
+#[test]
+fn runs_command() {
+    let status = Command::new("true").status().unwrap();
+    assert!(status.success());
+}
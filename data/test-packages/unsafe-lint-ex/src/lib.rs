@@ -0,0 +1,18 @@
+#![deny(unsafe_code)]
+
+pub fn double(x: u32) -> u32 {
+    x * 2
+}
+
+pub mod raw {
+    #![allow(unsafe_code)]
+
+    pub fn first(xs: &[u32]) -> u32 {
+        unsafe { *xs.as_ptr() }
+    }
+}
+
+#[allow(unsafe_code)]
+pub fn second(xs: &[u32]) -> u32 {
+    unsafe { *xs.as_ptr().add(1) }
+}
@@ -0,0 +1,15 @@
+use std::arch::{asm, global_asm};
+
+global_asm!(".globl my_asm_fn", "my_asm_fn:", "ret");
+
+fn add_one(x: u64) -> u64 {
+    let mut y = x;
+    unsafe {
+        asm!("add {0}, 1", inout(reg) y);
+    }
+    y
+}
+
+fn no_asm() {
+    println!("not assembly");
+}
@@ -0,0 +1,17 @@
+use libloading::Library;
+
+fn load_plugin() -> Library {
+    unsafe { Library::new("libplugin.so").unwrap() }
+}
+
+fn load_plugin_from(path: &str) -> Library {
+    unsafe { libloading::Library::new(path).unwrap() }
+}
+
+fn dlopen_lib() -> *mut libc::c_void {
+    unsafe { libc::dlopen(b"libm.so.6\0".as_ptr() as *const _, libc::RTLD_NOW) }
+}
+
+fn not_dyn_load() -> Vec<u8> {
+    Vec::new()
+}
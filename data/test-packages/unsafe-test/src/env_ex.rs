@@ -0,0 +1,23 @@
+use std::env;
+
+const BUILD_TOKEN: &str = env!("CI_TOKEN");
+
+fn read_token() -> Option<String> {
+    env::var("GITHUB_TOKEN").ok()
+}
+
+fn set_debug() {
+    env::set_var("RUST_LOG", "debug");
+}
+
+fn first_arg() -> Option<String> {
+    std::env::args().nth(1)
+}
+
+fn build_profile() -> Option<&'static str> {
+    option_env!("PROFILE")
+}
+
+fn not_env() -> String {
+    format!("{}", 1)
+}
@@ -0,0 +1,31 @@
+#[export_name = "plugin_init"]
+extern "C" fn init() -> i32 {
+    0
+}
+
+#[no_mangle]
+fn shutdown() {
+    cleanup();
+}
+
+fn cleanup() {}
+
+#[unsafe(no_mangle)]
+extern "C" fn plugin_free() {}
+
+extern "C" fn callback(x: i32) -> i32 {
+    x + 1
+}
+
+extern "Rust" fn rust_abi() {}
+
+struct Plugin;
+
+impl Plugin {
+    #[unsafe(export_name = "plugin_version")]
+    extern "C" fn version() -> u32 {
+        1
+    }
+}
+
+fn not_exported() {}
@@ -0,0 +1,33 @@
+use std::ptr;
+
+fn read_first(p: *const u32) -> u32 {
+    unsafe { ptr::read(p) }
+}
+
+fn write_first(p: *mut u32) {
+    unsafe { ptr::write(p, 5) }
+}
+
+fn copy_buf(src: *const u8, dst: *mut u8, n: usize) {
+    unsafe { std::ptr::copy_nonoverlapping(src, dst, n) }
+}
+
+fn same(a: *const u8, b: *const u8) -> bool {
+    ptr::eq(a, b)
+}
+
+fn read_second(p: *const u32) -> u32 {
+    unsafe {
+        let q = p.add(1);
+        *q
+    }
+}
+
+fn write_at(p: *mut u32, n: isize) {
+    unsafe { *p.offset(n) = 0 }
+}
+
+fn zero_last(v: &mut [u8]) {
+    let p = v.as_mut_ptr().wrapping_add(v.len() - 1);
+    unsafe { *p = 0 }
+}
@@ -0,0 +1,22 @@
+use std::ffi::CStr;
+use std::os::raw::c_char;
+
+fn boxed(p: *mut u32) -> Box<u32> {
+    unsafe { Box::from_raw(p) }
+}
+
+fn bytes<'a>(p: *const u8, len: usize) -> &'a [u8] {
+    unsafe { std::slice::from_raw_parts(p, len) }
+}
+
+fn name<'a>(p: *const c_char) -> &'a CStr {
+    unsafe { CStr::from_ptr(p) }
+}
+
+fn rebuild(p: *mut u8, len: usize, cap: usize) -> Vec<u8> {
+    unsafe { Vec::from_raw_parts(p, len, cap) }
+}
+
+fn deref(p: *const u32) -> u32 {
+    unsafe { *p }
+}
@@ -0,0 +1,17 @@
+use std::cell::Cell;
+use std::marker::PhantomData;
+
+struct RawHandle(*mut u8);
+
+unsafe impl Send for RawHandle {}
+unsafe impl Sync for RawHandle {}
+
+struct Shared<T>(Cell<T>);
+
+unsafe impl<T: Send> std::marker::Sync for Shared<T> {}
+
+unsafe trait Zeroable {}
+
+unsafe impl Zeroable for RawHandle {}
+
+struct NotSend(PhantomData<*const ()>);
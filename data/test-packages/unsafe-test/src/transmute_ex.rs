@@ -0,0 +1,19 @@
+use std::mem;
+use std::mem::transmute_copy;
+
+fn float_bits(x: f32) -> u32 {
+    unsafe { mem::transmute(x) }
+}
+
+fn read_header(buf: &[u8; 4]) -> u32 {
+    unsafe { transmute_copy(buf) }
+}
+
+fn fn_from_ptr(p: *const ()) -> fn() {
+    unsafe { std::mem::transmute::<*const (), fn()>(p) }
+}
+
+fn not_transmute() -> u32 {
+    let v = [1u8, 2, 3, 4];
+    unsafe { *v.get_unchecked(0) as u32 }
+}
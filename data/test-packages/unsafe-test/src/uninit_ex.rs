@@ -0,0 +1,19 @@
+use std::mem::{self, MaybeUninit};
+
+fn zeroed_buf() -> [u8; 16] {
+    unsafe { mem::zeroed() }
+}
+
+fn old_uninit() -> u64 {
+    unsafe { std::mem::uninitialized() }
+}
+
+fn init_later() -> u32 {
+    let mut x = MaybeUninit::<u32>::uninit();
+    x.write(5);
+    unsafe { MaybeUninit::assume_init(x) }
+}
+
+fn safe_zeroed() -> MaybeUninit<u32> {
+    MaybeUninit::zeroed()
+}
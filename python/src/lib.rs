@@ -0,0 +1,134 @@
+//! Python bindings for cargo-scan: a `cargo_scan` module whose `scan_crate`
+//! function returns the effects of a crate as `Effect` records.
+//!
+//! ```python
+//! import cargo_scan
+//!
+//! for effect in cargo_scan.scan_crate("path/to/crate", quick=True):
+//!     print(effect.caller, effect.callee, effect.to_dict())
+//! ```
+
+use std::path::{Path, PathBuf};
+
+use cargo_scan::effect::EffectInstance;
+use cargo_scan::error::ScanError;
+use cargo_scan::scan_config::{ResolverKind, ScanConfig};
+use pyo3::create_exception;
+use pyo3::exceptions::{PyException, PyOSError};
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+
+create_exception!(cargo_scan, CargoScanError, PyException);
+
+/// An effect found by a scan, with the same fields as a row of the CSV
+/// output of the `scan` binary
+#[pyclass(module = "cargo_scan", frozen, get_all)]
+#[derive(Debug, Clone)]
+struct Effect {
+    crate_name: String,
+    caller: String,
+    callee: String,
+    effect: String,
+    dir: String,
+    file: String,
+    line: usize,
+    col: usize,
+    end_line: usize,
+    end_col: usize,
+    provenance: String,
+    target_kind: String,
+}
+
+impl Effect {
+    fn new(e: &EffectInstance, crate_path: &Path) -> Self {
+        let loc = e.call_loc().relative_to(crate_path);
+        Self {
+            crate_name: e.caller().crate_name().to_string(),
+            caller: e.caller_path().to_string(),
+            callee: e.callee_path().to_string(),
+            effect: e.eff_type().to_csv(),
+            dir: loc.dir().to_string_lossy().into_owned(),
+            file: loc.file().to_string_lossy().into_owned(),
+            line: loc.start_line(),
+            col: loc.start_col(),
+            end_line: loc.end_line(),
+            end_col: loc.end_col(),
+            provenance: e.provenance().to_string(),
+            target_kind: e.target_kind().to_string(),
+        }
+    }
+}
+
+#[pymethods]
+impl Effect {
+    /// The effect as a dict, e.g. to build a pandas DataFrame
+    fn to_dict<'py>(&self, py: Python<'py>) -> PyResult<&'py PyDict> {
+        let dict = PyDict::new(py);
+        dict.set_item("crate_name", &self.crate_name)?;
+        dict.set_item("caller", &self.caller)?;
+        dict.set_item("callee", &self.callee)?;
+        dict.set_item("effect", &self.effect)?;
+        dict.set_item("dir", &self.dir)?;
+        dict.set_item("file", &self.file)?;
+        dict.set_item("line", self.line)?;
+        dict.set_item("col", self.col)?;
+        dict.set_item("end_line", self.end_line)?;
+        dict.set_item("end_col", self.end_col)?;
+        dict.set_item("provenance", &self.provenance)?;
+        dict.set_item("target_kind", &self.target_kind)?;
+        Ok(dict)
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "Effect(caller={:?}, callee={:?}, effect={:?}, location=\"{}/{}:{}:{}\")",
+            self.caller,
+            self.callee,
+            self.effect,
+            self.dir,
+            self.file,
+            self.line,
+            self.col
+        )
+    }
+}
+
+fn to_py_err(err: ScanError) -> PyErr {
+    match err {
+        ScanError::Io(e) => PyOSError::new_err(e.to_string()),
+        e => CargoScanError::new_err(e.to_string()),
+    }
+}
+
+/// Scans the crate at `path` and returns its effects. With `quick=True`,
+/// names are resolved syntactically instead of with rust-analyzer, which is
+/// much faster but less precise. Calls to the functions (or modules) in
+/// `sinks` are reported as effects as well.
+#[pyfunction]
+#[pyo3(signature = (path, quick = false, sinks = Vec::new()))]
+fn scan_crate(
+    py: Python<'_>,
+    path: PathBuf,
+    quick: bool,
+    sinks: Vec<String>,
+) -> PyResult<Vec<Effect>> {
+    let resolver = if quick { ResolverKind::Quick } else { ResolverKind::RustAnalyzer };
+    let mut builder = ScanConfig::builder().resolver(resolver);
+    for sink in &sinks {
+        builder = builder.sink(sink);
+    }
+    let config = builder.build();
+
+    // Let other Python threads run during the scan
+    let results = py.allow_threads(|| config.scan(&path)).map_err(to_py_err)?;
+    Ok(results.effects.iter().map(|e| Effect::new(e, &path)).collect())
+}
+
+#[pymodule]
+#[pyo3(name = "cargo_scan")]
+fn cargo_scan_py(py: Python<'_>, m: &PyModule) -> PyResult<()> {
+    m.add_class::<Effect>()?;
+    m.add_function(wrap_pyfunction!(scan_crate, m)?)?;
+    m.add("CargoScanError", py.get_type::<CargoScanError>())?;
+    Ok(())
+}
@@ -0,0 +1,219 @@
+//! RustSec security advisories.
+//!
+//! Reads a local copy of the [RustSec advisory
+//! database](https://github.com/rustsec/advisory-db) (the same checkout
+//! `cargo audit` uses), so that auditors can see the known advisories of a
+//! crate alongside its effects. Each advisory is a Markdown file under
+//! `crates/<crate name>/`, starting with a TOML block that lists the
+//! versions which are patched or unaffected.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Context, Result};
+use log::warn;
+use semver::{Version, VersionReq};
+use serde::{Deserialize, Serialize};
+
+use crate::util::CrateId;
+
+const TOML_START: &str = "```toml";
+const TOML_END: &str = "```";
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Advisory {
+    /// The advisory id, e.g. RUSTSEC-2023-0001
+    pub id: String,
+    pub package: String,
+    pub title: String,
+    /// The kind of informational advisory (e.g. `unmaintained`), or None for
+    /// a vulnerability
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub informational: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    /// Version requirements matching the versions with a fix
+    #[serde(default)]
+    pub patched: Vec<String>,
+    /// Version requirements matching the versions never affected
+    #[serde(default)]
+    pub unaffected: Vec<String>,
+}
+
+#[derive(Deserialize, Debug)]
+struct RawAdvisoryInfo {
+    id: String,
+    package: String,
+    url: Option<String>,
+    informational: Option<String>,
+    withdrawn: Option<toml::Value>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+struct RawVersions {
+    #[serde(default)]
+    patched: Vec<String>,
+    #[serde(default)]
+    unaffected: Vec<String>,
+}
+
+#[derive(Deserialize, Debug)]
+struct RawAdvisory {
+    advisory: RawAdvisoryInfo,
+    #[serde(default)]
+    versions: RawVersions,
+}
+
+impl Advisory {
+    /// Parses an advisory file of the database. Returns None for withdrawn
+    /// advisories.
+    pub fn parse(contents: &str) -> Result<Option<Self>> {
+        let toml_start = contents
+            .find(TOML_START)
+            .ok_or_else(|| anyhow!("Missing TOML block in advisory"))?
+            + TOML_START.len();
+        let toml_len = contents[toml_start..]
+            .find(TOML_END)
+            .ok_or_else(|| anyhow!("Unterminated TOML block in advisory"))?;
+        let raw: RawAdvisory =
+            toml::from_str(&contents[toml_start..toml_start + toml_len])?;
+        if raw.advisory.withdrawn.is_some() {
+            return Ok(None);
+        }
+
+        let title = contents[toml_start + toml_len..]
+            .lines()
+            .find_map(|l| l.strip_prefix("# "))
+            .unwrap_or_default()
+            .trim()
+            .to_string();
+        Ok(Some(Self {
+            id: raw.advisory.id,
+            package: raw.advisory.package,
+            title,
+            informational: raw.advisory.informational,
+            url: raw.advisory.url,
+            patched: raw.versions.patched,
+            unaffected: raw.versions.unaffected,
+        }))
+    }
+
+    /// Whether the version is affected, i.e. it is neither patched nor
+    /// unaffected
+    pub fn affects(&self, version: &Version) -> bool {
+        !self.patched.iter().chain(&self.unaffected).any(|req| {
+            match VersionReq::parse(req) {
+                Ok(req) => req.matches(version),
+                Err(e) => {
+                    warn!("Invalid version requirement {:?} in {}: {}", req, self.id, e);
+                    false
+                }
+            }
+        })
+    }
+}
+
+impl std::fmt::Display for Advisory {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.id, self.title)?;
+        if let Some(kind) = &self.informational {
+            write!(f, " ({})", kind)?;
+        }
+        Ok(())
+    }
+}
+
+/// The advisories of the database, by crate name
+#[derive(Debug, Default)]
+pub struct AdvisoryDb {
+    advisories: HashMap<String, Vec<Advisory>>,
+}
+
+impl AdvisoryDb {
+    /// Where `cargo audit` keeps its copy of the database
+    pub fn default_path() -> Option<PathBuf> {
+        Some(home::cargo_home().ok()?.join("advisory-db"))
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let crates_dir = path.join("crates");
+        let mut db = Self::default();
+        for crate_dir in fs::read_dir(crates_dir)
+            .with_context(|| format!("Failed to read advisory database {:?}", path))?
+        {
+            let crate_dir = crate_dir?.path();
+            if !crate_dir.is_dir() {
+                continue;
+            }
+            for file in fs::read_dir(crate_dir)? {
+                let file = file?.path();
+                if file.extension().is_none_or(|e| e != "md") {
+                    continue;
+                }
+                match Advisory::parse(&fs::read_to_string(&file)?) {
+                    Ok(Some(advisory)) => db
+                        .advisories
+                        .entry(advisory.package.clone())
+                        .or_default()
+                        .push(advisory),
+                    Ok(None) => (),
+                    Err(e) => warn!("Skipping advisory {:?}: {}", file, e),
+                }
+            }
+        }
+        Ok(db)
+    }
+
+    /// Returns the advisories affecting the crate version, sorted by id
+    pub fn advisories_for(&self, crate_id: &CrateId) -> Vec<Advisory> {
+        let mut res = self
+            .advisories
+            .get(&crate_id.crate_name)
+            .into_iter()
+            .flatten()
+            .filter(|a| a.affects(&crate_id.version))
+            .cloned()
+            .collect::<Vec<_>>();
+        res.sort_by(|a1, a2| a1.id.cmp(&a2.id));
+        res
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ADVISORY: &str = r#"```toml
+[advisory]
+id = "RUSTSEC-2023-0001"
+package = "tokio"
+date = "2023-01-04"
+url = "https://github.com/tokio-rs/tokio/security/advisories/GHSA-7rrj-xr53-82p7"
+categories = ["memory-corruption"]
+
+[versions]
+patched = [">= 1.18.4, < 1.19.0", ">= 1.20.3, < 1.21.0", ">= 1.23.1"]
+unaffected = ["< 1.7.0"]
+```
+
+# reject_remote_clients Configuration corruption
+
+On Windows, configuring a named pipe server...
+"#;
+
+    #[test]
+    fn test_parse_advisory() {
+        let advisory = Advisory::parse(ADVISORY).unwrap().unwrap();
+        assert_eq!(advisory.id, "RUSTSEC-2023-0001");
+        assert_eq!(advisory.title, "reject_remote_clients Configuration corruption");
+        assert!(advisory.affects(&Version::new(1, 20, 0)));
+        assert!(!advisory.affects(&Version::new(1, 18, 5)));
+        assert!(!advisory.affects(&Version::new(1, 25, 0)));
+        assert!(!advisory.affects(&Version::new(1, 6, 0)));
+
+        let withdrawn =
+            ADVISORY.replace("[versions]", "withdrawn = \"2023-02-01\"\n\n[versions]");
+        assert_eq!(Advisory::parse(&withdrawn).unwrap(), None);
+    }
+}
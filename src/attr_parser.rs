@@ -1,6 +1,7 @@
 /// Parsing module for `#[cfg(..)]` attributes.
 use proc_macro2::{TokenStream, TokenTree};
 use std::collections::HashMap;
+use std::fmt;
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Ord, PartialOrd)]
 pub enum CfgOpt {
@@ -47,6 +48,73 @@ impl CfgPred {
     }
 }
 
+impl CfgPred {
+    /// Returns true if the predicate depends on any of the crate's features
+    pub fn mentions_feature(&self) -> bool {
+        self.mentions(&|key| key == "feature")
+    }
+
+    /// Returns true if the predicate depends on the target platform, e.g.
+    /// `target_os = "linux"` or `windows`
+    pub fn mentions_platform(&self) -> bool {
+        self.mentions(&is_platform_key)
+    }
+
+    /// Returns true if the predicate only holds when compiling tests, e.g.
+    /// `test` or `all(test, unix)`
+    pub fn requires_test(&self) -> bool {
+        match self {
+            CfgPred::Option(CfgOpt::Name(name)) => name == "test",
+            CfgPred::All(preds) => preds.iter().any(|x| x.requires_test()),
+            _ => false,
+        }
+    }
+
+    fn mentions(&self, is_key: &impl Fn(&str) -> bool) -> bool {
+        match self {
+            CfgPred::Invalid => false,
+            CfgPred::Option(CfgOpt::Name(name)) => is_key(name),
+            CfgPred::Option(CfgOpt::Pair { key, .. }) => is_key(key),
+            CfgPred::All(preds) | CfgPred::Any(preds) => {
+                preds.iter().any(|x| x.mentions(is_key))
+            }
+            CfgPred::Not(pred) => pred.mentions(is_key),
+        }
+    }
+}
+
+/// Returns true for the cfg options which describe the target platform, and
+/// are set by the compiler rather than the crate
+pub fn is_platform_key(key: &str) -> bool {
+    key.starts_with("target_") || key == "unix" || key == "windows"
+}
+
+impl fmt::Display for CfgOpt {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CfgOpt::Name(name) => write!(f, "{}", name),
+            CfgOpt::Pair { key, value } => write!(f, "{} = \"{}\"", key, value),
+        }
+    }
+}
+
+/// Writes the predicate as it would appear in a `#[cfg(...)]` attribute
+impl fmt::Display for CfgPred {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let write_list = |f: &mut fmt::Formatter<'_>, name, preds: &[CfgPred]| {
+            let preds = preds.iter().map(|x| x.to_string()).collect::<Vec<_>>();
+            write!(f, "{}({})", name, preds.join(", "))
+        };
+        match self {
+            CfgPred::Invalid => write!(f, "<invalid>"),
+            CfgPred::Option(opt) => write!(f, "{}", opt),
+            CfgPred::All(preds) => write_list(f, "all", preds),
+            CfgPred::Any(preds) => write_list(f, "any", preds),
+            CfgPred::Not(pred) => write!(f, "not({})", pred),
+        }
+    }
+}
+
 fn parse_pred(it: &mut dyn Iterator<Item = TokenTree>) -> Option<CfgPred> {
     let mut in_group = false;
     let mut peek_iter = it.peekable();
@@ -1,14 +1,20 @@
-use anyhow::{anyhow, Context, Result};
-use cargo::core::source::MaybePackage;
+use crate::error::Result;
+use anyhow::{anyhow, Context};
+use cargo::core::dependency::DepKind;
+use cargo::core::PackageId;
 use cargo::ops::{fetch, FetchOptions};
 use cargo::{core::Workspace, ops::generate_lockfile, util::config};
 use cargo_lock::{Dependency, Lockfile, Package};
 use cargo_toml::Manifest;
 use clap::Args as ClapArgs;
+use log::warn;
 use petgraph::graph::{DiGraph, NodeIndex};
-use petgraph::visit::DfsPostOrder;
+use petgraph::visit::{DfsPostOrder, Reversed};
+use petgraph::Direction;
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use std::collections::{HashMap, HashSet};
+use std::fmt;
 use std::fs::{create_dir_all, remove_file, File};
 use std::io::Write;
 use std::iter::IntoIterator;
@@ -17,10 +23,82 @@ use std::path::{Path, PathBuf};
 use std::str::FromStr;
 use toml;
 
-use crate::audit_file::{AuditFile, AuditVersion, DefaultAuditType};
-use crate::effect::EffectType;
+use crate::advisories::{Advisory, AdvisoryDb};
+use crate::audit_file::{
+    unix_time, AuditFile, AuditVersion, DefaultAuditConfig, DefaultAuditType,
+};
+use crate::auditing::util::hash_dir;
+use crate::effect::{EffectSignature, EffectType, Provenance};
 use crate::ident::{CanonicalPath, IdentPath};
-use crate::util::{load_cargo_toml, CrateId};
+use crate::progress::Progress;
+use crate::project_config::TrustConfig;
+use crate::signing::{canonical_json, Signature, SigningKey};
+use crate::sink::load_sinks_file;
+use crate::util::{find_registry_crate, load_cargo_toml, relative_path, CrateId};
+
+/// A reviewer's approval of a crate's audit
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct SignOff {
+    pub reviewer: String,
+    /// SHA-256 of the audit file contents the reviewer approved. The sign-off
+    /// no longer counts once the audit changes.
+    pub audit_hash: [u8; 32],
+    /// The reviewer's signature of the sign-off, with their signing key (see
+    /// `signing`). Sign-offs without a valid one are dropped when the chain is
+    /// loaded, and only those signed with the key trusted for the reviewer
+    /// count (see `AuditChain::set_trust`).
+    #[serde(default)]
+    pub signature: Option<Signature>,
+}
+
+impl SignOff {
+    /// What the reviewer signs: the canonical serialization of the crate, the
+    /// reviewer and the hash of the audit they approved
+    fn signed_bytes(&self, crate_id: &CrateId) -> Result<Vec<u8>> {
+        let json = serde_json::json!({
+            "crate": crate_id.to_string(),
+            "reviewer": self.reviewer,
+            "audit_hash": self.audit_hash,
+        });
+        Ok(canonical_json(&json)?)
+    }
+
+    fn verify(&self, crate_id: &CrateId) -> Result<()> {
+        let signature =
+            self.signature.as_ref().ok_or_else(|| anyhow!("it isn't signed"))?;
+        Ok(signature.verify(&self.signed_bytes(crate_id)?)?)
+    }
+
+    /// Returns whether the sign-off is signed with the key `trust` has for its
+    /// reviewer
+    fn is_trusted(&self, crate_id: &CrateId, trust: &TrustConfig) -> bool {
+        let (Some(signature), Ok(msg)) = (&self.signature, self.signed_bytes(crate_id))
+        else {
+            return false;
+        };
+        trust.trusted_signer(signature, &msg) == Some(self.reviewer.as_str())
+    }
+}
+
+/// The review status of a crate's audit in the chain
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReviewStatus {
+    /// There are still effects left to audit
+    Unaudited,
+    /// The audit is complete, but doesn't have enough reviewer sign-offs yet
+    AwaitingSignOff,
+    Audited,
+}
+
+impl fmt::Display for ReviewStatus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ReviewStatus::Unaudited => write!(f, "unaudited"),
+            ReviewStatus::AwaitingSignOff => write!(f, "awaiting sign-off"),
+            ReviewStatus::Audited => write!(f, "audited"),
+        }
+    }
+}
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct AuditChain {
@@ -29,6 +107,37 @@ pub struct AuditChain {
     crate_path: PathBuf,
     crate_policies: HashMap<CrateId, (PathBuf, AuditVersion)>,
     scanned_effects: Vec<EffectType>,
+    /// Whether the paths in the saved manifest are relative to its directory.
+    /// Always false once loaded.
+    #[serde(default)]
+    relative_paths: bool,
+    /// The number of distinct reviewers who must sign off on a crate's audit
+    /// before it counts as audited. Chains from before sign-offs don't
+    /// require any.
+    #[serde(default)]
+    required_reviewers: usize,
+    #[serde(default)]
+    sign_offs: HashMap<CrateId, Vec<SignOff>>,
+    /// The reviewers whose sign-offs count, with their keys. Comes from the
+    /// project config rather than the manifest, so that editing the manifest
+    /// can't add reviewers.
+    #[serde(skip)]
+    trust: TrustConfig,
+    /// The reviewers assigned to each crate
+    #[serde(default)]
+    assignments: HashMap<CrateId, Vec<String>>,
+    /// The RustSec advisories affecting each crate, as of when the chain was
+    /// created
+    #[serde(default)]
+    advisories: HashMap<CrateId, Vec<Advisory>>,
+    /// The members of the workspace at `crate_path`, which are audited in
+    /// full rather than as dependencies
+    #[serde(default)]
+    workspace_members: Vec<CrateId>,
+    /// The proc-macro crates in the chain, whose code runs inside the
+    /// compiler during every build
+    #[serde(default)]
+    proc_macros: Vec<CrateId>,
 }
 
 impl AuditChain {
@@ -42,6 +151,178 @@ impl AuditChain {
             crate_path,
             crate_policies: HashMap::new(),
             scanned_effects,
+            relative_paths: false,
+            required_reviewers: 0,
+            sign_offs: HashMap::new(),
+            assignments: HashMap::new(),
+            advisories: HashMap::new(),
+            workspace_members: Vec::new(),
+            proc_macros: Vec::new(),
+            trust: TrustConfig::default(),
+        }
+    }
+
+    pub fn required_reviewers(&self) -> usize {
+        self.required_reviewers
+    }
+
+    pub fn set_required_reviewers(&mut self, n: usize) {
+        self.required_reviewers = n;
+    }
+
+    /// Sets the reviewers whose sign-offs count, by the keys in `trust.keys`
+    pub fn set_trust(&mut self, trust: TrustConfig) {
+        self.trust = trust;
+    }
+
+    /// Returns the SHA-256 of the crate's current audit file, which reviewers
+    /// sign off on
+    fn audit_hash(&self, crate_id: &CrateId) -> Result<[u8; 32]> {
+        let (audit_file_path, _) = self
+            .crate_policies
+            .get(crate_id)
+            .ok_or_else(|| anyhow!("Couldn't find entry for crate: {}", crate_id))?;
+        Ok(Sha256::digest(std::fs::read(audit_file_path)?).into())
+    }
+
+    /// Records the reviewer's approval of the crate's current audit, signed
+    /// with their key, replacing any earlier sign-off of theirs. The key must
+    /// be the one trusted for the reviewer.
+    pub fn sign_off(
+        &mut self,
+        crate_id: &CrateId,
+        reviewer: &str,
+        key: &SigningKey,
+    ) -> Result<()> {
+        let audit_hash = self.audit_hash(crate_id)?;
+        let mut sign_off =
+            SignOff { reviewer: reviewer.to_string(), audit_hash, signature: None };
+        sign_off.signature = Some(key.sign(&sign_off.signed_bytes(crate_id)?));
+        if !sign_off.is_trusted(crate_id, &self.trust) {
+            return Err(anyhow!(
+                "The signing key {} isn't trusted for {}; add it to [trust.keys] in \
+                the project config",
+                key.public_key(),
+                reviewer
+            )
+            .into());
+        }
+
+        let sign_offs = self.sign_offs.entry(crate_id.clone()).or_default();
+        sign_offs.retain(|s| s.reviewer != reviewer);
+        sign_offs.push(sign_off);
+        Ok(())
+    }
+
+    /// Returns the sign-offs which approve the crate's current audit and are
+    /// signed with the key trusted for their reviewer, one for each reviewer
+    pub fn valid_sign_offs(&self, crate_id: &CrateId) -> Result<Vec<&SignOff>> {
+        let Some(sign_offs) = self.sign_offs.get(crate_id) else {
+            return Ok(Vec::new());
+        };
+        let audit_hash = self.audit_hash(crate_id)?;
+        let mut reviewers = HashSet::new();
+        Ok(sign_offs
+            .iter()
+            .filter(|s| s.audit_hash == audit_hash)
+            .filter(|s| s.is_trusted(crate_id, &self.trust))
+            .filter(|s| reviewers.insert(s.reviewer.as_str()))
+            .collect())
+    }
+
+    /// Drops the sign-offs which aren't signed by their reviewer, or whose
+    /// signature doesn't match, e.g. because the manifest was edited
+    fn verify_sign_offs(&mut self) {
+        for (crate_id, sign_offs) in &mut self.sign_offs {
+            sign_offs.retain(|s| match s.verify(crate_id) {
+                Ok(()) => true,
+                Err(e) => {
+                    warn!(
+                        "Ignoring the sign-off of {} by {}, its signature can't be \
+                        verified: {}",
+                        crate_id, s.reviewer, e
+                    );
+                    false
+                }
+            });
+        }
+    }
+
+    pub fn assign(&mut self, crate_id: &CrateId, reviewer: &str) {
+        let reviewers = self.assignments.entry(crate_id.clone()).or_default();
+        if !reviewers.iter().any(|r| r == reviewer) {
+            reviewers.push(reviewer.to_string());
+        }
+    }
+
+    /// Returns the assigned reviewers who haven't signed off on the current
+    /// audit of each crate, sorted by crate
+    pub fn outstanding_assignments(&self) -> Result<Vec<(&CrateId, &str)>> {
+        let mut outstanding = Vec::new();
+        for (crate_id, reviewers) in &self.assignments {
+            let signed = self.valid_sign_offs(crate_id)?;
+            for r in reviewers {
+                if !signed.iter().any(|s| &s.reviewer == r) {
+                    outstanding.push((crate_id, r.as_str()));
+                }
+            }
+        }
+        outstanding.sort_by(|(c1, r1), (c2, r2)| {
+            (&c1.crate_name, &c1.version, r1).cmp(&(&c2.crate_name, &c2.version, r2))
+        });
+        Ok(outstanding)
+    }
+
+    /// Records the advisories affecting each crate in the chain, and returns
+    /// the number of affected crates
+    pub fn set_advisories(&mut self, db: &AdvisoryDb) -> usize {
+        self.advisories = self
+            .crate_policies
+            .keys()
+            .map(|c| (c.clone(), db.advisories_for(c)))
+            .filter(|(_, advisories)| !advisories.is_empty())
+            .collect();
+        self.advisories.len()
+    }
+
+    pub fn advisories(&self, crate_id: &CrateId) -> &[Advisory] {
+        self.advisories.get(crate_id).map_or(&[], |a| a.as_slice())
+    }
+
+    /// Whether the crate is a proc macro, i.e. runs arbitrary code at compile
+    /// time
+    pub fn is_proc_macro(&self, crate_id: &CrateId) -> bool {
+        self.proc_macros.contains(crate_id)
+    }
+
+    fn set_proc_macros(&mut self, proc_macros: &HashSet<CrateId>) {
+        self.proc_macros = proc_macros.iter().cloned().collect();
+        self.proc_macros.sort_by(|c1, c2| {
+            (&c1.crate_name, &c1.version).cmp(&(&c2.crate_name, &c2.version))
+        });
+    }
+
+    /// A crate is only audited once it has no effects left to audit, or
+    /// whose decisions have expired, and enough distinct reviewers have signed
+    /// off on its current audit
+    pub fn review_status(&mut self, crate_id: &CrateId) -> Result<ReviewStatus> {
+        let audit_file = self
+            .read_audit_file(crate_id)?
+            .ok_or_else(|| anyhow!("Couldn't find audit for crate {}", crate_id))?;
+        if !audit_file.is_fully_audited(unix_time()) {
+            return Ok(ReviewStatus::Unaudited);
+        }
+        if self.valid_sign_offs(crate_id)?.len() < self.required_reviewers {
+            return Ok(ReviewStatus::AwaitingSignOff);
+        }
+        Ok(ReviewStatus::Audited)
+    }
+
+    /// The directory paths in the manifest are relative to when saved
+    fn manifest_dir(manifest_path: &Path) -> PathBuf {
+        match manifest_path.parent() {
+            Some(p) if !p.as_os_str().is_empty() => p.to_path_buf(),
+            _ => PathBuf::from("."),
         }
     }
 
@@ -59,10 +340,19 @@ impl AuditChain {
 
     pub fn read_audit_chain(path: PathBuf) -> Result<Option<AuditChain>> {
         if path.is_dir() {
-            Err(anyhow!("Manifest path is a directory"))
+            Err(anyhow!("Manifest path is a directory").into())
         } else if path.is_file() {
             let toml_string = std::fs::read_to_string(path.as_path())?;
             let mut audit_chain: AuditChain = toml::from_str(&toml_string)?;
+            audit_chain.verify_sign_offs();
+            if audit_chain.relative_paths {
+                let manifest_dir = AuditChain::manifest_dir(&path);
+                audit_chain.crate_path = manifest_dir.join(&audit_chain.crate_path);
+                for (audit_file_path, _) in audit_chain.crate_policies.values_mut() {
+                    *audit_file_path = manifest_dir.join(&audit_file_path);
+                }
+                audit_chain.relative_paths = false;
+            }
             audit_chain.manifest_path = path;
             Ok(Some(audit_chain))
         } else {
@@ -70,10 +360,20 @@ impl AuditChain {
         }
     }
 
+    /// Saves the manifest with all paths relative to its directory, so the
+    /// manifest and its audit files can be moved or shared together
     pub fn save_to_file(mut self) -> Result<()> {
         let path = mem::take(&mut self.manifest_path);
+        let manifest_dir = AuditChain::manifest_dir(&path);
+        self.crate_path = relative_path(&self.crate_path, &manifest_dir);
+        for (audit_file_path, _) in self.crate_policies.values_mut() {
+            *audit_file_path = relative_path(audit_file_path, &manifest_dir);
+        }
+        self.relative_paths = true;
+
         let mut f = File::create(path)?;
-        let toml = toml::to_string(&self)?;
+        let toml =
+            toml::to_string(&self).context("Failed to serialize the audit chain")?;
         f.write_all(toml.as_bytes())?;
         Ok(())
     }
@@ -95,6 +395,20 @@ impl AuditChain {
             .context("Can't find an associated audit for the crate")?
             .clone();
         match AuditFile::read_audit_file(audit_file_path.clone())? {
+            Some(audit_file) if !audit_file.base_dir.exists() => {
+                // The audit was created on another machine, so look for the
+                // same crate here
+                let Some(crate_path) = self.locate_crate(crate_id, &audit_file) else {
+                    return Err(anyhow!(
+                        "Couldn't find a local copy of {} matching its audit",
+                        crate_id
+                    )
+                    .into());
+                };
+                let audit_file = audit_file.relocate(crate_path)?;
+                audit_file.save_to_file(audit_file_path.clone())?;
+                self.read_audit_file(crate_id)
+            }
             Some(audit_file) => {
                 if audit_file.version != expected_version {
                     // The audit file has been updated in a different audit, so we need to
@@ -113,6 +427,25 @@ impl AuditChain {
         }
     }
 
+    /// Finds a local copy of the crate with the same contents as when the
+    /// audit was created
+    fn locate_crate(
+        &self,
+        crate_id: &CrateId,
+        audit_file: &AuditFile,
+    ) -> Option<PathBuf> {
+        let candidates = [
+            Some(self.crate_path.clone())
+                .filter(|p| load_cargo_toml(p).ok().as_ref() == Some(crate_id)),
+            find_registry_crate(crate_id),
+        ];
+        candidates
+            .into_iter()
+            .flatten()
+            .filter_map(|p| p.canonicalize().ok())
+            .find(|p| hash_dir(p).is_ok_and(|hash| hash == audit_file.hash))
+    }
+
     /// Returns the full package name with version if there is exactly one
     /// package matching the input, or none otherwise
     pub fn resolve_crate_id(&self, crate_name: &str) -> Option<CrateId> {
@@ -163,6 +496,13 @@ impl AuditChain {
     /// Loads the lockfile for the given crate path. Will generate a new one
     /// with the default configuration if none exists.
     pub fn load_lockfile(&self) -> Result<Lockfile> {
+        self.load_or_generate_lockfile(false)
+    }
+
+    /// Loads the lockfile for the given crate path, generating a new one if
+    /// none exists. In offline mode, only cached packages are considered when
+    /// generating it.
+    fn load_or_generate_lockfile(&self, offline: bool) -> Result<Lockfile> {
         let mut crate_path = self.crate_path.clone();
         crate_path = crate_path.canonicalize()?;
         crate_path.push("Cargo.lock");
@@ -170,11 +510,12 @@ impl AuditChain {
             Ok(l)
         } else {
             println!("Lockfile missing: generating new lockfile");
-            let config = config::Config::default()?;
+            let config = cargo_config(offline)?;
             crate_path.pop();
             crate_path.push("Cargo.toml");
             let workspace = Workspace::new(&crate_path, &config)?;
-            generate_lockfile(&workspace)?;
+            generate_lockfile(&workspace)
+                .map_err(|e| missing_crates_error(e, offline))?;
             crate_path.pop();
             crate_path.push("Cargo.lock");
             let l = Lockfile::load(&crate_path)?;
@@ -204,58 +545,106 @@ impl AuditChain {
         let start_node = dep_nodes.get(&Dependency::from(&start_package)).context(
             format!("Missing package {:?} in the dependency graph", start_package),
         )?;
-        // Visit nodes in dfs post-order so we don't have to recursively add
-        // packages whose public caller-checked functions have been updated.
-        let mut visit = DfsPostOrder::new(dep_graph, *start_node);
-        while let Some(n) = visit.next(dep_graph) {
+        // The dependency graph has edges from each package to its
+        // dependencies, so walk it in reverse to find the packages which
+        // depend on the updated crate. Visit them in reverse dfs post-order
+        // (i.e. topological order), so each package is only updated after the
+        // dependencies whose public caller-checked functions have changed.
+        let dependents = Reversed(dep_graph);
+        let mut visit = DfsPostOrder::new(dependents, *start_node);
+        let mut update_order = Vec::new();
+        while let Some(n) = visit.next(dependents) {
+            update_order.push(n);
+        }
+        for n in update_order.into_iter().rev() {
             // TODO: Only update packages whose dependencies have changed public
             //       caller-checked lists.
-            let package = &dep_graph[n];
-            let crate_id = CrateId::from(package);
-            let mut crate_audit_file = self
-                .read_audit_file(&crate_id)?
-                .context(format!("Couldn't find audit for {}", crate_id))?;
-            let starting_pub_caller_checked = crate_audit_file
-                .pub_caller_checked
-                .keys()
-                .cloned()
-                .collect::<HashSet<_>>();
-
-            let removed_effect_instances =
-                crate_audit_file.remove_sinks_from_tree(&removed_fns);
-            let package_pub_fns = &crate_audit_file
-                .pub_caller_checked
-                .keys()
-                .cloned()
-                .collect::<HashSet<_>>();
-            let next_removed_fns = starting_pub_caller_checked
-                .difference(package_pub_fns)
-                .cloned()
-                .collect::<Vec<_>>();
-            if !next_removed_fns.is_empty() || !removed_effect_instances.is_empty() {
-                // If the audit file changes, we need to bump the version so
-                // other audit chains know to recalculate their effects
-                crate_audit_file.version += 1;
-            }
+            let crate_id = CrateId::from(&dep_graph[n]);
+            let next_removed_fns = self.remove_sinks(&crate_id, &removed_fns)?;
             removed_fns.extend(next_removed_fns);
+        }
 
-            // reconstruct invariant
-            crate_audit_file.recalc_pub_caller_checked(&starting_pub_caller_checked);
-            crate_audit_file.save_to_file(
-                self.crate_policies
-                    .get(&crate_id)
-                    .context(format!("Missing crate {} from chain", &crate_id))?
-                    .0
-                    .clone(),
-            )?;
+        Ok(removed_fns)
+    }
 
-            self.crate_policies
-                .get_mut(&crate_id)
-                .context("Couldn't find the crate in the chain manifest")?
-                .1 = crate_audit_file.version;
+    /// Removes the effects originating from `removed_fns` from the crate's
+    /// audit, and returns the public functions which are no longer
+    /// caller-checked as a result
+    fn remove_sinks(
+        &mut self,
+        crate_id: &CrateId,
+        removed_fns: &HashSet<CanonicalPath>,
+    ) -> Result<Vec<CanonicalPath>> {
+        let mut crate_audit_file = self
+            .read_audit_file(crate_id)?
+            .context(format!("Couldn't find audit for {}", crate_id))?;
+        let starting_pub_caller_checked =
+            crate_audit_file.pub_caller_checked.keys().cloned().collect::<HashSet<_>>();
+
+        let removed_effect_instances =
+            crate_audit_file.remove_sinks_from_tree(removed_fns);
+        let package_pub_fns =
+            &crate_audit_file.pub_caller_checked.keys().cloned().collect::<HashSet<_>>();
+        let next_removed_fns = starting_pub_caller_checked
+            .difference(package_pub_fns)
+            .cloned()
+            .collect::<Vec<_>>();
+        if !next_removed_fns.is_empty() || !removed_effect_instances.is_empty() {
+            // If the audit file changes, we need to bump the version so
+            // other audit chains know to recalculate their effects
+            crate_audit_file.version += 1;
         }
 
-        Ok(removed_fns)
+        // reconstruct invariant
+        crate_audit_file.recalc_pub_caller_checked(&starting_pub_caller_checked);
+        crate_audit_file.save_to_file(
+            self.crate_policies
+                .get(crate_id)
+                .context(format!("Missing crate {} from chain", crate_id))?
+                .0
+                .clone(),
+        )?;
+
+        self.crate_policies
+            .get_mut(crate_id)
+            .context("Couldn't find the crate in the chain manifest")?
+            .1 = crate_audit_file.version;
+
+        Ok(next_removed_fns)
+    }
+
+    /// Returns the crate's saved audit, without recalculating it if its
+    /// dependencies have changed
+    fn saved_audit_file(&self, crate_id: &CrateId) -> Result<Option<AuditFile>> {
+        let (audit_file_path, _) = self
+            .crate_policies
+            .get(crate_id)
+            .ok_or_else(|| anyhow!("Couldn't find entry for crate: {}", crate_id))?;
+        AuditFile::read_audit_file(audit_file_path.clone())
+    }
+
+    /// Returns the public caller-checked functions in the crate's saved audit
+    fn saved_pub_caller_checked(
+        &self,
+        crate_id: &CrateId,
+    ) -> Result<HashSet<CanonicalPath>> {
+        Ok(self
+            .saved_audit_file(crate_id)?
+            .map(|a| a.pub_caller_checked.into_keys().collect())
+            .unwrap_or_default())
+    }
+
+    /// Removes the crate and its audit file from the chain
+    fn remove_crate(&mut self, crate_id: &CrateId) -> Result<()> {
+        if let Some((audit_file_path, _)) = self.crate_policies.remove(crate_id) {
+            if audit_file_path.is_file() {
+                remove_file(audit_file_path)?;
+            }
+        }
+        self.sign_offs.remove(crate_id);
+        self.assignments.remove(crate_id);
+        self.advisories.remove(crate_id);
+        Ok(())
     }
 
     /// Gets the root crate id
@@ -265,9 +654,34 @@ impl AuditChain {
             self.crate_path.to_string_lossy()
         ))?
         .package
-        .ok_or_else(|| anyhow!("Can't load root package for the root crate path"))?;
+        .ok_or_else(|| {
+            anyhow!(
+                "Can't load root package for the root crate path{}",
+                if self.workspace_members.is_empty() {
+                    ""
+                } else {
+                    " (it is a virtual workspace, so specify a member crate)"
+                }
+            )
+        })?;
+
+        Ok(CrateId::from_toml_package(&root_package)?)
+    }
+
+    /// Returns the workspace members, or just the root crate for chains
+    /// created before workspaces were supported
+    pub fn root_crates(&self) -> Result<Vec<CrateId>> {
+        if self.workspace_members.is_empty() {
+            Ok(vec![self.root_crate()?])
+        } else {
+            Ok(self.workspace_members.clone())
+        }
+    }
 
-        CrateId::from_toml_package(&root_package)
+    /// The crates which aren't workspace members, i.e. the dependencies
+    pub fn dependency_crates(&self) -> Result<Vec<&CrateId>> {
+        let roots = self.root_crates()?;
+        Ok(self.crate_policies.keys().filter(|c| !roots.contains(c)).collect())
     }
 }
 
@@ -304,16 +718,74 @@ pub struct Create {
         EffectType::FFICall,
         EffectType::UnsafeCall,
         EffectType::RawPointer,
-        EffectType::UnionField,
+        EffectType::RawPointerWrite,
+        EffectType::PtrOffset,
+        EffectType::UnionFieldRead,
+        EffectType::UnionFieldWrite,
         EffectType::StaticMut,
         EffectType::StaticExt,
         EffectType::FnPtrCreation,
         EffectType::ClosureCreation,
+        EffectType::IndirectCall,
+        EffectType::Transmute,
+        EffectType::InlineAsm,
+        EffectType::UninitMem,
+        EffectType::RawConstruct,
+        EffectType::DynLoad,
+        EffectType::FFIExport,
+        EffectType::SendSyncImpl,
     ])]
     pub effect_types: Vec<EffectType>,
+
+    /// Keep effects in dependency functions that are unreachable from the
+    /// dependency's public API. These are excluded from dependency audits by
+    /// default.
+    #[clap(long, default_value_t = false)]
+    pub include_dead_code: bool,
+
+    /// Maximum call-graph depth to propagate caller-checked annotations in
+    /// default audits. Callers beyond it are left unaudited for review.
+    #[clap(long)]
+    pub max_depth: Option<usize>,
+
+    /// Maximum number of functions to mark caller-checked for each effect in
+    /// default audits. Callers beyond it are left unaudited for review.
+    #[clap(long)]
+    pub max_marked_fns: Option<usize>,
+
+    /// Also scan the macro-expanded source of each crate to find effects
+    /// inside macro invocations (requires building the crates)
+    #[clap(long, default_value_t = false)]
+    pub expand_macros: bool,
+
+    /// TOML file of additional sink patterns to look for in every crate
+    #[clap(long)]
+    pub sinks_file: Option<PathBuf>,
+
+    /// Number of distinct trusted reviewers who must sign off on each crate's
+    /// audit
+    #[clap(long, default_value_t = 0)]
+    pub required_reviewers: usize,
+
+    /// Local copy of the RustSec advisory database to check the crates
+    /// against (defaults to the one `cargo audit` uses, if present)
+    #[clap(long)]
+    pub advisory_db: Option<PathBuf>,
+
+    /// Don't access the network, and only use crates already in the local
+    /// cargo cache
+    #[clap(long, default_value_t = false, conflicts_with = "download_root_crate")]
+    pub offline: bool,
+
+    /// Also scan each crate's build script, and tag the effects of build
+    /// scripts and of crates only used by them (i.e. build-dependencies)
+    /// with the `build` provenance, as they run at compile time
+    #[clap(long, default_value_t = false)]
+    pub include_build_script: bool,
 }
 
 impl Create {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         crate_path: String,
         manifest_path: String,
@@ -322,6 +794,7 @@ impl Create {
         download_root_crate: Option<String>,
         download_version: Option<String>,
         effect_types: Vec<EffectType>,
+        include_dead_code: bool,
     ) -> Self {
         Self {
             crate_path,
@@ -331,6 +804,91 @@ impl Create {
             download_root_crate,
             download_version,
             effect_types,
+            include_dead_code,
+            max_depth: None,
+            max_marked_fns: None,
+            expand_macros: false,
+            sinks_file: None,
+            required_reviewers: 0,
+            advisory_db: None,
+            offline: false,
+            include_build_script: false,
+        }
+    }
+}
+
+/// Update the chain after its lockfile has changed
+#[derive(Clone, ClapArgs, Debug)]
+pub struct Update {
+    /// Path to manifest
+    pub manifest_path: String,
+
+    /// Folder to save the audits of new crates to
+    #[clap(short = 'p', long = "audit-path", default_value = ".audit_files")]
+    pub audit_path: String,
+
+    /// Keep effects in dependency functions that are unreachable from the
+    /// dependency's public API
+    #[clap(long, default_value_t = false)]
+    pub include_dead_code: bool,
+
+    /// Maximum call-graph depth to propagate caller-checked annotations in
+    /// new default audits
+    #[clap(long)]
+    pub max_depth: Option<usize>,
+
+    /// Maximum number of functions to mark caller-checked for each effect in
+    /// new default audits
+    #[clap(long)]
+    pub max_marked_fns: Option<usize>,
+
+    /// Also scan the macro-expanded source of new crates
+    #[clap(long, default_value_t = false)]
+    pub expand_macros: bool,
+
+    /// TOML file of additional sink patterns to look for in new crates
+    #[clap(long)]
+    pub sinks_file: Option<PathBuf>,
+
+    /// Local copy of the RustSec advisory database to check the crates
+    /// against (defaults to the one `cargo audit` uses, if present)
+    #[clap(long)]
+    pub advisory_db: Option<PathBuf>,
+
+    /// Don't access the network, and only use crates already in the local
+    /// cargo cache
+    #[clap(long, default_value_t = false)]
+    pub offline: bool,
+
+    /// Also scan the build scripts of new crates, and tag build-time effects
+    /// with the `build` provenance
+    #[clap(long, default_value_t = false)]
+    pub include_build_script: bool,
+}
+
+impl Update {
+    /// The arguments the chain would be created with, for making the audits
+    /// of new crates
+    fn create_args(&self, chain: &AuditChain) -> Create {
+        Create {
+            crate_path: chain.crate_path.to_string_lossy().to_string(),
+            manifest_path: self.manifest_path.clone(),
+            audit_path: self.audit_path.clone(),
+            // NOTE: Audits of crates whose dependencies changed are remade in
+            //       place
+            force_overwrite: true,
+            download_root_crate: None,
+            download_version: None,
+            effect_types: chain.scanned_effects.clone(),
+            include_dead_code: self.include_dead_code,
+            max_depth: self.max_depth,
+            max_marked_fns: self.max_marked_fns,
+            expand_macros: self.expand_macros,
+            sinks_file: self.sinks_file.clone(),
+            required_reviewers: chain.required_reviewers,
+            advisory_db: self.advisory_db.clone(),
+            offline: self.offline,
+            include_build_script: self.include_build_script,
         }
     }
 }
@@ -349,10 +907,14 @@ fn create_audit_chain_dirs(args: &Create, crate_download_path: &str) -> Result<(
     Ok(())
 }
 
+/// The crate dependency graph, along with the package of each node
+type DependencyGraph = (DiGraph<String, ()>, HashMap<NodeIndex, Package>);
+
+/// Returns the dependency graph and the nodes of the given root crates
 fn make_dependency_graph(
     packages: &Vec<Package>,
-    root_name: &str,
-) -> (DiGraph<String, ()>, HashMap<NodeIndex, Package>, NodeIndex) {
+    root_names: &[String],
+) -> Result<(DependencyGraph, Vec<NodeIndex>)> {
     let mut graph = DiGraph::new();
     let mut node_map = HashMap::new();
     let mut package_map = HashMap::new();
@@ -378,24 +940,36 @@ fn make_dependency_graph(
         }
     }
 
-    let root_idx = *node_map.get(root_name).unwrap();
-    (graph, package_map, root_idx)
+    let root_idxs = root_names
+        .iter()
+        .map(|r| {
+            node_map
+                .get(r)
+                .copied()
+                .ok_or_else(|| anyhow!("Couldn't find {} in the lockfile", r))
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    Ok(((graph, package_map), root_idxs))
 }
 
+/// Collects the caller-checked functions of the given dependencies, along with
+/// the effect signatures of their public functions
 fn collect_dependency_sinks(
     chain: &mut AuditChain,
     deps: &Vec<Dependency>,
-) -> Result<HashSet<CanonicalPath>> {
+) -> Result<(HashSet<CanonicalPath>, HashMap<CanonicalPath, EffectSignature>)> {
     let mut sinks = HashSet::new();
+    let mut signatures = HashMap::new();
     for dep in deps {
         let dep_id = CrateId::from(dep);
         let audit_file = chain.read_audit_file(&dep_id)?.context(
             "couldnt read dependency audit file (maybe created it out of order)",
         )?;
         sinks.extend(audit_file.pub_caller_checked.keys().cloned());
+        signatures.extend(audit_file.pub_effect_signatures);
     }
 
-    Ok(sinks)
+    Ok((sinks, signatures))
 }
 
 /// Creates a new default audit file for the given package and returns the path to
@@ -404,12 +978,14 @@ fn collect_dependency_sinks(
 fn make_new_audit_file(
     chain: &mut AuditChain,
     package: &Package,
-    root_name: &str,
+    is_member: bool,
+    provenance: Provenance,
     args: &Create,
     crate_path: &Path,
     audit_type: DefaultAuditType,
     relevant_effects: &[EffectType],
     quick_mode: bool,
+    previous: Option<&AuditFile>,
 ) -> Result<()> {
     let audit_file_path = PathBuf::from(format!(
         "{}/{}-{}.audit",
@@ -417,35 +993,66 @@ fn make_new_audit_file(
         package.name.as_str(),
         package.version
     ));
-    // download the new audit
-    let full_name = format!("{}-{}", package.name, package.version);
-    let package_path = if full_name == root_name {
-        // We are creating a audit for the root crate
-        PathBuf::from(args.crate_path.clone()).canonicalize()?
-    } else {
-        PathBuf::from(crate_path).canonicalize()?
-    };
+    // NOTE: Workspace members (and other path dependencies) are scanned in
+    //       place rather than downloaded
+    let package_path = PathBuf::from(crate_path).canonicalize()?;
 
     // Try to create a new default audit
     if audit_file_path.is_dir() {
-        return Err(anyhow!("Audit path is a directory"));
+        return Err(anyhow!("Audit path is a directory").into());
     }
     if audit_file_path.is_file() {
         if args.force_overwrite {
             remove_file(audit_file_path.clone())?;
         } else {
-            return Err(anyhow!("Audit file already exists"));
+            return Err(anyhow!("Audit file already exists").into());
         }
     }
 
-    let sinks = collect_dependency_sinks(chain, &package.dependencies)?;
-    let audit_file = AuditFile::new_default_with_sinks(
+    let (mut sinks, dep_signatures) =
+        collect_dependency_sinks(chain, &package.dependencies)?;
+    if let Some(sinks_file) = &args.sinks_file {
+        sinks.extend(
+            load_sinks_file(sinks_file)?.iter().map(|s| CanonicalPath::new(s.as_str())),
+        );
+    }
+    // Dead code is only pruned from dependencies; workspace members are
+    // audited in full
+    let config = DefaultAuditConfig {
+        include_dead_code: is_member || args.include_dead_code,
+        dep_signatures,
+        max_depth: args.max_depth,
+        max_marked_fns: args.max_marked_fns,
+        expand_macros: args.expand_macros,
+        include_build_script: args.include_build_script,
+        // NOTE: Proc macros are always tagged, while build-dependencies are
+        //       only tagged along with the build scripts that use them
+        provenance: match provenance {
+            Provenance::Build if !args.include_build_script => Provenance::Src,
+            p => p,
+        },
+        ..Default::default()
+    };
+    let mut audit_file = AuditFile::new_default_with_sinks(
         &package_path,
         sinks,
         audit_type,
         relevant_effects,
         quick_mode,
+        &config,
     )?;
+    // NOTE: Cargo verifies registry crates against the checksums in the
+    //       lockfile when fetching them, so the audited source is the one
+    //       cargo builds
+    audit_file.checksum = package.checksum.as_ref().map(|c| c.to_string());
+    if let Some(previous) = previous {
+        let carried = audit_file.carry_forward(previous);
+        println!(
+            "  Carried forward the audit of {} of {} effects from unchanged functions",
+            carried,
+            audit_file.audit_trees.len()
+        );
+    }
     audit_file.save_to_file(audit_file_path.clone())?;
 
     chain.add_crate_audit_file(package, audit_file_path, audit_file.version);
@@ -453,6 +1060,190 @@ fn make_new_audit_file(
     Ok(())
 }
 
+/// The cargo configuration to resolve and fetch crates with
+fn cargo_config(offline: bool) -> Result<config::Config> {
+    let mut config = config::Config::default()?;
+    if offline {
+        config.configure(0, false, None, false, false, true, &None, &[], &[])?;
+    }
+    Ok(config)
+}
+
+/// Explains how to fix crates missing from the cache in offline mode
+fn missing_crates_error(e: anyhow::Error, offline: bool) -> anyhow::Error {
+    if offline {
+        anyhow!(
+            "{:#}\nSome crates aren't in the local cargo cache. Run `cargo fetch` \
+            with network access to cache them first.",
+            e
+        )
+    } else {
+        e
+    }
+}
+
+/// The crates of a workspace and its dependencies, as resolved by cargo
+struct ResolvedWorkspace {
+    members: Vec<CrateId>,
+    /// Where each crate is located
+    crate_paths: HashMap<CrateId, PathBuf>,
+    /// The proc-macro crates, which the compiler loads and runs
+    proc_macros: HashSet<CrateId>,
+    /// The crates whose code only runs at compile time, i.e. those only used
+    /// by build scripts or proc macros (including the proc macros
+    /// themselves), and where each one runs
+    compile_time: HashMap<CrateId, Provenance>,
+}
+
+impl ResolvedWorkspace {
+    /// Where the code of the crate runs
+    fn provenance(&self, crate_id: &CrateId) -> Provenance {
+        self.compile_time.get(crate_id).copied().unwrap_or_default()
+    }
+}
+
+/// Returns the crates of the workspace at the crate path and of its
+/// dependencies, downloading them if needed
+fn resolve_workspace(crate_path: &Path, offline: bool) -> Result<ResolvedWorkspace> {
+    let manifest_path = crate_path.canonicalize()?.join("Cargo.toml");
+    let config = cargo_config(offline)?;
+    let _lock = config.acquire_package_cache_lock();
+    let workspace = Workspace::new(&manifest_path, &config)?;
+    // NOTE: A crate outside of a workspace is the only member of its own
+    //       workspace
+    let members = workspace
+        .members()
+        .map(|p| CrateId::new(p.name().to_string(), p.version().clone()))
+        .collect::<Vec<_>>();
+    let fetch_options = FetchOptions { config: &config, targets: Vec::new() };
+    // NOTE: The package set includes every source in the lockfile: registry
+    //       crates are downloaded, git dependencies are checked out at their
+    //       locked revision, and path dependencies are used in place
+    let (resolve, package_set) = fetch(&workspace, &fetch_options)
+        .map_err(|e| missing_crates_error(e, offline))?;
+    let packages = package_set
+        .get_many(resolve.iter())
+        .map_err(|e| missing_crates_error(e, offline))?;
+    let crate_id =
+        |id: PackageId| CrateId::new(id.name().to_string(), id.version().clone());
+    let crate_paths = packages
+        .iter()
+        .map(|p| (crate_id(p.package_id()), p.root().to_path_buf()))
+        .collect::<HashMap<_, _>>();
+    let proc_macro_ids = packages
+        .iter()
+        .filter(|p| p.proc_macro())
+        .map(|p| p.package_id())
+        .collect::<HashSet<_>>();
+
+    // Crates reachable from the members through something other than a
+    // build-dependency or a proc macro end up in the built artifacts
+    let mut runtime = HashSet::new();
+    let mut worklist = workspace.members().map(|p| p.package_id()).collect::<Vec<_>>();
+    while let Some(id) = worklist.pop() {
+        if proc_macro_ids.contains(&id) || !runtime.insert(id) {
+            continue;
+        }
+        worklist.extend(
+            resolve
+                .deps(id)
+                .filter(|(_, deps)| deps.iter().any(|d| d.kind() != DepKind::Build))
+                .map(|(dep_id, _)| dep_id),
+        );
+    }
+    // Of the rest, those reachable from a proc macro run inside the compiler,
+    // and the others only run as part of a build script
+    let mut macro_time = HashSet::new();
+    let mut worklist = proc_macro_ids.iter().copied().collect::<Vec<_>>();
+    while let Some(id) = worklist.pop() {
+        if runtime.contains(&id) || !macro_time.insert(id) {
+            continue;
+        }
+        worklist.extend(resolve.deps(id).map(|(dep_id, _)| dep_id));
+    }
+    let compile_time = resolve
+        .iter()
+        .filter(|id| !runtime.contains(id))
+        .map(|id| {
+            let provenance = if macro_time.contains(&id) {
+                Provenance::ProcMacro
+            } else {
+                Provenance::Build
+            };
+            (crate_id(id), provenance)
+        })
+        .collect::<HashMap<_, _>>();
+
+    Ok(ResolvedWorkspace {
+        members,
+        crate_paths,
+        proc_macros: proc_macro_ids.into_iter().map(crate_id).collect(),
+        compile_time,
+    })
+}
+
+fn member_names(members: &[CrateId]) -> Vec<String> {
+    members.iter().map(|m| format!("{}-{}", m.crate_name, m.version)).collect()
+}
+
+/// Returns the crates reachable from the workspace members, with each crate
+/// coming after all of its dependencies
+fn workspace_post_order(
+    graph: &DiGraph<String, ()>,
+    member_nodes: &[NodeIndex],
+) -> Vec<NodeIndex> {
+    let mut order = Vec::new();
+    let mut traverse = DfsPostOrder::empty(graph);
+    for member_node in member_nodes {
+        // Crates reachable from several members are only visited once
+        traverse.move_to(*member_node);
+        while let Some(node) = traverse.next(graph) {
+            order.push(node);
+        }
+    }
+    order
+}
+
+/// Returns whether the crate is a workspace member, and the kind of default
+/// audit to make for it
+fn default_audit_type(
+    graph: &DiGraph<String, ()>,
+    node: NodeIndex,
+    member_nodes: &[NodeIndex],
+) -> (bool, DefaultAuditType) {
+    // Members which other members depend on are audited like dependencies,
+    // so that their effects carry over to the members using them
+    let is_member = member_nodes.contains(&node);
+    let used_by_member = graph
+        .neighbors_directed(node, Direction::Incoming)
+        .any(|n| member_nodes.contains(&n));
+    if is_member && !used_by_member {
+        (true, DefaultAuditType::Empty)
+    } else {
+        (is_member, DefaultAuditType::CallerChecked)
+    }
+}
+
+/// Records the RustSec advisories affecting the crates of the chain, using
+/// the given database or the default one if it exists
+fn check_advisories(chain: &mut AuditChain, advisory_db: Option<PathBuf>) -> Result<()> {
+    let advisory_db =
+        advisory_db.or_else(|| AdvisoryDb::default_path().filter(|p| p.is_dir()));
+    if let Some(path) = advisory_db {
+        println!("Checking RustSec advisories");
+        let affected = chain.set_advisories(&AdvisoryDb::load(&path)?);
+        for crate_id in chain.all_crates() {
+            for advisory in chain.advisories(crate_id) {
+                println!("  {} has {}", crate_id, advisory);
+            }
+        }
+        if affected == 0 {
+            println!("  No crates have known advisories");
+        }
+    }
+    Ok(())
+}
+
 pub fn create_new_audit_chain(
     args: Create,
     crate_download_path: &str,
@@ -464,76 +1255,211 @@ pub fn create_new_audit_chain(
         PathBuf::from(&args.crate_path),
         args.effect_types.clone(),
     );
+    chain.set_required_reviewers(args.required_reviewers);
 
     create_audit_chain_dirs(&args, crate_download_path)?;
 
     println!("Loading audit package lockfile");
     // If the lockfile doesn't exist, generate it
-    let lockfile = chain.load_lockfile()?;
-
-    let mut crate_path_buf = Path::new(&args.crate_path).canonicalize()?;
-    let crate_data = load_cargo_toml(&crate_path_buf)?;
+    let lockfile = chain.load_or_generate_lockfile(args.offline)?;
 
-    let root_name = format!("{}-{}", crate_data.crate_name, crate_data.version);
-
-    let config = config::Config::default()?;
-    let _lock = config.acquire_package_cache_lock();
-    let set = HashSet::new();
-    crate_path_buf.push("Cargo.toml");
-    let workspace = Workspace::new(Path::new(&crate_path_buf), &config)?;
-    let fetch_options = FetchOptions { config: &config, targets: Vec::new() };
-    let (resolve, _package_set) = fetch(&workspace, &fetch_options)?;
-    let crate_paths: HashMap<CrateId, PathBuf> =
-        HashMap::from_iter(resolve.iter().filter_map(|p| {
-            // NOTE: We should return Some for every element here
-            let source_id = p.source_id();
-            let Ok(mut source) = source_id.load(&config, &set) else {
-                return None;
-            };
-            match source.download(p) {
-                Ok(MaybePackage::Ready(pkg)) => {
-                    let crate_id =
-                        CrateId::new(p.name().to_string(), p.version().clone());
-                    Some((crate_id, pkg.root().to_path_buf()))
-                }
-                _ => None,
-            }
-        }));
+    let resolved = resolve_workspace(Path::new(&args.crate_path), args.offline)?;
+    chain.workspace_members = resolved.members.clone();
+    chain.set_proc_macros(&resolved.proc_macros);
 
     println!("Creating dependency graph");
-    let (graph, package_map, root_node) =
-        make_dependency_graph(&lockfile.packages, &root_name);
-    let mut traverse = DfsPostOrder::new(&graph, root_node);
-    while let Some(node) = traverse.next(&graph) {
+    let ((graph, package_map), member_nodes) =
+        make_dependency_graph(&lockfile.packages, &member_names(&resolved.members))?;
+    let order = workspace_post_order(&graph, &member_nodes);
+    let progress = Progress::new(order.len(), "packages", "Making default audits");
+    for node in order {
         let package = package_map.get(&node).unwrap();
-        println!("Making default audit for {} v{}", package.name, package.version);
-
-        let audit_type = if node == root_node {
-            DefaultAuditType::Empty
-        } else {
-            DefaultAuditType::CallerChecked
-        };
+        progress.start(
+            &format!("{} v{}", package.name, package.version),
+            &format!("Making default audit for {} v{}", package.name, package.version),
+        );
 
-        let crate_download_path = crate_paths
-            .get(&CrateId::from(package))
-            .context("Unresolved path for a crate")?;
+        let (is_member, audit_type) = default_audit_type(&graph, node, &member_nodes);
+        let crate_id = CrateId::from(package);
+        let crate_download_path = resolved
+            .crate_paths
+            .get(&crate_id)
+            .with_context(|| format!("Couldn't find the source of {}", crate_id))?;
 
         make_new_audit_file(
             &mut chain,
             package,
-            &root_name,
+            is_member,
+            resolved.provenance(&crate_id),
             &args,
             crate_download_path,
             audit_type,
             &args.effect_types,
             quick_mode,
+            None,
         )?;
+        progress.inc();
     }
+    progress.finish();
+
+    check_advisories(&mut chain, args.advisory_db.clone())?;
 
     println!("Finished creating audit chain");
     Ok(chain)
 }
 
+/// Updates the chain after its lockfile has changed (e.g. after a
+/// `cargo update`). Crates which are no longer in the lockfile are removed,
+/// and new crates (including new versions of existing ones) get default
+/// audits. Existing audits are kept, unless a dependency of the crate gained
+/// public caller-checked functions, in which case its default audit is remade
+/// to pick up the new sinks. New and remade audits carry forward the
+/// annotations of the earlier audit for effects in unchanged functions.
+/// Public caller-checked functions which were only in the old versions of
+/// dependencies are removed from the audits using them.
+pub fn update_audit_chain(
+    chain: &mut AuditChain,
+    args: Update,
+    quick_mode: bool,
+) -> Result<()> {
+    println!("Updating audit chain");
+    let create_args = args.create_args(chain);
+    create_dir_all(&create_args.audit_path)?;
+
+    println!("Loading audit package lockfile");
+    let lockfile = chain.load_or_generate_lockfile(args.offline)?;
+    let resolved = resolve_workspace(&chain.crate_path, args.offline)?;
+    chain.workspace_members = resolved.members.clone();
+    chain.set_proc_macros(&resolved.proc_macros);
+
+    let ((graph, package_map), member_nodes) =
+        make_dependency_graph(&lockfile.packages, &member_names(&resolved.members))?;
+    let order = workspace_post_order(&graph, &member_nodes);
+    let current =
+        order.iter().map(|n| CrateId::from(&package_map[n])).collect::<HashSet<_>>();
+
+    // Save the public caller-checked functions of the dropped crates before
+    // removing them, to compare against their new versions
+    let mut dropped = chain
+        .crate_policies
+        .keys()
+        .filter(|c| !current.contains(c))
+        .cloned()
+        .collect::<Vec<_>>();
+    dropped.sort_by(|c1, c2| {
+        (&c1.crate_name, &c1.version).cmp(&(&c2.crate_name, &c2.version))
+    });
+    let mut dropped_pub_fns: HashMap<String, HashSet<CanonicalPath>> = HashMap::new();
+    // The audit of the latest dropped version of each crate, to carry forward
+    // to its new version
+    let mut dropped_audits: HashMap<String, AuditFile> = HashMap::new();
+    for crate_id in &dropped {
+        let Some(audit_file) = chain.saved_audit_file(crate_id)? else {
+            continue;
+        };
+        dropped_pub_fns
+            .entry(crate_id.crate_name.clone())
+            .or_default()
+            .extend(audit_file.pub_caller_checked.keys().cloned());
+        dropped_audits.insert(crate_id.crate_name.clone(), audit_file);
+    }
+
+    // The public caller-checked functions each updated crate gained and lost
+    let mut changes: HashMap<
+        NodeIndex,
+        (HashSet<CanonicalPath>, HashSet<CanonicalPath>),
+    > = HashMap::new();
+    let mut added = Vec::new();
+    let mut remade = Vec::new();
+    for node in order {
+        let package = &package_map[&node];
+        let crate_id = CrateId::from(package);
+        let dep_changes = graph
+            .neighbors_directed(node, Direction::Outgoing)
+            .filter_map(|d| changes.get(&d))
+            .collect::<Vec<_>>();
+        let gained_sinks = dep_changes.iter().any(|(gained, _)| !gained.is_empty());
+
+        let (old_pub_fns, previous) = if !chain.crate_policies.contains_key(&crate_id) {
+            println!("Making default audit for {}", crate_id);
+            added.push(crate_id.clone());
+            (
+                dropped_pub_fns.get(&crate_id.crate_name).cloned().unwrap_or_default(),
+                dropped_audits.get(&crate_id.crate_name).cloned(),
+            )
+        } else if gained_sinks {
+            println!("Remaking default audit for {}", crate_id);
+            remade.push(crate_id.clone());
+            (
+                chain.saved_pub_caller_checked(&crate_id)?,
+                chain.saved_audit_file(&crate_id)?,
+            )
+        } else {
+            let lost_sinks = dep_changes
+                .iter()
+                .flat_map(|(_, lost)| lost.iter().cloned())
+                .collect::<HashSet<_>>();
+            if !lost_sinks.is_empty() {
+                let lost = chain.remove_sinks(&crate_id, &lost_sinks)?;
+                if !lost.is_empty() {
+                    changes.insert(node, (HashSet::new(), lost.into_iter().collect()));
+                }
+            }
+            continue;
+        };
+
+        let (is_member, audit_type) = default_audit_type(&graph, node, &member_nodes);
+        let crate_download_path = resolved
+            .crate_paths
+            .get(&crate_id)
+            .with_context(|| format!("Couldn't find the source of {}", crate_id))?;
+        make_new_audit_file(
+            chain,
+            package,
+            is_member,
+            resolved.provenance(&crate_id),
+            &create_args,
+            crate_download_path,
+            audit_type,
+            &create_args.effect_types,
+            quick_mode,
+            previous.as_ref(),
+        )?;
+
+        let new_pub_fns = chain.saved_pub_caller_checked(&crate_id)?;
+        let gained = new_pub_fns.difference(&old_pub_fns).cloned().collect();
+        let lost = old_pub_fns.difference(&new_pub_fns).cloned().collect();
+        changes.insert(node, (gained, lost));
+    }
+
+    for crate_id in &dropped {
+        println!("Removing audit for {}", crate_id);
+        chain.remove_crate(crate_id)?;
+    }
+
+    check_advisories(chain, args.advisory_db)?;
+
+    println!(
+        "Finished updating audit chain: {} crates added, {} remade, {} removed",
+        added.len(),
+        remade.len(),
+        dropped.len()
+    );
+    if !added.is_empty() || !remade.is_empty() {
+        println!(
+            "NOTE: Review the new and remade audits; only the annotations of \
+            effects in unchanged functions were carried forward:"
+        );
+        for crate_id in &added {
+            println!("  {}", crate_id);
+        }
+        for crate_id in &remade {
+            println!("  {}", crate_id);
+        }
+    }
+    Ok(())
+}
+
 // Mirror of the above that returns HashSet of sinks
 pub fn create_dependency_sinks(
     _args: Create,
@@ -548,12 +1474,68 @@ where
     I: IntoIterator<Item = Package>,
 {
     let name = cargo_lock::Name::from_str(&crate_id.crate_name)?;
-    let version = cargo_lock::Version::parse(&format!("{}", crate_id.version))?;
+    let version = cargo_lock::Version::parse(&format!("{}", crate_id.version))
+        .context("Invalid crate version")?;
     for p in project_packages {
         if p.name == name && p.version == version {
             return Ok(p);
         }
     }
 
-    Err(anyhow!("Couldn't find package in workspace"))
+    Err(anyhow!("Couldn't find package in workspace").into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sign_off_signature() {
+        let crate_id = CrateId::new("foo".to_string(), "1.0.0".parse().unwrap());
+        let key = SigningKey::from_hex(&"07".repeat(32)).unwrap();
+        let mut sign_off = SignOff {
+            reviewer: "alice".to_string(),
+            audit_hash: [1; 32],
+            signature: None,
+        };
+        assert!(sign_off.verify(&crate_id).is_err());
+
+        sign_off.signature = Some(key.sign(&sign_off.signed_bytes(&crate_id).unwrap()));
+        assert!(sign_off.verify(&crate_id).is_ok());
+
+        let other_crate = CrateId::new("foo".to_string(), "1.0.1".parse().unwrap());
+        assert!(sign_off.verify(&other_crate).is_err());
+        let renamed = SignOff { reviewer: "mallory".to_string(), ..sign_off.clone() };
+        assert!(renamed.verify(&crate_id).is_err());
+        let changed = SignOff { audit_hash: [2; 32], ..sign_off.clone() };
+        assert!(changed.verify(&crate_id).is_err());
+    }
+
+    #[test]
+    fn test_trusted_sign_offs() {
+        let crate_id = CrateId::new("foo".to_string(), "1.0.0".parse().unwrap());
+        let sign = |reviewer: &str, key: &SigningKey| {
+            let mut sign_off = SignOff {
+                reviewer: reviewer.to_string(),
+                audit_hash: [1; 32],
+                signature: None,
+            };
+            sign_off.signature =
+                Some(key.sign(&sign_off.signed_bytes(&crate_id).unwrap()));
+            sign_off
+        };
+        let alice = SigningKey::from_hex(&"07".repeat(32)).unwrap();
+        let bob = SigningKey::from_hex(&"08".repeat(32)).unwrap();
+        let mut trust = TrustConfig::default();
+        trust.keys.insert("alice".to_string(), alice.public_key());
+        trust.keys.insert("bob".to_string(), bob.public_key());
+
+        assert!(sign("alice", &alice).is_trusted(&crate_id, &trust));
+        // A valid signature only counts with the key trusted for the reviewer
+        let own_key = SigningKey::from_hex(&"09".repeat(32)).unwrap();
+        assert!(sign("alice", &own_key).verify(&crate_id).is_ok());
+        assert!(!sign("alice", &own_key).is_trusted(&crate_id, &trust));
+        assert!(!sign("bob", &alice).is_trusted(&crate_id, &trust));
+        assert!(!sign("carol", &own_key).is_trusted(&crate_id, &trust));
+    }
 }
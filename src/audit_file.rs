@@ -1,26 +1,43 @@
-use super::effect::{EffectInstance, SrcLoc};
+pub use super::effect::EffectInfo;
+use super::effect::EffectInstance;
 use crate::auditing::util::{
     hash_dir, MAX_AUDIT_FILE_SIZE, MAX_CALLER_CHECKED_TREE_SIZE,
 };
-use crate::effect::{Effect, EffectType};
+use crate::baseline::effect_hash;
+use crate::cbor;
+use crate::effect::{Effect, EffectSignature, EffectType, Provenance};
+use crate::filter::PathGlob;
 use crate::ident::CanonicalPath;
-use crate::scanner;
-use crate::scanner::ScanResults;
+use crate::native_libs::NativeLib;
+use crate::risk::effects_risk_score;
+use crate::scan_config::{ResolverKind, ScanConfig};
+use crate::scanner::{ScanResults, ScanTargets};
+use crate::schema::{self, AUDIT_FILE_SCHEMA_VERSION};
+use crate::util::{
+    git_config, load_cargo_toml, normalize_path, root_relative_dir, CrateId,
+};
 
-use std::collections::{HashMap, HashSet};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::env;
 use std::fmt;
 use std::fs::File;
-use std::io::Write;
+use std::io::{BufRead, BufReader, Read, Write};
 use std::path::Path as FilePath;
 use std::path::PathBuf;
+use std::sync::{OnceLock, RwLock};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
-use anyhow::{anyhow, Result};
+use crate::error::Result;
+use anyhow::anyhow;
+use itertools::Itertools;
+use log::info;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use serde_with::serde_as;
 
 /// SafetyAnnotation is really a lattice with `Skipped` as the top element, and
 /// `Unsafe` as the bottom element.
-#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, JsonSchema)]
 pub enum SafetyAnnotation {
     Skipped,
     Safe,
@@ -40,26 +57,7 @@ impl fmt::Display for SafetyAnnotation {
     }
 }
 
-#[derive(PartialEq, Debug, Serialize, Deserialize, Clone, Hash, Eq)]
-pub struct EffectInfo {
-    pub caller_path: CanonicalPath,
-    pub callee_loc: SrcLoc,
-}
-
-impl EffectInfo {
-    pub fn new(caller_path: CanonicalPath, callee_loc: SrcLoc) -> Self {
-        EffectInfo { caller_path, callee_loc }
-    }
-
-    pub fn from_instance(effect: &EffectInstance) -> Self {
-        let caller_src_path = effect.caller().clone();
-        let callee_loc = effect.call_loc().clone();
-
-        EffectInfo::new(caller_src_path, callee_loc)
-    }
-}
-
-#[derive(PartialEq, Debug, Serialize, Deserialize, Clone)]
+#[derive(PartialEq, Debug, Serialize, Deserialize, Clone, JsonSchema)]
 pub enum EffectTree {
     Leaf(EffectInfo, SafetyAnnotation),
     Branch(EffectInfo, Vec<EffectTree>),
@@ -89,14 +87,108 @@ impl EffectTree {
         }
     }
 
+    /// Returns the annotation summarizing the leaves of the tree: unsafe if
+    /// any leaf is, otherwise skipped if any leaf is still unaudited, and
+    /// safe only if every leaf is
+    pub fn summary_annotation(&self) -> SafetyAnnotation {
+        match self {
+            EffectTree::Leaf(_, a) => *a,
+            EffectTree::Branch(_, ts) => {
+                let annotations =
+                    ts.iter().map(|t| t.summary_annotation()).collect::<Vec<_>>();
+                [
+                    SafetyAnnotation::Unsafe,
+                    SafetyAnnotation::Skipped,
+                    SafetyAnnotation::CallerChecked,
+                ]
+                .into_iter()
+                .find(|a| annotations.contains(a))
+                .unwrap_or(SafetyAnnotation::Safe)
+            }
+        }
+    }
+
+    pub fn effect_info(&self) -> &EffectInfo {
+        match self {
+            EffectTree::Leaf(e, _) | EffectTree::Branch(e, _) => e,
+        }
+    }
+
+    /// Copies the annotations of an earlier version of the tree, for the
+    /// nodes whose function is unchanged along with all the functions on the
+    /// way to it from the effect. Returns whether any annotation was copied.
+    fn carry_forward<F>(&mut self, previous: &EffectTree, unchanged: &F) -> bool
+    where
+        F: Fn(&CanonicalPath) -> bool,
+    {
+        let info = self.effect_info();
+        if info.caller_path.as_str() != previous.effect_info().caller_path.as_str()
+            || !unchanged(&info.caller_path)
+        {
+            return false;
+        }
+        match (self, previous) {
+            (EffectTree::Leaf(_, a), EffectTree::Leaf(_, prev_a)) => {
+                if *prev_a == SafetyAnnotation::Skipped {
+                    return false;
+                }
+                *a = *prev_a;
+                true
+            }
+            (EffectTree::Branch(_, trees), EffectTree::Branch(_, prev_trees)) => {
+                let mut carried = false;
+                for t in trees {
+                    let caller = t.effect_info().caller_path.as_str();
+                    if let Some(prev_t) = prev_trees.iter().find(|prev_t| {
+                        prev_t.effect_info().caller_path.as_str() == caller
+                    }) {
+                        carried |= t.carry_forward(prev_t, unchanged);
+                    }
+                }
+                carried
+            }
+            // The effect was resolved here before, rather than left to the
+            // callers
+            (tree @ EffectTree::Branch(..), EffectTree::Leaf(_, prev_a))
+                if *prev_a != SafetyAnnotation::Skipped
+                    && *prev_a != SafetyAnnotation::CallerChecked =>
+            {
+                *tree = EffectTree::Leaf(tree.effect_info().clone(), *prev_a);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Returns the locations of the leaves with the given annotation
+    pub fn leaves_with(&self, annotation: SafetyAnnotation) -> Vec<&EffectInfo> {
+        match self {
+            EffectTree::Leaf(e, a) if *a == annotation => vec![e],
+            EffectTree::Leaf(_, _) => Vec::new(),
+            EffectTree::Branch(_, ts) => {
+                ts.iter().flat_map(|t| t.leaves_with(annotation)).collect()
+            }
+        }
+    }
+
+    /// Returns the leaf at the given location, if there is one
+    pub fn find_leaf_mut(&mut self, location: &EffectInfo) -> Option<&mut EffectTree> {
+        match self {
+            EffectTree::Leaf(e, _) if e == location => Some(self),
+            EffectTree::Leaf(_, _) => None,
+            EffectTree::Branch(_, ts) => {
+                ts.iter_mut().find_map(|t| t.find_leaf_mut(location))
+            }
+        }
+    }
+
     pub fn get_effect_infos(&self) -> HashSet<EffectInfo> {
         match self {
             EffectTree::Leaf(e, _) => vec![e.clone()].into_iter().collect::<HashSet<_>>(),
             EffectTree::Branch(e, next) => {
                 let mut res = next
                     .iter()
-                    .map(|x| x.get_effect_infos())
-                    .flatten()
+                    .flat_map(|x| x.get_effect_infos())
                     .collect::<HashSet<_>>();
                 res.insert(e.clone());
                 res
@@ -112,36 +204,614 @@ pub enum DefaultAuditType {
     CallerChecked,
 }
 
+/// Options for creating a default audit file
+#[derive(Clone, Debug, Default)]
+pub struct DefaultAuditConfig {
+    /// Keep effects in functions that are unreachable from the crate's public
+    /// API (or from `main`). By default these dead-code effects are left out
+    /// of the audit trees, since they can't affect users of the crate.
+    pub include_dead_code: bool,
+    /// Known effect signatures of public functions in dependency crates
+    pub dep_signatures: HashMap<CanonicalPath, EffectSignature>,
+    /// Maximum call-graph depth to propagate caller-checked annotations from
+    /// each effect. Callers beyond it are left unaudited for manual review.
+    pub max_depth: Option<usize>,
+    /// Maximum number of functions to mark caller-checked for each effect.
+    /// Callers beyond it are left unaudited for manual review.
+    pub max_marked_fns: Option<usize>,
+    /// Also scan the macro-expanded source of the crate, to find effects
+    /// inside macro invocations
+    pub expand_macros: bool,
+    /// Also scan the crate's build script, tagging its effects with the
+    /// `build` provenance
+    pub include_build_script: bool,
+    /// The provenance to tag all of the crate's effects with, for crates
+    /// which only run at compile time (e.g. proc macros)
+    pub provenance: Provenance,
+    /// Scan the crate under each of these sets of enabled features instead of
+    /// its default configuration, and record which effects are only found
+    /// under some of them
+    pub feature_sets: Vec<Vec<String>>,
+    /// Evaluate platform cfgs for this target triple instead of the host
+    pub target: Option<String>,
+    /// Which of the crate's tests, examples and benchmarks to scan
+    pub targets: ScanTargets,
+}
+
+impl DefaultAuditConfig {
+    fn has_propagation_limits(&self) -> bool {
+        self.max_depth.is_some() || self.max_marked_fns.is_some()
+    }
+}
+
 pub type AuditVersion = u32;
 
+/// A review of the crate from outside the audit (e.g. a cargo-crev review),
+/// based on which its effects were provisionally marked safe
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct ThirdPartyReview {
+    /// Where the review comes from, e.g. `crev`
+    pub source: String,
+    pub reviewers: Vec<String>,
+    #[serde(default)]
+    pub comments: Vec<String>,
+}
+
+/// The number of recent decisions an audit file keeps (see
+/// `AuditFile::decision_history`)
+pub const DECISION_HISTORY_LEN: usize = 20;
+
+/// A decision recorded in an audit, along with the effect's tree from before
+/// it so that it can be undone
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct AuditDecision {
+    pub effect: EffectInstance,
+    /// The location the decision was made for
+    pub location: EffectInfo,
+    pub annotation: SafetyAnnotation,
+    pub previous: EffectTree,
+    /// For decisions on all the locations matching a pattern (see
+    /// `AuditFile::mark_matching`), the pattern
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pattern: Option<String>,
+    /// The other effects the pattern matched, with their trees from before
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub others: Vec<(EffectInstance, EffectTree)>,
+}
+
+impl AuditDecision {
+    pub fn new(
+        effect: EffectInstance,
+        location: EffectInfo,
+        annotation: SafetyAnnotation,
+        previous: EffectTree,
+    ) -> Self {
+        Self { effect, location, annotation, previous, pattern: None, others: Vec::new() }
+    }
+}
+
+/// What the decision was made for, e.g. `foo::f at src/lib.rs:3:4..3:9`
+impl fmt::Display for AuditDecision {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.pattern {
+            Some(pattern) => write!(f, "the locations matching {}", pattern),
+            None => {
+                write!(f, "{} at {}", self.location.caller_path, self.location.callee_loc)
+            }
+        }
+    }
+}
+
+/// Adds a decision to the history, forgetting the oldest ones past
+/// `DECISION_HISTORY_LEN`, and records when and by whom it was made for each
+/// of the effects it changed
+pub(crate) fn push_decision(
+    history: &mut Vec<AuditDecision>,
+    decision_info: &mut HashMap<EffectInstance, DecisionInfo>,
+    decision: AuditDecision,
+) {
+    let (decided_at, auditor) = (unix_time(), auditor());
+    let effects =
+        std::iter::once(&decision.effect).chain(decision.others.iter().map(|(e, _)| e));
+    for effect in effects {
+        let info = decision_info.entry(effect.clone()).or_insert_with(|| DecisionInfo {
+            decided_at,
+            auditor: None,
+            time_spent: 0,
+            expires_at: None,
+        });
+        info.decided_at = decided_at;
+        info.auditor.clone_from(&auditor);
+        // A new decision isn't bound by the expiration of the old one
+        info.expires_at = None;
+    }
+    history.push(decision);
+    if history.len() > DECISION_HISTORY_LEN {
+        history.drain(..history.len() - DECISION_HISTORY_LEN);
+    }
+}
+
+/// Forgets when the decision for an effect was made once it's undone, if
+/// that leaves the effect unaudited
+pub(crate) fn forget_undone(
+    decision_info: &mut HashMap<EffectInstance, DecisionInfo>,
+    effect: &EffectInstance,
+    previous: &EffectTree,
+) {
+    if let EffectTree::Leaf(_, SafetyAnnotation::Skipped) = previous {
+        decision_info.remove(effect);
+    }
+}
+
+/// The current time, in seconds since the Unix epoch
+pub fn unix_time() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs())
+}
+
+/// Formats a time given in seconds since the Unix epoch, e.g.
+/// `2024-01-31T12:00:00Z`
+pub fn format_time(secs: u64) -> String {
+    humantime::format_rfc3339_seconds(UNIX_EPOCH + Duration::from_secs(secs)).to_string()
+}
+
+/// Parses a time given as a date, e.g. `2024-01-31` for its start in UTC, or
+/// as an RFC 3339 time, into seconds since the Unix epoch
+pub fn parse_time(s: &str) -> Result<u64> {
+    let time = humantime::parse_rfc3339_weak(s)
+        .or_else(|_| humantime::parse_rfc3339_weak(&format!("{} 00:00:00", s)))
+        .map_err(|e| anyhow!("Invalid time {}: {}", s, e))?;
+    Ok(time.duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs()))
+}
+
+/// The environment variable to read the auditor's identity from
+pub const AUDITOR_ENV: &str = "CARGO_SCAN_AUDITOR";
+
+static AUDITOR: RwLock<Option<String>> = RwLock::new(None);
+
+/// Sets the identity of the auditor recorded with decisions from now on
+pub fn set_auditor(auditor: Option<String>) {
+    *AUDITOR.write().unwrap() = auditor;
+}
+
+/// The identity of the auditor recorded with decisions and policies: the one
+/// set with `set_auditor`, or else the first of
+/// - the `CARGO_SCAN_AUDITOR` environment variable
+/// - the `cargo-scan.auditor` git config, e.g. a name and email address or a
+///   key fingerprint
+/// - the git `user.name` and `user.email`, as in a commit
+/// - the current user
+pub fn auditor() -> Option<String> {
+    static DEFAULT_AUDITOR: OnceLock<Option<String>> = OnceLock::new();
+    AUDITOR
+        .read()
+        .unwrap()
+        .clone()
+        .or_else(|| DEFAULT_AUDITOR.get_or_init(default_auditor).clone())
+}
+
+fn default_auditor() -> Option<String> {
+    env::var(AUDITOR_ENV)
+        .ok()
+        .filter(|a| !a.is_empty())
+        .or_else(|| git_config("cargo-scan.auditor"))
+        .or_else(|| {
+            let name = git_config("user.name")?;
+            Some(match git_config("user.email") {
+                Some(email) => format!("{} <{}>", name, email),
+                None => name,
+            })
+        })
+        .or_else(|| env::var("USER").or_else(|_| env::var("USERNAME")).ok())
+}
+
+/// When and by whom the decision for an effect was made
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct DecisionInfo {
+    /// When the decision was last changed, in seconds since the Unix epoch
+    pub decided_at: u64,
+    pub auditor: Option<String>,
+    /// Seconds spent on the effect in interactive audits, over all sessions
+    #[serde(default)]
+    pub time_spent: u64,
+    /// When the decision has to be audited again, in seconds since the Unix
+    /// epoch
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<u64>,
+}
+
+/// The progress of an unfinished interactive audit, so that the next audit
+/// can resume where it left off
+#[serde_as]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct AuditSession {
+    /// When the audit was started, in seconds since the Unix epoch
+    pub started_at: u64,
+    /// When the auditor last worked on the audit
+    pub updated_at: u64,
+    /// The effect the auditor was on when they quit
+    pub current: Option<EffectInstance>,
+    /// The effects shown to the auditor so far, in the order they were shown
+    pub shown: Vec<EffectInstance>,
+    /// Seconds spent on each effect which hasn't been decided on yet
+    #[serde_as(as = "Vec<(_, _)>")]
+    #[schemars(with = "Vec<(EffectInstance, u64)>")]
+    pub time_spent: HashMap<EffectInstance, u64>,
+}
+
+impl Default for AuditSession {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AuditSession {
+    /// Starts a new session now
+    pub fn new() -> Self {
+        let now = unix_time();
+        Self {
+            started_at: now,
+            updated_at: now,
+            current: None,
+            shown: Vec::new(),
+            time_spent: HashMap::new(),
+        }
+    }
+
+    /// Notes that the effect is shown to the auditor
+    pub fn show(&mut self, effect: &EffectInstance) {
+        self.current = Some(effect.clone());
+        if !self.shown.contains(effect) {
+            self.shown.push(effect.clone());
+        }
+        self.updated_at = unix_time();
+    }
+
+    /// Adds to the time spent on the effect
+    pub fn spend(&mut self, effect: &EffectInstance, time: Duration) {
+        *self.time_spent.entry(effect.clone()).or_default() += time.as_secs();
+        self.updated_at = unix_time();
+    }
+
+    /// Moves the time spent on the effect to its decision, once it's made
+    pub fn charge(
+        &mut self,
+        effect: &EffectInstance,
+        decision_info: &mut HashMap<EffectInstance, DecisionInfo>,
+    ) {
+        if let (Some(secs), Some(info)) =
+            (self.time_spent.get(effect), decision_info.get_mut(effect))
+        {
+            info.time_spent += secs;
+            self.time_spent.remove(effect);
+        }
+    }
+}
+
+/// Why an effect in a policy no longer matches the crate's source (see
+/// `AuditFile::stale_effects`)
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Staleness {
+    /// The function containing the effect is still there, but the effect
+    /// isn't found at its location any more
+    MissingLocation,
+    /// The function containing the effect, or one of the callers it was
+    /// marked caller-checked for, no longer exists
+    MissingFunction(CanonicalPath),
+}
+
+impl fmt::Display for Staleness {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Staleness::MissingLocation => write!(f, "no longer found at its location"),
+            Staleness::MissingFunction(func) => write!(f, "{} no longer exists", func),
+        }
+    }
+}
+
+/// How much of a policy has been audited (see `AuditFile::stats`). Stats for
+/// several policies can be added up.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct AuditStats {
+    /// The number of base effects
+    pub effects: usize,
+    /// The number of base effects with every location audited
+    pub audited: usize,
+    /// The number of locations to audit, i.e. the leaves of the effect trees
+    pub locations: usize,
+    pub unaudited_locations: usize,
+    /// The number of audited effects marked safe, unsafe and caller-checked
+    pub safe: usize,
+    pub unsafe_effects: usize,
+    pub caller_checked: usize,
+    /// Seconds spent on the audited effects whose time was tracked, and the
+    /// number of those effects
+    pub time_spent: u64,
+    pub timed: usize,
+}
+
+impl AuditStats {
+    pub fn unaudited(&self) -> usize {
+        self.effects - self.audited
+    }
+
+    /// The percentage of the effects which have been audited
+    pub fn coverage(&self) -> f64 {
+        if self.effects == 0 {
+            100.0
+        } else {
+            100.0 * self.audited as f64 / self.effects as f64
+        }
+    }
+
+    /// The time left to audit the remaining effects, going by the average time
+    /// spent on the effects audited so far. None if no time was tracked.
+    pub fn estimated_remaining(&self) -> Option<Duration> {
+        if self.timed == 0 {
+            return None;
+        }
+        let per_effect = self.time_spent / self.timed as u64;
+        Some(Duration::from_secs(per_effect * self.unaudited() as u64))
+    }
+}
+
+impl std::ops::AddAssign<&AuditStats> for AuditStats {
+    fn add_assign(&mut self, other: &AuditStats) {
+        self.effects += other.effects;
+        self.audited += other.audited;
+        self.locations += other.locations;
+        self.unaudited_locations += other.unaudited_locations;
+        self.safe += other.safe;
+        self.unsafe_effects += other.unsafe_effects;
+        self.caller_checked += other.caller_checked;
+        self.time_spent += other.time_spent;
+        self.timed += other.timed;
+    }
+}
+
+impl fmt::Display for AuditStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}/{} effects audited ({:.0}%): {} safe, {} unsafe, {} caller-checked",
+            self.audited,
+            self.effects,
+            self.coverage(),
+            self.safe,
+            self.unsafe_effects,
+            self.caller_checked
+        )?;
+        if self.unaudited_locations > 0 {
+            write!(f, "; {} locations left", self.unaudited_locations)?;
+            if let Some(remaining) = self.estimated_remaining() {
+                write!(f, ", about {} to go", humantime::format_duration(remaining))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Marks the leaf at the location with the annotation, and returns its
+/// previous annotation. See `AuditFile::record_decision`.
+fn decide(
+    tree: &mut EffectTree,
+    location: &EffectInfo,
+    annotation: SafetyAnnotation,
+    scan_res: Option<&ScanResults>,
+) -> Result<SafetyAnnotation> {
+    let prev_locations = tree.get_effect_infos();
+    let leaf = tree.find_leaf_mut(location).ok_or_else(|| {
+        anyhow!("No location to audit at {} in the effect's tree", location.callee_loc)
+    })?;
+
+    let callers = match (annotation, scan_res) {
+        (SafetyAnnotation::CallerChecked, Some(scan_res)) => scan_res
+            .get_callers(&location.caller_path)?
+            .into_iter()
+            .filter(|e| !prev_locations.contains(e))
+            .map(|e| EffectTree::Leaf(e, SafetyAnnotation::Skipped))
+            .collect::<Vec<_>>(),
+        _ => Vec::new(),
+    };
+    let prev = leaf.get_leaf_annotation().unwrap();
+    if callers.is_empty() {
+        leaf.set_annotation(annotation);
+    } else {
+        *leaf = EffectTree::Branch(location.clone(), callers);
+    }
+    Ok(prev)
+}
+
+/// Marks the unaudited locations matching the pattern in the trees (see
+/// `AuditFile::mark_matching`), and returns the number of locations marked
+/// along with the decision to keep in the history, if any were
+pub(crate) fn mark_matching_trees<'a, I>(
+    trees: I,
+    pattern: &PathGlob,
+    annotation: SafetyAnnotation,
+    scan_res: Option<&ScanResults>,
+) -> Result<(usize, Option<AuditDecision>)>
+where
+    I: IntoIterator<Item = (&'a EffectInstance, &'a mut EffectTree)>,
+{
+    let mut marked = 0;
+    let mut changed = Vec::new();
+    let mut first_location = None;
+    for (effect, tree) in trees {
+        let locations = tree
+            .leaves_with(SafetyAnnotation::Skipped)
+            .into_iter()
+            .filter(|l| pattern.matches(effect.callee_path(), l.caller_path.as_str()))
+            .cloned()
+            .collect::<Vec<_>>();
+        if locations.is_empty() {
+            continue;
+        }
+        changed.push((effect.clone(), tree.clone()));
+        first_location.get_or_insert_with(|| locations[0].clone());
+        for location in &locations {
+            decide(tree, location, annotation, scan_res)?;
+        }
+        marked += locations.len();
+    }
+    let decision = first_location.map(|location| {
+        let (effect, previous) = changed.remove(0);
+        AuditDecision {
+            pattern: Some(pattern.to_string()),
+            others: changed,
+            ..AuditDecision::new(effect, location, annotation, previous)
+        }
+    });
+    Ok((marked, decision))
+}
+
 // TODO: Include information about crate/version
 // TODO: We should include more information from the ScanResult
 #[serde_as]
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug, JsonSchema)]
 pub struct AuditFile {
+    /// Version of the serialized format (see `schema`)
+    #[serde(default)]
+    pub schema_version: u32,
     #[serde_as(as = "Vec<(_, _)>")]
+    #[schemars(with = "Vec<(EffectInstance, EffectTree)>")]
     pub audit_trees: HashMap<EffectInstance, EffectTree>,
     /// Contains a map from public functions marked caller-checked to a set of
     /// all base EffectInstances that flow into that function
     #[serde_as(as = "Vec<(_, _)>")]
+    #[schemars(with = "Vec<(CanonicalPath, HashSet<EffectInstance>)>")]
     pub pub_caller_checked: HashMap<CanonicalPath, HashSet<EffectInstance>>,
     // TODO: Make the base_dir a crate instead
     pub base_dir: PathBuf,
+    /// The crate at `base_dir`. Along with the `hash` of its contents, this
+    /// identifies the crate if the audit is loaded on another machine.
+    #[serde(default)]
+    #[schemars(with = "Option<String>")]
+    pub crate_id: Option<CrateId>,
     pub hash: [u8; 32],
+    /// The SHA-256 checksum of the crate's `.crate` file recorded in
+    /// Cargo.lock, for crates from a registry
+    #[serde(default)]
+    pub checksum: Option<String>,
+    /// Whether the source locations in the saved file are relative to the
+    /// crate root. Always false once loaded.
+    #[serde(default)]
+    relative_locs: bool,
     pub version: AuditVersion,
     pub scanned_effects: Vec<EffectType>,
+    /// Effects in dead code which were excluded from the audit trees
+    #[serde(default)]
+    pub dead_code_effects: Vec<EffectInstance>,
+    /// The effect signature of every public function in the crate
+    #[serde_as(as = "Vec<(_, _)>")]
+    #[serde(default)]
+    #[schemars(with = "Vec<(CanonicalPath, EffectSignature)>")]
+    pub pub_effect_signatures: HashMap<CanonicalPath, EffectSignature>,
+    /// Known effect signatures of the dependency functions the crate calls
+    #[serde_as(as = "Vec<(_, _)>")]
+    #[serde(default)]
+    #[schemars(with = "Vec<(CanonicalPath, EffectSignature)>")]
+    pub dep_effect_signatures: HashMap<CanonicalPath, EffectSignature>,
+    /// The effects in this crate each public function may transitively perform
+    #[serde_as(as = "Vec<(_, _)>")]
+    #[serde(default)]
+    #[schemars(with = "Vec<(CanonicalPath, HashSet<Effect>)>")]
+    pub pub_fn_effects: HashMap<CanonicalPath, HashSet<Effect>>,
+    /// The dependency functions each public function may transitively call.
+    /// Used to stitch the audit files of a chain into a whole-program call
+    /// graph.
+    #[serde_as(as = "Vec<(_, _)>")]
+    #[serde(default)]
+    #[schemars(with = "Vec<(CanonicalPath, HashSet<CanonicalPath>)>")]
+    pub pub_dep_calls: HashMap<CanonicalPath, HashSet<CanonicalPath>>,
+    /// A shortest example call path from a public function to each effect
+    #[serde_as(as = "Vec<(_, _)>")]
+    #[serde(default)]
+    #[schemars(with = "Vec<(EffectInstance, Vec<EffectInfo>)>")]
+    pub example_paths: HashMap<EffectInstance, Vec<EffectInfo>>,
+    /// Native libraries linked by the crate
+    #[serde(default)]
+    pub native_libs: Vec<NativeLib>,
+    /// The native library each FFI effect binds to, where known
+    #[serde_as(as = "Vec<(_, _)>")]
+    #[serde(default)]
+    #[schemars(with = "Vec<(EffectInstance, String)>")]
+    pub ffi_libs: HashMap<EffectInstance, String>,
+    /// Set if the effects were marked safe based on a third-party review
+    /// rather than audited
+    #[serde(default)]
+    pub third_party_review: Option<ThirdPartyReview>,
+    /// Hash of the tokens of each function, by function path, used to carry
+    /// audit decisions forward to other versions of the crate. Functions
+    /// sharing a path have an empty hash.
+    #[serde(default)]
+    pub fn_hashes: HashMap<String, String>,
+    /// The auditor's comment on the decision for each effect, e.g. why it was
+    /// marked safe or what callers have to check
+    #[serde_as(as = "Vec<(_, _)>")]
+    #[serde(default)]
+    #[schemars(with = "Vec<(EffectInstance, String)>")]
+    pub comments: HashMap<EffectInstance, String>,
+    /// The named criteria the audit was certified to meet, e.g.
+    /// `safe-to-deploy` (see `criteria`)
+    #[serde(default)]
+    pub criteria: Vec<String>,
+    /// The most recent decisions, oldest first, so that mistakes can be
+    /// undone (see `undo_last_decision`)
+    #[serde(default)]
+    pub decision_history: Vec<AuditDecision>,
+    /// When and by whom the decision for each effect was made
+    #[serde_as(as = "Vec<(_, _)>")]
+    #[serde(default)]
+    #[schemars(with = "Vec<(EffectInstance, DecisionInfo)>")]
+    pub decision_info: HashMap<EffectInstance, DecisionInfo>,
+    /// The progress of the interactive audit, if the last one was left
+    /// unfinished
+    #[serde(default)]
+    pub session: Option<AuditSession>,
+    /// Decisions made before this time, in seconds since the Unix epoch, have
+    /// to be audited again once it has passed
+    #[serde(default)]
+    pub expires_at: Option<u64>,
+    /// Decisions older than this many seconds have to be audited again
+    #[serde(default)]
+    pub max_age: Option<u64>,
+    /// The identity of the auditor who created the policy (see `auditor`)
+    #[serde(default)]
+    pub auditor: Option<String>,
 }
 
 impl AuditFile {
     pub fn empty(p: PathBuf, relevant_effects: Vec<EffectType>) -> Result<Self> {
         let hash = hash_dir(p.clone())?;
         Ok(AuditFile {
+            schema_version: AUDIT_FILE_SCHEMA_VERSION,
             audit_trees: HashMap::new(),
             pub_caller_checked: HashMap::new(),
+            crate_id: load_cargo_toml(&p).ok(),
             base_dir: p,
             hash,
+            checksum: None,
+            relative_locs: false,
             version: 0,
             scanned_effects: relevant_effects,
+            dead_code_effects: Vec::new(),
+            pub_effect_signatures: HashMap::new(),
+            dep_effect_signatures: HashMap::new(),
+            pub_fn_effects: HashMap::new(),
+            pub_dep_calls: HashMap::new(),
+            example_paths: HashMap::new(),
+            native_libs: Vec::new(),
+            ffi_libs: HashMap::new(),
+            third_party_review: None,
+            fn_hashes: HashMap::new(),
+            comments: HashMap::new(),
+            criteria: Vec::new(),
+            decision_history: Vec::new(),
+            decision_info: HashMap::new(),
+            session: None,
+            expires_at: None,
+            max_age: None,
+            auditor: auditor(),
         })
     }
 
@@ -163,87 +833,265 @@ impl AuditFile {
             .collect::<HashMap<_, _>>();
     }
 
+    /// Compute the effect signatures of the crate's public functions from the
+    /// scan results, and record the signatures of any dependency functions the
+    /// crate calls.
+    pub fn set_effect_signatures(
+        &mut self,
+        scan_res: &ScanResults,
+        dep_signatures: &HashMap<CanonicalPath, EffectSignature>,
+    ) {
+        let signatures = scan_res.effect_signatures(dep_signatures);
+        self.pub_effect_signatures = scan_res
+            .pub_fns
+            .iter()
+            .map(|f| (f.clone(), signatures.get(f).cloned().unwrap_or_default()))
+            .collect();
+
+        let called =
+            scan_res.node_idxs.keys().map(|f| f.as_str()).collect::<HashSet<_>>();
+        self.dep_effect_signatures = dep_signatures
+            .iter()
+            .filter(|(f, _)| called.contains(f.as_str()))
+            .map(|(f, sig)| (f.clone(), sig.clone()))
+            .collect();
+    }
+
+    /// Record the effects and dependency functions each public function may
+    /// transitively reach. The dependency functions are the ones with known
+    /// effect signatures, i.e. the public functions of the dependency crates.
+    pub fn set_pub_fn_calls(
+        &mut self,
+        scan_res: &ScanResults,
+        dep_signatures: &HashMap<CanonicalPath, EffectSignature>,
+    ) {
+        let effects = scan_res.transitive_effects(&scan_res.effects);
+        self.pub_fn_effects = scan_res
+            .pub_fns
+            .iter()
+            .filter_map(|f| {
+                let effects = effects.get(f)?;
+                Some((f.clone(), effects.iter().map(|e| e.eff_type().clone()).collect()))
+            })
+            .collect();
+
+        let dep_fns = dep_signatures.keys().map(|f| f.as_str()).collect::<HashSet<_>>();
+        let mut dep_calls = scan_res.transitive_callees(|f| dep_fns.contains(f.as_str()));
+        self.pub_dep_calls = scan_res
+            .pub_fns
+            .iter()
+            .filter_map(|f| Some((f.clone(), dep_calls.remove(f)?)))
+            .collect();
+    }
+
+    /// Returns the known effect signature of the dependency function called by
+    /// a sink call effect, if there is one
+    pub fn sink_signature(&self, effect: &EffectInstance) -> Option<&EffectSignature> {
+        let sink = effect.pattern()?;
+        self.dep_effect_signatures
+            .iter()
+            .find(|(f, _)| f.as_str() == sink.as_str())
+            .map(|(_, sig)| sig)
+    }
+
+    /// Serializes the audit file with all of its source locations relative to
+    /// the crate root, so it can be loaded on another machine or OS
+    pub fn to_portable_json(&self) -> Result<String> {
+        Ok(serde_json::to_string(&self.to_portable_value()?)?)
+    }
+
+    /// Like `to_portable_json`, but in the more compact CBOR (see `cbor`)
+    pub fn to_portable_cbor(&self) -> Result<Vec<u8>> {
+        cbor::to_vec(&self.to_portable_value()?)
+    }
+
+    pub(crate) fn to_portable_value(&self) -> Result<serde_json::Value> {
+        let mut json = serde_json::to_value(self)?;
+        let base_dir = self.base_dir.clone();
+        relocate_src_locs(&mut json, &|dir| root_relative_dir(dir, &base_dir));
+        json["base_dir"] = serde_json::to_value(normalize_path(&self.base_dir))?;
+        json["relative_locs"] = serde_json::Value::Bool(true);
+        Ok(json)
+    }
+
+    /// Saves the audit file in its portable form (see `to_portable_json`), as
+    /// CBOR if the path has the `cbor` extension and as JSON otherwise
     pub fn save_to_file(&self, p: PathBuf) -> Result<()> {
-        let json = serde_json::to_string(self)?;
+        let bytes = if p.extension().is_some_and(|ext| ext == "cbor") {
+            self.to_portable_cbor()?
+        } else {
+            self.to_portable_json()?.into_bytes()
+        };
         let mut f = File::create(p)?;
-        f.write_all(json.as_bytes())?;
+        f.write_all(&bytes)?;
         Ok(())
     }
 
+    /// Parses an audit file saved by `to_portable_json`, or by an older
+    /// version of cargo-scan (see `schema`)
+    pub fn from_json_str(json_string: &str) -> Result<AuditFile> {
+        Self::from_json_value(serde_json::from_str(json_string)?)
+    }
+
+    /// Parses an audit file saved by `to_portable_cbor`
+    pub fn from_cbor<R: Read>(reader: R) -> Result<AuditFile> {
+        Self::from_json_value(cbor::from_reader(reader)?)
+    }
+
+    pub(crate) fn from_json_value(mut json: serde_json::Value) -> Result<AuditFile> {
+        schema::migrate_audit_file(&mut json)?;
+        if json["relative_locs"] == serde_json::Value::Bool(true) {
+            let base_dir: PathBuf = serde_json::from_value(json["base_dir"].clone())?;
+            relocate_src_locs(&mut json, &|dir| rebase_dir(dir, &base_dir));
+            json["relative_locs"] = serde_json::Value::Bool(false);
+        }
+        Ok(serde_json::from_value(json)?)
+    }
+
     /// Returns Some audit file if it exists, or None if we should create a new one.
     /// Errors if the audit filepath is invalid or if we can't read an existing
-    /// audit file
+    /// audit file. The file may be JSON or CBOR.
     pub fn read_audit_file(path: PathBuf) -> Result<Option<AuditFile>> {
         if path.is_dir() {
-            Err(anyhow!("Audit path is a directory"))
+            Err(anyhow!("Audit path is a directory").into())
         } else if path.is_file() {
-            let json_string = std::fs::read_to_string(path.as_path())?;
-            let audit_file = serde_json::from_str(&json_string)?;
-            Ok(Some(audit_file))
+            let mut reader = BufReader::new(File::open(path.as_path())?);
+            if cbor::is_cbor(reader.fill_buf()?) {
+                Ok(Some(Self::from_cbor(reader)?))
+            } else {
+                Ok(Some(Self::from_json_value(serde_json::from_reader(reader)?)?))
+            }
         } else {
             Ok(None)
         }
     }
 
+    /// Marks every base effect as safe
+    fn mark_all_safe(&mut self) {
+        for (_, mut t) in self.audit_trees.iter_mut() {
+            if let EffectTree::Leaf(_, a) = &mut t {
+                *a = SafetyAnnotation::Safe;
+            }
+        }
+    }
+
+    /// Provisionally marks every effect which isn't marked unsafe as safe,
+    /// based on a third-party review of the crate. Returns the public
+    /// functions which are no longer caller-checked.
+    pub fn apply_third_party_review(
+        &mut self,
+        review: ThirdPartyReview,
+    ) -> HashSet<CanonicalPath> {
+        fn mark_safe(tree: &mut EffectTree) {
+            match tree {
+                EffectTree::Leaf(_, SafetyAnnotation::Unsafe) => (),
+                EffectTree::Leaf(_, a) => *a = SafetyAnnotation::Safe,
+                EffectTree::Branch(_, ts) => ts.iter_mut().for_each(mark_safe),
+            }
+        }
+
+        let pub_fns = self.pub_caller_checked.keys().cloned().collect::<HashSet<_>>();
+        self.audit_trees.values_mut().for_each(mark_safe);
+        self.recalc_pub_caller_checked(&pub_fns);
+        self.third_party_review = Some(review);
+        // Bump the version so other chains know to recalculate their effects
+        self.version += 1;
+
+        pub_fns.into_iter().filter(|f| !self.pub_caller_checked.contains_key(f)).collect()
+    }
+
+    /// Moves the audit to a copy of the crate at a new location, e.g. when
+    /// the audit was created on another machine
+    pub fn relocate(self, new_base_dir: PathBuf) -> Result<Self> {
+        let mut json = serde_json::to_value(&self)?;
+        relocate_src_locs(&mut json, &|dir| match dir.strip_prefix(&self.base_dir) {
+            Ok(rel) => new_base_dir.join(rel),
+            Err(_) => dir.to_path_buf(),
+        });
+        json["base_dir"] = serde_json::to_value(&new_base_dir)?;
+        Ok(serde_json::from_value(json)?)
+    }
+
+    /// Returns the shortest call-graph distance from `base_fn` to each of its
+    /// transitive callers, in breadth-first order, stopping at the propagation
+    /// limits in `config`.
+    fn caller_depths(
+        base_fn: &CanonicalPath,
+        scan_res: &ScanResults,
+        config: &DefaultAuditConfig,
+    ) -> Result<HashMap<CanonicalPath, usize>> {
+        let max_depth = config.max_depth.unwrap_or(usize::MAX);
+        let max_marked_fns = config.max_marked_fns.unwrap_or(usize::MAX);
+
+        let mut depths = HashMap::from([(base_fn.clone(), 0)]);
+        let mut worklist = VecDeque::from([base_fn.clone()]);
+        while let Some(f) = worklist.pop_front() {
+            let depth = depths[&f];
+            if depth >= max_depth {
+                continue;
+            }
+            for caller in scan_res.get_callers(&f)? {
+                // NOTE: depths includes the base function, which isn't marked
+                if depths.len() > max_marked_fns {
+                    return Ok(depths);
+                }
+                if !depths.contains_key(&caller.caller_path) {
+                    depths.insert(caller.caller_path.clone(), depth + 1);
+                    worklist.push_back(caller.caller_path);
+                }
+            }
+        }
+
+        Ok(depths)
+    }
+
     /// Mark caller-checked functions but don't add a caller to the tree more
-    /// than once (so we don't get an infinite cycle).
+    /// than once (so we don't get an infinite cycle). Each function is placed
+    /// at its shortest distance from the effect, which keeps the tree size
+    /// linear in the size of the call graph even when it has cycles.
+    ///
+    /// Callers missing from `depths` are beyond the propagation limits, so
+    /// they are left as unaudited leaves for manual review.
     fn mark_caller_checked_recurse(
-        base_effect: &EffectInstance,
         tree: &mut EffectTree,
-        pub_caller_checked: &mut HashMap<CanonicalPath, HashSet<EffectInstance>>,
         scan_res: &ScanResults,
-        prev_callers: &mut HashSet<CanonicalPath>,
+        depths: &HashMap<CanonicalPath, usize>,
+        depth: usize,
+        visited: &mut HashSet<CanonicalPath>,
         tree_size: &mut i32,
     ) -> Result<()> {
         // TODO: Make this configurable/obsolete
         if *tree_size > MAX_CALLER_CHECKED_TREE_SIZE {
-            return Err(anyhow!("exceeded maximum effect tree size"));
+            return Err(anyhow!("exceeded maximum effect tree size").into());
         }
         if let EffectTree::Leaf(effect_info, annotation) = tree {
-            // Add the function to the list of sinks if it is public
-            if scan_res.pub_fns.contains(&effect_info.caller_path) {
-                pub_caller_checked
-                    .entry(effect_info.caller_path.clone())
-                    .or_default()
-                    .insert(base_effect.clone());
-            }
-
             let mut callers = scan_res
                 .get_callers(&effect_info.caller_path)?
                 .into_iter()
-                .filter_map(|e| {
-                    if prev_callers.contains(&e.caller_path) {
-                        None
-                    } else {
-                        Some(EffectTree::Leaf(e, SafetyAnnotation::Skipped))
-                    }
+                .filter(|e| match depths.get(&e.caller_path) {
+                    Some(d) => *d == depth + 1 && visited.insert(e.caller_path.clone()),
+                    None => visited.insert(e.caller_path.clone()),
                 })
+                .map(|e| EffectTree::Leaf(e, SafetyAnnotation::Skipped))
                 .collect::<Vec<_>>();
             if callers.is_empty() {
                 *annotation = SafetyAnnotation::CallerChecked;
             } else {
                 for eff in callers.iter_mut() {
                     *tree_size += 1;
-                    // NOTE: This will always be a leaf since it is only created
-                    //       from the map above
-                    let next_caller = if let EffectTree::Leaf(i, _) = eff {
-                        i.caller_path.clone()
-                    } else {
-                        return Err(anyhow!(
-                            "Terminal node of effect tree should be a leaf"
-                        ));
-                    };
-
-                    prev_callers.insert(next_caller.clone());
+                    if let EffectTree::Leaf(info, _) = eff {
+                        if !depths.contains_key(&info.caller_path) {
+                            continue;
+                        }
+                    }
                     AuditFile::mark_caller_checked_recurse(
-                        base_effect,
                         eff,
-                        pub_caller_checked,
                         scan_res,
-                        prev_callers,
+                        depths,
+                        depth + 1,
+                        visited,
                         tree_size,
                     )?;
-                    prev_callers.remove(&next_caller);
                 }
                 *tree = EffectTree::Branch(effect_info.clone(), callers);
             }
@@ -252,22 +1100,24 @@ impl AuditFile {
         Ok(())
     }
 
-    /// Mark all callers of functions in the effect tree to be caller-checked.
+    /// Mark all callers of functions in the effect tree to be caller-checked,
+    /// up to the propagation limits in `config`.
     fn mark_caller_checked(
         base_effect: &EffectInstance,
         tree: &mut EffectTree,
-        pub_caller_checked: &mut HashMap<CanonicalPath, HashSet<EffectInstance>>,
         scan_res: &ScanResults,
+        config: &DefaultAuditConfig,
         tree_size: &mut i32,
     ) -> Result<()> {
-        let mut callers = HashSet::new();
-        callers.insert(base_effect.caller().clone());
+        let depths = Self::caller_depths(base_effect.caller(), scan_res, config)?;
+        let mut visited = HashSet::new();
+        visited.insert(base_effect.caller().clone());
         Self::mark_caller_checked_recurse(
-            base_effect,
             tree,
-            pub_caller_checked,
             scan_res,
-            &mut callers,
+            &depths,
+            0,
+            &mut visited,
             tree_size,
         )
     }
@@ -393,6 +1243,235 @@ impl AuditFile {
         (unaudited_base, unaudited_total)
     }
 
+    /// Returns the locations which are still left to audit, i.e. the unaudited
+    /// leaves of the effect trees, along with their base effect
+    pub fn unaudited_locations(&self) -> Vec<(&EffectInstance, &EffectInfo)> {
+        self.audit_trees
+            .iter()
+            .flat_map(|(e, t)| {
+                t.leaves_with(SafetyAnnotation::Skipped).into_iter().map(move |l| (e, l))
+            })
+            .collect()
+    }
+
+    /// Returns whether every location in the effect's tree has been audited.
+    /// Effects which aren't part of the audit are never audited.
+    pub fn is_audited(&self, effect: &EffectInstance) -> bool {
+        self.audit_trees
+            .get(effect)
+            .is_some_and(|t| t.leaves_with(SafetyAnnotation::Skipped).is_empty())
+    }
+
+    /// Returns how much of the policy has been audited. Audited effects count
+    /// under the decision made on the effect itself, so a caller-checked
+    /// effect counts as caller-checked whatever its callers were marked.
+    pub fn stats(&self) -> AuditStats {
+        fn count_leaves(t: &EffectTree) -> usize {
+            match t {
+                EffectTree::Leaf(..) => 1,
+                EffectTree::Branch(_, ts) => ts.iter().map(count_leaves).sum(),
+            }
+        }
+
+        let mut stats =
+            AuditStats { effects: self.audit_trees.len(), ..Default::default() };
+        for (e, t) in &self.audit_trees {
+            let unaudited = t.leaves_with(SafetyAnnotation::Skipped).len();
+            stats.locations += count_leaves(t);
+            stats.unaudited_locations += unaudited;
+            if unaudited > 0 {
+                continue;
+            }
+            stats.audited += 1;
+            match t {
+                EffectTree::Leaf(_, SafetyAnnotation::Unsafe) => {
+                    stats.unsafe_effects += 1
+                }
+                EffectTree::Leaf(_, SafetyAnnotation::CallerChecked)
+                | EffectTree::Branch(..) => stats.caller_checked += 1,
+                EffectTree::Leaf(..) => stats.safe += 1,
+            }
+            if let Some(info) = self.decision_info.get(e).filter(|i| i.time_spent > 0) {
+                stats.time_spent += info.time_spent;
+                stats.timed += 1;
+            }
+        }
+        stats
+    }
+
+    /// Returns everyone who took part in the policy: the auditor who created
+    /// it and those who made its decisions, sorted
+    pub fn auditors(&self) -> Vec<&str> {
+        let decided = self.decision_info.values().filter_map(|i| i.auditor.as_deref());
+        self.auditor.as_deref().into_iter().chain(decided).sorted().dedup().collect()
+    }
+
+    /// Returns whether the decision on an audited effect has expired by `now`
+    /// (in seconds since the Unix epoch), as set for the decision itself or
+    /// for the whole policy. Decisions made before their time was recorded
+    /// are taken to be as old as can be.
+    pub fn is_expired(&self, effect: &EffectInstance, now: u64) -> bool {
+        if !self.is_audited(effect) {
+            return false;
+        }
+        let info = self.decision_info.get(effect);
+        let decided_at = info.map_or(0, |i| i.decided_at);
+        info.and_then(|i| i.expires_at).is_some_and(|t| t <= now)
+            || self.expires_at.is_some_and(|t| t <= now && decided_at < t)
+            || self.max_age.is_some_and(|age| decided_at.saturating_add(age) <= now)
+    }
+
+    /// Returns the audited effects whose decisions have expired by `now` (see
+    /// `is_expired`), sorted by location
+    pub fn expired_effects(&self, now: u64) -> Vec<&EffectInstance> {
+        let mut expired = self
+            .audit_trees
+            .keys()
+            .filter(|e| self.is_expired(e, now))
+            .collect::<Vec<_>>();
+        expired.sort_by_key(|e| e.call_loc().to_string());
+        expired
+    }
+
+    /// Returns whether every effect has been audited, with a decision which
+    /// hasn't expired by `now`. Expired decisions count as unaudited.
+    pub fn is_fully_audited(&self, now: u64) -> bool {
+        self.unaudited_effects().0 == 0 && self.expired_effects(now).is_empty()
+    }
+
+    /// Marks the effects whose decisions have expired by `now` unaudited
+    /// again, so that they are audited again. Returns the number of effects.
+    pub fn reopen_expired(&mut self, now: u64) -> usize {
+        let expired = self.expired_effects(now).into_iter().cloned().collect::<Vec<_>>();
+        for e in &expired {
+            let info = EffectInfo::from_instance(e);
+            self.audit_trees
+                .insert(e.clone(), EffectTree::Leaf(info, SafetyAnnotation::Skipped));
+            self.decision_info.remove(e);
+        }
+        if !expired.is_empty() {
+            self.refresh_pub_caller_checked();
+        }
+        expired.len()
+    }
+
+    /// Records the decision for a location in the effect's tree (see
+    /// `unaudited_locations`), and returns the previous one.
+    ///
+    /// Marking a location caller-checked adds its callers in `scan_res` to
+    /// the tree, to be audited in turn; without the scan results, or if there
+    /// are no new callers, the location is simply marked caller-checked.
+    /// `pub_caller_checked` isn't updated until `refresh_pub_caller_checked`
+    /// is called. The decision is kept in `decision_history`, to be undone
+    /// with `undo_last_decision`.
+    pub fn record_decision(
+        &mut self,
+        effect: &EffectInstance,
+        location: &EffectInfo,
+        annotation: SafetyAnnotation,
+        scan_res: Option<&ScanResults>,
+    ) -> Result<SafetyAnnotation> {
+        let tree = self
+            .audit_trees
+            .get_mut(effect)
+            .ok_or_else(|| anyhow!("Effect isn't part of the audit: {:?}", effect))?;
+        let previous = tree.clone();
+        let prev = decide(tree, location, annotation, scan_res)?;
+        push_decision(
+            &mut self.decision_history,
+            &mut self.decision_info,
+            AuditDecision::new(effect.clone(), location.clone(), annotation, previous),
+        );
+        Ok(prev)
+    }
+
+    /// Changes the decision for an effect which was already audited, and
+    /// returns the previous one. The decisions for any callers of the effect
+    /// are dropped along with it, and marking it caller-checked adds its
+    /// callers to audit again, as in `record_decision`.
+    pub fn revise_decision(
+        &mut self,
+        effect: &EffectInstance,
+        annotation: SafetyAnnotation,
+        scan_res: Option<&ScanResults>,
+    ) -> Result<SafetyAnnotation> {
+        let tree = self
+            .audit_trees
+            .get_mut(effect)
+            .ok_or_else(|| anyhow!("Effect isn't part of the audit: {:?}", effect))?;
+        let previous = tree.clone();
+        let location = previous.effect_info().clone();
+        *tree = EffectTree::Leaf(location.clone(), SafetyAnnotation::Skipped);
+        decide(tree, &location, annotation, scan_res)?;
+        let prev = previous.summary_annotation();
+        push_decision(
+            &mut self.decision_history,
+            &mut self.decision_info,
+            AuditDecision::new(effect.clone(), location, annotation, previous),
+        );
+        Ok(prev)
+    }
+
+    /// Records the decision for every unaudited location where the effect's
+    /// callee, or the function containing the location or its module,
+    /// matches the pattern, e.g. all the calls into generated bindings.
+    /// Returns the number of locations marked. The locations are marked as by
+    /// `record_decision`, and are undone together.
+    pub fn mark_matching(
+        &mut self,
+        pattern: &PathGlob,
+        annotation: SafetyAnnotation,
+        scan_res: Option<&ScanResults>,
+    ) -> Result<usize> {
+        let (marked, decision) = mark_matching_trees(
+            self.audit_trees.iter_mut(),
+            pattern,
+            annotation,
+            scan_res,
+        )?;
+        if let Some(decision) = decision {
+            push_decision(&mut self.decision_history, &mut self.decision_info, decision);
+        }
+        Ok(marked)
+    }
+
+    /// Undoes the most recent decision in the history by restoring the trees
+    /// of the effects it changed, and returns the decision, or None if there
+    /// is nothing to undo. As with `record_decision`, `pub_caller_checked`
+    /// isn't updated.
+    pub fn undo_last_decision(&mut self) -> Result<Option<AuditDecision>> {
+        let Some(decision) = self.decision_history.pop() else {
+            return Ok(None);
+        };
+        let changed = || {
+            std::iter::once((&decision.effect, &decision.previous))
+                .chain(decision.others.iter().map(|(e, t)| (e, t)))
+        };
+        if let Some((missing, _)) =
+            changed().find(|(e, _)| !self.audit_trees.contains_key(e))
+        {
+            let err = anyhow!(
+                "Effect of the last decision is no longer part of the audit: {:?}",
+                missing
+            );
+            self.decision_history.push(decision);
+            return Err(err.into());
+        }
+        for (effect, previous) in changed() {
+            self.audit_trees.insert(effect.clone(), previous.clone());
+            forget_undone(&mut self.decision_info, effect, previous);
+        }
+        Ok(Some(decision))
+    }
+
+    /// Recalculates `pub_caller_checked` from the effect trees for the public
+    /// functions recorded in the audit, for when the scan results aren't at
+    /// hand (see `recalc_pub_caller_checked`)
+    pub fn refresh_pub_caller_checked(&mut self) {
+        let pub_fns = self.pub_effect_signatures.keys().cloned().collect();
+        self.recalc_pub_caller_checked(&pub_fns);
+    }
+
     /// Print information about the audit:
     /// - total base effects
     /// - unaudited
@@ -405,9 +1484,20 @@ impl AuditFile {
 
         println!("Audit file info:");
         println!("  - total base effects: {}", self.audit_trees.len());
+        let auditors = self.auditors();
+        if !auditors.is_empty() {
+            println!("  - auditors: {}", auditors.join(", "));
+        }
         if self.has_unsafe_effect() {
             println!("  - package marked UNSAFE");
         }
+        if let Some(review) = &self.third_party_review {
+            println!(
+                "  - effects provisionally marked safe from a {} review by: {}",
+                review.source,
+                review.reviewers.join(", ")
+            );
+        }
         if unaudited_total == 0 {
             println!("  - package fully audited");
             let num_pub_cc = self.pub_caller_checked.len();
@@ -428,6 +1518,65 @@ impl AuditFile {
         }
     }
 
+    /// Returns the effects in the policy which no longer match the crate's
+    /// source as scanned in `scan_res`, e.g. since the code containing them
+    /// was changed or removed, along with why, sorted by location
+    pub fn stale_effects(
+        &self,
+        scan_res: &ScanResults,
+    ) -> Vec<(&EffectInstance, Staleness)> {
+        let found = scan_res
+            .effects
+            .iter()
+            .map(|e| (e.caller(), e.call_loc(), e.callee()))
+            .collect::<HashSet<_>>();
+        let mut stale = self
+            .audit_trees
+            .iter()
+            .filter_map(|(e, t)| {
+                if !found.contains(&(e.caller(), e.call_loc(), e.callee())) {
+                    return Some(if scan_res.fn_locs.contains_key(e.caller()) {
+                        (e, Staleness::MissingLocation)
+                    } else {
+                        (e, Staleness::MissingFunction(e.caller().clone()))
+                    });
+                }
+                // The callers a caller-checked effect was marked for
+                let missing = t
+                    .get_effect_infos()
+                    .into_iter()
+                    .map(|i| i.caller_path)
+                    .filter(|f| f != e.caller() && !scan_res.fn_locs.contains_key(f))
+                    .min_by(|f1, f2| f1.as_str().cmp(f2.as_str()))?;
+                Some((e, Staleness::MissingFunction(missing)))
+            })
+            .collect::<Vec<_>>();
+        stale.sort_by_key(|(e, _)| e.call_loc().to_string());
+        stale
+    }
+
+    /// Removes the effects from the policy, along with everything recorded
+    /// about them, including the decisions on them which could be undone
+    pub fn prune_effects(&mut self, effects: &HashSet<EffectInstance>) {
+        self.audit_trees.retain(|e, _| !effects.contains(e));
+        self.comments.retain(|e, _| !effects.contains(e));
+        self.decision_info.retain(|e, _| !effects.contains(e));
+        self.example_paths.retain(|e, _| !effects.contains(e));
+        self.ffi_libs.retain(|e, _| !effects.contains(e));
+        self.decision_history.retain(|d| {
+            !effects.contains(&d.effect)
+                && !d.others.iter().any(|(e, _)| effects.contains(e))
+        });
+        if let Some(session) = &mut self.session {
+            session.shown.retain(|e| !effects.contains(e));
+            session.time_spent.retain(|e, _| !effects.contains(e));
+            if session.current.as_ref().is_some_and(|e| effects.contains(e)) {
+                session.current = None;
+            }
+        }
+        self.refresh_pub_caller_checked();
+    }
+
     /// Removes any effect trees which have the given sink as the root. Returns
     /// the removed effects.
     pub fn remove_sinks_from_tree(
@@ -458,7 +1607,12 @@ impl AuditFile {
             .unzip();
         let new_trees = new_trees.into_iter().flatten();
         self.audit_trees = new_trees.collect::<HashMap<_, _>>();
-        removed_effects.into_iter().flatten().collect::<Vec<_>>()
+        let removed_effects = removed_effects.into_iter().flatten().collect::<Vec<_>>();
+        for e in &removed_effects {
+            self.comments.remove(e);
+            self.decision_info.remove(e);
+        }
+        removed_effects
     }
 
     fn scan_with_sinks(
@@ -466,18 +1620,69 @@ impl AuditFile {
         sinks: HashSet<CanonicalPath>,
         relevant_effects: &[EffectType],
         quick: bool,
+        config: &DefaultAuditConfig,
     ) -> Result<(AuditFile, ScanResults)> {
         let mut audit_file =
             AuditFile::empty(crate_path.to_path_buf(), relevant_effects.to_vec())?;
-        let ident_sinks =
-            sinks.iter().map(|x| x.clone().to_path()).collect::<HashSet<_>>();
-        let scan_res = scanner::scan_crate_with_sinks(
-            crate_path,
-            ident_sinks,
-            relevant_effects,
-            quick,
-        )?;
-        audit_file.set_base_audit_trees(scan_res.effects_set());
+        let mut scan_config = ScanConfig::builder()
+            .effect_types(relevant_effects)
+            .sinks(sinks)
+            .resolver(if quick {
+                ResolverKind::Quick
+            } else {
+                ResolverKind::RustAnalyzer
+            })
+            .expand_macros(config.expand_macros)
+            .include_build_script(config.include_build_script)
+            .targets(config.targets);
+        for features in &config.feature_sets {
+            scan_config = scan_config.feature_set(features.clone());
+        }
+        if let Some(target) = &config.target {
+            scan_config = scan_config.target(target);
+        }
+        let mut scan_res = scan_config.build().scan(crate_path)?;
+        if !config.provenance.is_src() {
+            for e in scan_res.effects.iter_mut() {
+                e.set_provenance(config.provenance);
+            }
+        }
+
+        if config.include_dead_code {
+            audit_file.set_base_audit_trees(scan_res.effects_set());
+        } else {
+            let reachable = scan_res.reachable_fns();
+            let (dead, live): (Vec<_>, Vec<_>) = scan_res
+                .effects
+                .iter()
+                .partition(|e| scan_res.is_dead_code_effect(e, &reachable));
+            audit_file.set_base_audit_trees(live);
+            audit_file.dead_code_effects = dead.into_iter().cloned().collect();
+        }
+        audit_file.set_effect_signatures(&scan_res, &config.dep_signatures);
+        audit_file.set_pub_fn_calls(&scan_res, &config.dep_signatures);
+        audit_file.example_paths = audit_file
+            .audit_trees
+            .keys()
+            .filter_map(|e| Some((e.clone(), scan_res.example_call_path(e)?)))
+            .collect();
+        for (f, hash) in &scan_res.fn_hashes {
+            audit_file
+                .fn_hashes
+                .entry(f.to_string())
+                .and_modify(|h| {
+                    if h != hash {
+                        h.clear()
+                    }
+                })
+                .or_insert_with(|| hash.clone());
+        }
+        audit_file.native_libs = scan_res.native_libs.clone();
+        audit_file.ffi_libs = audit_file
+            .audit_trees
+            .keys()
+            .filter_map(|e| Some((e.clone(), scan_res.ffi_library(e)?.to_string())))
+            .collect();
 
         Ok((audit_file, scan_res))
     }
@@ -492,6 +1697,7 @@ impl AuditFile {
             HashSet::new(),
             relevant_effects,
             quick_mode,
+            &DefaultAuditConfig::default(),
         )
     }
 
@@ -505,6 +1711,7 @@ impl AuditFile {
             HashSet::new(),
             relevant_effects,
             quick,
+            &DefaultAuditConfig::default(),
         )
     }
 
@@ -513,12 +1720,14 @@ impl AuditFile {
         sinks: HashSet<CanonicalPath>,
         relevant_effects: &[EffectType],
         quick: bool,
+        config: &DefaultAuditConfig,
     ) -> Result<AuditFile> {
         Self::new_caller_checked_default_with_sinks_and_results(
             crate_path,
             sinks,
             relevant_effects,
             quick,
+            config,
         )
         .map(|x| x.0)
     }
@@ -528,28 +1737,50 @@ impl AuditFile {
         sinks: HashSet<CanonicalPath>,
         relevant_effects: &[EffectType],
         quick: bool,
+        config: &DefaultAuditConfig,
     ) -> Result<(AuditFile, ScanResults)> {
         let (mut audit_file, scan_res) =
-            Self::scan_with_sinks(crate_path, sinks, relevant_effects, quick)?;
+            Self::scan_with_sinks(crate_path, sinks, relevant_effects, quick, config)?;
+
+        // Fast path: nothing in a crate which forbids unsafe code and calls no
+        // sinks needs to be caller-checked, so its policy is minimal
+        if scan_res.is_forbid_unsafe_without_sinks() {
+            info!("Crate forbids unsafe code and has no sinks, marking all effects safe");
+            audit_file.mark_all_safe();
+            return Ok((audit_file, scan_res));
+        }
 
         let mut total_size = 0i32;
-        let mut pub_caller_checked = HashMap::new();
         for (e, t) in audit_file.audit_trees.iter_mut() {
             let mut tree_size = 0;
-            AuditFile::mark_caller_checked(
-                e,
-                t,
-                &mut pub_caller_checked,
-                &scan_res,
-                &mut tree_size,
-            )?;
+            AuditFile::mark_caller_checked(e, t, &scan_res, config, &mut tree_size)?;
             total_size += tree_size;
             // TODO: Make this configurable/obsolete
             if total_size > MAX_AUDIT_FILE_SIZE {
-                return Err(anyhow!("total size of audit file is too big"));
+                return Err(anyhow!("total size of audit file is too big").into());
             }
         }
 
+        // With propagation limits, only the public functions marked in the
+        // (truncated) effect trees are caller-checked
+        if config.has_propagation_limits() {
+            audit_file.recalc_pub_caller_checked(&scan_res.pub_fns);
+            return Ok((audit_file, scan_res));
+        }
+
+        // Otherwise every public function which can transitively reach an
+        // effect must be marked caller-checked for it. This is computed over
+        // the strongly connected components of the call graph, so recursive
+        // functions get all the effects of their cycle.
+        let transitive = scan_res.transitive_effects(audit_file.audit_trees.keys());
+        let pub_caller_checked = scan_res
+            .pub_fns
+            .iter()
+            .filter_map(|f| {
+                let effects = transitive.get(f)?;
+                Some((f.clone(), effects.iter().map(|e| (*e).clone()).collect()))
+            })
+            .collect();
         audit_file.pub_caller_checked = pub_caller_checked;
 
         Ok((audit_file, scan_res))
@@ -560,18 +1791,10 @@ impl AuditFile {
         sinks: HashSet<CanonicalPath>,
         relevant_effects: &[EffectType],
         quick: bool,
+        config: &DefaultAuditConfig,
     ) -> Result<AuditFile> {
-        let mut audit_file =
-            AuditFile::empty(crate_path.to_path_buf(), relevant_effects.to_vec())?;
-        let ident_sinks =
-            sinks.iter().map(|x| x.clone().to_path()).collect::<HashSet<_>>();
-        let scan_res = scanner::scan_crate_with_sinks(
-            crate_path,
-            ident_sinks,
-            relevant_effects,
-            quick,
-        )?;
-        audit_file.set_base_audit_trees(scan_res.effects_set());
+        let (audit_file, _scan_res) =
+            Self::scan_with_sinks(crate_path, sinks, relevant_effects, quick, config)?;
 
         Ok(audit_file)
     }
@@ -581,14 +1804,11 @@ impl AuditFile {
         sinks: HashSet<CanonicalPath>,
         relevant_effects: &[EffectType],
         quick: bool,
+        config: &DefaultAuditConfig,
     ) -> Result<AuditFile> {
         let (mut audit_file, _scan_res) =
-            Self::scan_with_sinks(crate_path, sinks, relevant_effects, quick)?;
-        for (_, mut t) in audit_file.audit_trees.iter_mut() {
-            if let EffectTree::Leaf(_, a) = &mut t {
-                *a = SafetyAnnotation::Safe;
-            }
-        }
+            Self::scan_with_sinks(crate_path, sinks, relevant_effects, quick, config)?;
+        audit_file.mark_all_safe();
 
         Ok(audit_file)
     }
@@ -599,6 +1819,7 @@ impl AuditFile {
         audit_type: DefaultAuditType,
         relevant_effects: &[EffectType],
         quick: bool,
+        config: &DefaultAuditConfig,
     ) -> Result<AuditFile> {
         match audit_type {
             DefaultAuditType::CallerChecked => {
@@ -607,6 +1828,7 @@ impl AuditFile {
                     sinks,
                     relevant_effects,
                     quick,
+                    config,
                 )
             }
             DefaultAuditType::Empty => Self::new_empty_default_with_sinks(
@@ -614,16 +1836,78 @@ impl AuditFile {
                 sinks,
                 relevant_effects,
                 quick,
+                config,
             ),
             DefaultAuditType::Safe => Self::new_safe_default_with_sinks(
                 crate_path,
                 sinks,
                 relevant_effects,
                 quick,
+                config,
             ),
         }
     }
 
+    /// Carries the audit decisions of an earlier audit of the crate (e.g. of
+    /// its previous version) forward to this one, for the effects in
+    /// functions which haven't changed since. The effects in new or changed
+    /// functions are left for review. Returns the number of effects whose
+    /// annotations were carried forward.
+    pub fn carry_forward(&mut self, previous: &AuditFile) -> usize {
+        if previous.third_party_review.is_some() {
+            return 0;
+        }
+        let fn_hashes = &self.fn_hashes;
+        let unchanged = |f: &CanonicalPath| match (
+            fn_hashes.get(f.as_str()),
+            previous.fn_hashes.get(f.as_str()),
+        ) {
+            (Some(h), Some(prev_h)) => !h.is_empty() && h == prev_h,
+            _ => false,
+        };
+
+        // Match up the effects which are the same apart from their location,
+        // in the order they appear
+        let by_loc = |e: &&EffectInstance| {
+            let loc = e.call_loc();
+            (loc.file().clone(), loc.start_line(), loc.start_col())
+        };
+        let mut previous_trees: HashMap<String, VecDeque<&EffectInstance>> =
+            HashMap::new();
+        for e in previous.audit_trees.keys().sorted_by_key(by_loc) {
+            previous_trees.entry(effect_hash(e)).or_default().push_back(e);
+        }
+
+        let mut carried = 0;
+        let effects =
+            self.audit_trees.keys().sorted_by_key(by_loc).cloned().collect_vec();
+        for e in effects {
+            let Some(prev_e) =
+                previous_trees.get_mut(&effect_hash(&e)).and_then(|es| es.pop_front())
+            else {
+                continue;
+            };
+            let tree = self.audit_trees.get_mut(&e).unwrap();
+            if tree.carry_forward(&previous.audit_trees[prev_e], &unchanged) {
+                carried += 1;
+                if let Some(comment) = previous.comments.get(prev_e) {
+                    self.comments.insert(e, comment.clone());
+                }
+            }
+        }
+
+        if carried > 0 {
+            self.refresh_pub_caller_checked();
+        }
+        carried
+    }
+
+    /// The crate's risk score (see [`crate::risk`]). Effects in dead code
+    /// only count for a fraction of their weight.
+    pub fn risk_score(&self) -> f64 {
+        effects_risk_score(self.audit_trees.keys(), &self.dead_code_effects)
+    }
+
     /// Gets the difference between the public functions marked caller-checked
     /// in `p1` and `p2`
     pub fn pub_diff(p1: &AuditFile, p2: &AuditFile) -> HashSet<CanonicalPath> {
@@ -636,3 +1920,51 @@ impl AuditFile {
             .collect::<HashSet<CanonicalPath>>()
     }
 }
+
+fn rebase_dir(dir: &FilePath, base_dir: &FilePath) -> PathBuf {
+    if dir.as_os_str().is_empty() || dir.is_absolute() {
+        // Default locations and ones outside the crate are left as they are
+        dir.to_path_buf()
+    } else if dir == FilePath::new(".") {
+        base_dir.to_path_buf()
+    } else {
+        base_dir.join(dir)
+    }
+}
+
+const SRC_LOC_FIELDS: [&str; 8] = [
+    "dir",
+    "file",
+    "start_line",
+    "start_col",
+    "end_line",
+    "end_col",
+    "start_byte",
+    "end_byte",
+];
+
+/// Rewrites the directory of every serialized `SrcLoc` in the JSON value
+/// with `f`. Source locations are nested throughout the audit file (e.g. in
+/// every CanonicalPath), so this is simpler than relocating each field.
+fn relocate_src_locs<F>(json: &mut serde_json::Value, f: &F)
+where
+    F: Fn(&FilePath) -> PathBuf,
+{
+    match json {
+        serde_json::Value::Object(obj) => {
+            // Older audit files don't have the byte offsets
+            let is_src_loc = SRC_LOC_FIELDS[..6].iter().all(|k| obj.contains_key(*k))
+                && obj.keys().all(|k| SRC_LOC_FIELDS.contains(&k.as_str()));
+            match obj.get_mut("dir") {
+                Some(serde_json::Value::String(dir)) if is_src_loc => {
+                    *dir = f(FilePath::new(dir)).to_string_lossy().to_string();
+                }
+                _ => obj.values_mut().for_each(|v| relocate_src_locs(v, f)),
+            }
+        }
+        serde_json::Value::Array(arr) => {
+            arr.iter_mut().for_each(|v| relocate_src_locs(v, f));
+        }
+        _ => (),
+    }
+}
@@ -1,9 +1,15 @@
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
+use std::time::Instant;
 
 use crate::audit_chain::AuditChain;
-use crate::audit_file::{EffectInfo, EffectTree};
+use crate::audit_file::{
+    forget_undone, format_time, mark_matching_trees, push_decision, AuditDecision,
+    DecisionInfo, EffectInfo, EffectTree,
+};
+use crate::auditing::editor::open_in_editor;
 use crate::auditing::info::*;
-use crate::effect::{Effect, EffectInstance};
+use crate::effect::{signature_to_string, Effect, EffectInstance};
+use crate::filter::PathGlob;
 use crate::ident::CanonicalPath;
 use crate::scanner::scan_crate;
 use crate::sink::Sink;
@@ -21,6 +27,12 @@ pub enum AuditStatus {
     AuditChildEffect,
     AuditParentEffect,
     ExpandContext,
+    /// Open the effect in the auditor's editor, then ask again
+    OpenEditor,
+    /// Undo the last decision
+    Undo,
+    /// Make the same decision for all the locations matching a pattern
+    MarkMatching,
 }
 
 // Returns Some SafetyAnnotation if the user selects one, None if the user
@@ -32,12 +44,12 @@ fn get_user_annotation(
     loop {
         if let Ok(a) = Text::new(&format!(
             r#"Select how to mark this effect:
-  (s)afe, (u)nsafe, (c)aller checked,{} (e)xpand context, ask me (l)ater, e(x)it tool
+  (s)afe, (u)nsafe, (c)aller checked,{} (e)xpand context, (v)iew in $EDITOR, ask me (l)ater, (m)ark all matching a pattern, (z) undo last decision, e(x)it tool
 "#,
             if allow_effect_origin { " audit effect (o)rigin," } else { "" }
         ))
         .with_validator(move |x: &str| match x {
-            "s" | "u" | "c" | "e" | "l" | "x" => Ok(Validation::Valid),
+            "s" | "u" | "c" | "e" | "v" | "l" | "m" | "z" | "x" => Ok(Validation::Valid),
             "o" if allow_effect_origin => Ok(Validation::Valid),
             _ => Ok(Validation::Invalid("Invalid input".into())),
         })
@@ -55,11 +67,90 @@ fn get_user_annotation(
         "l" => Ok((Some(SafetyAnnotation::Skipped), AuditStatus::ContinueAudit)),
         "o" => Ok((None, AuditStatus::AuditChildEffect)),
         "e" => Ok((None, AuditStatus::ExpandContext)),
+        "v" => Ok((None, AuditStatus::OpenEditor)),
+        "m" => Ok((None, AuditStatus::MarkMatching)),
+        "z" => Ok((None, AuditStatus::Undo)),
         "x" => Ok((None, AuditStatus::EarlyExit)),
         _ => Err(anyhow!("Invalid annotation selection")),
     }
 }
 
+/// Asks the auditor for an optional comment on their decision for an effect,
+/// e.g. why it is safe or what its callers have to check. Returns None if the
+/// comment is left empty.
+fn get_user_comment(current: Option<&String>) -> Option<String> {
+    let mut prompt = Text::new("Comment on this decision (optional):");
+    if let Some(current) = current {
+        prompt = prompt.with_initial_value(current);
+    }
+    match prompt.prompt() {
+        Ok(c) if !c.trim().is_empty() => Some(c.trim().to_string()),
+        Ok(_) => None,
+        Err(_) => {
+            println!("Error accepting user input. Keeping the previous comment...");
+            current.cloned()
+        }
+    }
+}
+
+/// Asks the auditor for a pattern, by default the crate of the effect's
+/// callee, and a decision for all the unaudited locations matching it.
+/// Returns None if the pattern is left empty.
+fn get_user_mark_matching(
+    effect: &EffectInstance,
+) -> Option<(PathGlob, SafetyAnnotation)> {
+    let callee = effect.callee_path();
+    let default = format!("{}::**", callee.split("::").next().unwrap_or(callee));
+    let pattern = Text::new(
+        "Mark all the unaudited locations whose callee, caller or module matches:",
+    )
+    .with_default(&default)
+    .with_validator(|x: &str| match PathGlob::new(x) {
+        Ok(_) => Ok(Validation::Valid),
+        Err(e) => Ok(Validation::Invalid(e.to_string().into())),
+    })
+    .prompt()
+    .ok()?;
+    let pattern = PathGlob::new(pattern.trim()).ok()?;
+    let annotation = Text::new("Mark them (s)afe, (u)nsafe or (c)aller checked:")
+        .with_validator(|x: &str| match x {
+            "s" | "u" | "c" => Ok(Validation::Valid),
+            _ => Ok(Validation::Invalid("Invalid input".into())),
+        })
+        .prompt()
+        .ok()?;
+    let annotation = match annotation.as_str() {
+        "s" => SafetyAnnotation::Safe,
+        "u" => SafetyAnnotation::Unsafe,
+        _ => SafetyAnnotation::CallerChecked,
+    };
+    Some((pattern, annotation))
+}
+
+/// Prompts for a comment on the effect once the auditor has decided on its
+/// tree, keeping `comments` up to date
+fn update_comment(
+    e: &EffectInstance,
+    t: &EffectTree,
+    comments: &mut HashMap<EffectInstance, String>,
+) {
+    if t.summary_annotation() == SafetyAnnotation::Skipped {
+        return;
+    }
+    match get_user_comment(comments.get(e)) {
+        Some(c) => comments.insert(e.clone(), c),
+        None => comments.remove(e),
+    };
+}
+
+/// Opens the location of the effect in the auditor's editor, so they can see
+/// more of the code before deciding
+fn open_effect_in_editor(effect: &EffectInfo) {
+    if let Err(e) = open_in_editor(&effect.callee_loc) {
+        println!("Couldn't open the effect in an editor: {}", e);
+    }
+}
+
 fn print_and_update_audit<'a>(
     orig_effect: &'a EffectInstance,
     effect_tree: &mut EffectTree,
@@ -88,7 +179,7 @@ fn print_and_update_audit<'a>(
         scan_res,
         effect_tree,
         effect_history,
-        curr_effect,
+        curr_effect.clone(),
         config,
     ) {
         Ok(AuditStatus::ExpandContext) => {
@@ -102,6 +193,16 @@ fn print_and_update_audit<'a>(
                 &config,
             )
         }
+        Ok(AuditStatus::OpenEditor) => {
+            open_effect_in_editor(&curr_effect);
+            print_and_update_audit(
+                orig_effect,
+                effect_tree,
+                effect_history,
+                scan_res,
+                config,
+            )
+        }
         res => res,
     }
 }
@@ -147,7 +248,7 @@ fn update_audit_child<'a>(
         scan_res,
         effect_tree,
         effect_history,
-        curr_effect,
+        curr_effect.clone(),
         config,
     ) {
         Ok(AuditStatus::ExpandContext) => {
@@ -161,6 +262,10 @@ fn update_audit_child<'a>(
                 &config,
             )
         }
+        Ok(AuditStatus::OpenEditor) => {
+            open_effect_in_editor(&curr_effect);
+            update_audit_child(orig_effect, effect_tree, effect_history, scan_res, config)
+        }
         res => res,
     }
 }
@@ -190,8 +295,10 @@ fn audit_branch<'a>(
                         scan_res,
                         config,
                     )? {
-                        AuditStatus::EarlyExit => {
-                            return Ok(AuditStatus::EarlyExit);
+                        s @ (AuditStatus::EarlyExit
+                        | AuditStatus::Undo
+                        | AuditStatus::MarkMatching) => {
+                            return Ok(s);
                         }
                         AuditStatus::AuditChildEffect => {
                             audit_child = true;
@@ -208,8 +315,10 @@ fn audit_branch<'a>(
                         scan_res,
                         config,
                     )? {
-                        AuditStatus::EarlyExit => {
-                            return Ok(AuditStatus::EarlyExit);
+                        s @ (AuditStatus::EarlyExit
+                        | AuditStatus::Undo
+                        | AuditStatus::MarkMatching) => {
+                            return Ok(s);
                         }
                         AuditStatus::AuditChildEffect => {
                             audit_child = true;
@@ -250,6 +359,63 @@ fn audit_effect_tree(
     }
 }
 
+fn print_sink_signature(
+    e: &EffectInstance,
+    sink_signatures: &HashMap<EffectInstance, String>,
+) {
+    if let (Some(sink), Some(sig)) = (e.pattern(), sink_signatures.get(e)) {
+        println!("Known effects of {}: {}", sink, sig);
+    }
+}
+
+fn print_ffi_library(e: &EffectInstance, ffi_libs: &HashMap<EffectInstance, String>) {
+    if let Some(lib) = ffi_libs.get(e) {
+        println!("FFI call into native library: {}", lib);
+    }
+}
+
+fn print_comment(e: &EffectInstance, comments: &HashMap<EffectInstance, String>) {
+    if let Some(comment) = comments.get(e) {
+        println!("Auditor comment: {}", comment);
+    }
+}
+
+/// How urgently an effect should be audited: effects with a higher rank are
+/// audited first (see `AuditOrder`)
+pub(crate) type AuditRank = (bool, bool, bool, usize);
+
+/// Returns a function ranking the effects for the audit queue, as given by
+/// `config.order`
+pub(crate) fn audit_ranker<'a>(
+    scan_res: &'a ScanResults,
+    config: &'a Config,
+) -> impl Fn(&EffectInstance) -> AuditRank + 'a {
+    let reachable = match config.order {
+        AuditOrder::File => HashSet::new(),
+        AuditOrder::Reachable | AuditOrder::Priority => scan_res.reachable_fns(),
+    };
+    let (root_reachable, centrality) = match config.order {
+        AuditOrder::Priority => (
+            config.root_reachable_fns.as_ref().map(|fns| scan_res.reachable_from(fns)),
+            scan_res.caller_centrality(),
+        ),
+        AuditOrder::File | AuditOrder::Reachable => (None, HashMap::new()),
+    };
+
+    move |e| match config.order {
+        AuditOrder::File => Default::default(),
+        AuditOrder::Reachable => {
+            (false, !scan_res.is_dead_code_effect(e, &reachable), false, 0)
+        }
+        AuditOrder::Priority => (
+            root_reachable.as_ref().is_some_and(|fns| fns.contains(e.caller())),
+            !scan_res.is_dead_code_effect(e, &reachable),
+            scan_res.pub_fns.contains(e.caller()),
+            centrality.get(e.caller()).copied().unwrap_or_default(),
+        ),
+    }
+}
+
 // TODO: When we exit early, we have no way of knowing which effects the user
 //       has already gone through in this audit and marked "skipped" and so we
 //       will re-prompt the user once we resume auditing the audit file. We would
@@ -279,70 +445,201 @@ pub fn start_audit(
         println!("WARNING: package has been marked as unsafe");
     }
 
+    // Look up the known effects of any dependency functions called by the
+    // effects before we borrow the audit trees mutably
+    let sink_signatures = audit_file
+        .audit_trees
+        .keys()
+        .filter_map(|e| {
+            let sig = audit_file.sink_signature(e)?;
+            Some((e.clone(), signature_to_string(sig)))
+        })
+        .collect::<HashMap<_, _>>();
+    let example_paths = audit_file.example_paths.clone();
+    let ffi_libs = audit_file.ffi_libs.clone();
+    let comments = &mut audit_file.comments;
+    let history = &mut audit_file.decision_history;
+    let decision_info = &mut audit_file.decision_info;
+    let mut session = audit_file.session.take().unwrap_or_default();
+
+    let rank = audit_ranker(&scan_res, config);
+
     // Sort the base audit locs before presenting them to the user so they don't
     // have to jump between files as much
+    let base_dir = audit_file.base_dir.clone();
     let mut audit_locs: Vec<(&EffectInstance, &mut EffectTree)> =
         audit_file.audit_trees.iter_mut().collect();
+    let total_locs = audit_locs.len();
+    audit_locs.retain(|(e, _)| config.filter.matches(e, &base_dir));
+    if audit_locs.len() < total_locs {
+        println!(
+            "Only auditing {} of {} effects, as given by the filters",
+            audit_locs.len(),
+            total_locs
+        );
+    }
     audit_locs.sort_by(|(a, _), (b, _)| {
         let a_loc = a.call_loc();
         let b_loc = b.call_loc();
         let a_path = a_loc.filepath_string();
         let b_path = b_loc.filepath_string();
 
-        a_path
-            .cmp(&b_path)
+        rank(b)
+            .cmp(&rank(a))
+            .then_with(|| a_path.cmp(&b_path))
             .then_with(|| a_loc.start_line().cmp(&b_loc.start_line()))
             .then_with(|| a_loc.start_col().cmp(&b_loc.start_col()))
     });
 
-    // Iterate through the effects and prompt the user for if they're safe
-    for (e, t) in audit_locs {
-        match t.get_leaf_annotation() {
-            Some(SafetyAnnotation::Skipped) => {
-                match audit_effect_tree(e, t, &scan_res, config)? {
-                    AuditStatus::EarlyExit => {
-                        break;
-                    }
-                    AuditStatus::AuditChildEffect => {
-                        dependency_audit_effect = Some(e.clone());
-                        break;
-                    }
-                    AuditStatus::AuditParentEffect => {
-                        return Err(anyhow!("We should never return this status here"));
+    // Pick up where the last audit left off, if it was left unfinished
+    let resume_idx = session
+        .current
+        .as_ref()
+        .and_then(|current| audit_locs.iter().position(|(e, _)| *e == current));
+    if let Some(idx) = resume_idx.filter(|idx| *idx > 0) {
+        println!(
+            "Resuming the audit started at {} ({} effects shown so far)",
+            format_time(session.started_at),
+            session.shown.len()
+        );
+        audit_locs.rotate_left(idx);
+    }
+
+    // Iterate through the effects and prompt the user for if they're safe.
+    // Undoing a decision goes back to its effect, so we iterate by index.
+    let mut i = 0;
+    let mut finished = true;
+    while i < audit_locs.len() {
+        let e = audit_locs[i].0;
+        let t = &mut *audit_locs[i].1;
+        if !matches!(t.get_leaf_annotation(), None | Some(SafetyAnnotation::Skipped)) {
+            i += 1;
+            continue;
+        }
+        print_sink_signature(e, &sink_signatures);
+        print_ffi_library(e, &ffi_libs);
+        print_comment(e, comments);
+        if let Some(path) = example_paths.get(e) {
+            print_example_call_path(path);
+        }
+        print_caller_tree(e.caller(), &scan_res.caller_tree(e.caller()));
+
+        let previous = t.clone();
+        session.show(e);
+        let start = Instant::now();
+        let status = audit_effect_tree(e, t, &scan_res, config)?;
+        session.spend(e, start.elapsed());
+        match status {
+            AuditStatus::EarlyExit => {
+                finished = false;
+                break;
+            }
+            AuditStatus::AuditChildEffect => {
+                dependency_audit_effect = Some(e.clone());
+                finished = false;
+                break;
+            }
+            AuditStatus::AuditParentEffect => {
+                return Err(anyhow!("We should never return this status here"));
+            }
+            AuditStatus::MarkMatching => {
+                if let Some((pattern, annotation)) = get_user_mark_matching(e) {
+                    let trees = audit_locs.iter_mut().map(|(e, t)| (*e, &mut **t));
+                    let (marked, decision) = mark_matching_trees(
+                        trees,
+                        &pattern,
+                        annotation,
+                        Some(&scan_res),
+                    )?;
+                    println!("Marked {} locations as {}", marked, annotation);
+                    if let Some(decision) = decision {
+                        push_decision(history, decision_info, decision);
+                        session.charge(e, decision_info);
                     }
-                    _ => (),
                 }
+                // Continue with this effect, if it didn't match
+                continue;
             }
-
-            Some(_) => (),
-
-            None => match audit_effect_tree(e, t, &scan_res, config)? {
-                AuditStatus::EarlyExit => {
-                    break;
-                }
-                AuditStatus::AuditChildEffect => {
-                    dependency_audit_effect = Some(e.clone());
-                    break;
+            AuditStatus::Undo => {
+                if *t != previous {
+                    // Undo the decisions made on this effect so far
+                    *t = previous;
+                    println!("Undid the decisions on this effect");
+                } else if let Some(idx) =
+                    undo_last_decision(history, decision_info, &mut audit_locs)
+                {
+                    i = idx;
                 }
-                AuditStatus::AuditParentEffect => {
-                    return Err(anyhow!("We should never return this status here"));
+                continue;
+            }
+            _ => {
+                update_comment(e, t, comments);
+                if *t != previous {
+                    push_decision(
+                        history,
+                        decision_info,
+                        AuditDecision::new(
+                            e.clone(),
+                            t.effect_info().clone(),
+                            t.summary_annotation(),
+                            previous,
+                        ),
+                    );
+                    session.charge(e, decision_info);
                 }
-                _ => (),
-            },
+            }
         }
+        i += 1;
     }
 
-    println!("No more effects to audit");
+    if finished {
+        println!("No more effects to audit");
+        session.current = None;
+    }
 
     // NOTE: We recalculate the public functions here so we don't have to keep
     //       track of them during the audit. This is a bit slower, but simplifies
     //       the code dramatically.
     audit_file.recalc_pub_caller_checked(&scan_res.pub_fns);
 
+    // Keep the session around to resume it, unless there's nothing left to do
+    if audit_file.unaudited_effects().0 > 0 {
+        audit_file.session = Some(session);
+    }
+
     Ok(dependency_audit_effect)
 }
 
-fn update_audit_annotation(
+/// Undoes the last decision in the history by restoring the trees of the
+/// effects it changed, and returns the index of the first of them to audit
+/// them again
+fn undo_last_decision(
+    history: &mut Vec<AuditDecision>,
+    decision_info: &mut HashMap<EffectInstance, DecisionInfo>,
+    audit_locs: &mut [(&EffectInstance, &mut EffectTree)],
+) -> Option<usize> {
+    let Some(decision) = history.pop() else {
+        println!("No decisions to undo");
+        return None;
+    };
+    let changed = std::iter::once((&decision.effect, &decision.previous))
+        .chain(decision.others.iter().map(|(e, t)| (e, t)))
+        .map(|(e, t)| Some((audit_locs.iter().position(|(l, _)| *l == e)?, t)))
+        .collect::<Option<Vec<_>>>();
+    let Some(changed) = changed else {
+        println!("Can't undo the last decision, as its effects aren't all being audited");
+        history.push(decision);
+        return None;
+    };
+    for (idx, previous) in &changed {
+        *audit_locs[*idx].1 = (*previous).clone();
+        forget_undone(decision_info, audit_locs[*idx].0, previous);
+    }
+    println!("Undid marking {} as {}", decision, decision.annotation);
+    changed.iter().map(|(idx, _)| *idx).min()
+}
+
+pub(crate) fn update_audit_annotation(
     annotation: SafetyAnnotation,
     scan_res: &ScanResults,
     effect_tree: &mut EffectTree,
@@ -416,7 +713,10 @@ fn update_audit_from_input(
         )),
         Ok((_, s @ AuditStatus::AuditChildEffect))
         | Ok((_, s @ AuditStatus::EarlyExit))
-        | Ok((_, s @ AuditStatus::ExpandContext)) => Ok(s),
+        | Ok((_, s @ AuditStatus::ExpandContext))
+        | Ok((_, s @ AuditStatus::OpenEditor))
+        | Ok((_, s @ AuditStatus::Undo))
+        | Ok((_, s @ AuditStatus::MarkMatching)) => Ok(s),
         Ok((_, AuditStatus::AuditParentEffect)) => {
             // TODO: This is for the case where we are walking down the effect
             //       stack for auditing child effects and the user decides they
@@ -509,8 +809,11 @@ pub fn audit_pub_fn(
             (AuditStatus::AuditParentEffect, _) => {
                 return Err(anyhow!("Cannot audit parent effect in this context"));
             }
-            (AuditStatus::ExpandContext, _) => {
-                return Err(anyhow!("Shouldn't return ExpandContext when auditing public function effects"));
+            (AuditStatus::ExpandContext, _)
+            | (AuditStatus::OpenEditor, _)
+            | (AuditStatus::Undo, _)
+            | (AuditStatus::MarkMatching, _) => {
+                return Err(anyhow!("Shouldn't return ExpandContext, OpenEditor, Undo or MarkMatching when auditing public function effects"));
             }
         }
     }
@@ -543,10 +846,15 @@ fn audit_pub_fn_effect(
             )
             })?;
 
+        print_comment(base_effect, &audit_file.comments);
+        let previous = effect_tree.clone();
         loop {
             let res = audit_effect_tree(base_effect, effect_tree, scan_res, &config)?;
             match res {
-                AuditStatus::ContinueAudit => break,
+                AuditStatus::ContinueAudit => {
+                    update_comment(base_effect, effect_tree, &mut audit_file.comments);
+                    break;
+                }
                 s @ AuditStatus::EarlyExit => {
                     return Ok((s, None));
                 }
@@ -559,6 +867,18 @@ fn audit_pub_fn_effect(
                 AuditStatus::ExpandContext => {
                     config.expand_context();
                 }
+                // Handled when the effect is printed
+                AuditStatus::OpenEditor => (),
+                AuditStatus::Undo => {
+                    // Only the decisions on this effect can be undone here
+                    *effect_tree = previous.clone();
+                    println!("Undid the decisions on this effect");
+                }
+                AuditStatus::MarkMatching => {
+                    println!(
+                        "Can't mark locations by pattern when auditing public functions"
+                    );
+                }
             }
         }
     }
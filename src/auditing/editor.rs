@@ -0,0 +1,90 @@
+//! Opening an effect's location in the auditor's editor, for context beyond
+//! the snippet shown during the audit.
+
+use std::env;
+use std::path::Path;
+use std::process::Command;
+
+use anyhow::{anyhow, Context, Result};
+
+use crate::effect::SrcLoc;
+
+/// The program and arguments opening the file at the line and column (both
+/// 1-based) with `editor`, the value of `$VISUAL` or `$EDITOR`, e.g.
+/// `code --wait` or `vim`. Editors are told the position the way they expect
+/// it: `-g file:line:col` for VS Code and its forks, `file:line:col` for e.g.
+/// Helix and Sublime Text, and `+line file` for the others, as for vim, emacs
+/// and nano.
+fn editor_command(
+    editor: &str,
+    file: &Path,
+    line: usize,
+    col: usize,
+) -> Result<(String, Vec<String>)> {
+    let mut words = editor.split_whitespace().map(String::from);
+    let program = words.next().ok_or_else(|| anyhow!("The editor command is empty"))?;
+    let mut args = words.collect::<Vec<_>>();
+    let file = file.display();
+    let name = Path::new(&program)
+        .file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    match name.as_str() {
+        "code" | "code-insiders" | "codium" | "cursor" => {
+            args.push("-g".to_string());
+            args.push(format!("{}:{}:{}", file, line, col));
+        }
+        "hx" | "helix" | "subl" | "zed" => {
+            args.push(format!("{}:{}:{}", file, line, col))
+        }
+        _ => {
+            args.push(format!("+{}", line));
+            args.push(file.to_string());
+        }
+    }
+    Ok((program, args))
+}
+
+/// Opens the start of the location in `$VISUAL` or `$EDITOR`, and waits for
+/// terminal editors to exit
+pub fn open_in_editor(loc: &SrcLoc) -> Result<()> {
+    let editor = env::var("VISUAL")
+        .or_else(|_| env::var("EDITOR"))
+        .map_err(|_| anyhow!("Set $EDITOR to open effects in an editor"))?;
+    let file = loc.dir().join(loc.file());
+    // syn's columns are 0-based
+    let (program, args) =
+        editor_command(&editor, &file, loc.start_line(), loc.start_col() + 1)?;
+    let status = Command::new(&program)
+        .args(&args)
+        .status()
+        .with_context(|| format!("Failed to run {}", program))?;
+    if !status.success() {
+        return Err(anyhow!("{} exited with {}", program, status));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_editor_command() {
+        let file = Path::new("src/lib.rs");
+        let command = |editor| editor_command(editor, file, 12, 5).unwrap();
+        assert_eq!(
+            command("vim"),
+            ("vim".into(), vec!["+12".into(), "src/lib.rs".into()])
+        );
+        assert_eq!(
+            command("/usr/bin/code --wait"),
+            (
+                "/usr/bin/code".into(),
+                vec!["--wait".into(), "-g".into(), "src/lib.rs:12:5".into()]
+            )
+        );
+        assert_eq!(command("hx"), ("hx".into(), vec!["src/lib.rs:12:5".into()]));
+        assert!(editor_command(" ", file, 1, 1).is_err());
+    }
+}
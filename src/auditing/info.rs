@@ -1,14 +1,17 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
 use std::path::PathBuf;
 
 use anyhow::{anyhow, Result};
-use clap::Parser;
+use clap::{Parser, ValueEnum};
 use codespan_reporting::diagnostic::{Diagnostic, Label};
 use codespan_reporting::files::SimpleFiles;
 use codespan_reporting::term;
 use codespan_reporting::term::termcolor::{ColorChoice, StandardStream};
 
+use crate::filter::EffectFilter;
 use crate::ident::CanonicalPath;
+use crate::scanner::CallerTree;
 use crate::{
     audit_file::EffectInfo,
     effect::{Effect, EffectInstance, SrcLoc},
@@ -27,6 +30,45 @@ pub struct Config {
     //       can now that chains are our primary auditing mechanism?)
     #[clap(default_value_t = false)]
     pub allow_effect_origin: bool,
+
+    #[clap(long, default_value_t = AuditOrder::default())]
+    /// The order to audit the effects in
+    pub order: AuditOrder,
+
+    /// Only prompt for the effects which pass the filter
+    #[clap(skip)]
+    pub filter: EffectFilter,
+
+    /// When auditing a dependency in a chain, the public functions of the
+    /// crate which the root crate may transitively call
+    #[clap(skip)]
+    pub root_reachable_fns: Option<HashSet<CanonicalPath>>,
+}
+
+/// The order to audit effects in. Effects which rank the same are audited in
+/// file order.
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum AuditOrder {
+    /// By file and line
+    File,
+    /// Effects reachable from the crate's public API first
+    #[default]
+    Reachable,
+    /// Effects reachable from the root crate (when auditing a dependency in a
+    /// chain), then those reachable from the public API, then those in public
+    /// functions, then those in the most widely called functions first
+    Priority,
+}
+
+impl fmt::Display for AuditOrder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            AuditOrder::File => "file",
+            AuditOrder::Reachable => "reachable",
+            AuditOrder::Priority => "priority",
+        };
+        write!(f, "{}", s)
+    }
 }
 
 impl Default for Config {
@@ -35,6 +77,9 @@ impl Default for Config {
             lines_before_effect: 4,
             lines_after_effect: 1,
             allow_effect_origin: false,
+            order: AuditOrder::default(),
+            filter: EffectFilter::default(),
+            root_reachable_fns: None,
         }
     }
 }
@@ -45,6 +90,9 @@ impl Config {
             lines_before_effect: lines_before,
             lines_after_effect: lines_after,
             allow_effect_origin,
+            order: AuditOrder::default(),
+            filter: EffectFilter::default(),
+            root_reachable_fns: None,
         }
     }
 
@@ -52,6 +100,14 @@ impl Config {
         self.lines_before_effect += 5;
         self.lines_after_effect += 5;
     }
+
+    pub fn lines_before(&self) -> usize {
+        self.lines_before_effect as usize
+    }
+
+    pub fn lines_after(&self) -> usize {
+        self.lines_after_effect as usize
+    }
 }
 
 pub fn print_effect_src(
@@ -138,11 +194,19 @@ pub fn print_effect_src(
         // We are in the original function, so print all the effects in the
         // EffectInstance
         match effect_origin.eff_type() {
-            Effect::SinkCall(sink) => format!("sink call: {}", sink),
+            Effect::SinkCall(sink) => format!(
+                "sink call: {} ({}, {} severity)",
+                sink,
+                sink.category(),
+                sink.severity()
+            ),
             Effect::FFICall(call) => format!("ffi call: {}", call),
             Effect::UnsafeCall(call) => format!("unsafe call: {}", call),
-            Effect::RawPointer(ptr) => format!("raw pointer access: {}", ptr),
-            Effect::UnionField(union) => format!("union access: {}", union),
+            Effect::RawPointer(ptr) => format!("raw pointer read: {}", ptr),
+            Effect::RawPointerWrite(ptr) => format!("raw pointer write: {}", ptr),
+            Effect::PtrOffset(call) => format!("pointer arithmetic: {}", call),
+            Effect::UnionFieldRead(union) => format!("union read: {}", union),
+            Effect::UnionFieldWrite(union) => format!("union write: {}", union),
             Effect::StaticMut(var) => format!("static mut access: {}", var),
             Effect::StaticExt(var) => format!("static ffi variable access: {}", var),
             Effect::FnPtrCreation =>
@@ -155,6 +219,33 @@ pub fn print_effect_src(
                     .to_string()
             }
             Effect::FFIDecl(decl) => format!("ffi declaration: {}", decl),
+            Effect::IndirectCall(origin) => {
+                format!("indirect call through a pointer to: {}", origin)
+            }
+            Effect::Transmute(call) => format!("transmute: {}", call),
+            Effect::InlineAsm => "inline assembly".to_string(),
+            Effect::RawConstruct(call) => format!(
+                "construction from a raw pointer: {} (verify the pointer's ownership and lifetime)",
+                call
+            ),
+            Effect::UninitMem(call) => format!(
+                "uninitialized memory: {} (verify the memory is initialized and valid for its type)",
+                call
+            ),
+            Effect::DynLoad(call, Some(lib)) => {
+                format!("dynamic library loading: {} (loads {})", call, lib)
+            }
+            Effect::DynLoad(call, None) => format!("dynamic library loading: {}", call),
+            Effect::FFIExport(Some(symbol)) => format!("ffi export: {}", symbol),
+            Effect::FFIExport(None) => {
+                "ffi export (callable from foreign code through a pointer)".to_string()
+            }
+            Effect::SendSyncImpl(marker) => format!(
+                "unsafe impl {} for {} (verify the type is thread safe)",
+                marker,
+                effect_origin.callee()
+            ),
+            Effect::Custom(name) => format!("{}: {}", name, effect_origin.callee()),
         }
     } else {
         "call safety marked as caller-checked".to_string()
@@ -162,8 +253,23 @@ pub fn print_effect_src(
     let l = labels.remove(0);
     labels.insert(0, l.with_message(label_msg));
 
+    // Effects behind an off-by-default feature or for another platform may
+    // not matter to the crate's users
+    let mut notes = Vec::new();
+    if !effect_origin.cfg_gates().is_empty() {
+        let gates = effect_origin.cfg_gates().join(", ");
+        notes.push(format!("only compiled with cfg({})", gates));
+    }
+    if !effect_origin.platform_gates().is_empty() {
+        let gates = effect_origin.platform_gates().join(", ");
+        notes.push(format!("only compiled on cfg({})", gates));
+    }
+
     // construct the codespan diagnostic
-    let diag = Diagnostic::help().with_code("Audit location").with_labels(labels);
+    let diag = Diagnostic::help()
+        .with_code("Audit location")
+        .with_labels(labels)
+        .with_notes(notes);
 
     let writer = StandardStream::stderr(ColorChoice::Always);
     let codespan_config = codespan_reporting::term::Config {
@@ -261,6 +367,59 @@ fn print_call_stack(
     Ok(())
 }
 
+/// Print an example call path from a public function to an effect (see
+/// `ScanResults::example_call_path`)
+pub fn print_example_call_path(path: &[EffectInfo]) {
+    // TODO: Colorize
+    println!("Example call path from the public API:");
+    for (i, step) in path.iter().enumerate() {
+        println!("{}{} ({})", "  ".repeat(i + 1), step.caller_path, step.callee_loc);
+    }
+}
+
+/// Returns the lines showing the tree of callers of a function (see
+/// `ScanResults::caller_tree`), indented by their depth in the tree
+pub fn caller_tree_lines(trees: &[CallerTree]) -> Vec<String> {
+    fn add_lines(trees: &[CallerTree], depth: usize, lines: &mut Vec<String>) {
+        for t in trees {
+            let note = if t.is_entry {
+                " [entry point]"
+            } else if t.repeated {
+                " [callers shown above]"
+            } else if t.callers.is_empty() {
+                " [no callers]"
+            } else {
+                ""
+            };
+            lines.push(format!(
+                "{}{} ({}){}",
+                "  ".repeat(depth + 1),
+                t.call.caller_path,
+                t.call.callee_loc,
+                note
+            ));
+            add_lines(&t.callers, depth + 1, lines);
+        }
+    }
+    let mut lines = Vec::new();
+    add_lines(trees, 0, &mut lines);
+    lines
+}
+
+/// Print the tree of callers of the function containing an effect, up to the
+/// nearest entry points of the crate
+pub fn print_caller_tree(f: &CanonicalPath, trees: &[CallerTree]) {
+    // TODO: Colorize
+    if trees.is_empty() {
+        println!("{} has no callers in the crate", f);
+        return;
+    }
+    println!("Callers of {}:", f);
+    for line in caller_tree_lines(trees) {
+        println!("{}", line);
+    }
+}
+
 pub fn print_effect_info(
     orig_effect: &EffectInstance,
     curr_effect: &EffectInfo,
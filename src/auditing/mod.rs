@@ -1,5 +1,7 @@
 pub mod audit;
+pub mod editor;
 pub mod info;
 pub mod reset;
 pub mod review;
+pub mod tui;
 pub mod util;
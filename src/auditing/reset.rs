@@ -93,6 +93,8 @@ pub fn reset_annotation(mut audit: AuditFile, audit_path: PathBuf) -> Result<()>
                     }
                 };
                 *tree = EffectTree::Leaf(info, SafetyAnnotation::Skipped);
+                audit.comments.remove(block.0);
+                audit.decision_info.remove(block.0);
             }
         }
     }
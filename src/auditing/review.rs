@@ -4,8 +4,12 @@ use std::path::Path;
 
 use super::info::Config;
 use super::util::is_audit_scan_valid;
-use crate::audit_file::{AuditFile, EffectInfo, EffectTree, SafetyAnnotation};
-use crate::auditing::info::print_effect_info;
+use crate::audit_file::{
+    format_time, AuditFile, EffectInfo, EffectTree, SafetyAnnotation,
+};
+use crate::auditing::info::{
+    print_caller_tree, print_effect_info, print_example_call_path,
+};
 use crate::effect::{EffectInstance, SrcLoc};
 use crate::ident::CanonicalPath;
 use crate::scanner;
@@ -71,8 +75,30 @@ pub fn review_audit(
         return Err(anyhow!("Invalid audit file during review"));
     }
 
+    if let Some(auditor) = &audit_file.auditor {
+        println!("Policy created by {}", auditor);
+    }
+    let auditors = audit_file.auditors();
+    if !auditors.is_empty() {
+        println!("Auditors: {}", auditors.join(", "));
+    }
+
     for (e, a) in audit_file.audit_trees.iter() {
         review_effect_tree_info(e, a, &scan_res.fn_locs, config)?;
+        if let Some(info) = audit_file.decision_info.get(e) {
+            println!(
+                "Decided by {} at {}",
+                info.auditor.as_deref().unwrap_or("unknown auditor"),
+                format_time(info.decided_at)
+            );
+        }
+        if let Some(comment) = audit_file.comments.get(e) {
+            println!("Auditor comment: {}", comment);
+        }
+        if let Some(path) = audit_file.example_paths.get(e) {
+            print_example_call_path(path);
+        }
+        print_caller_tree(e.caller(), &scan_res.caller_tree(e.caller()));
     }
 
     Ok(())
@@ -0,0 +1,445 @@
+//! Interactive terminal UI for auditing a crate.
+//!
+//! The left pane lists every effect location that is still unaudited,
+//! including the callers of functions marked caller-checked, and the right
+//! pane previews the source code around the selected location.
+
+use std::collections::HashMap;
+use std::fs::read_to_string;
+use std::io::{self, Stdout};
+use std::path::PathBuf;
+use std::time::Instant;
+
+use anyhow::{anyhow, Result};
+use crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use crossterm::execute;
+use crossterm::terminal::{
+    disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen,
+};
+use ratatui::backend::CrosstermBackend;
+use ratatui::layout::{Constraint, Direction, Layout};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Borders, List, ListItem, ListState, Paragraph};
+use ratatui::{Frame, Terminal};
+
+use super::audit::{audit_ranker, update_audit_annotation, AuditRank};
+use super::editor::open_in_editor;
+use super::info::{caller_tree_lines, Config};
+use crate::audit_file::{
+    format_time, push_decision, AuditDecision, AuditFile, AuditSession, EffectInfo,
+    EffectTree, SafetyAnnotation,
+};
+use crate::effect::EffectInstance;
+use crate::filter::EffectFilter;
+use crate::scanner::ScanResults;
+
+const HELP: &str =
+    " ↑/k ↓/j: move  s: safe  u: unsafe  c: caller-checked  l: skip  z: undo  v: open in $EDITOR  q: save and quit";
+
+/// An unaudited leaf of one of the audit trees
+struct UnauditedLoc {
+    base_effect: EffectInstance,
+    /// Indices of the branches leading from the root of the tree to the leaf
+    tree_path: Vec<usize>,
+    info: EffectInfo,
+}
+
+impl UnauditedLoc {
+    fn label(&self) -> String {
+        if self.tree_path.is_empty() {
+            format!("{}: {}", self.info.caller_path, self.base_effect.eff_type().to_csv())
+        } else {
+            format!("{}: calls caller-checked fn", self.info.caller_path)
+        }
+    }
+}
+
+fn collect_unaudited(
+    base_effect: &EffectInstance,
+    tree: &EffectTree,
+    tree_path: &mut Vec<usize>,
+    locs: &mut Vec<UnauditedLoc>,
+) {
+    match tree {
+        EffectTree::Leaf(info, SafetyAnnotation::Skipped) => locs.push(UnauditedLoc {
+            base_effect: base_effect.clone(),
+            tree_path: tree_path.clone(),
+            info: info.clone(),
+        }),
+        EffectTree::Leaf(..) => (),
+        EffectTree::Branch(_, children) => {
+            for (i, child) in children.iter().enumerate() {
+                tree_path.push(i);
+                collect_unaudited(base_effect, child, tree_path, locs);
+                tree_path.pop();
+            }
+        }
+    }
+}
+
+/// Returns the unaudited locations of the audit file, sorted by the rank of
+/// their effect, then by file and line
+fn unaudited_locs(
+    audit_file: &AuditFile,
+    filter: &EffectFilter,
+    ranks: &HashMap<EffectInstance, AuditRank>,
+) -> Vec<UnauditedLoc> {
+    let mut locs = Vec::new();
+    let trees = audit_file.audit_trees.iter();
+    for (e, t) in trees.filter(|(e, _)| filter.matches(e, &audit_file.base_dir)) {
+        collect_unaudited(e, t, &mut Vec::new(), &mut locs);
+    }
+    locs.sort_by(|a, b| {
+        let a_loc = &a.info.callee_loc;
+        let b_loc = &b.info.callee_loc;
+        ranks
+            .get(&b.base_effect)
+            .cmp(&ranks.get(&a.base_effect))
+            .then_with(|| a_loc.filepath_string().cmp(&b_loc.filepath_string()))
+            .then_with(|| a_loc.start_line().cmp(&b_loc.start_line()))
+            .then_with(|| a_loc.start_col().cmp(&b_loc.start_col()))
+    });
+    locs
+}
+
+struct App<'a> {
+    audit_file: &'a mut AuditFile,
+    scan_res: &'a ScanResults,
+    config: &'a Config,
+    locs: Vec<UnauditedLoc>,
+    list_state: ListState,
+    sources: HashMap<PathBuf, Vec<String>>,
+    /// The rank of each effect in the audit queue (see `Config::order`)
+    ranks: HashMap<EffectInstance, AuditRank>,
+    status: String,
+    session: AuditSession,
+    /// The effect being shown and since when
+    viewing: Option<(EffectInstance, Instant)>,
+}
+
+impl<'a> App<'a> {
+    fn new(
+        audit_file: &'a mut AuditFile,
+        scan_res: &'a ScanResults,
+        config: &'a Config,
+    ) -> Self {
+        let rank = audit_ranker(scan_res, config);
+        let ranks = audit_file.audit_trees.keys().map(|e| (e.clone(), rank(e))).collect();
+        let locs = unaudited_locs(audit_file, &config.filter, &ranks);
+        let session = audit_file.session.take().unwrap_or_default();
+        // Pick up where the last audit left off, if it was left unfinished
+        let resume_idx = session
+            .current
+            .as_ref()
+            .and_then(|current| locs.iter().position(|l| l.base_effect == *current));
+        let mut list_state = ListState::default();
+        if !locs.is_empty() {
+            list_state.select(Some(resume_idx.unwrap_or(0)));
+        }
+        let status = match resume_idx {
+            Some(_) => format!(
+                "{} unaudited locations, resuming the audit started at {}",
+                locs.len(),
+                format_time(session.started_at)
+            ),
+            None => format!("{} unaudited locations", locs.len()),
+        };
+        Self {
+            audit_file,
+            scan_res,
+            config,
+            locs,
+            list_state,
+            sources: HashMap::new(),
+            ranks,
+            status,
+            session,
+            viewing: None,
+        }
+    }
+
+    /// Adds the time spent on the effect being shown to the session, and
+    /// starts timing the selected one
+    fn view_selected(&mut self) {
+        let now = Instant::now();
+        if let Some((effect, since)) = self.viewing.take() {
+            self.session.spend(&effect, now - since);
+        }
+        if let Some(idx) = self.list_state.selected() {
+            let effect = self.locs[idx].base_effect.clone();
+            self.session.show(&effect);
+            self.viewing = Some((effect, now));
+        }
+    }
+
+    fn selected_effect(&self) -> Option<&EffectInstance> {
+        self.list_state.selected().map(|idx| &self.locs[idx].base_effect)
+    }
+
+    fn select_offset(&mut self, offset: isize) {
+        if self.locs.is_empty() {
+            return;
+        }
+        let curr = self.list_state.selected().unwrap_or(0);
+        let next = curr.saturating_add_signed(offset).min(self.locs.len() - 1);
+        self.list_state.select(Some(next));
+    }
+
+    /// Annotates the selected location and refreshes the list of unaudited
+    /// locations
+    fn annotate(&mut self, annotation: SafetyAnnotation) -> Result<()> {
+        let Some(idx) = self.list_state.selected() else {
+            return Ok(());
+        };
+        let loc = &self.locs[idx];
+        let mut tree =
+            self.audit_file.audit_trees.get_mut(&loc.base_effect).ok_or_else(|| {
+                anyhow!("Couldn't find audit tree for effect: {:?}", loc.base_effect)
+            })?;
+        for &i in &loc.tree_path {
+            tree = match tree {
+                EffectTree::Branch(_, children) => &mut children[i],
+                EffectTree::Leaf(..) => {
+                    return Err(anyhow!("Audit tree changed while auditing"))
+                }
+            };
+        }
+        let previous = tree.clone();
+        update_audit_annotation(annotation, self.scan_res, tree, loc.info.clone())?;
+        push_decision(
+            &mut self.audit_file.decision_history,
+            &mut self.audit_file.decision_info,
+            AuditDecision::new(
+                loc.base_effect.clone(),
+                loc.info.clone(),
+                annotation,
+                previous,
+            ),
+        );
+        self.status = format!("Marked {} as {}", loc.info.caller_path, annotation);
+
+        let effect = loc.base_effect.clone();
+        self.view_selected();
+        self.session.charge(&effect, &mut self.audit_file.decision_info);
+        self.refresh_locs(idx);
+        Ok(())
+    }
+
+    /// Undoes the last decision, which may be from an earlier audit session
+    fn undo(&mut self) -> Result<()> {
+        self.status = match self.audit_file.undo_last_decision()? {
+            Some(d) => format!("Undid marking {} as {}", d, d.annotation),
+            None => "No decisions to undo".to_string(),
+        };
+        let idx = self.list_state.selected().unwrap_or(0);
+        self.refresh_locs(idx);
+        Ok(())
+    }
+
+    /// Lists the unaudited locations again, keeping the selection near `idx`
+    fn refresh_locs(&mut self, idx: usize) {
+        self.locs = unaudited_locs(self.audit_file, &self.config.filter, &self.ranks);
+        if self.locs.is_empty() {
+            self.list_state.select(None);
+        } else {
+            self.list_state.select(Some(idx.min(self.locs.len() - 1)));
+        }
+    }
+
+    fn source_lines(&mut self, info: &EffectInfo) -> Vec<Line<'static>> {
+        let loc = &info.callee_loc;
+        let path = loc.dir().join(loc.file());
+        let src = self.sources.entry(path.clone()).or_insert_with(|| {
+            read_to_string(&path)
+                .map(|s| s.lines().map(String::from).collect())
+                .unwrap_or_default()
+        });
+        if src.is_empty() {
+            return vec![Line::from(format!("Couldn't read {}", path.display()))];
+        }
+
+        let first = loc.start_line().saturating_sub(self.config.lines_before()).max(1);
+        let last = (loc.end_line() + self.config.lines_after()).min(src.len());
+        (first..=last)
+            .map(|line_num| {
+                let text = format!("{:>5} | {}", line_num, src[line_num - 1]);
+                if (loc.start_line()..=loc.end_line()).contains(&line_num) {
+                    Line::styled(
+                        text,
+                        Style::default().bg(Color::Yellow).fg(Color::Black),
+                    )
+                } else {
+                    Line::from(text)
+                }
+            })
+            .collect()
+    }
+
+    fn draw(&mut self, f: &mut Frame) {
+        let rows = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Min(0),
+                Constraint::Length(1),
+                Constraint::Length(1),
+            ])
+            .split(f.size());
+        let panes = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(40), Constraint::Percentage(60)])
+            .split(rows[0]);
+
+        let items =
+            self.locs.iter().map(|l| ListItem::new(l.label())).collect::<Vec<_>>();
+        let list = List::new(items)
+            .block(Block::default().borders(Borders::ALL).title("Unaudited effects"))
+            .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+        f.render_stateful_widget(list, panes[0], &mut self.list_state);
+
+        let preview = match self.list_state.selected() {
+            Some(idx) => {
+                let loc = &self.locs[idx];
+                let info = loc.info.clone();
+                let mut lines = vec![
+                    Line::from(vec![
+                        Span::styled(
+                            "Caller: ",
+                            Style::default().add_modifier(Modifier::BOLD),
+                        ),
+                        Span::raw(info.caller_path.to_string()),
+                    ]),
+                    Line::from(vec![
+                        Span::styled(
+                            "Effect: ",
+                            Style::default().add_modifier(Modifier::BOLD),
+                        ),
+                        Span::raw(loc.base_effect.eff_type().to_csv()),
+                    ]),
+                    Line::from(vec![
+                        Span::styled(
+                            "Location: ",
+                            Style::default().add_modifier(Modifier::BOLD),
+                        ),
+                        Span::raw(info.callee_loc.to_string()),
+                    ]),
+                    Line::from(""),
+                ];
+                lines.extend(self.source_lines(&info));
+                lines.push(Line::from(""));
+                lines.push(Line::styled(
+                    "Callers:",
+                    Style::default().add_modifier(Modifier::BOLD),
+                ));
+                let callers = self.scan_res.caller_tree(&info.caller_path);
+                if callers.is_empty() {
+                    lines.push(Line::from("  none in the crate"));
+                }
+                lines.extend(caller_tree_lines(&callers).into_iter().map(Line::from));
+                Paragraph::new(lines)
+            }
+            None => Paragraph::new("No more effects to audit"),
+        };
+        f.render_widget(
+            preview.block(Block::default().borders(Borders::ALL).title("Source")),
+            panes[1],
+        );
+
+        f.render_widget(Paragraph::new(self.status.as_str()), rows[1]);
+        f.render_widget(
+            Paragraph::new(HELP).style(Style::default().add_modifier(Modifier::REVERSED)),
+            rows[2],
+        );
+    }
+
+    /// Opens the selected location in the auditor's editor, handing the
+    /// terminal over to it until it exits
+    fn open_editor(
+        &mut self,
+        terminal: &mut Terminal<CrosstermBackend<Stdout>>,
+    ) -> Result<()> {
+        let Some(idx) = self.list_state.selected() else {
+            return Ok(());
+        };
+        disable_raw_mode()?;
+        execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
+        let res = open_in_editor(&self.locs[idx].info.callee_loc);
+        execute!(terminal.backend_mut(), EnterAlternateScreen)?;
+        enable_raw_mode()?;
+        terminal.clear()?;
+        res?;
+        self.status = format!("Opened {}", self.locs[idx].info.callee_loc);
+        Ok(())
+    }
+
+    fn run(&mut self, terminal: &mut Terminal<CrosstermBackend<Stdout>>) -> Result<()> {
+        loop {
+            if self.viewing.as_ref().map(|(e, _)| e) != self.selected_effect() {
+                self.view_selected();
+            }
+            terminal.draw(|f| self.draw(f))?;
+
+            let Event::Key(key) = event::read()? else {
+                continue;
+            };
+            if key.kind != KeyEventKind::Press {
+                continue;
+            }
+            let res = match key.code {
+                KeyCode::Char('q') | KeyCode::Esc => {
+                    self.view_selected();
+                    self.audit_file.session = Some(self.session.clone());
+                    return Ok(());
+                }
+                KeyCode::Up | KeyCode::Char('k') => {
+                    self.select_offset(-1);
+                    Ok(())
+                }
+                KeyCode::Down | KeyCode::Char('j') | KeyCode::Char('l') => {
+                    self.select_offset(1);
+                    Ok(())
+                }
+                KeyCode::Char('s') => self.annotate(SafetyAnnotation::Safe),
+                KeyCode::Char('u') => self.annotate(SafetyAnnotation::Unsafe),
+                KeyCode::Char('c') => self.annotate(SafetyAnnotation::CallerChecked),
+                KeyCode::Char('z') => self.undo(),
+                KeyCode::Char('v') => self.open_editor(terminal),
+                _ => Ok(()),
+            };
+            if let Err(e) = res {
+                self.status = format!("Error: {}", e);
+            }
+        }
+    }
+}
+
+/// Audits the unaudited effects of the audit file in an interactive terminal
+/// UI. Skipped effects stay unaudited so they can be audited later.
+pub fn start_tui_audit(
+    audit_file: &mut AuditFile,
+    scan_res: &ScanResults,
+    config: &Config,
+) -> Result<()> {
+    enable_raw_mode()?;
+    let mut stdout = io::stdout();
+    execute!(stdout, EnterAlternateScreen)?;
+    let mut terminal = Terminal::new(CrosstermBackend::new(stdout))?;
+
+    let res = App::new(audit_file, scan_res, config).run(&mut terminal);
+
+    // Always restore the terminal, even if the audit failed
+    disable_raw_mode()?;
+    execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
+    terminal.show_cursor()?;
+    res?;
+
+    // NOTE: As in the command line audit, we recalculate the public functions
+    //       once we are done instead of tracking them during the audit.
+    audit_file.recalc_pub_caller_checked(&scan_res.pub_fns);
+
+    // Keep the session around to resume it, unless there's nothing left to do
+    if audit_file.unaudited_effects().0 == 0 {
+        audit_file.session = None;
+    }
+    Ok(())
+}
@@ -0,0 +1,143 @@
+//! Baseline of known effects.
+//!
+//! A baseline records the effects found by a scan so that later scans only
+//! report new effects. Effects are identified by a stable hash which leaves
+//! out line and column numbers, so that unrelated edits moving code around
+//! don't make known effects show up again.
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::fs;
+use std::path::Path as FilePath;
+
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::effect::EffectInstance;
+
+/// Version of the baseline file format
+const BASELINE_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Baseline {
+    version: u32,
+    /// Number of effects with each hash
+    effects: BTreeMap<String, usize>,
+}
+
+/// Returns a hash of the effect which doesn't change when the effect moves
+/// within its file
+pub fn effect_hash(e: &EffectInstance) -> String {
+    let mut hasher = Sha256::new();
+    for part in [
+        e.caller().crate_name().as_str(),
+        e.caller_path(),
+        e.callee_path(),
+        &e.eff_type().to_csv(),
+        &e.call_loc().file().to_string_lossy(),
+    ] {
+        hasher.update(part.as_bytes());
+        // Separator, so that e.g. ("ab", "c") and ("a", "bc") differ
+        hasher.update([0]);
+    }
+    hasher.finalize().iter().fold(String::new(), |mut s, b| {
+        let _ = write!(s, "{:02x}", b);
+        s
+    })
+}
+
+impl Baseline {
+    pub fn from_effects(effects: &[EffectInstance]) -> Self {
+        let mut baseline = Self { version: BASELINE_VERSION, effects: BTreeMap::new() };
+        for e in effects {
+            *baseline.effects.entry(effect_hash(e)).or_default() += 1;
+        }
+        baseline
+    }
+
+    pub fn load(path: &FilePath) -> Result<Self> {
+        let json = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read baseline {:?}", path))?;
+        let baseline: Self = serde_json::from_str(&json)
+            .with_context(|| format!("Failed to parse baseline {:?}", path))?;
+        if baseline.version != BASELINE_VERSION {
+            return Err(anyhow!(
+                "Unsupported baseline version {} in {:?} (expected {})",
+                baseline.version,
+                path,
+                BASELINE_VERSION
+            ));
+        }
+        Ok(baseline)
+    }
+
+    pub fn save(&self, path: &FilePath) -> Result<()> {
+        fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// The total number of effects in the baseline
+    pub fn len(&self) -> usize {
+        self.effects.values().sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the effects which aren't in the baseline.
+    ///
+    /// If the baseline contains n effects with the same hash, the first n
+    /// such effects are considered known and any further ones are new.
+    pub fn new_effects(&self, effects: Vec<EffectInstance>) -> Vec<EffectInstance> {
+        let mut known = self.effects.clone();
+        effects
+            .into_iter()
+            .filter(|e| match known.get_mut(&effect_hash(e)) {
+                Some(n) if *n > 0 => {
+                    *n -= 1;
+                    false
+                }
+                _ => true,
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::effect::Effect;
+    use crate::ident::CanonicalPath;
+    use proc_macro2::{Ident, Span};
+
+    fn asm_effect(file: &str, caller: &str) -> EffectInstance {
+        EffectInstance::new_effect(
+            FilePath::new(file),
+            CanonicalPath::new(caller),
+            CanonicalPath::new("asm"),
+            &Ident::new("asm", Span::call_site()),
+            Effect::InlineAsm,
+        )
+    }
+
+    #[test]
+    fn test_baseline_new_effects() {
+        let old =
+            vec![asm_effect("src/lib.rs", "foo::f"), asm_effect("src/lib.rs", "foo::g")];
+        let baseline = Baseline::from_effects(&old);
+        assert_eq!(baseline.len(), 2);
+
+        let new = vec![
+            asm_effect("src/lib.rs", "foo::f"),
+            asm_effect("src/lib.rs", "foo::f"),
+            asm_effect("src/lib.rs", "foo::g"),
+            asm_effect("src/other.rs", "foo::g"),
+        ];
+        let reported = baseline.new_effects(new);
+        assert_eq!(reported.len(), 2);
+        assert_eq!(reported[0].caller_path(), "foo::f");
+        assert_eq!(reported[1].call_loc().file(), FilePath::new("other.rs"));
+    }
+}
@@ -3,11 +3,18 @@ use cargo_scan::auditing::audit::start_audit;
 use cargo_scan::auditing::info::Config;
 use cargo_scan::auditing::reset::reset_annotation;
 use cargo_scan::auditing::review::review_audit;
+use cargo_scan::auditing::tui::start_tui_audit;
 use cargo_scan::auditing::util::{hash_dir, is_audit_scan_valid};
-use cargo_scan::effect::{EffectInstance, EffectType, DEFAULT_EFFECT_TYPES};
+use cargo_scan::db::ScanDb;
+use cargo_scan::effect::{
+    signature_to_string, EffectInstance, EffectType, DEFAULT_EFFECT_TYPES,
+};
+use cargo_scan::filter::EffectFilter;
 use cargo_scan::ident::IdentPath;
-use cargo_scan::scanner::{self, scan_crate};
-use cargo_scan::util::load_cargo_toml;
+use cargo_scan::project_config::ProjectConfig;
+use cargo_scan::scanner;
+use cargo_scan::sink::load_sinks_file;
+use cargo_scan::util::{self, load_cargo_toml};
 
 use std::collections::{HashMap, HashSet};
 use std::fs::{self, create_dir_all, File};
@@ -18,7 +25,6 @@ use anyhow::{anyhow, Context, Result};
 use clap::{Parser, ValueEnum};
 use home::home_dir;
 use inquire::{validator::Validation, Text};
-use petgraph::dot::Dot;
 
 /// Interactively vet a package audit
 #[derive(Parser, Debug)]
@@ -65,6 +71,11 @@ struct Args {
     #[clap(short, long, default_value_t = false)]
     preview: bool,
 
+    /// Audit the crate in an interactive terminal UI instead of prompting
+    /// for each effect in turn
+    #[clap(long, default_value_t = false)]
+    tui: bool,
+
     /// Reset an annotation to "skipped" for a base effect
     #[clap(long)]
     reset_annotation: bool,
@@ -85,9 +96,29 @@ struct Args {
 
     /// The types of Effects the audit should track. Defaults to all unsafe
     /// behavior.
-    #[clap(long, value_parser, num_args = 1.., default_values_t = DEFAULT_EFFECT_TYPES)]
+    #[clap(
+        long,
+        value_parser,
+        num_args = 1..,
+        value_delimiter = ',',
+        default_values_t = DEFAULT_EFFECT_TYPES
+    )]
     effect_types: Vec<EffectType>,
 
+    /// Only audit effects in files matching the given glob, relative to the
+    /// crate root, e.g. 'src/net/**'. Repeat to give several
+    #[clap(long)]
+    include_path: Vec<String>,
+
+    /// Only audit effects whose callee starts with one of the given
+    /// comma-separated prefixes, e.g. libc::
+    #[clap(long, value_delimiter = ',')]
+    callee_prefix: Vec<String>,
+
+    /// TOML file of additional sink patterns to look for
+    #[clap(long)]
+    sinks_file: Option<PathBuf>,
+
     /// TESTING ONLY: Import all caller-checked public functions from audits in
     /// a folder as additional sinks for an audit. This functionality should
     /// eventuallly be replaced by the chain binary, but is included here for
@@ -95,9 +126,32 @@ struct Args {
     #[clap(long)]
     sinks_folder: Option<String>,
 
+    /// Also save the audit decisions to the given SQLite database, replacing
+    /// those of any earlier audit of the same crate version
+    #[clap(long)]
+    db: Option<PathBuf>,
+
+    /// Identity of the auditor to record with the decisions, e.g. a name and
+    /// email address or a key fingerprint. Defaults to $CARGO_SCAN_AUDITOR,
+    /// then the `cargo-scan.auditor` git config, then the git user name and
+    /// email, then the current user
+    #[clap(long)]
+    auditor: Option<String>,
+
     /// TESTING ONLY: Use the quick-mode scan option
     #[clap(long, default_value_t = false)]
     quick_mode: bool,
+
+    /// Project config file to use instead of the nearest .cargo-scan.toml
+    #[clap(long = "config", conflicts_with = "no_config")]
+    project_config: Option<PathBuf>,
+
+    /// Don't read a .cargo-scan.toml project config file
+    #[clap(long, default_value_t = false)]
+    no_config: bool,
+
+    #[clap(skip)]
+    project: ProjectConfig,
 }
 
 #[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
@@ -151,6 +205,10 @@ where
                 )
             })
             .collect::<HashMap<_, _>>();
+        audit_file.comments.clear();
+        audit_file.decision_history.clear();
+        audit_file.decision_info.clear();
+        audit_file.session = None;
         audit_file.hash = hash_dir(audit_file.base_dir.clone())?;
 
         let mut audit_string = audit_file_path
@@ -193,6 +251,10 @@ where
                         )
                     })
                     .collect::<HashMap<_, _>>();
+                audit_file.comments.clear();
+                audit_file.decision_history.clear();
+                audit_file.decision_info.clear();
+                audit_file.session = None;
                 audit_file.hash = hash_dir(audit_file.base_dir.clone())?;
 
                 let mut audit_file_string = audit_file_path
@@ -237,8 +299,12 @@ fn audit_crate(args: Args, audit_file: Option<AuditFile>) -> Result<()> {
         };
 
         // Load extra sinks if we have any
-        let sinks =
+        let mut sinks =
             get_sinks(args.sinks_folder.as_ref()).unwrap_or_else(|_| HashSet::new());
+        if let Some(sinks_file) = &args.sinks_file {
+            sinks.extend(load_sinks_file(sinks_file)?);
+        }
+        sinks.extend(args.project.sinks()?);
 
         println!("Scanning crate...");
         scanner::scan_crate_with_sinks(
@@ -254,7 +320,7 @@ fn audit_crate(args: Args, audit_file: Option<AuditFile>) -> Result<()> {
         let path = Path::new(callgraph_file);
         if !path.exists() {
             let mut file = File::create(callgraph_file)?;
-            file.write_all(&format!("{}", Dot::new(&scan_res.call_graph)).into_bytes())?;
+            file.write_all(scan_res.call_graph_dot().as_bytes())?;
         } else {
             println!("Callgraph filepath already exists");
         }
@@ -303,7 +369,9 @@ fn audit_crate(args: Args, audit_file: Option<AuditFile>) -> Result<()> {
         }
     };
 
-    if start_audit(&mut audit_file, scan_res, &args.config)?.is_some() {
+    if args.tui {
+        start_tui_audit(&mut audit_file, &scan_res, &args.config)?;
+    } else if start_audit(&mut audit_file, scan_res, &args.config)?.is_some() {
         // The user marked that they want to audit a child effect, but we aren't
         // able to do so in this mode.
         return Err(anyhow!("Can't audit dependency crate effects in this binary"));
@@ -315,6 +383,11 @@ fn audit_crate(args: Args, audit_file: Option<AuditFile>) -> Result<()> {
     println!("Saving audit to file");
     audit_file.save_to_file(audit_file_path)?;
 
+    if let Some(db_path) = &args.db {
+        let crate_id = load_cargo_toml(&args.crate_path)?;
+        ScanDb::open(db_path)?.save_audit(&crate_id, &audit_file)?;
+    }
+
     Ok(())
 }
 
@@ -329,7 +402,17 @@ fn runner(args: Args) -> Result<()> {
         println!("Previewing crate effects.");
         println!("Scanning crate...");
 
-        let res = scan_crate(&args.crate_path, &args.effect_types, args.quick_mode)?;
+        let mut sinks = match &args.sinks_file {
+            Some(sinks_file) => load_sinks_file(sinks_file)?,
+            None => HashSet::new(),
+        };
+        sinks.extend(args.project.sinks()?);
+        let res = scanner::scan_crate_with_sinks(
+            &args.crate_path,
+            sinks,
+            &args.effect_types,
+            args.quick_mode,
+        )?;
         for effect in res.effects {
             println!("{}", effect.to_csv());
         }
@@ -342,34 +425,69 @@ fn runner(args: Args) -> Result<()> {
     } else if args.review {
         match audit_file {
             None => Err(anyhow!("Audit file to review doesn't exist")),
-            Some(af) => {
-                match args.review_info {
-                    ReviewInfo::All => review_audit(
-                        &af,
-                        &args.crate_path,
-                        &args.config,
-                        args.quick_mode,
-                        args.ignore_hash,
-                    ),
-                    ReviewInfo::PubFuns => {
-                        println!("Public functions marked caller-checked:");
-                        for pub_fn in af.pub_caller_checked.keys() {
-                            // TODO: Print more info
-                            println!("  {}", pub_fn);
+            Some(af) => match args.review_info {
+                ReviewInfo::All => review_audit(
+                    &af,
+                    &args.crate_path,
+                    &args.config,
+                    args.quick_mode,
+                    args.ignore_hash,
+                ),
+                ReviewInfo::PubFuns => {
+                    println!("Public functions marked caller-checked:");
+                    for pub_fn in af.pub_caller_checked.keys() {
+                        match af.pub_effect_signatures.get(pub_fn) {
+                            Some(sig) => {
+                                println!(
+                                    "  {} (effects: {})",
+                                    pub_fn,
+                                    signature_to_string(sig)
+                                )
+                            }
+                            None => println!("  {}", pub_fn),
                         }
-                        Ok(())
                     }
+                    Ok(())
                 }
-            }
+            },
         }
     } else {
         audit_crate(args, audit_file)
     }
 }
 
+/// The project config, along with the effect filter it gives, with the
+/// filters on the command line taking precedence
+fn load_project_config(args: &Args) -> Result<(ProjectConfig, EffectFilter)> {
+    let project = if args.no_config {
+        ProjectConfig::default()
+    } else {
+        ProjectConfig::load_or_find(args.project_config.as_deref())?
+    };
+    util::fs::exclude_globs(&project.ignore)?;
+    let filter = project.effect_filter(
+        vec![],
+        args.include_path.clone(),
+        args.callee_prefix.clone(),
+    )?;
+    Ok((project, filter))
+}
+
 fn main() {
     cargo_scan::util::init_logging();
     let mut args = Args::parse();
+    set_auditor(args.auditor.clone());
+
+    match load_project_config(&args) {
+        Ok((project, filter)) => {
+            args.project = project;
+            args.config.filter = filter;
+        }
+        Err(e) => {
+            println!("Error: {}", e);
+            return;
+        }
+    }
 
     if let Some(audit_file_path) = &mut args.audit_file_path {
         // If the user-chosen audit file path is a directory, make the audit path
@@ -0,0 +1,125 @@
+//! The `cargo scan` Cargo subcommand.
+//!
+//! Runs the `scan`, `audit`, `chain`, `policy` and `report` tools on the
+//! package in the current directory, e.g. `cargo scan scan --format json`,
+//! instead of taking the path to the crate as an argument. The package is found the way Cargo
+//! finds it, by looking for a Cargo.toml in the current directory and its
+//! parents, or is given with `--manifest-path`.
+//!
+//! The tools are installed alongside this binary, and any arguments after the
+//! subcommand are passed on to them; run e.g. `cargo scan audit --help` for
+//! their options.
+
+use cargo_scan::util::find_crate_root;
+
+use anyhow::{bail, Context, Result};
+use clap::{Args as ClapArgs, Parser, Subcommand};
+use std::env;
+use std::ffi::OsString;
+use std::path::PathBuf;
+use std::process::{Command as Process, ExitCode};
+
+/// Cargo runs `cargo-scan scan ...` for `cargo scan ...`, so the tools are
+/// subcommands of the `scan` subcommand
+#[derive(Parser, Debug)]
+#[command(name = "cargo", bin_name = "cargo")]
+enum Cargo {
+    /// Find and audit the side effects of the current package
+    #[command(version)]
+    Scan(Args),
+}
+
+#[derive(ClapArgs, Debug)]
+struct Args {
+    /// Path to the Cargo.toml of the package to scan, instead of finding it
+    /// from the current directory
+    #[clap(long)]
+    manifest_path: Option<PathBuf>,
+
+    #[clap(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// List the effects in the package
+    #[command(disable_help_flag = true)]
+    Scan(ToolArgs),
+    /// Interactively audit the package
+    #[command(disable_help_flag = true)]
+    Audit(ToolArgs),
+    /// Manage the audits of the package and its dependencies. Unlike the other
+    /// subcommands, the package path isn't filled in, as each chain
+    /// subcommand takes its own paths
+    #[command(disable_help_flag = true)]
+    Chain(ToolArgs),
+    /// Revise or undo the decisions in the package's audit. Like `chain`, it
+    /// isn't given the package path, as it finds the package itself
+    #[command(disable_help_flag = true)]
+    Policy(ToolArgs),
+    /// Render the scan of the package as an HTML page
+    #[command(disable_help_flag = true)]
+    Report(ToolArgs),
+}
+
+#[derive(ClapArgs, Debug)]
+struct ToolArgs {
+    /// Arguments passed on to the tool
+    #[clap(trailing_var_arg = true, allow_hyphen_values = true)]
+    args: Vec<OsString>,
+}
+
+impl Command {
+    /// The name of the tool's binary, its arguments, and whether it takes the
+    /// path to the crate as its first argument
+    fn tool(self) -> (&'static str, Vec<OsString>, bool) {
+        match self {
+            Self::Scan(t) => ("scan", t.args, true),
+            Self::Audit(t) => ("audit", t.args, true),
+            Self::Chain(t) => ("chain", t.args, false),
+            Self::Policy(t) => ("policy", t.args, false),
+            Self::Report(t) => ("report", t.args, true),
+        }
+    }
+}
+
+/// The package's directory, from `--manifest-path` or the current directory
+fn crate_root(manifest_path: Option<PathBuf>) -> Result<PathBuf> {
+    match manifest_path {
+        Some(path) => {
+            if path.file_name().is_none_or(|f| f != "Cargo.toml") {
+                bail!("--manifest-path must be a path to a Cargo.toml file");
+            }
+            let dir = path.parent().filter(|d| !d.as_os_str().is_empty());
+            Ok(dir.map_or_else(|| PathBuf::from("."), PathBuf::from))
+        }
+        None => find_crate_root(&env::current_dir()?),
+    }
+}
+
+fn main() -> Result<ExitCode> {
+    let Cargo::Scan(args) = Cargo::parse();
+    let (tool, mut tool_args, takes_crate_path) = args.command.tool();
+    // `scan --package` and `scan --git` scan a crate from elsewhere instead
+    let scans_remote = tool == "scan"
+        && tool_args.iter().any(|a| {
+            let a = a.to_string_lossy();
+            let flag = a.split('=').next().unwrap_or_default();
+            a == "-p" || flag == "--package" || flag == "--git"
+        });
+    if takes_crate_path && !scans_remote {
+        tool_args.insert(0, crate_root(args.manifest_path)?.into_os_string());
+    }
+
+    let exe = env::current_exe()?;
+    let tool_exe = exe.with_file_name(tool).with_extension(env::consts::EXE_EXTENSION);
+    let status =
+        Process::new(&tool_exe).args(&tool_args).status().with_context(|| {
+            format!("Failed to run {:?}; is cargo-scan installed?", tool_exe)
+        })?;
+    Ok(match status.code() {
+        Some(code) => ExitCode::from(code as u8),
+        // Killed by a signal
+        None => ExitCode::FAILURE,
+    })
+}
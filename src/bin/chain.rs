@@ -1,13 +1,30 @@
-use cargo_scan::audit_chain::{create_new_audit_chain, AuditChain, Create};
-use cargo_scan::audit_file::AuditFile;
+use cargo_scan::audit_chain::{
+    create_new_audit_chain, update_audit_chain, AuditChain, Create, ReviewStatus, Update,
+};
+use cargo_scan::audit_file::{
+    set_auditor, unix_time, AuditFile, AuditStats, ThirdPartyReview,
+};
 use cargo_scan::auditing::audit::{audit_pub_fn, start_audit};
-use cargo_scan::auditing::info::Config as AuditConfig;
+use cargo_scan::auditing::info::{AuditOrder, Config as AuditConfig};
 use cargo_scan::auditing::review::review_audit;
-use cargo_scan::effect::Effect;
+use cargo_scan::chain_graph::{ChainCallGraph, EffectQuery};
+use cargo_scan::crev;
+use cargo_scan::criteria;
+use cargo_scan::effect::{signature_to_string, Effect, EffectType};
+use cargo_scan::ident::CanonicalPath;
+use cargo_scan::progress;
+use cargo_scan::project_config::ProjectConfig;
+use cargo_scan::registry::RegistryClient;
+use cargo_scan::signing::{self, SigningKey};
+use cargo_scan::util::CrateId;
+use cargo_scan::vet::{self, VetCriteria, CALLER_CHECKED_CRITERIA, SAFE_CRITERIA};
 use cargo_scan::{download_crate, scanner};
 
 use anyhow::{anyhow, Context, Result};
-use clap::{Args as ClapArgs, Parser, Subcommand, ValueEnum};
+use clap::{
+    Args as ClapArgs, Command as ClapCommand, CommandFactory, FromArgMatches, Parser,
+    Subcommand, ValueEnum,
+};
 use std::collections::HashSet;
 use std::fs::create_dir_all;
 use std::path::PathBuf;
@@ -21,6 +38,15 @@ struct OuterArgs {
 
     #[clap(long, default_value_t = false)]
     quick_mode: bool,
+
+    /// Don't show progress bars on stderr while creating a chain
+    #[clap(long, default_value_t = false)]
+    no_progress: bool,
+
+    /// Identity of the auditor to record with the decisions, as with `audit
+    /// --auditor`
+    #[clap(long)]
+    auditor: Option<String>,
 }
 
 #[derive(Parser, Debug)]
@@ -35,8 +61,22 @@ struct Args {
 #[derive(Subcommand, Debug)]
 enum Command {
     Create(Create),
+    Update(Update),
     Review(Review),
     Audit(Audit),
+    Reaches(Reaches),
+    SignOff(SignOff),
+    Assign(Assign),
+    Assignments(Assignments),
+    Status(Status),
+    Stats(Stats),
+    ExportVet(ExportVet),
+    ImportCrev(ImportCrev),
+    Fetch(Fetch),
+    Publish(Publish),
+    Keygen(Keygen),
+    Certify(Certify),
+    Check(Check),
 }
 
 trait CommandRunner {
@@ -47,8 +87,22 @@ impl CommandRunner for Command {
     fn run_command(self, args: OuterArgs) -> Result<()> {
         match self {
             Self::Create(create) => create.run_command(args),
+            Self::Update(update) => update.run_command(args),
             Self::Review(review) => review.run_command(args),
             Self::Audit(audit) => audit.run_command(args),
+            Self::Reaches(reaches) => reaches.run_command(args),
+            Self::SignOff(sign_off) => sign_off.run_command(args),
+            Self::Assign(assign) => assign.run_command(args),
+            Self::Assignments(assignments) => assignments.run_command(args),
+            Self::Status(status) => status.run_command(args),
+            Self::Stats(stats) => stats.run_command(args),
+            Self::ExportVet(export_vet) => export_vet.run_command(args),
+            Self::ImportCrev(import_crev) => import_crev.run_command(args),
+            Self::Fetch(fetch) => fetch.run_command(args),
+            Self::Publish(publish) => publish.run_command(args),
+            Self::Keygen(keygen) => keygen.run_command(args),
+            Self::Certify(certify) => certify.run_command(args),
+            Self::Check(check) => check.run_command(args),
         }
     }
 }
@@ -92,12 +146,21 @@ impl CommandRunner for Create {
             std::fs::rename(&tmp_path, &self.crate_path)?;
         }
 
+        progress::enable(!args.no_progress);
         let chain = create_new_audit_chain(self, &args.crate_download_path, false)?;
         chain.save_to_file()?;
         Ok(())
     }
 }
 
+impl CommandRunner for Update {
+    fn run_command(self, args: OuterArgs) -> Result<()> {
+        let mut chain = read_chain(&self.manifest_path)?;
+        update_audit_chain(&mut chain, self, args.quick_mode)?;
+        Ok(chain.save_to_file()?)
+    }
+}
+
 #[derive(Clone, ClapArgs, Debug)]
 struct Review {
     /// Path to chain manifest
@@ -107,6 +170,10 @@ struct Review {
     review_info: ReviewInfo,
     /// What crate to review, defaults to all crates.
     review_target: Option<String>,
+    /// With `--review-info crates`, list the crates with the highest risk
+    /// score first, instead of the most widely called ones
+    #[clap(long, default_value_t = false)]
+    by_risk: bool,
 }
 
 #[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
@@ -136,16 +203,51 @@ impl CommandRunner for Review {
                     "Couldn't find audit chain manifest at {}",
                     &self.manifest_path
                 )),
-                Err(e) => Err(e),
+                Err(e) => Err(e.into()),
             }?;
 
         // Don't have to do the usual review process of loading up the crate's
         // audit file if we are just printing out the list of crates for the given
         // manifest file
         if self.review_info == ReviewInfo::Crates {
+            // List the crates whose code is most widely called first, since
+            // those are the most important to audit, or the riskiest ones if
+            // asked to. Proc macros come before all of them, as they run
+            // arbitrary code in every build.
+            let graph = ChainCallGraph::from_chain(&mut chain)?;
             println!("Dependency crates:");
-            for krate in chain.all_crates() {
-                println!("  - {}", krate);
+            let crates = chain.all_crates().into_iter().cloned().collect::<Vec<_>>();
+            let mut ranked = Vec::new();
+            for (krate, centrality) in
+                graph.rank_crates(&crates.iter().collect::<Vec<_>>())
+            {
+                let audit_file = chain.read_audit_file(krate)?;
+                let risk = audit_file.as_ref().map_or(0.0, AuditFile::risk_score);
+                ranked.push((krate, centrality, risk, audit_file));
+            }
+            if self.by_risk {
+                ranked.sort_by(|(_, _, r1, _), (_, _, r2, _)| r2.total_cmp(r1));
+            }
+            ranked.sort_by_key(|(krate, _, _, _)| !chain.is_proc_macro(krate));
+            for (krate, centrality, risk, audit_file) in ranked {
+                println!(
+                    "  - {} (called from {} external functions, risk score {:.1})",
+                    krate, centrality, risk
+                );
+                if chain.is_proc_macro(krate) {
+                    println!("      proc macro: runs at compile time");
+                }
+                let mut libs = audit_file
+                    .iter()
+                    .flat_map(|a| a.native_libs.iter().map(|l| l.name.as_str()))
+                    .collect::<Vec<_>>();
+                libs.dedup();
+                if !libs.is_empty() {
+                    println!("      links native libraries: {}", libs.join(", "));
+                }
+                for advisory in chain.advisories(krate) {
+                    println!("      advisory {}", advisory);
+                }
             }
 
             return Ok(());
@@ -158,6 +260,7 @@ impl CommandRunner for Review {
 
         for review_crate in crates_to_review {
             println!("Reviewing audit for {}", review_crate);
+            print_advisories(&chain, &review_crate);
             let audit_file = chain.read_audit_file(&review_crate)?.ok_or_else(|| {
                 anyhow!(format!(
                     "Couldn't find audit for crate {} in chain",
@@ -182,6 +285,10 @@ struct Audit {
     manifest_path: String,
     /// Name of the crate to review (defaults to the root crate if none is provided)
     crate_name: Option<String>,
+    /// The order to audit the effects in. With `priority`, effects reachable
+    /// from the root crate come first
+    #[clap(long, default_value_t = AuditOrder::default())]
+    order: AuditOrder,
 }
 
 // TODO: print more info during auding (e.g. saving files)
@@ -196,9 +303,18 @@ impl CommandRunner for Audit {
                     None => chain.root_crate()?,
                 };
 
+                print_advisories(&chain, &crate_id);
+
                 // TODO: Handle more than one audit matching a crate
                 if let Some(orig_audit_file) = chain.read_audit_file(&crate_id)? {
                     let mut new_audit_file = orig_audit_file.clone();
+                    let reopened = new_audit_file.reopen_expired(unix_time());
+                    if reopened > 0 {
+                        println!(
+                            "Auditing {} effects again since their decisions expired",
+                            reopened
+                        );
+                    }
                     let crate_path = PathBuf::from(&orig_audit_file.base_dir);
 
                     // Iterate through the crate's dependencies and add the
@@ -211,6 +327,18 @@ impl CommandRunner for Audit {
 
                     let mut audit_config = AuditConfig::default();
                     audit_config.allow_effect_origin = true;
+                    audit_config.order = self.order;
+                    let root_crate = chain.root_crate()?;
+                    if self.order == AuditOrder::Priority && crate_id != root_crate {
+                        let graph = ChainCallGraph::from_chain(&mut chain)?;
+                        audit_config.root_reachable_fns = Some(
+                            graph
+                                .crate_fns_reachable_from(&root_crate, &crate_id)
+                                .into_iter()
+                                .map(|f| CanonicalPath::new(f.as_str()))
+                                .collect(),
+                        );
+                    }
 
                     // TODO: Mechanism for re-auditing the default policies
                     // NOTE: audit_res will contain an EffectBlock if the user
@@ -220,7 +348,11 @@ impl CommandRunner for Audit {
                     // Save the audit immediately after audit so we don't error
                     // out and forget to save
                     chain.save_audit_file(&crate_id, &new_audit_file)?;
-                    let removed_fns = if let Some(dep_effect) = audit_res? {
+                    // Public functions of this crate which are no longer
+                    // caller-checked after the audit
+                    let mut removed_fns =
+                        AuditFile::pub_diff(&orig_audit_file, &new_audit_file);
+                    let dep_removed_fns = if let Some(dep_effect) = audit_res? {
                         // TODO: Print parents of an effect the user audits when
                         //       auditing children
                         match dep_effect.eff_type() {
@@ -239,6 +371,7 @@ impl CommandRunner for Audit {
                     } else {
                         HashSet::new()
                     };
+                    removed_fns.extend(dep_removed_fns);
 
                     // if any public function annotations have changed,
                     // update parent packages
@@ -255,7 +388,17 @@ impl CommandRunner for Audit {
                 "Couldn't find audit chain manifest at {}",
                 &self.manifest_path
             )),
-            Err(e) => Err(e),
+            Err(e) => Err(e.into()),
+        }
+    }
+}
+
+/// Warn about the RustSec advisories of the crate, if it has any
+fn print_advisories(chain: &AuditChain, crate_id: &CrateId) {
+    for advisory in chain.advisories(crate_id) {
+        println!("WARNING: {} has security advisory {}", crate_id, advisory);
+        if let Some(url) = &advisory.url {
+            println!("    {}", url);
         }
     }
 }
@@ -272,8 +415,12 @@ fn review_crate_audit_file(
         ReviewInfo::PubFuns => {
             println!("Public functions marked caller-checked:");
             for pub_fn in audit_file.pub_caller_checked.keys() {
-                // TODO: Print more info
-                println!("  {}", pub_fn);
+                match audit_file.pub_effect_signatures.get(pub_fn) {
+                    Some(sig) => {
+                        println!("  {} (effects: {})", pub_fn, signature_to_string(sig))
+                    }
+                    None => println!("  {}", pub_fn),
+                }
             }
             Ok(())
         }
@@ -284,12 +431,732 @@ fn review_crate_audit_file(
     }
 }
 
+/// List the functions of a crate which can transitively reach the given
+/// effects in any crate of the chain
+#[derive(Clone, ClapArgs, Debug)]
+struct Reaches {
+    /// Path to chain manifest
+    manifest_path: String,
+    /// Name of the crate whose functions to list (defaults to the root crate)
+    crate_name: Option<String>,
+    /// The types of effects to look for
+    #[clap(long, value_parser, num_args = 1..)]
+    effect_types: Vec<EffectType>,
+    /// Sink patterns to look for (e.g. `std::net`)
+    #[clap(long, num_args = 1..)]
+    sinks: Vec<String>,
+}
+
+impl CommandRunner for Reaches {
+    fn run_command(self, _args: OuterArgs) -> Result<()> {
+        let mut chain = AuditChain::read_audit_chain(PathBuf::from(&self.manifest_path))?
+            .ok_or_else(|| {
+                anyhow!("Couldn't find audit chain manifest at {}", &self.manifest_path)
+            })?;
+        let crate_id = match &self.crate_name {
+            Some(crate_name) => chain
+                .resolve_crate_id(crate_name)
+                .context(format!("Couldn't resolve crate_name for {}", crate_name))?,
+            None => chain.root_crate()?,
+        };
+        if self.effect_types.is_empty() && self.sinks.is_empty() {
+            return Err(anyhow!("Must provide at least one effect type or sink"));
+        }
+
+        let graph = ChainCallGraph::from_chain(&mut chain)?;
+        let query = EffectQuery { effect_types: self.effect_types, sinks: self.sinks };
+        let fns = graph.crate_fns_reaching(&crate_id, &query);
+        if fns.is_empty() {
+            println!("No functions in {} reach the given effects", crate_id);
+        } else {
+            println!("Functions in {} reaching the given effects:", crate_id);
+            for f in fns {
+                println!("  {}", f);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Loads the chain, counting the sign-offs of the reviewers whose keys the
+/// project config trusts
+fn read_chain(manifest_path: &str) -> Result<AuditChain> {
+    let mut chain = AuditChain::read_audit_chain(PathBuf::from(manifest_path))?
+        .ok_or_else(|| {
+            anyhow!("Couldn't find audit chain manifest at {}", manifest_path)
+        })?;
+    chain.set_trust(ProjectConfig::load_or_find(None)?.trust);
+    Ok(chain)
+}
+
+/// Sign off on a crate's completed audit as one of its reviewers
+#[derive(Clone, ClapArgs, Debug)]
+struct SignOff {
+    /// Path to chain manifest
+    manifest_path: String,
+    /// Name of the crate to sign off on
+    crate_name: String,
+    /// Name of the reviewer signing off
+    #[clap(short, long)]
+    reviewer: String,
+}
+
+impl CommandRunner for SignOff {
+    fn run_command(self, _args: OuterArgs) -> Result<()> {
+        let mut chain = read_chain(&self.manifest_path)?;
+        let crate_id = chain
+            .resolve_crate_id(&self.crate_name)
+            .context(format!("Couldn't resolve crate_name for {}", &self.crate_name))?;
+        if chain.review_status(&crate_id)? == ReviewStatus::Unaudited {
+            return Err(anyhow!(
+                "Can't sign off on {}, it still has effects left to audit",
+                crate_id
+            ));
+        }
+
+        let key = signing::signing_key()?;
+        chain.sign_off(&crate_id, &self.reviewer, &key)?;
+        let signed = chain.valid_sign_offs(&crate_id)?.len();
+        println!(
+            "{} signed off on {} ({}/{} sign-offs)",
+            self.reviewer,
+            crate_id,
+            signed,
+            chain.required_reviewers()
+        );
+        Ok(chain.save_to_file()?)
+    }
+}
+
+/// Assign reviewers to sign off on a crate's audit
+#[derive(Clone, ClapArgs, Debug)]
+struct Assign {
+    /// Path to chain manifest
+    manifest_path: String,
+    /// Name of the crate to assign
+    crate_name: String,
+    /// Names of the reviewers to assign
+    #[clap(required = true)]
+    reviewers: Vec<String>,
+}
+
+impl CommandRunner for Assign {
+    fn run_command(self, _args: OuterArgs) -> Result<()> {
+        let mut chain = read_chain(&self.manifest_path)?;
+        let crate_id = chain
+            .resolve_crate_id(&self.crate_name)
+            .context(format!("Couldn't resolve crate_name for {}", &self.crate_name))?;
+        for reviewer in &self.reviewers {
+            chain.assign(&crate_id, reviewer);
+        }
+        Ok(chain.save_to_file()?)
+    }
+}
+
+/// List the assigned reviewers who haven't signed off on their crates yet
+#[derive(Clone, ClapArgs, Debug)]
+struct Assignments {
+    /// Path to chain manifest
+    manifest_path: String,
+    /// Only list the assignments of this reviewer
+    #[clap(short, long)]
+    reviewer: Option<String>,
+}
+
+impl CommandRunner for Assignments {
+    fn run_command(self, _args: OuterArgs) -> Result<()> {
+        let chain = read_chain(&self.manifest_path)?;
+        let outstanding = chain
+            .outstanding_assignments()?
+            .into_iter()
+            .filter(|(_, r)| self.reviewer.as_deref().is_none_or(|x| x == *r))
+            .collect::<Vec<_>>();
+        if outstanding.is_empty() {
+            println!("No outstanding assignments");
+        }
+        for (crate_id, reviewer) in outstanding {
+            println!("  {}: {}", reviewer, crate_id);
+        }
+        Ok(())
+    }
+}
+
+/// Print the review status of each crate in the chain
+#[derive(Clone, ClapArgs, Debug)]
+struct Status {
+    /// Path to chain manifest
+    manifest_path: String,
+}
+
+impl CommandRunner for Status {
+    fn run_command(self, _args: OuterArgs) -> Result<()> {
+        let mut chain = read_chain(&self.manifest_path)?;
+        let mut crates = chain.all_crates().into_iter().cloned().collect::<Vec<_>>();
+        crates.sort_by(|c1, c2| {
+            (&c1.crate_name, &c1.version).cmp(&(&c2.crate_name, &c2.version))
+        });
+        let now = unix_time();
+        for crate_id in crates {
+            let status = chain.review_status(&crate_id)?;
+            let signed = chain.valid_sign_offs(&crate_id)?.len();
+            let audit_file = chain.read_audit_file(&crate_id)?;
+            let third_party = match &audit_file {
+                Some(AuditFile { third_party_review: Some(review), .. }) => {
+                    format!(", provisionally safe from a {} review", review.source)
+                }
+                _ => String::new(),
+            };
+            let certified = match &audit_file {
+                Some(a) if !a.criteria.is_empty() => {
+                    format!(", certified {}", a.criteria.join(", "))
+                }
+                _ => String::new(),
+            };
+            let advisories = chain
+                .advisories(&crate_id)
+                .iter()
+                .map(|a| a.id.as_str())
+                .collect::<Vec<_>>();
+            let advisories = if advisories.is_empty() {
+                String::new()
+            } else {
+                format!(" [{}]", advisories.join(", "))
+            };
+            let expired = match &audit_file {
+                Some(a) => a.expired_effects(now).len(),
+                None => 0,
+            };
+            let expired = if expired > 0 {
+                format!(", {} decisions expired", expired)
+            } else {
+                String::new()
+            };
+            let proc_macro =
+                if chain.is_proc_macro(&crate_id) { " (proc macro)" } else { "" };
+            println!(
+                "  {}{}: {} ({}/{} sign-offs{}{}{}){}",
+                crate_id,
+                proc_macro,
+                status,
+                signed,
+                chain.required_reviewers(),
+                third_party,
+                certified,
+                expired,
+                advisories
+            );
+        }
+        Ok(())
+    }
+}
+
+/// Print how much of each crate in the chain has been audited, and overall
+#[derive(Clone, ClapArgs, Debug)]
+struct Stats {
+    /// Path to chain manifest
+    manifest_path: String,
+}
+
+impl CommandRunner for Stats {
+    fn run_command(self, _args: OuterArgs) -> Result<()> {
+        let mut chain = read_chain(&self.manifest_path)?;
+        let mut crates = chain.all_crates().into_iter().cloned().collect::<Vec<_>>();
+        crates.sort_by(|c1, c2| {
+            (&c1.crate_name, &c1.version).cmp(&(&c2.crate_name, &c2.version))
+        });
+        let mut total = AuditStats::default();
+        for crate_id in crates {
+            match chain.read_audit_file(&crate_id)? {
+                Some(audit_file) => {
+                    let stats = audit_file.stats();
+                    println!("  {}: {}", crate_id, stats);
+                    total += &stats;
+                }
+                None => println!("  {}: no audit file", crate_id),
+            }
+        }
+        println!("Overall: {}", total);
+        Ok(())
+    }
+}
+
+/// Export the completed crate audits to a cargo-vet `audits.toml`
+#[derive(Clone, ClapArgs, Debug)]
+struct ExportVet {
+    /// Path to chain manifest
+    manifest_path: String,
+    /// The audits.toml file to add the audits to (created if it doesn't exist)
+    #[clap(short, long, default_value = "audits.toml")]
+    output: PathBuf,
+    /// The criteria to certify for crates whose effects are all safe
+    #[clap(long, default_value = SAFE_CRITERIA)]
+    safe_criteria: String,
+    /// The criteria to certify for crates with caller-checked public functions
+    #[clap(long, default_value = CALLER_CHECKED_CRITERIA)]
+    caller_checked_criteria: String,
+    /// Only export crates with enough reviewer sign-offs
+    #[clap(long, default_value_t = false)]
+    signed_off_only: bool,
+}
+
+impl CommandRunner for ExportVet {
+    fn run_command(self, _args: OuterArgs) -> Result<()> {
+        let mut chain = read_chain(&self.manifest_path)?;
+        let criteria = VetCriteria {
+            safe: self.safe_criteria,
+            caller_checked: self.caller_checked_criteria,
+        };
+        let audits = vet::chain_vet_audits(&mut chain, &criteria, self.signed_off_only)?;
+
+        let existing = if self.output.exists() {
+            Some(std::fs::read_to_string(&self.output)?)
+        } else {
+            None
+        };
+        let merged = vet::merge_audits_toml(existing.as_deref(), &audits, &criteria)?;
+        std::fs::write(&self.output, merged)?;
+        println!(
+            "Exported {} audits to {}",
+            audits.values().map(|a| a.len()).sum::<usize>(),
+            self.output.display()
+        );
+        Ok(())
+    }
+}
+
+/// Provisionally mark the effects of dependency crates with positive
+/// cargo-crev reviews as safe
+#[derive(Clone, ClapArgs, Debug)]
+struct ImportCrev {
+    /// Path to chain manifest
+    manifest_path: String,
+    /// Directory containing the crev proofs (defaults to where `cargo crev`
+    /// fetches them)
+    #[clap(long)]
+    proofs_dir: Option<PathBuf>,
+    /// Only consider reviews by these crev ids
+    #[clap(long, num_args = 1..)]
+    trusted_ids: Vec<String>,
+}
+
+impl CommandRunner for ImportCrev {
+    fn run_command(self, _args: OuterArgs) -> Result<()> {
+        let mut chain = read_chain(&self.manifest_path)?;
+        let proofs_dir = match self.proofs_dir {
+            Some(dir) => dir,
+            None => crev::default_proofs_dir()
+                .ok_or_else(|| anyhow!("Couldn't find the crev proofs directory"))?,
+        };
+        let reviews = crev::load_reviews(&proofs_dir)?;
+
+        let crates = chain.dependency_crates()?.into_iter().cloned().collect::<Vec<_>>();
+        for crate_id in crates {
+            let Some(crate_reviews) =
+                crev::positive_reviews(&reviews, &crate_id, &self.trusted_ids)
+            else {
+                continue;
+            };
+            let mut audit_file = chain
+                .read_audit_file(&crate_id)?
+                .ok_or_else(|| anyhow!("Couldn't find audit for crate {}", crate_id))?;
+            if audit_file.third_party_review.is_some() {
+                continue;
+            }
+
+            let review = ThirdPartyReview {
+                source: "crev".to_string(),
+                reviewers: crate_reviews.iter().map(|r| r.reviewer.clone()).collect(),
+                comments: crate_reviews
+                    .iter()
+                    .filter_map(|r| r.comment.clone())
+                    .collect(),
+            };
+            let removed_fns = audit_file.apply_third_party_review(review);
+            chain.save_audit_file(&crate_id, &audit_file)?;
+            if !removed_fns.is_empty() {
+                chain.remove_cross_crate_effects(removed_fns, &crate_id)?;
+            }
+            println!(
+                "Marked the effects of {} safe based on {} crev reviews",
+                crate_id,
+                crate_reviews.len()
+            );
+        }
+        Ok(chain.save_to_file()?)
+    }
+}
+
+/// Resolves the crates named on the command line, or returns all the
+/// dependency crates of the chain if none are
+fn target_crates(chain: &AuditChain, crate_names: &[String]) -> Result<Vec<CrateId>> {
+    if crate_names.is_empty() {
+        return Ok(chain.dependency_crates()?.into_iter().cloned().collect());
+    }
+    crate_names
+        .iter()
+        .map(|name| {
+            chain
+                .resolve_crate_id(name)
+                .context(format!("Couldn't resolve crate_name for {}", name))
+        })
+        .collect()
+}
+
+/// Replace the policies of crates in the chain with the ones published to a
+/// shared audit registry
+#[derive(Clone, ClapArgs, Debug)]
+struct Fetch {
+    /// Path to chain manifest
+    manifest_path: String,
+    /// Names of the crates to fetch policies for (defaults to all dependency
+    /// crates)
+    crate_names: Vec<String>,
+    /// URL of the registry (defaults to $CARGO_SCAN_REGISTRY)
+    #[clap(long)]
+    registry: Option<String>,
+    /// Also replace policies which have already been fully audited
+    #[clap(long, default_value_t = false)]
+    overwrite: bool,
+    /// Also accept policies signed with these keys, given as
+    /// `AUDITOR=PUBLIC_KEY`, in addition to those trusted in the project
+    /// config
+    #[clap(long, num_args = 1..)]
+    trusted_keys: Vec<String>,
+}
+
+impl CommandRunner for Fetch {
+    fn run_command(self, _args: OuterArgs) -> Result<()> {
+        let mut chain = read_chain(&self.manifest_path)?;
+        let client = RegistryClient::from_env(self.registry.as_deref())?;
+        let mut trust = ProjectConfig::load_or_find(None)?.trust;
+        for trusted_key in &self.trusted_keys {
+            let (auditor, key) = trusted_key.rsplit_once('=').ok_or_else(|| {
+                anyhow!(
+                    "Trusted keys must be given as AUDITOR=PUBLIC_KEY: {}",
+                    trusted_key
+                )
+            })?;
+            trust.keys.insert(auditor.to_string(), key.to_string());
+        }
+        let trusted_registry = trust.trusts_registry(client.url());
+
+        for crate_id in target_crates(&chain, &self.crate_names)? {
+            if !self.overwrite
+                && chain.review_status(&crate_id)? != ReviewStatus::Unaudited
+            {
+                continue;
+            }
+            let Some(fetched) = client.fetch_policy(&crate_id)? else {
+                continue;
+            };
+            let signature = match fetched.verify() {
+                Ok(signature) => signature,
+                Err(e) => {
+                    println!(
+                        "WARNING: Skipping the registry policy for {}, its signature \
+                        can't be verified: {}",
+                        crate_id, e
+                    );
+                    continue;
+                }
+            };
+            if !trusted_registry
+                && trust.trusted_signer(signature, &fetched.signed_bytes).is_none()
+            {
+                println!(
+                    "WARNING: Skipping the registry policy for {}, it was signed with \
+                    an untrusted key: {}",
+                    crate_id, signature.public_key
+                );
+                continue;
+            }
+            let fetched = fetched.audit_file;
+            let untrusted = if trusted_registry {
+                vec![]
+            } else {
+                trust.untrusted_auditors(&fetched.auditors())
+            };
+            if !untrusted.is_empty() {
+                println!(
+                    "WARNING: Skipping the registry policy for {}, it was audited by \
+                    untrusted auditors: {}",
+                    crate_id,
+                    untrusted.join(", ")
+                );
+                continue;
+            }
+            let local = chain
+                .read_audit_file(&crate_id)?
+                .ok_or_else(|| anyhow!("Couldn't find audit for crate {}", crate_id))?;
+            if fetched.hash != local.hash {
+                println!(
+                    "WARNING: Skipping the registry policy for {}, it was made for \
+                    different crate contents",
+                    crate_id
+                );
+                continue;
+            }
+            if fetched.scanned_effects != local.scanned_effects {
+                println!(
+                    "WARNING: Skipping the registry policy for {}, it was made for \
+                    different effect types",
+                    crate_id
+                );
+                continue;
+            }
+            // Parent policies only have the effects of the public functions
+            // which were caller-checked before, so we can't add new ones
+            let gained = AuditFile::pub_diff(&fetched, &local);
+            if !gained.is_empty() {
+                println!(
+                    "WARNING: Skipping the registry policy for {}, it marks {} more \
+                    public functions caller-checked",
+                    crate_id,
+                    gained.len()
+                );
+                continue;
+            }
+
+            let mut fetched = fetched.relocate(local.base_dir.clone())?;
+            // Bump the version so other chains know to recalculate their effects
+            fetched.version = local.version + 1;
+            chain.save_audit_file(&crate_id, &fetched)?;
+            let removed_fns = AuditFile::pub_diff(&local, &fetched);
+            if !removed_fns.is_empty() {
+                chain.remove_cross_crate_effects(removed_fns, &crate_id)?;
+            }
+            println!("Fetched the policy for {}", crate_id);
+        }
+        Ok(chain.save_to_file()?)
+    }
+}
+
+/// Publish the completed policies of crates in the chain to a shared audit
+/// registry
+#[derive(Clone, ClapArgs, Debug)]
+struct Publish {
+    /// Path to chain manifest
+    manifest_path: String,
+    /// Names of the crates to publish policies for (defaults to all dependency
+    /// crates)
+    crate_names: Vec<String>,
+    /// URL of the registry (defaults to $CARGO_SCAN_REGISTRY)
+    #[clap(long)]
+    registry: Option<String>,
+    /// Only publish crates with enough reviewer sign-offs
+    #[clap(long, default_value_t = false)]
+    signed_off_only: bool,
+}
+
+impl CommandRunner for Publish {
+    fn run_command(self, _args: OuterArgs) -> Result<()> {
+        let mut chain = read_chain(&self.manifest_path)?;
+        let client = RegistryClient::from_env(self.registry.as_deref())?;
+        let key = signing::signing_key()?;
+
+        for crate_id in target_crates(&chain, &self.crate_names)? {
+            match chain.review_status(&crate_id)? {
+                ReviewStatus::Unaudited => continue,
+                ReviewStatus::AwaitingSignOff if self.signed_off_only => continue,
+                _ => (),
+            }
+            let audit_file = chain
+                .read_audit_file(&crate_id)?
+                .ok_or_else(|| anyhow!("Couldn't find audit for crate {}", crate_id))?;
+            // Only share what was actually audited
+            if audit_file.third_party_review.is_some() {
+                continue;
+            }
+            let hash = client.publish_policy(&crate_id, &audit_file, &key)?;
+            println!("Published the policy for {} ({})", crate_id, hash);
+        }
+        Ok(())
+    }
+}
+
+/// Create a key to sign published policies and sign-offs with, and print its
+/// public key for others to trust
+#[derive(Clone, ClapArgs, Debug)]
+struct Keygen {
+    /// Where to save the key (defaults to $CARGO_SCAN_SIGNING_KEY, the
+    /// `cargo-scan.signingKey` git config, or ~/.cargo-scan/signing-key)
+    #[clap(long)]
+    output: Option<PathBuf>,
+}
+
+impl CommandRunner for Keygen {
+    fn run_command(self, _args: OuterArgs) -> Result<()> {
+        let path = self.output.or_else(signing::signing_key_path).ok_or_else(|| {
+            anyhow!("No default path for the signing key; pass --output")
+        })?;
+        let key = SigningKey::generate();
+        key.save(&path)?;
+        println!("Saved a new signing key to {}", path.display());
+        println!("Public key: {}", key.public_key());
+        Ok(())
+    }
+}
+
+/// Certify that a crate's completed audit meets the given criteria
+#[derive(Clone, ClapArgs, Debug)]
+struct Certify {
+    /// Path to chain manifest
+    manifest_path: String,
+    /// Name of the crate to certify
+    crate_name: String,
+    /// The criteria to certify, e.g. `safe-to-deploy`
+    #[clap(required = true)]
+    criteria: Vec<String>,
+    /// Remove the criteria from the crate instead
+    #[clap(long, default_value_t = false)]
+    revoke: bool,
+}
+
+impl CommandRunner for Certify {
+    fn run_command(self, _args: OuterArgs) -> Result<()> {
+        let mut chain = read_chain(&self.manifest_path)?;
+        let crate_id = chain
+            .resolve_crate_id(&self.crate_name)
+            .context(format!("Couldn't resolve crate_name for {}", &self.crate_name))?;
+        let mut audit_file = chain
+            .read_audit_file(&crate_id)?
+            .ok_or_else(|| anyhow!("Couldn't find audit for crate {}", crate_id))?;
+
+        if self.revoke {
+            audit_file.criteria.retain(|c| !self.criteria.contains(c));
+        } else {
+            if let Err(failure) = criteria::check_certifiable(&audit_file) {
+                return Err(anyhow!("Can't certify {}, it {}", crate_id, failure));
+            }
+            for c in self.criteria {
+                if !audit_file.criteria.contains(&c) {
+                    audit_file.criteria.push(c);
+                }
+            }
+        }
+        chain.save_audit_file(&crate_id, &audit_file)?;
+        println!(
+            "{} is certified as: {}",
+            crate_id,
+            if audit_file.criteria.is_empty() {
+                "nothing".to_string()
+            } else {
+                audit_file.criteria.join(", ")
+            }
+        );
+        Ok(chain.save_to_file()?)
+    }
+}
+
+/// Check that every crate in the chain meets the given criteria
+#[derive(Clone, ClapArgs, Debug)]
+struct Check {
+    /// Path to chain manifest
+    manifest_path: String,
+    /// The criteria the crates must meet, e.g. `safe-to-deploy`
+    #[clap(short, long)]
+    criteria: String,
+    /// Also require enough reviewer sign-offs on each crate
+    #[clap(long, default_value_t = false)]
+    signed_off_only: bool,
+}
+
+impl CommandRunner for Check {
+    fn run_command(self, _args: OuterArgs) -> Result<()> {
+        let mut chain = read_chain(&self.manifest_path)?;
+        let mut crates = chain.all_crates().into_iter().cloned().collect::<Vec<_>>();
+        crates.sort_by(|c1, c2| {
+            (&c1.crate_name, &c1.version).cmp(&(&c2.crate_name, &c2.version))
+        });
+
+        let mut failed = 0;
+        for crate_id in &crates {
+            let audit_file = chain
+                .read_audit_file(crate_id)?
+                .ok_or_else(|| anyhow!("Couldn't find audit for crate {}", crate_id))?;
+            let result = criteria::check_criteria(&audit_file, &self.criteria);
+            let missing_sign_offs = self.signed_off_only
+                && chain.review_status(crate_id)? == ReviewStatus::AwaitingSignOff;
+            match result {
+                Err(failure) => println!("  {}: {}", crate_id, failure),
+                Ok(()) if missing_sign_offs => {
+                    println!("  {}: doesn't have enough sign-offs", crate_id)
+                }
+                Ok(()) => continue,
+            }
+            failed += 1;
+        }
+
+        if failed > 0 {
+            Err(anyhow!(
+                "{} of {} crates don't meet the {} criteria",
+                failed,
+                crates.len(),
+                self.criteria
+            ))
+        } else {
+            println!("All {} crates meet the {} criteria", crates.len(), self.criteria);
+            Ok(())
+        }
+    }
+}
+
+/// The command line, with the defaults given by the project config. With a
+/// manifest in the config, the subcommands take the manifest path with
+/// `--manifest-path` instead of as their first argument, as it can't be
+/// left out before their other arguments otherwise.
+fn command_with_defaults(project: &ProjectConfig) -> ClapCommand {
+    let path_default = |path: &Option<PathBuf>| {
+        path.as_ref().map(|p| project.resolve_path(p).to_string_lossy().into_owned())
+    };
+    let manifest = path_default(&project.chain.manifest);
+    let audit_path = path_default(&project.chain.audit_path);
+    let crate_download_path = path_default(&project.chain.crate_download_path);
+
+    let mut cmd = Args::command();
+    if let Some(path) = crate_download_path {
+        cmd = cmd.mut_arg("crate_download_path", |a| a.default_value(path));
+    }
+    let subcommands =
+        cmd.get_subcommands().map(|c| c.get_name().to_string()).collect::<Vec<_>>();
+    for name in subcommands {
+        let is_check = name == "check";
+        cmd = cmd.mut_subcommand(name, |sub| {
+            sub.mut_args(|a| match (a.get_id().as_str(), &manifest, &audit_path) {
+                ("manifest_path", Some(manifest), _) => a
+                    .long("manifest-path")
+                    .required(false)
+                    .default_value(manifest.clone()),
+                ("audit_path", _, Some(audit_path)) => {
+                    a.default_value(audit_path.clone())
+                }
+                ("criteria", _, _) if is_check => match &project.audit.criteria {
+                    Some(criteria) => a.required(false).default_value(criteria.clone()),
+                    None => a,
+                },
+                _ => a,
+            })
+        });
+    }
+    cmd
+}
+
 fn main() {
     cargo_scan::util::init_logging();
-    let args = Args::parse();
+    let project = match ProjectConfig::load_or_find(None) {
+        Ok(project) => project,
+        Err(e) => {
+            println!("Error reading project config: {}", e);
+            std::process::exit(1);
+        }
+    };
+    let matches = command_with_defaults(&project).get_matches();
+    let args = Args::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+    set_auditor(args.outer_args.auditor.clone());
 
-    match args.command.run_command(args.outer_args) {
-        Ok(()) => (),
-        Err(e) => println!("Error running command: {}", e),
+    // Exit with an error, so that e.g. `chain check` can fail a CI job
+    if let Err(e) = args.command.run_command(args.outer_args) {
+        println!("Error running command: {}", e);
+        std::process::exit(1);
     }
 }
@@ -34,7 +34,8 @@ pub fn main() -> Result<()> {
     filepath.push(&args.file);
 
     let resolver = Resolver::new(&args.crate_path)?;
-    let file_resolver = ResolverImpl::new(&resolver, &filepath)?;
+    let snapshot = resolver.snapshot();
+    let file_resolver = ResolverImpl::new(&snapshot, &filepath)?;
 
     let s = SrcLoc::new(filepath.as_path(), args.line, args.col, args.line, args.col);
     let i = Ident::new(&args.name);
@@ -0,0 +1,42 @@
+//! Convert a report or audit file between JSON and the more compact CBOR.
+//!
+//! The direction is taken from the input: JSON is converted to CBOR and CBOR
+//! to JSON. Works for any of the JSON documents written by cargo-scan.
+
+use cargo_scan::cbor;
+
+use anyhow::{Context, Result};
+use clap::Parser;
+use std::fs;
+use std::path::PathBuf;
+
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+struct Args {
+    /// The JSON or CBOR file to convert
+    input: PathBuf,
+
+    /// Path to write the converted file to
+    output: PathBuf,
+}
+
+fn main() -> Result<()> {
+    let args = Args::parse();
+
+    let bytes = fs::read(&args.input)
+        .with_context(|| format!("Failed to read {:?}", args.input))?;
+    let (converted, format) = if cbor::is_cbor(&bytes) {
+        (cbor::cbor_to_json(bytes.as_slice())?.into_bytes(), "JSON")
+    } else {
+        (cbor::json_to_cbor(&bytes)?, "CBOR")
+    };
+    fs::write(&args.output, &converted)?;
+    println!(
+        "Wrote {} to {} ({} bytes, from {})",
+        format,
+        args.output.display(),
+        converted.len(),
+        bytes.len()
+    );
+    Ok(())
+}
@@ -0,0 +1,81 @@
+//! Run the scan server, which keeps crates loaded between requests (see
+//! `cargo_scan::server` for the JSON-RPC methods).
+//!
+//! Serves one JSON-RPC request per line on a Unix socket given with
+//! `--socket`, one client at a time, or else on stdin and stdout, e.g. for
+//! an editor extension running it as a subprocess.
+
+use cargo_scan::effect::DEFAULT_EFFECT_TYPES;
+use cargo_scan::server::Server;
+use cargo_scan::sink::load_sinks_file;
+
+use anyhow::Result;
+use clap::Parser;
+use std::io::{self, BufReader};
+use std::path::PathBuf;
+
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+struct Args {
+    /// Path of a Unix socket to listen on, instead of stdin and stdout
+    #[clap(long)]
+    socket: Option<PathBuf>,
+
+    /// TOML file of additional sink patterns to look for
+    #[clap(long)]
+    sinks_file: Option<PathBuf>,
+}
+
+#[cfg(unix)]
+fn serve_socket(server: &mut Server, socket: &PathBuf) -> Result<()> {
+    use log::warn;
+    use std::os::unix::fs::FileTypeExt;
+    use std::os::unix::net::UnixListener;
+
+    // Remove a socket left behind by an earlier server which didn't shut
+    // down, but never anything else which happens to be at the path
+    match std::fs::symlink_metadata(socket) {
+        Ok(meta) if meta.file_type().is_socket() => std::fs::remove_file(socket)?,
+        Ok(_) => anyhow::bail!("{} already exists and isn't a socket", socket.display()),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => (),
+        Err(e) => return Err(e.into()),
+    }
+    let listener = UnixListener::bind(socket)?;
+    eprintln!("Listening on {}", socket.display());
+    for stream in listener.incoming() {
+        match stream {
+            Ok(stream) => {
+                if let Err(e) = server.serve(BufReader::new(&stream), &stream) {
+                    warn!("Connection failed: {}", e);
+                }
+            }
+            Err(e) => warn!("Connection failed: {}", e),
+        }
+        if server.is_shut_down() {
+            break;
+        }
+    }
+    std::fs::remove_file(socket)?;
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn serve_socket(_server: &mut Server, _socket: &PathBuf) -> Result<()> {
+    anyhow::bail!("--socket is only supported on Unix; serve on stdin and stdout instead")
+}
+
+fn main() -> Result<()> {
+    cargo_scan::util::init_logging();
+    let args = Args::parse();
+
+    let sinks = match &args.sinks_file {
+        Some(path) => load_sinks_file(path)?,
+        None => Default::default(),
+    };
+    let mut server = Server::new(sinks, DEFAULT_EFFECT_TYPES);
+
+    match &args.socket {
+        Some(socket) => serve_socket(&mut server, socket),
+        None => Ok(server.serve(io::stdin().lock(), io::stdout().lock())?),
+    }
+}
@@ -1,6 +1,12 @@
 use std::{collections::HashSet, path::PathBuf};
 
-use cargo_scan::{audit_file::AuditFile, effect::EffectType};
+use cargo_scan::{
+    audit_file::{AuditFile, DefaultAuditConfig},
+    effect::EffectType,
+    ident::CanonicalPath,
+    scanner::ScanTargets,
+    sink::load_sinks_file,
+};
 
 use anyhow::{anyhow, Result};
 use clap::Parser;
@@ -27,6 +33,45 @@ struct Args {
     /// Run in quick mode (turns off RustAnalyzer)
     #[clap(long, default_value_t = false)]
     quick_mode: bool,
+
+    /// Keep effects in functions unreachable from the crate's public API
+    #[clap(long, default_value_t = false)]
+    include_dead_code: bool,
+
+    /// Maximum call-graph depth to propagate caller-checked annotations
+    #[clap(long)]
+    max_depth: Option<usize>,
+
+    /// Maximum number of functions to mark caller-checked for each effect
+    #[clap(long)]
+    max_marked_fns: Option<usize>,
+
+    /// Also scan the macro-expanded source to find effects inside macro
+    /// invocations (requires building the crate)
+    #[clap(long, default_value_t = false)]
+    expand_macros: bool,
+
+    /// TOML file of additional sink patterns to look for
+    #[clap(long)]
+    sinks_file: Option<PathBuf>,
+
+    /// Also scan the crate's build script, whose effects happen at compile
+    /// time
+    #[clap(long, default_value_t = false)]
+    include_build_script: bool,
+
+    /// Also scan the crate's integration tests, `#[cfg(test)]` code and
+    /// `#[test]` functions
+    #[clap(long, default_value_t = false, conflicts_with = "expand_macros")]
+    include_tests: bool,
+
+    /// Also scan the crate's examples
+    #[clap(long, default_value_t = false, conflicts_with = "expand_macros")]
+    include_examples: bool,
+
+    /// Also scan the crate's benchmarks
+    #[clap(long, default_value_t = false, conflicts_with = "expand_macros")]
+    include_benches: bool,
 }
 
 // TODO: Combine this with DefaultAuditType once we implement every version
@@ -44,17 +89,40 @@ fn runner(args: Args) -> Result<()> {
         return Err(anyhow!("Audit file already exists"));
     }
 
+    let config = DefaultAuditConfig {
+        include_dead_code: args.include_dead_code,
+        max_depth: args.max_depth,
+        max_marked_fns: args.max_marked_fns,
+        expand_macros: args.expand_macros,
+        include_build_script: args.include_build_script,
+        targets: ScanTargets {
+            tests: args.include_tests,
+            examples: args.include_examples,
+            benches: args.include_benches,
+        },
+        ..Default::default()
+    };
+    let sinks = match &args.sinks_file {
+        Some(sinks_file) => load_sinks_file(sinks_file)?
+            .iter()
+            .map(|s| CanonicalPath::new(s.as_str()))
+            .collect(),
+        None => HashSet::new(),
+    };
     let audit_file = match args.audit_type {
-        AuditType::CallerChecked => AuditFile::new_caller_checked_default(
+        AuditType::CallerChecked => AuditFile::new_caller_checked_default_with_sinks(
             &args.crate_path,
+            sinks,
             &EffectType::unsafe_effects(),
             args.quick_mode,
+            &config,
         )?,
         AuditType::Safe => AuditFile::new_safe_default_with_sinks(
             &args.crate_path,
-            HashSet::new(),
+            sinks,
             &EffectType::unsafe_effects(),
             args.quick_mode,
+            &config,
         )?,
     };
 
@@ -0,0 +1,99 @@
+//! Scan the last few published versions of a crate.
+//!
+//! Prints a timeline of the effect counts of each kind (in CSV format),
+//! followed by any sudden jumps between versions. A version which suddenly
+//! adds e.g. process execution is a common sign of a supply-chain attack.
+
+use cargo_scan::download_crate;
+use cargo_scan::scan_stats::{self, effect_jumps, effect_kind_counts};
+
+use anyhow::Result;
+use clap::Parser;
+use log::warn;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::{create_dir_all, remove_dir_all};
+
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+struct Args {
+    /// Name of the crate on crates.io
+    crate_name: String,
+
+    /// Number of the most recent versions to scan
+    #[clap(short = 'n', long, default_value_t = 5)]
+    versions: usize,
+
+    /// Path to download crates to for scanning
+    #[clap(short = 'd', long = "crate-download-path", default_value = ".history_tmp")]
+    crate_download_path: String,
+
+    /// Report effect kinds whose count grows by at least this factor between
+    /// two versions
+    #[clap(long, default_value_t = 2.0)]
+    jump_factor: f64,
+
+    // Run in quick mode (turns off RustAnalyzer)
+    #[clap(short, long, default_value_t = false)]
+    quick_mode: bool,
+}
+
+fn main() -> Result<()> {
+    cargo_scan::util::init_logging();
+    let args = Args::parse();
+
+    let versions = download_crate::get_crate_versions(&args.crate_name, args.versions)?;
+    create_dir_all(&args.crate_download_path)?;
+
+    let mut timeline = Vec::new();
+    for version in versions {
+        let version = version.to_string();
+        let crate_path = match download_crate::download_crate_from_info(
+            &args.crate_name,
+            &version,
+            &args.crate_download_path,
+        ) {
+            Ok(p) => p,
+            Err(e) => {
+                warn!("Couldn't download {} v{}: {}", args.crate_name, version, e);
+                continue;
+            }
+        };
+        let stats =
+            scan_stats::get_crate_stats_default(crate_path.clone(), args.quick_mode);
+        timeline.push((version, effect_kind_counts(&stats.effects)));
+        remove_dir_all(crate_path)?;
+    }
+
+    print_timeline(&timeline);
+
+    println!();
+    let jumps = effect_jumps(&timeline, args.jump_factor);
+    if jumps.is_empty() {
+        println!("No sudden changes in effects between versions");
+    }
+    for jump in jumps {
+        println!(
+            "Sudden change in v{}: {} went from {} to {}",
+            jump.version, jump.kind, jump.before, jump.after
+        );
+    }
+
+    Ok(())
+}
+
+fn print_timeline(timeline: &[(String, BTreeMap<String, usize>)]) {
+    let kinds =
+        timeline.iter().flat_map(|(_, counts)| counts.keys()).collect::<BTreeSet<_>>();
+
+    let mut header = vec!["version", "total"];
+    header.extend(kinds.iter().map(|k| k.as_str()));
+    println!("{}", header.join(", "));
+    for (version, counts) in timeline {
+        let total = counts.values().sum::<usize>();
+        let mut row = vec![version.clone(), total.to_string()];
+        row.extend(
+            kinds.iter().map(|k| counts.get(*k).copied().unwrap_or(0).to_string()),
+        );
+        println!("{}", row.join(", "));
+    }
+}
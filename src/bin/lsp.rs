@@ -0,0 +1,340 @@
+//! A language server showing the effects of the open Rust files as
+//! diagnostics.
+//!
+//! Editors run it as a subprocess, talking the Language Server Protocol on
+//! stdin and stdout. Each file is scanned when it's opened and saved, with its
+//! crate kept loaded between scans (see `LoadedCrate`). Quick fixes insert a
+//! suppression comment for an effect, or show the effect's entry in the
+//! crate's audit file, if there is one.
+
+use cargo_scan::effect::DEFAULT_EFFECT_TYPES;
+use cargo_scan::ident::IdentPath;
+use cargo_scan::report::{effect_reports, LSP_SOURCE};
+use cargo_scan::scanner::LoadedCrate;
+use cargo_scan::sink::load_sinks_file;
+use cargo_scan::util::{find_crate_root, load_cargo_toml};
+
+use anyhow::{anyhow, Result};
+use clap::Parser;
+use home::home_dir;
+use log::{info, warn};
+use lsp_server::{Connection, Message, Notification, Request, RequestId, Response};
+use lsp_types::notification::{
+    DidChangeTextDocument, DidCloseTextDocument, DidOpenTextDocument,
+    DidSaveTextDocument, Notification as _, PublishDiagnostics,
+};
+use lsp_types::request::{CodeActionRequest, ExecuteCommand, Request as _, ShowDocument};
+use lsp_types::{
+    CodeAction, CodeActionKind, CodeActionOrCommand, CodeActionParams,
+    CodeActionProviderCapability, Command, Diagnostic, ExecuteCommandOptions,
+    ExecuteCommandParams, Position, PublishDiagnosticsParams, Range, ServerCapabilities,
+    ShowDocumentParams, TextDocumentSyncCapability, TextDocumentSyncKind,
+    TextDocumentSyncOptions, TextDocumentSyncSaveOptions, TextEdit, Url, WorkspaceEdit,
+};
+use serde::de::DeserializeOwned;
+use serde_json::{json, Value};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// The command showing an effect's entry in its audit file
+const SHOW_POLICY_COMMAND: &str = "cargo-scan.showPolicy";
+
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+struct Args {
+    /// Directory of the audit files to show effects in, named
+    /// `<crate>-<version>.audit` as by `audit`. Defaults to ~/.cargo_audits
+    #[clap(long)]
+    audit_dir: Option<PathBuf>,
+
+    /// TOML file of additional sink patterns to look for
+    #[clap(long)]
+    sinks_file: Option<PathBuf>,
+}
+
+struct State {
+    /// The loaded crates, by their root
+    crates: HashMap<PathBuf, LoadedCrate>,
+    /// The contents of the open files, as last sent by the editor
+    documents: HashMap<Url, String>,
+    sinks: HashSet<IdentPath>,
+    audit_dir: Option<PathBuf>,
+    next_request_id: i32,
+}
+
+fn params<T: DeserializeOwned>(params: Value) -> Result<T> {
+    Ok(serde_json::from_value(params)?)
+}
+
+impl State {
+    /// The diagnostics of the effects in the file, as saved
+    fn diagnostics(&mut self, path: &Path) -> Result<Vec<Diagnostic>> {
+        let dir = path.parent().ok_or_else(|| anyhow!("No crate for {:?}", path))?;
+        let crate_root = find_crate_root(dir)?;
+        if !self.crates.contains_key(&crate_root) {
+            info!("Loading crate {:?}", crate_root);
+            self.crates.insert(crate_root.clone(), LoadedCrate::load(&crate_root)?);
+        }
+        let loaded = self.crates.get_mut(&crate_root).expect("the crate is loaded");
+        let results = loaded.scan_file(path, self.sinks.clone(), DEFAULT_EFFECT_TYPES)?;
+        Ok(effect_reports(&crate_root, results)
+            .iter()
+            .map(|e| e.to_lsp_diagnostic())
+            .collect())
+    }
+
+    /// Scans the file and publishes its effects. Files outside of a crate, or
+    /// which fail to scan, get no diagnostics.
+    fn publish_diagnostics(&mut self, connection: &Connection, uri: Url) -> Result<()> {
+        let diagnostics = match uri.to_file_path() {
+            Ok(path) => self.diagnostics(&path).unwrap_or_else(|e| {
+                warn!("Failed to scan {}: {}", uri, e);
+                Vec::new()
+            }),
+            Err(()) => Vec::new(),
+        };
+        let params = PublishDiagnosticsParams::new(uri, diagnostics, None);
+        let notification = Notification::new(PublishDiagnostics::METHOD.into(), params);
+        connection.sender.send(notification.into())?;
+        Ok(())
+    }
+
+    fn handle_notification(
+        &mut self,
+        connection: &Connection,
+        notification: Notification,
+    ) -> Result<()> {
+        match notification.method.as_str() {
+            DidOpenTextDocument::METHOD => {
+                let p: lsp_types::DidOpenTextDocumentParams =
+                    params(notification.params)?;
+                let uri = p.text_document.uri;
+                self.documents.insert(uri.clone(), p.text_document.text);
+                self.publish_diagnostics(connection, uri)?;
+            }
+            DidChangeTextDocument::METHOD => {
+                // Files are only scanned again once saved, since the scanner
+                // reads them from disk
+                let p: lsp_types::DidChangeTextDocumentParams =
+                    params(notification.params)?;
+                if let Some(change) = p.content_changes.into_iter().last() {
+                    self.documents.insert(p.text_document.uri, change.text);
+                }
+            }
+            DidSaveTextDocument::METHOD => {
+                let p: lsp_types::DidSaveTextDocumentParams =
+                    params(notification.params)?;
+                self.publish_diagnostics(connection, p.text_document.uri)?;
+            }
+            DidCloseTextDocument::METHOD => {
+                let p: lsp_types::DidCloseTextDocumentParams =
+                    params(notification.params)?;
+                self.documents.remove(&p.text_document.uri);
+                let params =
+                    PublishDiagnosticsParams::new(p.text_document.uri, vec![], None);
+                let notification =
+                    Notification::new(PublishDiagnostics::METHOD.into(), params);
+                connection.sender.send(notification.into())?;
+            }
+            _ => (),
+        }
+        Ok(())
+    }
+
+    fn handle_request(&mut self, connection: &Connection, request: Request) -> Response {
+        let id = request.id.clone();
+        let result = match request.method.as_str() {
+            CodeActionRequest::METHOD => {
+                params(request.params).map(|p| json!(self.code_actions(p)))
+            }
+            ExecuteCommand::METHOD => params(request.params)
+                .and_then(|p| self.execute_command(connection, p))
+                .map(|()| Value::Null),
+            _ => {
+                return Response::new_err(
+                    id,
+                    lsp_server::ErrorCode::MethodNotFound as i32,
+                    format!("Unknown method: {}", request.method),
+                )
+            }
+        };
+        match result {
+            Ok(result) => Response::new_ok(id, result),
+            Err(e) => Response::new_err(
+                id,
+                lsp_server::ErrorCode::RequestFailed as i32,
+                e.to_string(),
+            ),
+        }
+    }
+
+    /// The audit file of the crate containing the file, if there is one
+    fn audit_file(&self, path: &Path) -> Option<PathBuf> {
+        let crate_root = find_crate_root(path.parent()?).ok()?;
+        let crate_id = load_cargo_toml(&crate_root).ok()?;
+        let audit_dir = match &self.audit_dir {
+            Some(dir) => dir.clone(),
+            None => home_dir()?.join(".cargo_audits"),
+        };
+        let audit_file = audit_dir.join(format!("{}.audit", crate_id));
+        audit_file.is_file().then_some(audit_file)
+    }
+
+    /// Quick fixes for the cargo-scan diagnostics in the range: a suppression
+    /// comment, and showing the effect in the audit file
+    fn code_actions(&self, p: CodeActionParams) -> Vec<CodeActionOrCommand> {
+        let uri = p.text_document.uri;
+        let text = self.documents.get(&uri);
+        let audit_file = uri.to_file_path().ok().and_then(|path| self.audit_file(&path));
+        let mut actions = Vec::new();
+        for diagnostic in p.context.diagnostics {
+            if diagnostic.source.as_deref() != Some(LSP_SOURCE) {
+                continue;
+            }
+            let Some(lsp_types::NumberOrString::String(effect_type)) = &diagnostic.code
+            else {
+                continue;
+            };
+            // Indent the comment like the line it suppresses the effect on
+            let line = diagnostic.range.start.line;
+            let indent = text
+                .and_then(|t| t.lines().nth(line as usize))
+                .map(|l| l[..l.len() - l.trim_start().len()].to_string())
+                .unwrap_or_default();
+            let start = Position::new(line, 0);
+            let edit = TextEdit::new(
+                Range::new(start, start),
+                format!("{}// cargo-scan: allow({})\n", indent, effect_type),
+            );
+            actions.push(CodeActionOrCommand::CodeAction(CodeAction {
+                title: format!("Suppress {} with a cargo-scan comment", effect_type),
+                kind: Some(CodeActionKind::QUICKFIX),
+                diagnostics: Some(vec![diagnostic.clone()]),
+                edit: Some(WorkspaceEdit::new(HashMap::from([(
+                    uri.clone(),
+                    vec![edit],
+                )]))),
+                ..Default::default()
+            }));
+
+            let caller = diagnostic.data.as_ref().and_then(|d| d["caller"].as_str());
+            if let (Some(audit_file), Some(caller)) = (&audit_file, caller) {
+                actions.push(CodeActionOrCommand::CodeAction(CodeAction {
+                    title: "Show the effect in the audit file".to_string(),
+                    kind: Some(CodeActionKind::QUICKFIX),
+                    diagnostics: Some(vec![diagnostic.clone()]),
+                    command: Some(Command::new(
+                        "Show the effect in the audit file".to_string(),
+                        SHOW_POLICY_COMMAND.to_string(),
+                        Some(vec![json!(audit_file), json!(caller)]),
+                    )),
+                    ..Default::default()
+                }));
+            }
+        }
+        actions
+    }
+
+    /// Asks the editor to show the audit file, at the first effect in the
+    /// caller
+    fn execute_command(
+        &mut self,
+        connection: &Connection,
+        p: ExecuteCommandParams,
+    ) -> Result<()> {
+        if p.command != SHOW_POLICY_COMMAND {
+            return Err(anyhow!("Unknown command: {}", p.command));
+        }
+        let (audit_file, caller): (PathBuf, String) = match p.arguments.as_slice() {
+            [audit_file, caller] => {
+                (params(audit_file.clone())?, params(caller.clone())?)
+            }
+            _ => return Err(anyhow!("Expected an audit file and a caller")),
+        };
+        let text = fs::read_to_string(&audit_file)?;
+        let entry = format!("\"caller\":{{\"ident_path\":\"{}\"", caller);
+        let position = text.find(&entry).map_or(Position::new(0, 0), |offset| {
+            let before = &text[..offset];
+            let line = before.matches('\n').count();
+            let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+            // LSP columns count UTF-16 code units
+            let col = before[line_start..].encode_utf16().count();
+            Position::new(line as u32, col as u32)
+        });
+        let uri = Url::from_file_path(audit_file.canonicalize()?)
+            .map_err(|()| anyhow!("Invalid audit file path: {:?}", audit_file))?;
+        let params = ShowDocumentParams {
+            uri,
+            external: Some(false),
+            take_focus: Some(true),
+            selection: Some(Range::new(position, position)),
+        };
+        self.next_request_id += 1;
+        let request = Request::new(
+            RequestId::from(self.next_request_id),
+            ShowDocument::METHOD.into(),
+            params,
+        );
+        connection.sender.send(request.into())?;
+        Ok(())
+    }
+}
+
+fn main() -> Result<()> {
+    cargo_scan::util::init_logging();
+    let args = Args::parse();
+    let sinks = match &args.sinks_file {
+        Some(path) => load_sinks_file(path)?,
+        None => HashSet::new(),
+    };
+
+    let (connection, io_threads) = Connection::stdio();
+    let capabilities = ServerCapabilities {
+        text_document_sync: Some(TextDocumentSyncCapability::Options(
+            TextDocumentSyncOptions {
+                open_close: Some(true),
+                change: Some(TextDocumentSyncKind::FULL),
+                save: Some(TextDocumentSyncSaveOptions::Supported(true)),
+                ..Default::default()
+            },
+        )),
+        code_action_provider: Some(CodeActionProviderCapability::Simple(true)),
+        execute_command_provider: Some(ExecuteCommandOptions {
+            commands: vec![SHOW_POLICY_COMMAND.to_string()],
+            ..Default::default()
+        }),
+        ..Default::default()
+    };
+    connection.initialize(serde_json::to_value(capabilities)?)?;
+
+    let mut state = State {
+        crates: HashMap::new(),
+        documents: HashMap::new(),
+        sinks,
+        audit_dir: args.audit_dir,
+        next_request_id: 0,
+    };
+    for message in &connection.receiver {
+        match message {
+            Message::Request(request) => {
+                if connection.handle_shutdown(&request)? {
+                    break;
+                }
+                let response = state.handle_request(&connection, request);
+                connection.sender.send(response.into())?;
+            }
+            Message::Notification(notification) => {
+                if let Err(e) = state.handle_notification(&connection, notification) {
+                    warn!("Failed to handle notification: {}", e);
+                }
+            }
+            // The responses to showing documents
+            Message::Response(_) => (),
+        }
+    }
+    // The writer thread only stops once the connection is dropped
+    drop(connection);
+    io_threads.join()?;
+    Ok(())
+}
@@ -0,0 +1,425 @@
+//! Change the decisions recorded in a crate's policy (audit file) without
+//! running the audit again.
+//!
+//! Effects are given by their ID, as listed by `policy list`, or by a unique
+//! prefix of it, or in bulk by a glob pattern over Rust paths. Each change is kept in the policy's decision history, so that
+//! it can be undone with `policy undo`. `policy lint` finds the effects which
+//! no longer match the crate's source.
+
+use cargo_scan::audit_file::{self, AuditFile, DecisionInfo, SafetyAnnotation};
+use cargo_scan::effect::EffectInstance;
+use cargo_scan::filter::PathGlob;
+use cargo_scan::report::EffectReport;
+use cargo_scan::scanner::{self, ScanResults};
+use cargo_scan::util::{find_crate_root, load_cargo_toml};
+
+use anyhow::{anyhow, Context, Result};
+use clap::{Parser, Subcommand, ValueEnum};
+use home::home_dir;
+use std::env;
+use std::path::PathBuf;
+use std::time::Duration;
+
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+struct Args {
+    /// Path to the audit file. Defaults to the audit of the crate in the
+    /// current directory in ~/.cargo_audits, as made by `audit`
+    #[clap(short, long, global = true)]
+    audit_file: Option<PathBuf>,
+
+    /// Identity of the auditor to record with the decisions, e.g. a name and
+    /// email address or a key fingerprint. Defaults to $CARGO_SCAN_AUDITOR,
+    /// then the `cargo-scan.auditor` git config, then the git user name and
+    /// email, then the current user
+    #[clap(long, global = true)]
+    auditor: Option<String>,
+
+    #[clap(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Change the decision recorded for an effect. Decisions on the effect's
+    /// callers are dropped along with it.
+    Revise {
+        /// ID of the effect, or a unique prefix of it
+        effect_id: String,
+        /// The new decision
+        decision: Decision,
+        /// Comment on the new decision, replacing any earlier one
+        #[clap(long)]
+        comment: Option<String>,
+        /// Run in quick mode (turns off RustAnalyzer) when finding the callers
+        /// of a caller-checked effect
+        #[clap(short, long, default_value_t = false)]
+        quick_mode: bool,
+    },
+    /// Make the same decision for every unaudited location where the effect's
+    /// callee, or the function containing the location or its module,
+    /// matches a pattern, e.g. `windows_sys::**`
+    Mark {
+        /// Glob pattern over Rust paths, where `*` matches within a path
+        /// segment and `**` matches any number of segments
+        pattern: PathGlob,
+        /// The decision for the matching locations
+        decision: Decision,
+        /// Run in quick mode (turns off RustAnalyzer) when finding the callers
+        /// of caller-checked effects
+        #[clap(short, long, default_value_t = false)]
+        quick_mode: bool,
+    },
+    /// Undo the most recent decision, from an audit, a revision or a mark
+    Undo,
+    /// List the effects in the audit, with their IDs and decisions
+    List,
+    /// List the recent decisions, most recent first
+    History,
+    /// List the effects in the policy which no longer match the crate's
+    /// source, e.g. since the function containing them was removed
+    Lint {
+        /// Remove the stale effects from the policy, along with their
+        /// decisions
+        #[clap(long, default_value_t = false)]
+        prune: bool,
+        /// Run in quick mode (turns off RustAnalyzer) when scanning the crate
+        #[clap(short, long, default_value_t = false)]
+        quick_mode: bool,
+    },
+    /// Set when decisions have to be audited again, for an effect or for the
+    /// whole policy. `chain audit` audits expired decisions again.
+    Expire {
+        /// ID of the effect, or a unique prefix of it. Defaults to the whole
+        /// policy.
+        effect_id: Option<String>,
+        /// When the decisions expire, as a date (e.g. `2025-06-30`) or an RFC
+        /// 3339 time. For the whole policy, only decisions made before then
+        /// expire.
+        #[clap(long, value_parser = parse_at)]
+        at: Option<u64>,
+        /// For the whole policy, how old decisions can get before they expire,
+        /// e.g. `180days`
+        #[clap(long, conflicts_with = "effect_id")]
+        max_age: Option<humantime::Duration>,
+        /// Remove the expiration instead
+        #[clap(long, default_value_t = false, conflicts_with_all = ["at", "max_age"])]
+        clear: bool,
+    },
+    /// Show how much of the policy has been audited, by decision, with an
+    /// estimate of the time left
+    Stats,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum Decision {
+    Safe,
+    Unsafe,
+    CallerChecked,
+}
+
+impl From<Decision> for SafetyAnnotation {
+    fn from(d: Decision) -> Self {
+        match d {
+            Decision::Safe => SafetyAnnotation::Safe,
+            Decision::Unsafe => SafetyAnnotation::Unsafe,
+            Decision::CallerChecked => SafetyAnnotation::CallerChecked,
+        }
+    }
+}
+
+fn parse_at(s: &str) -> std::result::Result<u64, String> {
+    audit_file::parse_time(s).map_err(|e| e.to_string())
+}
+
+fn effect_id(effect: &EffectInstance) -> String {
+    EffectReport::from(effect).id()
+}
+
+/// The effect in the audit with the ID, or a unique prefix of it
+fn find_effect(audit_file: &AuditFile, id: &str) -> Result<EffectInstance> {
+    let mut matches =
+        audit_file.audit_trees.keys().filter(|e| effect_id(e).starts_with(id));
+    let effect = matches.next().ok_or_else(|| anyhow!("No effect with ID {}", id))?;
+    if matches.next().is_some() {
+        return Err(anyhow!("More than one effect has an ID starting with {}", id));
+    }
+    Ok(effect.clone())
+}
+
+/// Scans the audited crate if the decision is caller-checked, to find the
+/// callers to audit next
+fn scan_for_callers(
+    audit_file: &AuditFile,
+    annotation: SafetyAnnotation,
+    quick_mode: bool,
+) -> Result<Option<ScanResults>> {
+    if annotation != SafetyAnnotation::CallerChecked {
+        return Ok(None);
+    }
+    println!("Scanning crate for the callers of the effects...");
+    Ok(Some(scanner::scan_crate(
+        &audit_file.base_dir,
+        &audit_file.scanned_effects,
+        quick_mode,
+    )?))
+}
+
+fn refresh_pub_caller_checked(
+    audit_file: &mut AuditFile,
+    scan_res: Option<&ScanResults>,
+) {
+    match scan_res {
+        Some(scan_res) => audit_file.recalc_pub_caller_checked(&scan_res.pub_fns),
+        None => audit_file.refresh_pub_caller_checked(),
+    }
+}
+
+fn print_unaudited(audit_file: &AuditFile) {
+    let (_, unaudited) = audit_file.unaudited_effects();
+    if unaudited > 0 {
+        println!("{} locations are left to audit", unaudited);
+    }
+}
+
+fn default_audit_file() -> Result<PathBuf> {
+    let crate_root = find_crate_root(&env::current_dir()?)?;
+    let crate_id = load_cargo_toml(&crate_root)?;
+    let home = home_dir().context("Couldn't find the home directory")?;
+    Ok(home.join(".cargo_audits").join(format!("{}.audit", crate_id)))
+}
+
+fn main() -> Result<()> {
+    cargo_scan::util::init_logging();
+    let args = Args::parse();
+    audit_file::set_auditor(args.auditor);
+
+    let audit_file_path = match args.audit_file {
+        Some(path) => path,
+        None => default_audit_file()?,
+    };
+    let mut audit_file = AuditFile::read_audit_file(audit_file_path.clone())?
+        .ok_or_else(|| anyhow!("No audit file at {}", audit_file_path.display()))?;
+
+    match args.command {
+        Command::Revise { effect_id, decision, comment, quick_mode } => {
+            let effect = find_effect(&audit_file, &effect_id)?;
+            let annotation = SafetyAnnotation::from(decision);
+            let scan_res = scan_for_callers(&audit_file, annotation, quick_mode)?;
+            let prev =
+                audit_file.revise_decision(&effect, annotation, scan_res.as_ref())?;
+            if let Some(comment) = comment {
+                audit_file.comments.insert(effect.clone(), comment);
+            }
+            refresh_pub_caller_checked(&mut audit_file, scan_res.as_ref());
+            println!(
+                "Revised {} at {} from {} to {}",
+                effect.caller_path(),
+                effect.call_loc(),
+                prev,
+                annotation
+            );
+            print_unaudited(&audit_file);
+        }
+        Command::Mark { pattern, decision, quick_mode } => {
+            let annotation = SafetyAnnotation::from(decision);
+            let scan_res = scan_for_callers(&audit_file, annotation, quick_mode)?;
+            let marked =
+                audit_file.mark_matching(&pattern, annotation, scan_res.as_ref())?;
+            if marked == 0 {
+                println!("No unaudited locations match {}", pattern);
+                return Ok(());
+            }
+            refresh_pub_caller_checked(&mut audit_file, scan_res.as_ref());
+            println!(
+                "Marked {} locations matching {} as {}",
+                marked, pattern, annotation
+            );
+            print_unaudited(&audit_file);
+        }
+        Command::Undo => match audit_file.undo_last_decision()? {
+            Some(d) => {
+                audit_file.refresh_pub_caller_checked();
+                println!("Undid marking {} as {}", d, d.annotation);
+            }
+            None => {
+                println!("No decisions to undo");
+                return Ok(());
+            }
+        },
+        Command::List => {
+            let now = audit_file::unix_time();
+            let mut effects = audit_file.audit_trees.iter().collect::<Vec<_>>();
+            effects.sort_by_key(|(e, _)| e.call_loc().to_string());
+            for (e, t) in effects {
+                let decided = match audit_file.decision_info.get(e) {
+                    Some(info) => format!(
+                        " (by {} at {}, {} spent)",
+                        info.auditor.as_deref().unwrap_or("unknown"),
+                        audit_file::format_time(info.decided_at),
+                        humantime::format_duration(Duration::from_secs(info.time_spent))
+                    ),
+                    None => String::new(),
+                };
+                let expired =
+                    if audit_file.is_expired(e, now) { " [expired]" } else { "" };
+                println!(
+                    "{}  {} at {}: {}{}{}",
+                    &effect_id(e)[..12],
+                    e.caller_path(),
+                    e.call_loc(),
+                    t.summary_annotation(),
+                    decided,
+                    expired
+                );
+            }
+            return Ok(());
+        }
+        Command::Lint { prune, quick_mode } => {
+            println!("Scanning crate for the effects in the policy...");
+            let scan_res = scanner::scan_crate(
+                &audit_file.base_dir,
+                &audit_file.scanned_effects,
+                quick_mode,
+            )?;
+            let stale = audit_file.stale_effects(&scan_res);
+            if stale.is_empty() {
+                println!("No stale effects");
+                return Ok(());
+            }
+            for (e, staleness) in &stale {
+                println!(
+                    "{}  {} at {}: {} (stale: {})",
+                    &effect_id(e)[..12],
+                    e.caller_path(),
+                    e.call_loc(),
+                    audit_file.audit_trees[*e].summary_annotation(),
+                    staleness
+                );
+            }
+            if !prune {
+                println!("{} stale effects; pass --prune to remove them", stale.len());
+                return Ok(());
+            }
+            let stale = stale.into_iter().map(|(e, _)| e.clone()).collect();
+            audit_file.prune_effects(&stale);
+            println!("Removed {} stale effects", stale.len());
+        }
+        Command::Expire { effect_id: Some(id), at, clear, .. } => {
+            let effect = find_effect(&audit_file, &id)?;
+            if !audit_file.is_audited(&effect) {
+                return Err(anyhow!("{} hasn't been audited yet", id));
+            }
+            if at.is_none() && !clear {
+                return Err(anyhow!("Pass --at or --clear"));
+            }
+            // Decisions made before their time was recorded are taken to be
+            // as old as can be (see `AuditFile::is_expired`)
+            let info =
+                audit_file.decision_info.entry(effect.clone()).or_insert(DecisionInfo {
+                    decided_at: 0,
+                    auditor: None,
+                    time_spent: 0,
+                    expires_at: None,
+                });
+            info.expires_at = at;
+            match at {
+                Some(at) => println!(
+                    "The decision on {} at {} expires at {}",
+                    effect.caller_path(),
+                    effect.call_loc(),
+                    audit_file::format_time(at)
+                ),
+                None => println!(
+                    "The decision on {} at {} no longer expires",
+                    effect.caller_path(),
+                    effect.call_loc()
+                ),
+            }
+        }
+        Command::Expire { effect_id: None, at, max_age, clear } => {
+            if clear {
+                audit_file.expires_at = None;
+                audit_file.max_age = None;
+                println!("The policy no longer expires");
+            } else if at.is_none() && max_age.is_none() {
+                return Err(anyhow!("Pass --at, --max-age or --clear"));
+            }
+            if let Some(at) = at {
+                audit_file.expires_at = Some(at);
+                println!(
+                    "Decisions made before {} expire then",
+                    audit_file::format_time(at)
+                );
+            }
+            if let Some(max_age) = max_age {
+                audit_file.max_age = Some(max_age.as_secs());
+                println!("Decisions expire once they are {} old", max_age);
+            }
+            let expired = audit_file.expired_effects(audit_file::unix_time()).len();
+            if expired > 0 {
+                println!("{} decisions have expired already", expired);
+            }
+        }
+        Command::Stats => {
+            let stats = audit_file.stats();
+            println!(
+                "Effects: {} ({} audited, {} unaudited, {:.0}% coverage)",
+                stats.effects,
+                stats.audited,
+                stats.unaudited(),
+                stats.coverage()
+            );
+            println!(
+                "Locations: {} ({} left to audit)",
+                stats.locations, stats.unaudited_locations
+            );
+            println!(
+                "Decisions: {} safe, {} unsafe, {} caller-checked",
+                stats.safe, stats.unsafe_effects, stats.caller_checked
+            );
+            if stats.timed > 0 {
+                println!(
+                    "Time spent: {} over {} effects",
+                    humantime::format_duration(Duration::from_secs(stats.time_spent)),
+                    stats.timed
+                );
+            }
+            match stats.estimated_remaining() {
+                Some(remaining) if stats.unaudited() > 0 => println!(
+                    "Estimated time left: {}",
+                    humantime::format_duration(remaining)
+                ),
+                _ => (),
+            }
+            return Ok(());
+        }
+        Command::History => {
+            for d in audit_file.decision_history.iter().rev() {
+                if d.pattern.is_some() {
+                    let effects = d.others.len() + 1;
+                    println!(
+                        "{:12}  {}: {} ({} {})",
+                        "",
+                        d,
+                        d.annotation,
+                        effects,
+                        if effects == 1 { "effect" } else { "effects" }
+                    );
+                } else {
+                    println!(
+                        "{}  {}: {} (was {})",
+                        &effect_id(&d.effect)[..12],
+                        d,
+                        d.annotation,
+                        d.previous.summary_annotation()
+                    );
+                }
+            }
+            return Ok(());
+        }
+    }
+
+    audit_file.save_to_file(audit_file_path)?;
+    Ok(())
+}
@@ -0,0 +1,56 @@
+//! Render the scan of a crate as a standalone HTML page.
+//!
+//! If an audit file is provided, the page also shows the audit status of
+//! each effect.
+
+use cargo_scan::audit_file::AuditFile;
+use cargo_scan::report::Report;
+use cargo_scan::scan_stats;
+
+use anyhow::{anyhow, Result};
+use clap::Parser;
+use std::fs;
+use std::path::PathBuf;
+
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+struct Args {
+    /// Path to crate directory; should contain a 'src' directory and a Cargo.toml file
+    crate_path: PathBuf,
+
+    /// Path to the crate's audit file, to include the audit status of effects
+    #[clap(short, long)]
+    audit_file: Option<PathBuf>,
+
+    /// Path to write the HTML page to
+    #[clap(short, long, default_value = "report.html")]
+    output: PathBuf,
+
+    // Run in quick mode (turns off RustAnalyzer)
+    #[clap(short, long, default_value_t = false)]
+    quick_mode: bool,
+
+    /// Lines of source code to show before and after each effect
+    #[clap(long, default_value_t = 2)]
+    context: usize,
+}
+
+fn main() -> Result<()> {
+    cargo_scan::util::init_logging();
+    let args = Args::parse();
+
+    let stats = scan_stats::get_crate_stats_default(args.crate_path, args.quick_mode);
+    let mut report = match &args.audit_file {
+        Some(path) => {
+            let audit_file = AuditFile::read_audit_file(path.clone())?
+                .ok_or_else(|| anyhow!("Couldn't find audit file at {:?}", path))?;
+            Report::from_stats_with_audit(&stats, &audit_file)
+        }
+        None => Report::from_stats(&stats),
+    };
+
+    report.include_snippets(args.context);
+    fs::write(&args.output, report.to_html()?)?;
+    println!("Wrote report to {}", args.output.display());
+    Ok(())
+}
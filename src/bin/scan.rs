@@ -3,46 +3,558 @@
 //! Prints out potentially dangerous effects to stdout (one per line),
 //! in CSV format,
 //! followed by various metadata.
+//! With `--format json` or `--format sarif`, prints a single JSON report or
+//! SARIF log instead, and with `--format jsonl`, each effect as a line of
+//! JSON as soon as it's found. With `--format github` or `--format gitlab`, prints
+//! GitHub Actions workflow commands annotating each effect or a GitLab Code
+//! Quality report. With `--format markdown`, prints a summary for pull
+//! requests or review tickets, and with `--format stats`, the number of
+//! effects per type, module and file and of unsafe and skipped code. With
+//! `--format terminal`, prints a diagnostic with a highlighted snippet for
+//! each effect.
+//! With `--fail-on`, exits with code 1 if any reported effect matches one of
+//! the given conditions.
+//! With `--package` or `--git`, downloads and scans a crates.io package or
+//! a git repository instead of a local crate.
 
-use cargo_scan::effect::EffectInstance;
+use cargo_scan::audit_file::DefaultAuditConfig;
+use cargo_scan::baseline::Baseline;
+use cargo_scan::db::ScanDb;
+use cargo_scan::download_crate;
+use cargo_scan::effect::{EffectInstance, DEFAULT_EFFECT_TYPES};
+use cargo_scan::fail_on::FailOn;
+use cargo_scan::filter::{EffectClass, EffectFilter};
+use cargo_scan::ident::CanonicalPath;
+use cargo_scan::progress;
+use cargo_scan::project_config::ProjectConfig;
+use cargo_scan::report::{EffectReport, OutputFormat, Report};
 use cargo_scan::scan_stats::{self, CrateStats};
+use cargo_scan::scanner::{self, ScanTargets};
+use cargo_scan::sink::{load_sinks_file, Severity, SinkCategory};
+use cargo_scan::suppress;
+use cargo_scan::util::{self, csv, load_cargo_toml};
 
+use anyhow::{bail, Context, Result};
 use clap::Parser;
+use log::info;
+use petgraph::dot::Dot;
+use std::collections::HashSet;
+use std::fs;
+use std::io::{IsTerminal, Write};
 use std::path::PathBuf;
+use std::process::ExitCode;
+
+/// Lines of source code to show before and after each effect with
+/// `--format terminal`, unless given with `--context`
+const TERMINAL_CONTEXT_LINES: usize = 1;
 
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
 struct Args {
     /// Path to crate directory; should contain a 'src' directory and a Cargo.toml file
-    crate_path: PathBuf,
+    #[clap(required_unless_present = "remote")]
+    crate_path: Option<PathBuf>,
+
+    /// Download and scan the given crates.io package instead, e.g.
+    /// `serde_json@1.0.96`, or its latest version without `@<version>`
+    #[clap(short, long, group = "remote", conflicts_with = "crate_path")]
+    package: Option<String>,
+
+    /// Clone and scan the crate in the given git repository instead
+    #[clap(long, group = "remote", conflicts_with = "crate_path")]
+    git: Option<String>,
+
+    /// The commit, branch or tag to scan with `--git`, instead of the default
+    /// branch
+    #[clap(long, requires = "git", conflicts_with_all = ["crate_path", "package"])]
+    rev: Option<String>,
+
+    /// Keep the package or repository downloaded with `--package` or `--git`
+    /// in the download path, and scan the kept copy instead of downloading it
+    /// again on later scans. Repositories are only kept for a `--rev`
+    #[clap(long, default_value_t = false, requires = "remote")]
+    cache: bool,
 
     // Turned off for now -- chain binary not being used
     // /// Include transitive effects in dependency crates
     // #[arg(short, long, default_value_t = false)]
     // transitive: bool,
-    /// Path to download crates to for auditing
+    /// Path to download packages to with `--package` and `--git`
     #[clap(short = 'd', long = "crate-download-path", default_value = ".stats_tmp")]
     crate_download_path: String,
 
     // Run in quick mode (turns off RustAnalyzer)
     #[clap(short, long, default_value_t = false)]
     quick_mode: bool,
+
+    /// Output format
+    #[clap(long, default_value_t = OutputFormat::Csv)]
+    format: OutputFormat,
+
+    /// Also scan the macro-expanded source to find effects inside macro
+    /// invocations (requires building the crate)
+    #[clap(long, default_value_t = false)]
+    expand_macros: bool,
+
+    /// TOML file of additional sink patterns to look for
+    #[clap(long)]
+    sinks_file: Option<PathBuf>,
+
+    /// Also scan the crate's build script, whose effects happen at compile
+    /// time
+    #[clap(long, default_value_t = false)]
+    include_build_script: bool,
+
+    /// Also scan the crate's integration tests, `#[cfg(test)]` code and
+    /// `#[test]` functions
+    #[clap(long, default_value_t = false, conflicts_with = "expand_macros")]
+    include_tests: bool,
+
+    /// Also scan the crate's examples
+    #[clap(long, default_value_t = false, conflicts_with = "expand_macros")]
+    include_examples: bool,
+
+    /// Also scan the crate's benchmarks
+    #[clap(long, default_value_t = false, conflicts_with = "expand_macros")]
+    include_benches: bool,
+
+    /// Scan with only the given comma-separated features enabled, instead of
+    /// the default ones. Repeat to scan under several combinations and mark
+    /// the effects only found under some of them
+    #[clap(long, conflicts_with = "expand_macros")]
+    features: Vec<String>,
+
+    /// Evaluate platform cfgs (e.g. `#[cfg(windows)]`) for the given target
+    /// triple instead of the host, e.g. `x86_64-pc-windows-msvc`
+    #[clap(long, conflicts_with = "expand_macros")]
+    target: Option<String>,
+
+    /// Write the crate's call graph to the given file, in the DOT format
+    #[clap(long)]
+    emit_callgraph: Option<PathBuf>,
+
+    /// Also save the scan results to the given SQLite database, replacing
+    /// any earlier scan of the same crate version
+    #[clap(long)]
+    db: Option<PathBuf>,
+
+    /// Only report effects which aren't in the given baseline file. If the
+    /// file doesn't exist, record the current effects in it instead
+    #[clap(long)]
+    baseline: Option<PathBuf>,
+
+    /// Also show the effects suppressed by `// cargo-scan: allow(...)`
+    /// comments
+    #[clap(long, default_value_t = false)]
+    show_suppressed: bool,
+
+    /// Only report sink calls in the given categories (net, fs, process, env,
+    /// crypto, mem, other)
+    #[clap(long, value_parser, num_args = 1..)]
+    categories: Vec<SinkCategory>,
+
+    /// Only report sink calls of at least the given severity (low, medium,
+    /// high)
+    #[clap(long)]
+    min_severity: Option<Severity>,
+
+    /// Only report effects of the given comma-separated types: effect types
+    /// (e.g. unsafe-call), ffi, sink or sink:<category>
+    #[clap(long, value_parser, value_delimiter = ',')]
+    effect_types: Vec<EffectClass>,
+
+    /// Only report effects in files matching the given glob, relative to the
+    /// crate root, e.g. 'src/net/**'. Repeat to give several
+    #[clap(long)]
+    include_path: Vec<String>,
+
+    /// Only report effects whose callee starts with one of the given
+    /// comma-separated prefixes, e.g. libc::
+    #[clap(long, value_delimiter = ',')]
+    callee_prefix: Vec<String>,
+
+    /// Project config file to use instead of the nearest .cargo-scan.toml
+    #[clap(long = "config", conflicts_with = "no_config")]
+    project_config: Option<PathBuf>,
+
+    /// Don't read a .cargo-scan.toml project config file
+    #[clap(long, default_value_t = false)]
+    no_config: bool,
+
+    /// Don't show a progress bar on stderr while scanning the crate's files
+    #[clap(long, default_value_t = false)]
+    no_progress: bool,
+
+    #[clap(skip)]
+    project: ProjectConfig,
+
+    /// The filter given by `--effect-types`, `--include-path` and
+    /// `--callee-prefix`, and by the project config
+    #[clap(skip)]
+    filter: EffectFilter,
+
+    /// Include the given number of lines of source code before and after
+    /// each effect in JSON reports and terminal output
+    #[clap(long)]
+    context: Option<usize>,
+
+    /// In JSON and CBOR reports, list the effects in each function under the
+    /// function and the unsafe block containing them, instead of in a flat list
+    #[clap(long, default_value_t = false)]
+    group_by_block: bool,
+
+    /// Exit with code 1 if any reported effect matches the given conditions:
+    /// an effect type (e.g. ffi_call), ffi, sink, sink:<category> (e.g.
+    /// sink:net) or new-effects (requires --baseline)
+    #[clap(long, value_parser, num_args = 1..)]
+    fail_on: Vec<FailOn>,
 }
 
-fn main() {
+impl Args {
+    /// The crate to scan; set from `--package` or `--git` once it's downloaded
+    fn crate_path(&self) -> &PathBuf {
+        self.crate_path.as_ref().expect("crate path or package is required")
+    }
+}
+
+/// Whether the effect passes the filters. Effects other than sink calls have
+/// no category or severity, so they're filtered out by either.
+fn matches_filters(e: &EffectInstance, args: &Args) -> bool {
+    let category_ok = args.categories.is_empty()
+        || e.sink_category().is_some_and(|c| args.categories.contains(&c));
+    let severity_ok = match args.min_severity {
+        Some(min) => e.sink_severity().is_some_and(|s| s >= min),
+        None => true,
+    };
+    category_ok && severity_ok && args.filter.matches(e, args.crate_path())
+}
+
+/// Parses a comma-separated set of features, e.g. `std,alloc`
+fn parse_feature_set(features: &str) -> Vec<String> {
+    features
+        .split(',')
+        .map(|f| f.trim())
+        .filter(|f| !f.is_empty())
+        .map(String::from)
+        .collect()
+}
+
+/// The additional sinks given with `--sinks-file` and in the project config
+fn load_sinks(args: &Args) -> Result<HashSet<CanonicalPath>> {
+    let mut sinks = match &args.sinks_file {
+        Some(path) => load_sinks_file(path)?,
+        None => HashSet::new(),
+    };
+    sinks.extend(args.project.sinks()?);
+    Ok(sinks.iter().map(|s| CanonicalPath::new(s.as_str())).collect())
+}
+
+/// Prints each effect as a line of JSON as soon as the file containing it is
+/// scanned. Reachability from the public API is only known once the whole
+/// crate is scanned, so every effect is reported as reachable. Returns the
+/// number of effects matching each `--fail-on` condition.
+fn stream_jsonl(args: &Args, targets: &ScanTargets) -> Result<Vec<usize>> {
+    let unsupported = [
+        ("--expand-macros", args.expand_macros),
+        ("--include-build-script", args.include_build_script),
+        ("--features", !args.features.is_empty()),
+        ("--baseline", args.baseline.is_some()),
+        ("--db", args.db.is_some()),
+    ];
+    if let Some((flag, _)) = unsupported.iter().find(|(_, used)| *used) {
+        bail!("--format jsonl doesn't support {}", flag);
+    }
+
+    let sinks = load_sinks(args)?.into_iter().map(|s| s.to_path()).collect();
+    let mut matching = vec![0; args.fail_on.len()];
+    let mut out = std::io::stdout().lock();
+    let mut write_result = Ok(());
+    let results = scanner::scan_crate_streaming(
+        args.crate_path(),
+        sinks,
+        DEFAULT_EFFECT_TYPES,
+        args.quick_mode,
+        args.target.as_deref(),
+        targets,
+        |effects| {
+            let (effects, suppressed) = suppress::partition_suppressed(effects);
+            let suppressed = suppressed.into_iter().filter(|_| args.show_suppressed);
+            let effects = effects
+                .into_iter()
+                .map(|e| (e, None))
+                .chain(suppressed.map(|(e, s)| (e, Some(s))))
+                .filter(|(e, _)| matches_filters(e, args));
+            for (e, suppression) in effects {
+                if suppression.is_none() {
+                    for (n, condition) in matching.iter_mut().zip(&args.fail_on) {
+                        *n += condition.matches(&e) as usize;
+                    }
+                }
+                let report = EffectReport {
+                    location: e.call_loc().relative_to(args.crate_path()),
+                    suppression,
+                    ..EffectReport::from(&e)
+                };
+                if write_result.is_ok() {
+                    write_result = serde_json::to_string(&report)
+                        .map_err(anyhow::Error::from)
+                        .and_then(|line| Ok(writeln!(out, "{}", line)?));
+                }
+            }
+        },
+    )?;
+    write_result?;
+
+    if let Some(path) = &args.emit_callgraph {
+        fs::write(path, Dot::new(&results.call_graph).to_string())?;
+    }
+    Ok(matching)
+}
+
+fn make_report(stats: &CrateStats, args: &Args) -> Report {
+    let mut report = Report::from_stats(stats);
+    if args.show_suppressed {
+        report.include_suppressed(stats);
+    }
+    if let Some(context) = args.context {
+        report.include_snippets(context);
+    }
+    if args.group_by_block
+        && matches!(args.format, OutputFormat::Json | OutputFormat::Cbor)
+    {
+        report.group_by_block(stats);
+    }
+    report
+}
+
+/// Downloads the package given with `--package`, unless it's cached in the
+/// download path, and returns the path to its source
+fn fetch_package(package: &str, args: &Args) -> Result<PathBuf> {
+    let (name, version) = match package.split_once('@') {
+        Some((name, version)) => (name, version.to_string()),
+        None => {
+            let latest = download_crate::get_crate_versions(package, 1)?;
+            let version = latest.last().with_context(|| {
+                format!("Couldn't find any versions of package {}", package)
+            })?;
+            (package, version.to_string())
+        }
+    };
+
+    let cached =
+        PathBuf::from(&args.crate_download_path).join(format!("{}-{}", name, version));
+    if args.cache && cached.join("Cargo.toml").is_file() {
+        info!("Scanning cached download of {} v{}", name, version);
+        return Ok(cached);
+    }
+    fs::create_dir_all(&args.crate_download_path)?;
+    Ok(download_crate::download_crate_from_info(
+        name,
+        &version,
+        &args.crate_download_path,
+    )?)
+}
+
+/// Clones the repository given with `--git`, unless it's cached in the
+/// download path, and returns the path to its source
+fn fetch_git(url: &str, args: &Args) -> Result<PathBuf> {
+    let repo = url.trim_end_matches('/').trim_end_matches(".git");
+    let repo = repo.rsplit(['/', ':']).next().unwrap_or(repo);
+    let rev = args.rev.as_deref();
+    let dir_name = format!("{}-{}", repo, rev.unwrap_or("HEAD").replace('/', "-"));
+    let dir = PathBuf::from(&args.crate_download_path).join(dir_name);
+    if args.cache && rev.is_some() && dir.join("Cargo.toml").is_file() {
+        info!("Scanning cached clone of {} at {}", url, rev.unwrap_or_default());
+        return Ok(dir);
+    }
+    download_crate::clone_git_repo(url, rev, &dir)?;
+    if !dir.join("Cargo.toml").is_file() {
+        bail!("No Cargo.toml at the root of the repository {}", url);
+    }
+    Ok(dir)
+}
+
+fn main() -> Result<ExitCode> {
     cargo_scan::util::init_logging();
-    let args = Args::parse();
+    let mut args = Args::parse();
+    if args.fail_on.contains(&FailOn::NewEffects) && args.baseline.is_none() {
+        bail!("--fail-on new-effects requires --baseline");
+    }
+
+    progress::enable(!args.no_progress);
+    if !args.no_config {
+        args.project = ProjectConfig::load_or_find(args.project_config.as_deref())?;
+    }
+    util::fs::exclude_globs(&args.project.ignore)?;
+    args.filter = args.project.effect_filter(
+        args.effect_types.clone(),
+        args.include_path.clone(),
+        args.callee_prefix.clone(),
+    )?;
 
+    let crate_path = match (&args.package, &args.git) {
+        (Some(package), _) => fetch_package(package, &args)?,
+        (None, Some(url)) => fetch_git(url, &args)?,
+        (None, None) => return scan(&args),
+    };
+    args.crate_path = Some(crate_path.clone());
+    let result = scan(&args);
+    if !args.cache {
+        fs::remove_dir_all(&crate_path)?;
+    }
+    result
+}
+
+fn scan(args: &Args) -> Result<ExitCode> {
     // Note: old version without default_audit:
     // scanner::scan_crate(&args.crate_path, &args.effect_types)?
-    let stats = scan_stats::get_crate_stats_default(args.crate_path, args.quick_mode);
+    let targets = ScanTargets {
+        tests: args.include_tests,
+        examples: args.include_examples,
+        benches: args.include_benches,
+    };
+    let sinks = load_sinks(args)?;
+    let has_config = args.expand_macros
+        || !sinks.is_empty()
+        || args.include_build_script
+        || !args.features.is_empty()
+        || args.target.is_some()
+        || targets != ScanTargets::default();
+
+    if args.format == OutputFormat::Jsonl {
+        let matching = stream_jsonl(args, &targets)?;
+        return Ok(fail_on_result(args, &matching));
+    }
+
+    let mut stats = if has_config {
+        let config = DefaultAuditConfig {
+            expand_macros: args.expand_macros,
+            include_build_script: args.include_build_script,
+            feature_sets: args.features.iter().map(|f| parse_feature_set(f)).collect(),
+            target: args.target.clone(),
+            targets,
+            ..Default::default()
+        };
+        scan_stats::get_crate_stats_with_config(
+            args.crate_path().clone(),
+            sinks,
+            DEFAULT_EFFECT_TYPES,
+            args.quick_mode,
+            &config,
+        )?
+    } else {
+        scan_stats::get_crate_stats_default(args.crate_path().clone(), args.quick_mode)
+    };
+
+    if let Some(path) = &args.emit_callgraph {
+        fs::write(path, Dot::new(&stats.call_graph).to_string())?;
+    }
+
+    if let Some(path) = &args.db {
+        let crate_id = load_cargo_toml(args.crate_path())?;
+        ScanDb::open(path)?.save_scan(&crate_id, &stats)?;
+    }
+
+    if let Some(path) = &args.baseline {
+        if path.exists() {
+            let baseline = Baseline::load(path)?;
+            let effects = std::mem::take(&mut stats.effects);
+            stats.effects = baseline.new_effects(effects);
+            info!(
+                "Found {} effects not in the baseline ({} known)",
+                stats.effects.len(),
+                baseline.len()
+            );
+        } else {
+            let baseline = Baseline::from_effects(&stats.effects);
+            baseline.save(path)?;
+            eprintln!("Recorded {} effects in baseline {:?}", baseline.len(), path);
+            stats.effects.clear();
+        }
+    }
+
+    // Filter after updating the baseline, so it records all the effects
+    stats.effects.retain(|e| matches_filters(e, args));
+    stats.suppressed_effects.retain(|(e, _)| matches_filters(e, args));
+
+    match args.format {
+        OutputFormat::Csv => {
+            println!("{}", EffectInstance::csv_header());
+            for effect in &stats.effects {
+                println!("{}", effect.to_csv_relative_to(args.crate_path()));
+            }
 
-    println!("{}", EffectInstance::csv_header());
-    for effect in &stats.effects {
-        println!("{}", effect.to_csv());
+            if args.show_suppressed {
+                println!();
+                println!("{}, reason", EffectInstance::csv_header());
+                for (effect, suppression) in &stats.suppressed_effects {
+                    let reason = suppression.reason.as_deref().unwrap_or_default();
+                    println!(
+                        "{}, {}",
+                        effect.to_csv_relative_to(args.crate_path()),
+                        csv::sanitize_comma(reason)
+                    );
+                }
+            }
+
+            println!();
+            println!("{}", CrateStats::metadata_csv_header());
+            println!("{}", stats.metadata_csv());
+        }
+        // Printed while scanning
+        OutputFormat::Jsonl => unreachable!(),
+        OutputFormat::Json => {
+            println!("{}", make_report(&stats, args).to_json()?)
+        }
+        OutputFormat::Cbor => {
+            std::io::stdout().write_all(&make_report(&stats, args).to_cbor()?)?
+        }
+        OutputFormat::Sarif => {
+            println!("{}", make_report(&stats, args).to_sarif()?)
+        }
+        OutputFormat::Github => {
+            print!("{}", make_report(&stats, args).to_github_annotations())
+        }
+        OutputFormat::Gitlab => {
+            println!("{}", make_report(&stats, args).to_gitlab_code_quality()?)
+        }
+        OutputFormat::Markdown => {
+            print!("{}", make_report(&stats, args).to_markdown()?)
+        }
+        OutputFormat::Stats => print!("{}", stats.summary()),
+        OutputFormat::Terminal => {
+            let mut report = make_report(&stats, args);
+            if args.context.is_none() {
+                report.include_snippets(TERMINAL_CONTEXT_LINES);
+            }
+            if !std::io::stdout().is_terminal() {
+                colored::control::set_override(false);
+            }
+            print!("{}", report.to_terminal())
+        }
     }
 
-    println!();
-    println!("{}", CrateStats::metadata_csv_header());
-    println!("{}", stats.metadata_csv());
+    let matching = args
+        .fail_on
+        .iter()
+        .map(|condition| stats.effects.iter().filter(|e| condition.matches(e)).count())
+        .collect::<Vec<_>>();
+    Ok(fail_on_result(args, &matching))
+}
+
+/// Fails if any effects match one of the `--fail-on` conditions, given the
+/// number matching each one
+fn fail_on_result(args: &Args, matching: &[usize]) -> ExitCode {
+    let mut failed = false;
+    for (condition, &n) in args.fail_on.iter().zip(matching) {
+        if n > 0 {
+            eprintln!("Found {} effects matching --fail-on {}", n, condition);
+            failed = true;
+        }
+    }
+    if failed {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    }
 }
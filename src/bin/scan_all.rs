@@ -1,5 +1,6 @@
 //! Run a scan for a list of crates in parallel.
 
+use cargo_scan::db::ScanDb;
 use cargo_scan::effect::EffectInstance;
 use cargo_scan::scan_stats::{self, CrateStats};
 use cargo_scan::util;
@@ -63,6 +64,10 @@ struct Args {
     // Don't collect raw list of effects
     #[clap(long, default_value_t = false)]
     skip_raw: bool,
+
+    /// Also save the results of each crate to the given SQLite database
+    #[clap(long)]
+    db: Option<PathBuf>,
 }
 
 /*
@@ -240,6 +245,8 @@ fn main() {
     }
 
     let mut all_stats = AllStats::new(crates.clone());
+    let mut db =
+        args.db.as_ref().map(|p| ScanDb::open(p).expect("Failed to open database"));
 
     let batch_size = args.num_threads * 5;
     let num_batches = (num_crates + batch_size - 1) / batch_size;
@@ -274,6 +281,13 @@ fn main() {
         // Wait for threads
         info!("Waiting for threads... (batch {} of {})", batch, num_batches);
         for (i, (crt, stats)) in rx.iter().enumerate() {
+            if let Some(db) = &mut db {
+                let saved = util::load_cargo_toml(&stats.crate_path)
+                    .and_then(|crate_id| db.save_scan(&crate_id, &stats));
+                if let Err(e) = saved {
+                    error!("Failed to save {} to the database: {:?}", crt, e);
+                }
+            }
             all_stats.push_stats(crt, stats);
             if (start + i + 1) % progress_inc == 0 {
                 println!(
@@ -0,0 +1,63 @@
+//! Compare the scans of two versions of a crate.
+//!
+//! Each side is either a report saved with `scan --format json` (or `cbor`)
+//! or a crate directory to scan. Prints the effects added and removed in the new
+//! version, each with a stable identifier.
+
+use cargo_scan::cbor;
+use cargo_scan::report::Report;
+use cargo_scan::scan_stats;
+
+use anyhow::{Context, Result};
+use clap::Parser;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+struct Args {
+    /// The old version: a JSON report or a path to a crate directory
+    old: PathBuf,
+
+    /// The new version: a JSON report or a path to a crate directory
+    new: PathBuf,
+
+    // Run in quick mode (turns off RustAnalyzer)
+    #[clap(short, long, default_value_t = false)]
+    quick_mode: bool,
+
+    /// Print the differences as JSON
+    #[clap(long, default_value_t = false)]
+    json: bool,
+}
+
+fn load_report(path: &Path, quick_mode: bool) -> Result<Report> {
+    if path.is_dir() {
+        let stats = scan_stats::get_crate_stats_default(path.to_path_buf(), quick_mode);
+        Ok(Report::from_stats(&stats))
+    } else {
+        let bytes = fs::read(path)
+            .with_context(|| format!("Failed to read report {:?}", path))?;
+        if cbor::is_cbor(&bytes) {
+            Report::from_cbor(bytes.as_slice())
+        } else {
+            Report::from_json(&String::from_utf8_lossy(&bytes))
+        }
+        .with_context(|| format!("Failed to parse report {:?}", path))
+    }
+}
+
+fn main() -> Result<()> {
+    cargo_scan::util::init_logging();
+    let args = Args::parse();
+
+    let old = load_report(&args.old, args.quick_mode)?;
+    let new = load_report(&args.new, args.quick_mode)?;
+    let diff = old.diff(&new);
+    if args.json {
+        println!("{}", diff.to_json()?);
+    } else {
+        print!("{}", diff.to_text());
+    }
+    Ok(())
+}
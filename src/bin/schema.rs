@@ -0,0 +1,32 @@
+//! Print the JSON Schema of one of the JSON formats written by cargo-scan,
+//! e.g. to validate reports or generate code to read them.
+
+use cargo_scan::schema::{self, JsonFormat};
+
+use anyhow::Result;
+use clap::Parser;
+use std::fs;
+use std::path::PathBuf;
+
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+struct Args {
+    /// The format to print the schema of
+    #[clap(value_enum)]
+    format: JsonFormat,
+
+    /// Path to write the schema to, instead of printing it
+    #[clap(short, long)]
+    output: Option<PathBuf>,
+}
+
+fn main() -> Result<()> {
+    let args = Args::parse();
+
+    let schema = schema::json_schema(args.format)?;
+    match &args.output {
+        Some(path) => fs::write(path, schema)?,
+        None => println!("{}", schema),
+    }
+    Ok(())
+}
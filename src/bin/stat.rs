@@ -41,11 +41,22 @@ struct Args {
         EffectType::FFICall,
         EffectType::UnsafeCall,
         EffectType::RawPointer,
-        EffectType::UnionField,
+        EffectType::RawPointerWrite,
+        EffectType::PtrOffset,
+        EffectType::UnionFieldRead,
+        EffectType::UnionFieldWrite,
         EffectType::StaticMut,
         EffectType::StaticExt,
         EffectType::FnPtrCreation,
         EffectType::ClosureCreation,
+        EffectType::IndirectCall,
+        EffectType::Transmute,
+        EffectType::InlineAsm,
+        EffectType::UninitMem,
+        EffectType::RawConstruct,
+        EffectType::DynLoad,
+        EffectType::FFIExport,
+        EffectType::SendSyncImpl,
     ])]
     effect_types: Vec<EffectType>,
 }
@@ -117,6 +128,7 @@ fn main() -> Result<()> {
         None,
         None,
         args.effect_types,
+        false,
     );
 
     let mut chain = create_new_audit_chain(create, &args.audit_file_path, false)?;
@@ -0,0 +1,66 @@
+//! Compact binary output in CBOR.
+//!
+//! The reports and audit files of ecosystem-wide scans add up to gigabytes of
+//! JSON. CBOR has the same data model as JSON, so each of them converts to
+//! CBOR and back without loss, at a fraction of the size. CBOR files are
+//! decoded as they're read, instead of being read into a string first.
+
+use std::io::Read;
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::error::Result;
+
+pub fn to_vec<T: Serialize + ?Sized>(value: &T) -> Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    ciborium::into_writer(value, &mut bytes)?;
+    Ok(bytes)
+}
+
+pub fn from_reader<T: DeserializeOwned, R: Read>(reader: R) -> Result<T> {
+    Ok(ciborium::from_reader(reader)?)
+}
+
+/// Returns true if the document is CBOR rather than JSON. JSON documents
+/// start with an ASCII character, while CBOR arrays and maps start with a
+/// byte of at least 0x80.
+pub fn is_cbor(bytes: &[u8]) -> bool {
+    bytes.first().is_some_and(|b| *b >= 0x80)
+}
+
+/// Converts a JSON document to CBOR
+pub fn json_to_cbor(json: &[u8]) -> Result<Vec<u8>> {
+    let value: serde_json::Value = serde_json::from_slice(json)?;
+    to_vec(&value)
+}
+
+/// Converts a CBOR document to (pretty-printed) JSON
+pub fn cbor_to_json<R: Read>(cbor: R) -> Result<String> {
+    let value: serde_json::Value = from_reader(cbor)?;
+    Ok(serde_json::to_string_pretty(&value)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_json_round_trip() {
+        let json = json!({
+            "version": 2,
+            "effects": [{ "caller": "a::f", "line": 3, "pub_reachable": true }],
+            "hash": [0, 255],
+            "crate_id": null,
+        });
+        let cbor = json_to_cbor(json.to_string().as_bytes()).unwrap();
+        assert!(is_cbor(&cbor));
+        assert!(!is_cbor(json.to_string().as_bytes()));
+        assert!(cbor.len() < json.to_string().len());
+
+        let back: serde_json::Value =
+            serde_json::from_str(&cbor_to_json(cbor.as_slice()).unwrap()).unwrap();
+        assert_eq!(back, json);
+    }
+}
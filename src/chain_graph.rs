@@ -0,0 +1,253 @@
+//! Whole-program call graph for an audit chain.
+//!
+//! Each audit file only has a per-crate view: the effects its public
+//! functions perform in the crate, and the dependency functions they call.
+//! Stitching these together across the chain lets us answer questions that
+//! cross crate boundaries, e.g. which root crate functions can reach a network
+//! call in any dependency.
+
+use crate::audit_chain::AuditChain;
+use crate::effect::{Effect, EffectType};
+use crate::ident::{IdentPath, Pattern};
+use crate::util::CrateId;
+
+use anyhow::{anyhow, Result};
+use petgraph::graph::{DiGraph, NodeIndex};
+use petgraph::Direction;
+use std::collections::{HashMap, HashSet, VecDeque};
+
+#[derive(Debug, Default)]
+pub struct ChainCallGraph {
+    pub call_graph: DiGraph<IdentPath, ()>,
+    pub node_idxs: HashMap<IdentPath, NodeIndex>,
+    // The crate whose audit file the function's summary came from
+    pub fn_crates: HashMap<NodeIndex, CrateId>,
+    // The effects each function performs within its own crate
+    pub fn_effects: HashMap<NodeIndex, HashSet<Effect>>,
+}
+
+/// A query for effects in the whole-program call graph
+#[derive(Debug, Clone, Default)]
+pub struct EffectQuery {
+    pub effect_types: Vec<EffectType>,
+    /// Sink patterns, e.g. `std::net`
+    pub sinks: Vec<String>,
+}
+
+impl EffectQuery {
+    pub fn matches(&self, e: &Effect) -> bool {
+        if EffectType::matches_effect(&self.effect_types, e) {
+            return true;
+        }
+        match e {
+            Effect::SinkCall(sink) => {
+                let sink_path = IdentPath::new(sink.as_str());
+                self.sinks.iter().any(|s| sink_path.matches(&Pattern::new(s)))
+            }
+            _ => false,
+        }
+    }
+}
+
+impl ChainCallGraph {
+    /// Stitch together the audit files of every crate in the chain
+    pub fn from_chain(chain: &mut AuditChain) -> Result<Self> {
+        let mut graph = ChainCallGraph::default();
+        let crates = chain.all_crates().into_iter().cloned().collect::<Vec<_>>();
+        for crate_id in crates {
+            let audit_file = chain
+                .read_audit_file(&crate_id)?
+                .ok_or_else(|| anyhow!("Couldn't find audit for crate {}", crate_id))?;
+
+            for (f, effects) in audit_file.pub_fn_effects {
+                let idx = graph.update_call_graph(f.to_path());
+                graph.fn_crates.insert(idx, crate_id.clone());
+                graph.fn_effects.entry(idx).or_default().extend(effects);
+            }
+            for (f, callees) in audit_file.pub_dep_calls {
+                let caller = graph.update_call_graph(f.to_path());
+                graph.fn_crates.insert(caller, crate_id.clone());
+                for callee in callees {
+                    let callee = graph.update_call_graph(callee.to_path());
+                    graph.call_graph.update_edge(caller, callee, ());
+                }
+            }
+        }
+
+        Ok(graph)
+    }
+
+    fn update_call_graph(&mut self, f: IdentPath) -> NodeIndex {
+        if let Some(idx) = self.node_idxs.get(&f) {
+            return *idx;
+        }
+        let idx = self.call_graph.add_node(f.clone());
+        self.node_idxs.insert(f, idx);
+        idx
+    }
+
+    /// Returns every function which can transitively reach an effect matching
+    /// the query, in any crate of the chain
+    pub fn fns_reaching(&self, query: &EffectQuery) -> HashSet<NodeIndex> {
+        let mut visited = HashSet::new();
+        let mut worklist = self
+            .fn_effects
+            .iter()
+            .filter(|(_, effects)| effects.iter().any(|e| query.matches(e)))
+            .map(|(idx, _)| *idx)
+            .collect::<VecDeque<_>>();
+        while let Some(node) = worklist.pop_front() {
+            if !visited.insert(node) {
+                continue;
+            }
+            worklist.extend(
+                self.call_graph
+                    .neighbors_directed(node, Direction::Incoming)
+                    .filter(|n| !visited.contains(n)),
+            );
+        }
+
+        visited
+    }
+
+    /// Returns the functions of the given crate which can transitively reach
+    /// an effect matching the query, sorted by path
+    pub fn crate_fns_reaching(
+        &self,
+        crate_id: &CrateId,
+        query: &EffectQuery,
+    ) -> Vec<&IdentPath> {
+        let mut fns = self
+            .fns_reaching(query)
+            .into_iter()
+            .filter(|idx| self.fn_crates.get(idx) == Some(crate_id))
+            .map(|idx| &self.call_graph[idx])
+            .collect::<Vec<_>>();
+        fns.sort_by(|a, b| a.as_str().cmp(b.as_str()));
+        fns
+    }
+
+    /// Returns the functions of `crate_id` which the functions of `from` may
+    /// transitively call
+    pub fn crate_fns_reachable_from(
+        &self,
+        from: &CrateId,
+        crate_id: &CrateId,
+    ) -> HashSet<&IdentPath> {
+        let mut visited = HashSet::new();
+        let mut worklist = self
+            .fn_crates
+            .iter()
+            .filter(|(_, c)| *c == from)
+            .map(|(idx, _)| *idx)
+            .collect::<VecDeque<_>>();
+        while let Some(node) = worklist.pop_front() {
+            if !visited.insert(node) {
+                continue;
+            }
+            worklist.extend(
+                self.call_graph
+                    .neighbors_directed(node, Direction::Outgoing)
+                    .filter(|n| !visited.contains(n)),
+            );
+        }
+
+        visited
+            .into_iter()
+            .filter(|idx| self.fn_crates.get(idx) == Some(crate_id))
+            .map(|idx| &self.call_graph[idx])
+            .collect()
+    }
+
+    /// Returns the number of functions outside of the crate which may
+    /// transitively call one of its functions
+    pub fn crate_centrality(&self, crate_id: &CrateId) -> usize {
+        let mut visited = HashSet::new();
+        let mut worklist = self
+            .fn_crates
+            .iter()
+            .filter(|(_, c)| *c == crate_id)
+            .map(|(idx, _)| *idx)
+            .collect::<VecDeque<_>>();
+        while let Some(node) = worklist.pop_front() {
+            if !visited.insert(node) {
+                continue;
+            }
+            worklist.extend(
+                self.call_graph
+                    .neighbors_directed(node, Direction::Incoming)
+                    .filter(|n| !visited.contains(n)),
+            );
+        }
+
+        visited.iter().filter(|idx| self.fn_crates.get(idx) != Some(crate_id)).count()
+    }
+
+    /// Ranks the given crates by how widely their code is called from the
+    /// rest of the chain (see `crate_centrality`), most central first
+    pub fn rank_crates<'a>(&self, crates: &[&'a CrateId]) -> Vec<(&'a CrateId, usize)> {
+        let mut ranked =
+            crates.iter().map(|c| (*c, self.crate_centrality(c))).collect::<Vec<_>>();
+        ranked.sort_by(|(c1, n1), (c2, n2)| {
+            n2.cmp(n1)
+                .then_with(|| c1.crate_name.cmp(&c2.crate_name))
+                .then_with(|| c1.version.cmp(&c2.version))
+        });
+        ranked
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ident::CanonicalPath;
+    use crate::sink::Sink;
+    use semver::Version;
+
+    #[test]
+    fn test_crate_fns_reaching_across_crates() {
+        let root = CrateId::new("app".to_string(), Version::new(0, 1, 0));
+        let dep = CrateId::new("net".to_string(), Version::new(1, 0, 0));
+
+        let mut graph = ChainCallGraph::default();
+        let fetch = graph.update_call_graph(IdentPath::new("app::fetch"));
+        let compute = graph.update_call_graph(IdentPath::new("app::compute"));
+        let connect = graph.update_call_graph(IdentPath::new("net::connect"));
+        graph.fn_crates.insert(fetch, root.clone());
+        graph.fn_crates.insert(compute, root.clone());
+        graph.fn_crates.insert(connect, dep.clone());
+        graph.call_graph.add_edge(fetch, connect, ());
+        let sink = Sink::new_match(
+            &CanonicalPath::new("std::net::TcpStream::connect"),
+            &HashSet::from([IdentPath::new("std::net")]),
+        )
+        .unwrap();
+        graph.fn_effects.insert(connect, HashSet::from([Effect::SinkCall(sink)]));
+
+        let query =
+            EffectQuery { sinks: vec!["std::net".to_string()], ..Default::default() };
+        let fns = graph.crate_fns_reaching(&root, &query);
+        assert_eq!(fns, vec![&IdentPath::new("app::fetch")]);
+
+        let ranked = graph.rank_crates(&[&root, &dep]);
+        assert_eq!(ranked, vec![(&dep, 1), (&root, 0)]);
+    }
+
+    #[test]
+    fn test_crate_fns_reachable_from_root() {
+        let root = CrateId::new("app".to_string(), Version::new(0, 1, 0));
+        let dep = CrateId::new("net".to_string(), Version::new(1, 0, 0));
+
+        let mut graph = ChainCallGraph::default();
+        let fetch = graph.update_call_graph(IdentPath::new("app::fetch"));
+        let connect = graph.update_call_graph(IdentPath::new("net::connect"));
+        let listen = graph.update_call_graph(IdentPath::new("net::listen"));
+        graph.fn_crates.insert(fetch, root.clone());
+        graph.fn_crates.insert(connect, dep.clone());
+        graph.fn_crates.insert(listen, dep.clone());
+        graph.call_graph.add_edge(fetch, connect, ());
+
+        let fns = graph.crate_fns_reachable_from(&root, &dep);
+        assert_eq!(fns, HashSet::from([&IdentPath::new("net::connect")]));
+    }
+}
@@ -0,0 +1,228 @@
+//! Importing cargo-crev reviews.
+//!
+//! [cargo-crev](https://github.com/crev-dev/cargo-crev) reviewers publish
+//! package reviews as "proofs" in git repositories, which `cargo crev`
+//! fetches to a local proofs directory. We read the package reviews from
+//! there, and use positive reviews of a crate version to provisionally mark
+//! the effects in its audit as safe. Such audits are flagged as relying on a
+//! third-party review (see `AuditFile::third_party_review`).
+//!
+//! NOTE: We don't verify the signatures of the proofs or compute crev's web
+//!       of trust. Only reviews from the proof repositories the user fetched
+//!       are considered, and these can further be restricted to a list of
+//!       trusted reviewer ids.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use log::{debug, warn};
+use semver::Version;
+use serde::{Deserialize, Serialize};
+use walkdir::WalkDir;
+
+use crate::util::CrateId;
+
+const REVIEW_START: &str = "----- BEGIN CREV PACKAGE REVIEW -----";
+const REVIEW_SIGNATURE: &str = "----- BEGIN CREV PACKAGE REVIEW SIGNATURE -----";
+
+/// Crev's source identifier for crates.io packages
+const CRATES_IO_SOURCE: &str = "https://crates.io";
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum CrevRating {
+    Dangerous,
+    Negative,
+    Neutral,
+    Positive,
+    Strong,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+struct CrevId {
+    id: String,
+    url: Option<String>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+struct CrevPackage {
+    source: String,
+    name: String,
+    version: Version,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+struct CrevReviewInfo {
+    rating: CrevRating,
+}
+
+/// The body of a crev package review proof, leaving out the fields we don't
+/// use
+#[derive(Deserialize, Debug, Clone)]
+struct CrevProof {
+    from: CrevId,
+    package: CrevPackage,
+    review: Option<CrevReviewInfo>,
+    comment: Option<String>,
+}
+
+/// A review of a single crate version
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CrevReview {
+    pub crate_id: CrateId,
+    /// The reviewer's crev id
+    pub reviewer: String,
+    /// The reviewer's proof repository
+    pub reviewer_url: Option<String>,
+    pub rating: CrevRating,
+    pub comment: Option<String>,
+}
+
+impl CrevReview {
+    pub fn is_positive(&self) -> bool {
+        self.rating >= CrevRating::Positive
+    }
+}
+
+/// Parses the package reviews in the contents of a proof file. Other kinds of
+/// proofs (e.g. trust proofs) are skipped.
+pub fn parse_proofs(contents: &str) -> Vec<CrevReview> {
+    let mut reviews = Vec::new();
+    for section in contents.split(REVIEW_START).skip(1) {
+        let body = section.split(REVIEW_SIGNATURE).next().unwrap_or_default();
+        let proof = match serde_yaml::from_str::<CrevProof>(body) {
+            Ok(proof) => proof,
+            Err(e) => {
+                warn!("Skipping malformed crev review: {}", e);
+                continue;
+            }
+        };
+        if proof.package.source != CRATES_IO_SOURCE {
+            debug!("Skipping crev review from source {}", proof.package.source);
+            continue;
+        }
+        // Reviews without a rating are for e.g. reporting issues
+        let Some(review) = proof.review else {
+            continue;
+        };
+        reviews.push(CrevReview {
+            crate_id: CrateId::new(proof.package.name, proof.package.version),
+            reviewer: proof.from.id,
+            reviewer_url: proof.from.url,
+            rating: review.rating,
+            comment: proof.comment.filter(|c| !c.is_empty()),
+        });
+    }
+    reviews
+}
+
+/// Where `cargo crev` stores the proof repositories it fetched
+pub fn default_proofs_dir() -> Option<PathBuf> {
+    let data_dir = if cfg!(target_os = "macos") {
+        home::home_dir()?.join("Library/Application Support")
+    } else {
+        home::home_dir()?.join(".local/share")
+    };
+    Some(data_dir.join("crev").join("proofs"))
+}
+
+/// Loads all the package reviews in the proof files under the directory
+pub fn load_reviews(proofs_dir: &Path) -> Result<Vec<CrevReview>> {
+    let mut reviews = Vec::new();
+    for entry in WalkDir::new(proofs_dir) {
+        let entry = entry.with_context(|| {
+            format!("Failed to read proofs directory {:?}", proofs_dir)
+        })?;
+        let path = entry.path();
+        if path.is_file() && path.extension().is_some_and(|e| e == "crev") {
+            let contents = fs::read_to_string(path)?;
+            reviews.extend(parse_proofs(&contents));
+        }
+    }
+    Ok(reviews)
+}
+
+/// Returns the reviews of the crate version, or None if any of them is
+/// negative (or there are no positive ones). If `trusted_ids` isn't empty,
+/// only reviews by those reviewers are considered.
+pub fn positive_reviews<'a>(
+    reviews: &'a [CrevReview],
+    crate_id: &CrateId,
+    trusted_ids: &[String],
+) -> Option<Vec<&'a CrevReview>> {
+    let matching = reviews
+        .iter()
+        .filter(|r| &r.crate_id == crate_id)
+        .filter(|r| trusted_ids.is_empty() || trusted_ids.contains(&r.reviewer))
+        .collect::<Vec<_>>();
+    if matching.is_empty() || matching.iter().any(|r| r.rating < CrevRating::Neutral) {
+        return None;
+    }
+    let positive = matching.into_iter().filter(|r| r.is_positive()).collect::<Vec<_>>();
+    if positive.is_empty() {
+        None
+    } else {
+        Some(positive)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PROOF: &str = r#"
+----- BEGIN CREV PACKAGE REVIEW -----
+version: -1
+date: "2023-01-01T00:00:00+00:00"
+from:
+  id-type: crev
+  id: FYlr8YoYGVvDwHQxqEIs89reKKDy-oWisoO0qXXEfHE
+  url: "https://github.com/someone/crev-proofs"
+package:
+  source: "https://crates.io"
+  name: log
+  version: 0.4.20
+  digest: 1Yh0Ai7iVWmG7FiZlE8xOdl0PRbH3N1UGCyLIjVbsGo
+review:
+  thoroughness: medium
+  understanding: high
+  rating: positive
+comment: Only logging
+----- BEGIN CREV PACKAGE REVIEW SIGNATURE -----
+zTXFTY1MvnSn2B2v9ggWeZClZ9ZRiNZpcLnyq6AIkNw
+----- END CREV PACKAGE REVIEW -----
+----- BEGIN CREV PACKAGE REVIEW -----
+version: -1
+date: "2023-01-01T00:00:00+00:00"
+from:
+  id-type: crev
+  id: other
+package:
+  source: "https://crates.io"
+  name: libc
+  version: 0.2.150
+  digest: 1Yh0Ai7iVWmG7FiZlE8xOdl0PRbH3N1UGCyLIjVbsGo
+review:
+  thoroughness: low
+  understanding: low
+  rating: negative
+----- BEGIN CREV PACKAGE REVIEW SIGNATURE -----
+zTXFTY1MvnSn2B2v9ggWeZClZ9ZRiNZpcLnyq6AIkNw
+----- END CREV PACKAGE REVIEW -----
+"#;
+
+    #[test]
+    fn test_parse_proofs() {
+        let reviews = parse_proofs(PROOF);
+        assert_eq!(reviews.len(), 2);
+        assert_eq!(reviews[0].crate_id.crate_name, "log");
+        assert_eq!(reviews[0].comment.as_deref(), Some("Only logging"));
+
+        let log = CrateId::new("log".to_string(), Version::new(0, 4, 20));
+        assert_eq!(positive_reviews(&reviews, &log, &[]).map(|r| r.len()), Some(1));
+        assert!(positive_reviews(&reviews, &log, &["other".to_string()]).is_none());
+        let libc = CrateId::new("libc".to_string(), Version::new(0, 2, 150));
+        assert!(positive_reviews(&reviews, &libc, &[]).is_none());
+    }
+}
@@ -0,0 +1,109 @@
+//! Named audit criteria.
+//!
+//! Auditors certify which criteria a crate's audit meets, e.g. `safe-to-run`
+//! or `crypto-reviewed`, so that a crate can be audited to different levels of
+//! assurance. The certified criteria are saved in the crate's policy (see
+//! `AuditFile::criteria`), and a chain meets a criteria if all of its crates
+//! do. Criteria are free-form names, but some of the built-in ones imply
+//! others, e.g. a crate which is safe to deploy is also safe to run.
+
+use std::collections::HashSet;
+use std::fmt;
+
+use crate::audit_file::{unix_time, AuditFile};
+
+pub const SAFE_TO_RUN: &str = "safe-to-run";
+pub const SAFE_TO_DEPLOY: &str = "safe-to-deploy";
+pub const CRYPTO_REVIEWED: &str = "crypto-reviewed";
+
+/// Pairs of criteria where the first implies the second
+const IMPLIED_CRITERIA: &[(&str, &str)] = &[(SAFE_TO_DEPLOY, SAFE_TO_RUN)];
+
+/// Returns the criteria along with all the criteria they imply
+pub fn with_implied<'a, I>(criteria: I) -> HashSet<String>
+where
+    I: IntoIterator<Item = &'a String>,
+{
+    let mut all = criteria.into_iter().cloned().collect::<HashSet<_>>();
+    let mut stack = all.iter().cloned().collect::<Vec<_>>();
+    while let Some(c) = stack.pop() {
+        for (_, implied) in IMPLIED_CRITERIA.iter().filter(|(from, _)| *from == c) {
+            if all.insert(implied.to_string()) {
+                stack.push(implied.to_string());
+            }
+        }
+    }
+    all
+}
+
+/// Why an audit doesn't meet a criteria
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CriteriaFailure {
+    /// The audit still has effects left to audit, or whose decisions have
+    /// expired
+    Unaudited,
+    /// Some effects were marked unsafe
+    Unsafe,
+    /// The effects were only marked safe based on a third-party review
+    ThirdPartyReview,
+    /// The criteria wasn't certified for the audit
+    NotCertified,
+}
+
+impl fmt::Display for CriteriaFailure {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CriteriaFailure::Unaudited => write!(f, "has effects left to audit"),
+            CriteriaFailure::Unsafe => write!(f, "has effects marked unsafe"),
+            CriteriaFailure::ThirdPartyReview => {
+                write!(f, "was only reviewed by a third party")
+            }
+            CriteriaFailure::NotCertified => write!(f, "isn't certified"),
+        }
+    }
+}
+
+/// Checks whether the audit is complete enough to certify criteria for it.
+/// Effects whose decisions have expired count as unaudited.
+pub fn check_certifiable(audit_file: &AuditFile) -> Result<(), CriteriaFailure> {
+    if !audit_file.is_fully_audited(unix_time()) {
+        Err(CriteriaFailure::Unaudited)
+    } else if audit_file.has_unsafe_effect() {
+        Err(CriteriaFailure::Unsafe)
+    } else if audit_file.third_party_review.is_some() {
+        Err(CriteriaFailure::ThirdPartyReview)
+    } else {
+        Ok(())
+    }
+}
+
+/// Checks whether the audit meets the criteria
+pub fn check_criteria(
+    audit_file: &AuditFile,
+    criteria: &str,
+) -> Result<(), CriteriaFailure> {
+    check_certifiable(audit_file)?;
+    if with_implied(&audit_file.criteria).contains(criteria) {
+        Ok(())
+    } else {
+        Err(CriteriaFailure::NotCertified)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_implied_criteria() {
+        let deploy = with_implied(&[SAFE_TO_DEPLOY.to_string()]);
+        assert!(deploy.contains(SAFE_TO_DEPLOY));
+        assert!(deploy.contains(SAFE_TO_RUN));
+
+        let run = with_implied(&[SAFE_TO_RUN.to_string()]);
+        assert!(!run.contains(SAFE_TO_DEPLOY));
+
+        let crypto = with_implied(&[CRYPTO_REVIEWED.to_string()]);
+        assert_eq!(crypto.len(), 1);
+    }
+}
@@ -0,0 +1,404 @@
+//! Storage of scan results and audit decisions in a SQLite database.
+//!
+//! Reports and audit files are one file per crate, which doesn't scale to
+//! scanning thousands of crate versions across an organization. The database
+//! holds the effects, function and unsafe block locations, call edges and
+//! audit decisions of any number of crates, keyed by crate name and version,
+//! and can be queried across all of them.
+//!
+//! Saving the scan (or audit) of a crate version again replaces its earlier
+//! rows. Locations are stored relative to the crate root, like in reports.
+
+use crate::audit_file::{AuditFile, EffectTree};
+use crate::effect::SrcLoc;
+use crate::report::{EffectReport, Report};
+use crate::scan_stats::CrateStats;
+use crate::util::CrateId;
+
+use anyhow::{Context, Result};
+use petgraph::visit::EdgeRef;
+use rusqlite::{params, Connection, OptionalExtension, Row, ToSql};
+use std::path::{Path, PathBuf};
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS crates (
+    id INTEGER PRIMARY KEY,
+    name TEXT NOT NULL,
+    version TEXT NOT NULL,
+    root TEXT NOT NULL,
+    UNIQUE (name, version)
+);
+CREATE TABLE IF NOT EXISTS effects (
+    crate_id INTEGER NOT NULL REFERENCES crates (id),
+    effect_id TEXT NOT NULL,
+    caller TEXT NOT NULL,
+    callee TEXT NOT NULL,
+    effect_type TEXT NOT NULL,
+    pattern TEXT,
+    dir TEXT NOT NULL,
+    file TEXT NOT NULL,
+    start_line INTEGER NOT NULL,
+    start_col INTEGER NOT NULL,
+    end_line INTEGER NOT NULL,
+    end_col INTEGER NOT NULL,
+    pub_reachable INTEGER NOT NULL
+);
+CREATE INDEX IF NOT EXISTS effects_crate ON effects (crate_id);
+CREATE INDEX IF NOT EXISTS effects_callee ON effects (callee);
+CREATE TABLE IF NOT EXISTS blocks (
+    crate_id INTEGER NOT NULL REFERENCES crates (id),
+    kind TEXT NOT NULL,
+    function TEXT,
+    dir TEXT NOT NULL,
+    file TEXT NOT NULL,
+    start_line INTEGER NOT NULL,
+    start_col INTEGER NOT NULL,
+    end_line INTEGER NOT NULL,
+    end_col INTEGER NOT NULL
+);
+CREATE TABLE IF NOT EXISTS call_edges (
+    crate_id INTEGER NOT NULL REFERENCES crates (id),
+    caller TEXT NOT NULL,
+    callee TEXT NOT NULL,
+    dir TEXT NOT NULL,
+    file TEXT NOT NULL,
+    line INTEGER NOT NULL,
+    col INTEGER NOT NULL
+);
+CREATE INDEX IF NOT EXISTS call_edges_callee ON call_edges (callee);
+CREATE TABLE IF NOT EXISTS decisions (
+    crate_id INTEGER NOT NULL REFERENCES crates (id),
+    effect_id TEXT NOT NULL,
+    function TEXT NOT NULL,
+    annotation TEXT NOT NULL,
+    comment TEXT
+);
+";
+
+/// An effect stored in the database
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EffectRow {
+    pub crate_id: CrateId,
+    /// See [`EffectReport::id`]
+    pub effect_id: String,
+    pub caller: String,
+    pub callee: String,
+    pub effect_type: String,
+    pub pattern: Option<String>,
+    pub location: SrcLoc,
+    pub pub_reachable: bool,
+}
+
+/// An audit decision on an effect, for one of the functions on the way to it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DecisionRow {
+    pub effect_id: String,
+    pub function: String,
+    pub annotation: String,
+    pub comment: Option<String>,
+}
+
+pub struct ScanDb {
+    conn: Connection,
+}
+
+fn loc_from_row(row: &Row, first: usize) -> rusqlite::Result<SrcLoc> {
+    let dir: String = row.get(first)?;
+    let file: String = row.get(first + 1)?;
+    Ok(SrcLoc::new(
+        &PathBuf::from(dir).join(file),
+        row.get(first + 2)?,
+        row.get(first + 3)?,
+        row.get(first + 4)?,
+        row.get(first + 5)?,
+    ))
+}
+
+fn crate_id_from_row(row: &Row, first: usize) -> rusqlite::Result<CrateId> {
+    let name: String = row.get(first)?;
+    let version: String = row.get(first + 1)?;
+    let version = version.parse().map_err(|e| {
+        rusqlite::Error::FromSqlConversionFailure(
+            first + 1,
+            rusqlite::types::Type::Text,
+            Box::new(e),
+        )
+    })?;
+    Ok(CrateId::new(name, version))
+}
+
+/// Adds the leaves of the tree, which hold the decisions
+fn collect_decisions<'a>(tree: &'a EffectTree, leaves: &mut Vec<&'a EffectTree>) {
+    match tree {
+        EffectTree::Leaf(..) => leaves.push(tree),
+        EffectTree::Branch(_, children) => {
+            children.iter().for_each(|t| collect_decisions(t, leaves))
+        }
+    }
+}
+
+impl ScanDb {
+    /// Opens the database at `path`, creating it if it doesn't exist
+    pub fn open(path: &Path) -> Result<Self> {
+        let conn = Connection::open(path)
+            .with_context(|| format!("Failed to open database {:?}", path))?;
+        Self::from_connection(conn)
+    }
+
+    pub fn open_in_memory() -> Result<Self> {
+        Self::from_connection(Connection::open_in_memory()?)
+    }
+
+    fn from_connection(conn: Connection) -> Result<Self> {
+        conn.execute_batch(SCHEMA)?;
+        Ok(Self { conn })
+    }
+
+    /// The id of the crate's row, adding one if there isn't one yet
+    fn crate_row(&self, crate_id: &CrateId, root: &Path) -> Result<i64> {
+        let version = crate_id.version.to_string();
+        self.conn.execute(
+            "INSERT INTO crates (name, version, root) VALUES (?1, ?2, ?3)
+             ON CONFLICT (name, version) DO UPDATE SET root = excluded.root",
+            params![crate_id.crate_name, version, root.to_string_lossy()],
+        )?;
+        Ok(self.conn.query_row(
+            "SELECT id FROM crates WHERE name = ?1 AND version = ?2",
+            params![crate_id.crate_name, version],
+            |row| row.get(0),
+        )?)
+    }
+
+    fn find_crate_row(&self, crate_id: &CrateId) -> Result<Option<i64>> {
+        Ok(self
+            .conn
+            .query_row(
+                "SELECT id FROM crates WHERE name = ?1 AND version = ?2",
+                params![crate_id.crate_name, crate_id.version.to_string()],
+                |row| row.get(0),
+            )
+            .optional()?)
+    }
+
+    /// Saves the scan results of the crate, replacing any earlier scan of
+    /// the same version
+    pub fn save_scan(&mut self, crate_id: &CrateId, stats: &CrateStats) -> Result<()> {
+        let report = Report::from_stats(stats);
+        let row_id = self.crate_row(crate_id, &report.crate_root)?;
+        let root = &stats.crate_path;
+
+        let tx = self.conn.transaction()?;
+        for table in ["effects", "blocks", "call_edges"] {
+            tx.execute(&format!("DELETE FROM {} WHERE crate_id = ?1", table), [row_id])?;
+        }
+
+        let mut insert_effect = tx.prepare(
+            "INSERT INTO effects VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)",
+        )?;
+        for e in &report.effects {
+            let loc = &e.location;
+            insert_effect.execute(params![
+                row_id,
+                e.id(),
+                e.caller,
+                e.callee,
+                e.effect_type.to_string(),
+                e.pattern,
+                loc.dir().to_string_lossy(),
+                loc.file().to_string_lossy(),
+                loc.start_line(),
+                loc.start_col(),
+                loc.end_line(),
+                loc.end_col(),
+                e.pub_reachable,
+            ])?;
+        }
+        drop(insert_effect);
+
+        let mut insert_block =
+            tx.prepare("INSERT INTO blocks VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)")?;
+        let fn_blocks =
+            stats.fn_locs.iter().map(|(f, loc)| ("fn", Some(f.as_str()), loc));
+        let unsafe_blocks =
+            stats.unsafe_block_locs.iter().map(|loc| ("unsafe", None, loc));
+        for (kind, function, loc) in fn_blocks.chain(unsafe_blocks) {
+            let loc = loc.relative_to(root);
+            insert_block.execute(params![
+                row_id,
+                kind,
+                function,
+                loc.dir().to_string_lossy(),
+                loc.file().to_string_lossy(),
+                loc.start_line(),
+                loc.start_col(),
+                loc.end_line(),
+                loc.end_col(),
+            ])?;
+        }
+        drop(insert_block);
+
+        let mut insert_edge =
+            tx.prepare("INSERT INTO call_edges VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)")?;
+        let graph = &stats.call_graph;
+        for edge in graph.edge_references() {
+            let loc = edge.weight().relative_to(root);
+            insert_edge.execute(params![
+                row_id,
+                graph[edge.source()].as_str(),
+                graph[edge.target()].as_str(),
+                loc.dir().to_string_lossy(),
+                loc.file().to_string_lossy(),
+                loc.start_line(),
+                loc.start_col(),
+            ])?;
+        }
+        drop(insert_edge);
+
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Saves the decisions of the crate's audit, replacing those of any
+    /// earlier audit of the same version
+    pub fn save_audit(&mut self, crate_id: &CrateId, audit: &AuditFile) -> Result<()> {
+        let row_id = self.crate_row(crate_id, &audit.base_dir)?;
+
+        let tx = self.conn.transaction()?;
+        tx.execute("DELETE FROM decisions WHERE crate_id = ?1", [row_id])?;
+        let mut insert =
+            tx.prepare("INSERT INTO decisions VALUES (?1, ?2, ?3, ?4, ?5)")?;
+        for (e, tree) in &audit.audit_trees {
+            let effect_id = EffectReport::from(e).id();
+            let comment = audit.comments.get(e);
+            let mut leaves = Vec::new();
+            collect_decisions(tree, &mut leaves);
+            for leaf in leaves {
+                let EffectTree::Leaf(info, annotation) = leaf else { continue };
+                insert.execute(params![
+                    row_id,
+                    effect_id,
+                    info.caller_path.as_str(),
+                    annotation.to_string(),
+                    comment,
+                ])?;
+            }
+        }
+        drop(insert);
+
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// The crates in the database, in order of name and version
+    pub fn crates(&self) -> Result<Vec<CrateId>> {
+        let mut stmt = self.conn.prepare("SELECT name, version FROM crates")?;
+        let mut crates = stmt
+            .query_map([], |row| crate_id_from_row(row, 0))?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        crates.sort_by(|a, b| {
+            (&a.crate_name, &a.version).cmp(&(&b.crate_name, &b.version))
+        });
+        Ok(crates)
+    }
+
+    fn query_effects<P: ToSql>(&self, filter: &str, arg: P) -> Result<Vec<EffectRow>> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT c.name, c.version, e.effect_id, e.caller, e.callee, e.effect_type,
+                    e.pattern, e.dir, e.file, e.start_line, e.start_col, e.end_line,
+                    e.end_col, e.pub_reachable
+             FROM effects e JOIN crates c ON e.crate_id = c.id
+             WHERE {}
+             ORDER BY c.name, c.version, e.rowid",
+            filter
+        ))?;
+        let rows = stmt.query_map(params![arg], |row| {
+            Ok(EffectRow {
+                crate_id: crate_id_from_row(row, 0)?,
+                effect_id: row.get(2)?,
+                caller: row.get(3)?,
+                callee: row.get(4)?,
+                effect_type: row.get(5)?,
+                pattern: row.get(6)?,
+                location: loc_from_row(row, 7)?,
+                pub_reachable: row.get(13)?,
+            })
+        })?;
+        Ok(rows.collect::<rusqlite::Result<Vec<_>>>()?)
+    }
+
+    /// The effects of a crate version, in the order they were found
+    pub fn effects(&self, crate_id: &CrateId) -> Result<Vec<EffectRow>> {
+        let Some(row_id) = self.find_crate_row(crate_id)? else {
+            return Ok(Vec::new());
+        };
+        self.query_effects("e.crate_id = ?1", row_id)
+    }
+
+    /// The effects across all crates which match the sink pattern, e.g.
+    /// `std::process`
+    pub fn effects_matching(&self, pattern: &str) -> Result<Vec<EffectRow>> {
+        self.query_effects("e.pattern = ?1", pattern)
+    }
+
+    /// The functions of any crate which call `callee` directly
+    pub fn callers(&self, callee: &str) -> Result<Vec<(CrateId, String)>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT DISTINCT c.name, c.version, e.caller
+             FROM call_edges e JOIN crates c ON e.crate_id = c.id
+             WHERE e.callee = ?1
+             ORDER BY c.name, c.version, e.caller",
+        )?;
+        let rows = stmt
+            .query_map([callee], |row| Ok((crate_id_from_row(row, 0)?, row.get(2)?)))?;
+        Ok(rows.collect::<rusqlite::Result<Vec<_>>>()?)
+    }
+
+    /// The audit decisions on the effects of a crate version
+    pub fn decisions(&self, crate_id: &CrateId) -> Result<Vec<DecisionRow>> {
+        let Some(row_id) = self.find_crate_row(crate_id)? else {
+            return Ok(Vec::new());
+        };
+        let mut stmt = self.conn.prepare(
+            "SELECT effect_id, function, annotation, comment FROM decisions
+             WHERE crate_id = ?1 ORDER BY effect_id, function",
+        )?;
+        let rows = stmt.query_map([row_id], |row| {
+            Ok(DecisionRow {
+                effect_id: row.get(0)?,
+                function: row.get(1)?,
+                annotation: row.get(2)?,
+                comment: row.get(3)?,
+            })
+        })?;
+        Ok(rows.collect::<rusqlite::Result<Vec<_>>>()?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scan_stats;
+
+    #[test]
+    fn test_scan_db() -> Result<()> {
+        let path = PathBuf::from("data/test-packages/dependency-ex");
+        let stats = scan_stats::get_crate_stats_default(path, true);
+        let crate_id = CrateId::new("dependency-ex".to_string(), "0.1.0".parse()?);
+
+        let mut db = ScanDb::open_in_memory()?;
+        db.save_scan(&crate_id, &stats)?;
+        // Saving the same version again replaces its rows
+        db.save_scan(&crate_id, &stats)?;
+
+        assert_eq!(db.crates()?, std::slice::from_ref(&crate_id));
+        let effects = db.effects(&crate_id)?;
+        assert_eq!(effects.len(), 2);
+        let fs_effects = db.effects_matching("std::fs")?;
+        assert_eq!(fs_effects.len(), 1);
+        assert_eq!(fs_effects[0].callee, "std::fs::File::open");
+        assert_eq!(fs_effects[0].location.dir(), Path::new("src"));
+        assert!(db.effects_matching("std::net")?.is_empty());
+        assert!(db.decisions(&crate_id)?.is_empty());
+        Ok(())
+    }
+}
@@ -0,0 +1,151 @@
+//! Custom effect detectors.
+//!
+//! An `EffectDetector` is called on the syntax nodes of the scanned code,
+//! and reports the nodes it's interested in as `Effect::Custom` effects,
+//! e.g. calls to an organization's deprecated crypto module:
+//!
+//! ```
+//! use cargo_scan::detector::{self, DetectorContext, EffectDetector};
+//! use cargo_scan::ident::CanonicalPath;
+//! use std::sync::Arc;
+//!
+//! struct DeprecatedCrypto;
+//!
+//! impl EffectDetector for DeprecatedCrypto {
+//!     fn name(&self) -> &str {
+//!         "[DeprecatedCrypto]"
+//!     }
+//!
+//!     fn detect_expr<'a>(
+//!         &self,
+//!         e: &'a syn::Expr,
+//!         cx: &DetectorContext<'a, '_>,
+//!     ) -> Option<CanonicalPath> {
+//!         let syn::Expr::Call(call) = e else { return None };
+//!         let syn::Expr::Path(p) = &*call.func else { return None };
+//!         let callee = cx.resolve_path(&p.path);
+//!         callee.as_str().starts_with("our_crypto::legacy::").then_some(callee)
+//!     }
+//! }
+//!
+//! detector::register(Arc::new(DeprecatedCrypto));
+//! ```
+//!
+//! Detectors are registered for the whole process, and every scan started
+//! after registering one runs it, whichever resolver the scan uses.
+
+use std::fmt;
+use std::sync::{Arc, RwLock};
+
+use crate::ident::CanonicalPath;
+use crate::resolution::resolve::Resolve;
+
+/// A detector of effects the scanner doesn't know about
+pub trait EffectDetector: Send + Sync {
+    /// The name of the effects the detector reports, shown in place of the
+    /// effect type, e.g. `[DeprecatedCrypto]`
+    fn name(&self) -> &str;
+
+    /// Called on each expression the scanner visits. Returns the callee of
+    /// the effect, if the expression is one.
+    fn detect_expr<'a>(
+        &self,
+        _e: &'a syn::Expr,
+        _cx: &DetectorContext<'a, '_>,
+    ) -> Option<CanonicalPath> {
+        None
+    }
+
+    /// Called on each item the scanner visits, including those nested in
+    /// functions. Returns the callee of the effect, if the item is one.
+    fn detect_item<'a>(
+        &self,
+        _i: &'a syn::Item,
+        _cx: &DetectorContext<'a, '_>,
+    ) -> Option<CanonicalPath> {
+        None
+    }
+}
+
+impl fmt::Debug for dyn EffectDetector {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "EffectDetector({})", self.name())
+    }
+}
+
+/// The scanner's state when a detector is called, giving access to its name
+/// resolution
+pub struct DetectorContext<'a, 'r> {
+    resolver: &'r dyn DetectorResolver<'a>,
+    caller: Option<&'r CanonicalPath>,
+}
+
+impl<'a, 'r> DetectorContext<'a, 'r> {
+    pub(crate) fn new<R: Resolve<'a>>(
+        resolver: &'r R,
+        caller: Option<&'r CanonicalPath>,
+    ) -> Self {
+        Self { resolver, caller }
+    }
+
+    /// The function the node is in, or `None` at module level
+    pub fn caller(&self) -> Option<&CanonicalPath> {
+        self.caller
+    }
+
+    pub fn resolve_path(&self, p: &'a syn::Path) -> CanonicalPath {
+        self.resolver.resolve_path(p)
+    }
+
+    pub fn resolve_ident(&self, i: &'a syn::Ident) -> CanonicalPath {
+        self.resolver.resolve_ident(i)
+    }
+
+    pub fn resolve_method(&self, i: &'a syn::Ident) -> CanonicalPath {
+        self.resolver.resolve_method(i)
+    }
+
+    /// The path of the module the node is in
+    pub fn resolve_mod_path(&self) -> CanonicalPath {
+        self.resolver.resolve_mod_path()
+    }
+}
+
+/// The part of `Resolve` available to detectors, as `Resolve` itself can't be
+/// made into a trait object
+trait DetectorResolver<'a> {
+    fn resolve_path(&self, p: &'a syn::Path) -> CanonicalPath;
+    fn resolve_ident(&self, i: &'a syn::Ident) -> CanonicalPath;
+    fn resolve_method(&self, i: &'a syn::Ident) -> CanonicalPath;
+    fn resolve_mod_path(&self) -> CanonicalPath;
+}
+
+impl<'a, R: Resolve<'a>> DetectorResolver<'a> for R {
+    fn resolve_path(&self, p: &'a syn::Path) -> CanonicalPath {
+        Resolve::resolve_path(self, p)
+    }
+
+    fn resolve_ident(&self, i: &'a syn::Ident) -> CanonicalPath {
+        Resolve::resolve_ident(self, i)
+    }
+
+    fn resolve_method(&self, i: &'a syn::Ident) -> CanonicalPath {
+        Resolve::resolve_method(self, i)
+    }
+
+    fn resolve_mod_path(&self) -> CanonicalPath {
+        Resolve::resolve_mod_path(self)
+    }
+}
+
+static DETECTORS: RwLock<Vec<Arc<dyn EffectDetector>>> = RwLock::new(Vec::new());
+
+/// Registers a detector, to be run by every scan started afterwards
+pub fn register(detector: Arc<dyn EffectDetector>) {
+    DETECTORS.write().unwrap_or_else(|e| e.into_inner()).push(detector);
+}
+
+/// The detectors registered so far, in order of registration
+pub fn registered() -> Vec<Arc<dyn EffectDetector>> {
+    DETECTORS.read().unwrap_or_else(|e| e.into_inner()).clone()
+}
@@ -1,11 +1,15 @@
-use std::fs::{create_dir_all, remove_file, write, File};
-use std::path::PathBuf;
+use std::fs::{create_dir_all, remove_dir_all, remove_file, write, File};
+use std::path::{Path, PathBuf};
+use std::process::Command;
 
-use anyhow::Result;
+use crate::error::{Result, ScanError};
+use anyhow::anyhow;
 use cargo_lock::Package;
 use curl::easy::Easy;
 use flate2::read::GzDecoder;
 use log::info;
+use semver::Version;
+use sha2::{Digest, Sha256};
 use tar::Archive;
 
 fn get_crates_io_url(package_name: &str, package_version: &str) -> String {
@@ -15,24 +19,64 @@ fn get_crates_io_url(package_name: &str, package_version: &str) -> String {
     )
 }
 
+fn get_crates_io_versions_url(package_name: &str) -> String {
+    format!("https://crates.io/api/v1/crates/{}/versions", package_name)
+}
+
+fn get_crates_io_version_url(package_name: &str, package_version: &str) -> String {
+    format!("https://crates.io/api/v1/crates/{}/{}", package_name, package_version)
+}
+
+/// Fetches the contents of the url, after setting any extra options of the
+/// request with `configure`
+fn fetch(
+    url: &str,
+    configure: impl FnOnce(&mut Easy) -> Result<(), curl::Error>,
+) -> Result<Vec<u8>> {
+    let download_err = |source| ScanError::Download { url: url.to_string(), source };
+
+    let mut dst = Vec::new();
+    let mut easy = Easy::new();
+    easy.follow_location(true).map_err(download_err)?;
+    configure(&mut easy).map_err(download_err)?;
+    easy.url(url).map_err(download_err)?;
+
+    {
+        let mut transfer = easy.transfer();
+        transfer
+            .write_function(|data| {
+                dst.extend_from_slice(data);
+                Ok(data.len())
+            })
+            .map_err(download_err)?;
+        transfer.perform().map_err(download_err)?;
+    }
+
+    Ok(dst)
+}
+
+/// Downloads and unpacks the crate. If a checksum is given (e.g. the one
+/// recorded in Cargo.lock), the downloaded `.crate` file must match it before
+/// it is unpacked.
 fn download_crate(
     url: &str,
     package_name: &str,
     package_version: &str,
     download_dir: &str,
+    expected_checksum: Option<&str>,
 ) -> Result<PathBuf> {
-    let mut dst = Vec::new();
-    let mut easy = Easy::new();
-    easy.follow_location(true)?;
-    easy.url(url)?;
+    let dst = fetch(url, |_| Ok(()))?;
 
-    {
-        let mut transfer = easy.transfer();
-        transfer.write_function(|data| {
-            dst.extend_from_slice(data);
-            Ok(data.len())
-        })?;
-        transfer.perform()?;
+    if let Some(expected_checksum) = expected_checksum {
+        let checksum = format!("{:x}", Sha256::digest(&dst));
+        if checksum != expected_checksum {
+            return Err(ScanError::ChecksumMismatch {
+                package: package_name.to_string(),
+                version: package_version.to_string(),
+                expected: expected_checksum.to_string(),
+                actual: checksum,
+            });
+        }
     }
 
     let package_dir_name = format!("{}-{}", package_name, package_version);
@@ -76,17 +120,35 @@ fn download_crate(
     Ok(download_dir)
 }
 
-/// Downloads the crate from the package name and version
+/// Returns the checksum crates.io records for the published version of the
+/// crate, i.e. the one cargo checks downloads against
+fn get_crate_checksum(package_name: &str, package_version: &str) -> Result<String> {
+    // crates.io rejects API requests without a user agent
+    let dst = fetch(&get_crates_io_version_url(package_name, package_version), |easy| {
+        easy.useragent("cargo-scan")
+    })?;
+
+    let json: serde_json::Value = serde_json::from_slice(&dst)?;
+    let checksum = json["version"]["checksum"].as_str().ok_or_else(|| {
+        anyhow!("Couldn't find the checksum of {} v{}", package_name, package_version)
+    })?;
+    Ok(checksum.to_string())
+}
+
+/// Downloads the crate from the package name and version, verifying it
+/// against the checksum crates.io has for it
 pub fn download_crate_from_info(
     package_name: &str,
     package_version: &str,
     download_dir: &str,
 ) -> Result<PathBuf> {
+    let checksum = get_crate_checksum(package_name, package_version)?;
     let url = get_crates_io_url(package_name, package_version);
-    download_crate(&url, package_name, package_version, download_dir)
+    download_crate(&url, package_name, package_version, download_dir, Some(&checksum))
 }
 
-/// Downloads the crate from the `cargo_lock::Package`
+/// Downloads the crate from the `cargo_lock::Package`, verifying it against
+/// the package's checksum if it has one
 pub fn download_crate_from_package(
     package: &Package,
     download_dir: &str,
@@ -110,5 +172,149 @@ pub fn download_crate_from_package(
         package.name.as_ref(),
         &package.version.to_string(),
         download_dir,
+        package.checksum.as_ref().map(|c| c.to_string()).as_deref(),
     )
 }
+
+/// Runs git with the arguments in the directory
+fn git(dir: &Path, args: &[&str]) -> Result<()> {
+    let output = Command::new("git").arg("-C").arg(dir).args(args).output()?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "git {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        )
+        .into());
+    }
+    Ok(())
+}
+
+/// Clones the git repository into `dir`, replacing anything already there,
+/// and checks out the revision (a commit, branch or tag), or the default
+/// branch if none is given. Only the revision itself is fetched when the
+/// server allows it, falling back to the whole history otherwise (e.g. for
+/// abbreviated commit hashes).
+pub fn clone_git_repo(url: &str, rev: Option<&str>, dir: &Path) -> Result<()> {
+    if dir.exists() {
+        info!("Another clone of this repository already exists, cloning it again");
+        remove_dir_all(dir)?;
+    }
+    create_dir_all(dir)?;
+    git(dir, &["init", "--quiet"])?;
+    git(dir, &["remote", "add", "origin", url])?;
+
+    let rev = rev.unwrap_or("HEAD");
+    if git(dir, &["fetch", "--quiet", "--depth", "1", "origin", rev]).is_ok() {
+        return git(dir, &["checkout", "--quiet", "FETCH_HEAD"]);
+    }
+    info!("Couldn't fetch {} alone, fetching the whole repository", rev);
+    git(dir, &["fetch", "--quiet", "--tags", "origin"])?;
+    git(dir, &["checkout", "--quiet", rev])
+}
+
+/// Returns the latest `n` versions of the crate published to crates.io, in
+/// ascending order, skipping yanked versions
+pub fn get_crate_versions(package_name: &str, n: usize) -> Result<Vec<Version>> {
+    // crates.io rejects API requests without a user agent
+    let dst = fetch(&get_crates_io_versions_url(package_name), |easy| {
+        easy.useragent("cargo-scan")
+    })?;
+
+    let json: serde_json::Value = serde_json::from_slice(&dst)?;
+    let versions = json["versions"]
+        .as_array()
+        .ok_or_else(|| anyhow!("Couldn't find versions for crate {}", package_name))?;
+    let mut versions = versions
+        .iter()
+        .filter(|v| !v["yanked"].as_bool().unwrap_or(false))
+        .filter_map(|v| Version::parse(v["num"].as_str()?).ok())
+        .collect::<Vec<_>>();
+    versions.sort();
+
+    let skip = versions.len().saturating_sub(n);
+    Ok(versions.into_iter().skip(skip).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+
+    #[test]
+    fn test_download_crate_checksum() {
+        let tmp = std::env::temp_dir().join("cargo-scan-test-download-checksum");
+        let _ = remove_dir_all(&tmp);
+        let download_dir = tmp.join("downloads");
+        create_dir_all(&download_dir).unwrap();
+
+        // A .crate file is a gzipped tarball of the `name-version` directory
+        let mut tarball =
+            tar::Builder::new(GzEncoder::new(Vec::new(), Compression::default()));
+        let manifest = b"[package]";
+        let mut header = tar::Header::new_gnu();
+        header.set_size(manifest.len() as u64);
+        header.set_mode(0o644);
+        header.set_cksum();
+        tarball.append_data(&mut header, "foo-0.1.0/Cargo.toml", &manifest[..]).unwrap();
+        let bytes = tarball.into_inner().unwrap().finish().unwrap();
+        let crate_file = tmp.join("foo-0.1.0.crate");
+        write(&crate_file, &bytes).unwrap();
+
+        let url = format!("file://{}", crate_file.display());
+        let dir = download_dir.to_str().unwrap();
+        let checksum = format!("{:x}", Sha256::digest(&bytes));
+        let wrong = format!("{:x}", Sha256::digest(b"something else"));
+
+        let err = download_crate(&url, "foo", "0.1.0", dir, Some(&wrong)).unwrap_err();
+        assert!(matches!(err, ScanError::ChecksumMismatch { .. }));
+        assert!(!download_dir.join("foo-0.1.0").exists());
+
+        let path = download_crate(&url, "foo", "0.1.0", dir, Some(&checksum)).unwrap();
+        assert!(path.join("Cargo.toml").is_file());
+        remove_dir_all(&tmp).unwrap();
+    }
+
+    #[test]
+    fn test_clone_git_repo() {
+        let tmp = std::env::temp_dir().join("cargo-scan-test-clone-git-repo");
+        let _ = remove_dir_all(&tmp);
+        let origin = tmp.join("origin");
+        create_dir_all(&origin).unwrap();
+        let commit = |msg: &str| {
+            let user = ["-c", "user.name=t", "-c", "user.email=t@t"];
+            let args =
+                [&user[..], &["commit", "-q", "--allow-empty", "-m", msg]].concat();
+            git(&origin, &args).unwrap()
+        };
+        git(&origin, &["init", "--quiet"]).unwrap();
+        write(origin.join("Cargo.toml"), "[package]").unwrap();
+        git(&origin, &["add", "-A"]).unwrap();
+        commit("first");
+        let head = |dir: &Path| {
+            let out = Command::new("git")
+                .arg("-C")
+                .arg(dir)
+                .args(["rev-parse", "HEAD"])
+                .output()
+                .unwrap();
+            String::from_utf8(out.stdout).unwrap().trim().to_string()
+        };
+        let first = head(&origin);
+        commit("second");
+
+        let url = format!("file://{}", origin.display());
+        let clone = tmp.join("clone");
+        clone_git_repo(&url, None, &clone).unwrap();
+        assert_eq!(head(&clone), head(&origin));
+        assert!(clone.join("Cargo.toml").is_file());
+
+        // An abbreviated hash can't be fetched on its own
+        clone_git_repo(&url, Some(&first[..7]), &clone).unwrap();
+        assert_eq!(head(&clone), first);
+
+        assert!(clone_git_repo(&url, Some("no-such-rev"), &clone).is_err());
+        remove_dir_all(&tmp).unwrap();
+    }
+}
@@ -3,18 +3,21 @@
 //! The main types are:
 //! - Effect, which represents an abstract effect
 //! - EffectInstance, which represents an instance of an effect in source code
-//! - EffectBlock, which represents a block of source code which may contain
-//!     zero or more effects (such as an unsafe block).
+//! - FnDec, which represents the declaration of a function which may contain
+//!   zero or more effects
 
 use super::ident::{CanonicalPath, IdentPath};
-use super::sink::Sink;
-use super::util::csv;
+use super::schema::EFFECT_SCHEMA_VERSION;
+use super::sink::{Severity, Sink, SinkCategory};
+use super::util::{csv, root_relative_dir};
 
 use log::debug;
 use parse_display::{Display, FromStr};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use std::collections::HashSet;
 use std::fmt;
+use std::ops::Range;
 use std::path::{Path as FilePath, PathBuf as FilePathBuf};
 use syn;
 use syn::spanned::Spanned;
@@ -25,7 +28,9 @@ use syn::spanned::Spanned;
 */
 
 /// Data representing a source code location for some identifier, block, or expression
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash, Default)]
+#[derive(
+    Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash, Default, JsonSchema,
+)]
 pub struct SrcLoc {
     /// Directory in which the expression occurs
     dir: FilePathBuf,
@@ -36,6 +41,11 @@ pub struct SrcLoc {
     start_col: usize,
     end_line: usize,
     end_col: usize,
+    /// Byte offsets of the start and end in the file, if known
+    #[serde(default)]
+    start_byte: usize,
+    #[serde(default)]
+    end_byte: usize,
 }
 
 impl SrcLoc {
@@ -49,7 +59,23 @@ impl SrcLoc {
         // TBD: use unwrap_or_else
         let dir = filepath.parent().unwrap().to_owned();
         let file = FilePathBuf::from(filepath.file_name().unwrap());
-        Self { dir, file, start_line, start_col, end_line, end_col }
+        Self {
+            dir,
+            file,
+            start_line,
+            start_col,
+            end_line,
+            end_col,
+            start_byte: 0,
+            end_byte: 0,
+        }
+    }
+
+    /// Sets the byte offsets of the location in its file
+    pub fn with_byte_range(mut self, range: Range<usize>) -> Self {
+        self.start_byte = range.start;
+        self.end_byte = range.end;
+        self
     }
 
     pub fn from_span<S>(filepath: &FilePath, span: &S) -> Self
@@ -65,6 +91,22 @@ impl SrcLoc {
         let end_col = span_end.column;
 
         Self::new(filepath, start_line, start_col, end_line, end_col)
+            .with_byte_range(span.span().byte_range())
+    }
+
+    /// Whether the other location is within this one, e.g. an effect within
+    /// an unsafe block
+    pub fn contains(&self, other: &SrcLoc) -> bool {
+        self.dir == other.dir
+            && self.file == other.file
+            && (self.start_line, self.start_col) <= (other.start_line, other.start_col)
+            && (other.end_line, other.end_col) <= (self.end_line, self.end_col)
+    }
+
+    /// The location with its directory relative to the crate root `root`
+    /// (see [`root_relative_dir`])
+    pub fn relative_to(&self, root: &FilePath) -> Self {
+        Self { dir: root_relative_dir(&self.dir, root), ..self.clone() }
     }
 
     pub fn sub1(&self) -> Self {
@@ -79,13 +121,23 @@ impl SrcLoc {
     }
 
     pub fn csv_header() -> &'static str {
-        "dir, file, line, col"
+        "dir, file, line, col, end_line, end_col, start_byte, end_byte"
     }
 
     pub fn to_csv(&self) -> String {
         let dir = csv::sanitize_path(&self.dir);
         let file = csv::sanitize_path(&self.file);
-        format!("{}, {}, {}, {}", dir, file, self.start_line, self.start_col)
+        format!(
+            "{}, {}, {}, {}, {}, {}, {}, {}",
+            dir,
+            file,
+            self.start_line,
+            self.start_col,
+            self.end_line,
+            self.end_col,
+            self.start_byte,
+            self.end_byte
+        )
     }
 
     pub fn dir(&self) -> &FilePathBuf {
@@ -112,6 +164,12 @@ impl SrcLoc {
         self.end_col
     }
 
+    /// The byte offsets of the location in its file. Empty (`0..0`) if
+    /// unknown, e.g. for locations saved by older versions.
+    pub fn byte_range(&self) -> Range<usize> {
+        self.start_byte..self.end_byte
+    }
+
     pub fn filepath_string(&self) -> String {
         self.dir.join(&self.file).to_string_lossy().to_string()
     }
@@ -140,7 +198,7 @@ impl fmt::Display for SrcLoc {
 /// - a sink pattern in the standard library
 /// - an FFI call
 /// - an unsafe operation such as a pointer dereference
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash, JsonSchema)]
 pub enum Effect {
     /// Function call (callee path) matching a sink pattern
     SinkCall(Sink),
@@ -148,10 +206,21 @@ pub enum Effect {
     FFICall(CanonicalPath),
     /// Unsafe function/method call
     UnsafeCall(CanonicalPath),
-    /// Pointer dereference
+    /// Pointer dereference which reads through the pointer, or a call which
+    /// does, e.g. `ptr::read`
     RawPointer(CanonicalPath),
+    /// Pointer dereference which writes through the pointer, i.e. one assigned
+    /// to, or a call which does, e.g. `ptr::write`
+    RawPointerWrite(CanonicalPath),
+    /// Call doing pointer arithmetic, e.g. `ptr.offset(n)`
+    PtrOffset(CanonicalPath),
     /// Reading a union field
-    UnionField(CanonicalPath),
+    // Named `UnionField` before writes were tracked as well (see `schema`)
+    UnionFieldRead(CanonicalPath),
+    /// Assigning to a union field
+    /// Note: This effect isn't unsafe, but matters for auditing type punning
+    /// along with reads of the field
+    UnionFieldWrite(CanonicalPath),
     /// Accessing a global mutable variable
     StaticMut(CanonicalPath),
     /// Accessing an external mutable variable
@@ -166,18 +235,56 @@ pub enum Effect {
     RawPtrCast,
     /// Declaration of a foreign function
     FFIDecl(CanonicalPath),
+    /// Call through a function pointer which holds a sink or FFI function
+    /// (origin path)
+    IndirectCall(CanonicalPath),
+    /// Call to `mem::transmute` or `mem::transmute_copy`
+    Transmute(CanonicalPath),
+    /// Inline assembly, i.e. an `asm!` or `global_asm!` invocation
+    InlineAsm,
+    /// Call which produces a value from possibly uninitialized memory, e.g.
+    /// `mem::zeroed` or `MaybeUninit::assume_init`
+    UninitMem(CanonicalPath),
+    /// Call which constructs an owned value or reference from a raw pointer,
+    /// e.g. `Box::from_raw` or `slice::from_raw_parts`
+    RawConstruct(CanonicalPath),
+    /// Loading a dynamic library at run time, e.g. `dlopen` (callee path and
+    /// the library, if given as a literal)
+    DynLoad(CanonicalPath, Option<String>),
+    /// Definition of a function callable from foreign code, i.e. one with
+    /// `#[no_mangle]`, `#[export_name]` or a non-Rust ABI (the exported
+    /// symbol, if it's exported by name)
+    FFIExport(Option<String>),
+    /// `unsafe impl` of `Send` or `Sync` (trait path), which claims the type
+    /// is thread safe. The callee is the implementing type.
+    SendSyncImpl(CanonicalPath),
+    /// Effect reported by a custom `EffectDetector` (the detector's name)
+    Custom(String),
 }
 impl Effect {
-    fn sink_pattern(&self) -> Option<&Sink> {
+    /// The sink called, for sink calls, and for indirect calls through a
+    /// function pointer holding a sink
+    fn sink_pattern(&self) -> Option<Sink> {
         match self {
-            Self::SinkCall(s) => Some(s),
+            Self::SinkCall(s) => Some(s.clone()),
+            Self::IndirectCall(origin) => Sink::new_process_spawn(origin)
+                .or_else(|| Sink::new_match(origin, &Sink::default_sinks())),
             _ => None,
         }
     }
 
     /// Return true if the type of unsafety is something that Rust considers unsafe.
     fn is_rust_unsafe(&self) -> bool {
-        !matches!(self, Self::SinkCall(_) | Self::FnPtrCreation | Self::ClosureCreation)
+        !matches!(
+            self,
+            Self::SinkCall(_)
+                | Self::FnPtrCreation
+                | Self::ClosureCreation
+                | Self::IndirectCall(_)
+                | Self::FFIExport(_)
+                | Self::UnionFieldWrite(_)
+                | Self::Custom(_)
+        )
     }
 
     fn simple_str(&self) -> &str {
@@ -186,18 +293,63 @@ impl Effect {
             Self::FFICall(_) => "[FFI Call]",
             Self::UnsafeCall(_) => "[UnsafeCall]",
             Self::RawPointer(_) => "[PtrDeref]",
-            Self::UnionField(_) => "[UnionField]",
+            Self::RawPointerWrite(_) => "[PtrWrite]",
+            Self::PtrOffset(_) => "[PtrOffset]",
+            Self::UnionFieldRead(_) => "[UnionField]",
+            Self::UnionFieldWrite(_) => "[UnionFieldWrite]",
             Self::StaticMut(_) => "[StaticMutVar]",
             Self::StaticExt(_) => "[StaticExtVar]",
             Self::FnPtrCreation => "[FnPtrCreation]",
             Self::ClosureCreation => "[ClosureCreation]",
             Self::RawPtrCast => "[RawPtrCast]",
             Self::FFIDecl(_) => "[FFI Declaration]",
+            Self::IndirectCall(_) => "[IndirectCall]",
+            Self::Transmute(_) => "[Transmute]",
+            Self::InlineAsm => "[InlineAsm]",
+            Self::UninitMem(_) => "[UninitMem]",
+            Self::RawConstruct(_) => "[RawConstruct]",
+            Self::DynLoad(_, _) => "[DynLoad]",
+            Self::FFIExport(_) => "[FFI Export]",
+            Self::SendSyncImpl(_) => "[SendSyncImpl]",
+            Self::Custom(name) => name.as_str(),
         }
     }
 
     pub fn to_csv(&self) -> String {
-        csv::sanitize_comma(self.simple_str())
+        match self {
+            // The callee is the function pointer, so name the function reached
+            Self::IndirectCall(origin) => {
+                csv::sanitize_comma(&format!("{} {}", self.simple_str(), origin))
+            }
+            _ => csv::sanitize_comma(self.simple_str()),
+        }
+    }
+
+    /// The category of the sink, for sink calls and indirect calls to sinks
+    pub fn sink_category(&self) -> Option<SinkCategory> {
+        self.sink_pattern().as_ref().map(Sink::category)
+    }
+
+    /// The severity of the sink, for sink calls and indirect calls to sinks
+    pub fn sink_severity(&self) -> Option<Severity> {
+        self.sink_pattern().as_ref().map(Sink::severity)
+    }
+
+    /// The function reached, for indirect calls through a function pointer
+    pub fn indirect_origin(&self) -> Option<&CanonicalPath> {
+        match self {
+            Self::IndirectCall(origin) => Some(origin),
+            _ => None,
+        }
+    }
+
+    /// The library being loaded, for dynamic library loads with a literal
+    /// argument
+    pub fn dyn_load_library(&self) -> Option<&str> {
+        match self {
+            Self::DynLoad(_, lib) => lib.as_deref(),
+            _ => None,
+        }
     }
 
     pub fn is_ffi_decl(&self) -> bool {
@@ -207,37 +359,79 @@ impl Effect {
 
 /// This is a field-less copy of Effect for easy pattern matching and passing
 /// command-line arguments.
-#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Display, FromStr)]
+#[derive(
+    Serialize,
+    Deserialize,
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    Hash,
+    Display,
+    FromStr,
+    JsonSchema,
+)]
 pub enum EffectType {
     SinkCall,
     FFICall,
     UnsafeCall,
     RawPointer,
-    UnionField,
+    RawPointerWrite,
+    PtrOffset,
+    UnionFieldRead,
+    UnionFieldWrite,
     StaticMut,
     StaticExt,
     FnPtrCreation,
     ClosureCreation,
     RawPtrCast,
     FFIDecl,
+    IndirectCall,
+    Transmute,
+    InlineAsm,
+    UninitMem,
+    RawConstruct,
+    DynLoad,
+    FFIExport,
+    SendSyncImpl,
+    Custom,
 }
 
-impl EffectType {
-    pub fn matches_effect(types: &[EffectType], e: &Effect) -> bool {
+impl From<&Effect> for EffectType {
+    fn from(e: &Effect) -> Self {
         match e {
-            Effect::SinkCall(_) => types.contains(&EffectType::SinkCall),
-            Effect::FFICall(_) => types.contains(&EffectType::FFICall),
-            Effect::UnsafeCall(_) => types.contains(&EffectType::UnsafeCall),
-            Effect::RawPointer(_) => types.contains(&EffectType::RawPointer),
-            Effect::UnionField(_) => types.contains(&EffectType::UnionField),
-            Effect::StaticMut(_) => types.contains(&EffectType::StaticMut),
-            Effect::StaticExt(_) => types.contains(&EffectType::StaticExt),
-            Effect::FnPtrCreation => types.contains(&EffectType::FnPtrCreation),
-            Effect::ClosureCreation => types.contains(&EffectType::ClosureCreation),
-            Effect::RawPtrCast => types.contains(&EffectType::RawPtrCast),
-            Effect::FFIDecl(_) => types.contains(&EffectType::FFIDecl),
+            Effect::SinkCall(_) => EffectType::SinkCall,
+            Effect::FFICall(_) => EffectType::FFICall,
+            Effect::UnsafeCall(_) => EffectType::UnsafeCall,
+            Effect::RawPointer(_) => EffectType::RawPointer,
+            Effect::RawPointerWrite(_) => EffectType::RawPointerWrite,
+            Effect::PtrOffset(_) => EffectType::PtrOffset,
+            Effect::UnionFieldRead(_) => EffectType::UnionFieldRead,
+            Effect::UnionFieldWrite(_) => EffectType::UnionFieldWrite,
+            Effect::StaticMut(_) => EffectType::StaticMut,
+            Effect::StaticExt(_) => EffectType::StaticExt,
+            Effect::FnPtrCreation => EffectType::FnPtrCreation,
+            Effect::ClosureCreation => EffectType::ClosureCreation,
+            Effect::RawPtrCast => EffectType::RawPtrCast,
+            Effect::FFIDecl(_) => EffectType::FFIDecl,
+            Effect::IndirectCall(_) => EffectType::IndirectCall,
+            Effect::Transmute(_) => EffectType::Transmute,
+            Effect::InlineAsm => EffectType::InlineAsm,
+            Effect::UninitMem(_) => EffectType::UninitMem,
+            Effect::RawConstruct(_) => EffectType::RawConstruct,
+            Effect::DynLoad(_, _) => EffectType::DynLoad,
+            Effect::FFIExport(_) => EffectType::FFIExport,
+            Effect::SendSyncImpl(_) => EffectType::SendSyncImpl,
+            Effect::Custom(_) => EffectType::Custom,
         }
     }
+}
+
+impl EffectType {
+    pub fn matches_effect(types: &[EffectType], e: &Effect) -> bool {
+        types.contains(&EffectType::from(e))
+    }
 
     pub fn unsafe_effects() -> Vec<EffectType> {
         vec![
@@ -245,12 +439,24 @@ impl EffectType {
             EffectType::FFICall,
             EffectType::UnsafeCall,
             EffectType::RawPointer,
-            EffectType::UnionField,
+            EffectType::RawPointerWrite,
+            EffectType::PtrOffset,
+            EffectType::UnionFieldRead,
+            EffectType::UnionFieldWrite,
             EffectType::StaticMut,
             EffectType::StaticExt,
             EffectType::FnPtrCreation,
             EffectType::ClosureCreation,
             EffectType::FFIDecl,
+            EffectType::IndirectCall,
+            EffectType::Transmute,
+            EffectType::InlineAsm,
+            EffectType::UninitMem,
+            EffectType::RawConstruct,
+            EffectType::DynLoad,
+            EffectType::FFIExport,
+            EffectType::SendSyncImpl,
+            EffectType::Custom,
         ]
     }
 }
@@ -262,18 +468,287 @@ pub const DEFAULT_EFFECT_TYPES: &[EffectType] = &[
     EffectType::FFICall,
     EffectType::UnsafeCall,
     EffectType::RawPointer,
-    EffectType::UnionField,
+    EffectType::RawPointerWrite,
+    EffectType::PtrOffset,
+    EffectType::UnionFieldRead,
+    EffectType::UnionFieldWrite,
     EffectType::StaticMut,
     EffectType::StaticExt,
     EffectType::FnPtrCreation,
     EffectType::ClosureCreation,
     EffectType::FFIDecl,
+    EffectType::IndirectCall,
+    EffectType::Transmute,
+    EffectType::InlineAsm,
+    EffectType::UninitMem,
+    EffectType::RawConstruct,
+    EffectType::DynLoad,
+    EffectType::FFIExport,
+    EffectType::SendSyncImpl,
+    EffectType::Custom,
+];
+
+/// The set of effect types a function may perform, either directly or
+/// transitively through the functions it calls.
+pub type EffectSignature = HashSet<EffectType>;
+
+/// Format an effect signature as a sorted, comma-separated list
+pub fn signature_to_string(sig: &EffectSignature) -> String {
+    let mut types = sig.iter().map(|t| t.to_string()).collect::<Vec<_>>();
+    types.sort();
+    if types.is_empty() {
+        "none".to_string()
+    } else {
+        types.join(", ")
+    }
+}
+
+/// Functions in `std` and `core` which reinterpret the bits of a value as
+/// another type
+const TRANSMUTE_FNS: &[&str] = &["transmute", "transmute_copy"];
+
+fn is_transmute(callee: &CanonicalPath) -> bool {
+    let krate = callee.crate_name();
+    let fn_name = callee.as_str().rsplit("::").next().unwrap_or_default();
+    matches!(krate.as_str(), "std" | "core") && TRANSMUTE_FNS.contains(&fn_name)
+}
+
+/// Functions and methods of raw pointers in `std` and `core` which read
+/// through the pointer, write through it, or do arithmetic on it
+const PTR_READ_FNS: &[&str] = &["read", "read_unaligned", "read_volatile"];
+const PTR_WRITE_FNS: &[&str] = &[
+    "write",
+    "write_unaligned",
+    "write_volatile",
+    "write_bytes",
+    "copy",
+    "copy_nonoverlapping",
+    "swap",
+    "swap_nonoverlapping",
+    "replace",
+    "drop_in_place",
+];
+const PTR_OFFSET_FNS: &[&str] = &[
+    "offset",
+    "add",
+    "sub",
+    "byte_offset",
+    "byte_add",
+    "byte_sub",
+    "offset_from",
+    "byte_offset_from",
+];
+
+/// Modules and types in `std` and `core` holding raw pointer functions and
+/// methods, e.g. `core::ptr::mut_ptr` for the methods of `*mut T`
+const PTR_MODS: &[&str] = &["ptr", "const_ptr", "mut_ptr", "non_null", "NonNull"];
+
+/// Returns the effect of a call which reads, writes, or offsets a raw pointer
+fn raw_ptr_call(callee: &CanonicalPath) -> Option<Effect> {
+    let krate = callee.crate_name();
+    if !matches!(krate.as_str(), "std" | "core") {
+        return None;
+    }
+    let mut segments = callee.as_str().split("::").collect::<Vec<_>>();
+    let fn_name = segments.pop()?;
+    if !segments.iter().any(|s| PTR_MODS.contains(s)) {
+        return None;
+    }
+    if PTR_READ_FNS.contains(&fn_name) {
+        Some(Effect::RawPointer(callee.clone()))
+    } else if PTR_WRITE_FNS.contains(&fn_name) {
+        Some(Effect::RawPointerWrite(callee.clone()))
+    } else if PTR_OFFSET_FNS.contains(&fn_name) {
+        Some(Effect::PtrOffset(callee.clone()))
+    } else {
+        None
+    }
+}
+
+/// Functions in `std` and `core` which produce a value from possibly
+/// uninitialized (or zeroed) memory, by the type or module they're in
+const UNINIT_MEM_FNS: &[(&str, &[&str])] = &[
+    ("mem", &["uninitialized", "zeroed"]),
+    (
+        "MaybeUninit",
+        &[
+            "assume_init",
+            "assume_init_read",
+            "assume_init_ref",
+            "assume_init_mut",
+            "assume_init_drop",
+            "array_assume_init",
+            "slice_assume_init_ref",
+            "slice_assume_init_mut",
+        ],
+    ),
+];
+
+fn is_uninit_mem(callee: &CanonicalPath) -> bool {
+    let krate = callee.crate_name();
+    let mut rev = callee.as_str().rsplit("::");
+    let (Some(fn_name), Some(parent)) = (rev.next(), rev.next()) else {
+        return false;
+    };
+    matches!(krate.as_str(), "std" | "core")
+        && UNINIT_MEM_FNS.iter().any(|(p, fns)| *p == parent && fns.contains(&fn_name))
+}
+
+/// Functions in `std`, `core` and `alloc` which construct an owned value or a
+/// reference from a raw pointer, by their path suffix. These assume the
+/// pointer's ownership and lifetime, which auditors must check separately
+/// from plain dereferences.
+const RAW_CONSTRUCT_FNS: &[&str] = &[
+    "Vec::from_raw_parts",
+    "Vec::from_raw_parts_in",
+    "Box::from_raw",
+    "Box::from_raw_in",
+    "Box::from_non_null",
+    "String::from_raw_parts",
+    "slice::from_raw_parts",
+    "slice::from_raw_parts_mut",
+    "slice::raw::from_raw_parts",
+    "slice::raw::from_raw_parts_mut",
+    "CStr::from_ptr",
+    "CString::from_raw",
+    "Rc::from_raw",
+    "Arc::from_raw",
+    "Weak::from_raw",
+];
+
+/// Returns true if the path ends with the given path suffix, e.g.
+/// `std::boxed::Box::from_raw` ends with `Box::from_raw`
+fn has_path_suffix(path: &str, suffix: &str) -> bool {
+    path == suffix
+        || path.strip_suffix(suffix).is_some_and(|prefix| prefix.ends_with("::"))
+}
+
+/// Returns true if the callee constructs a value from a raw pointer. Without
+/// rust-analyzer, types from the prelude aren't resolved, so e.g. a plain
+/// `Box::from_raw` path also matches.
+fn is_raw_construct(callee: &CanonicalPath) -> bool {
+    let krate = callee.crate_name();
+    let path = callee.as_str();
+    if matches!(krate.as_str(), "std" | "core" | "alloc") {
+        RAW_CONSTRUCT_FNS.iter().any(|f| has_path_suffix(path, f))
+    } else {
+        RAW_CONSTRUCT_FNS.contains(&path)
+    }
+}
+
+/// Functions which load a dynamic library at run time, per crate. Since the
+/// loaded code can't be seen statically, these defeat any audit of the crate.
+const DYN_LOAD_FNS: &[(&str, &[&str])] = &[
+    ("libloading", &["Library::new", "Library::open", "Library::load_with_flags"]),
+    ("dlopen", &["Library::open", "Container::load"]),
+    ("dlopen2", &["Library::open", "Container::load"]),
+    ("libc", &["dlopen", "dlmopen"]),
+    ("winapi", &["LoadLibraryA", "LoadLibraryW", "LoadLibraryExA", "LoadLibraryExW"]),
+    ("windows", &["LoadLibraryA", "LoadLibraryW", "LoadLibraryExA", "LoadLibraryExW"]),
+    (
+        "windows_sys",
+        &["LoadLibraryA", "LoadLibraryW", "LoadLibraryExA", "LoadLibraryExW"],
+    ),
 ];
 
+/// Returns true if the callee loads a dynamic library. Function names are
+/// matched as a suffix of the path, so that e.g. both
+/// `libloading::Library::new` and `libloading::os::unix::Library::new` match.
+fn is_dyn_load(callee: &CanonicalPath) -> bool {
+    let krate = callee.crate_name();
+    let path = callee.as_str();
+    DYN_LOAD_FNS
+        .iter()
+        .filter(|(c, _)| *c == krate.as_str())
+        .any(|(_, fns)| fns.iter().any(|f| has_path_suffix(path, f)))
+}
+
+/// Where the code containing an effect runs
+#[derive(
+    Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash, Default, JsonSchema,
+)]
+#[serde(rename_all = "kebab-case")]
+pub enum Provenance {
+    /// The crate's own source, i.e. code that ends up in the built artifact
+    #[default]
+    Src,
+    /// The crate's build script, or the code of a crate which is only used
+    /// by build scripts. This runs on the developer's machine at compile time.
+    Build,
+    /// The code of a proc-macro crate, or of a crate which is only used by
+    /// proc macros. This runs inside the compiler at compile time.
+    ProcMacro,
+}
+
+impl Provenance {
+    pub fn is_src(&self) -> bool {
+        *self == Provenance::Src
+    }
+}
+
+impl fmt::Display for Provenance {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Provenance::Src => write!(f, "src"),
+            Provenance::Build => write!(f, "build"),
+            Provenance::ProcMacro => write!(f, "proc-macro"),
+        }
+    }
+}
+
+/// The kind of target the code containing an effect belongs to
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    Serialize,
+    Deserialize,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    Default,
+    JsonSchema,
+)]
+#[serde(rename_all = "kebab-case")]
+pub enum TargetKind {
+    /// The crate's library or binaries
+    #[default]
+    Src,
+    /// Integration tests, `#[cfg(test)]` code and `#[test]` functions
+    Test,
+    /// Examples, under `examples/`
+    Example,
+    /// Benchmarks, under `benches/`
+    Bench,
+}
+
+impl TargetKind {
+    pub fn is_src(&self) -> bool {
+        *self == TargetKind::Src
+    }
+}
+
+impl fmt::Display for TargetKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TargetKind::Src => write!(f, "src"),
+            TargetKind::Test => write!(f, "test"),
+            TargetKind::Example => write!(f, "example"),
+            TargetKind::Bench => write!(f, "bench"),
+        }
+    }
+}
+
 /// Type representing an Effect instance, with complete context.
 /// This includes a field for which Effect it is an instance of.
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash, JsonSchema)]
 pub struct EffectInstance {
+    /// Version of the serialized format (see `schema`)
+    #[serde(default)]
+    schema_version: u32,
+
     /// Path to the caller function or module scope (Rust path::to::fun)
     caller: CanonicalPath,
 
@@ -286,6 +761,24 @@ pub struct EffectInstance {
     /// EffectInstance type
     /// If Sink, this includes the effect pattern -- prefix of callee (effect), e.g. libc.
     eff_type: Effect,
+
+    /// Where the code containing the effect runs
+    #[serde(default, skip_serializing_if = "Provenance::is_src")]
+    provenance: Provenance,
+
+    /// The kind of target the code containing the effect belongs to
+    #[serde(default, skip_serializing_if = "TargetKind::is_src")]
+    target_kind: TargetKind,
+
+    /// The feature-dependent `#[cfg(...)]` predicates of the items enclosing
+    /// the effect, outermost first
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    cfg_gates: Vec<String>,
+
+    /// The platform-dependent `#[cfg(...)]` predicates of the items
+    /// enclosing the effect, e.g. `unix`, outermost first
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    platform_gates: Vec<String>,
 }
 
 impl EffectInstance {
@@ -306,7 +799,15 @@ impl EffectInstance {
     {
         // Code to classify an effect based on call site information
         let call_loc = SrcLoc::from_span(filepath, callsite);
-        let eff_type = if let Some(ffi) = ffi {
+        let eff_type = if is_dyn_load(&callee) {
+            // Takes priority over the FFI and unsafe call classifications, as
+            // loading code at run time is the more important thing to audit
+            Some(Effect::DynLoad(callee.clone(), None))
+        } else if let Some(pat) = Sink::new_process_spawn(&callee) {
+            // Takes priority over the FFI classification, so that spawning a
+            // process through e.g. libc::fork is reported as such
+            Some(Effect::SinkCall(pat))
+        } else if let Some(ffi) = ffi {
             if !is_unsafe {
                 // This case can occur in certain contexts, e.g. with
                 // the wasm_bindgen attribute
@@ -327,6 +828,17 @@ impl EffectInstance {
                 );
             }
             Some(Effect::FFICall(ffi))
+        } else if is_transmute(&callee) {
+            // Takes priority over the std::mem sink pattern
+            Some(Effect::Transmute(callee.clone()))
+        } else if is_uninit_mem(&callee) {
+            // Also takes priority over the std::mem sink pattern
+            Some(Effect::UninitMem(callee.clone()))
+        } else if is_raw_construct(&callee) {
+            // Takes priority over the std::ffi sink pattern for CStr/CString
+            Some(Effect::RawConstruct(callee.clone()))
+        } else if let Some(eff) = raw_ptr_call(&callee) {
+            Some(eff)
         } else if let Some(pat) = Sink::new_match(&callee, sinks) {
             // callee.remove_src_loc();
             Some(Effect::SinkCall(pat))
@@ -335,7 +847,17 @@ impl EffectInstance {
         } else {
             None
         };
-        Some(Self { caller, call_loc, callee, eff_type: eff_type? })
+        Some(Self {
+            schema_version: EFFECT_SCHEMA_VERSION,
+            caller,
+            call_loc,
+            callee,
+            eff_type: eff_type?,
+            provenance: Provenance::Src,
+            target_kind: TargetKind::Src,
+            cfg_gates: Vec::new(),
+            platform_gates: Vec::new(),
+        })
     }
 
     pub fn new_effect<S>(
@@ -349,7 +871,17 @@ impl EffectInstance {
         S: Spanned,
     {
         let call_loc = SrcLoc::from_span(filepath, eff_site);
-        Self { caller, call_loc, callee, eff_type }
+        Self {
+            schema_version: EFFECT_SCHEMA_VERSION,
+            caller,
+            call_loc,
+            callee,
+            eff_type,
+            provenance: Provenance::Src,
+            target_kind: TargetKind::Src,
+            cfg_gates: Vec::new(),
+            platform_gates: Vec::new(),
+        }
     }
 
     pub fn caller(&self) -> &CanonicalPath {
@@ -374,15 +906,25 @@ impl EffectInstance {
     }
 
     pub fn csv_header() -> &'static str {
-        "crate, fn_decl, callee, effect, dir, file, line, col"
+        "crate, fn_decl, callee, effect, dir, file, line, col, end_line, end_col, start_byte, end_byte"
     }
 
     pub fn to_csv(&self) -> String {
+        self.csv_with_loc(&self.call_loc)
+    }
+
+    /// Like `to_csv`, with the directory of the effect relative to the crate
+    /// root `root`
+    pub fn to_csv_relative_to(&self, root: &FilePath) -> String {
+        self.csv_with_loc(&self.call_loc.relative_to(root))
+    }
+
+    fn csv_with_loc(&self, loc: &SrcLoc) -> String {
         let crt = self.caller.crate_name().to_string();
         let caller = self.caller.to_string();
         let callee = csv::sanitize_comma(self.callee.as_str());
         let effect = self.eff_type.to_csv();
-        let call_loc_csv = self.call_loc.to_csv();
+        let call_loc_csv = loc.to_csv();
 
         format!("{}, {}, {}, {}, {}", crt, caller, callee, effect, call_loc_csv)
     }
@@ -391,10 +933,35 @@ impl EffectInstance {
         &self.eff_type
     }
 
-    pub fn pattern(&self) -> Option<&Sink> {
+    /// Records the library loaded by a dynamic library load, when the call's
+    /// argument is a literal. Does nothing for other effects.
+    pub(crate) fn set_dyn_load_library(&mut self, library: String) {
+        if let Effect::DynLoad(_, lib @ None) = &mut self.eff_type {
+            *lib = Some(library);
+        }
+    }
+
+    /// Records the function an indirect call reaches, once the field or
+    /// static it's made through is known to hold it. Does nothing for other
+    /// effects.
+    pub(crate) fn set_indirect_origin(&mut self, origin: CanonicalPath) {
+        if let Effect::IndirectCall(o) = &mut self.eff_type {
+            *o = origin;
+        }
+    }
+
+    pub fn pattern(&self) -> Option<Sink> {
         self.eff_type.sink_pattern()
     }
 
+    pub fn sink_category(&self) -> Option<SinkCategory> {
+        self.eff_type.sink_category()
+    }
+
+    pub fn sink_severity(&self) -> Option<Severity> {
+        self.eff_type.sink_severity()
+    }
+
     /// Return true if the type of unsafety is something that Rust considers unsafe.
     pub fn is_rust_unsafe(&self) -> bool {
         self.eff_type.is_rust_unsafe()
@@ -403,12 +970,71 @@ impl EffectInstance {
     pub fn call_loc(&self) -> &SrcLoc {
         &self.call_loc
     }
+
+    pub fn set_caller(&mut self, caller: CanonicalPath) {
+        self.caller = caller;
+    }
+
+    pub fn set_call_loc(&mut self, call_loc: SrcLoc) {
+        self.call_loc = call_loc;
+    }
+
+    pub fn provenance(&self) -> Provenance {
+        self.provenance
+    }
+
+    pub fn set_provenance(&mut self, provenance: Provenance) {
+        self.provenance = provenance;
+    }
+
+    pub fn target_kind(&self) -> TargetKind {
+        self.target_kind
+    }
+
+    pub fn set_target_kind(&mut self, target_kind: TargetKind) {
+        self.target_kind = target_kind;
+    }
+
+    pub fn cfg_gates(&self) -> &[String] {
+        &self.cfg_gates
+    }
+
+    pub fn set_cfg_gates(&mut self, cfg_gates: Vec<String>) {
+        self.cfg_gates = cfg_gates;
+    }
+
+    pub fn platform_gates(&self) -> &[String] {
+        &self.platform_gates
+    }
+
+    pub fn set_platform_gates(&mut self, platform_gates: Vec<String>) {
+        self.platform_gates = platform_gates;
+    }
 }
 
 /*
     Data model for effect blocks (unsafe blocks, functions, and impls)
 */
 
+#[derive(PartialEq, Debug, Serialize, Deserialize, Clone, Hash, Eq, JsonSchema)]
+pub struct EffectInfo {
+    pub caller_path: CanonicalPath,
+    pub callee_loc: SrcLoc,
+}
+
+impl EffectInfo {
+    pub fn new(caller_path: CanonicalPath, callee_loc: SrcLoc) -> Self {
+        EffectInfo { caller_path, callee_loc }
+    }
+
+    pub fn from_instance(effect: &EffectInstance) -> Self {
+        let caller_src_path = effect.caller().clone();
+        let callee_loc = effect.call_loc().clone();
+
+        EffectInfo::new(caller_src_path, callee_loc)
+    }
+}
+
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
 pub enum Visibility {
     Public,
@@ -457,3 +1083,24 @@ impl FnDec {
 fn test_csv_header() {
     assert!(EffectInstance::csv_header().ends_with(SrcLoc::csv_header()));
 }
+
+#[test]
+fn test_src_loc_byte_range() {
+    let src = "fn f() {\n    g(\"é\", 1);\n}\n";
+    let item: syn::ItemFn = syn::parse_str(src).unwrap();
+    let syn::Stmt::Expr(expr, _) = &item.block.stmts[0] else { panic!() };
+    let loc = SrcLoc::from_span(FilePath::new("src/lib.rs"), expr);
+    assert_eq!(loc.to_csv(), "src, lib.rs, 2, 4, 2, 13, 13, 23");
+    assert_eq!(&src[loc.byte_range()], "g(\"é\", 1)");
+}
+
+#[test]
+fn test_src_loc_relative_to() {
+    let loc = SrcLoc::new(FilePath::new("/home/a/foo/src/bar/baz.rs"), 1, 0, 1, 0);
+    let root = FilePath::new("/home/a/foo");
+    assert_eq!(loc.relative_to(root).dir(), FilePath::new("src/bar"));
+    let build_rs = SrcLoc::new(FilePath::new("/home/a/foo/build.rs"), 1, 0, 1, 0);
+    assert_eq!(build_rs.relative_to(root).dir(), FilePath::new("."));
+    let outside = SrcLoc::new(FilePath::new("/home/b/lib.rs"), 1, 0, 1, 0);
+    assert_eq!(outside.relative_to(root).dir(), FilePath::new("/home/b"));
+}
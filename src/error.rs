@@ -0,0 +1,141 @@
+//! Errors returned by the library.
+//!
+//! The scanner, audit file, audit chain and download functions return a
+//! [`ScanError`], so that programs using cargo-scan as a library can tell
+//! e.g. a crate that failed to download from a file that failed to parse.
+//! Errors without a more specific kind are wrapped in [`ScanError::Other`].
+
+use std::path::PathBuf;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum ScanError {
+    /// The path doesn't point to a crate
+    #[error("Path is not a crate; {reason}: {path:?}")]
+    NotACrate { path: PathBuf, reason: &'static str },
+
+    /// A source file isn't valid Rust
+    #[error("Failed to parse {path:?}: {source}")]
+    Parse {
+        path: PathBuf,
+        #[source]
+        source: syn::Error,
+    },
+
+    /// rust-analyzer couldn't load the crate or resolve the names in a file
+    #[error("Failed to resolve names in {path:?}: {source}")]
+    Resolution {
+        path: PathBuf,
+        #[source]
+        source: Box<dyn std::error::Error + Send + Sync>,
+    },
+
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+
+    /// An audit file or report isn't valid JSON, or doesn't have the
+    /// expected fields
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+
+    /// A report or audit file isn't valid CBOR, or doesn't have the expected
+    /// fields
+    #[error("Invalid CBOR: {0}")]
+    CborDecode(#[from] ciborium::de::Error<std::io::Error>),
+
+    #[error("Failed to encode CBOR: {0}")]
+    CborEncode(#[from] ciborium::ser::Error<std::io::Error>),
+
+    /// An audit chain manifest isn't valid TOML, or doesn't have the
+    /// expected fields
+    #[error(transparent)]
+    Toml(#[from] toml::de::Error),
+
+    /// A crate's Cargo.toml couldn't be read
+    #[error(transparent)]
+    Manifest(#[from] cargo_toml::Error),
+
+    /// A crate's Cargo.lock couldn't be read
+    #[error(transparent)]
+    Lockfile(#[from] cargo_lock::Error),
+
+    /// A request to the crate's registry failed
+    #[cfg(feature = "native")]
+    #[error("Failed to download {url}: {source}")]
+    Download {
+        url: String,
+        #[source]
+        source: curl::Error,
+    },
+
+    /// A downloaded crate doesn't match the checksum it is expected to have
+    #[error(
+        "Checksum mismatch for {package} v{version}: expected {expected}, but the download has {actual}"
+    )]
+    ChecksumMismatch {
+        package: String,
+        version: String,
+        expected: String,
+        actual: String,
+    },
+
+    /// An audit file or effect was written by a newer version of cargo-scan,
+    /// in a format this version doesn't know (see `schema`)
+    #[error(
+        "Unsupported {kind} schema version {version} (expected at most {supported})"
+    )]
+    UnsupportedSchema { kind: &'static str, version: u32, supported: u32 },
+
+    #[error(transparent)]
+    Other(#[from] anyhow::Error),
+}
+
+impl ScanError {
+    #[cfg(feature = "native")]
+    pub(crate) fn resolution(path: &std::path::Path, source: anyhow::Error) -> Self {
+        Self::Resolution { path: path.to_path_buf(), source: source.into() }
+    }
+}
+
+pub type Result<T, E = ScanError> = std::result::Result<T, E>;
+
+#[cfg(all(test, feature = "native"))]
+mod tests {
+    use super::*;
+    use crate::effect::DEFAULT_EFFECT_TYPES;
+    use crate::scanner::{self, ScanResults};
+    use std::collections::{HashMap, HashSet};
+    use std::path::Path;
+
+    #[test]
+    fn test_scan_error_kinds() {
+        let res = scanner::scan_crate_with_sinks(
+            Path::new("data/test-packages/dependency-ex/src"),
+            HashSet::new(),
+            DEFAULT_EFFECT_TYPES,
+            true,
+        );
+        assert!(matches!(res, Err(ScanError::NotACrate { .. })));
+
+        let path = std::env::temp_dir().join("cargo-scan-test-invalid.rs");
+        std::fs::write(&path, "fn main() {").unwrap();
+        let res = scanner::scan_file_quick(
+            "invalid",
+            &path,
+            &mut ScanResults::new(),
+            HashSet::new(),
+            &HashMap::new(),
+        );
+        std::fs::remove_file(&path).unwrap();
+        assert!(matches!(res, Err(ScanError::Parse { path: p, .. }) if p == path));
+
+        let res = scanner::scan_file_quick(
+            "missing",
+            Path::new("data/test-packages/missing.rs"),
+            &mut ScanResults::new(),
+            HashSet::new(),
+            &HashMap::new(),
+        );
+        assert!(matches!(res, Err(ScanError::Io(_))));
+    }
+}
@@ -0,0 +1,254 @@
+//! Scanning the macro-expanded source of a crate.
+//!
+//! Effects inside macro invocations are invisible to the regular scan (they
+//! are only counted in `skipped_macros`). In this mode we additionally expand
+//! the crate with rustc's `-Zunpretty=expanded` (which is what `cargo expand`
+//! runs under the hood), scan the expanded source, and add the effects that
+//! the regular scan missed. Where possible, these effects are mapped back to
+//! the macro invocation they came from.
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet};
+use std::fs::{create_dir_all, remove_file, write};
+use std::hash::{Hash, Hasher};
+use std::path::{Path as FilePath, PathBuf as FilePathBuf};
+use std::process::Command;
+
+use anyhow::{anyhow, Context, Result};
+use log::{debug, info};
+
+use crate::effect::{EffectInstance, EffectType};
+use crate::ident::IdentPath;
+use crate::scanner::{scan_crate_with_sinks, scan_file_quick, ScanResults};
+use crate::util;
+
+/// Directory (in `~/.cargo-scan`, or the temp directory) to expand crates in
+const EXPANDED_DIR: &str = "expanded";
+
+/// The directory to build the crate and write its expanded source to.
+///
+/// This is outside the crate, since anything written to the crate changes its
+/// hash (see `hash_dir`) and so would make existing audits out of date. It is
+/// named after the crate's path so that expanding different crates with the
+/// same name doesn't clobber each other.
+pub fn expanded_dir(crate_path: &FilePath) -> Result<FilePathBuf> {
+    let crate_path = crate_path.canonicalize()?;
+    let mut hasher = DefaultHasher::new();
+    crate_path.hash(&mut hasher);
+    let name = crate_path.file_name().unwrap_or_default().to_string_lossy();
+
+    let base = home::home_dir()
+        .map(|home| home.join(".cargo-scan"))
+        .unwrap_or_else(std::env::temp_dir);
+    Ok(base.join(EXPANDED_DIR).join(format!("{}-{:016x}", name, hasher.finish())))
+}
+
+/// Runs the crate's library target (or its only target, if it has no
+/// library) through the compiler and returns the macro-expanded source.
+///
+/// The build goes to `target_dir`, and the crate is left unchanged: if it
+/// has a `Cargo.lock` it must be up to date, and if it doesn't have one, the
+/// one cargo creates is removed afterwards.
+pub fn expand_crate(crate_path: &FilePath, target_dir: &FilePath) -> Result<String> {
+    let lockfile = crate_path.join("Cargo.lock");
+    let had_lockfile = lockfile.exists();
+
+    let mut cmd = Command::new("cargo");
+    cmd.current_dir(crate_path).arg("rustc").arg("--quiet").arg("--profile=check");
+    cmd.env("CARGO_TARGET_DIR", target_dir);
+    if had_lockfile {
+        cmd.arg("--locked");
+    }
+    if crate_path.join("src/lib.rs").is_file() {
+        cmd.arg("--lib");
+    }
+    // NOTE: -Zunpretty is unstable; RUSTC_BOOTSTRAP lets us use it on a
+    //       stable toolchain, just like cargo-expand does.
+    cmd.arg("--").arg("-Zunpretty=expanded").env("RUSTC_BOOTSTRAP", "1");
+
+    debug!("Expanding crate: {:?}", cmd);
+    let output = cmd.output().context("Failed to run cargo rustc");
+    if !had_lockfile && lockfile.exists() {
+        remove_file(&lockfile)?;
+    }
+    let output = output?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "Failed to expand crate {:?}: {}",
+            crate_path,
+            String::from_utf8_lossy(&output.stderr)
+        ));
+    }
+    Ok(String::from_utf8(output.stdout)?)
+}
+
+/// Key identifying the same effect in the regular and expanded scans, which
+/// have different source locations
+fn effect_key(e: &EffectInstance) -> (String, String, EffectType) {
+    (e.caller_path().to_string(), e.callee_path().to_string(), e.eff_type().into())
+}
+
+/// Returns the effects of the expanded scan which are missing from the
+/// regular scan, with their locations mapped back to the original source
+/// where possible
+fn macro_effects(
+    results: &ScanResults,
+    expanded_results: ScanResults,
+) -> Vec<EffectInstance> {
+    let macro_calls = results
+        .macro_calls
+        .iter()
+        .map(|(f, locs)| (f.as_str(), locs))
+        .collect::<HashMap<_, _>>();
+    let known_fns =
+        results.fn_locs.keys().map(|f| (f.as_str(), f)).collect::<HashMap<_, _>>();
+
+    let mut seen: HashMap<_, usize> = HashMap::new();
+    for e in &results.effects {
+        *seen.entry(effect_key(e)).or_default() += 1;
+    }
+
+    let mut effects = Vec::new();
+    for mut e in expanded_results.effects {
+        // Only functions containing macro invocations, or generated by a
+        // macro, can have effects the regular scan didn't see
+        let macro_locs = macro_calls.get(e.caller_path());
+        if macro_locs.is_none() && known_fns.contains_key(e.caller_path()) {
+            continue;
+        }
+
+        match seen.get_mut(&effect_key(&e)) {
+            Some(n) if *n > 0 => *n -= 1,
+            _ => {
+                // Use the caller as found by the regular scan, which may
+                // include its source location
+                if let Some(&caller) = known_fns.get(e.caller_path()) {
+                    e.set_caller(caller.clone());
+                }
+                // NOTE: If the function contains several macro invocations we
+                //       can't tell which one the effect came from, so we
+                //       report the first.
+                if let Some(loc) = macro_locs.and_then(|locs| locs.first()) {
+                    e.set_call_loc(loc.clone());
+                } else {
+                    info!("Couldn't map expanded effect back to the source: {:?}", e);
+                }
+                effects.push(e);
+            }
+        }
+    }
+    effects
+}
+
+/// Scan the supplied crate, including the effects inside macro invocations.
+///
+/// The expanded source is written to the crate's `expanded_dir`, which is
+/// where effects that can't be mapped back to a macro invocation (e.g. in
+/// functions generated by a macro) point to. It is always scanned in quick
+/// mode since it isn't part of the crate's workspace.
+pub fn scan_crate_expanded(
+    crate_path: &FilePath,
+    sinks: HashSet<IdentPath>,
+    relevant_effects: &[EffectType],
+    quick_mode: bool,
+) -> Result<ScanResults> {
+    let mut results =
+        scan_crate_with_sinks(crate_path, sinks.clone(), relevant_effects, quick_mode)?;
+
+    info!("Expanding macros in crate: {:?}", crate_path);
+    let expanded_dir = expanded_dir(crate_path)?;
+    let expanded_src = expand_crate(crate_path, &expanded_dir.join("target"))?;
+    create_dir_all(expanded_dir.join("src"))?;
+    let expanded_path: FilePathBuf = expanded_dir.join("src").join("lib.rs");
+    write(&expanded_path, expanded_src)?;
+
+    let crate_name = util::load_cargo_toml(crate_path)?.crate_name;
+    let mut expanded_results = ScanResults::new();
+    scan_file_quick(
+        &crate_name,
+        &expanded_path,
+        &mut expanded_results,
+        sinks,
+        &HashMap::new(),
+    )?;
+    expanded_results
+        .effects
+        .retain(|e| EffectType::matches_effect(relevant_effects, e.eff_type()));
+
+    // Public functions generated by macros are missing from the regular scan
+    let known_fns = results.fn_locs.keys().map(|f| f.as_str()).collect::<HashSet<_>>();
+    let generated_pub_fns = expanded_results
+        .pub_fns
+        .iter()
+        .filter(|f| !known_fns.contains(f.as_str()))
+        .cloned()
+        .collect::<Vec<_>>();
+    results.pub_fns.extend(generated_pub_fns);
+
+    let new_effects = macro_effects(&results, expanded_results);
+    info!("Found {} effects inside macro invocations", new_effects.len());
+    for e in &new_effects {
+        // Functions generated by macros are missing from the call graph
+        results.update_call_graph(e.caller());
+    }
+    results.effects.extend(new_effects);
+
+    Ok(results)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auditing::util::hash_dir;
+    use crate::effect::DEFAULT_EFFECT_TYPES;
+    use std::fs::{copy, remove_dir_all};
+
+    /// Copies the macro-ex test package, with only the given files, to a
+    /// fresh temp directory
+    fn copy_macro_ex(name: &str, files: &[&str]) -> FilePathBuf {
+        let tmp = std::env::temp_dir().join(name);
+        let _ = remove_dir_all(&tmp);
+        create_dir_all(tmp.join("src")).unwrap();
+        let src = FilePath::new("data/test-packages/macro-ex");
+        for f in files {
+            copy(src.join(f), tmp.join(f)).unwrap();
+        }
+        tmp
+    }
+
+    #[test]
+    fn test_expand_leaves_crate_unchanged() {
+        let tmp = copy_macro_ex(
+            "cargo-scan-test-expand-unchanged",
+            &["Cargo.toml", "Cargo.lock", "src/lib.rs"],
+        );
+        let hash = hash_dir(&tmp).unwrap();
+
+        let results =
+            scan_crate_expanded(&tmp, HashSet::new(), DEFAULT_EFFECT_TYPES, true)
+                .unwrap();
+        assert!(results
+            .effects
+            .iter()
+            .any(|e| e.caller_path() == "macro_ex::delete_generated"));
+        assert!(!tmp.join("target").exists());
+        assert_eq!(hash_dir(&tmp).unwrap(), hash);
+        remove_dir_all(&tmp).unwrap();
+    }
+
+    #[test]
+    fn test_expand_without_lockfile() {
+        let tmp = copy_macro_ex(
+            "cargo-scan-test-expand-no-lockfile",
+            &["Cargo.toml", "src/lib.rs"],
+        );
+        let target_dir = tmp.with_extension("target");
+
+        let expanded = expand_crate(&tmp, &target_dir).unwrap();
+        assert!(expanded.contains("pub fn delete_generated"));
+        assert!(!tmp.join("Cargo.lock").exists());
+        assert!(!tmp.join("target").exists());
+        remove_dir_all(&tmp).unwrap();
+        remove_dir_all(&target_dir).unwrap();
+    }
+}
@@ -0,0 +1,112 @@
+//! Conditions for failing a scan, to gate CI pipelines.
+//!
+//! A condition is given on the command line as `--fail-on <condition>`, and
+//! the scan exits with a non-zero code if any reported effect matches it.
+//! Conditions are one of
+//! - an effect type, written as in suppression comments, e.g. `unsafe_call`;
+//! - `ffi`, for FFI calls, declarations, exports and foreign statics;
+//! - `sink`, for any sink call, or `sink:<category>`, e.g. `sink:net`, which
+//!   also cover indirect calls through function pointers holding a sink;
+//! - `new-effects`, for any effect which isn't in the baseline.
+
+use std::fmt;
+use std::str::FromStr;
+
+use anyhow::{anyhow, Error};
+
+use crate::effect::{EffectInstance, EffectType};
+use crate::sink::SinkCategory;
+use crate::suppress::{effect_type_from_name, effect_type_name};
+
+/// The effect types matched by the `ffi` condition
+pub(crate) const FFI_EFFECT_TYPES: [EffectType; 4] = [
+    EffectType::FFICall,
+    EffectType::FFIDecl,
+    EffectType::FFIExport,
+    EffectType::StaticExt,
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FailOn {
+    EffectType(EffectType),
+    Ffi,
+    /// Sink calls, in the given category if any
+    Sink(Option<SinkCategory>),
+    /// Effects which aren't in the baseline. Only meaningful when the scan
+    /// reports new effects only, so every reported effect matches it
+    NewEffects,
+}
+
+impl FailOn {
+    pub fn matches(&self, e: &EffectInstance) -> bool {
+        match self {
+            FailOn::EffectType(t) => EffectType::from(e.eff_type()) == *t,
+            FailOn::Ffi => FFI_EFFECT_TYPES.contains(&EffectType::from(e.eff_type())),
+            FailOn::Sink(None) => e.pattern().is_some(),
+            FailOn::Sink(Some(category)) => e.sink_category() == Some(*category),
+            FailOn::NewEffects => true,
+        }
+    }
+}
+
+impl FromStr for FailOn {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "ffi" => return Ok(FailOn::Ffi),
+            "sink" => return Ok(FailOn::Sink(None)),
+            "new-effects" => return Ok(FailOn::NewEffects),
+            _ => (),
+        }
+        if let Some(category) = s.strip_prefix("sink:") {
+            let category = category
+                .parse()
+                .map_err(|_| anyhow!("Unknown sink category: {}", category))?;
+            return Ok(FailOn::Sink(Some(category)));
+        }
+        effect_type_from_name(s).map(FailOn::EffectType).ok_or_else(|| {
+            anyhow!(
+                "Unknown condition: {} (expected an effect type such as `ffi_call`, \
+                 `ffi`, `sink`, `sink:<category>` or `new-effects`)",
+                s
+            )
+        })
+    }
+}
+
+impl fmt::Display for FailOn {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FailOn::EffectType(t) => write!(f, "{}", effect_type_name(*t)),
+            FailOn::Ffi => write!(f, "ffi"),
+            FailOn::Sink(None) => write!(f, "sink"),
+            FailOn::Sink(Some(category)) => write!(f, "sink:{}", category),
+            FailOn::NewEffects => write!(f, "new-effects"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_fail_on() {
+        for s in
+            ["ffi", "sink", "sink:net", "new-effects", "unsafe_call", "raw_pointer_write"]
+        {
+            assert_eq!(s.parse::<FailOn>().unwrap().to_string(), s);
+        }
+        assert_eq!(
+            "sink:process".parse::<FailOn>().unwrap(),
+            FailOn::Sink(Some(SinkCategory::Process))
+        );
+        assert_eq!(
+            "inline_asm".parse::<FailOn>().unwrap(),
+            FailOn::EffectType(EffectType::InlineAsm)
+        );
+        assert!("sink:disk".parse::<FailOn>().is_err());
+        assert!("unsafe".parse::<FailOn>().is_err());
+    }
+}
@@ -0,0 +1,323 @@
+//! Filters for focusing a scan or audit on some of the effects.
+//!
+//! An [`EffectFilter`] keeps the effects which match all of its criteria:
+//! - one of its effect classes, each an effect type written as in
+//!   suppression comments (e.g. `unsafe_call`, or `unsafe-call`), `ffi` for
+//!   FFI calls, declarations, exports and foreign statics, or `sink` for sink
+//!   calls, optionally in a category (e.g. `sink:net`), including indirect
+//!   calls through function pointers holding a sink;
+//! - one of its glob patterns, matched against the path of the effect's file
+//!   relative to the crate root, e.g. `src/net/**`, where `*` doesn't match
+//!   `/` and a directory matches the files under it;
+//! - none of its excluded glob patterns, e.g. the ignored directories of a
+//!   project (see `ProjectConfig`);
+//! - one of its callee prefixes, e.g. `libc::`.
+//!
+//! Criteria without any values match every effect.
+//!
+//! A [`PathGlob`] matches effects by the Rust paths of their callee and
+//! caller instead, e.g. to mark all the calls into generated bindings at once.
+
+use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
+
+use anyhow::{anyhow, Error};
+use globset::{GlobBuilder, GlobMatcher, GlobSet, GlobSetBuilder};
+
+use crate::effect::{EffectInstance, EffectType};
+use crate::error::Result;
+use crate::fail_on::FFI_EFFECT_TYPES;
+use crate::sink::SinkCategory;
+use crate::suppress::{effect_type_from_name, effect_type_name};
+
+/// A class of effects to keep, as given with `--effect-types`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EffectClass {
+    EffectType(EffectType),
+    Ffi,
+    /// Sink calls, in the given category if any
+    Sink(Option<SinkCategory>),
+}
+
+impl EffectClass {
+    pub fn matches(&self, e: &EffectInstance) -> bool {
+        let eff_type = EffectType::from(e.eff_type());
+        match self {
+            EffectClass::EffectType(t) => eff_type == *t,
+            EffectClass::Ffi => FFI_EFFECT_TYPES.contains(&eff_type),
+            EffectClass::Sink(None) => e.pattern().is_some(),
+            EffectClass::Sink(Some(category)) => e.sink_category() == Some(*category),
+        }
+    }
+}
+
+impl FromStr for EffectClass {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "ffi" => return Ok(EffectClass::Ffi),
+            "sink" => return Ok(EffectClass::Sink(None)),
+            _ => (),
+        }
+        if let Some(category) = s.strip_prefix("sink:") {
+            let category = category
+                .parse()
+                .map_err(|_| anyhow!("Unknown sink category: {}", category))?;
+            return Ok(EffectClass::Sink(Some(category)));
+        }
+        effect_type_from_name(&s.replace('-', "_"))
+            .map(EffectClass::EffectType)
+            .ok_or_else(|| {
+                anyhow!(
+                    "Unknown effect type: {} (expected an effect type such as \
+                     `unsafe-call`, `ffi`, `sink` or `sink:<category>`)",
+                    s
+                )
+            })
+    }
+}
+
+impl fmt::Display for EffectClass {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EffectClass::EffectType(t) => write!(f, "{}", effect_type_name(*t)),
+            EffectClass::Ffi => write!(f, "ffi"),
+            EffectClass::Sink(None) => write!(f, "sink"),
+            EffectClass::Sink(Some(category)) => write!(f, "sink:{}", category),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct EffectFilter {
+    effect_classes: Vec<EffectClass>,
+    include_paths: GlobSet,
+    exclude_paths: GlobSet,
+    callee_prefixes: Vec<String>,
+}
+
+/// Compiles the path patterns, where each pattern also matches the files
+/// under the directories it matches
+fn glob_set(patterns: &[String]) -> Result<GlobSet> {
+    let mut globs = GlobSetBuilder::new();
+    for pattern in patterns {
+        let dir_pattern = format!("{}/**", pattern.trim_end_matches('/'));
+        for p in [pattern, &dir_pattern] {
+            let glob = GlobBuilder::new(p)
+                .literal_separator(true)
+                .build()
+                .map_err(|e| anyhow!("Invalid path pattern {}: {}", pattern, e))?;
+            globs.add(glob);
+        }
+    }
+    Ok(globs.build().map_err(|e| anyhow!("{}", e))?)
+}
+
+impl EffectFilter {
+    pub fn new(
+        effect_classes: Vec<EffectClass>,
+        include_paths: Vec<String>,
+        callee_prefixes: Vec<String>,
+    ) -> Result<Self> {
+        Ok(Self {
+            effect_classes,
+            include_paths: glob_set(&include_paths)?,
+            exclude_paths: GlobSet::empty(),
+            callee_prefixes,
+        })
+    }
+
+    /// Also leaves out the effects in files matching any of the patterns
+    pub fn excluding_paths(mut self, patterns: &[String]) -> Result<Self> {
+        self.exclude_paths = glob_set(patterns)?;
+        Ok(self)
+    }
+
+    /// Whether the filter keeps every effect
+    pub fn is_empty(&self) -> bool {
+        self.effect_classes.is_empty()
+            && self.include_paths.is_empty()
+            && self.exclude_paths.is_empty()
+            && self.callee_prefixes.is_empty()
+    }
+
+    /// Whether the effect, found in the crate at `crate_path`, passes the
+    /// filter
+    pub fn matches(&self, e: &EffectInstance, crate_path: &Path) -> bool {
+        let class_ok = self.effect_classes.is_empty()
+            || self.effect_classes.iter().any(|c| c.matches(e));
+        let path_ok = (self.include_paths.is_empty() && self.exclude_paths.is_empty())
+            || {
+                let loc = e.call_loc().relative_to(crate_path);
+                let path = match loc.dir().to_str() {
+                    Some(".") => loc.file().to_path_buf(),
+                    _ => loc.dir().join(loc.file()),
+                };
+                (self.include_paths.is_empty() || self.include_paths.is_match(&path))
+                    && !self.exclude_paths.is_match(&path)
+            };
+        let callee = e.callee_path();
+        let callee_ok = self.callee_prefixes.is_empty()
+            || self.callee_prefixes.iter().any(|p| callee.starts_with(p.as_str()));
+        class_ok && path_ok && callee_ok
+    }
+}
+
+/// A glob pattern over Rust paths, e.g. `windows_sys::**`, where `*` matches
+/// within a path segment and `**` matches any number of segments
+#[derive(Debug, Clone)]
+pub struct PathGlob {
+    pattern: String,
+    glob: GlobMatcher,
+}
+
+impl PathGlob {
+    pub fn new(pattern: &str) -> Result<Self> {
+        let glob = GlobBuilder::new(&pattern.replace("::", "/"))
+            .literal_separator(true)
+            .build()
+            .map_err(|e| anyhow!("Invalid path pattern {}: {}", pattern, e.kind()))?
+            .compile_matcher();
+        Ok(Self { pattern: pattern.to_string(), glob })
+    }
+
+    pub fn is_match(&self, path: &str) -> bool {
+        self.glob.is_match(path.replace("::", "/"))
+    }
+
+    /// Whether the callee of an effect, or the caller at one of its
+    /// locations or the caller's module, matches the pattern
+    pub fn matches(&self, callee: &str, caller: &str) -> bool {
+        let module = caller.rsplit_once("::").map_or(caller, |(m, _)| m);
+        [callee, caller, module].into_iter().any(|p| self.is_match(p))
+    }
+}
+
+impl FromStr for PathGlob {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(Self::new(s)?)
+    }
+}
+
+impl fmt::Display for PathGlob {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.pattern)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::effect::Effect;
+    use crate::ident::CanonicalPath;
+    use proc_macro2::{Ident, Span};
+
+    fn effect(file: &str, callee: &str, eff_type: Effect) -> EffectInstance {
+        EffectInstance::new_effect(
+            Path::new(file),
+            CanonicalPath::new("foo::f"),
+            CanonicalPath::new(callee),
+            &Ident::new("f", Span::call_site()),
+            eff_type,
+        )
+    }
+
+    #[test]
+    fn test_parse_effect_class() {
+        assert_eq!(
+            "unsafe-call".parse::<EffectClass>().unwrap(),
+            EffectClass::EffectType(EffectType::UnsafeCall)
+        );
+        assert_eq!(
+            "unsafe_call".parse::<EffectClass>().unwrap().to_string(),
+            "unsafe_call"
+        );
+        for s in ["ffi", "sink", "sink:net"] {
+            assert_eq!(s.parse::<EffectClass>().unwrap().to_string(), s);
+        }
+        assert!("unsafe".parse::<EffectClass>().is_err());
+    }
+
+    #[test]
+    fn test_effect_filter() {
+        let root = Path::new("/c");
+        let ffi = effect(
+            "/c/src/net/mod.rs",
+            "libc::socket",
+            Effect::FFICall(CanonicalPath::new("libc::socket")),
+        );
+        let unsafe_call = effect(
+            "/c/src/lib.rs",
+            "foo::g",
+            Effect::UnsafeCall(CanonicalPath::new("foo::g")),
+        );
+        let build = effect(
+            "/c/build.rs",
+            "foo::h",
+            Effect::UnsafeCall(CanonicalPath::new("foo::h")),
+        );
+
+        let all = EffectFilter::default();
+        assert!(all.is_empty());
+        assert!([&ffi, &unsafe_call, &build].iter().all(|e| all.matches(e, root)));
+
+        let by_type = EffectFilter::new(vec![EffectClass::Ffi], vec![], vec![]).unwrap();
+        assert!(by_type.matches(&ffi, root));
+        assert!(!by_type.matches(&unsafe_call, root));
+
+        let by_path =
+            EffectFilter::new(vec![], vec!["src/net/**".into(), "*.rs".into()], vec![])
+                .unwrap();
+        assert!(by_path.matches(&ffi, root));
+        assert!(by_path.matches(&build, root));
+        // `*` doesn't match across directories
+        assert!(!by_path.matches(&unsafe_call, root));
+
+        let by_callee = EffectFilter::new(vec![], vec![], vec!["libc::".into()]).unwrap();
+        assert!(by_callee.matches(&ffi, root));
+        assert!(!by_callee.matches(&build, root));
+
+        // Directories match the files under them
+        let excluding = EffectFilter::default()
+            .excluding_paths(&["src/net".into(), "build.rs".into()])
+            .unwrap();
+        assert!(!excluding.is_empty());
+        assert!(!excluding.matches(&ffi, root));
+        assert!(!excluding.matches(&build, root));
+        assert!(excluding.matches(&unsafe_call, root));
+
+        // All the criteria must match
+        let both =
+            EffectFilter::new(vec![EffectClass::Ffi], vec!["src/lib.rs".into()], vec![])
+                .unwrap();
+        assert!(!both.matches(&ffi, root));
+
+        assert!(EffectFilter::new(vec![], vec!["src/[".into()], vec![]).is_err());
+    }
+
+    #[test]
+    fn test_path_glob() {
+        let bindings = PathGlob::new("windows_sys::**").unwrap();
+        assert!(bindings.is_match("windows_sys::Win32::Foundation::CloseHandle"));
+        assert!(!bindings.is_match("windows::Win32::Foundation::CloseHandle"));
+        assert!(bindings.matches("windows_sys::Win32::CloseHandle", "foo::close"));
+
+        // `*` only matches within a segment
+        let module = PathGlob::new("foo::bindings::*").unwrap();
+        assert!(module.is_match("foo::bindings::open"));
+        assert!(!module.is_match("foo::bindings::sys::open"));
+        assert!(module.matches("libc::open", "foo::bindings::open"));
+        // The caller's module matches
+        assert!(PathGlob::new("foo::bindings")
+            .unwrap()
+            .matches("libc::open", "foo::bindings::open"));
+        assert!(!module.matches("libc::open", "foo::open"));
+
+        assert_eq!(module.to_string(), "foo::bindings::*");
+        assert!(PathGlob::new("foo::[").is_err());
+    }
+}
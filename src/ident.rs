@@ -6,6 +6,7 @@
 //! Pattern: std::fs, std::fs::*
 
 use log::warn;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use std::fmt::{self, Display};
 
@@ -91,7 +92,7 @@ impl Ident {
 /// A Rust path identifier, with colons
 /// E.g.: std::env::var_os
 /// Semantically a (possibly empty) sequence of Idents
-#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize, JsonSchema)]
 pub struct IdentPath(String);
 impl Display for IdentPath {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
@@ -226,7 +227,7 @@ impl Default for IdentPath {
 /// Type representing a *canonical* path of Rust idents.
 /// i.e. from the root
 /// Should not be empty.
-#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize, JsonSchema)]
 pub struct CanonicalPath {
     ident_path: IdentPath,
     src_loc: SrcLoc,
@@ -449,7 +450,7 @@ impl CanonicalType {
 /// Currently supported: only patterns of the form
 /// <path>::* (includes <path> itself)
 /// The ::* is left implicit and should not be provided
-#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize, JsonSchema)]
 pub struct Pattern(IdentPath);
 impl Display for Pattern {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
@@ -1,14 +1,56 @@
+#[cfg(feature = "native")]
+pub mod advisories;
+#[cfg(feature = "native")]
 pub mod audit_chain;
+#[cfg(feature = "native")]
 pub mod audit_file;
+#[cfg(feature = "native")]
 pub mod auditing;
+pub mod baseline;
+pub mod cbor;
+#[cfg(feature = "native")]
+pub mod chain_graph;
+#[cfg(feature = "native")]
+pub mod crev;
+#[cfg(feature = "native")]
+pub mod criteria;
+#[cfg(feature = "native")]
+pub mod db;
+pub mod detector;
+#[cfg(feature = "native")]
 pub mod download_crate;
 pub mod effect;
+pub mod error;
+#[cfg(feature = "native")]
+pub mod expand;
+pub mod fail_on;
+pub mod filter;
 pub mod ident;
 pub mod loc_tracker;
+pub mod mem_fs;
+pub mod native_libs;
+#[cfg(feature = "native")]
+pub mod progress;
+pub mod project_config;
+#[cfg(feature = "native")]
+pub mod registry;
+#[cfg(feature = "native")]
+pub mod report;
+pub mod risk;
+#[cfg(feature = "native")]
+pub mod scan_config;
+#[cfg(feature = "native")]
 pub mod scan_stats;
 pub mod scanner;
+pub mod schema;
+#[cfg(feature = "native")]
+pub mod server;
+pub mod signing;
 pub mod sink;
+pub mod suppress;
 pub mod util;
+#[cfg(feature = "native")]
+pub mod vet;
 
 // Name resolution
 pub mod resolution;
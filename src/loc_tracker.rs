@@ -39,6 +39,13 @@ impl LoCTracker {
         }
     }
 
+    /// Add all the spans of another tracker
+    pub fn merge(&mut self, other: &LoCTracker) {
+        self.instances += other.instances;
+        self.lines += other.lines;
+        self.zero_size_lines += other.zero_size_lines;
+    }
+
     /// Return true if no spans were added
     pub fn is_empty(&self) -> bool {
         self.instances == 0
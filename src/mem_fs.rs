@@ -0,0 +1,93 @@
+//! An in-memory file system holding the files of a crate.
+//!
+//! It lets the scanning core run where there is no file system to read the
+//! crate from, e.g. when cargo-scan is built for `wasm32-unknown-unknown` to
+//! scan a crate pasted into a web page. See `scanner::scan_crate_in_memory`.
+
+use std::collections::BTreeMap;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use toml::value::Table;
+
+use crate::util;
+
+/// The files of a crate, by their path relative to the crate root, e.g.
+/// `Cargo.toml` or `src/lib.rs`
+#[derive(Debug, Default, Clone)]
+pub struct MemFs {
+    files: BTreeMap<PathBuf, String>,
+}
+
+impl MemFs {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a file, replacing any previous contents at the same path
+    pub fn insert(&mut self, path: impl Into<PathBuf>, contents: impl Into<String>) {
+        self.files.insert(path.into(), contents.into());
+    }
+
+    pub fn read_to_string(&self, path: &Path) -> io::Result<&str> {
+        self.files.get(path).map(String::as_str).ok_or_else(|| {
+            io::Error::new(io::ErrorKind::NotFound, format!("No such file: {:?}", path))
+        })
+    }
+
+    pub fn is_file(&self, path: &Path) -> bool {
+        self.files.contains_key(path)
+    }
+
+    /// Returns whether there are any files under the directory
+    pub fn is_dir(&self, path: &Path) -> bool {
+        self.files.keys().any(|f| f.starts_with(path) && f != path)
+    }
+
+    /// Returns the files under the directory with the given extension, in
+    /// order of their paths
+    pub fn files_with_extension<'a>(
+        &'a self,
+        dir: &'a Path,
+        ext: &'a str,
+    ) -> impl Iterator<Item = &'a Path> {
+        self.files
+            .keys()
+            .filter(move |f| f.starts_with(dir))
+            .filter(move |f| f.extension().is_some_and(|x| x.to_str() == Some(ext)))
+            .map(PathBuf::as_path)
+    }
+
+    /// Returns the root source file of the crate's library target, like
+    /// `util::lib_target_file`
+    pub fn lib_target_file(&self, cargo_toml: &Table) -> Option<PathBuf> {
+        let lib_rs =
+            Path::new(util::manifest_lib_path(cargo_toml).unwrap_or("src/lib.rs"));
+        self.is_file(lib_rs).then(|| lib_rs.to_path_buf())
+    }
+
+    /// Returns the source files of the crate's binary targets, like
+    /// `util::bin_target_files`
+    pub fn bin_target_files(&self, cargo_toml: &Table) -> Vec<PathBuf> {
+        let bin_dir = Path::new("src/bin");
+        let mut files = self
+            .files_with_extension(bin_dir, "rs")
+            .filter(|f| {
+                let in_bin_dir = f.parent() == Some(bin_dir);
+                let main_of_bin_dir = f.parent().and_then(Path::parent) == Some(bin_dir)
+                    && f.file_name().is_some_and(|n| n == "main.rs");
+                in_bin_dir || main_of_bin_dir
+            })
+            .map(Path::to_path_buf)
+            .collect::<Vec<_>>();
+        let main_rs = Path::new("src/main.rs");
+        if self.is_file(main_rs) {
+            files.push(main_rs.to_path_buf());
+        }
+        files.extend(util::manifest_bin_paths(cargo_toml).map(PathBuf::from));
+
+        files.sort();
+        files.dedup();
+        files
+    }
+}
@@ -0,0 +1,174 @@
+//! Native libraries linked by a crate.
+//!
+//! A crate can link a native library with a `#[link(name = "...")]`
+//! attribute on an extern block, from its build script (through the `cc` or
+//! `pkg-config` crates, or a `cargo:rustc-link-lib` directive), or by
+//! declaring it with the `links` key of its manifest. FFI calls are
+//! associated with the library their extern block binds to, so auditors can
+//! see what native code they actually reach.
+
+use crate::util;
+
+use anyhow::{anyhow, Context, Result};
+use log::debug;
+use parse_display::Display;
+use proc_macro2::{TokenStream, TokenTree};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::fs::read_to_string;
+use std::path::Path;
+use toml::value::Table;
+
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    Hash,
+    PartialOrd,
+    Ord,
+    Serialize,
+    Deserialize,
+    Display,
+    JsonSchema,
+)]
+#[display(style = "snake_case")]
+pub enum LinkSource {
+    /// `#[link(name = "...")]` on an extern block
+    LinkAttr,
+    /// `cc`, `pkg-config`, or `cargo:rustc-link-lib` in the build script
+    BuildScript,
+    /// The `links` key in Cargo.toml
+    Manifest,
+}
+
+#[derive(
+    Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize, JsonSchema,
+)]
+pub struct NativeLib {
+    pub name: String,
+    pub source: LinkSource,
+}
+
+impl NativeLib {
+    pub fn new(name: String, source: LinkSource) -> Self {
+        Self { name, source }
+    }
+}
+
+/// Returns the library named by a `#[link(name = "...")]` attribute, if any
+pub fn link_attr_lib(attrs: &[syn::Attribute]) -> Option<String> {
+    attrs.iter().filter(|a| a.path().is_ident("link")).find_map(|a| {
+        let mut name = None;
+        // Ignore the other keys, e.g. `kind` and `modifiers`
+        let _ = a.parse_nested_meta(|meta| {
+            if meta.path.is_ident("name") {
+                let s: syn::LitStr = meta.value()?.parse()?;
+                name = Some(s.value());
+            } else if meta.input.peek(syn::Token![=]) {
+                meta.value()?.parse::<syn::Expr>()?;
+            }
+            Ok(())
+        });
+        name
+    })
+}
+
+/// Returns the name in a `cargo:rustc-link-lib=[KIND[:MODIFIERS]=]NAME[:RENAME]`
+/// directive
+fn parse_link_lib_directive(s: &str) -> Option<String> {
+    let (_, value) = s.split_once("rustc-link-lib=")?;
+    let name = value.rsplit('=').next()?.split(':').next()?.trim();
+    (!name.is_empty()).then(|| name.to_string())
+}
+
+fn string_lit(t: &TokenTree) -> Option<String> {
+    let TokenTree::Literal(l) = t else { return None };
+    match syn::parse_str::<syn::LitStr>(&l.to_string()) {
+        Ok(s) => Some(s.value()),
+        Err(_) => None,
+    }
+}
+
+fn scan_build_tokens(tokens: TokenStream, libs: &mut Vec<String>) {
+    let tokens = tokens.into_iter().collect::<Vec<_>>();
+    for (i, t) in tokens.iter().enumerate() {
+        match t {
+            // e.g. `cc::Build::new().file("foo.c").compile("foo")` or
+            // `pkg_config::probe_library("foo")`
+            TokenTree::Ident(f)
+                if f == "compile" || f == "probe" || f == "probe_library" =>
+            {
+                if let Some(TokenTree::Group(args)) = tokens.get(i + 1) {
+                    if let Some(lib) =
+                        args.stream().into_iter().next().and_then(|a| string_lit(&a))
+                    {
+                        libs.push(lib);
+                    }
+                }
+            }
+            TokenTree::Group(g) => scan_build_tokens(g.stream(), libs),
+            lit @ TokenTree::Literal(_) => {
+                if let Some(lib) =
+                    string_lit(lit).and_then(|s| parse_link_lib_directive(&s))
+                {
+                    libs.push(lib);
+                }
+            }
+            _ => (),
+        }
+    }
+}
+
+/// Returns the native libraries linked by the build script source
+pub fn build_script_libs(src: &str) -> Result<Vec<String>> {
+    let tokens = src.parse::<TokenStream>().map_err(|e| anyhow!("{}", e))?;
+    let mut libs = Vec::new();
+    scan_build_tokens(tokens, &mut libs);
+    Ok(libs)
+}
+
+/// Returns the native libraries linked by the crate's build script and
+/// manifest. Those linked by attributes are found by the scanner.
+pub fn crate_native_libs(crate_path: &Path) -> Result<Vec<NativeLib>> {
+    let toml_string = read_to_string(crate_path.join("Cargo.toml"))?;
+    let cargo_toml =
+        toml::from_str::<Table>(&toml_string).context("Couldn't parse Cargo.toml")?;
+    let package = cargo_toml.get("package").and_then(|p| p.as_table());
+
+    let mut libs = Vec::new();
+    if let Some(links) = package.and_then(|p| p.get("links")).and_then(|l| l.as_str()) {
+        libs.push(NativeLib::new(links.to_string(), LinkSource::Manifest));
+    }
+
+    if let Some(build_rs) = util::build_script_file(crate_path)? {
+        match build_script_libs(&read_to_string(&build_rs)?) {
+            Ok(names) => libs.extend(
+                names.into_iter().map(|n| NativeLib::new(n, LinkSource::BuildScript)),
+            ),
+            Err(e) => debug!("Couldn't parse build script {:?}: {}", build_rs, e),
+        }
+    }
+
+    Ok(libs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build_script_libs() {
+        let src = r#"
+            fn main() {
+                cc::Build::new().file("src/foo.c").compile("foo");
+                pkg_config::Config::new().atleast_version("1.0").probe("zlib").unwrap();
+                println!("cargo:rustc-link-lib=static:+whole-archive=bar");
+                println!("cargo:rustc-link-search=native=/usr/lib");
+            }
+        "#;
+        let libs = build_script_libs(src).unwrap();
+        assert_eq!(libs, vec!["foo", "zlib", "bar"]);
+    }
+}
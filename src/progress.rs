@@ -0,0 +1,77 @@
+//! Progress bars for long-running work, such as scanning the files of a crate
+//! or making the default audits of a chain.
+//!
+//! Bars are only shown once a binary enables them with [`enable`], so that
+//! programs using cargo-scan as a library don't draw bars by default. They are
+//! drawn on stderr, and hidden when it isn't a terminal. Nested bars, e.g. the
+//! files of the crate currently audited while creating a chain, are drawn
+//! below their parent.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::OnceLock;
+
+use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+fn bars() -> &'static MultiProgress {
+    static BARS: OnceLock<MultiProgress> = OnceLock::new();
+    BARS.get_or_init(MultiProgress::new)
+}
+
+/// Shows progress bars from now on, if `enabled`
+pub fn enable(enabled: bool) {
+    ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+pub fn is_enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// A progress bar over a known number of items
+#[derive(Debug, Clone)]
+pub struct Progress {
+    bar: ProgressBar,
+}
+
+impl Progress {
+    /// A bar counting `len` items, e.g. `files` or `packages`, labelled with
+    /// `prefix`
+    pub fn new(len: usize, unit: &str, prefix: &str) -> Self {
+        if !is_enabled() {
+            return Self { bar: ProgressBar::hidden() };
+        }
+        let template = format!(
+            "{{prefix:.bold}} [{{bar:30.cyan/blue}}] {{pos}}/{{len}} {} \
+             (ETA {{eta}}) {{wide_msg}}",
+            unit
+        );
+        let style = ProgressStyle::with_template(&template)
+            .expect("progress bar template is valid")
+            .progress_chars("=> ");
+        let bar = ProgressBar::new(len as u64).with_style(style);
+        bar.set_prefix(prefix.to_string());
+        Self { bar: bars().add(bar) }
+    }
+
+    /// Starts on the next item, shown next to the bar. Without a visible bar,
+    /// e.g. in CI, prints `line` instead.
+    pub fn start(&self, item: &str, line: &str) {
+        if self.bar.is_hidden() {
+            println!("{}", line);
+        } else {
+            self.bar.set_message(item.to_string());
+        }
+    }
+
+    /// Counts an item as done
+    pub fn inc(&self) {
+        self.bar.inc(1);
+    }
+
+    /// Removes the bar once all the items are done
+    pub fn finish(&self) {
+        self.bar.finish_and_clear();
+        bars().remove(&self.bar);
+    }
+}
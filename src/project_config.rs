@@ -0,0 +1,336 @@
+//! Per-project configuration, read from a `.cargo-scan.toml` file.
+//!
+//! The file sets defaults for the options a team would otherwise have to pass
+//! consistently on every run, in CI and on each developer's machine:
+//!
+//! ```toml
+//! # Files and directories, relative to the crate root, whose effects are
+//! # left out of scans and audits, and which aren't read at all
+//! ignore = ["src/generated", "vendor"]
+//!
+//! # Additional sinks, as in a `--sinks-file`
+//! [sinks]
+//! prefixes = ["mycorp::auth"]
+//! functions = ["mycorp::crypto::decrypt"]
+//!
+//! # Default effect filters, as with `--effect-types`, `--include-path` and
+//! # `--callee-prefix`
+//! [filter]
+//! effect-types = ["ffi", "unsafe-call", "sink"]
+//! include-paths = ["src/**"]
+//! callee-prefixes = ["libc::"]
+//!
+//! [audit]
+//! # The criteria `chain check` requires by default
+//! criteria = "safe-to-deploy"
+//!
+//! # Default paths for the `chain` subcommands
+//! [chain]
+//! manifest = "audits/chain.manifest"
+//! audit-path = "audits"
+//! crate-download-path = ".audit_crates"
+//!
+//! # Whose policies `chain fetch` accepts from a registry without re-audit
+//! [trust]
+//! registries = ["https://audits.example.com/mycorp"]
+//! transitive = false
+//!
+//! # The public keys of trusted auditors, by their identity as recorded in
+//! # policies, and of the reviewers whose chain sign-offs count, by name
+//! [trust.keys]
+//! "Alice <alice@example.com>" = "d75a980182b10ab7d54bfed3c964073a0ee172f3daa62325af021a68f707511a"
+//! ```
+//!
+//! The binaries look for the file in the current directory and its parents,
+//! not in the scanned crate, so that a crate under audit can't change how
+//! it's scanned. Options given on the command line take precedence over the
+//! file, and relative paths in it are relative to the directory it's in.
+
+use std::collections::{BTreeMap, HashSet};
+use std::fs::read_to_string;
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+use serde::Deserialize;
+
+use crate::error::Result;
+use crate::filter::{EffectClass, EffectFilter};
+use crate::ident::IdentPath;
+use crate::signing::Signature;
+use crate::sink::SinkConfig;
+
+/// The name of the project configuration file
+pub const PROJECT_CONFIG_FILE: &str = ".cargo-scan.toml";
+
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct ProjectConfig {
+    /// Glob patterns of the files and directories whose effects are left out,
+    /// and which aren't walked at all
+    pub ignore: Vec<String>,
+    pub sinks: SinkConfig,
+    pub filter: FilterDefaults,
+    pub audit: AuditDefaults,
+    pub chain: ChainDefaults,
+    pub trust: TrustConfig,
+    /// The directory of the file, which relative paths are relative to
+    #[serde(skip)]
+    dir: PathBuf,
+}
+
+/// The default effect filters; see `EffectFilter`
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct FilterDefaults {
+    pub effect_types: Vec<String>,
+    pub include_paths: Vec<String>,
+    pub callee_prefixes: Vec<String>,
+}
+
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct AuditDefaults {
+    /// The criteria the crates of a chain must meet by default
+    pub criteria: Option<String>,
+}
+
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct ChainDefaults {
+    /// The chain manifest
+    pub manifest: Option<PathBuf>,
+    /// Where new chains save their audit files
+    pub audit_path: Option<PathBuf>,
+    /// Where crates are downloaded to for auditing
+    pub crate_download_path: Option<PathBuf>,
+}
+
+/// The auditors and registry namespaces whose policies are accepted without
+/// being audited again
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct TrustConfig {
+    /// The public keys of trusted auditors (see `signing`), by their identity
+    /// exactly as recorded in policies, and of trusted chain reviewers, by name
+    pub keys: BTreeMap<String, String>,
+    /// Registry URLs; every validly signed policy published under one of
+    /// them is trusted
+    pub registries: Vec<String>,
+    /// Also trust the decisions of other auditors in policies created by a
+    /// trusted auditor, instead of requiring every decision to be made by a
+    /// trusted auditor
+    pub transitive: bool,
+}
+
+impl TrustConfig {
+    /// Returns whether the registry at `url` is, or is under, one of the
+    /// trusted registry namespaces
+    pub fn trusts_registry(&self, url: &str) -> bool {
+        let url = url.trim_end_matches('/');
+        self.registries.iter().map(|r| r.trim_end_matches('/')).any(|r| {
+            url.strip_prefix(r)
+                .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+        })
+    }
+
+    pub fn trusts_auditor(&self, auditor: &str) -> bool {
+        self.keys.contains_key(auditor)
+    }
+
+    /// Returns the trusted auditor who signed `msg`, if the signature is
+    /// valid and made with one of the trusted keys
+    pub fn trusted_signer(&self, signature: &Signature, msg: &[u8]) -> Option<&str> {
+        signature.verify(msg).ok()?;
+        self.keys
+            .iter()
+            .find(|(_, key)| key.eq_ignore_ascii_case(&signature.public_key))
+            .map(|(auditor, _)| auditor.as_str())
+    }
+
+    /// Returns the auditors who took part in a policy signed by a trusted
+    /// auditor, but aren't trusted themselves. Unless trust is transitive,
+    /// all of them have to be trusted, and otherwise the signer vouches for
+    /// them.
+    pub fn untrusted_auditors<'a>(&self, auditors: &[&'a str]) -> Vec<&'a str> {
+        if self.transitive {
+            return vec![];
+        }
+        auditors.iter().copied().filter(|a| !self.trusts_auditor(a)).collect()
+    }
+}
+
+impl ProjectConfig {
+    pub fn from_toml(s: &str) -> Result<Self> {
+        Ok(toml::from_str(s).context("Couldn't parse project config")?)
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let s = read_to_string(path)
+            .with_context(|| format!("Couldn't read project config: {:?}", path))?;
+        let mut config = Self::from_toml(&s)
+            .with_context(|| format!("Invalid project config: {:?}", path))?;
+        config.dir = path.parent().map(Path::to_path_buf).unwrap_or_default();
+        Ok(config)
+    }
+
+    /// Loads the file at `path` if given, or else the nearest one to the
+    /// current directory, or the default config if there is none
+    pub fn load_or_find(path: Option<&Path>) -> Result<Self> {
+        match path {
+            Some(path) => Self::load(path),
+            None => Ok(Self::find(&std::env::current_dir()?)?.unwrap_or_default()),
+        }
+    }
+
+    /// Loads the nearest `.cargo-scan.toml` in `start` or one of its parents,
+    /// if there is one
+    pub fn find(start: &Path) -> Result<Option<Self>> {
+        let start = start.canonicalize()?;
+        let path = start
+            .ancestors()
+            .map(|dir| dir.join(PROJECT_CONFIG_FILE))
+            .find(|p| p.is_file());
+        path.map(|p| Self::load(&p)).transpose()
+    }
+
+    /// The additional sink patterns
+    pub fn sinks(&self) -> Result<HashSet<IdentPath>> {
+        Ok(self.sinks.sinks()?)
+    }
+
+    /// The effect filter given on the command line, with the file's defaults
+    /// for the criteria which aren't given there, and leaving out the ignored
+    /// paths
+    pub fn effect_filter(
+        &self,
+        effect_classes: Vec<EffectClass>,
+        include_paths: Vec<String>,
+        callee_prefixes: Vec<String>,
+    ) -> Result<EffectFilter> {
+        let effect_classes = if effect_classes.is_empty() {
+            self.filter
+                .effect_types
+                .iter()
+                .map(|t| t.parse::<EffectClass>())
+                .collect::<Result<Vec<_>, _>>()?
+        } else {
+            effect_classes
+        };
+        let or_default = |given: Vec<String>, default: &Vec<String>| {
+            if given.is_empty() {
+                default.clone()
+            } else {
+                given
+            }
+        };
+        EffectFilter::new(
+            effect_classes,
+            or_default(include_paths, &self.filter.include_paths),
+            or_default(callee_prefixes, &self.filter.callee_prefixes),
+        )?
+        .excluding_paths(&self.ignore)
+    }
+
+    /// Resolves a path in the file relative to its directory
+    pub fn resolve_path(&self, path: &Path) -> PathBuf {
+        self.dir.join(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::signing::SigningKey;
+
+    #[test]
+    fn test_project_config() {
+        let config = ProjectConfig::from_toml(
+            r#"
+            ignore = ["vendor"]
+
+            [sinks]
+            prefixes = ["mycorp::auth"]
+
+            [filter]
+            effect-types = ["ffi", "unsafe-call"]
+
+            [chain]
+            manifest = "audits/chain.manifest"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.sinks().unwrap().len(), 1);
+        assert!(!config.effect_filter(vec![], vec![], vec![]).unwrap().is_empty());
+        assert_eq!(config.audit.criteria, None);
+        assert_eq!(
+            config.chain.manifest.as_deref(),
+            Some(Path::new("audits/chain.manifest"))
+        );
+
+        let filter = ProjectConfig::default().effect_filter(vec![], vec![], vec![]);
+        assert!(filter.unwrap().is_empty());
+        assert!(ProjectConfig::from_toml("ignored = []").is_err());
+        let invalid = ProjectConfig::from_toml("[filter]\neffect-types = [\"unsafe\"]");
+        let invalid = invalid.unwrap();
+        assert!(invalid.effect_filter(vec![], vec![], vec![]).is_err());
+        // Given on the command line instead of the invalid default
+        assert!(invalid.effect_filter(vec![EffectClass::Ffi], vec![], vec![]).is_ok());
+    }
+
+    #[test]
+    fn test_trust_config() {
+        let alice = SigningKey::from_hex(&"01".repeat(32)).unwrap();
+        let bob = SigningKey::from_hex(&"02".repeat(32)).unwrap();
+        let eve = SigningKey::from_hex(&"03".repeat(32)).unwrap();
+        let config = ProjectConfig::from_toml(&format!(
+            r#"
+            [trust]
+            registries = ["https://audits.example.com/team/"]
+            [trust.keys]
+            alice = "{}"
+            bob = "{}"
+            "#,
+            alice.public_key(),
+            bob.public_key()
+        ))
+        .unwrap();
+        let trust = &config.trust;
+        assert!(!trust.transitive);
+        assert!(trust.trusts_registry("https://audits.example.com/team"));
+        assert!(trust.trusts_registry("https://audits.example.com/team/sub/"));
+        assert!(!trust.trusts_registry("https://audits.example.com/teammates"));
+        assert!(!trust.trusts_registry("https://audits.example.com"));
+
+        let policy = br#"{"auditor":"alice"}"#;
+        assert_eq!(trust.trusted_signer(&alice.sign(policy), policy), Some("alice"));
+        assert_eq!(trust.trusted_signer(&bob.sign(policy), policy), Some("bob"));
+        // Signed by an untrusted key, or not matching the signature
+        assert_eq!(trust.trusted_signer(&eve.sign(policy), policy), None);
+        let tampered = br#"{"auditor":"eve"}"#;
+        assert_eq!(trust.trusted_signer(&alice.sign(policy), tampered), None);
+        let forged = Signature { public_key: alice.public_key(), ..eve.sign(policy) };
+        assert_eq!(trust.trusted_signer(&forged, policy), None);
+
+        assert!(trust.untrusted_auditors(&["alice", "bob"]).is_empty());
+        assert_eq!(trust.untrusted_auditors(&["alice", "eve"]), ["eve"]);
+        let transitive = TrustConfig { transitive: true, ..trust.clone() };
+        assert!(transitive.untrusted_auditors(&["alice", "eve"]).is_empty());
+    }
+
+    #[test]
+    fn test_find_project_config() {
+        let tmp = std::env::temp_dir().join("cargo-scan-test-find-project-config");
+        let nested = tmp.join("crates/foo");
+        std::fs::create_dir_all(&nested).unwrap();
+        std::fs::write(tmp.join(PROJECT_CONFIG_FILE), "[chain]\naudit-path = \"audits\"")
+            .unwrap();
+
+        let config = ProjectConfig::find(&nested).unwrap().unwrap();
+        let audit_path = config.chain.audit_path.as_ref().unwrap();
+        assert_eq!(
+            config.resolve_path(audit_path),
+            tmp.canonicalize().unwrap().join("audits")
+        );
+        std::fs::remove_dir_all(&tmp).unwrap();
+    }
+}
@@ -0,0 +1,271 @@
+//! Client for a shared audit registry.
+//!
+//! A registry lets teams share the policies they audited for crate versions,
+//! so that others don't have to audit the same crates from scratch. It's a
+//! plain HTTP(S) server with a small REST API:
+//!
+//! - `GET`/`PUT {registry}/blobs/sha256/{hash}` reads or stores a policy file,
+//!   addressed by the SHA-256 hash of its contents.
+//! - `GET`/`PUT {registry}/policies/{crate}/{version}` reads or stores the
+//!   index entry of a crate version, a JSON object naming the hash of its
+//!   policy (`{"policy": "..."}`). A missing entry is a `404`.
+//!
+//! Policies are stored in their portable form (see
+//! `AuditFile::to_portable_json`), and fetched blobs are checked against their
+//! hash, so a registry can be backed by any static file server or cache.
+//! Requests are authenticated with a bearer token if one is given.
+//!
+//! Published policies are signed by the auditor who publishes them (see
+//! `signing`). The signature is stored in the policy's `signature` field, and
+//! covers the canonical serialization of the rest of the policy.
+
+use anyhow::{anyhow, Result};
+use curl::easy::{Easy, List};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::audit_file::AuditFile;
+use crate::signing::{canonical_json, Signature, SigningKey};
+use crate::util::CrateId;
+
+/// The environment variable to read the registry URL from, if not given
+pub const REGISTRY_ENV: &str = "CARGO_SCAN_REGISTRY";
+/// The environment variable to read the registry token from
+pub const REGISTRY_TOKEN_ENV: &str = "CARGO_SCAN_REGISTRY_TOKEN";
+
+/// The index entry of a crate version in the registry
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+struct PolicyEntry {
+    /// The SHA-256 hash of the policy file
+    policy: String,
+}
+
+/// A policy fetched from the registry
+pub struct FetchedPolicy {
+    pub audit_file: AuditFile,
+    /// The signature the policy was published with, if it has one
+    pub signature: Option<Signature>,
+    /// What the signature is over: the canonical serialization of the policy
+    /// without its signature
+    pub signed_bytes: Vec<u8>,
+}
+
+impl FetchedPolicy {
+    /// Checks that the policy is signed, and that the signature matches it
+    pub fn verify(&self) -> Result<&Signature> {
+        let signature =
+            self.signature.as_ref().ok_or_else(|| anyhow!("it isn't signed"))?;
+        signature.verify(&self.signed_bytes)?;
+        Ok(signature)
+    }
+}
+
+pub struct RegistryClient {
+    url: String,
+    token: Option<String>,
+}
+
+impl RegistryClient {
+    pub fn new(url: &str, token: Option<String>) -> Result<Self> {
+        if !url.starts_with("https://") && !url.starts_with("http://") {
+            return Err(anyhow!("Registry URL must be an HTTP(S) URL: {}", url));
+        }
+        Ok(RegistryClient { url: url.trim_end_matches('/').to_string(), token })
+    }
+
+    /// Creates a client for the registry at `url`, or at the URL in the
+    /// `CARGO_SCAN_REGISTRY` environment variable if none is given. The token
+    /// is read from `CARGO_SCAN_REGISTRY_TOKEN`.
+    pub fn from_env(url: Option<&str>) -> Result<Self> {
+        let url = match url {
+            Some(url) => url.to_string(),
+            None => std::env::var(REGISTRY_ENV).map_err(|_| {
+                anyhow!("No registry given; pass --registry or set {}", REGISTRY_ENV)
+            })?,
+        };
+        Self::new(&url, std::env::var(REGISTRY_TOKEN_ENV).ok())
+    }
+
+    /// The URL of the registry, without a trailing slash
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    fn policy_url(&self, crate_id: &CrateId) -> String {
+        format!("{}/policies/{}/{}", self.url, crate_id.crate_name, crate_id.version)
+    }
+
+    fn blob_url(&self, hash: &str) -> String {
+        format!("{}/blobs/sha256/{}", self.url, hash)
+    }
+
+    /// Sends a request, returning the response code and body
+    fn request(&self, url: &str, body: Option<&[u8]>) -> Result<(u32, Vec<u8>)> {
+        let mut dst = Vec::new();
+        let mut easy = Easy::new();
+        easy.follow_location(true)?;
+        easy.useragent("cargo-scan")?;
+        easy.url(url)?;
+
+        let mut headers = List::new();
+        if let Some(token) = &self.token {
+            headers.append(&format!("Authorization: Bearer {}", token))?;
+        }
+        if let Some(body) = body {
+            headers.append("Content-Type: application/json")?;
+            easy.custom_request("PUT")?;
+            easy.post_fields_copy(body)?;
+        }
+        easy.http_headers(headers)?;
+
+        {
+            let mut transfer = easy.transfer();
+            transfer.write_function(|data| {
+                dst.extend_from_slice(data);
+                Ok(data.len())
+            })?;
+            transfer.perform()?;
+        }
+
+        Ok((easy.response_code()?, dst))
+    }
+
+    fn get(&self, url: &str) -> Result<Option<Vec<u8>>> {
+        match self.request(url, None)? {
+            (200, body) => Ok(Some(body)),
+            (404, _) => Ok(None),
+            (code, _) => Err(anyhow!("Registry request to {} failed with {}", url, code)),
+        }
+    }
+
+    fn put(&self, url: &str, body: &[u8]) -> Result<()> {
+        match self.request(url, Some(body))? {
+            (200..=299, _) => Ok(()),
+            (code, _) => Err(anyhow!("Registry request to {} failed with {}", url, code)),
+        }
+    }
+
+    /// Fetches the policy published for the crate version, if there is one.
+    /// Its source locations are relative to the crate root until it is
+    /// relocated to a local copy of the crate. The signature isn't checked
+    /// (see `FetchedPolicy::verify`).
+    pub fn fetch_policy(&self, crate_id: &CrateId) -> Result<Option<FetchedPolicy>> {
+        let Some(entry) = self.get(&self.policy_url(crate_id))? else {
+            return Ok(None);
+        };
+        let entry: PolicyEntry = serde_json::from_slice(&entry)?;
+        let blob = self
+            .get(&self.blob_url(&entry.policy))?
+            .ok_or_else(|| anyhow!("Registry is missing the policy of {}", crate_id))?;
+        verify_blob(&blob, &entry.policy)?;
+
+        let fetched = parse_policy(&blob)?;
+        if fetched.audit_file.crate_id.as_ref() != Some(crate_id) {
+            return Err(anyhow!("Registry policy for {} is for another crate", crate_id));
+        }
+        Ok(Some(fetched))
+    }
+
+    /// Publishes the policy of the crate version, signed with `key`,
+    /// replacing any earlier one. Returns the hash the policy is stored
+    /// under.
+    pub fn publish_policy(
+        &self,
+        crate_id: &CrateId,
+        audit_file: &AuditFile,
+        key: &SigningKey,
+    ) -> Result<String> {
+        let blob = signed_policy(audit_file, key)?;
+        let hash = blob_hash(blob.as_bytes());
+        self.put(&self.blob_url(&hash), blob.as_bytes())?;
+        let entry = serde_json::to_vec(&PolicyEntry { policy: hash.clone() })?;
+        self.put(&self.policy_url(crate_id), &entry)?;
+        Ok(hash)
+    }
+}
+
+/// The portable form of the policy, with its signature by `key`
+fn signed_policy(audit_file: &AuditFile, key: &SigningKey) -> Result<String> {
+    let mut json = audit_file.to_portable_value()?;
+    let signature = key.sign(&canonical_json(&json)?);
+    json["signature"] = serde_json::to_value(signature)?;
+    Ok(serde_json::to_string(&json)?)
+}
+
+/// Parses a policy fetched from the registry, setting its signature apart
+/// from what it signs
+fn parse_policy(blob: &[u8]) -> Result<FetchedPolicy> {
+    let mut json: serde_json::Value = serde_json::from_slice(blob)?;
+    let signature = match json.as_object_mut().and_then(|o| o.remove("signature")) {
+        Some(signature) => Some(serde_json::from_value(signature)?),
+        None => None,
+    };
+    let signed_bytes = canonical_json(&json)?;
+    let audit_file = AuditFile::from_json_value(json)?;
+    Ok(FetchedPolicy { audit_file, signature, signed_bytes })
+}
+
+fn blob_hash(blob: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(blob))
+}
+
+/// Checks that a blob fetched from the registry matches its address
+fn verify_blob(blob: &[u8], hash: &str) -> Result<()> {
+    let actual = blob_hash(blob);
+    if actual != hash {
+        return Err(anyhow!(
+            "Registry policy doesn't match its hash: expected {}, but got {}",
+            hash,
+            actual
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use semver::Version;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_registry_urls() {
+        let client = RegistryClient::new("https://audits.example.com/", None).unwrap();
+        let crate_id =
+            CrateId { crate_name: "serde".to_string(), version: Version::new(1, 0, 193) };
+        assert_eq!(
+            client.policy_url(&crate_id),
+            "https://audits.example.com/policies/serde/1.0.193"
+        );
+        assert_eq!(
+            client.blob_url("ab12"),
+            "https://audits.example.com/blobs/sha256/ab12"
+        );
+        assert!(RegistryClient::new("ftp://audits.example.com", None).is_err());
+    }
+
+    #[test]
+    fn test_verify_blob() {
+        let blob = b"{\"audit_trees\":[]}";
+        assert!(verify_blob(blob, &blob_hash(blob)).is_ok());
+        assert!(verify_blob(b"tampered", &blob_hash(blob)).is_err());
+    }
+
+    #[test]
+    fn test_signed_policy() {
+        let crate_path = PathBuf::from("data/test-packages/fns-closures");
+        let audit_file = AuditFile::empty(crate_path, vec![]).unwrap();
+        let key = SigningKey::from_hex(&"05".repeat(32)).unwrap();
+        let blob = signed_policy(&audit_file, &key).unwrap();
+        let fetched = parse_policy(blob.as_bytes()).unwrap();
+        assert_eq!(fetched.verify().unwrap().public_key, key.public_key());
+
+        // Any change to the policy breaks its signature
+        let tampered = blob.replacen("\"version\":0", "\"version\":1", 1);
+        assert_ne!(tampered, blob);
+        assert!(parse_policy(tampered.as_bytes()).unwrap().verify().is_err());
+
+        let unsigned = audit_file.to_portable_json().unwrap();
+        assert!(parse_policy(unsigned.as_bytes()).unwrap().verify().is_err());
+    }
+}
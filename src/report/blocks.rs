@@ -0,0 +1,93 @@
+//! Grouping of the effects in a report by the blocks of code containing them.
+//!
+//! Each effect in a function is listed under the function, and effects in
+//! unsafe blocks are listed under the innermost unsafe block containing them,
+//! along with the block's span. Effects outside of functions (e.g. in
+//! `static` initializers) stay in the report's flat list of effects.
+
+use super::{EffectReport, Report};
+use crate::effect::SrcLoc;
+use crate::scan_stats::CrateStats;
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// An unsafe block and the effects inside it
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, JsonSchema)]
+pub struct UnsafeBlockReport {
+    pub location: SrcLoc,
+    pub effects: Vec<EffectReport>,
+}
+
+/// A function and the effects inside it
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, JsonSchema)]
+pub struct FnBlockReport {
+    pub function: String,
+    /// The location of the function's signature
+    pub location: SrcLoc,
+    /// The effects outside of unsafe blocks
+    pub effects: Vec<EffectReport>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub unsafe_blocks: Vec<UnsafeBlockReport>,
+}
+
+impl FnBlockReport {
+    fn add_effect(&mut self, e: EffectReport, unsafe_block_locs: &[SrcLoc]) {
+        // The innermost block is the one which starts last
+        let block = unsafe_block_locs
+            .iter()
+            .filter(|b| b.contains(&e.location))
+            .max_by_key(|b| (b.start_line(), b.start_col()));
+        let Some(block) = block else {
+            self.effects.push(e);
+            return;
+        };
+        match self.unsafe_blocks.iter_mut().find(|b| &b.location == block) {
+            Some(b) => b.effects.push(e),
+            None => self
+                .unsafe_blocks
+                .push(UnsafeBlockReport { location: block.clone(), effects: vec![e] }),
+        }
+    }
+}
+
+impl Report {
+    /// Moves the effects in functions from the flat list of effects to the
+    /// blocks containing them, in `functions`
+    pub fn group_by_block(&mut self, stats: &CrateStats) {
+        let fn_locs = stats
+            .fn_locs
+            .iter()
+            .map(|(f, loc)| (f.as_str(), loc.relative_to(&stats.crate_path)))
+            .collect::<HashMap<_, _>>();
+
+        let unsafe_block_locs = stats
+            .unsafe_block_locs
+            .iter()
+            .map(|loc| loc.relative_to(&stats.crate_path))
+            .collect::<Vec<_>>();
+        let mut functions: Vec<FnBlockReport> = Vec::new();
+        let mut fn_idxs = HashMap::new();
+        let mut outside_fns = Vec::new();
+        for e in std::mem::take(&mut self.effects) {
+            let Some(fn_loc) = fn_locs.get(e.caller.as_str()) else {
+                outside_fns.push(e);
+                continue;
+            };
+            let idx = *fn_idxs.entry(e.caller.clone()).or_insert_with(|| {
+                functions.push(FnBlockReport {
+                    function: e.caller.clone(),
+                    location: fn_loc.clone(),
+                    effects: Vec::new(),
+                    unsafe_blocks: Vec::new(),
+                });
+                functions.len() - 1
+            });
+            functions[idx].add_effect(e, &unsafe_block_locs);
+        }
+
+        self.effects = outside_fns;
+        self.functions = functions;
+    }
+}
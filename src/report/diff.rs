@@ -0,0 +1,175 @@
+//! Differences between two reports, e.g. of two versions of a crate.
+//!
+//! Effects are matched by [`EffectReport::id`], which leaves out line and
+//! column numbers, so that effects which only moved within their file aren't
+//! reported as both removed and added.
+
+use super::{effect_message, EffectReport, Report, REPORT_VERSION};
+
+use crate::cbor;
+
+use anyhow::{anyhow, Result};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::io::Read;
+
+/// An effect found in only one of the reports, along with its identifier
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, JsonSchema)]
+pub struct DiffEntry {
+    pub id: String,
+    #[serde(flatten)]
+    pub effect: EffectReport,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq, JsonSchema)]
+pub struct ReportDiff {
+    /// Effects which are only in the new report
+    pub added: Vec<DiffEntry>,
+    /// Effects which are only in the old report
+    pub removed: Vec<DiffEntry>,
+}
+
+/// The effects of `effects` which aren't in `other`. An effect which occurs
+/// more often in `effects` than in `other` is included for each extra
+/// occurrence.
+fn effects_not_in(effects: &[EffectReport], other: &[EffectReport]) -> Vec<DiffEntry> {
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    for e in other {
+        *counts.entry(e.id()).or_default() += 1;
+    }
+    let mut entries = Vec::new();
+    for e in effects {
+        let id = e.id();
+        match counts.get_mut(&id) {
+            Some(n) if *n > 0 => *n -= 1,
+            _ => entries.push(DiffEntry { id, effect: e.clone() }),
+        }
+    }
+    entries
+}
+
+impl Report {
+    /// Reads a report saved with `--format json`
+    pub fn from_json(json: &str) -> Result<Self> {
+        Self::check_version(serde_json::from_str(json)?)
+    }
+
+    /// Reads a report saved with `--format cbor`
+    pub fn from_cbor<R: Read>(reader: R) -> Result<Self> {
+        Self::check_version(cbor::from_reader(reader)?)
+    }
+
+    fn check_version(report: Self) -> Result<Self> {
+        if report.version > REPORT_VERSION {
+            return Err(anyhow!(
+                "Unsupported report version {} (expected at most {})",
+                report.version,
+                REPORT_VERSION
+            ));
+        }
+        Ok(report)
+    }
+
+    /// The effects added and removed in `new` compared to this report
+    pub fn diff(&self, new: &Report) -> ReportDiff {
+        ReportDiff {
+            added: effects_not_in(&new.effects, &self.effects),
+            removed: effects_not_in(&self.effects, &new.effects),
+        }
+    }
+}
+
+impl ReportDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty()
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    /// Lists the added and removed effects, one per line
+    pub fn to_text(&self) -> String {
+        let mut out = String::new();
+        for (title, sign, entries) in
+            [("Added", '+', &self.added), ("Removed", '-', &self.removed)]
+        {
+            let _ = writeln!(out, "{} effects: {}", title, entries.len());
+            for entry in entries {
+                let loc = &entry.effect.location;
+                let _ = writeln!(
+                    out,
+                    "{} {} {} ({}:{})",
+                    sign,
+                    &entry.id[..12],
+                    effect_message(&entry.effect),
+                    loc.dir().join(loc.file()).display(),
+                    loc.start_line()
+                );
+            }
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::effect::{Effect, EffectInstance};
+    use crate::ident::CanonicalPath;
+    use proc_macro2::{Ident, Span};
+    use std::path::Path as FilePath;
+
+    fn asm_effect(file: &str, caller: &str) -> EffectReport {
+        let eff = EffectInstance::new_effect(
+            FilePath::new(file),
+            CanonicalPath::new(caller),
+            CanonicalPath::new("asm"),
+            &Ident::new("asm", Span::call_site()),
+            Effect::InlineAsm,
+        );
+        EffectReport::from(&eff)
+    }
+
+    fn report(effects: Vec<EffectReport>) -> Report {
+        Report {
+            version: REPORT_VERSION,
+            crate_path: "foo".into(),
+            crate_root: "/foo".into(),
+            effects,
+            functions: Vec::new(),
+            metadata: Default::default(),
+        }
+    }
+
+    #[test]
+    fn test_report_diff() {
+        let old = report(vec![
+            asm_effect("1.0/src/lib.rs", "foo::f"),
+            asm_effect("1.0/src/lib.rs", "foo::g"),
+        ]);
+        let new = report(vec![
+            asm_effect("1.1/src/lib.rs", "foo::f"),
+            asm_effect("1.1/src/lib.rs", "foo::f"),
+            asm_effect("1.1/src/other.rs", "foo::h"),
+        ]);
+
+        let diff = old.diff(&new);
+        let callers = |entries: &[DiffEntry]| {
+            entries.iter().map(|e| e.effect.caller.clone()).collect::<Vec<_>>()
+        };
+        assert_eq!(callers(&diff.added), ["foo::f", "foo::h"]);
+        assert_eq!(callers(&diff.removed), ["foo::g"]);
+        assert_eq!(diff.added[0].id, old.effects[0].id());
+        assert!(old.diff(&old).is_empty());
+    }
+    #[test]
+    fn test_report_cbor() {
+        let report = report(vec![asm_effect("src/lib.rs", "foo::f")]);
+        let cbor = report.to_cbor().unwrap();
+        assert!(cbor.len() < report.to_json().unwrap().len());
+        assert_eq!(Report::from_cbor(cbor.as_slice()).unwrap(), report);
+    }
+}
@@ -0,0 +1,45 @@
+//! GitHub Actions workflow commands, which show up as annotations on the
+//! lines of pull requests.
+
+use super::sarif::result_level;
+use super::{effect_message, effect_type_description, Report};
+
+impl Report {
+    /// Returns one `::warning` or `::notice` workflow command per effect.
+    /// Suppressed effects are left out, as they've already been reviewed.
+    pub fn to_github_annotations(&self) -> String {
+        let mut out = String::new();
+        for e in self.effects.iter().filter(|e| e.suppression.is_none()) {
+            let loc = &e.location;
+            let file = self.file_path(loc).to_string_lossy().replace('\\', "/");
+            let command = match result_level(e) {
+                "warning" => "warning",
+                _ => "notice",
+            };
+            // Workflow command columns are 1-based, while syn's are 0-based
+            out.push_str(&format!(
+                "::{} file={},line={},col={},endLine={},endColumn={},title={}::{}\n",
+                command,
+                escape_property(&file),
+                loc.start_line(),
+                loc.start_col() + 1,
+                loc.end_line(),
+                loc.end_col() + 1,
+                escape_property(effect_type_description(e.effect_type)),
+                escape_data(&effect_message(e)),
+            ));
+        }
+        out
+    }
+}
+
+/// Escapes the message of a workflow command
+fn escape_data(s: &str) -> String {
+    s.replace('%', "%25").replace('\r', "%0D").replace('\n', "%0A")
+}
+
+/// Escapes a property value of a workflow command, which also can't contain
+/// the separators between properties
+fn escape_property(s: &str) -> String {
+    escape_data(s).replace(':', "%3A").replace(',', "%2C")
+}
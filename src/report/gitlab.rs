@@ -0,0 +1,62 @@
+//! GitLab Code Quality reports, which show up in the merge request widget.
+
+use std::collections::HashMap;
+
+use anyhow::Result;
+use serde_json::json;
+
+use super::sarif::result_level;
+use super::{effect_message, EffectReport, Report};
+use crate::sink::Severity;
+
+impl Report {
+    /// Returns the report as a GitLab Code Quality report, with one issue per
+    /// effect. Suppressed effects are left out, as they've already been
+    /// reviewed.
+    pub fn to_gitlab_code_quality(&self) -> Result<String> {
+        let mut occurrences = HashMap::new();
+        let issues = self
+            .effects
+            .iter()
+            .filter(|e| e.suppression.is_none())
+            .map(|e| {
+                let loc = &e.location;
+                let path = self.file_path(loc).to_string_lossy().replace('\\', "/");
+                let fingerprint = e.id();
+                // Effects which only differ in their line get the same hash,
+                // so number them to keep fingerprints unique
+                let n = occurrences.entry(fingerprint.clone()).or_insert(0);
+                *n += 1;
+                json!({
+                    "type": "issue",
+                    "check_name": e.effect_type.to_string(),
+                    "description": effect_message(e),
+                    "categories": ["Security"],
+                    "severity": code_quality_severity(e),
+                    "fingerprint": format!("{}-{}", fingerprint, n),
+                    "location": {
+                        "path": path,
+                        "lines": { "begin": loc.start_line(), "end": loc.end_line() },
+                    },
+                })
+            })
+            .collect::<Vec<_>>();
+        Ok(serde_json::to_string_pretty(&issues)?)
+    }
+}
+
+/// Sink calls are ranked by the severity of the sink, and other effects by
+/// whether Rust considers them unsafe. Effects external callers can't
+/// trigger are only informational.
+fn code_quality_severity(e: &EffectReport) -> &'static str {
+    if !e.pub_reachable {
+        return "info";
+    }
+    match e.severity {
+        Some(Severity::High) => "critical",
+        Some(Severity::Medium) => "major",
+        Some(Severity::Low) => "minor",
+        None if result_level(e) == "warning" => "major",
+        None => "minor",
+    }
+}
@@ -0,0 +1,263 @@
+//! Standalone HTML rendering of a report, for reviewers who don't use the
+//! command line.
+//!
+//! The page has no external dependencies: it includes a breakdown of the
+//! effects per crate, per module and per sink category, and a table of all effects with a
+//! snippet of the source code around each one, if the report includes them
+//! (see [`Report::include_snippets`]). Clicking a table header sorts the
+//! table by that column.
+
+use super::{caller_module, effect_type_description, EffectReport, Report, Snippet};
+use crate::audit_file::SafetyAnnotation;
+use crate::effect::SrcLoc;
+
+use anyhow::Result;
+use std::collections::BTreeMap;
+use std::fmt::{self, Write};
+
+const STYLE: &str = "
+body { font-family: sans-serif; margin: 2em; }
+table { border-collapse: collapse; margin-bottom: 2em; }
+th, td { border: 1px solid #ccc; padding: 4px 8px; text-align: left; vertical-align: top; }
+th { background: #eee; cursor: pointer; }
+pre { margin: 4px 0; background: #f8f8f8; }
+.effect-line { background: #fff3b0; }
+.unsafe { color: #b00; font-weight: bold; }
+.skipped { color: #b60; }
+.safe { color: #070; }
+";
+
+const SORT_SCRIPT: &str = "
+document.querySelectorAll('table.sortable th').forEach((th, col) => {
+  th.addEventListener('click', () => {
+    const body = th.closest('table').tBodies[0];
+    const asc = th.dataset.asc !== 'true';
+    th.dataset.asc = asc;
+    const key = row => row.cells[col].dataset.sort ?? row.cells[col].innerText;
+    const rows = Array.from(body.rows).sort((a, b) => {
+      const [x, y] = [key(a), key(b)];
+      const cmp = isNaN(x) || isNaN(y) ? x.localeCompare(y) : x - y;
+      return asc ? cmp : -cmp;
+    });
+    rows.forEach(row => body.appendChild(row));
+  });
+});
+";
+
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn audit_class(a: SafetyAnnotation) -> &'static str {
+    match a {
+        SafetyAnnotation::Unsafe => "unsafe",
+        SafetyAnnotation::Skipped => "skipped",
+        SafetyAnnotation::Safe | SafetyAnnotation::CallerChecked => "safe",
+    }
+}
+
+/// Renders the snippet, highlighting the lines of the effect
+fn render_snippet(snippet: &Snippet, loc: &SrcLoc) -> String {
+    let mut html = String::new();
+    for (line_num, line) in snippet.numbered_lines() {
+        let line = escape(line);
+        if (loc.start_line()..=loc.end_line()).contains(&line_num) {
+            let _ = writeln!(
+                html,
+                "<span class=\"effect-line\">{:>5} | {}</span>",
+                line_num, line
+            );
+        } else {
+            let _ = writeln!(html, "{:>5} | {}", line_num, line);
+        }
+    }
+    html
+}
+
+/// Writes a sortable table of effect counts grouped by the given key
+fn write_breakdown<F>(
+    html: &mut String,
+    title: &str,
+    effects: &[EffectReport],
+    has_audit: bool,
+    key: F,
+) -> fmt::Result
+where
+    F: Fn(&EffectReport) -> &str,
+{
+    let mut counts: BTreeMap<&str, (usize, usize)> = BTreeMap::new();
+    for e in effects {
+        let entry = counts.entry(key(e)).or_default();
+        entry.0 += 1;
+        if e.audit == Some(SafetyAnnotation::Skipped) {
+            entry.1 += 1;
+        }
+    }
+
+    writeln!(html, "<h2>Effects per {}</h2>", title)?;
+    writeln!(html, "<table class=\"sortable\">")?;
+    write!(html, "<thead><tr><th>{}</th><th>Effects</th>", escape(title))?;
+    if has_audit {
+        write!(html, "<th>Unaudited</th>")?;
+    }
+    writeln!(html, "</tr></thead><tbody>")?;
+    for (name, (total, unaudited)) in counts {
+        write!(html, "<tr><td>{}</td><td>{}</td>", escape(name), total)?;
+        if has_audit {
+            write!(html, "<td>{}</td>", unaudited)?;
+        }
+        writeln!(html, "</tr>")?;
+    }
+    writeln!(html, "</tbody></table>")
+}
+
+impl Report {
+    /// Returns the report as a standalone HTML page
+    pub fn to_html(&self) -> Result<String> {
+        let has_audit = self.effects.iter().any(|e| e.audit.is_some());
+        let title = format!("cargo-scan report: {}", self.crate_path.display());
+
+        let mut html = String::new();
+        writeln!(html, "<!DOCTYPE html>")?;
+        writeln!(html, "<html><head><meta charset=\"utf-8\">")?;
+        writeln!(html, "<title>{}</title>", escape(&title))?;
+        writeln!(html, "<style>{}</style></head><body>", STYLE)?;
+        writeln!(html, "<h1>{}</h1>", escape(&title))?;
+        writeln!(
+            html,
+            "<p>{} effects, {} lines of code scanned (report version {})</p>",
+            self.effects.len(),
+            self.metadata.total.loc_ub,
+            self.version
+        )?;
+
+        write_breakdown(&mut html, "crate", &self.effects, has_audit, |e| &e.crate_name)?;
+        write_breakdown(&mut html, "module", &self.effects, has_audit, |e| {
+            caller_module(&e.caller)
+        })?;
+        if self.effects.iter().any(|e| e.category.is_some()) {
+            write_breakdown(&mut html, "sink category", &self.effects, has_audit, |e| {
+                e.category.map_or("none", |c| c.as_str())
+            })?;
+        }
+
+        writeln!(html, "<h2>Effects</h2>")?;
+        writeln!(html, "<table class=\"sortable\"><thead><tr>")?;
+        writeln!(
+            html,
+            "<th>Crate</th><th>Caller</th><th>Callee</th><th>Effect</th><th>Location</th><th>Reachable</th>"
+        )?;
+        if has_audit {
+            writeln!(html, "<th>Audit</th>")?;
+        }
+        writeln!(html, "</tr></thead><tbody>")?;
+
+        for e in &self.effects {
+            let loc = &e.location;
+            let effect = match (&e.library, &e.pattern, e.category, e.severity) {
+                (Some(library), _, _, _) => {
+                    format!("{} (loads {})", e.effect_type, escape(library))
+                }
+                (None, Some(pattern), Some(category), Some(severity)) => format!(
+                    "{} ({}; {}, {} severity)",
+                    escape(pattern),
+                    e.effect_type,
+                    category,
+                    severity
+                ),
+                (None, Some(pattern), _, _) => {
+                    format!("{} ({})", escape(pattern), e.effect_type)
+                }
+                (None, None, _, _) => e.effect_type.to_string(),
+            };
+            // Build-time effects run on the developer's machine, so they're
+            // flagged wherever they show up
+            let effect = if e.provenance.is_src() {
+                effect
+            } else {
+                format!("{} [{}]", effect, e.provenance)
+            };
+            let effect = if e.target_kind.is_src() {
+                effect
+            } else {
+                format!("{} [{}]", effect, e.target_kind)
+            };
+            let effect = if e.cfg_gates.is_empty() {
+                effect
+            } else {
+                format!("{} (cfg {})", effect, escape(&e.cfg_gates.join(", ")))
+            };
+            let effect = if e.platform_gates.is_empty() {
+                effect
+            } else {
+                format!("{} (on {})", effect, escape(&e.platform_gates.join(", ")))
+            };
+            let effect = if e.features.is_empty() {
+                effect
+            } else {
+                format!("{} (only with {})", effect, escape(&e.features.join("; ")))
+            };
+            writeln!(html, "<tr>")?;
+            writeln!(html, "<td>{}</td>", escape(&e.crate_name))?;
+            writeln!(html, "<td>{}</td>", escape(&e.caller))?;
+            writeln!(html, "<td>{}</td>", escape(&e.callee))?;
+            writeln!(
+                html,
+                "<td title=\"{}\">{}</td>",
+                effect_type_description(e.effect_type),
+                effect
+            )?;
+
+            let loc_str = format!(
+                "{}:{}:{}",
+                loc.dir().join(loc.file()).display(),
+                loc.start_line(),
+                loc.start_col()
+            );
+            match &e.snippet {
+                Some(snippet) => writeln!(
+                    html,
+                    "<td data-sort=\"{0}\"><details><summary>{0}</summary><pre>{1}</pre></details></td>",
+                    escape(&loc_str),
+                    render_snippet(snippet, loc)
+                )?,
+                None => writeln!(html, "<td>{}</td>", escape(&loc_str))?,
+            }
+            writeln!(
+                html,
+                "<td title=\"Reachable from the public API\">{}</td>",
+                if e.pub_reachable { "yes" } else { "no" }
+            )?;
+
+            if has_audit {
+                match e.audit {
+                    Some(a) => match &e.audit_comment {
+                        Some(comment) => writeln!(
+                            html,
+                            "<td class=\"{}\"><details><summary>{}</summary>{}</details></td>",
+                            audit_class(a),
+                            escape(&a.to_string()),
+                            escape(comment)
+                        )?,
+                        None => writeln!(
+                            html,
+                            "<td class=\"{}\">{}</td>",
+                            audit_class(a),
+                            escape(&a.to_string())
+                        )?,
+                    },
+                    None => writeln!(html, "<td></td>")?,
+                }
+            }
+            writeln!(html, "</tr>")?;
+        }
+        writeln!(html, "</tbody></table>")?;
+
+        writeln!(html, "<script>{}</script>", SORT_SCRIPT)?;
+        writeln!(html, "</body></html>")?;
+        Ok(html)
+    }
+}
@@ -0,0 +1,74 @@
+//! Language Server Protocol diagnostics, which show the effects in editors.
+
+use lsp_types::{Diagnostic, DiagnosticSeverity, NumberOrString, Position, Range};
+use serde_json::json;
+
+use super::sarif::result_level;
+use super::{effect_message, EffectReport};
+use crate::suppress::effect_type_name;
+
+/// The source of the diagnostics, as shown in editors
+pub const LSP_SOURCE: &str = "cargo-scan";
+
+impl EffectReport {
+    /// The effect as a diagnostic on its span. Effects which Rust considers
+    /// unsafe and high-severity sink calls are warnings, the other effects
+    /// information, and effects external callers can't trigger hints.
+    ///
+    /// The diagnostic's code is the effect type's name in suppression
+    /// comments (e.g. `ffi_call`), and its data holds the effect's `id` and
+    /// caller.
+    pub fn to_lsp_diagnostic(&self) -> Diagnostic {
+        let severity = match result_level(self) {
+            _ if !self.pub_reachable => DiagnosticSeverity::HINT,
+            "warning" => DiagnosticSeverity::WARNING,
+            _ => DiagnosticSeverity::INFORMATION,
+        };
+        // LSP lines are 0-based, while syn's are 1-based
+        let loc = &self.location;
+        let position = |line: usize, col: usize| {
+            Position::new(line.saturating_sub(1) as u32, col as u32)
+        };
+        Diagnostic {
+            range: Range::new(
+                position(loc.start_line(), loc.start_col()),
+                position(loc.end_line(), loc.end_col()),
+            ),
+            severity: Some(severity),
+            code: Some(NumberOrString::String(effect_type_name(self.effect_type))),
+            source: Some(LSP_SOURCE.to_string()),
+            message: effect_message(self),
+            data: Some(json!({ "id": self.id(), "caller": self.caller })),
+            ..Default::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::effect::{Effect, EffectInstance};
+    use crate::ident::CanonicalPath;
+    use proc_macro2::{Ident, Span};
+    use std::path::Path;
+
+    #[test]
+    fn test_lsp_diagnostic() {
+        let effect = EffectInstance::new_effect(
+            Path::new("src/lib.rs"),
+            CanonicalPath::new("foo::f"),
+            CanonicalPath::new("libc::free"),
+            &Ident::new("free", Span::call_site()),
+            Effect::FFICall(CanonicalPath::new("libc::free")),
+        );
+        let report = EffectReport::from(&effect);
+        let diagnostic = report.to_lsp_diagnostic();
+        assert_eq!(diagnostic.severity, Some(DiagnosticSeverity::WARNING));
+        assert_eq!(diagnostic.code, Some(NumberOrString::String("ffi_call".into())));
+        assert_eq!(diagnostic.data.unwrap()["caller"], "foo::f");
+
+        let unreachable = EffectReport { pub_reachable: false, ..report };
+        let diagnostic = unreachable.to_lsp_diagnostic();
+        assert_eq!(diagnostic.severity, Some(DiagnosticSeverity::HINT));
+    }
+}
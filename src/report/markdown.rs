@@ -0,0 +1,135 @@
+//! Markdown summary of a report, for pasting into a pull request description
+//! or a security review ticket.
+//!
+//! The summary has the number of effects and the risk score of each crate, a
+//! table of the high-severity sink calls, and a collapsible section per module
+//! listing its effects. Suppressed effects are left out, as they've already
+//! been reviewed.
+
+use super::{caller_module, EffectReport, Report};
+use crate::risk::risk_score;
+use crate::sink::Severity;
+
+use anyhow::Result;
+use std::collections::BTreeMap;
+use std::fmt::{self, Write};
+
+/// Escapes text inside a table cell
+fn escape(s: &str) -> String {
+    s.replace('|', "\\|")
+}
+
+fn location(e: &EffectReport) -> String {
+    let loc = &e.location;
+    format!("{}:{}", loc.dir().join(loc.file()).display(), loc.start_line())
+}
+
+/// The effect type, along with the matched pattern and library if any
+fn effect_summary(e: &EffectReport) -> String {
+    match (&e.library, &e.pattern) {
+        (Some(library), _) => format!("{} (loads `{}`)", e.effect_type, escape(library)),
+        (None, Some(pattern)) => format!("{} (`{}`)", e.effect_type, escape(pattern)),
+        (None, None) => e.effect_type.to_string(),
+    }
+}
+
+/// Writes a table with a row per effect
+fn write_effect_table(md: &mut String, effects: &[&EffectReport]) -> fmt::Result {
+    writeln!(md, "| Caller | Callee | Effect | Location | Reachable |")?;
+    writeln!(md, "| --- | --- | --- | --- | --- |")?;
+    for e in effects {
+        writeln!(
+            md,
+            "| `{}` | `{}` | {} | {} | {} |",
+            escape(&e.caller),
+            escape(&e.callee),
+            effect_summary(e),
+            escape(&location(e)),
+            if e.pub_reachable { "yes" } else { "no" }
+        )?;
+    }
+    Ok(())
+}
+
+impl Report {
+    /// Returns a Markdown summary of the report
+    pub fn to_markdown(&self) -> Result<String> {
+        let effects =
+            self.effects.iter().filter(|e| e.suppression.is_none()).collect::<Vec<_>>();
+
+        let mut md = String::new();
+        writeln!(md, "# cargo-scan report: `{}`", self.crate_path.display())?;
+        writeln!(md)?;
+        writeln!(
+            md,
+            "{} effects, {} lines of code scanned.",
+            effects.len(),
+            self.metadata.total.loc_ub
+        )?;
+        writeln!(md)?;
+
+        let mut crates: BTreeMap<&str, Vec<&EffectReport>> = BTreeMap::new();
+        for e in &effects {
+            crates.entry(&e.crate_name).or_default().push(e);
+        }
+        writeln!(md, "## Effects per crate")?;
+        writeln!(md)?;
+        writeln!(md, "| Crate | Effects | High severity | Risk score |")?;
+        writeln!(md, "| --- | ---: | ---: | ---: |")?;
+        for (name, crate_effects) in crates {
+            let high = crate_effects
+                .iter()
+                .filter(|e| e.severity == Some(Severity::High))
+                .count();
+            let score = risk_score(
+                crate_effects
+                    .iter()
+                    .map(|e| (e.effect_type, e.severity, e.pub_reachable)),
+            );
+            writeln!(
+                md,
+                "| `{}` | {} | {} | {:.1} |",
+                escape(name),
+                crate_effects.len(),
+                high,
+                score
+            )?;
+        }
+        writeln!(md)?;
+
+        writeln!(md, "## High-severity findings")?;
+        writeln!(md)?;
+        let high = effects
+            .iter()
+            .copied()
+            .filter(|e| e.severity == Some(Severity::High))
+            .collect::<Vec<_>>();
+        if high.is_empty() {
+            writeln!(md, "None.")?;
+        } else {
+            write_effect_table(&mut md, &high)?;
+        }
+        writeln!(md)?;
+
+        let mut modules: BTreeMap<&str, Vec<&EffectReport>> = BTreeMap::new();
+        for e in &effects {
+            modules.entry(caller_module(&e.caller)).or_default().push(*e);
+        }
+        writeln!(md, "## Effects per module")?;
+        for (module, module_effects) in modules {
+            writeln!(md)?;
+            writeln!(
+                md,
+                "<details><summary><code>{}</code> ({} effects)</summary>",
+                module,
+                module_effects.len()
+            )?;
+            // GitHub only renders Markdown inside <details> after a blank line
+            writeln!(md)?;
+            write_effect_table(&mut md, &module_effects)?;
+            writeln!(md)?;
+            writeln!(md, "</details>")?;
+        }
+        Ok(md)
+    }
+}
@@ -0,0 +1,451 @@
+//! Structured reports of scan results.
+//!
+//! The CSV output of the `scan` binary is easy to read, but it drops the end
+//! of each effect's span and is hard to extend. A [`Report`] instead
+//! serializes the effects and scan metadata of a crate to JSON, so that they
+//! can be consumed by other tooling. Reports can also be rendered as SARIF
+//! for code scanning, or as a standalone HTML page for reviewers.
+
+use crate::audit_file::{AuditFile, SafetyAnnotation};
+use crate::cbor;
+use crate::effect::{EffectInstance, EffectType, Provenance, SrcLoc, TargetKind};
+use crate::loc_tracker::LoCTracker;
+use crate::native_libs::NativeLib;
+use crate::scan_stats::CrateStats;
+use crate::scanner::{ScanResults, UnsafeCodeLint};
+use crate::sink::{Severity, SinkCategory};
+use crate::suppress::{self, Suppression};
+
+use anyhow::Result;
+use clap::ValueEnum;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+use std::fmt::{self, Write};
+use std::path::{Path as FilePath, PathBuf};
+
+pub use blocks::{FnBlockReport, UnsafeBlockReport};
+pub use diff::{DiffEntry, ReportDiff};
+pub use lsp::LSP_SOURCE;
+pub use snippet::Snippet;
+
+mod blocks;
+mod diff;
+mod github;
+mod gitlab;
+mod html;
+mod lsp;
+mod markdown;
+mod sarif;
+mod snippet;
+mod terminal;
+
+/// The version of the report format. Bump this whenever a field is removed or
+/// changes meaning, so consumers can tell which fields to expect.
+pub const REPORT_VERSION: u32 = 2;
+
+/// The output formats supported by the scan binaries
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OutputFormat {
+    #[default]
+    Csv,
+    Json,
+    Jsonl,
+    /// The JSON report, encoded in the more compact CBOR
+    Cbor,
+    Sarif,
+    Github,
+    Gitlab,
+    Markdown,
+    Stats,
+    Terminal,
+}
+
+impl fmt::Display for OutputFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            OutputFormat::Csv => "csv",
+            OutputFormat::Json => "json",
+            OutputFormat::Jsonl => "jsonl",
+            OutputFormat::Cbor => "cbor",
+            OutputFormat::Sarif => "sarif",
+            OutputFormat::Github => "github",
+            OutputFormat::Gitlab => "gitlab",
+            OutputFormat::Markdown => "markdown",
+            OutputFormat::Stats => "stats",
+            OutputFormat::Terminal => "terminal",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+/// A single effect in the report
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, JsonSchema)]
+pub struct EffectReport {
+    pub crate_name: String,
+    /// The function or module scope containing the effect
+    pub caller: String,
+    pub callee: String,
+    pub effect_type: EffectType,
+    /// The matched sink pattern for sink calls, e.g. `std::fs`
+    pub pattern: Option<String>,
+    /// The category of the matched sink, for sink calls
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub category: Option<SinkCategory>,
+    /// The severity of the matched sink, for sink calls
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub severity: Option<Severity>,
+    /// The library loaded, for dynamic library loads with a literal argument
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub library: Option<String>,
+    /// The function reached, for indirect calls through a function pointer
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub origin: Option<String>,
+    /// The full span of the effect
+    pub location: SrcLoc,
+    /// The sets of features the effect was found under, if the crate was
+    /// scanned under several and the effect isn't found under all of them
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub features: Vec<String>,
+    /// The feature-dependent `#[cfg(...)]` predicates of the items enclosing
+    /// the effect, outermost first
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub cfg_gates: Vec<String>,
+    /// The platform-dependent `#[cfg(...)]` predicates of the items
+    /// enclosing the effect, e.g. `unix`, outermost first
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub platform_gates: Vec<String>,
+    /// Where the code containing the effect runs: `build` for build scripts
+    /// and crates only used by them, which run at compile time
+    #[serde(default, skip_serializing_if = "Provenance::is_src")]
+    pub provenance: Provenance,
+    /// The kind of target the code containing the effect belongs to, e.g.
+    /// `test` for tests
+    #[serde(default, skip_serializing_if = "TargetKind::is_src")]
+    pub target_kind: TargetKind,
+    /// Whether the effect can be reached from the crate's public API, i.e.
+    /// whether external callers can trigger it
+    #[serde(default = "pub_reachable_default")]
+    pub pub_reachable: bool,
+    /// The effect's annotation in the audit, if the report includes one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub audit: Option<SafetyAnnotation>,
+    /// The auditor's comment on the effect's annotation, if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub audit_comment: Option<String>,
+    /// The comment suppressing the effect, for suppressed effects
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub suppression: Option<Suppression>,
+    /// The source code around the effect, if the report includes snippets
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub snippet: Option<Snippet>,
+}
+
+impl From<&EffectInstance> for EffectReport {
+    fn from(eff: &EffectInstance) -> Self {
+        Self {
+            crate_name: eff.caller().crate_name().to_string(),
+            caller: eff.caller_path().to_string(),
+            callee: eff.callee_path().to_string(),
+            effect_type: EffectType::from(eff.eff_type()),
+            pattern: eff.pattern().map(|p| p.to_string()),
+            category: eff.sink_category(),
+            severity: eff.sink_severity(),
+            library: eff.eff_type().dyn_load_library().map(String::from),
+            origin: eff.eff_type().indirect_origin().map(|o| o.to_string()),
+            location: eff.call_loc().clone(),
+            features: Vec::new(),
+            cfg_gates: eff.cfg_gates().to_vec(),
+            platform_gates: eff.platform_gates().to_vec(),
+            provenance: eff.provenance(),
+            target_kind: eff.target_kind(),
+            pub_reachable: true,
+            audit: None,
+            audit_comment: None,
+            suppression: None,
+            snippet: None,
+        }
+    }
+}
+
+impl EffectReport {
+    /// A stable identifier of the effect, which doesn't change when the
+    /// effect moves within its file or the crate moves to another directory
+    pub fn id(&self) -> String {
+        let mut hasher = Sha256::new();
+        for part in [
+            self.crate_name.as_str(),
+            &self.caller,
+            &self.callee,
+            &self.effect_type.to_string(),
+            self.pattern.as_deref().unwrap_or_default(),
+            &self.location.file().to_string_lossy(),
+        ] {
+            hasher.update(part.as_bytes());
+            // Separator, so that e.g. ("ab", "c") and ("a", "bc") differ
+            hasher.update([0]);
+        }
+        hasher.finalize().iter().fold(String::new(), |mut s, b| {
+            let _ = write!(s, "{:02x}", b);
+            s
+        })
+    }
+}
+
+/// The effects in the results of a scan of the crate at `crate_path`, as in
+/// reports, leaving out those suppressed by comments
+pub fn effect_reports(crate_path: &FilePath, results: ScanResults) -> Vec<EffectReport> {
+    let unreachable =
+        results.dead_code_effects().into_iter().cloned().collect::<HashSet<_>>();
+    let (effects, _) = suppress::partition_suppressed(results.effects);
+    effects
+        .iter()
+        .map(|e| EffectReport {
+            pub_reachable: !unreachable.contains(e),
+            location: e.call_loc().relative_to(crate_path),
+            ..EffectReport::from(e)
+        })
+        .collect()
+}
+
+/// Reports from before reachability was tracked treat every effect as
+/// reachable
+fn pub_reachable_default() -> bool {
+    true
+}
+
+/// The number of code excerpts of some kind, and the lines of code they span
+#[derive(
+    Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq, JsonSchema,
+)]
+pub struct LoCSummary {
+    pub instances: usize,
+    pub loc_lb: usize,
+    pub loc_ub: usize,
+}
+
+impl From<&LoCTracker> for LoCSummary {
+    fn from(tracker: &LoCTracker) -> Self {
+        Self {
+            instances: tracker.get_instances(),
+            loc_lb: tracker.get_loc_lb(),
+            loc_ub: tracker.get_loc_ub(),
+        }
+    }
+}
+
+/// Scan metadata, matching the columns of `CrateStats::metadata_csv`
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq, JsonSchema)]
+pub struct ReportMetadata {
+    pub total: LoCSummary,
+    pub skipped_macros: LoCSummary,
+    pub skipped_conditional_code: LoCSummary,
+    pub skipped_fn_calls: LoCSummary,
+    pub skipped_fn_ptrs: LoCSummary,
+    pub skipped_other: LoCSummary,
+    pub unsafe_traits: LoCSummary,
+    pub unsafe_impls: LoCSummary,
+    #[serde(default)]
+    pub unsafe_blocks: LoCSummary,
+    pub pub_fns: usize,
+    pub pub_fns_with_effects: usize,
+    pub pub_total_effects: usize,
+    pub audited_fns: usize,
+    pub audited_loc: usize,
+    pub main_fns: usize,
+    pub main_reachable_effects: usize,
+    pub main_unreachable_effects: usize,
+    pub unsafe_code_lint: Option<UnsafeCodeLint>,
+    pub unsafe_code_overrides: usize,
+    pub native_libs: Vec<NativeLib>,
+    #[serde(default)]
+    pub suppressed_effects: usize,
+}
+
+impl From<&CrateStats> for ReportMetadata {
+    fn from(stats: &CrateStats) -> Self {
+        Self {
+            total: (&stats.total_loc).into(),
+            skipped_macros: (&stats.skipped_macros).into(),
+            skipped_conditional_code: (&stats.skipped_conditional_code).into(),
+            skipped_fn_calls: (&stats.skipped_fn_calls).into(),
+            skipped_fn_ptrs: (&stats.skipped_fn_ptrs).into(),
+            skipped_other: (&stats.skipped_other).into(),
+            unsafe_traits: (&stats.unsafe_traits).into(),
+            unsafe_impls: (&stats.unsafe_impls).into(),
+            unsafe_blocks: (&stats.unsafe_blocks).into(),
+            pub_fns: stats.pub_fns,
+            pub_fns_with_effects: stats.pub_fns_with_effects,
+            pub_total_effects: stats.pub_total_effects,
+            audited_fns: stats.audited_fns,
+            audited_loc: stats.audited_loc,
+            main_fns: stats.main_fns,
+            main_reachable_effects: stats.main_reachable_effects,
+            main_unreachable_effects: stats.main_unreachable_effects,
+            unsafe_code_lint: stats.unsafe_code_lint,
+            unsafe_code_overrides: stats.unsafe_code_overrides,
+            native_libs: stats.native_libs.clone(),
+            suppressed_effects: stats.suppressed_effects.len(),
+        }
+    }
+}
+
+/// The scan results of a single crate
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, JsonSchema)]
+pub struct Report {
+    /// See [`REPORT_VERSION`]
+    pub version: u32,
+    /// The path of the crate as given to the scan
+    pub crate_path: PathBuf,
+    /// The absolute path of the crate. The locations of effects are relative
+    /// to it, so reports are the same wherever the crate is scanned.
+    #[serde(default)]
+    pub crate_root: PathBuf,
+    pub effects: Vec<EffectReport>,
+    /// The effects in each function, if grouped with
+    /// [`Report::group_by_block`]. These are left out of `effects`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub functions: Vec<FnBlockReport>,
+    pub metadata: ReportMetadata,
+}
+
+impl Report {
+    pub fn from_stats(stats: &CrateStats) -> Self {
+        Self {
+            version: REPORT_VERSION,
+            crate_path: stats.crate_path.clone(),
+            crate_root: stats
+                .crate_path
+                .canonicalize()
+                .unwrap_or_else(|_| stats.crate_path.clone()),
+            effects: stats
+                .effects
+                .iter()
+                .map(|e| EffectReport {
+                    pub_reachable: !stats.unreachable_effects.contains(e),
+                    features: stats
+                        .feature_gated_effects
+                        .get(e)
+                        .cloned()
+                        .unwrap_or_default(),
+                    location: e.call_loc().relative_to(&stats.crate_path),
+                    ..EffectReport::from(e)
+                })
+                .collect(),
+            functions: Vec::new(),
+            metadata: stats.into(),
+        }
+    }
+
+    /// Includes the audit status of each effect from the crate's audit file
+    pub fn from_stats_with_audit(stats: &CrateStats, audit_file: &AuditFile) -> Self {
+        let mut report = Self::from_stats(stats);
+        for (eff, eff_report) in stats.effects.iter().zip(report.effects.iter_mut()) {
+            eff_report.audit =
+                audit_file.audit_trees.get(eff).map(|t| t.summary_annotation());
+            eff_report.audit_comment = audit_file.comments.get(eff).cloned();
+        }
+        report
+    }
+
+    /// Also lists the effects suppressed by comments in the source, along
+    /// with the comment suppressing each one
+    pub fn include_suppressed(&mut self, stats: &CrateStats) {
+        self.effects.extend(stats.suppressed_effects.iter().map(|(e, s)| EffectReport {
+            pub_reachable: !stats.unreachable_effects.contains(e),
+            features: stats.feature_gated_effects.get(e).cloned().unwrap_or_default(),
+            suppression: Some(s.clone()),
+            location: e.call_loc().relative_to(&stats.crate_path),
+            ..EffectReport::from(e)
+        }));
+    }
+
+    /// The path of the file containing the location, as seen from where the
+    /// crate was scanned
+    pub fn file_path(&self, loc: &SrcLoc) -> PathBuf {
+        let mut path = self.crate_path.clone();
+        if !loc.dir().as_os_str().is_empty() && loc.dir() != FilePath::new(".") {
+            path.push(loc.dir());
+        }
+        path.push(loc.file());
+        path
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    pub fn to_cbor(&self) -> Result<Vec<u8>> {
+        Ok(cbor::to_vec(self)?)
+    }
+}
+
+/// The path of the module (or type) containing the caller
+pub(crate) fn caller_module(caller: &str) -> &str {
+    caller.rsplit_once("::").map_or(caller, |(m, _)| m)
+}
+
+/// The callee, along with the function it holds for indirect calls through
+/// a function pointer
+pub(crate) fn callee_description(e: &EffectReport) -> String {
+    match &e.origin {
+        Some(origin) => format!("{} (holding {})", e.callee, origin),
+        None => e.callee.clone(),
+    }
+}
+
+/// A one-line description of the effect, e.g. for code scanning results
+fn effect_message(e: &EffectReport) -> String {
+    let callee = callee_description(e);
+    match (&e.pattern, &e.library) {
+        (_, Some(library)) => format!(
+            "{}: {} loads {} in {}",
+            effect_type_description(e.effect_type),
+            callee,
+            library,
+            e.caller
+        ),
+        (Some(pattern), None) => format!(
+            "{} to {} (matches {}) in {}",
+            effect_type_description(e.effect_type),
+            callee,
+            pattern,
+            e.caller
+        ),
+        (None, None) => format!(
+            "{}: {} in {}",
+            effect_type_description(e.effect_type),
+            callee,
+            e.caller
+        ),
+    }
+}
+
+fn effect_type_description(t: EffectType) -> &'static str {
+    match t {
+        EffectType::SinkCall => "Sink call",
+        EffectType::FFICall => "FFI call",
+        EffectType::UnsafeCall => "Unsafe call",
+        EffectType::RawPointer => "Raw pointer read",
+        EffectType::RawPointerWrite => "Raw pointer write",
+        EffectType::PtrOffset => "Pointer arithmetic",
+        EffectType::UnionFieldRead => "Union field read",
+        EffectType::UnionFieldWrite => "Union field write",
+        EffectType::StaticMut => "Static mut access",
+        EffectType::StaticExt => "Static FFI variable access",
+        EffectType::FnPtrCreation => "Function pointer creation",
+        EffectType::ClosureCreation => "Closure creation",
+        EffectType::RawPtrCast => "Cast to a raw pointer",
+        EffectType::FFIDecl => "FFI declaration",
+        EffectType::IndirectCall => "Indirect call through a function pointer",
+        EffectType::Transmute => "Transmute",
+        EffectType::InlineAsm => "Inline assembly",
+        EffectType::UninitMem => "Uninitialized memory",
+        EffectType::RawConstruct => "Construction from a raw pointer",
+        EffectType::DynLoad => "Dynamic library loading",
+        EffectType::FFIExport => "FFI export",
+        EffectType::SendSyncImpl => "Unsafe Send/Sync impl",
+        EffectType::Custom => "Custom detector effect",
+    }
+}
@@ -0,0 +1,131 @@
+//! SARIF 2.1.0 output, for CI code scanning (e.g. GitHub or Azure).
+
+use super::{effect_message, effect_type_description, EffectReport, Report};
+use crate::effect::EffectType;
+use crate::sink::Severity;
+
+use anyhow::Result;
+use serde_json::json;
+
+const SARIF_SCHEMA: &str = "https://json.schemastore.org/sarif-2.1.0.json";
+
+impl Report {
+    /// Returns the report as a SARIF 2.1.0 log for code scanning tools, with
+    /// one rule per effect type
+    pub fn to_sarif(&self) -> Result<String> {
+        let mut rules = Vec::new();
+        for e in &self.effects {
+            if !rules.contains(&e.effect_type) {
+                rules.push(e.effect_type);
+            }
+        }
+        let rules = rules
+            .into_iter()
+            .map(|t| {
+                json!({
+                    "id": t.to_string(),
+                    "shortDescription": { "text": effect_type_description(t) },
+                    "defaultConfiguration": { "level": sarif_level(t) },
+                })
+            })
+            .collect::<Vec<_>>();
+
+        let results = self
+            .effects
+            .iter()
+            .map(|e| {
+                let loc = &e.location;
+                let uri = self.file_path(loc).to_string_lossy().replace('\\', "/");
+                let message = effect_message(e);
+                // SARIF columns are 1-based, while syn's are 0-based
+                let mut result = json!({
+                    "ruleId": e.effect_type.to_string(),
+                    "level": result_level(e),
+                    "message": { "text": message },
+                    "locations": [{
+                        "physicalLocation": {
+                            "artifactLocation": { "uri": uri },
+                            "region": {
+                                "startLine": loc.start_line(),
+                                "startColumn": loc.start_col() + 1,
+                                "endLine": loc.end_line(),
+                                "endColumn": loc.end_col() + 1,
+                            },
+                        },
+                    }],
+                    "properties": {
+                        "crate": e.crate_name,
+                        "pubReachable": e.pub_reachable,
+                    },
+                });
+                if let (Some(category), Some(severity)) = (e.category, e.severity) {
+                    result["properties"]["category"] = json!(category);
+                    result["properties"]["severity"] = json!(severity);
+                }
+                if !e.features.is_empty() {
+                    result["properties"]["features"] = json!(e.features);
+                }
+                if !e.cfg_gates.is_empty() {
+                    result["properties"]["cfgGates"] = json!(e.cfg_gates);
+                }
+                if !e.platform_gates.is_empty() {
+                    result["properties"]["platformGates"] = json!(e.platform_gates);
+                }
+                if !e.provenance.is_src() {
+                    result["properties"]["provenance"] = json!(e.provenance);
+                }
+                if !e.target_kind.is_src() {
+                    result["properties"]["targetKind"] = json!(e.target_kind);
+                }
+                if let Some(suppression) = &e.suppression {
+                    result["suppressions"] = json!([{
+                        "kind": "inSource",
+                        "justification": suppression.reason.clone().unwrap_or_default(),
+                    }]);
+                }
+                result
+            })
+            .collect::<Vec<_>>();
+
+        let log = json!({
+            "$schema": SARIF_SCHEMA,
+            "version": "2.1.0",
+            "runs": [{
+                "tool": {
+                    "driver": {
+                        "name": "cargo-scan",
+                        "version": env!("CARGO_PKG_VERSION"),
+                        "rules": rules,
+                    },
+                },
+                "results": results,
+            }],
+        });
+        Ok(serde_json::to_string_pretty(&log)?)
+    }
+}
+
+/// The level of an effect's result. Effects external callers can't trigger
+/// are less urgent, while high-severity sinks (e.g. spawning processes) are
+/// more
+pub(super) fn result_level(e: &EffectReport) -> &'static str {
+    if !e.pub_reachable {
+        "note"
+    } else if e.severity == Some(Severity::High) {
+        "warning"
+    } else {
+        sarif_level(e.effect_type)
+    }
+}
+
+/// Effects which Rust considers unsafe are warnings, the rest are notes
+fn sarif_level(t: EffectType) -> &'static str {
+    match t {
+        EffectType::SinkCall
+        | EffectType::FnPtrCreation
+        | EffectType::ClosureCreation
+        | EffectType::RawPtrCast
+        | EffectType::IndirectCall => "note",
+        _ => "warning",
+    }
+}
@@ -0,0 +1,70 @@
+//! Snippets of the source code around effects, so that reviewers of exported
+//! reports can see what each effect is without opening its file.
+
+use super::Report;
+use crate::effect::SrcLoc;
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::read_to_string;
+use std::path::PathBuf;
+
+/// Consecutive lines of source code, starting at `first_line` (1-based)
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, JsonSchema)]
+pub struct Snippet {
+    pub first_line: usize,
+    pub lines: Vec<String>,
+}
+
+impl Snippet {
+    /// The lines along with their line numbers
+    pub fn numbered_lines(&self) -> impl Iterator<Item = (usize, &str)> {
+        self.lines.iter().enumerate().map(|(i, l)| (self.first_line + i, l.as_str()))
+    }
+}
+
+/// Reads source files at most once while taking snippets
+#[derive(Default)]
+struct SourceCache {
+    files: HashMap<PathBuf, Option<Vec<String>>>,
+}
+
+impl SourceCache {
+    /// The lines of the location in the file at `path`, along with `context`
+    /// lines before and after
+    fn snippet(
+        &mut self,
+        path: PathBuf,
+        loc: &SrcLoc,
+        context: usize,
+    ) -> Option<Snippet> {
+        let lines = self
+            .files
+            .entry(path.clone())
+            .or_insert_with(|| {
+                read_to_string(&path).ok().map(|s| s.lines().map(String::from).collect())
+            })
+            .as_ref()?;
+
+        let first = loc.start_line().saturating_sub(context).max(1);
+        let last = (loc.end_line() + context).min(lines.len());
+        if first > last {
+            return None;
+        }
+        Some(Snippet { first_line: first, lines: lines[first - 1..last].to_vec() })
+    }
+}
+
+impl Report {
+    /// Includes `context` lines of source code before and after each effect.
+    /// Effects whose file can't be read are left without a snippet.
+    pub fn include_snippets(&mut self, context: usize) {
+        let mut sources = SourceCache::default();
+        for i in 0..self.effects.len() {
+            let path = self.file_path(&self.effects[i].location);
+            self.effects[i].snippet =
+                sources.snippet(path, &self.effects[i].location, context);
+        }
+    }
+}
@@ -0,0 +1,186 @@
+//! Human-readable terminal rendering of a report, in the style of compiler
+//! diagnostics: each effect gets a header, its location, a syntax-highlighted
+//! snippet with the effect's span underlined, and notes on its caller.
+//!
+//! Highlighting works one line at a time, so block comments and string
+//! literals spanning several lines are only highlighted on their first line.
+
+use super::sarif::result_level;
+use super::{callee_description, effect_type_description, EffectReport, Report};
+
+use colored::{ColoredString, Colorize};
+use std::fmt::Write;
+use std::path::Path;
+
+const KEYWORDS: &[&str] = &[
+    "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else", "enum",
+    "extern", "false", "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod",
+    "move", "mut", "pub", "ref", "return", "self", "Self", "static", "struct", "super",
+    "trait", "true", "type", "union", "unsafe", "use", "where", "while",
+];
+
+/// Splits a line of Rust code into tokens, and colors keywords, literals,
+/// macro names and comments
+fn highlight(line: &str) -> String {
+    let chars = line.char_indices().collect::<Vec<_>>();
+    let mut out = String::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let (start, c) = chars[i];
+        let token_end = |j: usize| chars.get(j).map_or(line.len(), |(k, _)| *k);
+        if line[start..].starts_with("//") {
+            out.push_str(&line[start..].dimmed().to_string());
+            break;
+        } else if c == '"' {
+            // Up to the closing quote, skipping escaped characters
+            let mut j = i + 1;
+            while j < chars.len() && chars[j].1 != '"' {
+                j += if chars[j].1 == '\\' { 2 } else { 1 };
+            }
+            j = (j + 1).min(chars.len());
+            out.push_str(&line[start..token_end(j)].green().to_string());
+            i = j;
+        } else if c.is_ascii_digit() {
+            let mut j = i;
+            while j < chars.len()
+                && (chars[j].1.is_ascii_alphanumeric() || chars[j].1 == '_')
+            {
+                j += 1;
+            }
+            out.push_str(&line[start..token_end(j)].cyan().to_string());
+            i = j;
+        } else if c.is_alphabetic() || c == '_' {
+            let mut j = i;
+            while j < chars.len() && (chars[j].1.is_alphanumeric() || chars[j].1 == '_') {
+                j += 1;
+            }
+            let word = &line[start..token_end(j)];
+            let is_macro = chars.get(j).is_some_and(|(_, c)| *c == '!')
+                && chars.get(j + 1).is_none_or(|(_, c)| *c != '=');
+            let colored: ColoredString = if KEYWORDS.contains(&word) {
+                word.magenta()
+            } else if is_macro {
+                word.blue()
+            } else {
+                word.normal()
+            };
+            out.push_str(&colored.to_string());
+            i = j;
+        } else {
+            out.push(c);
+            i += 1;
+        }
+    }
+    out
+}
+
+/// The columns of the line to underline for the effect, if the line is part
+/// of its span. Lines inside a multi-line span are underlined from their first
+/// non-blank character.
+fn underline_range(
+    e: &EffectReport,
+    line_num: usize,
+    line: &str,
+) -> Option<(usize, usize)> {
+    let loc = &e.location;
+    if !(loc.start_line()..=loc.end_line()).contains(&line_num) {
+        return None;
+    }
+    let len = line.chars().count();
+    let indent = line.chars().take_while(|c| c.is_whitespace()).count();
+    let start = if line_num == loc.start_line() { loc.start_col() } else { indent };
+    let end = if line_num == loc.end_line() { loc.end_col() } else { len };
+    Some((start, end.max(start + 1)))
+}
+
+fn write_effect(out: &mut String, e: &EffectReport, path: &Path) {
+    let loc = &e.location;
+    let level = result_level(e);
+    let callee = callee_description(e);
+    let header = match (&e.library, &e.pattern) {
+        (Some(library), _) => format!("{}: `{}` loads {}", level, callee, library),
+        (None, Some(pattern)) => {
+            format!("{}: call to `{}` (matches {})", level, callee, pattern)
+        }
+        (None, None) => format!("{}: `{}`", level, callee),
+    };
+    let header = format!("{} [{}]", header, effect_type_description(e.effect_type));
+    let header =
+        if level == "warning" { header.yellow().bold() } else { header.cyan().bold() };
+    let _ = writeln!(out, "{}", header);
+
+    let width = e
+        .snippet
+        .as_ref()
+        .and_then(|s| s.numbered_lines().last())
+        .map_or(loc.end_line(), |(n, _)| n)
+        .to_string()
+        .len();
+    let gutter = format!("{:width$} |", "", width = width).blue().bold();
+    let _ = writeln!(
+        out,
+        "{:width$}{} {}:{}:{}",
+        "",
+        "-->".blue().bold(),
+        path.display(),
+        loc.start_line(),
+        loc.start_col() + 1,
+        width = width
+    );
+
+    if let Some(snippet) = &e.snippet {
+        let _ = writeln!(out, "{}", gutter);
+        for (line_num, line) in snippet.numbered_lines() {
+            let number = format!("{:>width$} |", line_num, width = width).blue().bold();
+            let _ = writeln!(out, "{} {}", number, highlight(line));
+            if let Some((start, end)) = underline_range(e, line_num, line) {
+                let marks = "^".repeat(end - start);
+                let marks = if level == "warning" {
+                    marks.yellow().bold()
+                } else {
+                    marks.cyan().bold()
+                };
+                let _ = writeln!(out, "{} {}{}", gutter, " ".repeat(start), marks);
+            }
+        }
+    }
+
+    let _ = writeln!(out, "{}", gutter);
+    let note = format!("{:width$} = {}:", "", "note".bold(), width = width);
+    let _ = writeln!(out, "{} in `{}` ({})", note, e.caller, e.crate_name);
+    if !e.pub_reachable {
+        let _ = writeln!(out, "{} not reachable from the crate's public API", note);
+    }
+    for (what, gates) in [("with", &e.cfg_gates), ("on", &e.platform_gates)] {
+        if !gates.is_empty() {
+            let _ = writeln!(
+                out,
+                "{} only compiled {} cfg({})",
+                note,
+                what,
+                gates.join(", ")
+            );
+        }
+    }
+    let _ = writeln!(out);
+}
+
+impl Report {
+    /// Returns the report as diagnostics for a terminal, one per effect.
+    /// Includes snippets only if the report does (see
+    /// [`Report::include_snippets`]).
+    pub fn to_terminal(&self) -> String {
+        let mut out = String::new();
+        for e in self.effects.iter().filter(|e| e.suppression.is_none()) {
+            write_effect(&mut out, e, &self.file_path(&e.location));
+        }
+        let _ = writeln!(
+            out,
+            "{}: {} effects in {}",
+            "summary".bold(),
+            self.effects.iter().filter(|e| e.suppression.is_none()).count(),
+            self.crate_path.display()
+        );
+        out
+    }
+}
@@ -179,6 +179,12 @@ impl<'a> Resolve<'a> for HackyResolver<'a> {
         result
     }
 
+    fn resolve_mod_path(&self) -> CanonicalPath {
+        let mut result = self.modpath.clone();
+        result.append_path(&self.get_mod_scope());
+        result
+    }
+
     fn resolve_ffi_ident(&self, i: &'a syn::Ident) -> Option<CanonicalPath> {
         self.ffi_decls.get(i).cloned()
     }
@@ -203,6 +209,17 @@ impl<'a> Resolve<'a> for HackyResolver<'a> {
         CanonicalType::new_owned_string(format!("UNKNOWN_TYPE::{}", i))
     }
 
+    fn resolve_impl_type(&self, p: &'a syn::Path) -> CanonicalPath {
+        // A type named by a single identifier which isn't imported is taken to
+        // be defined in the current module
+        let fst = &p.segments.first().unwrap().ident;
+        if p.segments.len() == 1 && !self.use_names.contains_key(fst) {
+            self.resolve_def(fst)
+        } else {
+            self.resolve_path(p)
+        }
+    }
+
     fn resolve_field_index(&self, idx: &'a syn::Index) -> CanonicalPath {
         let src_loc = SrcLoc::from_span(self.filepath, &idx.span());
         CanonicalPath::new_owned(format!("UNKNOWN_FIELD::{}", idx.index), src_loc)
@@ -327,6 +344,13 @@ impl<'a> HackyResolver<'a> {
     }
 
     fn scan_use_name(&mut self, n: &'a syn::UseName) {
+        // `use a::b::{self}` brings `b` itself into scope
+        if n.ident == "self" {
+            if let Some(&last) = self.scope_use.last() {
+                self.save_scope_use_under(last);
+            }
+            return;
+        }
         self.scope_use.push(&n.ident);
         self.save_scope_use_under(&n.ident);
         self.scope_use.pop();
@@ -1,4 +1,8 @@
+#[cfg(feature = "native")]
+mod file_resolver;
 pub mod hacky_resolver;
+#[cfg(feature = "native")]
 pub mod name_resolution;
 pub mod resolve;
+#[cfg(feature = "native")]
 mod util;
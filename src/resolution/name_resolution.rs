@@ -12,10 +12,13 @@ use crate::ident::{CanonicalPath, CanonicalType, Ident};
 use ra_ap_hir::{AssocItem, CfgAtom, Crate, Impl, Semantics};
 use ra_ap_hir_def::db::DefDatabase;
 use ra_ap_hir_def::{FunctionId, Lookup};
-use ra_ap_ide::{AnalysisHost, Diagnostic, FileId, LineCol, RootDatabase, TextSize};
+use ra_ap_ide::{
+    AnalysisHost, Change, Diagnostic, FileId, LineCol, RootDatabase, TextSize,
+};
+use ra_ap_ide_db::base_db::salsa::{ParallelDatabase, Snapshot};
 use ra_ap_ide_db::base_db::Upcast;
 use ra_ap_ide_db::defs::{Definition, IdentClass};
-use ra_ap_ide_db::FxHashMap;
+use ra_ap_ide_db::{FxHashMap, LineIndexDatabase};
 use ra_ap_load_cargo::{LoadCargoConfig, ProcMacroServerChoice};
 use ra_ap_project_model::{
     CargoConfig, CargoFeatures, CfgOverrides, InvocationLocation, InvocationStrategy,
@@ -122,30 +125,32 @@ impl Resolver {
         self.host.raw_database()
     }
 
-    fn find_file_id(&self, filepath: &Path) -> Result<FileId> {
+    /// Re-reads the file from disk, so that it's resolved with its current
+    /// contents. Returns whether it changed since it was last read.
+    ///
+    /// NOTE: Only the files rust-analyzer loaded with the crate are tracked;
+    ///       new files, or changes to Cargo.toml, need a new Resolver.
+    pub fn reload_file(&mut self, filepath: &Path) -> Result<bool> {
         let abs_path = canonicalize(filepath)?;
         let vfs_path = VfsPath::new_real_path(abs_path.display().to_string());
-
-        match self.vfs.file_id(&vfs_path) {
-            Some(file_id) => Ok(file_id),
-            None => Err(anyhow!("The id of path {:?} does not exist in Vfs", filepath)),
+        let Some(file_id) = self.vfs.file_id(&vfs_path) else {
+            return Err(anyhow!("The id of path {:?} does not exist in Vfs", filepath));
+        };
+        let text = std::fs::read_to_string(&abs_path)?;
+        if !self.vfs.set_file_contents(vfs_path, Some(text.clone().into_bytes())) {
+            return Ok(false);
         }
+        self.vfs.take_changes();
+        let mut change = Change::new();
+        change.change_file(file_id, Some(text.into()));
+        self.host.apply_change(change);
+        Ok(true)
     }
 
-    fn find_offset(&self, file_id: FileId, src_loc: SrcLoc) -> Result<TextSize> {
-        // LineCol is zero-based
-        let line: u32 = src_loc.start_line() as u32 - 1;
-        let col: u32 = src_loc.start_col() as u32 - 1;
-        let line_col = LineCol { line, col };
-
-        let line_index = self.host.analysis().file_line_index(file_id)?;
-        match line_index.offset(line_col) {
-            Some(offset) => Ok(offset),
-            None => Err(anyhow!(
-                "Could not find offset in file for source location {:?}",
-                src_loc
-            )),
-        }
+    /// Returns a snapshot of the database, which can be used to resolve files
+    /// on another thread
+    pub fn snapshot(&self) -> ResolverSnapshot<'_> {
+        ResolverSnapshot { db: self.db().snapshot(), vfs: &self.vfs }
     }
 
     pub fn get_cfg_options_for_crate(
@@ -174,6 +179,43 @@ impl Resolver {
     }
 }
 
+/// A read-only view of the Resolver's database.
+///
+/// The Resolver can't be shared between threads, but each thread can own a
+/// snapshot of it, so files can be resolved in parallel.
+pub struct ResolverSnapshot<'a> {
+    db: Snapshot<RootDatabase>,
+    vfs: &'a Vfs,
+}
+
+impl<'a> ResolverSnapshot<'a> {
+    fn find_file_id(&self, filepath: &Path) -> Result<FileId> {
+        let abs_path = canonicalize(filepath)?;
+        let vfs_path = VfsPath::new_real_path(abs_path.display().to_string());
+
+        match self.vfs.file_id(&vfs_path) {
+            Some(file_id) => Ok(file_id),
+            None => Err(anyhow!("The id of path {:?} does not exist in Vfs", filepath)),
+        }
+    }
+}
+
+fn find_offset(db: &RootDatabase, file_id: FileId, src_loc: SrcLoc) -> Result<TextSize> {
+    // LineCol is zero-based
+    let line: u32 = src_loc.start_line() as u32 - 1;
+    let col: u32 = src_loc.start_col() as u32 - 1;
+    let line_col = LineCol { line, col };
+
+    let line_index = db.line_index(file_id);
+    match line_index.offset(line_col) {
+        Some(offset) => Ok(offset),
+        None => Err(anyhow!(
+            "Could not find offset in file for source location {:?}",
+            src_loc
+        )),
+    }
+}
+
 /// Core API for the name resolution of Rust identifiers.
 /// ResolverImpl is used to perform the actual semantic queries.
 /// A new instance is created every time we parse a new file in Scanner.
@@ -181,7 +223,7 @@ impl Resolver {
 pub struct ResolverImpl<'a> {
     db: &'a RootDatabase,
     sems: Semantics<'a, RootDatabase>,
-    resolver: &'a Resolver,
+    vfs: &'a Vfs,
     /// The syntax tree of the file
     /// we are currently scanning
     src_file: SourceFile,
@@ -191,8 +233,8 @@ pub struct ResolverImpl<'a> {
 }
 
 impl<'a> ResolverImpl<'a> {
-    pub fn new(resolver: &'a Resolver, filepath: &Path) -> Result<Self> {
-        let db = resolver.db();
+    pub fn new(resolver: &'a ResolverSnapshot, filepath: &Path) -> Result<Self> {
+        let db = &*resolver.db;
         let sems = Semantics::new(db);
         let file_id = resolver.find_file_id(filepath)?;
         let src_file = sems.parse(file_id);
@@ -207,7 +249,7 @@ impl<'a> ResolverImpl<'a> {
         // )?;
         let file_diags = Vec::new();
 
-        Ok(ResolverImpl { db, sems, resolver, src_file, file_id, file_diags })
+        Ok(ResolverImpl { db, sems, vfs: resolver.vfs, src_file, file_id, file_diags })
     }
 
     fn def_source_loc(&self, def: &Definition) -> Option<SrcLoc> {
@@ -220,11 +262,11 @@ impl<'a> ResolverImpl<'a> {
         self.sems.parse(file_id);
 
         let range = self.sems.original_range(&node.value).range;
-        let vfs_path = self.resolver.vfs.file_path(file_id);
+        let vfs_path = self.vfs.file_path(file_id);
         let str_path = vfs_path.to_string();
         let filepath = Path::new(&str_path);
 
-        let line_index = self.resolver.host.analysis().file_line_index(file_id).ok()?;
+        let line_index = self.db.line_index(file_id);
         // LineCol is zero-based in RA
         let start_line_col = line_index.line_col(range.start());
         let end_line_col = line_index.line_col(range.end());
@@ -234,7 +276,8 @@ impl<'a> ResolverImpl<'a> {
             start_line_col.col as usize + 1,
             end_line_col.line as usize + 1,
             end_line_col.col as usize + 1,
-        );
+        )
+        .with_byte_range(range.start().into()..range.end().into());
 
         Some(src_loc)
     }
@@ -262,7 +305,7 @@ impl<'a> ResolverImpl<'a> {
     }
 
     fn token(&self, i: Ident, s: SrcLoc) -> Result<SyntaxToken> {
-        let offset = self.resolver.find_offset(self.file_id, s)?;
+        let offset = find_offset(self.db, self.file_id, s)?;
         get_token(&self.src_file, offset, i)
     }
 
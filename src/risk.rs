@@ -0,0 +1,105 @@
+//! Risk scores of crates.
+//!
+//! A crate's risk score sums a weight for each of its effects, so that
+//! maintainers can rank which dependencies to audit first. Effects which are
+//! more likely to be abused (e.g. spawning processes or loading libraries)
+//! weigh more, and effects that can't be reached from the crate's public API
+//! only count for a fraction of their weight.
+
+use crate::effect::{EffectInstance, EffectType};
+use crate::sink::Severity;
+
+/// The fraction of their weight which unreachable effects count for
+const UNREACHABLE_FACTOR: f64 = 0.25;
+
+/// The weight of an effect of the given type. Sink calls are weighted by the
+/// severity of the sink.
+pub fn effect_weight(t: EffectType, severity: Option<Severity>) -> f64 {
+    match t {
+        EffectType::SinkCall => match severity {
+            Some(Severity::High) => 10.0,
+            Some(Severity::Medium) | None => 5.0,
+            Some(Severity::Low) => 2.0,
+        },
+        // Weighted like a sink call of unknown severity
+        EffectType::Custom => 5.0,
+        EffectType::DynLoad | EffectType::InlineAsm => 8.0,
+        EffectType::FFICall
+        | EffectType::Transmute
+        | EffectType::RawPointerWrite
+        | EffectType::UninitMem => 4.0,
+        EffectType::UnsafeCall
+        | EffectType::RawConstruct
+        | EffectType::StaticExt
+        | EffectType::FFIExport
+        | EffectType::SendSyncImpl => 3.0,
+        EffectType::RawPointer
+        | EffectType::PtrOffset
+        | EffectType::UnionFieldRead
+        | EffectType::StaticMut => 2.0,
+        EffectType::UnionFieldWrite
+        | EffectType::FFIDecl
+        | EffectType::FnPtrCreation
+        | EffectType::IndirectCall => 1.0,
+        EffectType::ClosureCreation | EffectType::RawPtrCast => 0.5,
+    }
+}
+
+/// The risk score of a crate with the given effects, along with whether each
+/// can be reached from the crate's public API
+pub fn risk_score<I>(effects: I) -> f64
+where
+    I: IntoIterator<Item = (EffectType, Option<Severity>, bool)>,
+{
+    effects
+        .into_iter()
+        .map(|(t, severity, reachable)| {
+            let weight = effect_weight(t, severity);
+            if reachable {
+                weight
+            } else {
+                weight * UNREACHABLE_FACTOR
+            }
+        })
+        .sum()
+}
+
+/// The risk score of a crate with the given reachable and unreachable effects
+pub fn effects_risk_score<'a, R, U>(reachable: R, unreachable: U) -> f64
+where
+    R: IntoIterator<Item = &'a EffectInstance>,
+    U: IntoIterator<Item = &'a EffectInstance>,
+{
+    let item = |e: &EffectInstance, reachable| {
+        (EffectType::from(e.eff_type()), e.sink_severity(), reachable)
+    };
+    risk_score(
+        reachable
+            .into_iter()
+            .map(|e| item(e, true))
+            .chain(unreachable.into_iter().map(|e| item(e, false))),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_risk_score() {
+        assert_eq!(risk_score([]), 0.0);
+        // A reachable high-severity sink outweighs several unreachable ones
+        let process = (EffectType::SinkCall, Some(Severity::High), true);
+        let dead_process = (EffectType::SinkCall, Some(Severity::High), false);
+        assert_eq!(risk_score([process]), 10.0);
+        assert_eq!(risk_score([dead_process, dead_process, dead_process]), 7.5);
+        assert_eq!(
+            risk_score([process, (EffectType::ClosureCreation, None, true)]),
+            10.5
+        );
+        assert!(
+            effect_weight(EffectType::SinkCall, Some(Severity::Low))
+                < effect_weight(EffectType::SinkCall, Some(Severity::High))
+        );
+    }
+}
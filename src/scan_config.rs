@@ -0,0 +1,274 @@
+//! Configuration for embedding cargo-scan as a library.
+//!
+//! `ScanConfig` gathers every option of a scan in one place, and is built
+//! with [`ScanConfig::builder`]:
+//!
+//! ```no_run
+//! use cargo_scan::scan_config::{ResolverKind, ScanConfig};
+//! use cargo_scan::scanner::ScanTargets;
+//!
+//! let results = ScanConfig::builder()
+//!     .resolver(ResolverKind::Quick)
+//!     .targets(ScanTargets { tests: true, ..Default::default() })
+//!     .sink("my_crate::net::connect")
+//!     .num_threads(4)
+//!     .build()
+//!     .scan("path/to/crate".as_ref())?;
+//! println!("Found {} effects", results.effects.len());
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+//!
+//! The binaries and the audit files (see `AuditFile`) scan crates through it
+//! as well, so a `ScanConfig` finds the same effects as the `scan` binary
+//! with the corresponding flags.
+
+use crate::effect::{EffectType, DEFAULT_EFFECT_TYPES};
+use crate::error::Result;
+use crate::expand;
+use crate::ident::CanonicalPath;
+use crate::scanner::{self, ScanResults, ScanTargets};
+
+use anyhow::Context;
+use std::collections::HashSet;
+use std::path::Path;
+
+/// How names in the scanned code are resolved to the items they refer to
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ResolverKind {
+    /// rust-analyzer, which resolves names precisely but has to load the
+    /// crate along with its dependencies first
+    #[default]
+    RustAnalyzer,
+    /// A syntactic resolver based on the `use` declarations of each file,
+    /// which is much faster but misses calls through traits, methods and
+    /// re-exports
+    Quick,
+}
+
+/// The options of a scan. See the module documentation for an example.
+#[derive(Debug, Clone)]
+pub struct ScanConfig {
+    effect_types: Vec<EffectType>,
+    sinks: HashSet<CanonicalPath>,
+    resolver: ResolverKind,
+    expand_macros: bool,
+    include_build_script: bool,
+    feature_sets: Vec<Vec<String>>,
+    target: Option<String>,
+    targets: ScanTargets,
+    num_threads: Option<usize>,
+}
+
+impl Default for ScanConfig {
+    fn default() -> Self {
+        Self {
+            effect_types: DEFAULT_EFFECT_TYPES.to_vec(),
+            sinks: HashSet::new(),
+            resolver: ResolverKind::default(),
+            expand_macros: false,
+            include_build_script: false,
+            feature_sets: Vec::new(),
+            target: None,
+            targets: ScanTargets::default(),
+            num_threads: None,
+        }
+    }
+}
+
+impl ScanConfig {
+    /// A builder starting from the default options, which scan the crate's
+    /// library and binaries for the default effect types with rust-analyzer
+    pub fn builder() -> ScanConfigBuilder {
+        ScanConfigBuilder { config: Self::default() }
+    }
+
+    pub fn effect_types(&self) -> &[EffectType] {
+        &self.effect_types
+    }
+
+    pub fn sinks(&self) -> &HashSet<CanonicalPath> {
+        &self.sinks
+    }
+
+    pub fn resolver(&self) -> ResolverKind {
+        self.resolver
+    }
+
+    pub fn expand_macros(&self) -> bool {
+        self.expand_macros
+    }
+
+    pub fn include_build_script(&self) -> bool {
+        self.include_build_script
+    }
+
+    pub fn feature_sets(&self) -> &[Vec<String>] {
+        &self.feature_sets
+    }
+
+    pub fn target(&self) -> Option<&str> {
+        self.target.as_deref()
+    }
+
+    pub fn targets(&self) -> ScanTargets {
+        self.targets
+    }
+
+    pub fn num_threads(&self) -> Option<usize> {
+        self.num_threads
+    }
+
+    /// Scans the crate at `crate_path`
+    pub fn scan(&self, crate_path: &Path) -> Result<ScanResults> {
+        match self.num_threads {
+            Some(n) => rayon::ThreadPoolBuilder::new()
+                .num_threads(n)
+                .build()
+                .context("Failed to start the scan's thread pool")?
+                .install(|| self.scan_in_current_pool(crate_path)),
+            None => self.scan_in_current_pool(crate_path),
+        }
+    }
+
+    fn scan_in_current_pool(&self, crate_path: &Path) -> Result<ScanResults> {
+        let sinks =
+            self.sinks.iter().map(|s| s.clone().to_path()).collect::<HashSet<_>>();
+        let quick_mode = self.resolver == ResolverKind::Quick;
+        let mut results = if self.expand_macros {
+            expand::scan_crate_expanded(
+                crate_path,
+                sinks.clone(),
+                &self.effect_types,
+                quick_mode,
+            )?
+        } else if !self.feature_sets.is_empty() {
+            scanner::scan_crate_feature_sets(
+                crate_path,
+                sinks.clone(),
+                &self.effect_types,
+                quick_mode,
+                &self.feature_sets,
+                self.target.as_deref(),
+                &self.targets,
+            )?
+        } else {
+            scanner::scan_crate_with_features(
+                crate_path,
+                sinks.clone(),
+                &self.effect_types,
+                quick_mode,
+                None,
+                self.target.as_deref(),
+                &self.targets,
+            )?
+        };
+        if self.include_build_script {
+            if let Some(build_res) =
+                scanner::scan_build_script(crate_path, sinks, &self.effect_types)?
+            {
+                results.merge(build_res);
+            }
+        }
+        Ok(results)
+    }
+}
+
+/// Builds a [`ScanConfig`]; see [`ScanConfig::builder`]
+#[derive(Debug, Clone)]
+pub struct ScanConfigBuilder {
+    config: ScanConfig,
+}
+
+impl ScanConfigBuilder {
+    /// Only look for effects of the given types
+    pub fn effect_types(mut self, effect_types: &[EffectType]) -> Self {
+        self.config.effect_types = effect_types.to_vec();
+        self
+    }
+
+    /// Also report calls to the given function (or to anything under the
+    /// given module) as sink calls, e.g. `my_crate::net`
+    pub fn sink(mut self, path: &str) -> Self {
+        self.config.sinks.insert(CanonicalPath::new(path));
+        self
+    }
+
+    pub fn sinks(mut self, paths: impl IntoIterator<Item = CanonicalPath>) -> Self {
+        self.config.sinks.extend(paths);
+        self
+    }
+
+    pub fn resolver(mut self, resolver: ResolverKind) -> Self {
+        self.config.resolver = resolver;
+        self
+    }
+
+    /// Also scan the macro-expanded source to find effects inside macro
+    /// invocations. Requires building the crate, and can't be combined with
+    /// feature sets or tests, examples and benchmarks.
+    pub fn expand_macros(mut self, expand_macros: bool) -> Self {
+        self.config.expand_macros = expand_macros;
+        self
+    }
+
+    /// Also scan the crate's build script, whose effects happen at compile
+    /// time
+    pub fn include_build_script(mut self, include_build_script: bool) -> Self {
+        self.config.include_build_script = include_build_script;
+        self
+    }
+
+    /// Scan the crate under this set of enabled features, instead of its
+    /// default ones. Given several times, the crate is scanned under each
+    /// set, and the effects only found under some of them are recorded in
+    /// `ScanResults::feature_gated_effects`.
+    pub fn feature_set(mut self, features: Vec<String>) -> Self {
+        self.config.feature_sets.push(features);
+        self
+    }
+
+    /// Evaluate platform cfgs for the given target triple instead of the host
+    pub fn target(mut self, target: &str) -> Self {
+        self.config.target = Some(target.to_string());
+        self
+    }
+
+    /// Which of the crate's tests, examples and benchmarks to scan
+    pub fn targets(mut self, targets: ScanTargets) -> Self {
+        self.config.targets = targets;
+        self
+    }
+
+    /// Scan files on at most this many threads, instead of one per CPU
+    pub fn num_threads(mut self, num_threads: usize) -> Self {
+        self.config.num_threads = Some(num_threads);
+        self
+    }
+
+    pub fn build(self) -> ScanConfig {
+        self.config
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::effect::EffectType;
+
+    #[test]
+    fn test_scan_config() {
+        let config = ScanConfig::builder()
+            .resolver(ResolverKind::Quick)
+            .effect_types(&[EffectType::SinkCall])
+            .sink("Vec::new")
+            .num_threads(2)
+            .build();
+        assert_eq!(config.num_threads(), Some(2));
+        assert_eq!(config.targets(), ScanTargets::default());
+
+        let results = config.scan(Path::new("data/test-packages/dependency-ex")).unwrap();
+        let callees = results.effects.iter().map(|e| e.callee_path()).collect::<Vec<_>>();
+        // The quick resolver misses the `read_to_end` method call
+        assert_eq!(callees, ["std::fs::File::open", "Vec::new"]);
+    }
+}
@@ -6,14 +6,20 @@
 
 use crate::ident::CanonicalPath;
 
-use super::audit_file::{AuditFile, EffectTree};
-use super::effect::{EffectInstance, EffectType, DEFAULT_EFFECT_TYPES};
+use super::audit_file::{AuditFile, DefaultAuditConfig, EffectTree};
+use super::effect::{Effect, EffectInstance, EffectType, SrcLoc, DEFAULT_EFFECT_TYPES};
 use super::loc_tracker::LoCTracker;
-use super::scanner::ScanResults;
+use super::native_libs::NativeLib;
+use super::report::caller_module;
+use super::risk::effects_risk_score;
+use super::scanner::{ScanResults, UnsafeCodeLint};
+use super::suppress::{self, Suppression};
 
 use anyhow::Result;
 use log::{debug, warn};
-use std::collections::HashSet;
+use petgraph::graph::DiGraph;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt::Write;
 use std::path::PathBuf;
 
 #[derive(Debug, Default)]
@@ -22,6 +28,15 @@ pub struct CrateStats {
 
     // List of effects
     pub effects: Vec<EffectInstance>,
+    // Effects which can't be reached from the crate's public API (see
+    // `ScanResults::reachable_fns`)
+    pub unreachable_effects: HashSet<EffectInstance>,
+    // Effects left out of `effects` by a suppression comment (see
+    // `suppress`)
+    pub suppressed_effects: Vec<(EffectInstance, Suppression)>,
+    // When scanning under several sets of features, the effects only found
+    // under some of them, along with those sets
+    pub feature_gated_effects: HashMap<EffectInstance, Vec<String>>,
 
     // Scan metadata
     pub total_loc: LoCTracker,
@@ -32,8 +47,30 @@ pub struct CrateStats {
     pub skipped_other: LoCTracker,
     pub unsafe_traits: LoCTracker,
     pub unsafe_impls: LoCTracker,
+    pub unsafe_blocks: LoCTracker,
+    // The location of each function declaration and unsafe block, to group
+    // the effects by the blocks containing them
+    pub fn_locs: HashMap<CanonicalPath, SrcLoc>,
+    pub unsafe_block_locs: Vec<SrcLoc>,
     pub pub_fns: usize,
 
+    // Reachability from the `main` functions of binary targets; both counts
+    // are zero for library crates
+    pub main_fns: usize,
+    pub main_reachable_effects: usize,
+    pub main_unreachable_effects: usize,
+
+    // The crate-level `unsafe_code` lint, and the number of places which
+    // permit unsafe code again even though the crate denies or forbids it
+    pub unsafe_code_lint: Option<UnsafeCodeLint>,
+    pub unsafe_code_overrides: usize,
+
+    // Native libraries linked by the crate
+    pub native_libs: Vec<NativeLib>,
+
+    // Calls between functions, labeled by the location of the call
+    pub call_graph: DiGraph<CanonicalPath, SrcLoc>,
+
     // AuditFile metadata
     pub pub_fns_with_effects: usize,
     pub pub_total_effects: usize,
@@ -54,12 +91,15 @@ impl CrateStats {
         unsafe_trait, loc_lb, loc_ub, \
         unsafe_impl, loc_lb, loc_ub, \
         pub_fns, pub_fns_with_effects, pub_total_effects, \
-        audited_fns, audited_loc\
+        audited_fns, audited_loc, \
+        main_fns, main_reachable_effects, main_unreachable_effects, \
+        unsafe_code_lint, unsafe_code_overrides, native_libs, \
+        suppressed_effects\
         "
     }
     pub fn metadata_csv(&self) -> String {
         format!(
-            "{}, {}, {}, {}, {}, {}, {}, {}, {}, {}, {}, {}, {}, {}",
+            "{}, {}, {}, {}, {}, {}, {}, {}, {}, {}, {}, {}, {}, {}, {}, {}, {}, {}, {}, {}, {}",
             self.effects.len(),
             self.total_loc.as_csv(),
             self.skipped_macros.as_csv(),
@@ -74,10 +114,102 @@ impl CrateStats {
             self.pub_total_effects,
             self.audited_fns,
             self.audited_loc,
+            self.main_fns,
+            self.main_reachable_effects,
+            self.main_unreachable_effects,
+            self.unsafe_code_lint.map_or("none".to_string(), |l| l.to_string()),
+            self.unsafe_code_overrides,
+            self.native_libs_csv(),
+            self.suppressed_effects.len(),
         )
     }
 }
 
+impl CrateStats {
+    /// The names of the native libraries, separated by semicolons
+    fn native_libs_csv(&self) -> String {
+        let mut names =
+            self.native_libs.iter().map(|l| l.name.as_str()).collect::<Vec<_>>();
+        names.dedup();
+        names.join(";")
+    }
+
+    /// A human-readable summary of the effects, counted per effect type, per
+    /// module and per file, and of the unsafe and skipped code, along with
+    /// the crate's risk score (see [`crate::risk`])
+    pub fn summary(&self) -> String {
+        let mut out = String::new();
+        let _ = writeln!(out, "Effects: {}", self.effects.len());
+        let (unreachable, reachable): (Vec<_>, Vec<_>) =
+            self.effects.iter().partition(|e| self.unreachable_effects.contains(e));
+        let _ = writeln!(
+            out,
+            "Risk score: {:.1}",
+            effects_risk_score(reachable, unreachable)
+        );
+        let sections = [
+            (
+                "type",
+                count_effects(&self.effects, |e| {
+                    EffectType::from(e.eff_type()).to_string()
+                }),
+            ),
+            (
+                "module",
+                count_effects(&self.effects, |e| {
+                    caller_module(e.caller_path()).to_string()
+                }),
+            ),
+            (
+                "file",
+                count_effects(&self.effects, |e| {
+                    e.call_loc().dir().join(e.call_loc().file()).display().to_string()
+                }),
+            ),
+        ];
+        for (title, counts) in sections {
+            let _ = writeln!(out, "\nEffects per {}:", title);
+            for (name, count) in counts {
+                let _ = writeln!(out, "  {}: {}", name, count);
+            }
+        }
+
+        let _ = writeln!(out, "\nCode ({} lines scanned):", self.total_loc.get_loc_ub());
+        for (name, tracker) in [
+            ("unsafe blocks", &self.unsafe_blocks),
+            ("unsafe traits", &self.unsafe_traits),
+            ("unsafe impls", &self.unsafe_impls),
+            ("skipped macros", &self.skipped_macros),
+            ("skipped calls", &self.skipped_fn_calls),
+            ("skipped function pointers", &self.skipped_fn_ptrs),
+            ("skipped conditional code", &self.skipped_conditional_code),
+            ("skipped other", &self.skipped_other),
+        ] {
+            let _ = writeln!(
+                out,
+                "  {}: {} ({}-{} lines)",
+                name,
+                tracker.get_instances(),
+                tracker.get_loc_lb(),
+                tracker.get_loc_ub()
+            );
+        }
+        out
+    }
+}
+
+/// Counts the effects with each key, e.g. in each module
+fn count_effects<F>(effects: &[EffectInstance], key: F) -> BTreeMap<String, usize>
+where
+    F: Fn(&EffectInstance) -> String,
+{
+    let mut counts = BTreeMap::new();
+    for e in effects {
+        *counts.entry(key(e)).or_insert(0) += 1;
+    }
+    counts
+}
+
 pub fn get_crate_stats_default(crate_path: PathBuf, quick_mode: bool) -> CrateStats {
     get_crate_stats(crate_path.clone(), DEFAULT_EFFECT_TYPES, quick_mode).unwrap_or_else(
         |_| {
@@ -92,10 +224,29 @@ pub fn get_crate_stats(
     effect_types: &[EffectType],
     quick_mode: bool,
 ) -> Result<CrateStats> {
-    let (audit, results) = AuditFile::new_caller_checked_default_with_results(
+    get_crate_stats_with_config(
+        crate_path,
+        HashSet::new(),
+        effect_types,
+        quick_mode,
+        &DefaultAuditConfig::default(),
+    )
+}
+
+/// Get the stats of a crate, scanning it with additional sinks
+pub fn get_crate_stats_with_config(
+    crate_path: PathBuf,
+    sinks: HashSet<CanonicalPath>,
+    effect_types: &[EffectType],
+    quick_mode: bool,
+    config: &DefaultAuditConfig,
+) -> Result<CrateStats> {
+    let (audit, results) = AuditFile::new_caller_checked_default_with_sinks_and_results(
         &crate_path,
+        sinks,
         effect_types,
         quick_mode,
+        config,
     )?;
 
     let pub_fns = results.pub_fns.len();
@@ -111,9 +262,26 @@ pub fn get_crate_stats(
 
     let (audited_fns, audited_loc) = get_auditing_metrics(&audit, &results);
 
+    let main_fns = results.main_fns.len();
+    let (main_reachable_effects, main_unreachable_effects) = if main_fns > 0 {
+        let (reachable, unreachable) = results.partition_main_reachable();
+        (reachable.len(), unreachable.len())
+    } else {
+        (0, 0)
+    };
+
+    let unsafe_code_overrides = results.unsafe_code_overrides().len();
+    let unreachable_effects =
+        results.dead_code_effects().into_iter().cloned().collect::<HashSet<_>>();
+
+    let (effects, suppressed_effects) = suppress::partition_suppressed(results.effects);
+
     let result = CrateStats {
         crate_path,
-        effects: results.effects,
+        effects,
+        unreachable_effects,
+        suppressed_effects,
+        feature_gated_effects: results.feature_gated_effects,
         total_loc: results.total_loc,
         skipped_macros: results.skipped_macros,
         skipped_conditional_code: results.skipped_conditional_code,
@@ -122,16 +290,76 @@ pub fn get_crate_stats(
         skipped_other: results.skipped_other,
         unsafe_traits: results.unsafe_traits,
         unsafe_impls: results.unsafe_impls,
+        unsafe_blocks: results.unsafe_blocks,
+        fn_locs: results.fn_locs,
+        unsafe_block_locs: results.unsafe_block_locs,
         pub_fns,
         pub_fns_with_effects,
         pub_total_effects,
         audited_fns,
         audited_loc,
+        main_fns,
+        main_reachable_effects,
+        main_unreachable_effects,
+        unsafe_code_lint: results.crate_unsafe_lint,
+        unsafe_code_overrides,
+        native_libs: results.native_libs,
+        call_graph: results.call_graph,
     };
 
     Ok(result)
 }
 
+/// Counts the effects of each kind: the sink pattern for sink calls (e.g.
+/// `std::process`), and the effect type for everything else
+pub fn effect_kind_counts(effects: &[EffectInstance]) -> BTreeMap<String, usize> {
+    let mut counts = BTreeMap::new();
+    for e in effects {
+        let kind = match e.eff_type() {
+            Effect::SinkCall(sink) => sink.to_string(),
+            eff => EffectType::from(eff).to_string(),
+        };
+        *counts.entry(kind).or_insert(0) += 1;
+    }
+    counts
+}
+
+/// A sudden change in the number of effects of some kind between two
+/// consecutive versions of a crate
+#[derive(Debug, PartialEq, Eq)]
+pub struct EffectJump {
+    pub version: String,
+    pub kind: String,
+    pub before: usize,
+    pub after: usize,
+}
+
+/// Finds the effect kinds which newly appear in a version, or whose count
+/// grows by at least `factor` over the previous version. `timeline` holds the
+/// effect kind counts of each version, in order.
+pub fn effect_jumps(
+    timeline: &[(String, BTreeMap<String, usize>)],
+    factor: f64,
+) -> Vec<EffectJump> {
+    let mut jumps = Vec::new();
+    for pair in timeline.windows(2) {
+        let (_, prev) = &pair[0];
+        let (version, curr) = &pair[1];
+        for (kind, after) in curr {
+            let before = prev.get(kind).copied().unwrap_or(0);
+            if *after > before && *after as f64 >= before as f64 * factor {
+                jumps.push(EffectJump {
+                    version: version.clone(),
+                    kind: kind.clone(),
+                    before,
+                    after: *after,
+                });
+            }
+        }
+    }
+    jumps
+}
+
 // Calculates the total number of functions and the total lines of code that will be audited.
 fn get_auditing_metrics(audit: &AuditFile, results: &ScanResults) -> (usize, usize) {
     let mut total_loc = 0;
@@ -174,3 +402,57 @@ fn counter(tree: &EffectTree) -> HashSet<&CanonicalPath> {
 
     set
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_effect_jumps() {
+        let counts = |c: &[(&str, usize)]| {
+            c.iter().map(|(k, n)| (k.to_string(), *n)).collect::<BTreeMap<_, _>>()
+        };
+        let timeline = vec![
+            ("1.0.0".to_string(), counts(&[("std::fs", 4), ("UnsafeCall", 10)])),
+            ("1.0.1".to_string(), counts(&[("std::fs", 5), ("UnsafeCall", 10)])),
+            ("1.1.0".to_string(), counts(&[("std::fs", 5), ("std::process", 2)])),
+            ("1.2.0".to_string(), counts(&[("std::fs", 12), ("std::process", 2)])),
+        ];
+
+        let jumps = effect_jumps(&timeline, 2.0);
+        let jump = |version: &str, kind: &str, before, after| EffectJump {
+            version: version.to_string(),
+            kind: kind.to_string(),
+            before,
+            after,
+        };
+        assert_eq!(
+            jumps,
+            vec![jump("1.1.0", "std::process", 0, 2), jump("1.2.0", "std::fs", 5, 12)]
+        );
+    }
+
+    #[test]
+    fn test_summary() {
+        let crate_path = PathBuf::from("data/test-packages/dependency-ex");
+        let stats = get_crate_stats(crate_path, DEFAULT_EFFECT_TYPES, true).unwrap();
+        let summary = stats.summary();
+        for line in [
+            "Effects: 2",
+            "Risk score: 9.0",
+            "  RawPointerWrite: 1",
+            "  SinkCall: 1",
+            "  dependency_ex: 2",
+            "  data/test-packages/dependency-ex/src/lib.rs: 2",
+            "  unsafe blocks: 1 (2-2 lines)",
+            "  skipped macros: 0 (0-0 lines)",
+        ] {
+            assert!(
+                summary.lines().any(|l| l == line),
+                "missing {:?} in\n{}",
+                line,
+                summary
+            );
+        }
+    }
+}
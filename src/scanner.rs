@@ -3,34 +3,172 @@
 //! Parse a Rust crate or source file and collect effect blocks, function calls, and
 //! various other information.
 
-use crate::attr_parser::CfgPred;
-use crate::audit_file::EffectInfo;
+#[cfg(feature = "native")]
+use crate::attr_parser::is_platform_key;
+use crate::attr_parser::{CfgOpt, CfgPred};
+use crate::detector::{self, DetectorContext, EffectDetector};
 use crate::resolution::hacky_resolver::HackyResolver;
-use crate::resolution::name_resolution::Resolver;
+#[cfg(feature = "native")]
+use crate::resolution::name_resolution::{Resolver, ResolverSnapshot};
 
-use super::effect::{Effect, EffectInstance, EffectType, FnDec, SrcLoc, Visibility};
+use super::effect::{
+    Effect, EffectInfo, EffectInstance, EffectSignature, EffectType, FnDec, Provenance,
+    SrcLoc, TargetKind, Visibility,
+};
 use super::ident::{CanonicalPath, IdentPath};
 use super::loc_tracker::LoCTracker;
+use super::mem_fs::MemFs;
+use super::native_libs::{self, LinkSource, NativeLib};
+#[cfg(feature = "native")]
+use super::progress::Progress;
 use super::sink::Sink;
 use super::util;
-use crate::resolution::resolve::{FileResolver, Resolve};
+#[cfg(feature = "native")]
+use crate::resolution::resolve::FileResolver;
+use crate::resolution::resolve::{ident_from_syn, Resolve};
 
-use anyhow::{anyhow, Context, Result};
+use crate::error::{Result, ScanError};
+use anyhow::Context;
 use log::{debug, info, warn};
+use parse_display::Display;
+use petgraph::algo::tarjan_scc;
+use petgraph::dot::Dot;
 use petgraph::graph::{DiGraph, NodeIndex};
 use petgraph::visit::EdgeRef;
 use petgraph::Direction;
 use proc_macro2::{TokenStream, TokenTree};
 use quote::ToTokens;
+#[cfg(feature = "native")]
+use rayon::prelude::*;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use std::collections::HashMap;
 use std::collections::HashSet;
+use std::collections::VecDeque;
 use std::fmt::Debug;
 use std::fs::File;
+use std::hash::Hash;
 use std::io::Read;
 use std::path::Path as FilePath;
+use std::path::PathBuf;
+#[cfg(feature = "native")]
+use std::sync::mpsc;
+use std::sync::Arc;
 use syn::spanned::Spanned;
 use syn::ForeignItemFn;
 
+/// The module of the crate that the functions of its build script are placed
+/// under, named after Cargo's target for it
+pub const BUILD_SCRIPT_MOD: &str = "build_script_build";
+
+/// Which of the crate's tests, examples and benchmarks to scan, besides its
+/// library and binaries. None of them are scanned by default.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ScanTargets {
+    /// Integration tests under `tests/`, along with `#[cfg(test)]` code and
+    /// `#[test]` functions
+    pub tests: bool,
+    /// Examples under `examples/`
+    pub examples: bool,
+    /// Benchmarks under `benches/`
+    pub benches: bool,
+}
+
+impl ScanTargets {
+    /// The directories of the selected targets, along with their kind
+    #[cfg(feature = "native")]
+    fn dirs(&self) -> Vec<(&'static str, TargetKind)> {
+        [
+            ("tests", TargetKind::Test, self.tests),
+            ("examples", TargetKind::Example, self.examples),
+            ("benches", TargetKind::Bench, self.benches),
+        ]
+        .into_iter()
+        .filter(|(_, _, selected)| *selected)
+        .map(|(dir, kind, _)| (dir, kind))
+        .collect()
+    }
+}
+
+/// The directories of test, example and benchmark targets, which aren't
+/// part of the crate's library or binaries
+const TARGET_DIRS: [&str; 3] = ["tests", "examples", "benches"];
+
+/// A call to a function, along with the calls to the calling function in
+/// turn, up to the nearest entry points of the crate (see
+/// `ScanResults::caller_tree`)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CallerTree {
+    /// The calling function, along with the location of the call
+    pub call: EffectInfo,
+    /// Whether the calling function is an entry point of the crate, where the
+    /// tree stops
+    pub is_entry: bool,
+    /// Whether the callers of the calling function are left out since they
+    /// are already shown elsewhere in the tree
+    pub repeated: bool,
+    pub callers: Vec<CallerTree>,
+}
+
+/// The level of the `unsafe_code` lint set by an attribute, e.g.
+/// `#![forbid(unsafe_code)]`
+///
+/// The levels are ordered from the weakest to the strongest.
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    Display,
+    Serialize,
+    Deserialize,
+    JsonSchema,
+)]
+#[display(style = "snake_case")]
+pub enum UnsafeCodeLint {
+    Allow,
+    Expect,
+    Warn,
+    Deny,
+    Forbid,
+}
+
+impl UnsafeCodeLint {
+    /// Returns the level the attribute sets for the `unsafe_code` lint, if any
+    fn from_attr(attr: &syn::Attribute) -> Option<Self> {
+        let path = attr.path();
+        let lint = if path.is_ident("allow") {
+            UnsafeCodeLint::Allow
+        } else if path.is_ident("expect") {
+            UnsafeCodeLint::Expect
+        } else if path.is_ident("warn") {
+            UnsafeCodeLint::Warn
+        } else if path.is_ident("deny") {
+            UnsafeCodeLint::Deny
+        } else if path.is_ident("forbid") {
+            UnsafeCodeLint::Forbid
+        } else {
+            return None;
+        };
+        let syn::Meta::List(l) = &attr.meta else { return None };
+        let sets_unsafe_code = l.tokens.clone().into_iter().any(|t| match t {
+            TokenTree::Ident(i) => i == "unsafe_code",
+            _ => false,
+        });
+        sets_unsafe_code.then_some(lint)
+    }
+
+    /// Returns true if the level lets unsafe code compile without error
+    pub fn permits_unsafe(&self) -> bool {
+        matches!(self, Self::Allow | Self::Expect | Self::Warn)
+    }
+}
+
 /// Results of a scan
 ///
 /// Holds the intermediate state between scans which doesn't hold references
@@ -42,7 +180,53 @@ pub struct ScanResults {
     // Saved function declarations
     pub pub_fns: HashSet<CanonicalPath>,
     pub fn_locs: HashMap<CanonicalPath, SrcLoc>,
+    // Hash of the tokens of each function, used to tell whether it changed
+    // between versions of the crate
+    pub fn_hashes: HashMap<CanonicalPath, String>,
     pub trait_meths: HashSet<CanonicalPath>,
+    // Methods defined in trait impl blocks; these may be called through the
+    // trait from outside the crate even though they have no `pub` keyword
+    pub trait_impl_fns: HashSet<CanonicalPath>,
+    // The `main` functions of the crate's binary targets, if it has any
+    pub main_fns: HashSet<CanonicalPath>,
+    // Functions which foreign code can call, e.g. `#[no_mangle]` functions;
+    // these are entry points even if they aren't `pub`
+    pub exported_fns: HashSet<CanonicalPath>,
+    // The `#[test]` functions and the `main` functions of examples and
+    // benchmarks, when they're scanned; these are run by Cargo directly
+    pub target_fns: HashSet<CanonicalPath>,
+    // Struct fields and statics which hold a sink or FFI function pointer (or
+    // a table of them), by their path, along with the function they hold.
+    // Fields are keyed by the path of their struct and the field name.
+    pub fn_ptr_origins: HashMap<IdentPath, CanonicalPath>,
+    // Indirect calls through fields and statics which aren't known to hold a
+    // sink or FFI function yet; they may be bound in a file merged in later
+    pub fn_ptr_calls: Vec<EffectInstance>,
+    // Names which may refer to functions called without a call in the call
+    // graph: paths used as values (e.g. callbacks passed to
+    // `thread::spawn`) and the identifiers in static and const initializers
+    // and skipped macro invocations
+    pub fn_refs: HashSet<String>,
+
+    // The level of the `unsafe_code` lint set for the whole crate in the roots
+    // of its targets, e.g. by `#![forbid(unsafe_code)]` (see `set_crate_roots`)
+    pub crate_unsafe_lint: Option<UnsafeCodeLint>,
+    // The `unsafe_code` lints set at the top of each file by inner attributes
+    pub file_unsafe_lints: Vec<(SrcLoc, UnsafeCodeLint)>,
+    // Modules and items which locally permit unsafe code again, e.g. with
+    // `#[allow(unsafe_code)]`
+    pub unsafe_lint_overrides: Vec<(SrcLoc, UnsafeCodeLint)>,
+
+    // Native libraries linked by the crate
+    pub native_libs: Vec<NativeLib>,
+    // The library each FFI function's extern block links, by function path
+    pub ffi_libs: HashMap<String, String>,
+    // Locations of the skipped macro invocations in each function, used to
+    // map effects found in the expanded source back to the original code
+    pub macro_calls: HashMap<CanonicalPath, Vec<SrcLoc>>,
+    // When scanning under several sets of features, the effects which are
+    // only found under some of them, along with those sets
+    pub feature_gated_effects: HashMap<EffectInstance, Vec<String>>,
 
     pub call_graph: DiGraph<CanonicalPath, SrcLoc>,
     pub node_idxs: HashMap<CanonicalPath, NodeIndex>,
@@ -56,6 +240,9 @@ pub struct ScanResults {
     pub skipped_other: LoCTracker,
     pub unsafe_traits: LoCTracker,
     pub unsafe_impls: LoCTracker,
+    pub unsafe_blocks: LoCTracker,
+    // The location of each unsafe block
+    pub unsafe_block_locs: Vec<SrcLoc>,
     pub fn_loc_tracker: HashMap<CanonicalPath, LoCTracker>,
 
     // TODO other cases:
@@ -90,6 +277,12 @@ impl ScanResults {
         Ok(effects)
     }
 
+    /// Returns the call graph of the crate in the DOT format, with edges
+    /// labeled by the location of the call
+    pub fn call_graph_dot(&self) -> String {
+        Dot::new(&self.call_graph).to_string()
+    }
+
     pub fn add_fn_dec(&mut self, f: FnDec) {
         let fn_name = f.fn_name;
 
@@ -103,7 +296,413 @@ impl ScanResults {
         self.fn_locs.insert(fn_name, f.src_loc);
     }
 
-    fn update_call_graph(&mut self, method: &CanonicalPath) -> NodeIndex {
+    /// Returns the functions through which code outside the crate can enter
+    /// it: public functions (including `main`), trait impl methods,
+    /// functions exported to foreign code, and the entry points of any
+    /// scanned tests, examples and benchmarks.
+    pub fn entry_fns(&self) -> HashSet<&CanonicalPath> {
+        self.pub_fns
+            .iter()
+            .chain(self.trait_impl_fns.iter())
+            .chain(self.exported_fns.iter())
+            .chain(self.target_fns.iter())
+            .collect()
+    }
+
+    /// Returns all functions reachable in the call graph from the crate's
+    /// entry points (see `entry_fns`). The call graph doesn't include calls
+    /// inside skipped macros or through function pointers, so any function
+    /// whose name is used other than in a call (see `fn_refs`) is treated as
+    /// an entry point too.
+    pub fn reachable_fns(&self) -> HashSet<CanonicalPath> {
+        let referenced = self.fn_locs.keys().filter(|f| {
+            f.as_str().rsplit("::").next().is_some_and(|n| self.fn_refs.contains(n))
+        });
+        self.reachable_from(self.entry_fns().into_iter().chain(referenced))
+    }
+
+    /// Returns all functions reachable in the call graph from the `main`
+    /// functions of the crate's binary targets. This is empty for library
+    /// crates.
+    pub fn main_reachable_fns(&self) -> HashSet<CanonicalPath> {
+        self.reachable_from(&self.main_fns)
+    }
+
+    /// Returns all functions reachable in the call graph from the given roots,
+    /// including the roots themselves
+    pub fn reachable_from<'b>(
+        &self,
+        roots: impl IntoIterator<Item = &'b CanonicalPath>,
+    ) -> HashSet<CanonicalPath> {
+        let mut visited = HashSet::new();
+        let mut worklist = roots
+            .into_iter()
+            .filter_map(|f| self.node_idxs.get(f).copied())
+            .collect::<VecDeque<_>>();
+        while let Some(node) = worklist.pop_front() {
+            if !visited.insert(node) {
+                continue;
+            }
+            worklist.extend(
+                self.call_graph
+                    .neighbors_directed(node, Direction::Outgoing)
+                    .filter(|n| !visited.contains(n)),
+            );
+        }
+
+        visited.into_iter().map(|n| self.call_graph[n].clone()).collect()
+    }
+
+    /// Returns one shortest example call path from an entry point of the crate
+    /// (see `entry_fns`) to the effect, or None if the effect can't be reached
+    /// from any of them. Each step is a function along with the location where
+    /// it calls the next one; the last step is the effect itself.
+    pub fn example_call_path(&self, e: &EffectInstance) -> Option<Vec<EffectInfo>> {
+        let entry_fns = self.entry_fns();
+        let start = *self.node_idxs.get(e.caller())?;
+
+        // Search backwards from the effect, remembering which call edge we
+        // took to reach each function
+        let mut next_call: HashMap<NodeIndex, (NodeIndex, SrcLoc)> = HashMap::new();
+        let mut visited = HashSet::from([start]);
+        let mut worklist = VecDeque::from([start]);
+        while let Some(node) = worklist.pop_front() {
+            if entry_fns.contains(&self.call_graph[node]) {
+                let mut path = Vec::new();
+                let mut curr = node;
+                while let Some((callee, loc)) = next_call.get(&curr) {
+                    path.push(EffectInfo::new(
+                        self.call_graph[curr].clone(),
+                        loc.clone(),
+                    ));
+                    curr = *callee;
+                }
+                path.push(EffectInfo::from_instance(e));
+                return Some(path);
+            }
+            for edge in self.call_graph.edges_directed(node, Direction::Incoming) {
+                let caller = edge.source();
+                if visited.insert(caller) {
+                    next_call.insert(caller, (node, edge.weight().clone()));
+                    worklist.push_back(caller);
+                }
+            }
+        }
+
+        None
+    }
+
+    /// Returns the calls to the function, each along with the calls to its
+    /// caller in turn, up to the nearest entry points of the crate (see
+    /// `entry_fns`). The callers of each function are only listed the first
+    /// time it appears, so the tree stays small for widely called functions.
+    pub fn caller_tree(&self, f: &CanonicalPath) -> Vec<CallerTree> {
+        let entry_fns = self.entry_fns();
+        let Some(node) = self.node_idxs.get(f) else {
+            return Vec::new();
+        };
+        self.caller_subtrees(*node, &entry_fns, &mut HashSet::from([*node]))
+    }
+
+    fn caller_subtrees(
+        &self,
+        node: NodeIndex,
+        entry_fns: &HashSet<&CanonicalPath>,
+        seen: &mut HashSet<NodeIndex>,
+    ) -> Vec<CallerTree> {
+        let mut edges =
+            self.call_graph.edges_directed(node, Direction::Incoming).collect::<Vec<_>>();
+        edges.sort_by_key(|e| {
+            (self.call_graph[e.source()].to_string(), e.weight().to_string())
+        });
+        edges
+            .into_iter()
+            .map(|edge| {
+                let caller = edge.source();
+                let is_entry = entry_fns.contains(&self.call_graph[caller]);
+                let repeated = !seen.insert(caller);
+                let callers = if is_entry || repeated {
+                    Vec::new()
+                } else {
+                    self.caller_subtrees(caller, entry_fns, seen)
+                };
+                CallerTree {
+                    call: EffectInfo::new(
+                        self.call_graph[caller].clone(),
+                        edge.weight().clone(),
+                    ),
+                    is_entry,
+                    repeated,
+                    callers,
+                }
+            })
+            .collect()
+    }
+
+    /// Returns, for each function in the call graph, the effects it may
+    /// transitively perform: those in the function itself or in anything it
+    /// calls. Functions with no effects are omitted.
+    pub fn transitive_effects<'b>(
+        &self,
+        effects: impl IntoIterator<Item = &'b EffectInstance>,
+    ) -> HashMap<CanonicalPath, HashSet<&'b EffectInstance>> {
+        let mut local: HashMap<NodeIndex, HashSet<&EffectInstance>> = HashMap::new();
+        for e in effects {
+            if let Some(idx) = self.node_idxs.get(e.caller()) {
+                local.entry(*idx).or_default().insert(e);
+            }
+        }
+        self.propagate(local, Direction::Incoming)
+    }
+
+    /// Returns, for each function in the call graph, the functions matching
+    /// `is_target` which it may transitively call. Functions which can't reach
+    /// any target are omitted.
+    pub fn transitive_callees<F>(
+        &self,
+        is_target: F,
+    ) -> HashMap<CanonicalPath, HashSet<CanonicalPath>>
+    where
+        F: Fn(&CanonicalPath) -> bool,
+    {
+        let mut local: HashMap<NodeIndex, HashSet<CanonicalPath>> = HashMap::new();
+        for (f, idx) in self.node_idxs.iter().filter(|(f, _)| is_target(f)) {
+            for caller in self.call_graph.neighbors_directed(*idx, Direction::Incoming) {
+                local.entry(caller).or_default().insert(f.clone());
+            }
+        }
+        self.propagate(local, Direction::Incoming)
+    }
+
+    /// Returns, for each function in the call graph, the number of other
+    /// functions which may transitively call it. Functions with a high count
+    /// are central to the crate: most of its code depends on them.
+    pub fn caller_centrality(&self) -> HashMap<CanonicalPath, usize> {
+        let local = self
+            .node_idxs
+            .values()
+            .map(|idx| (*idx, HashSet::from([*idx])))
+            .collect::<HashMap<_, _>>();
+        // NOTE: Each function's set includes itself, so don't count it
+        self.propagate(local, Direction::Outgoing)
+            .into_iter()
+            .map(|(f, callers)| (f, callers.len() - 1))
+            .collect()
+    }
+
+    /// Propagates sets of items attached to functions along the call graph:
+    /// up to all of their transitive callers if `to` is `Incoming`, or down
+    /// to all of their transitive callees if it is `Outgoing`.
+    ///
+    /// The sets are propagated over the strongly connected components of the
+    /// call graph, so all functions in a cycle of (mutually) recursive calls
+    /// end up with the same set.
+    fn propagate<T: Clone + Eq + Hash>(
+        &self,
+        local: HashMap<NodeIndex, HashSet<T>>,
+        to: Direction,
+    ) -> HashMap<CanonicalPath, HashSet<T>> {
+        // NOTE: tarjan_scc returns the components in reverse topological order,
+        //       i.e. callees before callers
+        let mut sccs = tarjan_scc(&self.call_graph);
+        if to == Direction::Outgoing {
+            sccs.reverse();
+        }
+        let mut scc_of = HashMap::new();
+        let mut scc_sets: Vec<HashSet<T>> = Vec::new();
+        for (i, scc) in sccs.iter().enumerate() {
+            scc_of.extend(scc.iter().map(|n| (*n, i)));
+            let mut scc_set = HashSet::new();
+            for n in scc {
+                scc_set.extend(local.get(n).into_iter().flatten().cloned());
+                for m in self.call_graph.neighbors_directed(*n, to.opposite()) {
+                    match scc_of.get(&m) {
+                        Some(&j) if j != i => scc_set.extend(scc_sets[j].iter().cloned()),
+                        _ => (),
+                    }
+                }
+            }
+            scc_sets.push(scc_set);
+        }
+
+        self.node_idxs
+            .iter()
+            .filter_map(|(f, idx)| {
+                let set = &scc_sets[scc_of[idx]];
+                if set.is_empty() {
+                    None
+                } else {
+                    Some((f.clone(), set.clone()))
+                }
+            })
+            .collect()
+    }
+
+    /// Record the `main` functions defined at the top of the given binary
+    /// target source files (see `util::bin_target_files`)
+    pub fn set_main_fns(&mut self, bin_files: &[PathBuf]) {
+        self.main_fns = self
+            .fn_locs
+            .iter()
+            .filter(|(f, loc)| {
+                f.as_str().rsplit("::").next() == Some("main")
+                    && bin_files.iter().any(|b| *b == loc.dir().join(loc.file()))
+            })
+            .map(|(f, _)| f.clone())
+            .collect();
+    }
+
+    /// Record the `unsafe_code` lint set for the whole crate, given the root
+    /// source files of its library and binary targets (see
+    /// `util::lib_target_file` and `util::bin_target_files`). The crate only
+    /// denies unsafe code as far as every one of its roots does, and the
+    /// lints set at the top of a root aren't local overrides.
+    pub fn set_crate_roots(&mut self, root_files: &[PathBuf]) {
+        let in_file = |loc: &SrcLoc, f: &PathBuf| *f == loc.dir().join(loc.file());
+        let root_lints = self
+            .file_unsafe_lints
+            .iter()
+            .filter(|(loc, _)| root_files.iter().any(|f| in_file(loc, f)))
+            .cloned()
+            .collect::<Vec<_>>();
+        // The last attribute in a file takes effect, and a root without one
+        // permits unsafe code
+        self.crate_unsafe_lint = root_files
+            .iter()
+            .map(|f| {
+                root_lints.iter().rev().find(|(loc, _)| in_file(loc, f)).map(|(_, l)| *l)
+            })
+            .min()
+            .flatten();
+        self.unsafe_lint_overrides.retain(|o| !root_lints.contains(o));
+    }
+
+    /// Splits the effects into those reachable from the `main` function of one
+    /// of the crate's binary targets and those which aren't. Effects whose
+    /// caller is missing from the call graph are conservatively treated as
+    /// reachable.
+    pub fn partition_main_reachable(
+        &self,
+    ) -> (Vec<&EffectInstance>, Vec<&EffectInstance>) {
+        let reachable = self.main_reachable_fns();
+        self.effects.iter().partition(|e| {
+            !self.node_idxs.contains_key(e.caller()) || reachable.contains(e.caller())
+        })
+    }
+
+    /// Returns true if the effect occurs in a function that can't be reached
+    /// from any of the crate's entry points, given the set of `reachable`
+    /// functions computed by `reachable_fns`. Effects whose caller is missing
+    /// from the call graph are conservatively treated as reachable, and so are
+    /// effects outside of any function (e.g. `global_asm!` or an `unsafe impl
+    /// Send`), whose caller is their module.
+    pub fn is_dead_code_effect(
+        &self,
+        e: &EffectInstance,
+        reachable: &HashSet<CanonicalPath>,
+    ) -> bool {
+        self.node_idxs.contains_key(e.caller())
+            && self.fn_locs.contains_key(e.caller())
+            && !reachable.contains(e.caller())
+    }
+
+    /// Returns the effects which occur in dead code
+    pub fn dead_code_effects(&self) -> Vec<&EffectInstance> {
+        let reachable = self.reachable_fns();
+        self.effects.iter().filter(|e| self.is_dead_code_effect(e, &reachable)).collect()
+    }
+
+    /// Returns true if the crate forbids unsafe code and calls no sinks, so
+    /// the only effects it can have are function pointer and closure
+    /// creations which can't reach anything dangerous
+    pub fn is_forbid_unsafe_without_sinks(&self) -> bool {
+        self.crate_unsafe_lint == Some(UnsafeCodeLint::Forbid)
+            && self.effects.iter().all(|e| {
+                matches!(e.eff_type(), Effect::FnPtrCreation | Effect::ClosureCreation)
+            })
+    }
+
+    /// Returns the native library an FFI call or declaration binds to: the one
+    /// linked by its extern block, or otherwise the crate's only native
+    /// library, if it has exactly one
+    pub fn ffi_library(&self, e: &EffectInstance) -> Option<&str> {
+        let (Effect::FFICall(f) | Effect::FFIDecl(f)) = e.eff_type() else {
+            return None;
+        };
+        if let Some(lib) = self.ffi_libs.get(f.as_str()) {
+            return Some(lib);
+        }
+        match &self.native_libs[..] {
+            [lib, rest @ ..] if rest.iter().all(|l| l.name == lib.name) => {
+                Some(&lib.name)
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns the attributes which locally permit unsafe code even though the
+    /// crate denies or forbids it
+    pub fn unsafe_code_overrides(&self) -> &[(SrcLoc, UnsafeCodeLint)] {
+        match self.crate_unsafe_lint {
+            Some(lint) if !lint.permits_unsafe() => &self.unsafe_lint_overrides,
+            _ => &[],
+        }
+    }
+
+    /// Computes the effect signature of every function in the call graph, i.e.
+    /// the set of effect types it may perform directly or through its callees.
+    /// `dep_signatures` holds the known signatures of functions in dependency
+    /// crates, which are added to any node in this crate's graph with a
+    /// matching path.
+    pub fn effect_signatures(
+        &self,
+        dep_signatures: &HashMap<CanonicalPath, EffectSignature>,
+    ) -> HashMap<CanonicalPath, EffectSignature> {
+        // NOTE: Look up paths by string, since the src_loc of a CanonicalPath
+        //       for a dependency function depends on where it was resolved
+        let dep_signatures = dep_signatures
+            .iter()
+            .map(|(f, sig)| (f.as_str(), sig))
+            .collect::<HashMap<_, _>>();
+
+        let mut sigs: HashMap<NodeIndex, EffectSignature> = HashMap::new();
+        for (f, idx) in &self.node_idxs {
+            if let Some(dep_sig) = dep_signatures.get(f.as_str()) {
+                sigs.entry(*idx).or_default().extend(dep_sig.iter());
+            }
+        }
+        for e in &self.effects {
+            let Some(idx) = self.node_idxs.get(e.caller()) else { continue };
+            let sig = sigs.entry(*idx).or_default();
+            sig.insert(EffectType::from(e.eff_type()));
+            if let Some(dep_sig) =
+                e.pattern().and_then(|s| dep_signatures.get(s.as_str()))
+            {
+                sig.extend(dep_sig.iter());
+            }
+        }
+
+        // Propagate signatures from callees to callers until we reach a
+        // fixpoint. Signatures only grow, so this terminates even when the call
+        // graph has cycles.
+        let mut worklist = sigs.keys().copied().collect::<VecDeque<_>>();
+        while let Some(callee) = worklist.pop_front() {
+            let callee_sig = sigs.get(&callee).cloned().unwrap_or_default();
+            for caller in self.call_graph.neighbors_directed(callee, Direction::Incoming)
+            {
+                let caller_sig = sigs.entry(caller).or_default();
+                let old_len = caller_sig.len();
+                caller_sig.extend(callee_sig.iter());
+                if caller_sig.len() > old_len {
+                    worklist.push_back(caller);
+                }
+            }
+        }
+
+        sigs.into_iter().map(|(idx, sig)| (self.call_graph[idx].clone(), sig)).collect()
+    }
+
+    pub(crate) fn update_call_graph(&mut self, method: &CanonicalPath) -> NodeIndex {
         if let Some(node_idx) = self.node_idxs.get(method) {
             return node_idx.to_owned();
         }
@@ -119,6 +718,140 @@ impl ScanResults {
         let callee_idx = self.update_call_graph(callee);
         self.call_graph.add_edge(caller_idx, callee_idx, loc);
     }
+
+    /// Merge in the results of scanning other files of the same crate
+    pub fn merge(&mut self, other: ScanResults) {
+        self.effects.extend(other.effects);
+        self.pub_fns.extend(other.pub_fns);
+        self.fn_locs.extend(other.fn_locs);
+        self.fn_hashes.extend(other.fn_hashes);
+        self.trait_meths.extend(other.trait_meths);
+        self.trait_impl_fns.extend(other.trait_impl_fns);
+        self.main_fns.extend(other.main_fns);
+        self.exported_fns.extend(other.exported_fns);
+        self.target_fns.extend(other.target_fns);
+        self.fn_ptr_origins.extend(other.fn_ptr_origins);
+        self.fn_refs.extend(other.fn_refs);
+        self.fn_ptr_calls.extend(other.fn_ptr_calls);
+        self.crate_unsafe_lint = self.crate_unsafe_lint.or(other.crate_unsafe_lint);
+        self.file_unsafe_lints.extend(other.file_unsafe_lints);
+        self.unsafe_lint_overrides.extend(other.unsafe_lint_overrides);
+        self.native_libs.extend(other.native_libs);
+        self.ffi_libs.extend(other.ffi_libs);
+        self.macro_calls.extend(other.macro_calls);
+        self.feature_gated_effects.extend(other.feature_gated_effects);
+
+        for node in other.call_graph.raw_nodes() {
+            self.update_call_graph(&node.weight);
+        }
+        for edge in other.call_graph.raw_edges() {
+            let caller = &other.call_graph[edge.source()];
+            let callee = &other.call_graph[edge.target()];
+            self.add_call(caller, callee, edge.weight.clone());
+        }
+
+        self.total_loc.merge(&other.total_loc);
+        self.skipped_macros.merge(&other.skipped_macros);
+        self.skipped_conditional_code.merge(&other.skipped_conditional_code);
+        self.skipped_fn_calls.merge(&other.skipped_fn_calls);
+        self.skipped_fn_ptrs.merge(&other.skipped_fn_ptrs);
+        self.skipped_other.merge(&other.skipped_other);
+        self.unsafe_traits.merge(&other.unsafe_traits);
+        self.unsafe_impls.merge(&other.unsafe_impls);
+        self.unsafe_blocks.merge(&other.unsafe_blocks);
+        self.unsafe_block_locs.extend(other.unsafe_block_locs);
+        self.fn_loc_tracker.extend(other.fn_loc_tracker);
+        self._effects_loc.merge(&other._effects_loc);
+        self._skipped_build_rs.merge(&other._skipped_build_rs);
+
+        self.resolve_fn_ptr_calls();
+    }
+
+    /// Returns the function held by the field or static at `binding`, if it
+    /// holds a sink or FFI function. Quick mode can't resolve fields, or
+    /// statics which aren't imported, to their full path; these match the
+    /// binding with the same name if there's only one. Nor does it know the
+    /// crate's name, so `crate::` paths match the binding at the same path.
+    pub fn fn_ptr_origin(&self, binding: &IdentPath) -> Option<&CanonicalPath> {
+        if let Some(origin) = self.fn_ptr_origins.get(binding) {
+            return Some(origin);
+        }
+        let name = binding.as_str();
+        if let Some(path) = name.strip_prefix("crate::") {
+            return self
+                .fn_ptr_origins
+                .iter()
+                .find(|(b, _)| b.as_str().split_once("::").map(|(_, p)| p) == Some(path))
+                .map(|(_, origin)| origin);
+        }
+        let name = name.strip_prefix("UNKNOWN_FIELD::").unwrap_or(name);
+        if name.contains("::") {
+            return None;
+        }
+        let mut found = self
+            .fn_ptr_origins
+            .iter()
+            .filter(|(b, _)| b.last_ident().is_some_and(|i| i.as_str() == name));
+        match (found.next(), found.next()) {
+            (Some((_, origin)), None) => Some(origin),
+            _ => None,
+        }
+    }
+
+    /// Add the indirect calls through fields and statics which are now known
+    /// to hold a sink or FFI function to the effects
+    fn resolve_fn_ptr_calls(&mut self) {
+        for mut eff in std::mem::take(&mut self.fn_ptr_calls) {
+            let origin = match eff.eff_type() {
+                Effect::IndirectCall(binding) => self.fn_ptr_origin(binding.as_path()),
+                _ => None,
+            };
+            match origin.cloned() {
+                Some(origin) => {
+                    eff.set_indirect_origin(origin);
+                    self.effects.push(eff);
+                }
+                None => self.fn_ptr_calls.push(eff),
+            }
+        }
+    }
+
+    /// Merges in the results of scanning the same crate under a different
+    /// set of features. Only the effects and functions which weren't found
+    /// before are added, and the LoC and other metadata are left as is.
+    pub fn merge_feature_set(&mut self, other: ScanResults) {
+        let known = self.effects.iter().cloned().collect::<HashSet<_>>();
+        self.effects.extend(other.effects.into_iter().filter(|e| !known.contains(e)));
+        self.pub_fns.extend(other.pub_fns);
+        self.fn_locs.extend(other.fn_locs);
+        self.fn_hashes.extend(other.fn_hashes);
+        self.trait_meths.extend(other.trait_meths);
+        self.trait_impl_fns.extend(other.trait_impl_fns);
+        self.main_fns.extend(other.main_fns);
+        self.exported_fns.extend(other.exported_fns);
+        self.target_fns.extend(other.target_fns);
+        self.fn_ptr_origins.extend(other.fn_ptr_origins);
+        self.fn_refs.extend(other.fn_refs);
+        self.ffi_libs.extend(other.ffi_libs);
+        self.macro_calls.extend(other.macro_calls);
+        self.fn_loc_tracker.extend(other.fn_loc_tracker);
+
+        for node in other.call_graph.raw_nodes() {
+            self.update_call_graph(&node.weight);
+        }
+        for edge in other.call_graph.raw_edges() {
+            let caller = self.update_call_graph(&other.call_graph[edge.source()]);
+            let callee = self.update_call_graph(&other.call_graph[edge.target()]);
+            // Calls found under both sets of features are already there
+            if !self
+                .call_graph
+                .edges_connecting(caller, callee)
+                .any(|e| *e.weight() == edge.weight)
+            {
+                self.call_graph.add_edge(caller, callee, edge.weight.clone());
+            }
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -141,15 +874,33 @@ where
     /// Used only for sanity check / debugging purposes
     scope_unsafe_effects: usize,
 
-    /// Whether we are scanning an assignment expression.
-    /// Useful to check if a union field is accessed to
-    /// read its value, which is unsafe, or to write to it.
-    /// Accessing a union field to assign to it is safe.
-    scope_assign_lhs: bool,
+    /// The places written to by the assignment we are scanning, e.g. `u.f`
+    /// and `u` in `u.f = x`, or `(*p).f`, `(*p)` and `*p` in `(*p).f += x`.
+    /// Useful to check if a union field or raw pointer is accessed to read
+    /// its value or to write to it. Reading a union field is unsafe, while
+    /// assigning to it is safe.
+    scope_assigned_places: Vec<&'a syn::Expr>,
 
     /// Functions inside
     scope_fns: Vec<FnDec>,
 
+    /// Library linked by the current extern block, if any
+    scope_link_lib: Option<String>,
+
+    /// Local variables in the current function which hold a sink or FFI
+    /// function pointer, along with the function they hold
+    scope_fn_ptr_locals: HashMap<String, CanonicalPath>,
+
+    /// Parameters and local variables in the current function which hold a
+    /// raw pointer, along with the module of its methods in `core::ptr`
+    /// (`const_ptr` or `mut_ptr`). The resolver doesn't know the type of
+    /// pointers derived from others, e.g. `p.add(1)`, so we track them here.
+    scope_raw_ptr_locals: HashMap<String, &'static str>,
+
+    /// The feature-, platform- or test-dependent cfg predicates of the items
+    /// we are inside, outermost first. `#[test]` counts as `#[cfg(test)]`.
+    scope_cfg_gates: Vec<CfgPred>,
+
     /// Target to accumulate scan results
     data: &'a mut ScanResults,
 
@@ -158,6 +909,9 @@ where
 
     /// The set of enabled cfg options for this crate.
     enabled_cfg: &'a HashMap<String, Vec<String>>,
+
+    /// Custom detectors to run on each expression and item
+    detectors: Vec<Arc<dyn EffectDetector>>,
 }
 
 impl<'a, R> Scanner<'a, R>
@@ -180,11 +934,16 @@ where
             resolver,
             scope_unsafe: 0,
             scope_unsafe_effects: 0,
-            scope_assign_lhs: false,
+            scope_assigned_places: Vec::new(),
             scope_fns: Vec::new(),
+            scope_link_lib: None,
+            scope_fn_ptr_locals: HashMap::new(),
+            scope_raw_ptr_locals: HashMap::new(),
+            scope_cfg_gates: Vec::new(),
             data,
             sinks: Sink::default_sinks(),
             enabled_cfg,
+            detectors: detector::registered(),
         }
     }
 
@@ -192,6 +951,7 @@ where
     pub fn assert_top_level_invariant(&self) {
         self.resolver.assert_top_level_invariant();
         debug_assert!(self.scope_fns.is_empty());
+        debug_assert!(self.scope_cfg_gates.is_empty());
         debug_assert_eq!(self.scope_unsafe, 0);
         debug_assert_eq!(self.scope_unsafe_effects, 0);
     }
@@ -200,6 +960,11 @@ where
         self.sinks.extend(new_sinks);
     }
 
+    /// Run a detector besides the registered ones (see `detector::register`)
+    pub fn add_detector(&mut self, detector: Arc<dyn EffectDetector>) {
+        self.detectors.push(detector);
+    }
+
     /*
         Additional top-level items and modules
 
@@ -210,13 +975,26 @@ where
     pub fn scan_file(&mut self, f: &'a syn::File) {
         // track lines of code (LoC) at the file level
         self.data.total_loc.add(f);
+        // Which files are the roots of the crate's targets is only known once
+        // they are all scanned (see `ScanResults::set_crate_roots`)
+        for attr in &f.attrs {
+            if let Some(lint) = UnsafeCodeLint::from_attr(attr) {
+                let loc = SrcLoc::from_span(self.filepath, attr);
+                self.data.file_unsafe_lints.push((loc, lint));
+                self.scan_lint_attr(attr);
+            }
+        }
+        let gates = self.push_cfg_gates(&f.attrs);
         // scan the file and return a list of all calls in it
         for i in &f.items {
             self.scan_item(i);
         }
+        self.pop_cfg_gates(gates);
+        self.data.resolve_fn_ptr_calls();
     }
 
     pub fn scan_item(&mut self, i: &'a syn::Item) {
+        self.run_detectors(i, |d, cx| d.detect_item(i, cx));
         match i {
             syn::Item::Mod(m) => self.scan_mod(m),
             syn::Item::Use(u) => {
@@ -226,9 +1004,11 @@ where
             syn::Item::Fn(fun) => self.scan_fn_decl(fun),
             syn::Item::Trait(t) => self.scan_trait(t),
             syn::Item::ForeignMod(fm) => self.scan_foreign_mod(fm),
-            syn::Item::Macro(m) => {
-                self.data.skipped_macros.add(m);
+            syn::Item::Static(st) => {
+                self.scan_global_init(&st.attrs, &st.ident, &st.expr)
             }
+            syn::Item::Const(c) => self.scan_global_init(&c.attrs, &c.ident, &c.expr),
+            syn::Item::Macro(m) => self.scan_macro(m, &m.mac),
             _ => (),
             // For all syntax elements see
             // https://docs.rs/syn/latest/syn/enum.Item.html
@@ -246,6 +1026,11 @@ where
     // Return true if the attributes imply the code should be skipped
     pub fn skip_attr(&self, attr: &'a syn::Attribute) -> bool {
         let path = attr.path();
+        // Test functions are only compiled along with tests
+        if is_test_attr(attr) && !self.enabled_cfg.contains_key("test") {
+            debug!("Skipping test function");
+            return true;
+        }
         // if path.is_ident("cfg_args") || path.is_ident("cfg") {
         if path.is_ident("cfg") {
             let syn::Meta::List(l) = &attr.meta else { return false };
@@ -261,11 +1046,41 @@ where
         false
     }
 
+    // Record attributes which permit unsafe code for a module or item
+    fn scan_lint_attr(&mut self, attr: &'a syn::Attribute) {
+        match UnsafeCodeLint::from_attr(attr) {
+            Some(lint) if lint.permits_unsafe() => {
+                let loc = SrcLoc::from_span(self.filepath, attr);
+                self.data.unsafe_lint_overrides.push((loc, lint));
+            }
+            _ => (),
+        }
+    }
+
+    fn scan_lint_attrs(&mut self, attrs: &'a [syn::Attribute]) {
+        for attr in attrs {
+            self.scan_lint_attr(attr);
+        }
+    }
+
     // Return true if the attributes imply the code should be skipped
     pub fn skip_attrs(&self, attrs: &'a [syn::Attribute]) -> bool {
         attrs.iter().any(|x| self.skip_attr(x))
     }
 
+    // Record the feature-, platform- or test-dependent cfg predicates of an
+    // item we are about to scan. Returns how many were recorded, to pop once the item is scanned.
+    fn push_cfg_gates(&mut self, attrs: &'a [syn::Attribute]) -> usize {
+        let before = self.scope_cfg_gates.len();
+        self.scope_cfg_gates.extend(attrs.iter().filter_map(cfg_gate));
+        self.scope_cfg_gates.len() - before
+    }
+
+    fn pop_cfg_gates(&mut self, gates: usize) {
+        debug_assert!(self.scope_cfg_gates.len() >= gates);
+        self.scope_cfg_gates.truncate(self.scope_cfg_gates.len() - gates);
+    }
+
     // pub fn scan_mod(&mut self, m: &'a syn::ItemMod) {
     //     if self.skip_attrs(&m.attrs) {
     //         self.data.skipped_conditional_code.add(m);
@@ -286,13 +1101,16 @@ where
             self.data.skipped_conditional_code.add(m);
             return;
         }
+        self.scan_lint_attrs(&m.attrs);
 
         if let Some((_, items)) = &m.content {
+            let gates = self.push_cfg_gates(&m.attrs);
             self.resolver.push_mod(&m.ident);
             for i in items {
                 self.scan_item(i);
             }
             self.resolver.pop_mod();
+            self.pop_cfg_gates(gates);
         }
     }
 
@@ -325,9 +1143,17 @@ where
             return;
         }
 
+        self.scope_link_lib = native_libs::link_attr_lib(&fm.attrs);
+        if let Some(lib) = &self.scope_link_lib {
+            let lib = NativeLib::new(lib.clone(), LinkSource::LinkAttr);
+            self.data.native_libs.push(lib);
+        }
+        let gates = self.push_cfg_gates(&fm.attrs);
         for i in &fm.items {
             self.scan_foreign_item(i);
         }
+        self.pop_cfg_gates(gates);
+        self.scope_link_lib = None;
     }
 
     fn scan_foreign_item(&mut self, i: &'a syn::ForeignItem) {
@@ -356,6 +1182,9 @@ where
         let Some(cp) = self.resolver.resolve_ffi_ident(&f.sig.ident) else {
             return;
         };
+        if let Some(lib) = &self.scope_link_lib {
+            self.data.ffi_libs.insert(cp.to_string(), lib.clone());
+        }
         let ffi_dec = FnDec::new(self.filepath, &f, cp.clone(), &f.vis);
 
         // If it is not a public FFI declaration
@@ -372,7 +1201,9 @@ where
         // Notify ScanResults
         self.data.add_fn_dec(ffi_dec);
 
+        let gates = self.push_cfg_gates(&f.attrs);
         self.push_effect(f.span(), cp.clone(), Effect::FFIDecl(cp));
+        self.pop_cfg_gates(gates);
     }
 
     /*
@@ -384,6 +1215,7 @@ where
             self.data.skipped_conditional_code.add(t);
             return;
         }
+        self.scan_lint_attrs(&t.attrs);
 
         // let t_name = self.resolver.resolve_def(&t.ident);
         let t_unsafety = t.unsafety;
@@ -392,6 +1224,7 @@ where
             self.data.unsafe_traits.add(&t.ident);
         }
 
+        let gates = self.push_cfg_gates(&t.attrs);
         let all_impls = self.resolver.resolve_all_impl_methods(&t.ident);
         for item in &t.items {
             match item {
@@ -416,6 +1249,7 @@ where
                 }
             }
         }
+        self.pop_cfg_gates(gates);
     }
 
     fn scan_impl(&mut self, imp: &'a syn::ItemImpl) {
@@ -423,17 +1257,20 @@ where
             self.data.skipped_conditional_code.add(imp);
             return;
         }
+        self.scan_lint_attrs(&imp.attrs);
 
-        self.resolver.push_impl(imp);
-
+        let gates = self.push_cfg_gates(&imp.attrs);
+        // The self type is resolved in the scope the impl appears in
         if let Some((_, tr, _)) = &imp.trait_ {
             self.scan_impl_trait_path(tr, imp);
         }
+        self.resolver.push_impl(imp);
 
+        let in_trait_impl = imp.trait_.is_some();
         for item in &imp.items {
             match item {
                 syn::ImplItem::Fn(m) => {
-                    self.scan_method(m);
+                    self.scan_method(m, in_trait_impl);
                 }
                 syn::ImplItem::Macro(m) => {
                     self.data.skipped_macros.add(m);
@@ -448,28 +1285,23 @@ where
         }
 
         self.resolver.pop_impl();
+        self.pop_cfg_gates(gates);
     }
 
     fn scan_impl_trait_path(&mut self, tr: &'a syn::Path, imp: &'a syn::ItemImpl) {
         if imp.unsafety.is_some() {
             // we found an `unsafe impl` declaration
-            // let tr_name = self.resolver.resolve_path(tr);
-            // let self_ty = imp
-            //     .self_ty
-            //     .to_token_stream()
-            //     .into_iter()
-            //     .filter_map(|token| match token {
-            //         TokenTree::Ident(i) => Some(i),
-            //         _ => None,
-            //     })
-            //     .last();
-            // // resolve the implementing type of the trait, if there is one
-            // let tr_type = match &self_ty {
-            //     Some(ident) => Some(self.resolver.resolve_ident(ident)),
-            //     _ => None,
-            // };
-
             self.data.unsafe_impls.add(tr);
+
+            // Implementing Send or Sync claims the type is thread safe, which
+            // the compiler can't check, so these get an effect of their own
+            if let Some(marker) = send_sync_trait(tr) {
+                let self_ty = match &*imp.self_ty {
+                    syn::Type::Path(p) => self.resolver.resolve_impl_type(&p.path),
+                    _ => self.resolver.resolve_mod_path(),
+                };
+                self.push_scope_effect(tr, self_ty, Effect::SendSyncImpl(marker));
+            }
         }
     }
 
@@ -484,8 +1316,15 @@ where
             self.data.skipped_conditional_code.add(f);
             return;
         }
+        self.scan_lint_attrs(&f.attrs);
 
-        self.scan_fn(&f.sig, &f.block, &f.vis);
+        let gates = self.push_cfg_gates(&f.attrs);
+        let f_name = self.scan_fn(&f.sig, &f.block, &f.vis);
+        if f.attrs.iter().any(is_test_attr) {
+            self.data.target_fns.insert(f_name.clone());
+        }
+        self.scan_ffi_export(&f.attrs, &f.sig, f_name);
+        self.pop_cfg_gates(gates);
     }
 
     fn scan_trait_method(
@@ -498,12 +1337,15 @@ where
             self.data.skipped_conditional_code.add(m);
             return;
         }
+        self.scan_lint_attrs(&m.attrs);
 
         // If there is a default implementation, scan the function body as usual.
         // Otherwise, just create a node in the call graph for the abstract trait method.
         let f_name = self.resolver.resolve_def(&m.sig.ident);
         if let Some(body) = &m.default {
+            let gates = self.push_cfg_gates(&m.attrs);
             self.scan_fn(&m.sig, body, vis);
+            self.pop_cfg_gates(gates);
         } else {
             // Update call graph
             self.data.update_call_graph(&f_name);
@@ -520,14 +1362,43 @@ where
         }
     }
 
-    fn scan_method(&mut self, m: &'a syn::ImplItemFn) {
+    fn scan_method(&mut self, m: &'a syn::ImplItemFn, in_trait_impl: bool) {
         if self.skip_attrs(&m.attrs) {
             self.data.skipped_conditional_code.add(m);
             return;
         }
+        self.scan_lint_attrs(&m.attrs);
 
         // NB: may or may not be a method, if there is no self keyword
-        self.scan_fn(&m.sig, &m.block, &m.vis);
+        let gates = self.push_cfg_gates(&m.attrs);
+        let f_name = self.scan_fn(&m.sig, &m.block, &m.vis);
+        if in_trait_impl {
+            self.data.trait_impl_fns.insert(f_name.clone());
+        }
+        self.scan_ffi_export(&m.attrs, &m.sig, f_name);
+        self.pop_cfg_gates(gates);
+    }
+
+    /// Functions which foreign code can call are an effect, as they're part
+    /// of the crate's attack surface even if nothing in Rust calls them
+    fn scan_ffi_export(
+        &mut self,
+        attrs: &'a [syn::Attribute],
+        f_sig: &'a syn::Signature,
+        f_name: CanonicalPath,
+    ) {
+        let Some(symbol) = ffi_export_symbol(attrs, f_sig) else {
+            return;
+        };
+        self.data.exported_fns.insert(f_name.clone());
+        let eff = EffectInstance::new_effect(
+            self.filepath,
+            f_name.clone(),
+            f_name,
+            f_sig,
+            Effect::FFIExport(symbol),
+        );
+        self.add_effect(eff);
     }
 
     fn scan_fn(
@@ -535,7 +1406,7 @@ where
         f_sig: &'a syn::Signature,
         body: &'a syn::Block,
         vis: &'a syn::Visibility,
-    ) {
+    ) -> CanonicalPath {
         // Create fn decl
         let f_ident = &f_sig.ident;
         let f_name = self.resolver.resolve_def(f_ident);
@@ -545,10 +1416,36 @@ where
         let mut fn_loc = LoCTracker::new();
         fn_loc.add(body.span());
         self.data.fn_loc_tracker.insert(f_name.clone(), fn_loc);
+        // Functions sharing a path (e.g. under different cfgs) can't be told
+        // apart, so they get an empty hash
+        let fn_hash = fn_tokens_hash(f_sig, body);
+        self.data
+            .fn_hashes
+            .entry(f_name.clone())
+            .and_modify(|h| {
+                if *h != fn_hash {
+                    h.clear()
+                }
+            })
+            .or_insert(fn_hash);
 
         // Always push the new function declaration before scanning the
         // body so we have access to the function its in
         self.scope_fns.push(fn_dec.clone());
+        let outer_fn_ptr_locals = std::mem::take(&mut self.scope_fn_ptr_locals);
+        let outer_raw_ptr_locals = std::mem::replace(
+            &mut self.scope_raw_ptr_locals,
+            f_sig
+                .inputs
+                .iter()
+                .filter_map(|arg| match arg {
+                    syn::FnArg::Typed(x) => {
+                        Some((pat_ident(&x.pat)?.to_string(), raw_ptr_type(&x.ty)?))
+                    }
+                    syn::FnArg::Receiver(_) => None,
+                })
+                .collect(),
+        );
 
         // Notify resolver
         self.resolver.push_fn(f_ident);
@@ -569,6 +1466,8 @@ where
 
         // Reset state
         self.scope_fns.pop();
+        self.scope_fn_ptr_locals = outer_fn_ptr_locals;
+        self.scope_raw_ptr_locals = outer_raw_ptr_locals;
         self.resolver.pop_fn();
 
         // Reset unsafety
@@ -580,6 +1479,8 @@ where
             }
             self.scope_unsafe_effects = 0;
         }
+
+        f_name
     }
 
     fn scan_fn_statement(&mut self, s: &'a syn::Stmt) {
@@ -587,9 +1488,7 @@ where
             syn::Stmt::Local(l) => self.scan_fn_local(l),
             syn::Stmt::Expr(e, _semi) => self.scan_expr(e),
             syn::Stmt::Item(i) => self.scan_item_in_fn(i),
-            syn::Stmt::Macro(m) => {
-                self.data.skipped_macros.add(m);
-            }
+            syn::Stmt::Macro(m) => self.scan_macro(m, &m.mac),
         }
     }
 
@@ -604,11 +1503,30 @@ where
             return;
         }
 
+        if let Some(i) = pat_ident(&l.pat) {
+            // A binding shadows any earlier pointer of the same name
+            let kind = match &l.pat {
+                syn::Pat::Type(x) => raw_ptr_type(&x.ty),
+                _ => None,
+            }
+            .or_else(|| self.raw_ptr_kind(&l.init.as_ref()?.expr));
+            match kind {
+                Some(kind) => self.scope_raw_ptr_locals.insert(i.to_string(), kind),
+                None => self.scope_raw_ptr_locals.remove(&i.to_string()),
+            };
+        }
         if let Some(let_expr) = &l.init {
+            if let Some(origin) = self.fn_ptr_origin(&let_expr.expr) {
+                if let Some(i) = pat_ident(&l.pat) {
+                    self.scope_fn_ptr_locals.insert(i.to_string(), origin);
+                }
+            }
+            let gates = self.push_cfg_gates(&l.attrs);
             self.scan_expr(&let_expr.expr);
             if let Some((_, else_expr)) = &let_expr.diverge {
                 self.scan_expr(else_expr);
             }
+            self.pop_cfg_gates(gates);
         }
     }
 
@@ -618,6 +1536,7 @@ where
     */
 
     fn scan_expr(&mut self, e: &'a syn::Expr) {
+        self.run_detectors(e, |d, cx| d.detect_expr(e, cx));
         match e {
             syn::Expr::Array(x) => {
                 if self.skip_attrs(&x.attrs) {
@@ -635,9 +1554,24 @@ where
                     return;
                 }
 
-                self.scope_assign_lhs = true;
+                if let Some(origin) = self.fn_ptr_origin(&x.right) {
+                    match &*x.left {
+                        syn::Expr::Path(p) if p.path.get_ident().is_some() => {
+                            let i = p.path.get_ident().unwrap().to_string();
+                            self.scope_fn_ptr_locals.insert(i, origin);
+                        }
+                        syn::Expr::Field(f) => {
+                            if let syn::Member::Named(i) = &f.member {
+                                let field = self.resolver.resolve_field(i).to_path();
+                                self.data.fn_ptr_origins.insert(field, origin);
+                            }
+                        }
+                        _ => (),
+                    }
+                }
+                self.scope_assigned_places = assigned_places(&x.left);
                 self.scan_expr(&x.left);
-                self.scope_assign_lhs = false;
+                self.scope_assigned_places.clear();
                 self.scan_expr(&x.right);
             }
             syn::Expr::Async(x) => {
@@ -664,7 +1598,13 @@ where
                     return;
                 }
 
+                // Compound assignments, e.g. `*p += 1`, write to their
+                // left-hand side as well
+                if is_compound_assign(&x.op) {
+                    self.scope_assigned_places = assigned_places(&x.left);
+                }
                 self.scan_expr(&x.left);
+                self.scope_assigned_places.clear();
                 self.scan_expr(&x.right);
             }
             syn::Expr::Block(x) => {
@@ -697,6 +1637,10 @@ where
                 self.scan_expr_call_args(&x.args);
                 // Function call
                 self.scan_expr_call(&x.func);
+                // Dynamic library loads with a literal library
+                if let Some(library) = x.args.first().and_then(lit_str) {
+                    self.record_dyn_load_library(&x.func, library);
+                }
             }
             syn::Expr::Cast(x) => {
                 if self.skip_attrs(&x.attrs) {
@@ -737,7 +1681,7 @@ where
                 }
 
                 self.scan_expr(&x.base);
-                self.scan_field_access(x);
+                self.scan_field_access(x, self.is_assigned_place(e));
             }
             syn::Expr::ForLoop(x) => {
                 if self.skip_attrs(&x.attrs) {
@@ -800,9 +1744,7 @@ where
                     self.scan_fn_statement(s);
                 }
             }
-            syn::Expr::Macro(m) => {
-                self.data.skipped_macros.add(m);
-            }
+            syn::Expr::Macro(m) => self.scan_macro(m, &m.mac),
             syn::Expr::Match(x) => {
                 if self.skip_attrs(&x.attrs) {
                     self.data.skipped_conditional_code.add(x);
@@ -834,7 +1776,7 @@ where
                 // Arguments
                 self.scan_expr_call_args(&x.args);
                 // Function call
-                self.scan_expr_call_method(&x.method);
+                self.scan_expr_call_method(&x.receiver, &x.method);
             }
             syn::Expr::Paren(x) => {
                 if self.skip_attrs(&x.attrs) {
@@ -899,6 +1841,7 @@ where
                     return;
                 }
 
+                self.record_fn_ptr_fields(e);
                 for y in x.fields.iter() {
                     if self.skip_attrs(&y.attrs) {
                         self.data.skipped_conditional_code.add(y);
@@ -947,7 +1890,7 @@ where
                 }
 
                 if let syn::UnOp::Deref(_) = x.op {
-                    self.scan_deref(&x.expr);
+                    self.scan_deref(&x.expr, self.is_assigned_place(e));
                 }
                 self.scan_expr(&x.expr);
             }
@@ -1007,6 +1950,10 @@ where
     }
 
     fn scan_path(&mut self, x: &'a syn::Path) {
+        // The path may name a function used as a value, e.g. a callback
+        if let Some(s) = x.segments.last() {
+            self.data.fn_refs.insert(s.ident.to_string());
+        }
         let ty = self.resolver.resolve_path_type(x);
         // Function pointer creation
         if ty.is_function() || ty.is_fn_ptr() {
@@ -1033,6 +1980,102 @@ where
         }
     }
 
+    /*
+        Function pointers holding sinks
+        NOTE: Calls through fields and statics are only reported once the file
+        binding them is scanned too, when the results are merged.
+    */
+
+    /// If the expression evaluates to a sink or FFI function (or a table of
+    /// them), returns the function
+    fn fn_ptr_origin(&self, e: &'a syn::Expr) -> Option<CanonicalPath> {
+        match e {
+            syn::Expr::Path(p) => self.fn_ptr_binding(&p.path).or_else(|| {
+                let cp = self.resolver.resolve_path(&p.path);
+                let is_sink = Sink::new_match(&cp, &self.sinks).is_some();
+                if is_sink || self.resolver.resolve_ffi(&p.path).is_some() {
+                    Some(cp)
+                } else {
+                    None
+                }
+            }),
+            syn::Expr::Paren(x) => self.fn_ptr_origin(&x.expr),
+            syn::Expr::Group(x) => self.fn_ptr_origin(&x.expr),
+            syn::Expr::Reference(x) => self.fn_ptr_origin(&x.expr),
+            syn::Expr::Cast(x) => self.fn_ptr_origin(&x.expr),
+            syn::Expr::Array(x) => x.elems.iter().find_map(|y| self.fn_ptr_origin(y)),
+            syn::Expr::Call(x) => {
+                // e.g. Some(libc::write)
+                x.args.iter().find_map(|y| self.fn_ptr_origin(y))
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns the function held by a local variable, field, or static which
+    /// was bound to a sink or FFI function
+    fn fn_ptr_binding(&self, p: &'a syn::Path) -> Option<CanonicalPath> {
+        self.fn_ptr_local(p).or_else(|| {
+            let binding = self.resolver.resolve_path(p).to_path();
+            self.data.fn_ptr_origin(&binding).cloned()
+        })
+    }
+
+    /// Returns the function held by a local variable which was bound to a
+    /// sink or FFI function
+    fn fn_ptr_local(&self, p: &'a syn::Path) -> Option<CanonicalPath> {
+        let i = p.get_ident()?.to_string();
+        self.scope_fn_ptr_locals.get(&i).cloned()
+    }
+
+    /// Push an indirect call through a field or static. The field or static
+    /// is the callee, and stands in for the function it holds until the file
+    /// binding it is scanned, which may be a different one.
+    fn push_fn_ptr_call<S>(&mut self, eff_span: S, callee: CanonicalPath)
+    where
+        S: Debug + Spanned,
+    {
+        self.push_effect(eff_span, callee.clone(), Effect::IndirectCall(callee));
+        let eff = self.data.effects.pop().expect("effect was just pushed");
+        self.data.fn_ptr_calls.push(eff);
+    }
+
+    /// Record the fields of any struct literals in the expression which are
+    /// initialized with sink or FFI functions
+    fn record_fn_ptr_fields(&mut self, e: &'a syn::Expr) {
+        match e {
+            syn::Expr::Struct(x) => {
+                for y in x.fields.iter() {
+                    if let syn::Member::Named(i) = &y.member {
+                        if let Some(origin) = self.fn_ptr_origin(&y.expr) {
+                            let mut field = self.resolver.resolve_path(&x.path).to_path();
+                            field.push_ident(&ident_from_syn(i));
+                            self.data.fn_ptr_origins.insert(field, origin);
+                        }
+                    }
+                    self.record_fn_ptr_fields(&y.expr);
+                }
+            }
+            syn::Expr::Array(x) => {
+                for y in x.elems.iter() {
+                    self.record_fn_ptr_fields(y);
+                }
+            }
+            syn::Expr::Reference(x) => self.record_fn_ptr_fields(&x.expr),
+            syn::Expr::Paren(x) => self.record_fn_ptr_fields(&x.expr),
+            _ => (),
+        }
+    }
+
+    /// Record a static or const which holds sink or FFI functions
+    fn scan_fn_ptr_binding(&mut self, i: &'a syn::Ident, e: &'a syn::Expr) {
+        if let Some(origin) = self.fn_ptr_origin(e) {
+            let binding = self.resolver.resolve_def(i).to_path();
+            self.data.fn_ptr_origins.insert(binding, origin);
+        }
+        self.record_fn_ptr_fields(e);
+    }
+
     fn scan_closure(&mut self, x: &'a syn::ExprClosure) {
         self.syn_debug("scanning closure", x);
 
@@ -1047,35 +2090,55 @@ where
         }
     }
 
-    fn scan_deref(&mut self, x: &'a syn::Expr) {
+    fn scan_deref(&mut self, x: &'a syn::Expr, is_write: bool) {
         let mut tokens: TokenStream = TokenStream::new();
         x.to_tokens(&mut tokens);
         tokens.into_iter().for_each(|tt| {
             if let TokenTree::Ident(i) = tt {
                 let ty = self.resolver.resolve_field_type(&i);
                 let p = self.resolver.resolve_field(&i);
-                if ty.is_raw_ptr() {
+                if ty.is_raw_ptr()
+                    || self.scope_raw_ptr_locals.contains_key(&i.to_string())
+                {
                     // NOTE: Can only be done in an unsafe block
-                    self.push_effect(x.span(), p.clone(), Effect::RawPointer(p));
+                    let eff = if is_write {
+                        Effect::RawPointerWrite(p.clone())
+                    } else {
+                        Effect::RawPointer(p.clone())
+                    };
+                    self.push_effect(x.span(), p, eff);
                 }
             }
         });
     }
 
+    /// Returns true if the expression is written to by the assignment we are
+    /// scanning
+    fn is_assigned_place(&self, e: &'a syn::Expr) -> bool {
+        self.scope_assigned_places.iter().any(|p| std::ptr::eq(*p, e))
+    }
+
     // Check if the field being accessed is a Union field
-    fn scan_field_access(&mut self, x: &'a syn::ExprField) {
+    fn scan_field_access(&mut self, x: &'a syn::ExprField, is_write: bool) {
         if let syn::Member::Named(i) = &x.member {
             let ty = self.resolver.resolve_field_type(i);
-            if !ty.is_union_field() || self.scope_assign_lhs {
+            if !ty.is_union_field() {
                 return;
             }
             let cp = self.resolver.resolve_field(i);
-            // NOTE: Can only be done in an unsafe block
-            self.push_effect(x.span(), cp.clone(), Effect::UnionField(cp));
+            let eff = if is_write {
+                Effect::UnionFieldWrite(cp.clone())
+            } else {
+                // NOTE: Can only be done in an unsafe block
+                Effect::UnionFieldRead(cp.clone())
+            };
+            self.push_effect(x.span(), cp, eff);
         }
     }
 
     fn scan_unsafe_block(&mut self, x: &'a syn::ExprUnsafe) {
+        self.data.unsafe_blocks.add(x);
+        self.data.unsafe_block_locs.push(SrcLoc::from_span(self.filepath, x));
         self.scope_unsafe += 1;
         for s in &x.block.stmts {
             self.scan_fn_statement(s);
@@ -1102,34 +2165,160 @@ where
         }
     }
 
-    /// Push an effect into the current `EffectBlock`. Should be used when
-    /// pushing an effect in an unsafe block so all effects can be captured at
-    /// the same time.
-    fn push_effect<S>(&mut self, eff_span: S, callee: CanonicalPath, eff_type: Effect)
+    /// Inline assembly and reading environment variables at compile time are
+    /// effects; any other macro invocation is skipped
+    fn scan_macro<S>(&mut self, m: &'a S, mac: &'a syn::Macro)
     where
-        S: Debug + Spanned,
+        S: Debug + ToTokens,
     {
-        let caller = if eff_type.is_ffi_decl() {
-            &callee
+        let (callee, eff_type) = if is_asm_macro(mac) {
+            (self.resolver.resolve_path(&mac.path), Effect::InlineAsm)
+        } else if let Some(sink) = env_macro_sink(mac) {
+            // env! and option_env! are built in, so name them by their std path
+            (CanonicalPath::new(sink.as_str()), Effect::SinkCall(sink))
         } else {
-            let containing_fn = self.scope_fns.last().expect("not inside a function!");
-            &containing_fn.fn_name
+            self.data.skipped_macros.add(m);
+            self.record_fn_refs(mac.tokens.clone());
+            if let Some(containing_fn) = self.scope_fns.last() {
+                self.data
+                    .macro_calls
+                    .entry(containing_fn.fn_name.clone())
+                    .or_default()
+                    .push(SrcLoc::from_span(self.filepath, m));
+            }
+            return;
         };
 
-        let eff = EffectInstance::new_effect(
-            self.filepath,
-            caller.clone(),
-            callee,
-            &eff_span,
-            eff_type,
-        );
+        self.push_scope_effect(m, callee, eff_type);
+    }
 
-        if self.scope_unsafe > 0 && eff.is_rust_unsafe() {
-            self.scope_unsafe_effects += 1;
+    /// Record the identifiers in code which isn't scanned as names which may
+    /// refer to functions, as their calls aren't in the call graph
+    fn record_fn_refs(&mut self, tokens: TokenStream) {
+        for tt in tokens {
+            match tt {
+                TokenTree::Ident(i) => {
+                    self.data.fn_refs.insert(i.to_string());
+                }
+                TokenTree::Group(g) => self.record_fn_refs(g.stream()),
+                _ => (),
+            }
+        }
+    }
+
+    /// Push an effect which may occur outside of any function, e.g.
+    /// `global_asm!` or `env!` in a const. Then the module is the caller, so
+    /// it needs a node in the call graph like any other caller.
+    fn push_scope_effect<S>(
+        &mut self,
+        eff_span: S,
+        callee: CanonicalPath,
+        eff_type: Effect,
+    ) where
+        S: Debug + Spanned,
+    {
+        if self.scope_fns.is_empty() {
+            let caller = self.resolver.resolve_mod_path();
+            self.data.update_call_graph(&caller);
+            let eff = EffectInstance::new_effect(
+                self.filepath,
+                caller,
+                callee,
+                &eff_span,
+                eff_type,
+            );
+            self.add_effect(eff);
+        } else {
+            self.push_effect(eff_span, callee, eff_type);
+        }
+    }
+
+    /// Push the effects the custom detectors report for a syntax node
+    fn run_detectors<S, F>(&mut self, node: S, detect: F)
+    where
+        S: Debug + Spanned + Copy,
+        F: Fn(&dyn EffectDetector, &DetectorContext<'a, '_>) -> Option<CanonicalPath>,
+    {
+        if self.detectors.is_empty() {
+            return;
+        }
+        let caller = self.scope_fns.last().map(|f| &f.fn_name);
+        let cx = DetectorContext::new(&self.resolver, caller);
+        let found = self
+            .detectors
+            .iter()
+            .filter_map(|d| Some((detect(d.as_ref(), &cx)?, d.name().to_string())))
+            .collect::<Vec<_>>();
+        for (callee, name) in found {
+            self.push_scope_effect(node, callee, Effect::Custom(name));
+        }
+    }
+
+    /// Scan the initializer of a const or static, e.g. for function pointers
+    /// or secrets read from the environment at compile time
+    fn scan_global_init(
+        &mut self,
+        attrs: &'a [syn::Attribute],
+        i: &'a syn::Ident,
+        e: &'a syn::Expr,
+    ) {
+        self.scan_fn_ptr_binding(i, e);
+        self.record_fn_refs(e.to_token_stream());
+        if let syn::Expr::Macro(m) = e {
+            if env_macro_sink(&m.mac).is_some() {
+                let gates = self.push_cfg_gates(attrs);
+                self.scan_macro(m, &m.mac);
+                self.pop_cfg_gates(gates);
+            }
+        }
+    }
+
+    /// Record an effect found in the scanned code, along with the cfg
+    /// predicates it's gated by
+    fn add_effect(&mut self, mut eff: EffectInstance) {
+        let gates = |mentions: fn(&CfgPred) -> bool| {
+            self.scope_cfg_gates
+                .iter()
+                .filter(|p| mentions(p))
+                .map(|p| p.to_string())
+                .collect::<Vec<_>>()
+        };
+        eff.set_cfg_gates(gates(CfgPred::mentions_feature));
+        eff.set_platform_gates(gates(CfgPred::mentions_platform));
+        if self.scope_cfg_gates.iter().any(CfgPred::requires_test) {
+            eff.set_target_kind(TargetKind::Test);
         }
         self.data.effects.push(eff);
     }
 
+    /// Push an effect into the current `EffectBlock`. Should be used when
+    /// pushing an effect in an unsafe block so all effects can be captured at
+    /// the same time.
+    fn push_effect<S>(&mut self, eff_span: S, callee: CanonicalPath, eff_type: Effect)
+    where
+        S: Debug + Spanned,
+    {
+        let caller = if eff_type.is_ffi_decl() {
+            &callee
+        } else {
+            let containing_fn = self.scope_fns.last().expect("not inside a function!");
+            &containing_fn.fn_name
+        };
+
+        let eff = EffectInstance::new_effect(
+            self.filepath,
+            caller.clone(),
+            callee,
+            &eff_span,
+            eff_type,
+        );
+
+        if self.scope_unsafe > 0 && eff.is_rust_unsafe() {
+            self.scope_unsafe_effects += 1;
+        }
+        self.add_effect(eff);
+    }
+
     /// push an Effect to the list of results based on this call site.
     fn push_callsite<S>(
         &mut self,
@@ -1163,7 +2352,18 @@ where
         if self.scope_unsafe > 0 && eff.is_rust_unsafe() {
             self.scope_unsafe_effects += 1;
         }
-        self.data.effects.push(eff);
+        self.add_effect(eff);
+    }
+
+    /// Record the library loaded by the dynamic library load just pushed for
+    /// the call to `f`, if there was one
+    fn record_dyn_load_library(&mut self, f: &'a syn::Expr, library: String) {
+        let call_loc = SrcLoc::from_span(self.filepath, f);
+        if let Some(eff) = self.data.effects.last_mut() {
+            if *eff.call_loc() == call_loc {
+                eff.set_dyn_load_library(library);
+            }
+        }
     }
 
     // f in a call of the form (f)(args)
@@ -1174,6 +2374,15 @@ where
                 let ffi = self.resolver.resolve_ffi(&p.path);
                 let is_unsafe =
                     self.resolver.resolve_unsafe_path(&p.path) && self.scope_unsafe > 0;
+                if let Some(origin) = self.fn_ptr_local(&p.path) {
+                    self.push_effect(
+                        p.span(),
+                        callee.clone(),
+                        Effect::IndirectCall(origin),
+                    );
+                } else if is_static_name(&p.path) {
+                    self.push_fn_ptr_call(p.span(), callee.clone());
+                }
                 self.push_callsite(p, callee, ffi, is_unsafe);
             }
             syn::Expr::Paren(x) => {
@@ -1187,6 +2396,20 @@ where
             }
             syn::Expr::Macro(m) => {
                 self.data.skipped_macros.add(m);
+                self.record_fn_refs(m.mac.tokens.clone());
+            }
+            syn::Expr::Index(x) => {
+                // e.g. TABLE[i](x), where TABLE holds sink function pointers
+                if let syn::Expr::Path(p) = &*x.expr {
+                    let callee = self.resolver.resolve_path(&p.path);
+                    if let Some(origin) = self.fn_ptr_local(&p.path) {
+                        self.push_effect(x.span(), callee, Effect::IndirectCall(origin));
+                    } else {
+                        self.push_fn_ptr_call(x.span(), callee);
+                    }
+                }
+                self.syn_info("Skipped function call", x);
+                self.data.skipped_fn_calls.add(x);
             }
             other => {
                 // anything else could be a function, too -- could return a closure
@@ -1202,7 +2425,9 @@ where
             syn::Member::Named(i) => {
                 let is_unsafe =
                     self.resolver.resolve_unsafe_ident(i) && self.scope_unsafe > 0;
-                self.push_callsite(i, self.resolver.resolve_field(i), None, is_unsafe);
+                let callee = self.resolver.resolve_field(i);
+                self.push_fn_ptr_call(i.span(), callee.clone());
+                self.push_callsite(i, callee, None, is_unsafe);
             }
             syn::Member::Unnamed(idx) => {
                 self.push_callsite(
@@ -1215,12 +2440,251 @@ where
         }
     }
 
-    fn scan_expr_call_method(&mut self, i: &'a syn::Ident) {
+    fn scan_expr_call_method(&mut self, receiver: &'a syn::Expr, i: &'a syn::Ident) {
         let is_unsafe = self.resolver.resolve_unsafe_ident(i) && self.scope_unsafe > 0;
-        self.push_callsite(i, self.resolver.resolve_method(i), None, is_unsafe);
+        // Methods of raw pointers, e.g. `p.add(1)`, which the resolver can't
+        // tell apart from other methods of the same name
+        let callee = match self.raw_ptr_kind(receiver) {
+            Some(kind) => CanonicalPath::new_owned(
+                format!("core::ptr::{}::{}", kind, i),
+                SrcLoc::from_span(self.filepath, &i.span()),
+            ),
+            None => self.resolver.resolve_method(i),
+        };
+        self.push_callsite(i, callee, None, is_unsafe);
+    }
+
+    /// Returns the module of the methods of the raw pointer the expression
+    /// evaluates to (see `scope_raw_ptr_locals`), if it's one we know of
+    fn raw_ptr_kind(&self, e: &'a syn::Expr) -> Option<&'static str> {
+        match e {
+            syn::Expr::Path(x) => {
+                let i = x.path.get_ident()?;
+                self.scope_raw_ptr_locals.get(&i.to_string()).copied().or_else(|| {
+                    let ty = self.resolver.resolve_field_type(i);
+                    ty.is_raw_ptr().then(|| {
+                        if ty.as_str().starts_with("*mut") {
+                            "mut_ptr"
+                        } else {
+                            "const_ptr"
+                        }
+                    })
+                })
+            }
+            syn::Expr::Paren(x) => self.raw_ptr_kind(&x.expr),
+            syn::Expr::Group(x) => self.raw_ptr_kind(&x.expr),
+            syn::Expr::Cast(x) => raw_ptr_type(&x.ty),
+            syn::Expr::MethodCall(x) => match x.method.to_string().as_str() {
+                "as_ptr" | "cast_const" => Some("const_ptr"),
+                "as_mut_ptr" | "cast_mut" => Some("mut_ptr"),
+                m if RAW_PTR_DERIVE_METHODS.contains(&m) => {
+                    self.raw_ptr_kind(&x.receiver)
+                }
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+}
+
+/// Returns true if the path names a static by convention, i.e. in upper case,
+/// e.g. `HANDLER` or `ops::HANDLER`
+fn is_static_name(p: &syn::Path) -> bool {
+    p.segments.last().is_some_and(|s| {
+        let name = s.ident.to_string();
+        name.chars().any(char::is_uppercase) && !name.chars().any(char::is_lowercase)
+    })
+}
+
+/// Returns true for `asm!`, `global_asm!`, and `naked_asm!`
+fn is_asm_macro(mac: &syn::Macro) -> bool {
+    mac.path.segments.last().is_some_and(|s| {
+        s.ident == "asm" || s.ident == "global_asm" || s.ident == "naked_asm"
+    })
+}
+
+/// Returns the value of a string literal expression, e.g. `"libfoo.so"` or
+/// `b"libfoo.so\0"`
+fn lit_str(e: &syn::Expr) -> Option<String> {
+    match e {
+        syn::Expr::Lit(l) => match &l.lit {
+            syn::Lit::Str(s) => Some(s.value()),
+            syn::Lit::ByteStr(s) => {
+                let bytes = s.value();
+                let bytes = bytes.strip_suffix(b"\0").unwrap_or(&bytes);
+                Some(String::from_utf8_lossy(bytes).into_owned())
+            }
+            _ => None,
+        },
+        syn::Expr::Paren(x) => lit_str(&x.expr),
+        syn::Expr::Group(x) => lit_str(&x.expr),
+        syn::Expr::Reference(x) => lit_str(&x.expr),
+        _ => None,
+    }
+}
+
+/// Returns the symbol a function is exported under to foreign code, given
+/// its attributes: `Some(Some(symbol))` for `#[no_mangle]` and
+/// `#[export_name = "..."]` functions (also when wrapped in `unsafe(...)`),
+/// `Some(None)` for other functions with a non-Rust ABI, which foreign code
+/// can call through a pointer, and `None` for functions foreign code can't
+/// call.
+fn ffi_export_symbol(
+    attrs: &[syn::Attribute],
+    f_sig: &syn::Signature,
+) -> Option<Option<String>> {
+    for attr in attrs {
+        let meta = match &attr.meta {
+            syn::Meta::List(l) if l.path.is_ident("unsafe") => {
+                match l.parse_args::<syn::Meta>() {
+                    Ok(meta) => meta,
+                    Err(_) => continue,
+                }
+            }
+            meta => meta.clone(),
+        };
+        match &meta {
+            syn::Meta::Path(p) if p.is_ident("no_mangle") => {
+                return Some(Some(f_sig.ident.to_string()));
+            }
+            syn::Meta::NameValue(nv) if nv.path.is_ident("export_name") => {
+                if let syn::Expr::Lit(syn::ExprLit { lit: syn::Lit::Str(s), .. }) =
+                    &nv.value
+                {
+                    return Some(Some(s.value()));
+                }
+            }
+            _ => (),
+        }
+    }
+    match &f_sig.abi {
+        Some(abi) if abi.name.as_ref().is_none_or(|n| n.value() != "Rust") => Some(None),
+        _ => None,
+    }
+}
+
+/// Returns the places written to by an assignment with the given left-hand
+/// side: the left-hand side itself, and the places it is a field or element
+/// of, up to and including any dereference. E.g. for `(*p).f[i]`, these are
+/// `(*p).f[i]`, `(*p).f`, `(*p)` and `*p`, but not `p`, which is only read.
+fn assigned_places(lhs: &syn::Expr) -> Vec<&syn::Expr> {
+    let mut places = vec![lhs];
+    let mut place = lhs;
+    loop {
+        place = match place {
+            syn::Expr::Field(x) => &x.base,
+            syn::Expr::Index(x) => &x.expr,
+            syn::Expr::Paren(x) => &x.expr,
+            _ => break,
+        };
+        places.push(place);
+    }
+    places
+}
+
+fn is_compound_assign(op: &syn::BinOp) -> bool {
+    use syn::BinOp::*;
+    matches!(
+        op,
+        AddAssign(_)
+            | SubAssign(_)
+            | MulAssign(_)
+            | DivAssign(_)
+            | RemAssign(_)
+            | BitXorAssign(_)
+            | BitAndAssign(_)
+            | BitOrAssign(_)
+            | ShlAssign(_)
+            | ShrAssign(_)
+    )
+}
+
+/// Returns the full path of the trait if it's `Send` or `Sync`
+fn send_sync_trait(tr: &syn::Path) -> Option<CanonicalPath> {
+    let name = tr.segments.last()?.ident.to_string();
+    matches!(name.as_str(), "Send" | "Sync")
+        .then(|| CanonicalPath::new(&format!("std::marker::{}", name)))
+}
+
+/// Returns the sink for `env!` and `option_env!`
+fn env_macro_sink(mac: &syn::Macro) -> Option<Sink> {
+    Sink::new_env_macro(&mac.path.segments.last()?.ident.to_string())
+}
+
+/// The predicate of a `#[cfg(...)]` attribute, if it depends on the crate's
+/// features, the target platform or whether tests are compiled. Test
+/// function attributes are treated as `#[cfg(test)]`.
+fn cfg_gate(attr: &syn::Attribute) -> Option<CfgPred> {
+    if is_test_attr(attr) {
+        return Some(CfgOpt::Name("test".to_string()).into());
+    }
+    if !attr.path().is_ident("cfg") {
+        return None;
+    }
+    let syn::Meta::List(l) = &attr.meta else { return None };
+    let pred = CfgPred::parse(&l.tokens);
+    (pred.mentions_feature() || pred.mentions_platform() || pred.requires_test())
+        .then_some(pred)
+}
+
+/// Returns true for attributes which mark test functions, e.g. `#[test]` or
+/// `#[tokio::test]`
+fn is_test_attr(attr: &syn::Attribute) -> bool {
+    attr.path().segments.last().is_some_and(|s| s.ident == "test")
+}
+
+/// Returns the identifier bound by a simple pattern like `x` or `x: T`
+/// Methods of raw pointers which return a pointer of the same kind
+const RAW_PTR_DERIVE_METHODS: &[&str] = &[
+    "offset",
+    "add",
+    "sub",
+    "byte_offset",
+    "byte_add",
+    "byte_sub",
+    "wrapping_offset",
+    "wrapping_add",
+    "wrapping_sub",
+    "wrapping_byte_offset",
+    "wrapping_byte_add",
+    "wrapping_byte_sub",
+    "cast",
+];
+
+/// Returns the module of the methods of the raw pointer type in `core::ptr`,
+/// e.g. `mut_ptr` for `*mut u8`, or `None` if it isn't a raw pointer type
+fn raw_ptr_type(ty: &syn::Type) -> Option<&'static str> {
+    match ty {
+        syn::Type::Ptr(x) if x.mutability.is_some() => Some("mut_ptr"),
+        syn::Type::Ptr(_) => Some("const_ptr"),
+        syn::Type::Paren(x) => raw_ptr_type(&x.elem),
+        syn::Type::Group(x) => raw_ptr_type(&x.elem),
+        _ => None,
     }
 }
 
+fn pat_ident(p: &syn::Pat) -> Option<&syn::Ident> {
+    match p {
+        syn::Pat::Ident(x) => Some(&x.ident),
+        syn::Pat::Type(x) => pat_ident(&x.pat),
+        _ => None,
+    }
+}
+
+/// Hashes the tokens of a function's signature and body, so that changes to
+/// its formatting or comments don't count as changes to the function
+fn fn_tokens_hash(f_sig: &syn::Signature, body: &syn::Block) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(f_sig.to_token_stream().to_string());
+    hasher.update(body.to_token_stream().to_string());
+    format!("{:x}", hasher.finalize())
+}
+
+fn parse_file(filepath: &FilePath, src: &str) -> Result<syn::File> {
+    syn::parse_file(src)
+        .map_err(|source| ScanError::Parse { path: filepath.to_path_buf(), source })
+}
+
 /// Load the Rust file at the filepath and scan it (quick mode)
 pub fn scan_file_quick(
     crate_name: &str,
@@ -1232,7 +2696,20 @@ pub fn scan_file_quick(
     let mut file = File::open(filepath)?;
     let mut src = String::new();
     file.read_to_string(&mut src)?;
-    let syntax_tree = syn::parse_file(&src)?;
+    scan_source_quick(crate_name, filepath, &src, scan_results, sinks, enabled_cfg)
+}
+
+/// Scan the source of the Rust file at the filepath (quick mode), without
+/// reading the file itself
+pub fn scan_source_quick(
+    crate_name: &str,
+    filepath: &FilePath,
+    src: &str,
+    scan_results: &mut ScanResults,
+    sinks: HashSet<IdentPath>,
+    enabled_cfg: &HashMap<String, Vec<String>>,
+) -> Result<()> {
+    let syntax_tree = parse_file(filepath, src)?;
 
     let hacky_resolver = HackyResolver::new(crate_name, filepath);
 
@@ -1246,10 +2723,11 @@ pub fn scan_file_quick(
 }
 
 /// Load the Rust file at the filepath and scan it
+#[cfg(feature = "native")]
 pub fn scan_file(
     crate_name: &str,
     filepath: &FilePath,
-    resolver: &Resolver,
+    resolver: &ResolverSnapshot,
     scan_results: &mut ScanResults,
     sinks: HashSet<IdentPath>,
     enabled_cfg: &HashMap<String, Vec<String>>,
@@ -1260,10 +2738,11 @@ pub fn scan_file(
     let mut file = File::open(filepath)?;
     let mut src = String::new();
     file.read_to_string(&mut src)?;
-    let syntax_tree = syn::parse_file(&src)?;
+    let syntax_tree = parse_file(filepath, &src)?;
 
     // Initialize resolver
-    let file_resolver = FileResolver::new(crate_name, resolver, filepath)?;
+    let file_resolver = FileResolver::new(crate_name, resolver, filepath)
+        .map_err(|e| ScanError::resolution(filepath, e))?;
 
     // Initialize scanner
     let mut scanner = Scanner::new(filepath, file_resolver, scan_results, enabled_cfg);
@@ -1276,10 +2755,11 @@ pub fn scan_file(
 }
 
 /// Try to run scan_file, reporting any errors back to the user
+#[cfg(feature = "native")]
 pub fn try_scan_file(
     crate_name: &str,
     filepath: &FilePath,
-    resolver: &Resolver,
+    resolver: &ResolverSnapshot,
     scan_results: &mut ScanResults,
     sinks: HashSet<IdentPath>,
     enabled_cfg: &HashMap<String, Vec<String>>,
@@ -1299,66 +2779,601 @@ pub fn try_scan_file(
 }
 
 /// Scan the supplied crate with an additional list of sinks
+#[cfg(feature = "native")]
 pub fn scan_crate_with_sinks(
     crate_path: &FilePath,
     sinks: HashSet<IdentPath>,
     relevant_effects: &[EffectType],
     quick_mode: bool,
 ) -> Result<ScanResults> {
-    info!("Scanning crate: {:?}", crate_path);
+    scan_crate_with_features(
+        crate_path,
+        sinks,
+        relevant_effects,
+        quick_mode,
+        None,
+        None,
+        &ScanTargets::default(),
+    )
+}
 
-    // Make sure the path is a crate
-    if !crate_path.is_dir() {
-        return Err(anyhow!("Path is not a crate; not a directory: {:?}", crate_path));
+/// Scan the supplied crate with only the given features enabled (along with
+/// those they enable), skipping code excluded by `#[cfg(feature = "...")]`
+/// under them. If no features are given, the crate is scanned with its
+/// default configuration.
+///
+/// If a target triple is given, platform cfgs (e.g. `#[cfg(windows)]`) are
+/// evaluated for it instead of for the host. Tests, examples and benchmarks
+/// are only scanned if they are selected in `targets`.
+///
+/// NOTE: rust-analyzer still resolves names with the default features, so
+///       code only compiled under other features may resolve less well.
+#[cfg(feature = "native")]
+pub fn scan_crate_with_features(
+    crate_path: &FilePath,
+    sinks: HashSet<IdentPath>,
+    relevant_effects: &[EffectType],
+    quick_mode: bool,
+    features: Option<&[String]>,
+    target: Option<&str>,
+    targets: &ScanTargets,
+) -> Result<ScanResults> {
+    let setup = CrateScanSetup::new(crate_path, features, target, targets)?;
+    scan_setup_files(crate_path, &setup, sinks, relevant_effects, quick_mode, targets)
+}
+
+/// Scan the files of a crate whose scan is set up
+#[cfg(feature = "native")]
+fn scan_setup_files(
+    crate_path: &FilePath,
+    setup: &CrateScanSetup,
+    sinks: HashSet<IdentPath>,
+    relevant_effects: &[EffectType],
+    quick_mode: bool,
+    targets: &ScanTargets,
+) -> Result<ScanResults> {
+    // Scan the files in parallel, each with its own snapshot of the resolver,
+    // and merge the results in file order so they are deterministic.
+    // NOTE: Fields and statics holding sink functions are only detected as
+    //       indirect calls within the file that binds them.
+    let files =
+        setup.files.iter().map(|f| (f, setup.resolver.snapshot())).collect::<Vec<_>>();
+    let progress = setup.progress();
+    // The resolver itself can't be shared between threads
+    let (crate_name, enabled_cfg) = (&setup.crate_name, &setup.enabled_cfg);
+    let file_results = files
+        .into_par_iter()
+        .map(|(entry, snapshot)| {
+            let mut file_results = ScanResults::new();
+            try_scan_file(
+                crate_name,
+                entry.as_path(),
+                &snapshot,
+                &mut file_results,
+                sinks.clone(),
+                enabled_cfg,
+                quick_mode,
+            );
+            progress.inc();
+            file_results
+        })
+        .collect::<Vec<_>>();
+    progress.finish();
+
+    let mut scan_results = ScanResults::new();
+    for file_result in file_results {
+        scan_results.merge(file_result);
     }
+    for (dir, kind) in targets.dirs() {
+        scan_results.merge(scan_target_dir(
+            crate_path,
+            &setup.crate_name,
+            dir,
+            kind,
+            &sinks,
+            &setup.enabled_cfg,
+        ));
+    }
+
+    scan_results
+        .effects
+        .retain(|e| EffectType::matches_effect(relevant_effects, e.eff_type()));
+
+    finish_crate_scan(crate_path, &mut scan_results)?;
+    Ok(scan_results)
+}
+
+/// A crate loaded for repeated scans, e.g. by the scan server, which keeps
+/// rust-analyzer's analysis of the crate between scans instead of loading it
+/// again for each one.
+///
+/// The crate's files are re-read before each scan, so edits are picked up;
+/// after adding files or changing Cargo.toml, load the crate again.
+#[cfg(feature = "native")]
+pub struct LoadedCrate {
+    crate_path: PathBuf,
+    setup: CrateScanSetup,
+}
 
-    let mut cargo_toml_path = crate_path.to_path_buf();
-    cargo_toml_path.push("Cargo.toml");
-    if !cargo_toml_path.try_exists()? || !cargo_toml_path.is_file() {
-        return Err(anyhow!("Path is not a crate; missing Cargo.toml: {:?}", crate_path));
+#[cfg(feature = "native")]
+impl LoadedCrate {
+    /// Loads the crate, with its default features
+    pub fn load(crate_path: &FilePath) -> Result<Self> {
+        let setup = CrateScanSetup::new(crate_path, None, None, &ScanTargets::default())?;
+        Ok(Self { crate_path: crate_path.to_path_buf(), setup })
     }
 
-    let crate_name = util::load_cargo_toml(crate_path)?.crate_name;
+    pub fn crate_name(&self) -> &str {
+        &self.setup.crate_name
+    }
+
+    pub fn crate_path(&self) -> &FilePath {
+        &self.crate_path
+    }
+
+    /// The source files scanned, other than those of tests, examples and
+    /// benchmarks
+    pub fn files(&self) -> &[PathBuf] {
+        &self.setup.files
+    }
+
+    fn reload_file(&mut self, file: &FilePath) -> Result<()> {
+        self.setup
+            .resolver
+            .reload_file(file)
+            .map_err(|e| ScanError::resolution(file, e))?;
+        Ok(())
+    }
+
+    /// Scan the whole crate, like `scan_crate_with_sinks`
+    pub fn scan(
+        &mut self,
+        sinks: HashSet<IdentPath>,
+        relevant_effects: &[EffectType],
+    ) -> Result<ScanResults> {
+        for file in self.setup.files.clone() {
+            self.reload_file(&file)?;
+        }
+        let targets = ScanTargets::default();
+        scan_setup_files(
+            &self.crate_path,
+            &self.setup,
+            sinks,
+            relevant_effects,
+            false,
+            &targets,
+        )
+    }
+
+    /// Scan one of the crate's source files. Since the rest of the crate
+    /// isn't scanned, the results have no calls into the file from other
+    /// files, e.g. to tell which of its functions are reachable.
+    pub fn scan_file(
+        &mut self,
+        file: &FilePath,
+        sinks: HashSet<IdentPath>,
+        relevant_effects: &[EffectType],
+    ) -> Result<ScanResults> {
+        self.reload_file(file)?;
+        let mut scan_results = ScanResults::new();
+        scan_file(
+            &self.setup.crate_name,
+            file,
+            &self.setup.resolver.snapshot(),
+            &mut scan_results,
+            sinks,
+            &self.setup.enabled_cfg,
+        )?;
+        scan_results
+            .effects
+            .retain(|e| EffectType::matches_effect(relevant_effects, e.eff_type()));
+        Ok(scan_results)
+    }
+}
 
-    // TODO: this should *not* be created in the quick-mode case
-    let resolver = Resolver::new(crate_path)?;
+/// Scan the supplied crate like `scan_crate_with_features` with its default
+/// features, but pass the effects of each file to `on_effects` as soon as
+/// the file is scanned, instead of collecting them. Files are reported in
+/// the order they finish scanning.
+///
+/// The returned results have no effects, only the rest of the scan data
+/// (e.g. the call graph), so memory use doesn't grow with the number of
+/// effects.
+#[cfg(feature = "native")]
+pub fn scan_crate_streaming<F>(
+    crate_path: &FilePath,
+    sinks: HashSet<IdentPath>,
+    relevant_effects: &[EffectType],
+    quick_mode: bool,
+    target: Option<&str>,
+    targets: &ScanTargets,
+    mut on_effects: F,
+) -> Result<ScanResults>
+where
+    F: FnMut(Vec<EffectInstance>),
+{
+    let setup = CrateScanSetup::new(crate_path, None, target, targets)?;
 
     let mut scan_results = ScanResults::new();
+    let mut add_results = |results: ScanResults| {
+        // Merging may find the effects of indirect calls in earlier files
+        scan_results.merge(results);
+        let effects = std::mem::take(&mut scan_results.effects)
+            .into_iter()
+            .filter(|e| EffectType::matches_effect(relevant_effects, e.eff_type()))
+            .collect::<Vec<_>>();
+        if !effects.is_empty() {
+            on_effects(effects);
+        }
+    };
 
-    let enabled_cfg = resolver.get_cfg_options_for_crate(&crate_name).unwrap_or_default();
+    let files =
+        setup.files.iter().map(|f| (f, setup.resolver.snapshot())).collect::<Vec<_>>();
+    let progress = setup.progress();
+    let (tx, rx) = mpsc::channel();
+    std::thread::scope(|scope| {
+        let (crate_name, enabled_cfg, sinks, progress) =
+            (&setup.crate_name, &setup.enabled_cfg, &sinks, &progress);
+        scope.spawn(move || {
+            files.into_par_iter().for_each_with(tx, |tx, (entry, snapshot)| {
+                let mut file_results = ScanResults::new();
+                try_scan_file(
+                    crate_name,
+                    entry.as_path(),
+                    &snapshot,
+                    &mut file_results,
+                    sinks.clone(),
+                    enabled_cfg,
+                    quick_mode,
+                );
+                progress.inc();
+                // The receiver only hangs up once every file is scanned
+                let _ = tx.send(file_results);
+            });
+        });
+        for file_results in rx {
+            add_results(file_results);
+        }
+    });
+    progress.finish();
+    for (dir, kind) in targets.dirs() {
+        add_results(scan_target_dir(
+            crate_path,
+            &setup.crate_name,
+            dir,
+            kind,
+            &sinks,
+            &setup.enabled_cfg,
+        ));
+    }
+
+    finish_crate_scan(crate_path, &mut scan_results)?;
+    Ok(scan_results)
+}
+
+/// What scanning a crate needs before scanning its files: the resolver, the
+/// enabled cfg options and the files to scan
+#[cfg(feature = "native")]
+struct CrateScanSetup {
+    crate_name: String,
+    resolver: Resolver,
+    enabled_cfg: HashMap<String, Vec<String>>,
+    files: Vec<PathBuf>,
+}
+
+#[cfg(feature = "native")]
+impl CrateScanSetup {
+    /// A progress bar over the files to scan
+    fn progress(&self) -> Progress {
+        Progress::new(self.files.len(), "files", &format!("Scanning {}", self.crate_name))
+    }
+
+    fn new(
+        crate_path: &FilePath,
+        features: Option<&[String]>,
+        target: Option<&str>,
+        targets: &ScanTargets,
+    ) -> Result<Self> {
+        info!("Scanning crate: {:?}", crate_path);
+
+        // Make sure the path is a crate
+        if !crate_path.is_dir() {
+            return Err(ScanError::NotACrate {
+                path: crate_path.to_path_buf(),
+                reason: "not a directory",
+            });
+        }
+
+        let mut cargo_toml_path = crate_path.to_path_buf();
+        cargo_toml_path.push("Cargo.toml");
+        if !cargo_toml_path.try_exists()? || !cargo_toml_path.is_file() {
+            return Err(ScanError::NotACrate {
+                path: crate_path.to_path_buf(),
+                reason: "missing Cargo.toml",
+            });
+        }
+
+        let crate_name = util::load_cargo_toml(crate_path)?.crate_name;
+
+        // TODO: this should *not* be created in the quick-mode case
+        let resolver = Resolver::new(crate_path)
+            .map_err(|e| ScanError::resolution(crate_path, e))?;
+
+        let mut enabled_cfg =
+            resolver.get_cfg_options_for_crate(&crate_name).unwrap_or_default();
+        if let Some(features) = features {
+            let features = util::enabled_features(crate_path, features)?;
+            info!("Scanning with features: {}", feature_set_name(&features));
+            enabled_cfg.insert("feature".to_string(), features);
+        }
+        if let Some(target) = target {
+            info!("Scanning for target: {}", target);
+            enabled_cfg.retain(|key, _| !is_platform_key(key));
+            enabled_cfg.extend(
+                util::target_cfg(target)?
+                    .into_iter()
+                    .filter(|(key, _)| is_platform_key(key)),
+            );
+        }
+        if targets.tests {
+            enabled_cfg.insert("test".to_string(), Vec::new());
+        } else {
+            enabled_cfg.remove("test");
+        }
 
-    // TODO: For now, only walking through the src dir, but might want to
-    //       include others (e.g. might codegen in other dirs)
-    // If there is no src_dir, we walk through all .rs files in the crate.
+        // TODO: For now, only walking through the src dir, but might want to
+        //       include others (e.g. might codegen in other dirs)
+        // If there is no src_dir, we walk through all .rs files in the crate,
+        // except for those of its tests, examples and benchmarks.
 
-    let src_dir = crate_path.join(FilePath::new("src"));
-    let file_iter = if src_dir.is_dir() {
-        util::fs::walk_files_with_extension(&src_dir, "rs")
+        let src_dir = crate_path.join(FilePath::new("src"));
+        let file_iter = if src_dir.is_dir() {
+            util::fs::walk_files_with_extension(&src_dir, "rs")
+        } else {
+            info!("crate has no src dir; scanning all .rs files instead");
+            util::fs::walk_files_with_extension(crate_path, "rs")
+        };
+        let target_dirs = TARGET_DIRS.map(|dir| crate_path.join(dir));
+        let files = file_iter
+            .filter(|f| !target_dirs.iter().any(|dir| f.starts_with(dir)))
+            .collect();
+
+        Ok(Self { crate_name, resolver, enabled_cfg, files })
+    }
+}
+
+/// Records what is only known once all of the crate's files are scanned
+#[cfg(feature = "native")]
+fn finish_crate_scan(
+    crate_path: &FilePath,
+    scan_results: &mut ScanResults,
+) -> Result<()> {
+    let bin_files = util::bin_target_files(crate_path)?;
+    scan_results.set_main_fns(&bin_files);
+    let root_files = util::lib_target_file(crate_path)?.into_iter().chain(bin_files);
+    scan_results.set_crate_roots(&root_files.collect::<Vec<_>>());
+
+    scan_results.native_libs.extend(native_libs::crate_native_libs(crate_path)?);
+    scan_results.native_libs.sort();
+    scan_results.native_libs.dedup();
+
+    for (loc, lint) in scan_results.unsafe_code_overrides() {
+        warn!("Crate denies unsafe code, but it is set to {} at {}", lint, loc);
+    }
+    Ok(())
+}
+
+/// Scan the `.rs` files in a test, example or benchmark directory of the
+/// crate, tagging their effects with the kind of target.
+///
+/// Like the build script, these targets aren't part of the crate that
+/// rust-analyzer loads, so they are always scanned in quick mode. Their
+/// functions are placed under a module of the crate named after the
+/// directory, e.g. `my_crate::tests::integration` for `tests/integration.rs`.
+#[cfg(feature = "native")]
+fn scan_target_dir(
+    crate_path: &FilePath,
+    crate_name: &str,
+    dir: &str,
+    kind: TargetKind,
+    sinks: &HashSet<IdentPath>,
+    enabled_cfg: &HashMap<String, Vec<String>>,
+) -> ScanResults {
+    let mut results = ScanResults::new();
+    let target_dir = crate_path.join(dir);
+    if !target_dir.is_dir() {
+        return results;
+    }
+    info!("Scanning {} in {:?}", dir, target_dir);
+
+    // Tests and benchmarks are compiled with `cfg(test)`, examples aren't
+    let mut enabled_cfg = enabled_cfg.clone();
+    if kind == TargetKind::Example {
+        enabled_cfg.remove("test");
     } else {
-        info!("crate has no src dir; scanning all .rs files instead");
-        util::fs::walk_files_with_extension(crate_path, "rs")
+        enabled_cfg.insert("test".to_string(), Vec::new());
+    }
+
+    for file in util::fs::walk_files_with_extension(&target_dir, "rs") {
+        let rel_path = file.strip_prefix(&target_dir).unwrap_or(&file).with_extension("");
+        let mods = rel_path
+            .iter()
+            .map(|m| m.to_string_lossy().into_owned())
+            .filter(|m| m != "main" && m != "mod");
+        let module = std::iter::once(crate_name.to_string())
+            .chain(std::iter::once(dir.to_string()))
+            .chain(mods)
+            .collect::<Vec<_>>()
+            .join("::");
+
+        let mut file_results = ScanResults::new();
+        let scanned = scan_file_quick(
+            &module,
+            &file,
+            &mut file_results,
+            sinks.clone(),
+            &enabled_cfg,
+        );
+        if let Err(err) = scanned {
+            info!("Failed to scan file {} ({})", file.to_string_lossy(), err);
+            continue;
+        }
+        for e in file_results.effects.iter_mut() {
+            e.set_target_kind(kind);
+        }
+        // Examples and benchmarks without a test harness run from `main`.
+        // Paths replace the hyphens in the crate name with underscores.
+        let main_path = format!("{}::main", module).replace('-', "_");
+        let main = file_results.fn_locs.keys().find(|f| f.as_str() == main_path).cloned();
+        file_results.target_fns.extend(main);
+        results.merge(file_results);
+    }
+    results
+}
+
+/// Names a set of features in reports, e.g. `std,alloc`
+pub fn feature_set_name(features: &[String]) -> String {
+    if features.is_empty() {
+        "(no features)".to_string()
+    } else {
+        features.join(",")
+    }
+}
+
+/// Scan the supplied crate once under each of the given sets of features, and
+/// merge the results. The effects which aren't found under every set are
+/// recorded in `feature_gated_effects`, along with the sets they're found
+/// under. The LoC and other scan metadata are those of the first set.
+#[cfg(feature = "native")]
+pub fn scan_crate_feature_sets(
+    crate_path: &FilePath,
+    sinks: HashSet<IdentPath>,
+    relevant_effects: &[EffectType],
+    quick_mode: bool,
+    feature_sets: &[Vec<String>],
+    target: Option<&str>,
+    targets: &ScanTargets,
+) -> Result<ScanResults> {
+    let mut results: Option<ScanResults> = None;
+    let mut found_under: HashMap<EffectInstance, Vec<String>> = HashMap::new();
+    for features in feature_sets {
+        let set_results = scan_crate_with_features(
+            crate_path,
+            sinks.clone(),
+            relevant_effects,
+            quick_mode,
+            Some(features),
+            target,
+            targets,
+        )?;
+        for e in &set_results.effects {
+            found_under.entry(e.clone()).or_default().push(feature_set_name(features));
+        }
+        match &mut results {
+            Some(results) => results.merge_feature_set(set_results),
+            None => results = Some(set_results),
+        }
+    }
+
+    let mut results = results.unwrap_or_default();
+    results.feature_gated_effects = found_under
+        .into_iter()
+        .filter(|(_, sets)| sets.len() < feature_sets.len())
+        .collect();
+    Ok(results)
+}
+
+/// Scan the crate's build script, if it has one. Its effects are tagged with
+/// the `build` provenance, since it runs on the developer's machine at
+/// compile time.
+///
+/// Like the expanded source, the build script isn't part of the crate that
+/// rust-analyzer loads, so it is always scanned in quick mode. Its functions
+/// are placed under a `build_script_build` module of the crate, so that they
+/// don't clash with those of a binary target, and its `main` function counts
+/// as an entry point.
+pub fn scan_build_script(
+    crate_path: &FilePath,
+    sinks: HashSet<IdentPath>,
+    relevant_effects: &[EffectType],
+) -> Result<Option<ScanResults>> {
+    let Some(build_rs) = util::build_script_file(crate_path)? else {
+        return Ok(None);
     };
+    info!("Scanning build script: {:?}", build_rs);
 
-    for entry in file_iter {
-        try_scan_file(
+    let crate_name = util::load_cargo_toml(crate_path)?.crate_name;
+    let build_mod = format!("{}::{}", crate_name, BUILD_SCRIPT_MOD);
+    let mut results = ScanResults::new();
+    scan_file_quick(&build_mod, &build_rs, &mut results, sinks, &HashMap::new())?;
+
+    results
+        .effects
+        .retain(|e| EffectType::matches_effect(relevant_effects, e.eff_type()));
+    for e in results.effects.iter_mut() {
+        e.set_provenance(Provenance::Build);
+    }
+    // Paths replace the hyphens in the crate name with underscores
+    let main_path = format!("{}::main", build_mod).replace('-', "_");
+    let build_main = results.fn_locs.keys().find(|f| f.as_str() == main_path).cloned();
+    results.pub_fns.extend(build_main);
+
+    Ok(Some(results))
+}
+
+/// Scan the crate whose files are in `fs`, with paths relative to the crate
+/// root. Since neither rust-analyzer nor the file system are needed, this is
+/// the scan available when cargo-scan is built without the `native` feature,
+/// e.g. for WebAssembly.
+///
+/// The crate is scanned in quick mode with its default features, and only
+/// platform-independent code is scanned. Its tests, examples, benchmarks and
+/// build script aren't scanned.
+pub fn scan_crate_in_memory(
+    fs: &MemFs,
+    sinks: HashSet<IdentPath>,
+    relevant_effects: &[EffectType],
+) -> Result<ScanResults> {
+    let manifest = fs.read_to_string(FilePath::new("Cargo.toml"))?;
+    let crate_name = util::parse_cargo_toml(manifest)?.crate_name;
+    let features = util::manifest_enabled_features(manifest, &["default".to_string()])?;
+    let enabled_cfg = HashMap::from([("feature".to_string(), features)]);
+
+    let src_dir = FilePath::new("src");
+    let root_dir = if fs.is_dir(src_dir) { src_dir } else { FilePath::new("") };
+    let mut scan_results = ScanResults::new();
+    for file in fs
+        .files_with_extension(root_dir, "rs")
+        .filter(|f| !TARGET_DIRS.iter().any(|dir| f.starts_with(dir)))
+    {
+        let scanned = scan_source_quick(
             &crate_name,
-            entry.as_path(),
-            &resolver,
+            file,
+            fs.read_to_string(file)?,
             &mut scan_results,
             sinks.clone(),
             &enabled_cfg,
-            quick_mode,
         );
+        if let Err(err) = scanned {
+            info!("Failed to scan file {} ({})", file.to_string_lossy(), err);
+        }
     }
 
     scan_results
         .effects
         .retain(|e| EffectType::matches_effect(relevant_effects, e.eff_type()));
 
+    let cargo_toml = toml::from_str(manifest).context("Couldn't parse Cargo.toml")?;
+    let bin_files = fs.bin_target_files(&cargo_toml);
+    scan_results.set_main_fns(&bin_files);
+    let root_files = fs.lib_target_file(&cargo_toml).into_iter().chain(bin_files);
+    scan_results.set_crate_roots(&root_files.collect::<Vec<_>>());
     Ok(scan_results)
 }
 
-/// Scan the supplied crate
+/// Scan the supplied crate. See `ScanConfig` (in `scan_config`) for control
+/// over the other options of the scan.
+#[cfg(feature = "native")]
 pub fn scan_crate(
     crate_path: &FilePath,
     relevant_effects: &[EffectType],
@@ -1366,3 +3381,996 @@ pub fn scan_crate(
 ) -> Result<ScanResults> {
     scan_crate_with_sinks(crate_path, HashSet::new(), relevant_effects, quick_mode)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::effect::DEFAULT_EFFECT_TYPES;
+    use crate::fail_on::FailOn;
+    use crate::sink::{Severity, SinkCategory};
+    use proc_macro2::Span;
+
+    fn add_fn(res: &mut ScanResults, name: &str, vis: Visibility) {
+        let src_loc = SrcLoc::default();
+        res.add_fn_dec(FnDec { src_loc, fn_name: CanonicalPath::new(name), vis });
+    }
+
+    fn add_call(res: &mut ScanResults, caller: &str, callee: &str) {
+        let (caller, callee) = (CanonicalPath::new(caller), CanonicalPath::new(callee));
+        res.add_call(&caller, &callee, SrcLoc::default());
+    }
+
+    fn effect_in(caller: &str) -> EffectInstance {
+        let span = syn::Ident::new("x", Span::call_site());
+        let callee = CanonicalPath::new("std::process::exit");
+        EffectInstance::new_effect(
+            FilePath::new("src/lib.rs"),
+            CanonicalPath::new(caller),
+            callee,
+            &span,
+            Effect::UnsafeCall(CanonicalPath::new("std::process::exit")),
+        )
+    }
+
+    #[test]
+    fn test_dead_code_effects() {
+        let mut res = ScanResults::new();
+        add_fn(&mut res, "c::api", Visibility::Public);
+        add_fn(&mut res, "c::helper", Visibility::Private);
+        add_fn(&mut res, "c::unused", Visibility::Private);
+        add_fn(&mut res, "c::S::fmt", Visibility::Private);
+        add_fn(&mut res, "c::fmt_helper", Visibility::Private);
+        res.trait_impl_fns.insert(CanonicalPath::new("c::S::fmt"));
+        add_call(&mut res, "c::api", "c::helper");
+        add_call(&mut res, "c::S::fmt", "c::fmt_helper");
+        add_call(&mut res, "c::unused", "c::helper");
+        for f in ["c::helper", "c::unused", "c::fmt_helper"] {
+            res.effects.push(effect_in(f));
+        }
+
+        let reachable = res.reachable_fns();
+        assert!(reachable.contains(&CanonicalPath::new("c::helper")));
+        assert!(reachable.contains(&CanonicalPath::new("c::fmt_helper")));
+        assert!(!reachable.contains(&CanonicalPath::new("c::unused")));
+
+        let dead = res.dead_code_effects();
+        assert_eq!(dead.len(), 1);
+        assert_eq!(dead[0].caller_path(), "c::unused");
+    }
+
+    #[test]
+    fn test_callback_effects_not_dead() {
+        let mut res = ScanResults::new();
+        let filepath = FilePath::new("data/test-packages/fns-closures/src/callbacks.rs");
+        let sinks = Sink::default_sinks();
+        scan_file_quick("fns_closures", filepath, &mut res, sinks, &HashMap::new())
+            .unwrap();
+
+        let sink_callers = res
+            .effects
+            .iter()
+            .filter(|e| matches!(e.eff_type(), Effect::SinkCall(_)))
+            .map(|e| e.caller_path())
+            .collect::<HashSet<_>>();
+        for f in ["cleanup", "shell_output", "unused"] {
+            assert!(
+                sink_callers.contains(format!("fns_closures::callbacks::{}", f).as_str())
+            );
+        }
+        // Functions only called through function pointers or in macros aren't
+        // dead code
+        let dead =
+            res.dead_code_effects().iter().map(|e| e.caller_path()).collect::<Vec<_>>();
+        assert!(!dead.is_empty());
+        assert!(dead.iter().all(|f| *f == "fns_closures::callbacks::unused"));
+    }
+
+    /// Scans a crate with the given source files in quick mode, and returns
+    /// the callees of its effects
+    fn scan_crate_files(name: &str, files: &[(&str, &str)]) -> Vec<String> {
+        let tmp = std::env::temp_dir().join(name);
+        let _ = std::fs::remove_dir_all(&tmp);
+        std::fs::create_dir_all(tmp.join("src")).unwrap();
+        std::fs::write(
+            tmp.join("Cargo.toml"),
+            "[package]\nname = \"walked\"\nversion = \"0.1.0\"\nedition = \"2021\"\n",
+        )
+        .unwrap();
+        for (file, src) in files {
+            std::fs::write(tmp.join(file), src).unwrap();
+        }
+        let res = scan_crate_with_sinks(
+            &tmp,
+            Sink::default_sinks(),
+            DEFAULT_EFFECT_TYPES,
+            true,
+        )
+        .unwrap();
+        std::fs::remove_dir_all(&tmp).unwrap();
+        res.effects.iter().map(|e| e.callee_path().to_string()).collect()
+    }
+
+    #[test]
+    fn test_hidden_module_scanned() {
+        let callees = scan_crate_files(
+            "cargo-scan-test-hidden-module",
+            &[
+                ("src/lib.rs", "#[path = \".hidden.rs\"]\nmod hidden;\n"),
+                (
+                    "src/.hidden.rs",
+                    "pub fn wipe() { let _ = std::fs::remove_dir_all(\"/\"); }\n",
+                ),
+            ],
+        );
+        assert!(callees.iter().any(|c| c == "std::fs::remove_dir_all"));
+    }
+
+    #[test]
+    fn test_gitignored_module_scanned() {
+        let callees = scan_crate_files(
+            "cargo-scan-test-gitignored-module",
+            &[
+                ("src/lib.rs", "mod evil;\n"),
+                ("src/.gitignore", "evil.rs\n"),
+                (
+                    "src/evil.rs",
+                    "pub fn run() { let _ = std::process::Command::new(\"sh\"); }\n",
+                ),
+            ],
+        );
+        assert!(callees.iter().any(|c| c == "std::process::Command::new"));
+    }
+
+    #[test]
+    fn test_main_reachable_effects() {
+        let mut res = ScanResults::new();
+        add_fn(&mut res, "c::main", Visibility::Private);
+        add_fn(&mut res, "c::run", Visibility::Private);
+        add_fn(&mut res, "c::util::unused", Visibility::Public);
+        add_call(&mut res, "c::main", "c::run");
+        for f in ["c::run", "c::util::unused"] {
+            res.effects.push(effect_in(f));
+        }
+
+        // SrcLoc::default() places every function in the same file
+        let loc = SrcLoc::default();
+        res.set_main_fns(&[loc.dir().join(loc.file())]);
+        assert_eq!(res.main_fns, HashSet::from([CanonicalPath::new("c::main")]));
+
+        let (reachable, unreachable) = res.partition_main_reachable();
+        assert_eq!(reachable.len(), 1);
+        assert_eq!(reachable[0].caller_path(), "c::run");
+        assert_eq!(unreachable.len(), 1);
+        assert_eq!(unreachable[0].caller_path(), "c::util::unused");
+    }
+
+    #[test]
+    fn test_transitive_effects_through_cycles() {
+        let mut res = ScanResults::new();
+        add_fn(&mut res, "c::api", Visibility::Public);
+        add_fn(&mut res, "c::even", Visibility::Private);
+        add_fn(&mut res, "c::odd", Visibility::Private);
+        add_fn(&mut res, "c::leaf", Visibility::Private);
+        add_call(&mut res, "c::api", "c::even");
+        add_call(&mut res, "c::even", "c::odd");
+        add_call(&mut res, "c::odd", "c::even");
+        add_call(&mut res, "c::odd", "c::leaf");
+        res.effects.push(effect_in("c::odd"));
+        res.effects.push(effect_in("c::leaf"));
+
+        let transitive = res.transitive_effects(&res.effects);
+        for f in ["c::api", "c::even", "c::odd"] {
+            assert_eq!(transitive[&CanonicalPath::new(f)].len(), 2);
+        }
+        assert_eq!(transitive[&CanonicalPath::new("c::leaf")].len(), 1);
+
+        let centrality = res.caller_centrality();
+        assert_eq!(centrality[&CanonicalPath::new("c::api")], 0);
+        assert_eq!(centrality[&CanonicalPath::new("c::even")], 2);
+        assert_eq!(centrality[&CanonicalPath::new("c::leaf")], 3);
+    }
+
+    #[test]
+    fn test_example_call_path() {
+        let mut res = ScanResults::new();
+        add_fn(&mut res, "c::api", Visibility::Public);
+        add_fn(&mut res, "c::short", Visibility::Private);
+        add_fn(&mut res, "c::long1", Visibility::Private);
+        add_fn(&mut res, "c::long2", Visibility::Private);
+        add_fn(&mut res, "c::target", Visibility::Private);
+        add_fn(&mut res, "c::unused", Visibility::Private);
+        add_call(&mut res, "c::api", "c::long1");
+        add_call(&mut res, "c::long1", "c::long2");
+        add_call(&mut res, "c::long2", "c::target");
+        add_call(&mut res, "c::api", "c::short");
+        add_call(&mut res, "c::short", "c::target");
+
+        let path = res.example_call_path(&effect_in("c::target")).unwrap();
+        let callers = path.iter().map(|i| i.caller_path.as_str()).collect::<Vec<_>>();
+        assert_eq!(callers, vec!["c::api", "c::short", "c::target"]);
+        assert!(res.example_call_path(&effect_in("c::unused")).is_none());
+    }
+
+    #[test]
+    fn test_caller_tree() {
+        let mut res = ScanResults::new();
+        add_fn(&mut res, "c::api", Visibility::Public);
+        add_fn(&mut res, "c::helper", Visibility::Private);
+        add_fn(&mut res, "c::even", Visibility::Private);
+        add_fn(&mut res, "c::odd", Visibility::Private);
+        add_fn(&mut res, "c::target", Visibility::Private);
+        add_call(&mut res, "c::api", "c::helper");
+        add_call(&mut res, "c::helper", "c::target");
+        add_call(&mut res, "c::even", "c::target");
+        add_call(&mut res, "c::odd", "c::even");
+        add_call(&mut res, "c::even", "c::odd");
+
+        let tree = res.caller_tree(&CanonicalPath::new("c::target"));
+        let callers =
+            tree.iter().map(|t| t.call.caller_path.as_str()).collect::<Vec<_>>();
+        assert_eq!(callers, vec!["c::even", "c::helper"]);
+
+        // The recursion between even and odd stops at the repeated caller
+        let odd = &tree[0].callers[0];
+        assert_eq!(odd.call.caller_path.as_str(), "c::odd");
+        assert!(odd.callers[0].repeated && odd.callers[0].callers.is_empty());
+
+        // The tree stops at the public function
+        let api = &tree[1].callers[0];
+        assert_eq!(api.call.caller_path.as_str(), "c::api");
+        assert!(api.is_entry && api.callers.is_empty());
+    }
+
+    #[test]
+    fn test_effect_signatures() {
+        let mut res = ScanResults::new();
+        add_fn(&mut res, "c::api", Visibility::Public);
+        add_fn(&mut res, "c::even", Visibility::Private);
+        add_fn(&mut res, "c::odd", Visibility::Private);
+        add_call(&mut res, "c::api", "c::even");
+        add_call(&mut res, "c::even", "c::odd");
+        add_call(&mut res, "c::odd", "c::even");
+        add_call(&mut res, "c::odd", "dep::spawn");
+        res.effects.push(effect_in("c::even"));
+
+        let mut dep_sigs = HashMap::new();
+        dep_sigs.insert(
+            CanonicalPath::new("dep::spawn"),
+            EffectSignature::from([EffectType::FFICall]),
+        );
+        let sigs = res.effect_signatures(&dep_sigs);
+        let expected =
+            EffectSignature::from([EffectType::UnsafeCall, EffectType::FFICall]);
+        for f in ["c::api", "c::even", "c::odd"] {
+            assert_eq!(sigs[&CanonicalPath::new(f)], expected);
+        }
+    }
+
+    #[test]
+    fn test_indirect_sink_calls() {
+        let mut res = ScanResults::new();
+        let filepath =
+            FilePath::new("data/test-packages/fns-closures/src/fn_ptr_sinks.rs");
+        let sinks = Sink::default_sinks();
+        scan_file_quick("fns_closures", filepath, &mut res, sinks, &HashMap::new())
+            .unwrap();
+
+        let mut indirect = res
+            .effects
+            .iter()
+            .filter_map(|e| match e.eff_type() {
+                Effect::IndirectCall(origin) => Some((e.caller_path(), origin.as_str())),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        indirect.sort();
+        let expected = ["call_field", "call_local", "call_table"].map(|f| {
+            (format!("fns_closures::fn_ptr_sinks::{}", f), "std::fs::remove_file")
+        });
+        let expected = expected.iter().map(|(f, o)| (f.as_str(), *o)).collect::<Vec<_>>();
+        assert_eq!(indirect, expected);
+    }
+
+    #[test]
+    fn test_indirect_sink_calls_across_files() {
+        // The calls are scanned before the file binding the field and static
+        let scan = |file| {
+            let mut res = ScanResults::new();
+            let filepath = FilePath::new(file);
+            let sinks = Sink::default_sinks();
+            scan_file_quick("fns_closures", filepath, &mut res, sinks, &HashMap::new())
+                .unwrap();
+            res
+        };
+        let mut res = scan("data/test-packages/fns-closures/src/fn_ptr_uses.rs");
+        assert_eq!(res.fn_ptr_calls.len(), 2);
+        res.merge(scan("data/test-packages/fns-closures/src/fn_ptr_sinks.rs"));
+
+        let mut indirect = res
+            .effects
+            .iter()
+            .filter_map(|e| match e.eff_type() {
+                Effect::IndirectCall(origin) => Some((e.caller_path(), origin.as_str())),
+                _ => None,
+            })
+            .filter(|(f, _)| f.starts_with("fns_closures::fn_ptr_uses"))
+            .collect::<Vec<_>>();
+        indirect.sort();
+        let expected = ["call_other_field", "call_other_table"].map(|f| {
+            (format!("fns_closures::fn_ptr_uses::{}", f), "std::fs::remove_file")
+        });
+        let expected = expected.iter().map(|(f, o)| (f.as_str(), *o)).collect::<Vec<_>>();
+        assert_eq!(indirect, expected);
+        assert!(res.fn_ptr_calls.is_empty());
+    }
+
+    #[test]
+    #[cfg(feature = "native")]
+    fn test_indirect_sink_call_reporting() {
+        use crate::report::EffectReport;
+
+        let mut res = ScanResults::new();
+        let filepath =
+            FilePath::new("data/test-packages/fns-closures/src/indirect_process.rs");
+        let sinks = Sink::default_sinks();
+        scan_file_quick("fns_closures", filepath, &mut res, sinks, &HashMap::new())
+            .unwrap();
+
+        let eff = res
+            .effects
+            .iter()
+            .find(|e| matches!(e.eff_type(), Effect::IndirectCall(_)))
+            .unwrap();
+        // Classified by the sink the table holds, not the table
+        assert_eq!(eff.pattern().unwrap().as_str(), "libc::system");
+        assert_eq!(eff.sink_category(), Some(SinkCategory::Process));
+        assert!("sink:process".parse::<FailOn>().unwrap().matches(eff));
+        assert!(eff.to_csv().contains("[IndirectCall] libc::system"));
+
+        let report = EffectReport::from(eff);
+        assert_eq!(report.origin.as_deref(), Some("libc::system"));
+        assert_eq!(report.severity, Some(Severity::High));
+    }
+
+    #[test]
+    fn test_unsafe_code_lints() {
+        let mut res = ScanResults::new();
+        let filepath = FilePath::new("data/test-packages/unsafe-lint-ex/src/lib.rs");
+        let sinks = Sink::default_sinks();
+        scan_file_quick("unsafe_lint_ex", filepath, &mut res, sinks, &HashMap::new())
+            .unwrap();
+
+        // The lint only applies to the whole crate once lib.rs is its root
+        assert_eq!(res.crate_unsafe_lint, None);
+        let lib_rs = PathBuf::from("data/test-packages/unsafe-lint-ex/src/lib.rs");
+        res.set_crate_roots(std::slice::from_ref(&lib_rs));
+        assert_eq!(res.crate_unsafe_lint, Some(UnsafeCodeLint::Deny));
+        let overrides = res.unsafe_code_overrides();
+        assert_eq!(overrides.len(), 2);
+        assert!(overrides.iter().all(|(_, l)| *l == UnsafeCodeLint::Allow));
+        assert!(!res.is_forbid_unsafe_without_sinks());
+
+        // A binary target which doesn't deny unsafe code lets the crate use it
+        let main_rs = PathBuf::from("data/test-packages/unsafe-lint-ex/src/main.rs");
+        res.set_crate_roots(&[lib_rs, main_rs]);
+        assert_eq!(res.crate_unsafe_lint, None);
+        assert!(res.unsafe_code_overrides().is_empty());
+    }
+
+    #[test]
+    fn test_transmute_effects() {
+        let mut res = ScanResults::new();
+        let filepath =
+            FilePath::new("data/test-packages/unsafe-test/src/transmute_ex.rs");
+        let sinks = Sink::default_sinks();
+        scan_file_quick("unsafe_test", filepath, &mut res, sinks, &HashMap::new())
+            .unwrap();
+
+        let mut transmutes = res
+            .effects
+            .iter()
+            .filter_map(|e| match e.eff_type() {
+                Effect::Transmute(f) => Some((e.caller_path(), f.as_str())),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        transmutes.sort();
+        assert_eq!(
+            transmutes,
+            [
+                ("unsafe_test::transmute_ex::float_bits", "std::mem::transmute"),
+                ("unsafe_test::transmute_ex::fn_from_ptr", "std::mem::transmute"),
+                ("unsafe_test::transmute_ex::read_header", "std::mem::transmute_copy"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_send_sync_impl_effects() {
+        let mut res = ScanResults::new();
+        let filepath =
+            FilePath::new("data/test-packages/unsafe-test/src/send_sync_ex.rs");
+        let sinks = Sink::default_sinks();
+        scan_file_quick("unsafe_test", filepath, &mut res, sinks, &HashMap::new())
+            .unwrap();
+
+        let mut impls = res
+            .effects
+            .iter()
+            .filter_map(|e| match e.eff_type() {
+                Effect::SendSyncImpl(marker) => Some((e.callee_path(), marker.as_str())),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        impls.sort();
+        assert_eq!(
+            impls,
+            [
+                ("unsafe_test::send_sync_ex::RawHandle", "std::marker::Send"),
+                ("unsafe_test::send_sync_ex::RawHandle", "std::marker::Sync"),
+                ("unsafe_test::send_sync_ex::Shared", "std::marker::Sync"),
+            ]
+        );
+        assert_eq!(res.unsafe_impls.get_instances(), 4);
+        // The impls are at module scope, so they're never dead code
+        assert!(res.dead_code_effects().is_empty());
+    }
+
+    #[test]
+    fn test_ffi_export_effects() {
+        let mut res = ScanResults::new();
+        let filepath =
+            FilePath::new("data/test-packages/unsafe-test/src/ffi_export_ex.rs");
+        let sinks = Sink::default_sinks();
+        scan_file_quick("unsafe_test", filepath, &mut res, sinks, &HashMap::new())
+            .unwrap();
+
+        let mut exports = res
+            .effects
+            .iter()
+            .filter_map(|e| match e.eff_type() {
+                Effect::FFIExport(symbol) => Some((e.caller_path(), symbol.as_deref())),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        exports.sort();
+        assert_eq!(
+            exports,
+            [
+                ("unsafe_test::ffi_export_ex::Plugin::version", Some("plugin_version")),
+                ("unsafe_test::ffi_export_ex::callback", None),
+                ("unsafe_test::ffi_export_ex::init", Some("plugin_init")),
+                ("unsafe_test::ffi_export_ex::plugin_free", Some("plugin_free")),
+                ("unsafe_test::ffi_export_ex::shutdown", Some("shutdown")),
+            ]
+        );
+
+        // Exported functions are entry points even though they aren't pub.
+        // Quick mode doesn't resolve calls within a module to their full
+        // path, so add the call to cleanup as the full resolver finds it.
+        add_call(
+            &mut res,
+            "unsafe_test::ffi_export_ex::shutdown",
+            "unsafe_test::ffi_export_ex::cleanup",
+        );
+        let reachable =
+            res.reachable_fns().iter().map(|f| f.to_string()).collect::<HashSet<_>>();
+        assert!(reachable.contains("unsafe_test::ffi_export_ex::init"));
+        assert!(reachable.contains("unsafe_test::ffi_export_ex::cleanup"));
+        assert!(!reachable.contains("unsafe_test::ffi_export_ex::not_exported"));
+    }
+
+    #[test]
+    fn test_env_effects() {
+        let mut res = ScanResults::new();
+        let filepath = FilePath::new("data/test-packages/unsafe-test/src/env_ex.rs");
+        let sinks = Sink::default_sinks();
+        scan_file_quick("unsafe_test", filepath, &mut res, sinks, &HashMap::new())
+            .unwrap();
+
+        let mut env_effects = res
+            .effects
+            .iter()
+            .filter(|e| e.sink_category() == Some(SinkCategory::Env))
+            .map(|e| (e.caller_path(), e.callee_path()))
+            .collect::<Vec<_>>();
+        env_effects.sort();
+        assert_eq!(
+            env_effects,
+            [
+                ("unsafe_test::env_ex", "std::env"),
+                ("unsafe_test::env_ex::build_profile", "std::option_env"),
+                ("unsafe_test::env_ex::first_arg", "std::env::args"),
+                ("unsafe_test::env_ex::read_token", "std::env::var"),
+                ("unsafe_test::env_ex::set_debug", "std::env::set_var"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_uninit_mem_effects() {
+        let mut res = ScanResults::new();
+        let filepath = FilePath::new("data/test-packages/unsafe-test/src/uninit_ex.rs");
+        let sinks = Sink::default_sinks();
+        scan_file_quick("unsafe_test", filepath, &mut res, sinks, &HashMap::new())
+            .unwrap();
+
+        let mut uninits = res
+            .effects
+            .iter()
+            .filter_map(|e| match e.eff_type() {
+                Effect::UninitMem(f) => Some((e.caller_path(), f.as_str())),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        uninits.sort();
+        assert_eq!(
+            uninits,
+            [
+                (
+                    "unsafe_test::uninit_ex::init_later",
+                    "std::mem::MaybeUninit::assume_init"
+                ),
+                ("unsafe_test::uninit_ex::old_uninit", "std::mem::uninitialized"),
+                ("unsafe_test::uninit_ex::zeroed_buf", "std::mem::zeroed"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_raw_construct_effects() {
+        let mut res = ScanResults::new();
+        let filepath =
+            FilePath::new("data/test-packages/unsafe-test/src/raw_construct_ex.rs");
+        let sinks = Sink::default_sinks();
+        scan_file_quick("unsafe_test", filepath, &mut res, sinks, &HashMap::new())
+            .unwrap();
+
+        let mut constructs = res
+            .effects
+            .iter()
+            .filter_map(|e| match e.eff_type() {
+                Effect::RawConstruct(f) => Some((e.caller_path(), f.as_str())),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        constructs.sort();
+        assert_eq!(
+            constructs,
+            [
+                ("unsafe_test::raw_construct_ex::boxed", "Box::from_raw"),
+                ("unsafe_test::raw_construct_ex::bytes", "std::slice::from_raw_parts"),
+                ("unsafe_test::raw_construct_ex::name", "std::ffi::CStr::from_ptr"),
+                ("unsafe_test::raw_construct_ex::rebuild", "Vec::from_raw_parts"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_ptr_access_effects() {
+        let mut res = ScanResults::new();
+        let filepath =
+            FilePath::new("data/test-packages/unsafe-test/src/ptr_access_ex.rs");
+        let sinks = Sink::default_sinks();
+        scan_file_quick("unsafe_test", filepath, &mut res, sinks, &HashMap::new())
+            .unwrap();
+
+        let mut accesses = res
+            .effects
+            .iter()
+            .map(|e| (e.caller_path(), EffectType::from(e.eff_type())))
+            .collect::<Vec<_>>();
+        accesses.sort_by_key(|(caller, _)| *caller);
+        assert_eq!(
+            accesses,
+            [
+                ("unsafe_test::ptr_access_ex::copy_buf", EffectType::RawPointerWrite),
+                ("unsafe_test::ptr_access_ex::read_first", EffectType::RawPointer),
+                ("unsafe_test::ptr_access_ex::read_second", EffectType::PtrOffset),
+                ("unsafe_test::ptr_access_ex::read_second", EffectType::RawPointer),
+                ("unsafe_test::ptr_access_ex::write_at", EffectType::RawPointerWrite),
+                ("unsafe_test::ptr_access_ex::write_at", EffectType::PtrOffset),
+                ("unsafe_test::ptr_access_ex::write_first", EffectType::RawPointerWrite),
+                ("unsafe_test::ptr_access_ex::zero_last", EffectType::RawPointerWrite),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_assigned_places() {
+        let assigned = |s: &str| {
+            let syn::Expr::Assign(x) = syn::parse_str::<syn::Expr>(s).unwrap() else {
+                panic!("not an assignment: {}", s)
+            };
+            assigned_places(&x.left)
+                .iter()
+                .map(|p| p.to_token_stream().to_string())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(assigned("*p = 1"), ["* p"]);
+        assert_eq!(assigned("(*p).f = 1"), ["(* p) . f", "(* p)", "* p"]);
+        assert_eq!(assigned("u.f = 1"), ["u . f", "u"]);
+        assert_eq!(assigned("x[*p] = 1"), ["x [* p]", "x"]);
+        assert_eq!(assigned("x = *p"), ["x"]);
+    }
+
+    #[test]
+    fn test_dyn_load_effects() {
+        let mut res = ScanResults::new();
+        let filepath = FilePath::new("data/test-packages/unsafe-test/src/dyn_load_ex.rs");
+        let sinks = Sink::default_sinks();
+        scan_file_quick("unsafe_test", filepath, &mut res, sinks, &HashMap::new())
+            .unwrap();
+
+        let mut loads = res
+            .effects
+            .iter()
+            .filter_map(|e| match e.eff_type() {
+                Effect::DynLoad(f, lib) => {
+                    Some((e.caller_path(), f.as_str(), lib.as_deref()))
+                }
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        loads.sort();
+        assert_eq!(
+            loads,
+            [
+                ("unsafe_test::dyn_load_ex::dlopen_lib", "libc::dlopen", None),
+                (
+                    "unsafe_test::dyn_load_ex::load_plugin",
+                    "libloading::Library::new",
+                    Some("libplugin.so")
+                ),
+                (
+                    "unsafe_test::dyn_load_ex::load_plugin_from",
+                    "libloading::Library::new",
+                    None
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_inline_asm_effects() {
+        let mut res = ScanResults::new();
+        let filepath = FilePath::new("data/test-packages/unsafe-test/src/asm_ex.rs");
+        let sinks = Sink::default_sinks();
+        scan_file_quick("unsafe_test", filepath, &mut res, sinks, &HashMap::new())
+            .unwrap();
+
+        let mut asm = res
+            .effects
+            .iter()
+            .filter(|e| matches!(e.eff_type(), Effect::InlineAsm))
+            .map(|e| e.caller_path())
+            .collect::<Vec<_>>();
+        asm.sort();
+        assert_eq!(asm, ["unsafe_test::asm_ex", "unsafe_test::asm_ex::add_one"]);
+        assert_eq!(res.skipped_macros.get_instances(), 1);
+    }
+
+    #[test]
+    fn test_merge_feature_set() {
+        let filepath = FilePath::new("data/test-packages/cfg-ex/src/main.rs");
+        let scan_with = |features: &[&str]| {
+            let features = features.iter().map(|f| f.to_string()).collect();
+            let enabled_cfg = HashMap::from([("feature".to_string(), features)]);
+            let mut res = ScanResults::new();
+            let sinks = Sink::default_sinks();
+            scan_file_quick("cfg_ex", filepath, &mut res, sinks, &enabled_cfg).unwrap();
+            res
+        };
+        let effect_lines = |res: &ScanResults| {
+            let mut lines = res
+                .effects
+                .iter()
+                .map(|e| (e.caller_path().to_string(), e.call_loc().start_line()))
+                .collect::<Vec<_>>();
+            lines.sort();
+            lines
+        };
+
+        let mut res = scan_with(&[]);
+        assert_eq!(
+            effect_lines(&res),
+            [("cfg_ex::foo1".to_string(), 14), ("cfg_ex::foo2".to_string(), 24)]
+        );
+        res.merge_feature_set(scan_with(&["extra"]));
+        assert_eq!(
+            effect_lines(&res),
+            [
+                ("cfg_ex::foo1".to_string(), 14),
+                ("cfg_ex::foo2".to_string(), 19),
+                ("cfg_ex::foo2".to_string(), 24),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_cfg_gates() {
+        let filepath = FilePath::new("data/test-packages/cfg-ex/src/main.rs");
+        let enabled_cfg =
+            HashMap::from([("feature".to_string(), vec!["extra".to_string()])]);
+        let mut res = ScanResults::new();
+        let sinks = Sink::default_sinks();
+        scan_file_quick("cfg_ex", filepath, &mut res, sinks, &enabled_cfg).unwrap();
+
+        let mut gates = res
+            .effects
+            .iter()
+            .map(|e| (e.call_loc().start_line(), e.cfg_gates().to_vec()))
+            .collect::<Vec<_>>();
+        gates.sort();
+        // Gates on other cfg options, e.g. target_os, aren't recorded
+        assert_eq!(gates, [(14, vec![]), (19, vec!["feature = \"extra\"".to_string()])]);
+    }
+
+    #[test]
+    fn test_platform_gates() {
+        let filepath = FilePath::new("data/test-packages/cfg-ex/src/main.rs");
+        let mut res = ScanResults::new();
+        let sinks = Sink::default_sinks();
+        scan_file_quick("cfg_ex", filepath, &mut res, sinks, &HashMap::new()).unwrap();
+
+        let mut gates = res
+            .effects
+            .iter()
+            .map(|e| (e.call_loc().start_line(), e.platform_gates().to_vec()))
+            .collect::<Vec<_>>();
+        gates.sort();
+        assert_eq!(
+            gates,
+            [(14, vec!["not(target_os = \"linux\")".to_string()]), (24, vec![])]
+        );
+    }
+
+    #[test]
+    fn test_test_code() {
+        let filepath = FilePath::new("data/test-packages/targets-ex/src/lib.rs");
+        let scan_with = |enabled_cfg: HashMap<String, Vec<String>>| {
+            let mut res = ScanResults::new();
+            let sinks = Sink::default_sinks();
+            scan_file_quick("targets_ex", filepath, &mut res, sinks, &enabled_cfg)
+                .unwrap();
+            let mut effects = res
+                .effects
+                .iter()
+                .map(|e| (e.caller_path().to_string(), e.target_kind()))
+                .collect::<Vec<_>>();
+            effects.sort();
+            (effects, res.target_fns)
+        };
+
+        let (effects, target_fns) = scan_with(HashMap::new());
+        assert_eq!(effects, [("targets_ex::read_config".to_string(), TargetKind::Src)]);
+        assert!(target_fns.is_empty());
+
+        let (effects, target_fns) =
+            scan_with(HashMap::from([("test".to_string(), Vec::new())]));
+        assert_eq!(
+            effects,
+            [
+                ("targets_ex::read_config".to_string(), TargetKind::Src),
+                ("targets_ex::tests::test_read_config".to_string(), TargetKind::Test),
+            ]
+        );
+        let target_fns = target_fns.iter().map(|f| f.as_str()).collect::<Vec<_>>();
+        assert_eq!(target_fns, ["targets_ex::tests::test_read_config"]);
+    }
+
+    #[test]
+    #[cfg(feature = "native")]
+    fn test_scan_target_dir() {
+        let crate_path = FilePath::new("data/test-packages/targets-ex");
+        let sinks = Sink::default_sinks();
+        let mut res = ScanResults::new();
+        let targets = ScanTargets { tests: true, examples: true, benches: true };
+        for (dir, kind) in targets.dirs() {
+            res.merge(scan_target_dir(
+                crate_path,
+                "targets-ex",
+                dir,
+                kind,
+                &sinks,
+                &HashMap::new(),
+            ));
+        }
+
+        let mut effects = res
+            .effects
+            .iter()
+            .filter_map(|e| match e.eff_type() {
+                Effect::SinkCall(s) => {
+                    Some((e.caller_path(), s.as_str(), e.target_kind()))
+                }
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        effects.sort();
+        assert_eq!(
+            effects,
+            [
+                ("targets_ex::examples::server::main", "std::net", TargetKind::Example),
+                (
+                    "targets_ex::tests::integration::runs_command",
+                    "std::process",
+                    TargetKind::Test
+                ),
+            ]
+        );
+        let mut target_fns =
+            res.target_fns.iter().map(|f| f.as_str()).collect::<Vec<_>>();
+        target_fns.sort();
+        assert_eq!(
+            target_fns,
+            [
+                "targets_ex::examples::server::main",
+                "targets_ex::tests::integration::runs_command"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_scan_build_script() {
+        let crate_path = FilePath::new("data/test-packages/build-script-ex");
+        let res = scan_build_script(crate_path, HashSet::new(), DEFAULT_EFFECT_TYPES)
+            .unwrap()
+            .unwrap();
+
+        let mut sinks = res
+            .effects
+            .iter()
+            .filter_map(|e| match e.eff_type() {
+                Effect::SinkCall(s) => Some((e.caller_path(), s.as_str())),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        sinks.sort();
+        assert_eq!(
+            sinks,
+            [
+                ("build_script_ex::build_script_build::main", "std::env"),
+                ("build_script_ex::build_script_build::main", "std::process"),
+            ]
+        );
+        assert!(res.effects.iter().all(|e| e.provenance() == Provenance::Build));
+        assert!(res
+            .entry_fns()
+            .iter()
+            .any(|f| f.as_str() == "build_script_ex::build_script_build::main"));
+
+        let no_build_script = FilePath::new("data/test-packages/dummy");
+        assert!(scan_build_script(no_build_script, HashSet::new(), DEFAULT_EFFECT_TYPES)
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    #[cfg(feature = "native")]
+    fn test_scan_crate_streaming() {
+        let crate_path = FilePath::new("data/test-packages/targets-ex");
+        let targets = ScanTargets { tests: true, examples: true, benches: true };
+        let collected = scan_crate_with_features(
+            crate_path,
+            HashSet::new(),
+            DEFAULT_EFFECT_TYPES,
+            true,
+            None,
+            None,
+            &targets,
+        )
+        .unwrap();
+
+        let mut streamed = Vec::new();
+        let res = scan_crate_streaming(
+            crate_path,
+            HashSet::new(),
+            DEFAULT_EFFECT_TYPES,
+            true,
+            None,
+            &targets,
+            |effects| streamed.extend(effects),
+        )
+        .unwrap();
+
+        assert!(res.effects.is_empty());
+        assert_eq!(res.fn_locs.len(), collected.fn_locs.len());
+        let sorted = |effects: &[EffectInstance]| {
+            let mut csv = effects.iter().map(|e| e.to_csv()).collect::<Vec<_>>();
+            csv.sort();
+            csv
+        };
+        assert_eq!(sorted(&streamed), sorted(&collected.effects));
+        assert!(!streamed.is_empty());
+    }
+    #[test]
+    fn test_scan_crate_in_memory() {
+        let mut fs = MemFs::new();
+        fs.insert(
+            "Cargo.toml",
+            "[package]\nname = \"mem-ex\"\nversion = \"0.1.0\"\n\n\
+             [features]\ndefault = [\"std\"]\nstd = []\nnet = []\n",
+        );
+        fs.insert(
+            "src/lib.rs",
+            "pub fn remove() { std::fs::remove_file(\"foo\").unwrap(); }\n\
+             #[cfg(feature = \"std\")]\n\
+             pub fn exit() { std::process::exit(1); }\n\
+             #[cfg(feature = \"net\")]\n\
+             pub fn connect() { std::net::TcpStream::connect(\"a:1\").unwrap(); }\n",
+        );
+        fs.insert("src/main.rs", "fn main() { mem_ex::remove(); }\n");
+        fs.insert("tests/it.rs", "fn f() { std::process::abort(); }\n");
+
+        let res =
+            scan_crate_in_memory(&fs, HashSet::new(), DEFAULT_EFFECT_TYPES).unwrap();
+        let mut callees = res.effects.iter().map(|e| e.callee_path()).collect::<Vec<_>>();
+        callees.sort();
+        assert_eq!(callees, ["std::fs::remove_file", "std::process::exit"]);
+        assert_eq!(res.effects[0].call_loc().dir(), FilePath::new("src"));
+        let main_fns = res.main_fns.iter().map(|f| f.as_str()).collect::<Vec<_>>();
+        assert_eq!(main_fns, ["mem_ex::main"]);
+    }
+    struct LegacyCrypto;
+
+    impl EffectDetector for LegacyCrypto {
+        fn name(&self) -> &str {
+            "[LegacyCrypto]"
+        }
+
+        fn detect_expr<'a>(
+            &self,
+            e: &'a syn::Expr,
+            cx: &DetectorContext<'a, '_>,
+        ) -> Option<CanonicalPath> {
+            let syn::Expr::Call(call) = e else { return None };
+            let syn::Expr::Path(p) = &*call.func else { return None };
+            let callee = cx.resolve_path(&p.path);
+            callee.as_str().starts_with("our_crypto::legacy::").then_some(callee)
+        }
+
+        fn detect_item<'a>(
+            &self,
+            i: &'a syn::Item,
+            cx: &DetectorContext<'a, '_>,
+        ) -> Option<CanonicalPath> {
+            let syn::Item::Use(u) = i else { return None };
+            let used = u.tree.to_token_stream().to_string().replace(' ', "");
+            used.starts_with("our_crypto::legacy").then(|| cx.resolve_mod_path())
+        }
+    }
+
+    #[test]
+    fn test_custom_detector() {
+        let filepath = FilePath::new("src/lib.rs");
+        let src = "use our_crypto::legacy;\n\
+                   pub fn hash(x: &[u8]) -> Vec<u8> { legacy::md5(x) }\n\
+                   pub fn fine(x: &[u8]) -> Vec<u8> { our_crypto::sha256(x) }\n";
+        let syntax_tree = syn::parse_file(src).unwrap();
+        let resolver = HackyResolver::new("crypto_ex", filepath).unwrap();
+        let mut res = ScanResults::new();
+        let enabled_cfg = HashMap::new();
+        let mut scanner = Scanner::new(filepath, resolver, &mut res, &enabled_cfg);
+        scanner.add_detector(Arc::new(LegacyCrypto));
+        scanner.scan_file(&syntax_tree);
+
+        let effects = res
+            .effects
+            .iter()
+            .map(|e| (e.caller_path(), e.callee_path(), e.eff_type().to_csv()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            effects,
+            [
+                ("crypto_ex", "crypto_ex", "[LegacyCrypto]".to_string()),
+                (
+                    "crypto_ex::hash",
+                    "our_crypto::legacy::md5",
+                    "[LegacyCrypto]".to_string()
+                ),
+            ]
+        );
+        assert_eq!(EffectType::from(res.effects[0].eff_type()), EffectType::Custom);
+    }
+}
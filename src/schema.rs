@@ -0,0 +1,219 @@
+//! Versions of the serialized formats of effects and audit files.
+//!
+//! Serialized `EffectInstance`s and `AuditFile`s carry a `schema_version`,
+//! which is bumped whenever a change to their types would keep older files
+//! from parsing. Files without one were written before versioning, and have
+//! version 0. On load, older files are migrated to the current version one
+//! version at a time, on their JSON before it's deserialized, while files
+//! from newer versions of cargo-scan are rejected.
+//!
+//! To change the format, add a migration from the current version to the
+//! end of the corresponding list, which bumps the version.
+//!
+//! `ScanResults` isn't serialized as a whole; its serialized form, the JSON
+//! `Report`, has its own `version`.
+//!
+//! [`json_schema`] returns JSON Schema documents of the formats, for
+//! consumers validating them or generating code from them.
+
+use serde_json::Value;
+
+use crate::error::{Result, ScanError};
+#[cfg(feature = "native")]
+use crate::{
+    audit_file::AuditFile,
+    report::{EffectReport, Report, ReportDiff},
+};
+
+/// The current schema version of a serialized `EffectInstance`
+pub const EFFECT_SCHEMA_VERSION: u32 = EFFECT_MIGRATIONS.len() as u32;
+
+/// The current schema version of a serialized `AuditFile`
+pub const AUDIT_FILE_SCHEMA_VERSION: u32 = AUDIT_FILE_MIGRATIONS.len() as u32;
+
+/// A migration of JSON in some version of a schema to the next version
+type Migration = fn(&mut Value);
+
+/// Migrations of effects, where the one at index `i` migrates version `i`
+const EFFECT_MIGRATIONS: &[Migration] = &[effect_v0_to_v1];
+
+/// Migrations of audit files, where the one at index `i` migrates version `i`.
+/// These run after the effects in the file have been migrated.
+const AUDIT_FILE_MIGRATIONS: &[Migration] = &[audit_file_v0_to_v1];
+
+/// The JSON formats written by cargo-scan
+#[cfg(feature = "native")]
+#[derive(clap::ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JsonFormat {
+    /// A scan report, as output by `scan --format json`
+    Report,
+    /// A single effect, as output on each line by `scan --format jsonl`
+    Effect,
+    /// The differences between two reports, as output by `scan_diff`
+    ReportDiff,
+    /// An audit file
+    AuditFile,
+}
+
+/// Returns a JSON Schema document of the format
+#[cfg(feature = "native")]
+pub fn json_schema(format: JsonFormat) -> Result<String> {
+    let schema = match format {
+        JsonFormat::Report => schemars::schema_for!(Report),
+        JsonFormat::Effect => schemars::schema_for!(EffectReport),
+        JsonFormat::ReportDiff => schemars::schema_for!(ReportDiff),
+        JsonFormat::AuditFile => schemars::schema_for!(AuditFile),
+    };
+    Ok(serde_json::to_string_pretty(&schema)?)
+}
+
+/// Migrates the JSON of a serialized `EffectInstance` to the current version
+pub fn migrate_effect(json: &mut Value) -> Result<()> {
+    migrate(json, "effect", EFFECT_MIGRATIONS)
+}
+
+/// Migrates the JSON of a serialized `AuditFile`, and of the effects in it, to
+/// the current versions
+pub fn migrate_audit_file(json: &mut Value) -> Result<()> {
+    migrate_nested_effects(json)?;
+    migrate(json, "audit file", AUDIT_FILE_MIGRATIONS)
+}
+
+fn migrate(json: &mut Value, kind: &'static str, migrations: &[Migration]) -> Result<()> {
+    let current = migrations.len() as u32;
+    let version = match json.get("schema_version") {
+        None => 0,
+        Some(v) => v.as_u64().and_then(|v| u32::try_from(v).ok()).ok_or_else(|| {
+            ScanError::from(anyhow::anyhow!("Invalid {} schema version: {}", kind, v))
+        })?,
+    };
+    if version > current {
+        return Err(ScanError::UnsupportedSchema { kind, version, supported: current });
+    }
+    for migration in &migrations[version as usize..] {
+        migration(json);
+    }
+    if let Value::Object(obj) = json {
+        obj.insert("schema_version".to_string(), current.into());
+    }
+    Ok(())
+}
+
+/// Migrates every serialized effect nested in the JSON value. Effects are
+/// found in many fields of an audit file (e.g. as the keys of its maps), so
+/// this is simpler than migrating each field.
+fn migrate_nested_effects(json: &mut Value) -> Result<()> {
+    match json {
+        Value::Object(obj) => {
+            let is_effect = ["caller", "call_loc", "callee", "eff_type"]
+                .iter()
+                .all(|k| obj.contains_key(*k));
+            if is_effect {
+                migrate_effect(json)?;
+            } else {
+                obj.values_mut().try_for_each(migrate_nested_effects)?;
+            }
+        }
+        Value::Array(arr) => arr.iter_mut().try_for_each(migrate_nested_effects)?,
+        _ => (),
+    }
+    Ok(())
+}
+
+/// Renames the effect (or effect type) `UnionField` to `UnionFieldRead`, its
+/// name since writes to union fields are tracked as well
+fn rename_union_field(json: &mut Value) {
+    match json {
+        Value::String(s) if s == "UnionField" => *s = "UnionFieldRead".to_string(),
+        Value::Object(obj) => {
+            if let Some(path) = obj.remove("UnionField") {
+                obj.insert("UnionFieldRead".to_string(), path);
+            }
+        }
+        _ => (),
+    }
+}
+
+fn effect_v0_to_v1(json: &mut Value) {
+    if let Some(eff_type) = json.get_mut("eff_type") {
+        rename_union_field(eff_type);
+    }
+}
+
+fn audit_file_v0_to_v1(json: &mut Value) {
+    if let Some(Value::Array(types)) = json.get_mut("scanned_effects") {
+        types.iter_mut().for_each(rename_union_field);
+    }
+    // Effect signatures and effects by function, as (function, effects) pairs
+    for field in ["pub_effect_signatures", "dep_effect_signatures", "pub_fn_effects"] {
+        if let Some(Value::Array(entries)) = json.get_mut(field) {
+            for entry in entries {
+                if let Some(Value::Array(effects)) = entry.get_mut(1) {
+                    effects.iter_mut().for_each(rename_union_field);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_migrate_audit_file() {
+        let effect = json!({
+            "caller": "a::f",
+            "call_loc": {},
+            "callee": "a::U::x",
+            "eff_type": { "UnionField": "a::U::x" },
+        });
+        let mut audit = json!({
+            "audit_trees": [[effect, "tree"]],
+            "scanned_effects": ["SinkCall", "UnionField"],
+            "pub_effect_signatures": [["a::f", ["UnionField"]]],
+            "pub_fn_effects": [["a::f", [{ "UnionField": "a::U::x" }, "InlineAsm"]]],
+        });
+        migrate_audit_file(&mut audit).unwrap();
+
+        assert_eq!(audit["schema_version"], AUDIT_FILE_SCHEMA_VERSION);
+        let effect = &audit["audit_trees"][0][0];
+        assert_eq!(effect["schema_version"], EFFECT_SCHEMA_VERSION);
+        assert_eq!(effect["eff_type"], json!({ "UnionFieldRead": "a::U::x" }));
+        assert_eq!(audit["scanned_effects"], json!(["SinkCall", "UnionFieldRead"]));
+        assert_eq!(audit["pub_effect_signatures"][0][1], json!(["UnionFieldRead"]));
+        assert_eq!(
+            audit["pub_fn_effects"][0][1],
+            json!([{ "UnionFieldRead": "a::U::x" }, "InlineAsm"])
+        );
+
+        // Migrating again changes nothing
+        let migrated = audit.clone();
+        migrate_audit_file(&mut audit).unwrap();
+        assert_eq!(audit, migrated);
+    }
+
+    #[cfg(feature = "native")]
+    #[test]
+    fn test_json_schema() {
+        let schema: Value =
+            serde_json::from_str(&json_schema(JsonFormat::AuditFile).unwrap()).unwrap();
+        assert_eq!(schema["title"], "AuditFile");
+        let props = &schema["properties"];
+        assert_eq!(props["schema_version"]["type"], "integer");
+        assert_eq!(props["audit_trees"]["type"], "array");
+
+        let schema: Value =
+            serde_json::from_str(&json_schema(JsonFormat::Report).unwrap()).unwrap();
+        assert!(schema["required"].as_array().unwrap().contains(&"effects".into()));
+        assert!(schema["definitions"]["EffectReport"].is_object());
+    }
+
+    #[test]
+    fn test_reject_newer_schema() {
+        let mut audit = json!({ "schema_version": AUDIT_FILE_SCHEMA_VERSION + 1 });
+        let err = migrate_audit_file(&mut audit).unwrap_err();
+        assert!(matches!(err, ScanError::UnsupportedSchema { kind: "audit file", .. }));
+    }
+}
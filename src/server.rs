@@ -0,0 +1,335 @@
+//! A long-running scan server, answering JSON-RPC 2.0 requests.
+//!
+//! Loading a crate into rust-analyzer takes much longer than scanning it, so
+//! the server keeps the crates it's asked about loaded (see `LoadedCrate`),
+//! and each later request only scans the files again. Requests and responses
+//! are single lines of JSON; see the `daemon` binary for serving them on a
+//! socket or on stdin and stdout.
+//!
+//! Methods, with paths resolved relative to the server's directory:
+//! - `load_crate {crate_path}`: loads the crate, or loads it again after
+//!   files were added or Cargo.toml changed, and returns its name and files;
+//! - `scan_file {crate_path, file}`: the effects in one of the crate's files,
+//!   given relative to the crate root or as an absolute path;
+//! - `effects_for_crate {crate_path}`: the effects in the whole crate;
+//! - `audit_status {audit_file}`: how much of an audit is done, and whether
+//!   the audited crate changed since;
+//! - `shutdown`: stops the server once it has responded.
+//!
+//! Effects are given as in JSON reports, with their locations relative to the
+//! crate root. Effects suppressed by comments are left out.
+
+use std::collections::{HashMap, HashSet};
+use std::io::{BufRead, Write};
+use std::path::{Path, PathBuf};
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::audit_file::{AuditFile, SafetyAnnotation};
+use crate::auditing::util::is_audit_scan_valid;
+use crate::effect::EffectType;
+use crate::error::Result;
+use crate::ident::IdentPath;
+use crate::report::effect_reports;
+use crate::scanner::LoadedCrate;
+
+/// The JSON-RPC error codes the server responds with
+pub mod error_code {
+    pub const PARSE_ERROR: i64 = -32700;
+    pub const INVALID_REQUEST: i64 = -32600;
+    pub const METHOD_NOT_FOUND: i64 = -32601;
+    pub const INVALID_PARAMS: i64 = -32602;
+    /// The request was valid, but e.g. the crate failed to load or scan
+    pub const SCAN_FAILED: i64 = -32000;
+}
+
+#[derive(Deserialize)]
+struct Request {
+    jsonrpc: String,
+    /// Left out for notifications, which get no response
+    id: Option<Value>,
+    method: String,
+    #[serde(default)]
+    params: Value,
+}
+
+#[derive(Serialize)]
+struct Response {
+    jsonrpc: &'static str,
+    id: Value,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    result: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<RpcError>,
+}
+
+#[derive(Debug, Serialize)]
+struct RpcError {
+    code: i64,
+    message: String,
+}
+
+impl RpcError {
+    fn new(code: i64, message: impl ToString) -> Self {
+        Self { code, message: message.to_string() }
+    }
+}
+
+impl From<crate::error::ScanError> for RpcError {
+    fn from(e: crate::error::ScanError) -> Self {
+        Self::new(error_code::SCAN_FAILED, e)
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CrateParams {
+    crate_path: PathBuf,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct FileParams {
+    crate_path: PathBuf,
+    file: PathBuf,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct AuditStatusParams {
+    audit_file: PathBuf,
+}
+
+fn params<T: DeserializeOwned>(params: Value) -> Result<T, RpcError> {
+    serde_json::from_value(params)
+        .map_err(|e| RpcError::new(error_code::INVALID_PARAMS, e))
+}
+
+pub struct Server {
+    /// The loaded crates, by their canonical path
+    crates: HashMap<PathBuf, LoadedCrate>,
+    sinks: HashSet<IdentPath>,
+    effect_types: Vec<EffectType>,
+    shut_down: bool,
+}
+
+impl Server {
+    /// A server reporting the given effect types, with the given sinks in
+    /// addition to the default ones
+    pub fn new(sinks: HashSet<IdentPath>, effect_types: &[EffectType]) -> Self {
+        Self {
+            crates: HashMap::new(),
+            sinks,
+            effect_types: effect_types.to_vec(),
+            shut_down: false,
+        }
+    }
+
+    /// Whether a client asked the server to shut down
+    pub fn is_shut_down(&self) -> bool {
+        self.shut_down
+    }
+
+    /// Answers each request, one per line, until the reader is closed or a
+    /// client asks the server to shut down
+    pub fn serve<R: BufRead, W: Write>(
+        &mut self,
+        reader: R,
+        mut writer: W,
+    ) -> Result<()> {
+        for line in reader.lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            if let Some(response) = self.handle(&line) {
+                writeln!(writer, "{}", response)?;
+                writer.flush()?;
+            }
+            if self.shut_down {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    /// Answers a request, or returns None for a notification
+    pub fn handle(&mut self, request: &str) -> Option<String> {
+        let (id, result) = match serde_json::from_str::<Value>(request) {
+            Err(e) => (Value::Null, Err(RpcError::new(error_code::PARSE_ERROR, e))),
+            Ok(request) => match serde_json::from_value::<Request>(request) {
+                Err(e) => {
+                    (Value::Null, Err(RpcError::new(error_code::INVALID_REQUEST, e)))
+                }
+                Ok(request) if request.jsonrpc != "2.0" => (
+                    request.id.unwrap_or_default(),
+                    Err(RpcError::new(
+                        error_code::INVALID_REQUEST,
+                        "jsonrpc must be \"2.0\"",
+                    )),
+                ),
+                Ok(request) => {
+                    let result = self.call(&request.method, request.params);
+                    (request.id?, result)
+                }
+            },
+        };
+        let (result, error) = match result {
+            Ok(result) => (Some(result), None),
+            Err(error) => (None, Some(error)),
+        };
+        let response = Response { jsonrpc: "2.0", id, result, error };
+        Some(serde_json::to_string(&response).expect("responses serialize to JSON"))
+    }
+
+    fn call(&mut self, method: &str, params_value: Value) -> Result<Value, RpcError> {
+        match method {
+            "load_crate" => {
+                let p: CrateParams = params(params_value)?;
+                let key = canonical(&p.crate_path)?;
+                self.crates.remove(&key);
+                let loaded = self.loaded_crate(&p.crate_path)?;
+                Ok(json!({
+                    "crate_name": loaded.crate_name(),
+                    "files": loaded.files(),
+                }))
+            }
+            "scan_file" => {
+                let p: FileParams = params(params_value)?;
+                let (sinks, effect_types) =
+                    (self.sinks.clone(), self.effect_types.clone());
+                let loaded = self.loaded_crate(&p.crate_path)?;
+                let file = loaded.crate_path().join(&p.file);
+                let results = loaded.scan_file(&file, sinks, &effect_types)?;
+                Ok(json!({ "effects": effect_reports(loaded.crate_path(), results) }))
+            }
+            "effects_for_crate" => {
+                let p: CrateParams = params(params_value)?;
+                let (sinks, effect_types) =
+                    (self.sinks.clone(), self.effect_types.clone());
+                let loaded = self.loaded_crate(&p.crate_path)?;
+                let results = loaded.scan(sinks, &effect_types)?;
+                Ok(json!({
+                    "crate_name": loaded.crate_name(),
+                    "effects": effect_reports(loaded.crate_path(), results),
+                }))
+            }
+            "audit_status" => {
+                let p: AuditStatusParams = params(params_value)?;
+                audit_status(&p.audit_file)
+            }
+            "shutdown" => {
+                self.shut_down = true;
+                Ok(Value::Null)
+            }
+            _ => Err(RpcError::new(
+                error_code::METHOD_NOT_FOUND,
+                format!("Unknown method: {}", method),
+            )),
+        }
+    }
+
+    /// The crate at the path, loaded on first use
+    fn loaded_crate(&mut self, crate_path: &Path) -> Result<&mut LoadedCrate, RpcError> {
+        let key = canonical(crate_path)?;
+        if !self.crates.contains_key(&key) {
+            let loaded = LoadedCrate::load(&key)?;
+            self.crates.insert(key.clone(), loaded);
+        }
+        Ok(self.crates.get_mut(&key).expect("the crate was just loaded"))
+    }
+}
+
+fn canonical(path: &Path) -> Result<PathBuf, RpcError> {
+    path.canonicalize().map_err(|e| {
+        RpcError::new(error_code::INVALID_PARAMS, format!("{}: {:?}", e, path))
+    })
+}
+
+fn audit_status(audit_file: &Path) -> Result<Value, RpcError> {
+    let audit =
+        AuditFile::read_audit_file(audit_file.to_path_buf())?.ok_or_else(|| {
+            RpcError::new(
+                error_code::INVALID_PARAMS,
+                format!("No audit file at {:?}", audit_file),
+            )
+        })?;
+    let count =
+        |a| audit.audit_trees.values().filter(|t| t.summary_annotation() == a).count();
+    let (unaudited_effects, unaudited_locations) = audit.unaudited_effects();
+    // The audited crate may have moved since
+    let up_to_date = is_audit_scan_valid(&audit, &audit.base_dir).ok();
+    Ok(json!({
+        "crate_path": audit.base_dir,
+        "effects": audit.audit_trees.len(),
+        "safe": count(SafetyAnnotation::Safe),
+        "unsafe": count(SafetyAnnotation::Unsafe),
+        "caller_checked": count(SafetyAnnotation::CallerChecked),
+        "unaudited_effects": unaudited_effects,
+        "unaudited_locations": unaudited_locations,
+        "up_to_date": up_to_date,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::effect::DEFAULT_EFFECT_TYPES;
+
+    fn request(server: &mut Server, request: Value) -> Value {
+        let response = server.handle(&request.to_string()).unwrap();
+        serde_json::from_str(&response).unwrap()
+    }
+
+    #[test]
+    fn test_server_errors() {
+        let mut server = Server::new(HashSet::new(), DEFAULT_EFFECT_TYPES);
+        let response: Value = serde_json::from_str(&server.handle("{").unwrap()).unwrap();
+        assert_eq!(response["error"]["code"], error_code::PARSE_ERROR);
+
+        let unknown = json!({"jsonrpc": "2.0", "id": 1, "method": "scan_everything"});
+        let response = request(&mut server, unknown);
+        assert_eq!(response["id"], 1);
+        assert_eq!(response["error"]["code"], error_code::METHOD_NOT_FOUND);
+
+        let missing_params = json!({"jsonrpc": "2.0", "id": 2, "method": "scan_file"});
+        let response = request(&mut server, missing_params);
+        assert_eq!(response["error"]["code"], error_code::INVALID_PARAMS);
+
+        // Notifications get no response
+        let notification = json!({"jsonrpc": "2.0", "method": "shutdown"});
+        assert!(server.handle(&notification.to_string()).is_none());
+        assert!(server.is_shut_down());
+    }
+
+    #[test]
+    fn test_server_scan() {
+        let mut server = Server::new(HashSet::new(), DEFAULT_EFFECT_TYPES);
+        let crate_path = "data/test-packages/ffi-ex";
+        let requests = [
+            json!({"jsonrpc": "2.0", "id": 1, "method": "effects_for_crate",
+                   "params": {"crate_path": crate_path}}),
+            json!({"jsonrpc": "2.0", "id": 2, "method": "scan_file",
+                   "params": {"crate_path": crate_path, "file": "src/main.rs"}}),
+            json!({"jsonrpc": "2.0", "id": 3, "method": "shutdown"}),
+        ];
+        let input = requests.iter().map(|r| r.to_string() + "\n").collect::<String>();
+        let mut output = Vec::new();
+        server.serve(input.as_bytes(), &mut output).unwrap();
+
+        let responses = String::from_utf8(output).unwrap();
+        let responses = responses
+            .lines()
+            .map(|r| serde_json::from_str::<Value>(r).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(responses.len(), 3);
+        let crate_effects = responses[0]["result"]["effects"].as_array().unwrap();
+        assert!(!crate_effects.is_empty());
+        // The crate's effects are all in src/main.rs
+        let file_effects = responses[1]["result"]["effects"].as_array().unwrap();
+        assert_eq!(file_effects.len(), crate_effects.len());
+        assert_eq!(responses[2]["result"], Value::Null);
+    }
+}
@@ -0,0 +1,194 @@
+//! Signatures of policies and audit sign-offs.
+//!
+//! Auditors sign the policies they publish to a registry (see `registry`) and
+//! their sign-offs on audits in a chain (see `audit_chain`) with an Ed25519
+//! key, so that others can check who vouched for them. The secret key is kept
+//! in a file, found through the `CARGO_SCAN_SIGNING_KEY` environment
+//! variable, the `cargo-scan.signingKey` git config or the default location
+//! `~/.cargo-scan/signing-key`, and is created with `chain keygen`. Projects
+//! list the public keys they trust in `.cargo-scan.toml` (see
+//! `project_config`).
+//!
+//! Keys and signatures are written in hex. What's signed is the canonical
+//! serialization of a JSON value (see `canonical_json`), so the signature
+//! doesn't depend on how the value was formatted when it was stored.
+
+use std::fmt::Write as _;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Context, Result};
+use ed25519_compact::{KeyPair, PublicKey, Seed};
+use serde::{Deserialize, Serialize};
+
+use crate::util::git_config;
+
+/// The environment variable to read the path of the signing key from
+pub const SIGNING_KEY_ENV: &str = "CARGO_SCAN_SIGNING_KEY";
+
+/// A signature, along with the public key it was made with
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Signature {
+    pub public_key: String,
+    pub signature: String,
+}
+
+impl Signature {
+    /// Checks that the signature is a valid signature of `msg` by its key
+    pub fn verify(&self, msg: &[u8]) -> Result<()> {
+        let public_key = PublicKey::from_slice(&from_hex(&self.public_key)?)
+            .map_err(|e| anyhow!("Invalid public key {}: {}", self.public_key, e))?;
+        let signature =
+            ed25519_compact::Signature::from_slice(&from_hex(&self.signature)?)
+                .map_err(|e| anyhow!("Invalid signature: {}", e))?;
+        public_key
+            .verify(msg, &signature)
+            .map_err(|_| anyhow!("Signature by {} doesn't match", self.public_key))
+    }
+}
+
+/// An auditor's secret key
+pub struct SigningKey(KeyPair);
+
+impl SigningKey {
+    /// Creates a new random key
+    #[cfg(feature = "native")]
+    pub fn generate() -> Self {
+        SigningKey(KeyPair::from_seed(Seed::generate()))
+    }
+
+    /// Parses a key from the hex of its seed, as saved by `save`
+    pub fn from_hex(s: &str) -> Result<Self> {
+        let seed = Seed::from_slice(&from_hex(s.trim())?)
+            .map_err(|e| anyhow!("Invalid signing key: {}", e))?;
+        Ok(SigningKey(KeyPair::from_seed(seed)))
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let s = fs::read_to_string(path)
+            .with_context(|| format!("Couldn't read signing key: {:?}", path))?;
+        Self::from_hex(&s).with_context(|| format!("Invalid signing key: {:?}", path))
+    }
+
+    /// Saves the key to a new file, which only the current user can read
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let mut options = fs::OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+        let mut f = options
+            .open(path)
+            .with_context(|| format!("Couldn't create signing key: {:?}", path))?;
+        std::io::Write::write_all(
+            &mut f,
+            format!("{}\n", to_hex(&*self.0.sk.seed())).as_bytes(),
+        )?;
+        Ok(())
+    }
+
+    /// The public key, in hex, which others use to check the signatures
+    pub fn public_key(&self) -> String {
+        to_hex(&*self.0.pk)
+    }
+
+    pub fn sign(&self, msg: &[u8]) -> Signature {
+        Signature {
+            public_key: self.public_key(),
+            signature: to_hex(&*self.0.sk.sign(msg, None)),
+        }
+    }
+}
+
+/// The path of the auditor's signing key: the first of
+/// - the `CARGO_SCAN_SIGNING_KEY` environment variable
+/// - the `cargo-scan.signingKey` git config
+/// - `~/.cargo-scan/signing-key`
+pub fn signing_key_path() -> Option<PathBuf> {
+    std::env::var(SIGNING_KEY_ENV)
+        .ok()
+        .filter(|p| !p.is_empty())
+        .or_else(|| git_config("cargo-scan.signingKey"))
+        .map(PathBuf::from)
+        .or_else(|| Some(home::home_dir()?.join(".cargo-scan").join("signing-key")))
+}
+
+/// Loads the auditor's signing key (see `signing_key_path`)
+pub fn signing_key() -> Result<SigningKey> {
+    let path = signing_key_path()
+        .filter(|p| p.is_file())
+        .ok_or_else(|| anyhow!("No signing key found; create one with `chain keygen`"))?;
+    SigningKey::load(&path)
+}
+
+/// The canonical serialization of a JSON value, which is what gets signed:
+/// compact JSON with the keys of every object in sorted order
+pub fn canonical_json(value: &serde_json::Value) -> Result<Vec<u8>> {
+    fn sort_keys(value: &serde_json::Value) -> serde_json::Value {
+        match value {
+            serde_json::Value::Object(o) => {
+                let mut entries = o.iter().collect::<Vec<_>>();
+                entries.sort_by_key(|(k, _)| *k);
+                let o = entries.into_iter().map(|(k, v)| (k.clone(), sort_keys(v)));
+                serde_json::Value::Object(o.collect())
+            }
+            serde_json::Value::Array(a) => {
+                serde_json::Value::Array(a.iter().map(sort_keys).collect())
+            }
+            v => v.clone(),
+        }
+    }
+    Ok(serde_json::to_vec(&sort_keys(value))?)
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().fold(String::new(), |mut s, b| {
+        let _ = write!(s, "{:02x}", b);
+        s
+    })
+}
+
+fn from_hex(s: &str) -> Result<Vec<u8>> {
+    s.as_bytes()
+        .chunks(2)
+        .map(|pair| match pair {
+            [hi, lo] if hi.is_ascii_hexdigit() && lo.is_ascii_hexdigit() => {
+                let digit = |c: &u8| (*c as char).to_digit(16).unwrap_or_default() as u8;
+                Ok(digit(hi) << 4 | digit(lo))
+            }
+            _ => Err(anyhow!("Invalid hex: {}", s)),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sign_and_verify() {
+        let key = SigningKey::from_hex(&"07".repeat(32)).unwrap();
+        let signature = key.sign(b"policy");
+        assert_eq!(signature.public_key, key.public_key());
+        assert!(signature.verify(b"policy").is_ok());
+        assert!(signature.verify(b"tampered").is_err());
+
+        let other = SigningKey::from_hex(&"08".repeat(32)).unwrap();
+        let forged = Signature { public_key: other.public_key(), ..signature };
+        assert!(forged.verify(b"policy").is_err());
+        assert!(SigningKey::from_hex("not hex").is_err());
+        assert!(SigningKey::from_hex(&"0".repeat(63)).is_err());
+        assert!(SigningKey::from_hex(&"+f".repeat(32)).is_err());
+    }
+
+    #[test]
+    fn test_canonical_json() {
+        let a = serde_json::json!({"b": [1, {"d": 2, "c": 3}], "a": "x"});
+        let b =
+            serde_json::from_str(r#"{ "a": "x", "b": [1, {"c": 3, "d": 2}] }"#).unwrap();
+        assert_eq!(canonical_json(&a).unwrap(), canonical_json(&b).unwrap());
+        assert_eq!(canonical_json(&a).unwrap(), br#"{"a":"x","b":[1,{"c":3,"d":2}]}"#);
+    }
+}
@@ -4,11 +4,16 @@ use crate::ident::Ident;
 
 use super::ident::{CanonicalPath, IdentPath, Pattern};
 
+use anyhow::{anyhow, Context, Result};
 use log::warn;
+use parse_display::{Display, FromStr};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use std::{
     collections::HashSet,
     fmt::{self, Display},
+    fs::read_to_string,
+    path::Path,
 };
 
 // TODO: Convert these examples to canonical paths
@@ -32,6 +37,173 @@ const SINK_PATTERNS: &[&str] = &[
     "winapi",
 ];
 
+/// Sink patterns for popular crates which spawn processes. Process execution
+/// is the most common payload of malicious crates, so these are sinks by
+/// default even though other third-party sinks were removed (see below).
+const PROCESS_SINK_PATTERNS: &[&str] = &[
+    "async_process",
+    "async_std::process",
+    "duct",
+    "smol::process",
+    "subprocess",
+    "tokio::process",
+];
+
+/// Built-in macros which read environment variables at compile time. The
+/// values end up in the binary, so these can leak secrets from the build
+/// environment (e.g. CI tokens) just as well as reads at run time.
+const ENV_MACROS: &[&str] = &["env", "option_env"];
+
+/// Crates binding the OS APIs which spawn or replace processes. Calls into
+/// these are usually FFI calls, and their paths depend on the target's
+/// module layout, so we match them by crate and function name instead.
+const PROCESS_SPAWN_CRATES: &[&str] =
+    &["libc", "nix", "winapi", "windows", "windows_sys"];
+
+/// Functions which spawn or replace processes, e.g. `libc::fork`
+const PROCESS_SPAWN_FNS: &[&str] = &[
+    "fork",
+    "vfork",
+    "execl",
+    "execle",
+    "execlp",
+    "execv",
+    "execve",
+    "execveat",
+    "execvp",
+    "execvpe",
+    "fexecve",
+    "posix_spawn",
+    "posix_spawnp",
+    "system",
+    "popen",
+    "CreateProcessA",
+    "CreateProcessW",
+    "CreateProcessAsUserA",
+    "CreateProcessAsUserW",
+    "CreateProcessWithLogonW",
+    "CreateProcessWithTokenW",
+    "ShellExecuteA",
+    "ShellExecuteW",
+    "ShellExecuteExA",
+    "ShellExecuteExW",
+    "WinExec",
+];
+
+/// The kind of resource a sink gives access to
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    Hash,
+    PartialOrd,
+    Ord,
+    Serialize,
+    Deserialize,
+    FromStr,
+    JsonSchema,
+)]
+#[serde(rename_all = "lowercase")]
+#[display(style = "lowercase")]
+pub enum SinkCategory {
+    Net,
+    Fs,
+    Process,
+    Env,
+    Crypto,
+    Mem,
+    Other,
+}
+
+impl SinkCategory {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SinkCategory::Net => "net",
+            SinkCategory::Fs => "fs",
+            SinkCategory::Process => "process",
+            SinkCategory::Env => "env",
+            SinkCategory::Crypto => "crypto",
+            SinkCategory::Mem => "mem",
+            SinkCategory::Other => "other",
+        }
+    }
+}
+
+impl Display for SinkCategory {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// How much damage a sink could do if misused
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    Hash,
+    PartialOrd,
+    Ord,
+    Serialize,
+    Deserialize,
+    Display,
+    FromStr,
+    JsonSchema,
+)]
+#[serde(rename_all = "lowercase")]
+#[display(style = "lowercase")]
+pub enum Severity {
+    Low,
+    Medium,
+    High,
+}
+
+/// The category and severity of sink patterns. A sink gets the entry of the
+/// longest pattern it falls under, so this also covers user-supplied sinks
+/// under these paths (e.g. from crypto crates which aren't default sinks).
+const SINK_CATEGORIES: &[(&str, SinkCategory, Severity)] = &[
+    ("std::arch", SinkCategory::Mem, Severity::High),
+    ("std::backtrace", SinkCategory::Other, Severity::Low),
+    ("std::env", SinkCategory::Env, Severity::Medium),
+    ("std::option_env", SinkCategory::Env, Severity::Medium),
+    ("std::ffi", SinkCategory::Mem, Severity::Medium),
+    ("std::fs", SinkCategory::Fs, Severity::Medium),
+    ("std::intrinsics", SinkCategory::Mem, Severity::High),
+    ("std::io", SinkCategory::Fs, Severity::Medium),
+    ("std::mem", SinkCategory::Mem, Severity::Medium),
+    ("std::net", SinkCategory::Net, Severity::High),
+    ("std::os", SinkCategory::Other, Severity::Medium),
+    ("std::path", SinkCategory::Fs, Severity::Low),
+    ("std::panic", SinkCategory::Other, Severity::Low),
+    ("std::process", SinkCategory::Process, Severity::High),
+    ("std::os::unix::process", SinkCategory::Process, Severity::High),
+    ("std::os::windows::process", SinkCategory::Process, Severity::High),
+    ("std::simd", SinkCategory::Mem, Severity::Low),
+    ("libc", SinkCategory::Other, Severity::High),
+    ("winapi", SinkCategory::Other, Severity::High),
+    ("mio::net", SinkCategory::Net, Severity::High),
+    ("tokio::fs", SinkCategory::Fs, Severity::Medium),
+    ("tokio::net", SinkCategory::Net, Severity::High),
+    ("tokio::process", SinkCategory::Process, Severity::High),
+    ("async_process", SinkCategory::Process, Severity::High),
+    ("async_std::process", SinkCategory::Process, Severity::High),
+    ("duct", SinkCategory::Process, Severity::High),
+    ("smol::process", SinkCategory::Process, Severity::High),
+    ("subprocess", SinkCategory::Process, Severity::High),
+    ("hyper", SinkCategory::Net, Severity::High),
+    ("reqwest", SinkCategory::Net, Severity::High),
+    ("socket2", SinkCategory::Net, Severity::High),
+    ("aes", SinkCategory::Crypto, Severity::Medium),
+    ("openssl", SinkCategory::Crypto, Severity::Medium),
+    ("ring", SinkCategory::Crypto, Severity::Medium),
+    ("rsa", SinkCategory::Crypto, Severity::Medium),
+    ("rustls", SinkCategory::Crypto, Severity::Medium),
+    ("sha2", SinkCategory::Crypto, Severity::Medium),
+];
+
 // Removed sink patterns on 2023-11-16
 // "mio::net",
 // "mio::unix",
@@ -45,7 +217,7 @@ const SINK_PATTERNS: &[&str] = &[
 // "tokio_util::net",
 // "socket2",
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize, JsonSchema)]
 pub struct Sink(Pattern);
 
 impl Display for Sink {
@@ -73,6 +245,42 @@ impl Sink {
         Some(Self(result?))
     }
 
+    /// Get the sink for a call to a function which spawns or replaces a
+    /// process, e.g. `libc::fork` or `nix::unistd::execv`. The sink is named
+    /// after the crate and function, regardless of the module it's in.
+    pub fn new_process_spawn(callee: &CanonicalPath) -> Option<Self> {
+        let krate = callee.crate_name();
+        let fn_name = callee.as_str().rsplit("::").next()?;
+        if PROCESS_SPAWN_CRATES.contains(&krate.as_str())
+            && PROCESS_SPAWN_FNS.contains(&fn_name)
+        {
+            Some(Self(Pattern::new(&format!("{}::{}", krate, fn_name))))
+        } else {
+            None
+        }
+    }
+
+    /// Get the sink for an invocation of the `env!` or `option_env!` macro,
+    /// given the macro's name. The sink is named after the macro's path in
+    /// `std`, e.g. `std::option_env`.
+    pub fn new_env_macro(name: &str) -> Option<Self> {
+        if ENV_MACROS.contains(&name) {
+            Some(Self(Pattern::new(&format!("std::{}", name))))
+        } else {
+            None
+        }
+    }
+
+    fn is_process_spawn(&self) -> bool {
+        match self.as_str().split_once("::") {
+            Some((krate, fn_name)) => {
+                PROCESS_SPAWN_CRATES.contains(&krate)
+                    && PROCESS_SPAWN_FNS.contains(&fn_name)
+            }
+            None => false,
+        }
+    }
+
     pub fn first_ident(&self) -> Option<Ident> {
         self.0.first_ident()
     }
@@ -82,7 +290,168 @@ impl Sink {
         self.0.as_str()
     }
 
+    /// The entry of `SINK_CATEGORIES` for the longest pattern the sink falls
+    /// under
+    fn category_entry(&self) -> Option<&'static (&'static str, SinkCategory, Severity)> {
+        let sink = self.as_str();
+        SINK_CATEGORIES
+            .iter()
+            .filter(|(pat, _, _)| {
+                sink == *pat
+                    || sink.strip_prefix(pat).is_some_and(|rest| rest.starts_with("::"))
+            })
+            .max_by_key(|(pat, _, _)| pat.len())
+    }
+
+    pub fn category(&self) -> SinkCategory {
+        if self.is_process_spawn() {
+            return SinkCategory::Process;
+        }
+        self.category_entry().map_or(SinkCategory::Other, |(_, c, _)| *c)
+    }
+
+    pub fn severity(&self) -> Severity {
+        if self.is_process_spawn() {
+            return Severity::High;
+        }
+        self.category_entry().map_or(Severity::Medium, |(_, _, s)| *s)
+    }
+
     pub fn default_sinks() -> HashSet<IdentPath> {
-        SINK_PATTERNS.iter().map(|x| IdentPath::new(x)).collect::<HashSet<_>>()
+        SINK_PATTERNS
+            .iter()
+            .chain(PROCESS_SINK_PATTERNS)
+            .map(|x| IdentPath::new(x))
+            .collect::<HashSet<_>>()
+    }
+}
+
+/// Additional sinks supplied by the user, e.g. an organization's internal
+/// auth or crypto wrappers. Read from a TOML file of the form:
+///
+/// ```toml
+/// prefixes = ["mycorp::auth"]
+/// functions = ["mycorp::crypto::decrypt"]
+/// ```
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SinkConfig {
+    /// Paths whose items are all sinks
+    #[serde(default)]
+    pub prefixes: Vec<String>,
+    /// Individual functions which are sinks. As with any sink pattern, these
+    /// also match items nested under the function's path.
+    #[serde(default)]
+    pub functions: Vec<String>,
+}
+
+impl SinkConfig {
+    pub fn from_toml(s: &str) -> Result<Self> {
+        toml::from_str(s).context("Couldn't parse sink config")
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let s = read_to_string(path)
+            .with_context(|| format!("Couldn't read sink config: {:?}", path))?;
+        Self::from_toml(&s)
+    }
+
+    /// The sink patterns, to merge with the default sinks
+    pub fn sinks(&self) -> Result<HashSet<IdentPath>> {
+        self.prefixes
+            .iter()
+            .chain(&self.functions)
+            .map(|s| {
+                let p = IdentPath::new(s.trim_end_matches("::*"));
+                if p.is_empty() || !p.invariant() {
+                    Err(anyhow!("Invalid sink pattern: {}", s))
+                } else {
+                    Ok(p)
+                }
+            })
+            .collect()
+    }
+}
+
+/// Load the sink patterns from a user-supplied TOML file
+pub fn load_sinks_file(path: &Path) -> Result<HashSet<IdentPath>> {
+    SinkConfig::load(path)?.sinks()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sink_config() {
+        let config = SinkConfig::from_toml(
+            r#"
+            prefixes = ["mycorp::auth", "mycorp::net::*"]
+            functions = ["mycorp::crypto::decrypt"]
+            "#,
+        )
+        .unwrap();
+        let sinks = config.sinks().unwrap();
+        assert_eq!(sinks.len(), 3);
+        assert!(sinks.contains(&IdentPath::new("mycorp::net")));
+
+        let callee = CanonicalPath::new("mycorp::auth::login");
+        let sink = Sink::new_match(&callee, &sinks).unwrap();
+        assert_eq!(sink.as_str(), "mycorp::auth");
+
+        let callee = CanonicalPath::new("mycorp::crypto::encrypt");
+        assert!(Sink::new_match(&callee, &sinks).is_none());
+
+        assert!(SinkConfig::from_toml("functions = [\"not a path\"]")
+            .unwrap()
+            .sinks()
+            .is_err());
+        assert!(SinkConfig::from_toml("sinks = []").is_err());
+    }
+
+    #[test]
+    fn test_sink_category() {
+        let sinks = Sink::default_sinks();
+        let sink = |callee| Sink::new_match(&CanonicalPath::new(callee), &sinks).unwrap();
+        assert_eq!(sink("std::process::Command::new").category(), SinkCategory::Process);
+        assert_eq!(sink("std::process::Command::new").severity(), Severity::High);
+        assert_eq!(sink("std::fs::read_dir").category(), SinkCategory::Fs);
+        assert_eq!(sink("std::fs::read_dir").severity(), Severity::Medium);
+        assert_eq!(sink("std::env::var").category(), SinkCategory::Env);
+
+        let option_env = Sink::new_env_macro("option_env").unwrap();
+        assert_eq!(option_env.as_str(), "std::option_env");
+        assert_eq!(option_env.category(), SinkCategory::Env);
+        assert!(Sink::new_env_macro("println").is_none());
+
+        let sinks = HashSet::from([IdentPath::new("ring"), IdentPath::new("mycorp")]);
+        let sink = |callee| Sink::new_match(&CanonicalPath::new(callee), &sinks).unwrap();
+        assert_eq!(sink("ring::aead::seal").category(), SinkCategory::Crypto);
+        assert_eq!(sink("mycorp::auth::login").category(), SinkCategory::Other);
+        assert_eq!("process".parse::<SinkCategory>().unwrap(), SinkCategory::Process);
+        assert!(Severity::High > Severity::Low);
+    }
+
+    #[test]
+    fn test_process_spawn() {
+        let fork =
+            Sink::new_process_spawn(&CanonicalPath::new("libc::unix::fork")).unwrap();
+        assert_eq!(fork.as_str(), "libc::fork");
+        assert_eq!(fork.category(), SinkCategory::Process);
+        assert_eq!(fork.severity(), Severity::High);
+
+        let execv = CanonicalPath::new("nix::unistd::execv");
+        assert!(Sink::new_process_spawn(&execv).is_some());
+        assert!(
+            Sink::new_process_spawn(&CanonicalPath::new("libc::unix::read")).is_none()
+        );
+        assert!(Sink::new_process_spawn(&CanonicalPath::new("mycorp::fork")).is_none());
+
+        let sinks = Sink::default_sinks();
+        let callee = CanonicalPath::new("duct::cmd");
+        assert_eq!(
+            Sink::new_match(&callee, &sinks).unwrap().category(),
+            SinkCategory::Process
+        );
     }
 }
@@ -0,0 +1,231 @@
+//! Inline suppression comments.
+//!
+//! A comment of the form
+//! ```text
+//! // cargo-scan: allow(ffi_call, reason = "only reads the clock")
+//! ```
+//! suppresses effects of the listed types on the line immediately following
+//! it (or, for a trailing comment, on the same line). Several effect types can
+//! be listed, and the reason is optional. Suppressed effects are left out of
+//! the scan output, but are still kept track of so that they can be shown
+//! with `--show-suppressed`.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf as FilePathBuf;
+
+use anyhow::{anyhow, Result};
+use log::warn;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::effect::{EffectInstance, EffectType};
+
+/// Marker introducing a suppression comment
+const SUPPRESSION_MARKER: &str = "cargo-scan:";
+
+/// The effect types suppressed by a comment, and why
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
+pub struct Suppression {
+    pub effect_types: Vec<EffectType>,
+    pub reason: Option<String>,
+}
+
+impl Suppression {
+    pub fn suppresses(&self, e: &EffectInstance) -> bool {
+        self.effect_types.contains(&EffectType::from(e.eff_type()))
+    }
+}
+
+/// The name of the effect type in suppression comments, e.g. `ffi_call` for
+/// `FFICall`
+pub fn effect_type_name(t: EffectType) -> String {
+    let chars = t.to_string().chars().collect::<Vec<_>>();
+    let mut name = String::new();
+    for (i, c) in chars.iter().enumerate() {
+        // Start a new word at a lowercase-to-uppercase boundary, or at the
+        // last letter of an acronym (the "C" in "FFICall")
+        let word_start = i > 0
+            && c.is_uppercase()
+            && (chars[i - 1].is_lowercase()
+                || chars.get(i + 1).is_some_and(|n| n.is_lowercase()));
+        if word_start {
+            name.push('_');
+        }
+        name.push(c.to_ascii_lowercase());
+    }
+    name
+}
+
+/// The effect type with the given name, see [`effect_type_name`]
+pub fn effect_type_from_name(name: &str) -> Option<EffectType> {
+    EffectType::unsafe_effects()
+        .into_iter()
+        .chain([EffectType::RawPtrCast])
+        .find(|t| effect_type_name(*t) == name)
+}
+
+fn parse_effect_type(name: &str) -> Result<EffectType> {
+    effect_type_from_name(name)
+        .ok_or_else(|| anyhow!("Unknown effect type in suppression comment: {}", name))
+}
+
+/// Parses the arguments of `allow(...)`, starting just after the opening
+/// parenthesis
+fn parse_allow_args(mut s: &str) -> Result<Suppression> {
+    let mut effect_types = Vec::new();
+    let mut reason = None;
+    loop {
+        s = s.trim_start();
+        if let Some(rest) = s.strip_prefix(')') {
+            if !rest.trim().is_empty() {
+                return Err(anyhow!("Unexpected text after allow(...): {}", rest));
+            }
+            break;
+        }
+
+        let name_len =
+            s.find(|c: char| !(c.is_ascii_alphanumeric() || c == '_')).unwrap_or(s.len());
+        let (name, rest) = s.split_at(name_len);
+        s = rest.trim_start();
+        if name == "reason" {
+            s = s
+                .strip_prefix('=')
+                .ok_or_else(|| anyhow!("Expected `=` after `reason`"))?
+                .trim_start();
+            s = s.strip_prefix('"').ok_or_else(|| anyhow!("Expected a quoted reason"))?;
+            let mut text = String::new();
+            let mut chars = s.char_indices();
+            let end = loop {
+                match chars.next() {
+                    Some((i, '"')) => break i,
+                    Some((_, '\\')) => match chars.next() {
+                        Some((_, c)) => text.push(c),
+                        None => return Err(anyhow!("Unterminated reason")),
+                    },
+                    Some((_, c)) => text.push(c),
+                    None => return Err(anyhow!("Unterminated reason")),
+                }
+            };
+            reason = Some(text);
+            s = s[end + 1..].trim_start();
+        } else if name.is_empty() {
+            return Err(anyhow!("Expected an effect type, found: {}", s));
+        } else {
+            effect_types.push(parse_effect_type(name)?);
+        }
+
+        if let Some(rest) = s.strip_prefix(',') {
+            s = rest;
+        } else if !s.starts_with(')') {
+            return Err(anyhow!("Expected `,` or `)`, found: {}", s));
+        }
+    }
+
+    if effect_types.is_empty() {
+        return Err(anyhow!("Suppression comment doesn't list any effect types"));
+    }
+    Ok(Suppression { effect_types, reason })
+}
+
+/// Parses the suppression comment in a line of source code, if it has one
+pub fn parse_suppression(line: &str) -> Option<Result<Suppression>> {
+    let comment = &line[line.find("//")?..];
+    let directive = comment
+        [comment.find(SUPPRESSION_MARKER)? + SUPPRESSION_MARKER.len()..]
+        .trim_start();
+    let res = match directive.strip_prefix("allow") {
+        Some(args) => match args.trim_start().strip_prefix('(') {
+            Some(args) => parse_allow_args(args),
+            None => Err(anyhow!("Expected `(` after `allow`")),
+        },
+        None => Err(anyhow!("Expected `allow(...)` after `{}`", SUPPRESSION_MARKER)),
+    };
+    Some(res)
+}
+
+/// Finds the suppression comments which apply to effects starting on the
+/// given (1-indexed) line: a trailing comment on the line itself, or the
+/// comments and attributes directly above it
+fn line_suppressions(lines: &[String], lineno: usize) -> Vec<Suppression> {
+    let mut candidates = Vec::new();
+    if let Some(line) = lineno.checked_sub(1).and_then(|i| lines.get(i)) {
+        candidates.push((lineno, line));
+    }
+    for i in (0..lineno.saturating_sub(1)).rev() {
+        let line = lines[i].trim_start();
+        if !(line.starts_with("//") || line.starts_with("#[")) {
+            break;
+        }
+        candidates.push((i + 1, &lines[i]));
+    }
+
+    let mut res = Vec::new();
+    for (i, line) in candidates {
+        match parse_suppression(line) {
+            Some(Ok(s)) => res.push(s),
+            Some(Err(e)) => {
+                warn!("Ignoring malformed suppression comment at line {}: {}", i, e)
+            }
+            None => (),
+        }
+    }
+    res
+}
+
+/// Splits the effects into those which are reported and those which are
+/// suppressed by a comment, along with the matching suppression
+pub fn partition_suppressed(
+    effects: Vec<EffectInstance>,
+) -> (Vec<EffectInstance>, Vec<(EffectInstance, Suppression)>) {
+    let mut files: HashMap<FilePathBuf, Vec<String>> = HashMap::new();
+    let mut reported = Vec::new();
+    let mut suppressed = Vec::new();
+    for e in effects {
+        let loc = e.call_loc();
+        let path = loc.dir().join(loc.file());
+        let lines = files.entry(path).or_insert_with_key(|path| {
+            fs::read_to_string(path)
+                .map(|src| src.lines().map(|l| l.to_string()).collect())
+                .unwrap_or_default()
+        });
+        match line_suppressions(lines, loc.start_line())
+            .into_iter()
+            .find(|s| s.suppresses(&e))
+        {
+            Some(s) => suppressed.push((e, s)),
+            None => reported.push(e),
+        }
+    }
+    (reported, suppressed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_suppression() {
+        assert_eq!(effect_type_name(EffectType::FFICall), "ffi_call");
+        assert_eq!(effect_type_name(EffectType::FnPtrCreation), "fn_ptr_creation");
+
+        let s = parse_suppression(
+            r#"    // cargo-scan: allow(ffi_call, unsafe_call, reason = "reads \"now\", safely")"#,
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(s.effect_types, vec![EffectType::FFICall, EffectType::UnsafeCall]);
+        assert_eq!(s.reason.as_deref(), Some("reads \"now\", safely"));
+
+        let s =
+            parse_suppression("foo(); // cargo-scan: allow(sink_call)").unwrap().unwrap();
+        assert_eq!(s.effect_types, vec![EffectType::SinkCall]);
+        assert_eq!(s.reason, None);
+
+        assert!(parse_suppression("// a regular comment").is_none());
+        assert!(parse_suppression("// cargo-scan: allow(foo_call)").unwrap().is_err());
+        assert!(parse_suppression("// cargo-scan: allow(reason = \"x\")")
+            .unwrap()
+            .is_err());
+    }
+}
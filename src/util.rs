@@ -72,17 +72,62 @@ pub mod iter {
 
 /// Filesystem util
 pub mod fs {
+    use anyhow::{anyhow, Result};
+    use globset::{GlobBuilder, GlobSet, GlobSetBuilder};
+    use ignore::{DirEntry, WalkBuilder};
     use std::fmt::Debug;
     use std::fs::File;
     use std::io::{BufRead, BufReader, BufWriter};
     use std::path::{Path, PathBuf};
-    use walkdir::{DirEntry, WalkDir};
+    use std::sync::RwLock;
 
+    static EXCLUDED: RwLock<Option<GlobSet>> = RwLock::new(None);
+
+    /// Leaves the files and directories matching any of the glob patterns out
+    /// of every walk from now on, e.g. those ignored by the project config.
+    /// A pattern matches any path ending with it, e.g. `src/generated`
+    /// matches `my_crate/src/generated` and the files under it.
+    pub fn exclude_globs(patterns: &[String]) -> Result<()> {
+        let mut globs = GlobSetBuilder::new();
+        for pattern in patterns {
+            let pattern = pattern.trim_matches('/');
+            for p in [format!("**/{}", pattern), format!("**/{}/**", pattern)] {
+                let glob = GlobBuilder::new(&p)
+                    .literal_separator(true)
+                    .build()
+                    .map_err(|e| anyhow!("Invalid path pattern {}: {}", pattern, e))?;
+                globs.add(glob);
+            }
+        }
+        let globs = globs.build()?;
+        *EXCLUDED.write().unwrap_or_else(|e| e.into_inner()) = Some(globs);
+        Ok(())
+    }
+
+    /// Whether the walk should skip the entry: Cargo's `target` directory,
+    /// next to a Cargo.toml, or an excluded path
+    fn is_skipped(entry: &DirEntry) -> bool {
+        let path = entry.path();
+        let is_target_dir = entry.file_type().is_some_and(|t| t.is_dir())
+            && entry.file_name() == "target"
+            && path.parent().is_some_and(|dir| dir.join("Cargo.toml").is_file());
+        let excluded = EXCLUDED.read().unwrap_or_else(|e| e.into_inner());
+        is_target_dir || excluded.as_ref().is_some_and(|globs| globs.is_match(path))
+    }
+
+    /// Walks the directory, sorted by file name, skipping only the `target`
+    /// directory and excluded paths (see `exclude_globs`). Hidden files and
+    /// those listed in `.gitignore` or `.ignore` files are walked, since the
+    /// compiler builds any file a module points to, e.g. with
+    /// `#[path = ".hidden.rs"]`, and so code can't be hidden from the scan
+    /// that way.
     pub fn walk_files(p: &Path) -> impl Iterator<Item = PathBuf> {
         debug_assert!(p.is_dir());
-        WalkDir::new(p)
-            .sort_by_file_name()
-            .into_iter()
+        WalkBuilder::new(p)
+            .sort_by_file_name(|a, b| a.cmp(b))
+            .standard_filters(false)
+            .filter_entry(|entry| !is_skipped(entry))
+            .build()
             .filter_map(super::iter::warn_ok)
             .map(DirEntry::into_path)
     }
@@ -113,15 +158,17 @@ pub mod fs {
 }
 
 /// Parse Cargo TOML
-use anyhow::{Context, Result};
+use anyhow::{bail, Context, Result};
 use cargo_lock::{Dependency, Package};
 use log::debug;
 use semver::Version;
 use serde::de::{self, Unexpected, Visitor};
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::HashMap;
 use std::fmt;
 use std::fs::read_to_string;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::process::Command;
 use toml::{self, value::Table};
 
 #[derive(Eq, Hash, PartialEq, Debug, Clone)]
@@ -210,8 +257,26 @@ pub fn load_cargo_toml(crate_path: &Path) -> Result<CrateId> {
     debug!("Loading Cargo.toml at: {:?}", crate_path);
 
     let toml_string = read_to_string(crate_path.join("Cargo.toml"))?;
+    parse_cargo_toml(&toml_string)
+}
+
+/// Returns the directory of the nearest Cargo.toml in `start` or one of its
+/// parents, the way Cargo finds the manifest of the current package
+pub fn find_crate_root(start: &Path) -> Result<PathBuf> {
+    let start = start.canonicalize()?;
+    start
+        .ancestors()
+        .find(|dir| dir.join("Cargo.toml").is_file())
+        .map(Path::to_path_buf)
+        .with_context(|| {
+            format!("Couldn't find Cargo.toml in {:?} or its parents", start)
+        })
+}
+
+/// Reads the crate's name and version from the contents of its Cargo.toml
+pub fn parse_cargo_toml(toml_string: &str) -> Result<CrateId> {
     let cargo_toml =
-        toml::from_str::<Table>(&toml_string).context("Couldn't parse Cargo.toml")?;
+        toml::from_str::<Table>(toml_string).context("Couldn't parse Cargo.toml")?;
     let root_toml_table = cargo_toml
         .get("package")
         .context("No package in Cargo.toml")?
@@ -260,3 +325,215 @@ pub fn load_cargo_toml(crate_path: &Path) -> Result<CrateId> {
     debug!("Loaded: {:?}", result);
     Ok(result)
 }
+
+/// Returns the source files of the crate's binary targets: those declared in
+/// `[[bin]]` sections of Cargo.toml, along with the ones Cargo discovers
+/// automatically (`src/main.rs`, `src/bin/*.rs`, and `src/bin/*/main.rs`).
+pub fn bin_target_files(crate_path: &Path) -> Result<Vec<PathBuf>> {
+    let toml_string = read_to_string(crate_path.join("Cargo.toml"))?;
+    let cargo_toml =
+        toml::from_str::<Table>(&toml_string).context("Couldn't parse Cargo.toml")?;
+
+    let mut files = Vec::new();
+    let main_rs = crate_path.join("src/main.rs");
+    if main_rs.is_file() {
+        files.push(main_rs);
+    }
+    let bin_dir = crate_path.join("src/bin");
+    if bin_dir.is_dir() {
+        for entry in std::fs::read_dir(&bin_dir)? {
+            let path = entry?.path();
+            if path.is_dir() && path.join("main.rs").is_file() {
+                files.push(path.join("main.rs"));
+            } else if path.extension().is_some_and(|ext| ext == "rs") {
+                files.push(path);
+            }
+        }
+    }
+
+    files.extend(manifest_bin_paths(&cargo_toml).map(|path| crate_path.join(path)));
+
+    files.sort();
+    files.dedup();
+    Ok(files)
+}
+
+/// Returns the root source file of the crate's library target: the `path`
+/// set in the `[lib]` section of Cargo.toml, or else `src/lib.rs`, if there
+/// is one
+pub fn lib_target_file(crate_path: &Path) -> Result<Option<PathBuf>> {
+    let toml_string = read_to_string(crate_path.join("Cargo.toml"))?;
+    let cargo_toml =
+        toml::from_str::<Table>(&toml_string).context("Couldn't parse Cargo.toml")?;
+    let lib_rs = crate_path.join(manifest_lib_path(&cargo_toml).unwrap_or("src/lib.rs"));
+    Ok(lib_rs.is_file().then_some(lib_rs))
+}
+
+/// Returns the path set for the `[lib]` target of Cargo.toml
+pub fn manifest_lib_path(cargo_toml: &Table) -> Option<&str> {
+    cargo_toml.get("lib")?.as_table()?.get("path")?.as_str()
+}
+
+/// Returns the paths set for the `[[bin]]` targets of Cargo.toml
+pub fn manifest_bin_paths(cargo_toml: &Table) -> impl Iterator<Item = &str> {
+    let bins = cargo_toml.get("bin").and_then(|b| b.as_array());
+    bins.into_iter().flatten().filter_map(|b| b.as_table()?.get("path")?.as_str())
+}
+
+/// Returns the crate's build script: the `build` file set in Cargo.toml, or
+/// `build.rs` if there is one, unless `build = false` turns it off
+pub fn build_script_file(crate_path: &Path) -> Result<Option<PathBuf>> {
+    let toml_string = read_to_string(crate_path.join("Cargo.toml"))?;
+    let cargo_toml =
+        toml::from_str::<Table>(&toml_string).context("Couldn't parse Cargo.toml")?;
+    let package = cargo_toml.get("package").and_then(|p| p.as_table());
+
+    let build_rs = match package.and_then(|p| p.get("build")) {
+        Some(toml::Value::String(path)) => crate_path.join(path),
+        Some(toml::Value::Boolean(false)) => return Ok(None),
+        _ => crate_path.join("build.rs"),
+    };
+    Ok(Some(build_rs).filter(|f| f.is_file()))
+}
+
+/// Returns the features of the crate enabled by enabling the given ones,
+/// following the `[features]` table of Cargo.toml. Entries which enable
+/// features of dependencies (e.g. `dep:foo` or `foo/bar`) are left out, as
+/// they don't affect which of the crate's own code is compiled.
+pub fn enabled_features(crate_path: &Path, features: &[String]) -> Result<Vec<String>> {
+    let toml_string = read_to_string(crate_path.join("Cargo.toml"))?;
+    manifest_enabled_features(&toml_string, features)
+}
+
+/// Like `enabled_features`, for the contents of the crate's Cargo.toml
+pub fn manifest_enabled_features(
+    toml_string: &str,
+    features: &[String],
+) -> Result<Vec<String>> {
+    let cargo_toml =
+        toml::from_str::<Table>(toml_string).context("Couldn't parse Cargo.toml")?;
+    let feature_table = cargo_toml.get("features").and_then(|f| f.as_table());
+
+    let mut enabled = Vec::new();
+    let mut worklist = features.to_vec();
+    while let Some(feature) = worklist.pop() {
+        if enabled.contains(&feature) {
+            continue;
+        }
+        let implied = feature_table
+            .and_then(|t| t.get(&feature))
+            .and_then(|f| f.as_array())
+            .into_iter()
+            .flatten()
+            .filter_map(|f| f.as_str())
+            .filter(|f| !f.starts_with("dep:") && !f.contains('/'));
+        worklist.extend(implied.map(String::from));
+        enabled.push(feature);
+    }
+    enabled.sort();
+    Ok(enabled)
+}
+
+/// Returns the cfg options rustc sets for the target triple, e.g.
+/// `x86_64-pc-windows-msvc`, as a map from each option to its values
+pub fn target_cfg(target: &str) -> Result<HashMap<String, Vec<String>>> {
+    let output = Command::new("rustc")
+        .args(["--print", "cfg", "--target", target])
+        .output()
+        .context("Couldn't run rustc")?;
+    if !output.status.success() {
+        bail!(
+            "Couldn't get the cfg options of target {}: {}",
+            target,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+
+    let mut cfg: HashMap<String, Vec<String>> = HashMap::new();
+    for line in String::from_utf8_lossy(&output.stdout).lines() {
+        // Each line is either an option name, e.g. `unix`, or a key-value pair,
+        // e.g. `target_os="linux"`
+        match line.split_once('=') {
+            Some((key, value)) => cfg
+                .entry(key.to_string())
+                .or_default()
+                .push(value.trim_matches('"').to_string()),
+            None => {
+                cfg.entry(line.to_string()).or_default();
+            }
+        }
+    }
+    Ok(cfg)
+}
+
+/// Returns the path with `/` separators, so that paths saved on one platform
+/// can be loaded on another
+pub fn normalize_path(p: &Path) -> PathBuf {
+    PathBuf::from(p.to_string_lossy().replace('\\', "/"))
+}
+
+/// Returns the directory relative to the crate root `root` (`.` for the root
+/// itself), so that it is the same on every machine. Directories outside the
+/// crate are left as they are.
+pub fn root_relative_dir(dir: &Path, root: &Path) -> PathBuf {
+    match dir.strip_prefix(root) {
+        Ok(rel) if rel.as_os_str().is_empty() => PathBuf::from("."),
+        Ok(rel) => normalize_path(rel),
+        Err(_) => normalize_path(dir),
+    }
+}
+
+/// Returns `path` relative to the directory `base`, or `path` itself if it
+/// can't be made relative (e.g. they are on different drives)
+pub fn relative_path(path: &Path, base: &Path) -> PathBuf {
+    let path = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+    let base = base.canonicalize().unwrap_or_else(|_| base.to_path_buf());
+    if path.is_absolute() != base.is_absolute() {
+        return normalize_path(&path);
+    }
+
+    let mut path_comps = path.components().peekable();
+    let mut base_comps = base.components().peekable();
+    let mut shared = 0;
+    while let (Some(p), Some(b)) = (path_comps.peek(), base_comps.peek()) {
+        if p != b {
+            break;
+        }
+        path_comps.next();
+        base_comps.next();
+        shared += 1;
+    }
+    // Nothing in common, not even the root
+    if shared == 0 {
+        return normalize_path(&path);
+    }
+
+    let mut res = PathBuf::new();
+    for _ in base_comps {
+        res.push("..");
+    }
+    res.extend(path_comps);
+    if res.as_os_str().is_empty() {
+        res.push(".");
+    }
+    normalize_path(&res)
+}
+
+/// Looks for the source of a crate in the local Cargo registry, where Cargo
+/// unpacks the crates it downloads
+pub fn find_registry_crate(crate_id: &CrateId) -> Option<PathBuf> {
+    let registry_src = home::cargo_home().ok()?.join("registry").join("src");
+    let dir_name = format!("{}-{}", crate_id.crate_name, crate_id.version);
+    std::fs::read_dir(registry_src)
+        .ok()?
+        .filter_map(|index| index.ok())
+        .map(|index| index.path().join(&dir_name))
+        .find(|p| p.join("Cargo.toml").is_file())
+}
+
+/// The value of a git config key, if it's set
+pub fn git_config(key: &str) -> Option<String> {
+    let output = Command::new("git").args(["config", "--get", key]).output().ok()?;
+    let value = String::from_utf8(output.stdout).ok()?.trim().to_string();
+    (output.status.success() && !value.is_empty()).then_some(value)
+}
@@ -0,0 +1,258 @@
+//! Exporting audits to the cargo-vet format.
+//!
+//! Each completed crate audit in a chain becomes an entry in cargo-vet's
+//! `audits.toml`. Crates whose effects were all marked safe certify the safe
+//! criteria, and crates with public functions left caller-checked certify the
+//! (weaker) caller-checked criteria. Crates with effects left to audit or
+//! marked unsafe are not exported, and neither are crates which were only
+//! marked safe based on a third-party review (e.g. from cargo-crev).
+
+use std::collections::BTreeMap;
+
+use anyhow::{anyhow, Result};
+use log::info;
+use serde::{Deserialize, Serialize};
+use toml::{value::Table, Value};
+
+use crate::audit_chain::{AuditChain, ReviewStatus};
+use crate::audit_file::{unix_time, AuditFile};
+use crate::util::CrateId;
+
+/// Criteria defined by cargo-scan, used unless others are given
+pub const SAFE_CRITERIA: &str = "cargo-scan-safe";
+pub const CALLER_CHECKED_CRITERIA: &str = "cargo-scan-caller-checked";
+
+/// The cargo-vet criteria to certify for each kind of audit
+#[derive(Debug, Clone)]
+pub struct VetCriteria {
+    pub safe: String,
+    pub caller_checked: String,
+}
+
+impl Default for VetCriteria {
+    fn default() -> Self {
+        Self {
+            safe: SAFE_CRITERIA.to_string(),
+            caller_checked: CALLER_CHECKED_CRITERIA.to_string(),
+        }
+    }
+}
+
+/// A single entry of `audits.toml`
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct VetAudit {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub who: Vec<String>,
+    pub criteria: String,
+    pub version: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub notes: Option<String>,
+}
+
+impl VetAudit {
+    /// Returns the audit certified by a completed crate audit, or None if the
+    /// crate still has effects left to audit, was marked unsafe, or was only
+    /// reviewed by a third party
+    pub fn from_audit_file(
+        crate_id: &CrateId,
+        audit_file: &AuditFile,
+        criteria: &VetCriteria,
+        who: Vec<String>,
+    ) -> Option<Self> {
+        if !audit_file.is_fully_audited(unix_time())
+            || audit_file.has_unsafe_effect()
+            || audit_file.third_party_review.is_some()
+        {
+            return None;
+        }
+
+        let mut caller_checked = audit_file
+            .pub_caller_checked
+            .keys()
+            .map(|f| f.to_string())
+            .collect::<Vec<_>>();
+        caller_checked.sort();
+        let (criteria, notes) = if caller_checked.is_empty() {
+            (
+                &criteria.safe,
+                format!(
+                    "cargo-scan: all {} effects audited safe",
+                    audit_file.audit_trees.len()
+                ),
+            )
+        } else {
+            (
+                &criteria.caller_checked,
+                format!(
+                    "cargo-scan: all {} effects audited; public functions \
+                    left caller-checked: {}",
+                    audit_file.audit_trees.len(),
+                    caller_checked.join(", ")
+                ),
+            )
+        };
+
+        Some(Self {
+            who,
+            criteria: criteria.clone(),
+            version: crate_id.version.to_string(),
+            notes: Some(notes),
+        })
+    }
+}
+
+/// Returns the cargo-vet audits for the dependency crates of the chain,
+/// keyed by crate name. If `signed_off_only` is set, only crates with enough
+/// reviewer sign-offs are exported. The reviewers who signed off are listed
+/// as the auditors.
+pub fn chain_vet_audits(
+    chain: &mut AuditChain,
+    criteria: &VetCriteria,
+    signed_off_only: bool,
+) -> Result<BTreeMap<String, Vec<VetAudit>>> {
+    let mut crates = chain.dependency_crates()?.into_iter().cloned().collect::<Vec<_>>();
+    crates.sort_by(|c1, c2| {
+        (&c1.crate_name, &c1.version).cmp(&(&c2.crate_name, &c2.version))
+    });
+
+    let mut audits: BTreeMap<String, Vec<VetAudit>> = BTreeMap::new();
+    for crate_id in crates {
+        if signed_off_only && chain.review_status(&crate_id)? != ReviewStatus::Audited {
+            info!("Skipping {}, it isn't fully signed off", crate_id);
+            continue;
+        }
+        let audit_file = chain
+            .read_audit_file(&crate_id)?
+            .ok_or_else(|| anyhow!("Couldn't find audit for crate {}", crate_id))?;
+        let who = chain
+            .valid_sign_offs(&crate_id)?
+            .into_iter()
+            .map(|s| s.reviewer.clone())
+            .collect();
+        match VetAudit::from_audit_file(&crate_id, &audit_file, criteria, who) {
+            Some(audit) => {
+                audits.entry(crate_id.crate_name.clone()).or_default().push(audit)
+            }
+            None => info!("Skipping {}, its audit isn't complete", crate_id),
+        }
+    }
+    Ok(audits)
+}
+
+/// Adds the audits to the contents of an existing `audits.toml` (or an empty
+/// one), replacing earlier audits of the same version and criteria, and
+/// defining the cargo-scan criteria if they are used
+pub fn merge_audits_toml(
+    existing: Option<&str>,
+    audits: &BTreeMap<String, Vec<VetAudit>>,
+    criteria: &VetCriteria,
+) -> Result<String> {
+    let mut doc: Table = match existing {
+        Some(s) => toml::from_str(s)?,
+        None => Table::new(),
+    };
+
+    // NOTE: The safe criteria implies the caller-checked one, so it needs
+    //       both definitions
+    let uses_safe = criteria.safe == SAFE_CRITERIA;
+    let uses_caller_checked =
+        uses_safe || criteria.caller_checked == CALLER_CHECKED_CRITERIA;
+    let criteria_table = table_entry(&mut doc, "criteria")?;
+    if uses_caller_checked && !criteria_table.contains_key(CALLER_CHECKED_CRITERIA) {
+        criteria_table.insert(
+            CALLER_CHECKED_CRITERIA.to_string(),
+            criteria_def(
+                "All potentially dangerous effects found by cargo-scan were audited. \
+                Effects which aren't safe on their own are only reachable through \
+                public functions which callers must use safely.",
+                None,
+            ),
+        );
+    }
+    if uses_safe && !criteria_table.contains_key(SAFE_CRITERIA) {
+        criteria_table.insert(
+            SAFE_CRITERIA.to_string(),
+            criteria_def(
+                "All potentially dangerous effects found by cargo-scan were audited \
+                and marked safe.",
+                Some(CALLER_CHECKED_CRITERIA),
+            ),
+        );
+    }
+
+    let audits_table = table_entry(&mut doc, "audits")?;
+    for (crate_name, crate_audits) in audits {
+        let entries = audits_table
+            .entry(crate_name.clone())
+            .or_insert_with(|| Value::Array(Vec::new()))
+            .as_array_mut()
+            .ok_or_else(|| anyhow!("Expected a list of audits for {}", crate_name))?;
+        for audit in crate_audits {
+            entries.retain(|e| {
+                e.get("version").and_then(Value::as_str) != Some(audit.version.as_str())
+                    || e.get("criteria").and_then(Value::as_str)
+                        != Some(audit.criteria.as_str())
+            });
+            entries.push(Value::try_from(audit)?);
+        }
+    }
+
+    Ok(toml::to_string(&doc)?)
+}
+
+fn criteria_def(description: &str, implies: Option<&str>) -> Value {
+    let mut def = Table::new();
+    def.insert("description".to_string(), Value::String(description.to_string()));
+    if let Some(implies) = implies {
+        def.insert("implies".to_string(), Value::String(implies.to_string()));
+    }
+    Value::Table(def)
+}
+
+/// Returns the table under the given key, creating it if it is missing
+fn table_entry<'a>(doc: &'a mut Table, key: &str) -> Result<&'a mut Table> {
+    doc.entry(key.to_string())
+        .or_insert_with(|| Value::Table(Table::new()))
+        .as_table_mut()
+        .ok_or_else(|| anyhow!("Expected `{}` to be a table", key))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_merge_audits_toml() {
+        let existing = r#"
+[[audits.libc]]
+who = ["alice"]
+criteria = "cargo-scan-safe"
+version = "0.2.140"
+
+[[audits.libc]]
+who = ["bob"]
+criteria = "safe-to-deploy"
+version = "0.2.140"
+"#;
+        let audit = VetAudit {
+            who: vec!["carol".to_string()],
+            criteria: SAFE_CRITERIA.to_string(),
+            version: "0.2.140".to_string(),
+            notes: None,
+        };
+        let audits = BTreeMap::from([("libc".to_string(), vec![audit.clone()])]);
+        let merged =
+            merge_audits_toml(Some(existing), &audits, &VetCriteria::default()).unwrap();
+
+        let doc: Table = toml::from_str(&merged).unwrap();
+        let libc = doc["audits"]["libc"].as_array().unwrap();
+        assert_eq!(libc.len(), 2);
+        assert_eq!(libc[0]["who"][0].as_str(), Some("bob"));
+        assert_eq!(libc[1].clone().try_into::<VetAudit>().unwrap(), audit);
+        assert_eq!(
+            doc["criteria"][SAFE_CRITERIA]["implies"].as_str(),
+            Some(CALLER_CHECKED_CRITERIA)
+        );
+        assert!(doc["criteria"].get(CALLER_CHECKED_CRITERIA).is_some());
+    }
+}
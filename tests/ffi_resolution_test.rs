@@ -0,0 +1,36 @@
+use anyhow::Result;
+use std::path::Path;
+
+use cargo_scan::effect::{Effect, EffectType};
+use cargo_scan::scanner::scan_crate;
+
+/// FFI calls are resolved to the extern block they are declared in, even
+/// through glob imports and re-exports from another module
+#[test]
+fn ffi_calls_across_modules() -> Result<()> {
+    let crate_path = Path::new("data/test-packages/ffi-reexport-ex");
+    let res = scan_crate(crate_path, &EffectType::unsafe_effects(), false)?;
+
+    let mut ffi_calls = res
+        .effects
+        .iter()
+        .filter_map(|e| match e.eff_type() {
+            Effect::FFICall(_) | Effect::StaticExt(_) => {
+                Some((e.caller_path(), e.callee_path()))
+            }
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    ffi_calls.sort();
+
+    assert_eq!(
+        ffi_calls,
+        vec![
+            ("ffi_reexport_ex::api::extern_static", "ffi_reexport_ex::sys::errno_value"),
+            ("ffi_reexport_ex::api::full_path", "ffi_reexport_ex::sys::labs"),
+            ("ffi_reexport_ex::api::glob_import", "ffi_reexport_ex::sys::abs"),
+            ("ffi_reexport_ex::api::reexport", "ffi_reexport_ex::sys::abs"),
+        ]
+    );
+    Ok(())
+}
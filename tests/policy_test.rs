@@ -1,9 +1,40 @@
 //use crate_scan::audit_chain;
 use anyhow::Result;
 use assert_cmd::prelude::*;
+use std::collections::HashSet;
 use std::fs;
 use std::path::Path;
 use std::process::Command;
+use std::time::Duration;
+
+use cargo_scan::audit_chain::AuditChain;
+use cargo_scan::audit_file::{
+    set_auditor, AuditFile, AuditSession, DefaultAuditConfig, EffectInfo,
+    SafetyAnnotation, Staleness, DECISION_HISTORY_LEN,
+};
+use cargo_scan::effect::DEFAULT_EFFECT_TYPES;
+use cargo_scan::filter::PathGlob;
+use cargo_scan::ident::CanonicalPath;
+use cargo_scan::scan_config::{ResolverKind, ScanConfig};
+
+/// The test package most of these tests audit
+const DEPENDENCY_EX: &str = "data/test-packages/dependency-ex";
+
+/// Returns a new audit of the crate, with the default policies and sinks
+fn new_audit(crate_path: &Path) -> Result<AuditFile> {
+    Ok(AuditFile::new_empty_default_with_sinks(
+        crate_path,
+        HashSet::new(),
+        DEFAULT_EFFECT_TYPES,
+        true,
+        &DefaultAuditConfig::default(),
+    )?)
+}
+
+/// Returns a new audit of the dependency-ex test package
+fn dependency_ex_policy() -> AuditFile {
+    new_audit(Path::new(DEPENDENCY_EX)).unwrap()
+}
 
 // hanging -- ignored
 #[ignore]
@@ -43,3 +74,376 @@ fn cross_crate_effects() -> Result<()> {
 
     Ok(())
 }
+
+/// Audit decisions can be recorded without the interactive audit
+#[test]
+fn record_audit_decisions() -> Result<()> {
+    let crate_path = Path::new(DEPENDENCY_EX);
+    let mut audit = dependency_ex_policy();
+    let scan_res =
+        ScanConfig::builder().resolver(ResolverKind::Quick).build().scan(crate_path)?;
+
+    let locations = audit
+        .unaudited_locations()
+        .into_iter()
+        .map(|(e, l)| (e.clone(), l.clone()))
+        .collect::<Vec<_>>();
+    assert_eq!(locations.len(), audit.audit_trees.len());
+
+    for (effect, location) in &locations {
+        assert!(!audit.is_audited(effect));
+        let annotation = if effect.callee_path() == "std::fs::File::open" {
+            SafetyAnnotation::CallerChecked
+        } else {
+            SafetyAnnotation::Safe
+        };
+        let prev =
+            audit.record_decision(effect, location, annotation, Some(&scan_res))?;
+        assert_eq!(prev, SafetyAnnotation::Skipped);
+        // Nothing in the crate calls its public functions, so there are no
+        // callers to audit
+        assert!(audit.is_audited(effect));
+    }
+    assert!(audit.unaudited_locations().is_empty());
+
+    audit.refresh_pub_caller_checked();
+    let pub_caller_checked = audit.pub_caller_checked.keys().collect::<Vec<_>>();
+    assert_eq!(pub_caller_checked, [&CanonicalPath::new("dependency_ex::read_fn")]);
+
+    let (effect, location) = &locations[0];
+    let missing = EffectInfo::new(
+        CanonicalPath::new("dependency_ex::missing"),
+        location.callee_loc.clone(),
+    );
+    assert!(audit
+        .record_decision(effect, &missing, SafetyAnnotation::Safe, None)
+        .is_err());
+    Ok(())
+}
+
+/// Decisions can be undone, and revised once the effect is audited
+#[test]
+fn undo_and_revise_decisions() -> Result<()> {
+    let mut audit = dependency_ex_policy();
+    let (effect, location) = audit
+        .unaudited_locations()
+        .into_iter()
+        .map(|(e, l)| (e.clone(), l.clone()))
+        .next()
+        .unwrap();
+
+    audit.record_decision(&effect, &location, SafetyAnnotation::Safe, None)?;
+    assert!(audit.is_audited(&effect));
+    let undone = audit.undo_last_decision()?.unwrap();
+    assert_eq!(undone.annotation, SafetyAnnotation::Safe);
+    assert!(!audit.is_audited(&effect));
+    assert!(audit.undo_last_decision()?.is_none());
+
+    audit.record_decision(&effect, &location, SafetyAnnotation::Safe, None)?;
+    let prev = audit.revise_decision(&effect, SafetyAnnotation::Unsafe, None)?;
+    assert_eq!(prev, SafetyAnnotation::Safe);
+    assert_eq!(audit.audit_trees[&effect].summary_annotation(), SafetyAnnotation::Unsafe);
+    audit.undo_last_decision()?;
+    assert_eq!(audit.audit_trees[&effect].summary_annotation(), SafetyAnnotation::Safe);
+
+    // Only the most recent decisions are kept
+    for _ in 0..DECISION_HISTORY_LEN + 5 {
+        audit.revise_decision(&effect, SafetyAnnotation::Safe, None)?;
+    }
+    assert_eq!(audit.decision_history.len(), DECISION_HISTORY_LEN);
+    Ok(())
+}
+
+/// All the locations matching a pattern can be marked at once, and unmarked
+/// with a single undo
+#[test]
+fn mark_decisions_by_pattern() -> Result<()> {
+    let mut audit = dependency_ex_policy();
+    let fs_effects = audit
+        .audit_trees
+        .keys()
+        .filter(|e| e.callee_path().starts_with("std::fs::"))
+        .cloned()
+        .collect::<Vec<_>>();
+    assert!(!fs_effects.is_empty());
+
+    let pattern = PathGlob::new("std::fs::**")?;
+    let marked = audit.mark_matching(&pattern, SafetyAnnotation::Safe, None)?;
+    assert_eq!(marked, fs_effects.len());
+    assert!(fs_effects.iter().all(|e| audit.is_audited(e)));
+    assert_eq!(audit.mark_matching(&pattern, SafetyAnnotation::Safe, None)?, 0);
+    assert_eq!(audit.decision_history.len(), 1);
+
+    let undone = audit.undo_last_decision()?.unwrap();
+    assert_eq!(undone.pattern.as_deref(), Some("std::fs::**"));
+    assert!(fs_effects.iter().all(|e| !audit.is_audited(e)));
+    Ok(())
+}
+
+/// Decisions record their auditor, and the time spent on the effect in the
+/// audit session they were made in
+#[test]
+fn decision_info_and_sessions() -> Result<()> {
+    let mut audit = dependency_ex_policy();
+    let (effect, location) = audit
+        .unaudited_locations()
+        .into_iter()
+        .map(|(e, l)| (e.clone(), l.clone()))
+        .next()
+        .unwrap();
+
+    let mut session = AuditSession::new();
+    session.show(&effect);
+    session.spend(&effect, Duration::from_secs(30));
+    assert_eq!(session.current.as_ref(), Some(&effect));
+    assert_eq!(session.shown, vec![effect.clone()]);
+
+    set_auditor(Some("alice".to_string()));
+    audit.record_decision(&effect, &location, SafetyAnnotation::Safe, None)?;
+    session.charge(&effect, &mut audit.decision_info);
+    let info = &audit.decision_info[&effect];
+    assert_eq!(info.auditor.as_deref(), Some("alice"));
+    audit.auditor = Some("bob".to_string());
+    assert_eq!(audit.auditors(), vec!["alice", "bob"]);
+    assert_eq!(info.time_spent, 30);
+    assert!(session.time_spent.is_empty());
+
+    audit.undo_last_decision()?;
+    assert!(!audit.decision_info.contains_key(&effect));
+    Ok(())
+}
+
+/// Audit stats count the audited effects by decision, and add up over
+/// several policies
+#[test]
+fn audit_coverage_stats() -> Result<()> {
+    let mut audit = dependency_ex_policy();
+    let empty = audit.stats();
+    assert_eq!(empty.audited, 0);
+    assert_eq!(empty.unaudited(), audit.audit_trees.len());
+    assert_eq!(empty.unaudited_locations, empty.locations);
+    assert_eq!(empty.estimated_remaining(), None);
+
+    let (effect, location) = audit
+        .unaudited_locations()
+        .into_iter()
+        .map(|(e, l)| (e.clone(), l.clone()))
+        .next()
+        .unwrap();
+    audit.record_decision(&effect, &location, SafetyAnnotation::Unsafe, None)?;
+    audit.decision_info.get_mut(&effect).unwrap().time_spent = 60;
+    let stats = audit.stats();
+    assert_eq!(stats.audited, 1);
+    assert_eq!(stats.unsafe_effects, 1);
+    assert_eq!(stats.unaudited_locations, empty.unaudited_locations - 1);
+    assert_eq!(
+        stats.estimated_remaining(),
+        Some(Duration::from_secs(60 * stats.unaudited() as u64))
+    );
+
+    let mut total = empty.clone();
+    total += &stats;
+    assert_eq!(total.effects, 2 * stats.effects);
+    assert_eq!(total.audited, 1);
+    Ok(())
+}
+
+/// Effects which are no longer found in the crate's source are stale, and
+/// can be pruned along with their decisions
+#[test]
+fn stale_policy_entries() -> Result<()> {
+    let crate_path = Path::new(DEPENDENCY_EX);
+    let mut audit = dependency_ex_policy();
+    let mut scan_res =
+        ScanConfig::builder().resolver(ResolverKind::Quick).build().scan(crate_path)?;
+    assert!(audit.stale_effects(&scan_res).is_empty());
+
+    // Pretend the code containing the first effect was removed
+    let (effect, location) = audit
+        .unaudited_locations()
+        .into_iter()
+        .map(|(e, l)| (e.clone(), l.clone()))
+        .next()
+        .unwrap();
+    audit.record_decision(&effect, &location, SafetyAnnotation::Safe, None)?;
+    scan_res.effects.retain(|e| e.caller() != effect.caller());
+    let stale = audit.stale_effects(&scan_res);
+    assert!(stale.iter().any(|(e, s)| **e == effect && *s == Staleness::MissingLocation));
+    scan_res.fn_locs.remove(effect.caller());
+    let stale = audit.stale_effects(&scan_res);
+    assert!(
+        stale.contains(&(&effect, Staleness::MissingFunction(effect.caller().clone())))
+    );
+
+    let stale = stale.into_iter().map(|(e, _)| e.clone()).collect::<HashSet<_>>();
+    audit.prune_effects(&stale);
+    assert!(!audit.audit_trees.contains_key(&effect));
+    assert!(!audit.decision_info.contains_key(&effect));
+    assert!(audit.decision_history.is_empty());
+    assert!(audit.stale_effects(&scan_res).is_empty());
+    Ok(())
+}
+
+/// Decisions expire as set for the decision or the whole policy, and are
+/// audited again once reopened
+#[test]
+fn decision_expiration() -> Result<()> {
+    let mut audit = dependency_ex_policy();
+    let (effect, location) = audit
+        .unaudited_locations()
+        .into_iter()
+        .map(|(e, l)| (e.clone(), l.clone()))
+        .next()
+        .unwrap();
+    audit.record_decision(&effect, &location, SafetyAnnotation::Safe, None)?;
+    audit.decision_info.get_mut(&effect).unwrap().decided_at = 1000;
+    assert!(!audit.is_expired(&effect, 2000));
+
+    audit.decision_info.get_mut(&effect).unwrap().expires_at = Some(1500);
+    assert!(!audit.is_expired(&effect, 1499));
+    assert!(audit.is_expired(&effect, 1500));
+    audit.decision_info.get_mut(&effect).unwrap().expires_at = None;
+
+    // Only decisions made before the policy's expiration expire with it
+    audit.expires_at = Some(1500);
+    assert!(audit.is_expired(&effect, 2000));
+    audit.expires_at = Some(900);
+    assert!(!audit.is_expired(&effect, 2000));
+
+    audit.max_age = Some(500);
+    assert!(!audit.is_expired(&effect, 1499));
+    assert_eq!(audit.expired_effects(1500), vec![&effect]);
+
+    assert_eq!(audit.reopen_expired(1500), 1);
+    assert!(!audit.is_audited(&effect));
+    assert!(audit.expired_effects(1500).is_empty());
+    Ok(())
+}
+
+/// Audit files saved as CBOR load the same as those saved as JSON
+#[test]
+fn audit_file_cbor() -> Result<()> {
+    let audit = dependency_ex_policy();
+
+    let out_dir = Path::new("./.audit_cbor_test");
+    fs::create_dir_all(out_dir)?;
+    let (json_path, cbor_path) = (out_dir.join("audit.json"), out_dir.join("audit.cbor"));
+    audit.save_to_file(json_path.clone())?;
+    audit.save_to_file(cbor_path.clone())?;
+    assert!(fs::metadata(&cbor_path)?.len() < fs::metadata(&json_path)?.len());
+
+    let from_json = AuditFile::read_audit_file(json_path)?.unwrap();
+    let from_cbor = AuditFile::read_audit_file(cbor_path)?.unwrap();
+    assert_eq!(from_cbor.audit_trees, from_json.audit_trees);
+    assert_eq!(from_cbor.base_dir, from_json.base_dir);
+    assert_eq!(from_cbor.pub_effect_signatures, from_json.pub_effect_signatures);
+    assert_eq!(from_cbor.example_paths, from_json.example_paths);
+    assert_eq!(from_cbor.hash, from_json.hash);
+
+    fs::remove_dir_all(out_dir)?;
+    Ok(())
+}
+
+/// Writes a crate with the given source to `dir`, and returns its audit
+fn audit_crate_source(dir: &Path, src: &str) -> Result<AuditFile> {
+    fs::create_dir_all(dir.join("src"))?;
+    fs::write(
+        dir.join("Cargo.toml"),
+        "[package]\nname = \"carried\"\nversion = \"0.1.0\"\nedition = \"2021\"\n",
+    )?;
+    fs::write(dir.join("src/lib.rs"), src)?;
+    new_audit(dir)
+}
+
+/// Decisions are carried forward to a new version of the crate only for
+/// effects which are the same, in functions which haven't changed
+#[test]
+fn carry_forward_unchanged_effects() -> Result<()> {
+    let tmp = std::env::temp_dir().join("cargo-scan-test-carry-forward");
+    let _ = fs::remove_dir_all(&tmp);
+    let mut previous = audit_crate_source(
+        &tmp.join("v1"),
+        "use std::fs;
+pub fn unchanged(p: &str) -> std::io::Result<()> { fs::remove_file(p) }
+pub fn moved(p: &str) -> std::io::Result<()> { fs::remove_file(p) }
+pub fn new_callee(p: &str) -> std::io::Result<()> { fs::remove_file(p) }
+",
+    )?;
+    assert_eq!(previous.audit_trees.len(), 3);
+    previous.mark_matching(&PathGlob::new("**")?, SafetyAnnotation::Safe, None)?;
+
+    // Everything is a line further down, `moved`'s effect is at a different
+    // place in the function and `new_callee` calls something else
+    let mut audit = audit_crate_source(
+        &tmp.join("v2"),
+        "use std::fs;
+
+pub fn unchanged(p: &str) -> std::io::Result<()> { fs::remove_file(p) }
+pub fn moved(p: &str) -> std::io::Result<()> { let _ = p.len(); fs::remove_file(p) }
+pub fn new_callee(p: &str) -> std::io::Result<()> { fs::remove_dir(p) }
+",
+    )?;
+    assert_eq!(audit.carry_forward(&previous), 1);
+    let audited = audit
+        .audit_trees
+        .keys()
+        .filter(|e| audit.is_audited(e))
+        .map(|e| e.caller_path().to_string())
+        .collect::<Vec<_>>();
+    assert_eq!(audited, ["carried::unchanged"]);
+
+    fs::remove_dir_all(&tmp)?;
+    Ok(())
+}
+
+/// `chain check` fails for crates whose decisions have expired, as they have
+/// to be audited again
+#[test]
+fn chain_check_expired_decisions() -> Result<()> {
+    let out_dir = std::env::temp_dir().join("cargo-scan-test-check-expired");
+    let _ = fs::remove_dir_all(&out_dir);
+    fs::create_dir_all(&out_dir)?;
+    let manifest_path = out_dir.join("chain.manifest");
+    let audit_path = out_dir.join("dependency-ex.audit");
+
+    let mut audit = dependency_ex_policy();
+    audit.mark_matching(&PathGlob::new("**")?, SafetyAnnotation::Safe, None)?;
+    audit.criteria = vec!["safe-to-run".to_string()];
+    audit.save_to_file(audit_path.clone())?;
+
+    let mut chain = AuditChain::new(
+        manifest_path.clone(),
+        Path::new(DEPENDENCY_EX).canonicalize()?,
+        DEFAULT_EFFECT_TYPES.to_vec(),
+    );
+    let package = cargo_lock::Package {
+        name: "dependency-ex".parse()?,
+        version: "0.1.0".parse()?,
+        source: None,
+        checksum: None,
+        dependencies: Vec::new(),
+        replace: None,
+    };
+    chain.add_crate_audit_file(&package, audit_path.clone(), audit.version);
+    chain.save_to_file()?;
+
+    let check = || -> Result<std::process::Output> {
+        Ok(Command::cargo_bin("chain")?
+            .args(["check", "-c", "safe-to-run"])
+            .arg(&manifest_path)
+            .output()?)
+    };
+    let output = check()?;
+    assert!(output.status.success(), "{:?}", output);
+
+    // Every decision is too old now
+    audit.max_age = Some(0);
+    audit.save_to_file(audit_path)?;
+    let output = check()?;
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout)
+        .contains("dependency-ex-0.1.0: has effects left to audit"));
+
+    fs::remove_dir_all(&out_dir)?;
+    Ok(())
+}